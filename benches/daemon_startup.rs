@@ -0,0 +1,77 @@
+//! How long a script takes to start and exit on its own and through `xmas daemon`.
+//!
+//! `cargo bench --bench daemon_startup` prints the median of a few runs of each.
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 20;
+
+fn main() {
+    #[cfg(unix)]
+    {
+        let dir = std::env::temp_dir().join(format!("xmas-daemon-bench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hello.js"), "console.log('hello');\n").unwrap();
+
+        let cold = median(&dir);
+        let daemon = Daemon::start(&dir);
+        let warm = median(&dir);
+        drop(daemon);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        println!("startup without daemon: {:?}", cold);
+        println!("startup with daemon:    {:?}", warm);
+    }
+    #[cfg(not(unix))]
+    println!("The daemon needs a Unix system");
+}
+
+/// The median time of running `hello.js` in `dir`.
+fn median(dir: &Path) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let status = Command::new(env!("CARGO_BIN_EXE_xmas"))
+                .arg("hello.js")
+                .current_dir(dir)
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+/// A daemon serving `dir`, stopped when dropped.
+struct Daemon(Child);
+
+impl Daemon {
+    fn start(dir: &Path) -> Self {
+        let child = Command::new(env!("CARGO_BIN_EXE_xmas"))
+            .arg("daemon")
+            .current_dir(dir)
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let socket = dir.join(".xmas/daemon.sock");
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !socket.exists() {
+            assert!(Instant::now() < deadline, "the daemon didn't start");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Self(child)
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
@@ -13,7 +13,7 @@
 
 use std::path::PathBuf;
 
-use clap::{Parser, ValueEnum};
+use clap::{ArgAction, Parser, ValueEnum};
 use thiserror::Error;
 
 /// Errors that can occur during bundling
@@ -52,6 +52,18 @@ pub struct BundleConfig {
     #[arg(short = 'm', long)]
     pub minify: bool,
 
+    /// Don't rename local identifiers when minifying
+    #[arg(long = "no-mangle", action = ArgAction::SetFalse)]
+    pub mangle: bool,
+
+    /// Don't fold constants or drop dead code when minifying
+    #[arg(long = "no-compress", action = ArgAction::SetFalse)]
+    pub compress: bool,
+
+    /// Keep the names of functions and classes
+    #[arg(long)]
+    pub keep_names: bool,
+
     /// Enable source maps
     #[arg(short = 's', long)]
     pub source_map: bool,
@@ -67,6 +79,10 @@ pub struct BundleConfig {
     /// External modules (won't be bundled)
     #[arg(short = 'e', long)]
     pub external: Vec<String>,
+
+    /// Specifier prefixes replaced before resolving, with what replaces them
+    #[arg(skip)]
+    pub alias: Vec<(String, String)>,
 }
 
 /// Bundle output format
@@ -88,10 +104,14 @@ impl Default for BundleConfig {
             output_dir: PathBuf::from("dist"),
             output_filename: None,
             minify: false,
+            mangle: true,
+            compress: true,
+            keep_names: false,
             source_map: false,
             format: BundleFormat::Esm,
             tree_shake: true,
             external: Vec::new(),
+            alias: Vec::new(),
         }
     }
 }
@@ -131,13 +151,32 @@ pub async fn bundle(config: BundleConfig) -> BundleResult<()> {
         input: Some(input_items),
         dir: Some(config.output_dir.to_string_lossy().to_string()),
         format: Some(output_format),
-        minify: Some(rolldown::RawMinifyOptions::Bool(config.minify)),
+        minify: Some(if config.minify {
+            rolldown::RawMinifyOptions::Object(rolldown::MinifyOptionsObject {
+                mangle: config.mangle,
+                compress: config.compress,
+                remove_whitespace: true,
+            })
+        } else {
+            rolldown::RawMinifyOptions::Bool(false)
+        }),
+        keep_names: Some(config.keep_names),
         sourcemap: config.source_map.then(|| rolldown::SourceMapType::File),
         external: if config.external.is_empty() {
             None
         } else {
             Some(rolldown::IsExternal::from(config.external.clone()))
         },
+        resolve: (!config.alias.is_empty()).then(|| rolldown::ResolveOptions {
+            alias: Some(
+                config
+                    .alias
+                    .iter()
+                    .map(|(alias, target)| (alias.clone(), vec![Some(target.clone())]))
+                    .collect(),
+            ),
+            ..Default::default()
+        }),
         ..Default::default()
     });
 
@@ -165,5 +204,14 @@ mod tests {
         assert_eq!(config.format, BundleFormat::Esm);
         assert!(config.tree_shake);
         assert!(!config.minify);
+        assert!(config.mangle && config.compress && !config.keep_names);
+    }
+
+    #[test]
+    fn test_minify_flags() {
+        let config = BundleConfig::parse_from(["bundle", "a.ts", "-m", "--no-mangle"]);
+        assert!(config.minify && !config.mangle && config.compress);
+        let config = BundleConfig::parse_from(["bundle", "a.ts", "--keep-names"]);
+        assert!(config.mangle && config.keep_names);
     }
 }
@@ -0,0 +1,29 @@
+//! Generates the key the caches of compiled code under `~/.xmas` are signed with. It is
+//! new with every build, so a binary never loads bytecode another one wrote, which may
+//! come from another QuickJS revision.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let state = RandomState::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let key: String = (0..4u8)
+        .map(|i| {
+            let mut hasher = state.build_hasher();
+            hasher.write_u8(i);
+            hasher.write_u128(now);
+            hasher.write_u32(std::process::id());
+            format!("{:016x}", hasher.finish())
+        })
+        .collect();
+    println!("cargo:rustc-env=XMAS_BUILD_KEY={key}");
+    // the resolved dependencies pin QuickJS, and the engine is built from rsquickjs
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+    println!("cargo:rerun-if-changed=../rsquickjs");
+}
@@ -1,4 +1,4 @@
-use crate::event::{Emitter, EventEmitter, EventList};
+use crate::event::{event::Event, Emitter, EventEmitter, EventList};
 use crate::exceptions::{DOMException, DOMExceptionName};
 use crate::utils::mc_oneshot;
 use rsquickjs::{
@@ -122,6 +122,10 @@ impl<'js> AbortSignal<'js> {
                         struct Args<'js>(Ctx<'js>, This<Class<'js, AbortSignal<'js>>>);
                         let Args(ctx, signal) = Args(ctx, signal);
                         let mut borrow = signal_instance_2.borrow_mut();
+                        if borrow.aborted {
+                            // another source already aborted this signal
+                            return Ok(());
+                        }
                         borrow.aborted = true;
                         borrow.reason.clone_from(&signal.borrow().reason);
                         drop(borrow);
@@ -171,7 +175,8 @@ impl<'js> AbortSignal<'js> {
         borrow.reason = Some(reason.clone());
         borrow.sender.send(reason);
         drop(borrow);
-        Self::emit_str(this, &ctx, "abort", vec![], false)?;
+        let event = Class::instance(ctx.clone(), Event::from_type(&ctx, "abort"))?;
+        Self::dispatch_event(this, &ctx, event.into_value())?;
         Ok(())
     }
 
@@ -185,7 +190,20 @@ impl<'js> AbortSignal<'js> {
     }
 
     #[qjs(static)]
-    pub fn timeout(ctx: Ctx<'js>, milliseconds: u64) -> Result<Class<'js, Self>> {
+    pub fn timeout(ctx: Ctx<'js>, milliseconds: Value<'js>) -> Result<Class<'js, Self>> {
+        let Some(milliseconds) = milliseconds.as_number() else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "The \"milliseconds\" argument must be of type number",
+            ));
+        };
+        if !milliseconds.is_finite() || milliseconds < 0.0 {
+            return Err(Exception::throw_range(
+                &ctx,
+                "The \"milliseconds\" argument must be a non-negative finite number",
+            ));
+        }
+        let milliseconds = milliseconds as u64;
         let timeout_error =
             get_reason_or_dom_exception(&ctx, None, DOMExceptionName::TimeoutError)?;
 
@@ -204,12 +222,14 @@ impl<'js> AbortSignal<'js> {
             }),
         )?;
 
-        crate::timers::set_timeout_interval(
+        let id = crate::timers::set_timeout_interval(
             &ctx,
             cb,
             milliseconds,
             crate::utils::provider::ProviderType::Timeout,
         )?;
+        // like in Node.js, a pending timeout signal doesn't keep the script running
+        crate::timers::set_timer_ref(&ctx, id, false)?;
 
         Ok(signal_instance2)
     }
@@ -234,6 +254,7 @@ mod tests {
     use std::time::Duration;
 
     use crate::utils::test::test_async_with;
+    use rsquickjs::IntoJs;
 
     use super::*;
 
@@ -243,7 +264,8 @@ mod tests {
             super::super::init(&ctx).unwrap();
             crate::timers::init(&ctx).unwrap();
             Box::pin(async move {
-                let signal = AbortSignal::timeout(ctx, 5).unwrap();
+                let delay = 5.into_js(&ctx).unwrap();
+                let signal = AbortSignal::timeout(ctx.clone(), delay).unwrap();
 
                 assert!(!signal.borrow().aborted());
 
@@ -1,17 +1,23 @@
-use crate::utils::result::ResultExt;
-use rsquickjs::{prelude::Func, Ctx, Result, Value};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+use rsquickjs::{
+    prelude::{Func, Opt, Rest, This},
+    Ctx, Result, Value,
+};
 use std::sync::{Arc, Mutex};
-use std::{collections::HashMap, marker::PhantomData};
+use std::{collections::HashMap, marker::PhantomData, mem, rc::Rc};
 use tracing::trace;
 
 use crate::hooking::register_finalization_registry;
+use crate::permissions::Callers;
 use crate::utils::module::{export_default, ModuleInfo};
 use rsquickjs::{
+    class::{Trace, Tracer},
+    function::IntoArgs,
     module::{Declarations, Exports, ModuleDef},
     promise::PromiseHookType,
     qjs,
     runtime::PromiseHook,
-    Function, JsLifetime, Object,
+    Class, Function, JsLifetime, Object,
 };
 
 pub(crate) fn init_finalization_registry(ctx: &Ctx<'_>) -> Result<()> {
@@ -41,14 +47,17 @@ pub(crate) fn init_finalization_registry(ctx: &Ctx<'_>) -> Result<()> {
 }
 
 fn invoke_finalization_hook<'js>(ctx: Ctx<'js>, uid: Value<'js>) -> Result<()> {
+    let uid = uid.as_number().unwrap() as usize;
+    if let Some(contexts) = ctx.userdata::<Mutex<AsyncContextState>>() {
+        contexts.lock().unwrap().frames.remove(&uid);
+    }
+
     let bind_state = ctx.userdata::<Mutex<AsyncHookState>>().or_throw(&ctx)?;
     let state = bind_state.lock().unwrap();
     if state.hooks.is_empty() {
         return Ok(());
     }
 
-    let uid = uid.as_number().unwrap() as usize;
-
     let current_id = remove_id_map(&ctx, uid)?;
     if current_id.0 == 0 {
         return Ok(());
@@ -126,6 +135,151 @@ unsafe impl<'js> JsLifetime<'js> for AsyncHookIds<'js> {
     type Changed<'to> = AsyncHookIds<'to>;
 }
 
+/// The stores of every `AsyncLocalStorage` in one async context, copied on write, and
+/// the modules that started it, which keep their permission scope in it
+#[derive(Clone, Default)]
+struct Frame<'js> {
+    stores: Rc<HashMap<u64, Value<'js>>>,
+    callers: Callers,
+}
+
+impl<'js> Frame<'js> {
+    fn get(&self, storage: u64) -> Option<Value<'js>> {
+        self.stores.get(&storage).cloned()
+    }
+
+    /// A copy of this frame with the store of `storage` replaced
+    fn with(&self, storage: u64, store: Option<Value<'js>>) -> Self {
+        let mut stores = self.stores.as_ref().clone();
+        match store {
+            Some(store) => stores.insert(storage, store),
+            None => stores.remove(&storage),
+        };
+        Self {
+            stores: Rc::new(stores),
+            callers: self.callers.clone(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.stores.is_empty() && self.callers.is_empty()
+    }
+}
+
+impl<'js> Trace<'js> for Frame<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        for store in self.stores.values() {
+            tracer.mark(store);
+        }
+    }
+}
+
+/// The frame of the running code and those captured by pending promises and timers
+#[derive(Default)]
+struct AsyncContextState<'js> {
+    next_storage_id: u64,
+    current: Frame<'js>,
+    saved: Vec<Frame<'js>>,
+    frames: HashMap<usize, Frame<'js>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for AsyncContextState<'js> {
+    type Changed<'to> = AsyncContextState<'to>;
+}
+
+/// The modules that started the running async operation, see [`Callers::capture`]
+pub(crate) fn current_callers(ctx: &Ctx<'_>) -> Callers {
+    ctx.userdata::<Mutex<AsyncContextState>>()
+        .map(|state| state.lock().unwrap().current.callers.clone())
+        .unwrap_or_default()
+}
+
+fn current_frame<'js>(ctx: &Ctx<'js>) -> Result<Frame<'js>> {
+    let binding = ctx.userdata::<Mutex<AsyncContextState>>().or_throw(ctx)?;
+    let state = binding.lock().unwrap();
+    Ok(state.current.clone())
+}
+
+fn replace_frame<'js>(ctx: &Ctx<'js>, frame: Frame<'js>) -> Result<Frame<'js>> {
+    let binding = ctx.userdata::<Mutex<AsyncContextState>>().or_throw(ctx)?;
+    let mut state = binding.lock().unwrap();
+    Ok(mem::replace(&mut state.current, frame))
+}
+
+fn run_in_frame<'js, R>(
+    ctx: &Ctx<'js>,
+    frame: Frame<'js>,
+    f: impl FnOnce() -> Result<R>,
+) -> Result<R> {
+    let previous = replace_frame(ctx, frame)?;
+    let result = f();
+    replace_frame(ctx, previous)?;
+    result
+}
+
+/// Captures the frame when `object` is created and restores it while its callbacks run.
+///
+/// Returns whether a frame was captured, which needs a finalizer to be released.
+fn propagate_context(ctx: &Ctx<'_>, type_: PromiseHookType, object: usize) -> Result<bool> {
+    // the modules scheduling the operation, read before the state is locked
+    let stack = match type_ {
+        PromiseHookType::Init => Callers::capture_stack(ctx),
+        _ => Callers::default(),
+    };
+    let binding = ctx.userdata::<Mutex<AsyncContextState>>().or_throw(ctx)?;
+    let mut state = binding.lock().unwrap();
+    match type_ {
+        PromiseHookType::Init => {
+            let mut frame = state.current.clone();
+            frame.callers.extend(&stack);
+            if frame.is_empty() {
+                // the address may belong to a collected object
+                state.frames.remove(&object);
+                return Ok(false);
+            }
+            state.frames.insert(object, frame);
+            return Ok(true);
+        }
+        PromiseHookType::Before => {
+            let frame = state.frames.get(&object).cloned().unwrap_or_default();
+            let previous = mem::replace(&mut state.current, frame);
+            state.saved.push(previous);
+        }
+        PromiseHookType::After => {
+            if let Some(previous) = state.saved.pop() {
+                state.current = previous;
+            }
+        }
+        PromiseHookType::Resolve => {}
+    }
+    Ok(false)
+}
+
+/// Calls the `pick`ed callback of every enabled hook with `args`
+fn emit_hooks<'js, A>(
+    ctx: &Ctx<'js>,
+    pick: fn(&Hook<'js>) -> &Option<Function<'js>>,
+    args: A,
+) -> Result<()>
+where
+    A: IntoArgs<'js> + Clone,
+{
+    let funcs: Vec<Function> = {
+        let binding = ctx.userdata::<Mutex<AsyncHookState>>().or_throw(ctx)?;
+        let state = binding.lock().unwrap();
+        state
+            .hooks
+            .iter()
+            .filter(|hook| *hook.enabled.lock().unwrap())
+            .filter_map(|hook| pick(hook).clone())
+            .collect()
+    };
+    for func in funcs {
+        let _ = func.call::<_, ()>(args.clone());
+    }
+    Ok(())
+}
+
 fn create_hook<'js>(ctx: Ctx<'js>, hooks_obj: Object<'js>) -> Result<Value<'js>> {
     let init = hooks_obj.get::<_, Function>("init").ok();
     let before = hooks_obj.get::<_, Function>("before").ok();
@@ -190,6 +344,234 @@ fn trigger_async_id(ctx: Ctx<'_>) -> Result<u64> {
     Ok(ids.current_id.1)
 }
 
+/// Keeps a store for the async operations started inside `run`
+#[rsquickjs::class]
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+pub struct AsyncLocalStorage {
+    #[qjs(skip_trace)]
+    id: u64,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl AsyncLocalStorage {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'_>) -> Result<Self> {
+        let binding = ctx.userdata::<Mutex<AsyncContextState>>().or_throw(&ctx)?;
+        let mut state = binding.lock().unwrap();
+        state.next_storage_id += 1;
+        Ok(Self {
+            id: state.next_storage_id,
+        })
+    }
+
+    pub fn get_store<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let store = current_frame(&ctx)?.get(self.id);
+        Ok(store.unwrap_or_else(|| Value::new_undefined(ctx)))
+    }
+
+    pub fn run<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        store: Value<'js>,
+        callback: Function<'js>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let frame = current_frame(&ctx)?.with(self.id, Some(store));
+        run_in_frame(&ctx, frame, || callback.call((Rest(args.0),)))
+    }
+
+    pub fn exit<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        callback: Function<'js>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let frame = current_frame(&ctx)?.with(self.id, None);
+        run_in_frame(&ctx, frame, || callback.call((Rest(args.0),)))
+    }
+
+    /// Set the store for the rest of the current synchronous execution
+    pub fn enter_with<'js>(&self, ctx: Ctx<'js>, store: Value<'js>) -> Result<()> {
+        let frame = current_frame(&ctx)?.with(self.id, Some(store));
+        replace_frame(&ctx, frame)?;
+        Ok(())
+    }
+
+    pub fn disable(&self, ctx: Ctx<'_>) -> Result<()> {
+        let frame = current_frame(&ctx)?.with(self.id, None);
+        replace_frame(&ctx, frame)?;
+        Ok(())
+    }
+
+    /// Bind `func` to the current stores of every storage
+    #[qjs(static)]
+    pub fn bind<'js>(ctx: Ctx<'js>, func: Function<'js>) -> Result<Function<'js>> {
+        bind_frame(&ctx, current_frame(&ctx)?, func, None)
+    }
+
+    /// A function calling its first argument with the current stores of every storage
+    #[qjs(static)]
+    pub fn snapshot<'js>(ctx: Ctx<'js>) -> Result<Function<'js>> {
+        let frame = current_frame(&ctx)?;
+        Function::new(
+            ctx,
+            move |ctx: Ctx<'js>, func: Function<'js>, args: Rest<Value<'js>>| {
+                run_in_frame(&ctx, frame.clone(), || {
+                    func.call::<_, Value>((Rest(args.0),))
+                })
+            },
+        )
+    }
+}
+
+fn bind_frame<'js>(
+    ctx: &Ctx<'js>,
+    frame: Frame<'js>,
+    func: Function<'js>,
+    this_arg: Option<Value<'js>>,
+) -> Result<Function<'js>> {
+    Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, this: This<Value<'js>>, args: Rest<Value<'js>>| {
+            let this = this_arg.clone().unwrap_or(this.0);
+            run_in_frame(&ctx, frame.clone(), || {
+                func.call::<_, Value>((This(this), Rest(args.0)))
+            })
+        },
+    )
+}
+
+/// An async operation of userland code, like a connection pool handing out
+/// connections, which runs its callbacks in the context it was created in
+#[rsquickjs::class]
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+pub struct AsyncResource<'js> {
+    frame: Frame<'js>,
+    #[qjs(skip_trace)]
+    resource_type: String,
+    #[qjs(skip_trace)]
+    async_id: u64,
+    #[qjs(skip_trace)]
+    trigger_async_id: u64,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> AsyncResource<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, resource_type: String, options: Opt<Value<'js>>) -> Result<Self> {
+        // the options are an object or, in older code, the trigger id itself
+        let trigger_async_id = match options.0 {
+            Some(options) if options.is_number() => options.as_number().map(|id| id as u64),
+            Some(options) if options.is_object() => options.get_optional("triggerAsyncId")?,
+            _ => None,
+        };
+
+        let binding = ctx.userdata::<Mutex<AsyncHookIds>>().or_throw(&ctx)?;
+        let (async_id, trigger_async_id) = {
+            let mut ids = binding.lock().unwrap();
+            ids.next_async_id = ids.next_async_id.wrapping_add(1);
+            (
+                ids.next_async_id,
+                trigger_async_id.unwrap_or(ids.current_id.0),
+            )
+        };
+        emit_hooks(
+            &ctx,
+            |hook| &hook.init,
+            (async_id, resource_type.clone(), trigger_async_id),
+        )?;
+
+        Ok(Self {
+            frame: current_frame(&ctx)?,
+            resource_type,
+            async_id,
+            trigger_async_id,
+        })
+    }
+
+    /// Call `func` with `this_arg` in the context the resource was created in
+    pub fn run_in_async_scope(
+        &self,
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        this_arg: Opt<Value<'js>>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let this = this_arg
+            .0
+            .unwrap_or_else(|| Value::new_undefined(ctx.clone()));
+        let previous_id = {
+            let binding = ctx.userdata::<Mutex<AsyncHookIds>>().or_throw(&ctx)?;
+            let mut ids = binding.lock().unwrap();
+            mem::replace(&mut ids.current_id, (self.async_id, self.trigger_async_id))
+        };
+        emit_hooks(&ctx, |hook| &hook.before, (self.async_id,))?;
+        let result = run_in_frame(&ctx, self.frame.clone(), || {
+            func.call((This(this), Rest(args.0)))
+        });
+        emit_hooks(&ctx, |hook| &hook.after, (self.async_id,))?;
+        update_current_id(&ctx, previous_id)?;
+        result
+    }
+
+    pub fn emit_destroy(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        let async_id = this.borrow().async_id;
+        emit_hooks(&ctx, |hook| &hook.destroy, (async_id,))?;
+        Ok(this.0)
+    }
+
+    pub fn async_id(&self) -> u64 {
+        self.async_id
+    }
+
+    pub fn trigger_async_id(&self) -> u64 {
+        self.trigger_async_id
+    }
+
+    /// Bind `func` to run in the scope of this resource
+    pub fn bind(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        this_arg: Opt<Value<'js>>,
+    ) -> Result<Function<'js>> {
+        let resource = this.0;
+        let this_arg = this_arg.0;
+        Function::new(
+            ctx,
+            move |ctx: Ctx<'js>, this: This<Value<'js>>, args: Rest<Value<'js>>| {
+                let this = this_arg.clone().unwrap_or(this.0);
+                resource
+                    .borrow()
+                    .run_in_async_scope(ctx, func.clone(), Opt(Some(this)), args)
+            },
+        )
+    }
+
+    /// Bind `func` to run in the scope of a new resource of `resource_type`
+    #[qjs(static, rename = "bind")]
+    pub fn bind_static(
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        resource_type: Opt<String>,
+        this_arg: Opt<Value<'js>>,
+    ) -> Result<Function<'js>> {
+        let resource_type = resource_type
+            .0
+            .unwrap_or_else(|| "bound-anonymous-fn".into());
+        let resource = Class::instance(
+            ctx.clone(),
+            Self::new(ctx.clone(), resource_type, Opt(None))?,
+        )?;
+        Self::bind(This(resource), ctx, func, this_arg)
+    }
+
+    #[qjs(get)]
+    pub fn resource_type(&self) -> String {
+        self.resource_type.clone()
+    }
+}
+
 pub struct AsyncHooksModule;
 
 impl ModuleDef for AsyncHooksModule {
@@ -198,6 +580,8 @@ impl ModuleDef for AsyncHooksModule {
         declare.declare("currentId")?;
         declare.declare("executionAsyncId")?;
         declare.declare("triggerAsyncId")?;
+        declare.declare(stringify!(AsyncLocalStorage))?;
+        declare.declare(stringify!(AsyncResource))?;
         declare.declare("default")?;
 
         Ok(())
@@ -209,6 +593,8 @@ impl ModuleDef for AsyncHooksModule {
             default.set("currentId", Func::from(current_id))?;
             default.set("executionAsyncId", Func::from(execution_async_id))?;
             default.set("triggerAsyncId", Func::from(trigger_async_id))?;
+            Class::<AsyncLocalStorage>::define(default)?;
+            Class::<AsyncResource>::define(default)?;
 
             Ok(())
         })?;
@@ -231,6 +617,7 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     let _ = ctx.store_userdata(Mutex::new(AsyncHookState::default()));
     let _ = ctx.store_userdata(Mutex::new(AsyncHookIds::default()));
+    let _ = ctx.store_userdata(Mutex::new(AsyncContextState::default()));
 
     global.set(
         "invokeAsyncHook",
@@ -244,6 +631,7 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
                     _ => return,
                 };
 
+                let _ = propagate_context(&ctx, type_, uid);
                 let _ = invoke_async_hook(&ctx, type_, async_type.as_ref(), uid, None);
             },
         ),
@@ -266,7 +654,8 @@ pub fn promise_hook_tracker() -> PromiseHook {
                 .as_object()
                 .map(|v| unsafe { qjs::JS_VALUE_GET_PTR(v.as_raw()) } as usize);
 
-            if type_ == PromiseHookType::Init {
+            let captured = propagate_context(&ctx, type_, object).unwrap_or_default();
+            if type_ == PromiseHookType::Init && (captured || has_hooks(&ctx)) {
                 let _ = register_finalization_registry(&ctx, promise, object);
             }
 
@@ -275,6 +664,11 @@ pub fn promise_hook_tracker() -> PromiseHook {
     )
 }
 
+fn has_hooks(ctx: &Ctx<'_>) -> bool {
+    ctx.userdata::<Mutex<AsyncHookState>>()
+        .is_some_and(|state| !state.lock().unwrap().hooks.is_empty())
+}
+
 fn invoke_async_hook(
     ctx: &Ctx<'_>,
     type_: PromiseHookType,
@@ -380,3 +774,62 @@ fn update_current_id(ctx: &Ctx<'_>, id: (u64, u64)) -> Result<()> {
     bind_ids.lock().unwrap().current_id = id;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::async_with;
+
+    use crate::utils::test::{call_test, given_runtime, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_local_storage() {
+        let (rt, ctx) = given_runtime().await;
+        rt.set_promise_hook(Some(promise_hook_tracker())).await;
+
+        async_with!(ctx => |ctx| {
+            init(&ctx).unwrap();
+            ModuleEvaluator::eval_rust::<AsyncHooksModule>(ctx.clone(), "async_hooks")
+                .await
+                .unwrap();
+
+            let module = ModuleEvaluator::eval_js(
+                ctx.clone(),
+                "test",
+                r#"
+                    import { AsyncLocalStorage, AsyncResource } from 'async_hooks';
+                    export async function test() {
+                        const storage = new AsyncLocalStorage();
+                        const seen = [];
+                        const pending = storage.run('a', async () => {
+                            await null;
+                            seen.push(storage.getStore());
+                            await Promise.resolve().then(() => seen.push(storage.getStore()));
+                        });
+                        const other = storage.run('b', () =>
+                            Promise.resolve().then(() => storage.getStore())
+                        );
+                        seen.push(storage.getStore());
+                        await pending;
+                        seen.push(await other);
+
+                        const resource = storage.run('c', () => new AsyncResource('test'));
+                        seen.push(resource.runInAsyncScope(() => storage.getStore()));
+                        const bound = storage.run('d', () =>
+                            AsyncLocalStorage.bind(() => storage.getStore())
+                        );
+                        seen.push(bound());
+                        seen.push(storage.exit(() => storage.getStore()));
+                        return seen.join(',');
+                    }
+                "#,
+            )
+            .await
+            .unwrap();
+            let result = call_test::<String, _>(&ctx, &module, ()).await;
+            assert_eq!(result, ",a,a,b,c,d,");
+        })
+        .await;
+    }
+}
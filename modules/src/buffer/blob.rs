@@ -1,13 +1,29 @@
-use std::ops::RangeInclusive;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::Arc,
+};
 
+use crate::permissions::get_vsys;
 use crate::utils::{
     bytes::ObjectBytes,
     primordials::{BasePrimordials, Primordial},
     result::ResultExt,
 };
 use rsquickjs::{
-    atom::PredefinedAtom, class::Trace, function::Opt, Array, ArrayBuffer, Class, Coerced, Ctx,
-    Exception, FromJs, Result, Symbol, TypedArray, Value,
+    atom::PredefinedAtom,
+    class::Trace,
+    function::{Constructor, Opt, This},
+    Array, ArrayBuffer, Class, Coerced, Ctx, Exception, FromJs, Function, Object, Result, Symbol,
+    TypedArray, Value,
+};
+use xmas_vsys::{
+    error::{VsysError, VsysResult},
+    fs::{OpenOptions, SeekFrom},
+    Vsys,
 };
 
 use super::file::File;
@@ -20,6 +36,11 @@ enum EndingType {
     Transparent,
 }
 
+/// Blobs at least this large are moved to a temp file when the context has a vsys
+const DISK_BACKED_THRESHOLD: usize = 8 * 1024 * 1024;
+
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
 #[cfg(windows)]
 const LINE_ENDING: &[u8] = b"\r\n";
 #[cfg(not(windows))]
@@ -29,10 +50,121 @@ const LINE_ENDING: &[u8] = b"\n";
 #[derive(Trace, Clone, rsquickjs::JsLifetime)]
 pub struct Blob {
     #[qjs(skip_trace)]
-    data: Vec<u8>,
+    data: BlobData,
     mime_type: String,
 }
 
+/// Byte range of a blob source, slicing only narrows the range without copying.
+#[derive(Clone)]
+struct BlobData {
+    source: BlobSource,
+    offset: usize,
+    len: usize,
+}
+
+#[derive(Clone)]
+enum BlobSource {
+    Memory(Arc<[u8]>),
+    Disk(Arc<TempBlobFile>),
+}
+
+impl BlobData {
+    fn from_vec(data: Vec<u8>) -> Self {
+        Self {
+            len: data.len(),
+            offset: 0,
+            source: BlobSource::Memory(data.into()),
+        }
+    }
+
+    fn new(ctx: &Ctx<'_>, data: Vec<u8>) -> Self {
+        if data.len() >= DISK_BACKED_THRESHOLD {
+            if let Some(vsys) = get_vsys(ctx) {
+                match TempBlobFile::create(vsys, &data) {
+                    Ok(file) => {
+                        return Self {
+                            len: data.len(),
+                            offset: 0,
+                            source: BlobSource::Disk(Arc::new(file)),
+                        }
+                    }
+                    // a sandboxed fs is not an error, the blob just stays in memory
+                    Err(err) => tracing::debug!("Keeping blob in memory: {}", err),
+                }
+            }
+        }
+        Self::from_vec(data)
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Self {
+        Self {
+            source: self.source.clone(),
+            offset: self.offset + start,
+            len: end.saturating_sub(start),
+        }
+    }
+
+    fn read(&self) -> io::Result<Vec<u8>> {
+        self.read_range(0, self.len)
+    }
+
+    fn read_range(&self, start: usize, len: usize) -> io::Result<Vec<u8>> {
+        let offset = self.offset + start;
+        match &self.source {
+            BlobSource::Memory(bytes) => Ok(bytes[offset..offset + len].to_vec()),
+            BlobSource::Disk(file) => file.read(offset as u64, len).map_err(into_io_error),
+        }
+    }
+}
+
+/// Temp file owned by one or more blobs, removed once the last of them is dropped.
+struct TempBlobFile {
+    dir: PathBuf,
+    path: PathBuf,
+    vsys: Arc<Vsys>,
+}
+
+impl TempBlobFile {
+    fn create(vsys: Arc<Vsys>, data: &[u8]) -> VsysResult<Self> {
+        let dir = (vsys.fs().mkdtemp)("xmas-blob-")?;
+        let file = Self {
+            path: dir.join("data"),
+            dir,
+            vsys,
+        };
+        (file.vsys.fs().write)(&file.path, data)?;
+        Ok(file)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> VsysResult<Vec<u8>> {
+        let mut handle = (self.vsys.fs().open)(&self.path, &OpenOptions::new().read(true))?;
+        handle.seek(SeekFrom::Start(offset))?;
+
+        let mut buf = vec![0; len];
+        let mut filled = 0;
+        while filled < len {
+            match handle.read(&mut buf[filled..])? {
+                0 => return Err(VsysError::Io(io::ErrorKind::UnexpectedEof.into())),
+                read => filled += read,
+            }
+        }
+        Ok(buf)
+    }
+}
+
+impl Drop for TempBlobFile {
+    fn drop(&mut self) {
+        let _ = (self.vsys.fs().remove_dir_all)(&self.dir);
+    }
+}
+
+fn into_io_error(err: VsysError) -> io::Error {
+    match err {
+        VsysError::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
 fn normalize_type(mut mime_type: String) -> String {
     static INVALID_RANGE: RangeInclusive<u8> = 0x0020..=0x007E;
 
@@ -78,9 +210,9 @@ impl Blob {
         }
 
         let data = if let Some(parts) = parts.0 {
-            bytes_from_parts(&ctx, parts, endings)?
+            BlobData::new(&ctx, bytes_from_parts(&ctx, parts, endings)?)
         } else {
-            Vec::new()
+            BlobData::from_vec(Vec::new())
         };
 
         Ok(Self { data, mime_type })
@@ -88,7 +220,7 @@ impl Blob {
 
     #[qjs(get)]
     pub fn size(&self) -> usize {
-        self.data.len()
+        self.data.len
     }
 
     #[qjs(get, rename = "type")]
@@ -96,41 +228,75 @@ impl Blob {
         self.mime_type.clone()
     }
 
-    pub async fn text(&self) -> String {
-        String::from_utf8_lossy(&self.data).to_string()
+    pub async fn text(&self, ctx: Ctx<'_>) -> Result<String> {
+        let data = self.data.read().or_throw(&ctx)?;
+        Ok(String::from_utf8_lossy(&data).to_string())
     }
 
     #[qjs(rename = "arrayBuffer")]
     pub async fn array_buffer<'js>(&self, ctx: Ctx<'js>) -> Result<ArrayBuffer<'js>> {
-        ArrayBuffer::new(ctx, self.data.to_vec())
+        let data = self.data.read().or_throw(&ctx)?;
+        ArrayBuffer::new(ctx, data)
     }
 
     pub async fn bytes<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
-        TypedArray::new(ctx, self.data.to_vec()).map(|m| m.into_value())
+        let data = self.data.read().or_throw(&ctx)?;
+        TypedArray::new(ctx, data).map(|m| m.into_value())
     }
 
     pub fn slice(&self, start: Opt<isize>, end: Opt<isize>, content_type: Opt<String>) -> Blob {
-        let start = start.0.unwrap_or_default();
-        let start = if start < 0 {
-            (self.data.len() as isize + start).max(0) as usize
-        } else {
-            self.data.len().min(start as usize)
-        };
-        let end = end.0.unwrap_or_default();
-        let end = if end < 0 {
-            (self.data.len() as isize + end).max(0) as usize
-        } else {
-            self.data.len().min(end as usize)
+        let len = self.data.len;
+        let relative = |index: isize| {
+            if index < 0 {
+                (len as isize + index).max(0) as usize
+            } else {
+                len.min(index as usize)
+            }
         };
-        let data = &self.data[start..end];
+        let start = relative(start.0.unwrap_or_default());
+        let end = end.0.map(relative).unwrap_or(len);
         let mime_type = content_type.0.map(normalize_type).unwrap_or_default();
 
         Blob {
             mime_type,
-            data: data.to_vec(),
+            data: self.data.slice(start, end.max(start)),
         }
     }
 
+    pub fn stream<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let Some(readable_stream) = ctx
+            .globals()
+            .get::<_, Option<Constructor>>("ReadableStream")?
+        else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "ReadableStream is not supported",
+            ));
+        };
+
+        // chunks are read lazily so disk backed blobs are never loaded as a whole
+        let data = self.data.clone();
+        let position = Cell::new(0);
+        let pull = Function::new(ctx.clone(), move |controller: Object<'js>| {
+            let ctx = controller.ctx().clone();
+            let start = position.get();
+            let len = STREAM_CHUNK_SIZE.min(data.len - start);
+            if len == 0 {
+                let close: Function = controller.get("close")?;
+                return close.call::<_, ()>((This(controller),));
+            }
+            let chunk = data.read_range(start, len).or_throw(&ctx)?;
+            position.set(start + len);
+
+            let enqueue: Function = controller.get("enqueue")?;
+            enqueue.call::<_, ()>((This(controller), TypedArray::<u8>::new(ctx, chunk)?))
+        })?;
+
+        let source = Object::new(ctx)?;
+        source.set("pull", pull)?;
+        readable_stream.construct((source,))
+    }
+
     #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
     pub fn to_string_tag(&self) -> &'static str {
         stringify!(Blob)
@@ -140,22 +306,49 @@ impl Blob {
 impl Blob {
     pub fn from_bytes(data: Vec<u8>, content_type: Option<String>) -> Self {
         let mime_type = content_type.map(normalize_type).unwrap_or_default();
-        Self { mime_type, data }
+        Self {
+            mime_type,
+            data: BlobData::from_vec(data),
+        }
     }
 
-    pub fn get_bytes(&self) -> Vec<u8> {
-        self.data.clone()
+    pub fn get_bytes(&self) -> io::Result<Vec<u8>> {
+        self.data.read()
     }
+}
 
-    //FIXME: cant use procedural macro for Symbol rename + static, see https://github.com/DelSkayn/rquickjs/issues/315
-    pub fn has_instance(value: Value<'_>) -> bool {
-        if let Some(obj) = value.as_object() {
-            return obj.instance_of::<Self>() || obj.instance_of::<File>();
-        }
-        false
+/// Blobs registered by `URL.createObjectURL()`, keyed by their `blob:` URL.
+#[derive(Default, rsquickjs::JsLifetime)]
+struct ObjectUrls(HashMap<String, Blob>);
+
+/// Registers `blob` under a new `blob:` URL until it is revoked.
+pub fn create_object_url(ctx: &Ctx<'_>, blob: Blob) -> Result<String> {
+    if ctx.userdata::<RefCell<ObjectUrls>>().is_none() {
+        let _ = ctx.store_userdata(RefCell::new(ObjectUrls::default()));
+    }
+    let bytes = get_random(ctx).next_u128().to_le_bytes();
+    let uuid = uuid::Builder::from_random_bytes(bytes).into_uuid();
+    let url = ["blob:nodedata:", &uuid.to_string()].concat();
+    let urls = ctx.userdata::<RefCell<ObjectUrls>>().or_throw(ctx)?;
+    urls.borrow_mut().0.insert(url.clone(), blob);
+    Ok(url)
+}
+
+pub fn revoke_object_url(ctx: &Ctx<'_>, url: &str) {
+    if let Some(urls) = ctx.userdata::<RefCell<ObjectUrls>>() {
+        urls.borrow_mut().0.remove(url);
     }
 }
 
+/// The blob registered under `url`, unless it was revoked.
+pub fn resolve_object_url(ctx: &Ctx<'_>, url: &str) -> Option<Blob> {
+    ctx.userdata::<RefCell<ObjectUrls>>()?
+        .borrow()
+        .0
+        .get(url)
+        .cloned()
+}
+
 fn bytes_from_parts<'js>(
     ctx: &Ctx<'js>,
     parts: Value<'js>,
@@ -185,14 +378,11 @@ fn bytes_from_parts<'js>(
         }
         if let Some(object) = elem.as_object() {
             if let Some(x) = Class::<Blob>::from_object(object) {
-                data.extend_from_slice(&x.borrow().data);
+                data.extend(x.borrow().get_bytes().or_throw(ctx)?);
                 continue;
             }
             if let Some(x) = Class::<File>::from_object(object) {
-                let file = x.borrow();
-                let end = Some(file.size().try_into().or_throw(ctx)?);
-                let mime_type = Some(file.mime_type());
-                data.extend_from_slice(&file.slice(Opt(Some(0)), Opt(end), Opt(mime_type)).data);
+                data.extend(x.borrow().get_blob().get_bytes().or_throw(ctx)?);
                 continue;
             }
             if let Ok(x) = ObjectBytes::from(ctx, object) {
@@ -274,3 +464,56 @@ fn array_to_string(array: &Array) -> Result<String> {
 
     Ok(parts.join(","))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_data_slice() {
+        let data = BlobData::from_vec(b"hello world".to_vec());
+        let slice = data.slice(6, 11);
+        assert_eq!(slice.len, 5);
+        assert_eq!(slice.read().unwrap(), b"world");
+        assert_eq!(slice.slice(1, 3).read().unwrap(), b"or");
+        assert_eq!(data.slice(4, 2).len, 0);
+    }
+
+    #[test]
+    fn test_disk_backed_blob() {
+        let file = TempBlobFile::create(Arc::new(Vsys::new()), b"hello world").unwrap();
+        let path = file.path.clone();
+        let data = BlobData {
+            len: 11,
+            offset: 0,
+            source: BlobSource::Disk(Arc::new(file)),
+        };
+        assert_eq!(data.slice(6, 11).read().unwrap(), b"world");
+        assert!(data.read_range(0, 12).is_err());
+
+        drop(data);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_file_extends_blob() {
+        crate::utils::test::test_sync_with(|ctx| {
+            crate::buffer::init(&ctx)?;
+            let checks: Vec<bool> = ctx.eval(
+                r#"
+                const file = new File(["a"], "a.txt");
+                [
+                    file instanceof File,
+                    file instanceof Blob,
+                    !(new Blob(["a"]) instanceof File),
+                    Object.getPrototypeOf(File) === Blob,
+                    typeof file.slice === "function",
+                ]
+            "#,
+            )?;
+            assert!(checks.into_iter().all(|check| check));
+            Ok(())
+        })
+        .await;
+    }
+}
@@ -1,4 +1,4 @@
-use std::{mem::MaybeUninit, slice};
+use std::{mem::MaybeUninit, ptr, slice};
 
 use crate::utils::encoding::{bytes_from_b64, bytes_to_b64_string, Encoder};
 use crate::utils::{
@@ -13,8 +13,8 @@ use rsquickjs::{
     atom::PredefinedAtom,
     function::{Constructor, Opt},
     prelude::{Func, Rest, This},
-    Array, ArrayBuffer, Coerced, Ctx, Exception, IntoJs, JsLifetime, Object, Result, TypedArray,
-    Value,
+    Array, ArrayBuffer, BigInt, Coerced, Ctx, Exception, IntoJs, JsLifetime, Object, Result,
+    TypedArray, Value,
 };
 
 #[derive(JsLifetime)]
@@ -170,6 +170,10 @@ fn byte_length<'js>(ctx: Ctx<'js>, value: Value<'js>, encoding: Opt<String>) ->
     //slow path
     if let Some(encoding) = encoding.0 {
         let encoder = Encoder::from_str(&encoding).or_throw(&ctx)?;
+        if let Some(string) = value.as_string() {
+            let string = string.to_string()?;
+            return Ok(encoder.decode_from_string(string).or_throw(&ctx)?.len());
+        }
         let a = ObjectBytes::from(&ctx, &value)?;
         let bytes = a.as_bytes(&ctx)?;
         return Ok(encoder.decode(bytes).or_throw(&ctx)?.len());
@@ -229,6 +233,10 @@ fn concat<'js>(ctx: Ctx<'js>, list: Array<'js>, max_length: Opt<usize>) -> Resul
     Buffer(bytes).into_js(&ctx)
 }
 
+fn compare<'js>(ctx: Ctx<'js>, a: ObjectBytes<'js>, b: ObjectBytes<'js>) -> Result<i32> {
+    Ok(a.as_bytes(&ctx)?.cmp(b.as_bytes(&ctx)?) as i32)
+}
+
 fn from<'js>(
     ctx: Ctx<'js>,
     value: Value<'js>,
@@ -246,9 +254,15 @@ fn from<'js>(
         }
     }
 
-    // WARN: This is currently bugged for encodings that are not utf8 since we first
-    // convert to utf8 and then decode using the encoding.
-    // See https://github.com/quickjs-ng/quickjs/issues/992
+    // strings are decoded from their characters, their utf8 bytes would be wrong for
+    // latin1 and utf16le
+    if let (Some(string), Some(encoding)) = (value.as_string(), &encoding) {
+        let encoder = Encoder::from_str(encoding).or_throw(&ctx)?;
+        let bytes = encoder
+            .decode_from_string(string.to_string()?)
+            .or_throw(&ctx)?;
+        return Buffer(bytes).into_js(&ctx);
+    }
     if let Some(bytes) = get_string_bytes(&value, offset, length.0)? {
         return Buffer::from_encoding(&ctx, bytes, encoding)?.into_js(&ctx);
     }
@@ -311,41 +325,216 @@ fn is_encoding(value: Value) -> Result<bool> {
 }
 
 // Prototype Methods
+fn compare_range<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    target: ObjectBytes<'js>,
+    args: Rest<usize>,
+) -> Result<i32> {
+    let source = ObjectBytes::from(&ctx, this.0.as_inner())?;
+    let source_bytes = source.as_bytes(&ctx)?;
+    let target_bytes = target.as_bytes(&ctx)?;
+
+    let mut args_iter = args.0.into_iter();
+    let target_start = args_iter.next().unwrap_or_default();
+    let target_end = args_iter.next().unwrap_or(target_bytes.len());
+    let source_start = args_iter.next().unwrap_or_default();
+    let source_end = args_iter.next().unwrap_or(source_bytes.len());
+
+    if target_end > target_bytes.len() || source_end > source_bytes.len() {
+        return Err(Exception::throw_range(&ctx, "Index out of range"));
+    }
+
+    let source_bytes = source_bytes
+        .get(source_start..source_end)
+        .unwrap_or_default();
+    let target_bytes = target_bytes
+        .get(target_start..target_end)
+        .unwrap_or_default();
+    Ok(source_bytes.cmp(target_bytes) as i32)
+}
+
 fn copy<'js>(
     this: This<Object<'js>>,
     ctx: Ctx<'js>,
     target: ObjectBytes<'js>,
     args: Rest<usize>,
 ) -> Result<usize> {
+    let source = ObjectBytes::from(&ctx, this.0.as_inner())?;
+    let source_bytes = source.as_bytes(&ctx)?;
+    let target_bytes = view_bytes_mut(&ctx, &target)?;
+
     let mut args_iter = args.0.into_iter();
     let target_start = args_iter.next().unwrap_or_default();
     let source_start = args_iter.next().unwrap_or_default();
-    let source_end = args_iter.next().unwrap_or_else(|| this.0.len());
+    let source_end = args_iter
+        .next()
+        .unwrap_or(source_bytes.len())
+        .min(source_bytes.len());
 
-    let mut copyable_length = 0;
+    if source_start >= source_end || target_start >= target_bytes.len() {
+        return Ok(0);
+    }
+
+    let copyable_length = (source_end - source_start).min(target_bytes.len() - target_start);
 
-    if source_start >= source_end {
-        return Ok(copyable_length);
+    // source and target may be views of the same memory
+    unsafe {
+        ptr::copy(
+            source_bytes.as_ptr().add(source_start),
+            target_bytes.as_mut_ptr().add(target_start),
+            copyable_length,
+        );
     }
 
-    let source_bytes = ObjectBytes::from(&ctx, this.0.as_inner())?;
-    let source_bytes = source_bytes.as_bytes(&ctx)?;
+    Ok(copyable_length)
+}
 
-    if let Some((array_buffer, _, _)) = target.get_array_buffer()? {
-        let raw = array_buffer
-            .as_raw()
-            .ok_or(ERROR_MSG_ARRAY_BUFFER_DETACHED)
-            .or_throw(&ctx)?;
+fn equals<'js>(this: This<Object<'js>>, ctx: Ctx<'js>, other: ObjectBytes<'js>) -> Result<bool> {
+    let source = ObjectBytes::from(&ctx, this.0.as_inner())?;
+    Ok(source.as_bytes(&ctx)? == other.as_bytes(&ctx)?)
+}
 
-        let target_bytes = unsafe { slice::from_raw_parts_mut(raw.ptr.as_ptr(), raw.len) };
+fn fill<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    args: Rest<Value<'js>>,
+) -> Result<Object<'js>> {
+    let target = ObjectBytes::from(&ctx, this.0.as_inner())?;
+    let target_bytes = view_bytes_mut(&ctx, &target)?;
+
+    // offset and end may be left out for the encoding
+    let mut bounds = [0, target_bytes.len()];
+    let mut encoding = None;
+    for (index, arg) in args.0.iter().enumerate().take(3) {
+        if let Some(string) = arg.as_string() {
+            encoding = Some(string.to_string()?);
+            break;
+        }
+        if let (Some(bound), Some(number)) = (bounds.get_mut(index), arg.as_number()) {
+            *bound = number.max(0.0) as usize;
+        }
+    }
+    let [offset, end] = bounds;
 
-        copyable_length = (source_end - source_start).min(raw.len - target_start);
+    if offset > end || end > target_bytes.len() {
+        return Err(Exception::throw_range(
+            &ctx,
+            "The specified offset is out of range",
+        ));
+    }
 
-        target_bytes[target_start..target_start + copyable_length]
-            .copy_from_slice(&source_bytes[source_start..source_start + copyable_length]);
+    let mut pattern = value_bytes(&ctx, &value, encoding.as_deref())?;
+    if pattern.is_empty() {
+        pattern.push(0);
+    }
+    for (byte, fill) in target_bytes[offset..end]
+        .iter_mut()
+        .zip(pattern.iter().cycle())
+    {
+        *byte = *fill;
     }
 
-    Ok(copyable_length)
+    Ok(this.0)
+}
+
+fn includes<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    byte_offset: Opt<Value<'js>>,
+    encoding: Opt<String>,
+) -> Result<bool> {
+    Ok(search(&this, &ctx, &value, byte_offset, encoding, false)? != -1)
+}
+
+fn index_of<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    byte_offset: Opt<Value<'js>>,
+    encoding: Opt<String>,
+) -> Result<i64> {
+    search(&this, &ctx, &value, byte_offset, encoding, false)
+}
+
+fn last_index_of<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    byte_offset: Opt<Value<'js>>,
+    encoding: Opt<String>,
+) -> Result<i64> {
+    search(&this, &ctx, &value, byte_offset, encoding, true)
+}
+
+fn search<'js>(
+    this: &This<Object<'js>>,
+    ctx: &Ctx<'js>,
+    value: &Value<'js>,
+    byte_offset: Opt<Value<'js>>,
+    encoding: Opt<String>,
+    last: bool,
+) -> Result<i64> {
+    let source = ObjectBytes::from(ctx, this.0.as_inner())?;
+    let haystack = source.as_bytes(ctx)?;
+    let length = haystack.len() as i64;
+
+    // the byte offset may be left out for the encoding
+    let (byte_offset, encoding) = match byte_offset.0 {
+        Some(value) if value.is_string() => (None, Some(value.get::<String>()?)),
+        value => (
+            value
+                .and_then(|value| value.as_number())
+                .filter(|number| !number.is_nan()),
+            encoding.0,
+        ),
+    };
+    let needle = value_bytes(ctx, value, encoding.as_deref())?;
+
+    let start = match byte_offset {
+        Some(offset) if offset < 0.0 => length + offset as i64,
+        Some(offset) => offset as i64,
+        None if last => length,
+        None => 0,
+    };
+
+    if last {
+        if start < 0 {
+            return Ok(-1);
+        }
+        let end = (start.min(length) as usize + needle.len()).min(haystack.len());
+        return Ok(memchr::memmem::rfind(&haystack[..end], &needle).map_or(-1, |i| i as i64));
+    }
+
+    let start = start.max(0);
+    if start > length {
+        return Ok(if needle.is_empty() { length } else { -1 });
+    }
+    Ok(memchr::memmem::find(&haystack[start as usize..], &needle).map_or(-1, |i| start + i as i64))
+}
+
+/// Bytes of a value to search for or fill with
+fn value_bytes<'js>(ctx: &Ctx<'js>, value: &Value<'js>, encoding: Option<&str>) -> Result<Vec<u8>> {
+    if let Some(string) = value.as_string() {
+        let encoder = Encoder::from_optional_str(encoding).or_throw(ctx)?;
+        return encoder
+            .decode_from_string(string.to_string()?)
+            .or_throw(ctx);
+    }
+    if let Some(number) = value.as_number() {
+        return Ok(vec![number as i64 as u8]);
+    }
+    if let Some(obj) = value.as_object() {
+        if let Some(bytes) = ObjectBytes::from_array_buffer(obj)? {
+            return Ok(bytes.as_bytes(ctx)?.to_vec());
+        }
+    }
+    Err(Exception::throw_type(
+        ctx,
+        "The \"value\" argument must be one of type number or string or an instance of Buffer or Uint8Array",
+    ))
 }
 
 fn subarray<'js>(
@@ -382,12 +571,34 @@ fn subarray<'js>(
     Buffer::from_array_buffer_offset_length(&ctx, array_buffer, new_offset, length)
 }
 
-fn to_string(this: This<Object<'_>>, ctx: Ctx, encoding: Opt<String>) -> Result<String> {
+fn to_string<'js>(
+    this: This<Object<'js>>,
+    ctx: Ctx<'js>,
+    encoding: Opt<Option<String>>,
+    start: Opt<Value<'js>>,
+    end: Opt<Value<'js>>,
+) -> Result<String> {
     let typed_array = TypedArray::<u8>::from_object(this.0)?;
     let bytes: &[u8] = typed_array.as_ref();
+    let start = index_arg(start.0, 0, bytes.len());
+    let end = index_arg(end.0, bytes.len(), bytes.len()).max(start);
 
-    let encoder = Encoder::from_optional_str(encoding.as_deref()).or_throw(&ctx)?;
-    encoder.encode_to_string(bytes, true).or_throw(&ctx)
+    let encoder = Encoder::from_optional_str(encoding.0.flatten().as_deref()).or_throw(&ctx)?;
+    encoder
+        .encode_to_string(&bytes[start..end], true)
+        .or_throw(&ctx)
+}
+
+fn index_arg(value: Option<Value<'_>>, default: usize, len: usize) -> usize {
+    value
+        .and_then(|value| value.as_number())
+        .map_or(default, |number| {
+            if number > 0.0 {
+                (number as usize).min(len)
+            } else {
+                0
+            }
+        })
 }
 
 fn write<'js>(
@@ -396,38 +607,33 @@ fn write<'js>(
     string: String,
     args: Rest<Value<'js>>,
 ) -> Result<usize> {
-    let (offset, length, encoding) = get_write_parameters(&args, this.0.len())?;
-
     let target = ObjectBytes::from(&ctx, this.0.as_inner())?;
+    let target_bytes = view_bytes_mut(&ctx, &target)?;
 
-    let mut writable_length = 0;
+    let (offset, length, encoding) = get_write_parameters(&ctx, &args, target_bytes.len())?;
 
-    if let Some((array_buffer, _, _)) = target.get_array_buffer()? {
-        let raw = array_buffer
-            .as_raw()
-            .ok_or(ERROR_MSG_ARRAY_BUFFER_DETACHED)
-            .or_throw(&ctx)?;
-
-        let target_bytes = unsafe { slice::from_raw_parts_mut(raw.ptr.as_ptr(), raw.len) };
-
-        let encoder = Encoder::from_str(&encoding).or_throw(&ctx)?;
+    let encoder = Encoder::from_str(&encoding).or_throw(&ctx)?;
 
-        if encoder.as_label() == "utf-8" {
-            let (source_slice, valid_length) = safe_byte_slice(&string, length.min(string.len()));
-            writable_length = valid_length;
-            target_bytes[offset..offset + writable_length].copy_from_slice(source_slice);
-        } else {
-            let decode_bytes = encoder.decode_from_string(string).or_throw(&ctx)?;
-            writable_length = length.min(decode_bytes.len());
-            target_bytes[offset..offset + writable_length]
-                .copy_from_slice(&decode_bytes[..writable_length]);
-        };
-    }
+    let writable_length = if encoder.as_label() == "utf-8" {
+        let (source_slice, valid_length) = safe_byte_slice(&string, length.min(string.len()));
+        target_bytes[offset..offset + valid_length].copy_from_slice(source_slice);
+        valid_length
+    } else {
+        let decode_bytes = encoder.decode_from_string(string).or_throw(&ctx)?;
+        let writable_length = length.min(decode_bytes.len());
+        target_bytes[offset..offset + writable_length]
+            .copy_from_slice(&decode_bytes[..writable_length]);
+        writable_length
+    };
 
     Ok(writable_length)
 }
 
-fn get_write_parameters(args: &Rest<Value<'_>>, len: usize) -> Result<(usize, usize, String)> {
+fn get_write_parameters(
+    ctx: &Ctx<'_>,
+    args: &Rest<Value<'_>>,
+    len: usize,
+) -> Result<(usize, usize, String)> {
     let mut offset = 0;
     let mut length = len;
     let mut encoding = "utf8".to_owned();
@@ -436,7 +642,13 @@ fn get_write_parameters(args: &Rest<Value<'_>>, len: usize) -> Result<(usize, us
         if let Some(s) = v1.as_string() {
             return Ok((0, len, s.to_string()?));
         }
-        offset = v1.as_int().unwrap_or(0) as usize;
+        offset = v1.as_int().unwrap_or(0).max(0) as usize;
+        if offset > len {
+            return Err(Exception::throw_range(
+                ctx,
+                "The specified offset is out of range",
+            ));
+        }
     }
 
     if let Some(v2) = args.0.get(1) {
@@ -498,7 +710,7 @@ fn write_buf<'js>(
         (true, _) if value.as_big_int().is_none() => {
             return Err(Exception::throw_type(ctx, "Expected BigInt"))
         }
-        (_, true) if !value.is_number() => {
+        (false, _) if !value.is_number() => {
             return Err(Exception::throw_type(ctx, "Expected number"))
         }
         _ => (),
@@ -506,13 +718,20 @@ fn write_buf<'js>(
 
     // Extract and convert value
     let (byte_count, bytes) = if is_bigint {
-        let bigint = value.as_big_int().unwrap();
-        let (byte_count, val) = match (signed, bits) {
-            (true, 64) => (8, bigint.clone().to_i64().or_throw(ctx)? as u64),
-            (false, 64) => return Err(Exception::throw_type(ctx, "Uint64 is not supported")),
-            _ => return Err(Exception::throw_range(ctx, "Invalid BigInt size")),
-        };
-        (byte_count, endian_bytes(val, endian))
+        if bits != 64 {
+            return Err(Exception::throw_range(ctx, "Invalid BigInt size"));
+        }
+        // the decimal form is exact where a number would round
+        let val = value
+            .get::<Coerced<String>>()?
+            .parse::<i128>()
+            .ok()
+            .filter(|val| match signed {
+                true => (i64::MIN as i128..=i64::MAX as i128).contains(val),
+                false => (0..=u64::MAX as i128).contains(val),
+            })
+            .ok_or_else(|| Exception::throw_range(ctx, "Value out of range"))?;
+        (8, endian_bytes(val as u64, endian))
     } else if is_float {
         let float_val = value.as_float().unwrap();
         match (bits, endian) {
@@ -543,29 +762,103 @@ fn write_buf<'js>(
         )
     };
 
-    if offset >= this.0.len() || offset + byte_count > this.0.len() {
+    let target = ObjectBytes::from(ctx, this.0.as_inner())?;
+    let target_bytes = view_bytes_mut(ctx, &target)?;
+
+    if offset + byte_count > target_bytes.len() {
         return Err(Exception::throw_range(
             ctx,
             "The specified offset is out of range",
         ));
     }
 
-    let target = ObjectBytes::from(ctx, this.0.as_inner())?;
-    let mut writable_length = 0;
+    target_bytes[offset..offset + byte_count].copy_from_slice(&bytes[..byte_count]);
+
+    Ok(offset + byte_count)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_buf<'js>(
+    this: &This<Object<'js>>,
+    ctx: &Ctx<'js>,
+    offset: &Opt<usize>,
+    endian: Endian,
+    bits: u8,
+    signed: bool,
+    is_float: bool,
+    is_bigint: bool,
+) -> Result<Value<'js>> {
+    let offset = offset.0.unwrap_or_default();
+    let byte_count = (bits / 8) as usize;
 
-    if let Some((array_buffer, _, _)) = target.get_array_buffer()? {
-        let raw = array_buffer
-            .as_raw()
-            .ok_or(ERROR_MSG_ARRAY_BUFFER_DETACHED)
-            .or_throw(ctx)?;
+    let source = ObjectBytes::from(ctx, this.0.as_inner())?;
+    let source_bytes = source.as_bytes(ctx)?;
 
-        let target_bytes = unsafe { slice::from_raw_parts_mut(raw.ptr.as_ptr(), raw.len) };
+    if offset + byte_count > source_bytes.len() {
+        return Err(Exception::throw_range(
+            ctx,
+            "The specified offset is out of range",
+        ));
+    }
+
+    let val = bytes_to_u64(&source_bytes[offset..offset + byte_count], endian);
 
-        writable_length = offset + bytes.len();
-        target_bytes[offset..writable_length].copy_from_slice(&bytes);
+    if is_bigint {
+        let bigint = if signed {
+            BigInt::from_i64(ctx.clone(), val as i64)?
+        } else {
+            BigInt::from_u64(ctx.clone(), val)?
+        };
+        return Ok(bigint.into_value());
     }
 
-    Ok(writable_length)
+    let number = if is_float {
+        match bits {
+            32 => f32::from_bits(val as u32) as f64,
+            _ => f64::from_bits(val),
+        }
+    } else if signed {
+        // sign extend from the top bit read
+        let shift = 64 - bits as u32;
+        (((val << shift) as i64) >> shift) as f64
+    } else {
+        val as f64
+    };
+    Ok(Value::new_number(ctx.clone(), number))
+}
+
+/// Bits of the 1 to 6 bytes `readIntBE` and friends take
+fn int_bits(ctx: &Ctx<'_>, byte_length: usize) -> Result<u8> {
+    if !(1..=6).contains(&byte_length) {
+        return Err(Exception::throw_range(
+            ctx,
+            "The value of \"byteLength\" is out of range. It must be >= 1 and <= 6",
+        ));
+    }
+    Ok((byte_length * 8) as u8)
+}
+
+/// Writable bytes of a view, starting at its `byteOffset`
+fn view_bytes_mut<'a>(ctx: &Ctx<'_>, view: &'a ObjectBytes<'_>) -> Result<&'a mut [u8]> {
+    let length = view.as_bytes(ctx)?.len();
+    let Some((array_buffer, _, offset)) = view.get_array_buffer()? else {
+        return Ok(&mut []);
+    };
+    let raw = array_buffer
+        .as_raw()
+        .ok_or(ERROR_MSG_ARRAY_BUFFER_DETACHED)
+        .or_throw(ctx)?;
+
+    let length = length.min(raw.len.saturating_sub(offset));
+    Ok(unsafe { slice::from_raw_parts_mut(raw.ptr.as_ptr().add(offset), length) })
+}
+
+fn bytes_to_u64(bytes: &[u8], endian: Endian) -> u64 {
+    let fold = |val: u64, byte: &u8| (val << 8) | *byte as u64;
+    match endian {
+        Endian::Big => bytes.iter().fold(0, fold),
+        Endian::Little => bytes.iter().rev().fold(0, fold),
+    }
 }
 
 // Pure mathematical byte generation
@@ -608,13 +901,147 @@ pub(crate) fn set_prototype<'js>(ctx: &Ctx<'js>, constructor: Object<'js>) -> Re
     let _ = &constructor.set("allocUnsafe", Func::from(alloc_unsafe))?;
     let _ = &constructor.set("allocUnsafeSlow", Func::from(alloc_unsafe_slow))?;
     let _ = &constructor.set("byteLength", Func::from(byte_length))?;
+    let _ = &constructor.set("compare", Func::from(compare))?;
     let _ = &constructor.set("concat", Func::from(concat))?;
     let _ = &constructor.set(PredefinedAtom::From, Func::from(from))?;
     let _ = &constructor.set("isBuffer", Func::from(is_buffer))?;
     let _ = &constructor.set("isEncoding", Func::from(is_encoding))?;
 
     let prototype: &Object = &constructor.get(PredefinedAtom::Prototype)?;
+    prototype.set("compare", Func::from(compare_range))?;
     prototype.set("copy", Func::from(copy))?;
+    prototype.set("equals", Func::from(equals))?;
+    prototype.set("fill", Func::from(fill))?;
+    prototype.set("includes", Func::from(includes))?;
+    prototype.set("indexOf", Func::from(index_of))?;
+    prototype.set("lastIndexOf", Func::from(last_index_of))?;
+    prototype.set(
+        "readBigInt64BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 64, true, false, true)),
+    )?;
+    prototype.set(
+        "readBigInt64LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 64, true, false, true)),
+    )?;
+    prototype.set(
+        "readBigUInt64BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 64, false, false, true)),
+    )?;
+    prototype.set(
+        "readBigUInt64LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 64, false, false, true)),
+    )?;
+    prototype.set(
+        "readDoubleBE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 64, true, true, false)),
+    )?;
+    prototype.set(
+        "readDoubleLE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 64, true, true, false)),
+    )?;
+    prototype.set(
+        "readFloatBE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 32, true, true, false)),
+    )?;
+    prototype.set(
+        "readFloatLE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 32, true, true, false)),
+    )?;
+    prototype.set(
+        "readInt8",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 8, true, false, false)),
+    )?;
+    prototype.set(
+        "readInt16BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 16, true, false, false)),
+    )?;
+    prototype.set(
+        "readInt16LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 16, true, false, false)),
+    )?;
+    prototype.set(
+        "readInt32BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 32, true, false, false)),
+    )?;
+    prototype.set(
+        "readInt32LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 32, true, false, false)),
+    )?;
+    prototype.set(
+        "readIntBE",
+        Func::from(|t, c, o, l| {
+            let bits = int_bits(&c, l)?;
+            read_buf(&t, &c, &Opt(Some(o)), Endian::Big, bits, true, false, false)
+        }),
+    )?;
+    prototype.set(
+        "readIntLE",
+        Func::from(|t, c, o, l| {
+            let bits = int_bits(&c, l)?;
+            read_buf(
+                &t,
+                &c,
+                &Opt(Some(o)),
+                Endian::Little,
+                bits,
+                true,
+                false,
+                false,
+            )
+        }),
+    )?;
+    prototype.set(
+        "readUInt8",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 8, false, false, false)),
+    )?;
+    prototype.set(
+        "readUInt16BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 16, false, false, false)),
+    )?;
+    prototype.set(
+        "readUInt16LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 16, false, false, false)),
+    )?;
+    prototype.set(
+        "readUInt32BE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Big, 32, false, false, false)),
+    )?;
+    prototype.set(
+        "readUInt32LE",
+        Func::from(|t, c, o| read_buf(&t, &c, &o, Endian::Little, 32, false, false, false)),
+    )?;
+    prototype.set(
+        "readUIntBE",
+        Func::from(|t, c, o, l| {
+            let bits = int_bits(&c, l)?;
+            read_buf(
+                &t,
+                &c,
+                &Opt(Some(o)),
+                Endian::Big,
+                bits,
+                false,
+                false,
+                false,
+            )
+        }),
+    )?;
+    prototype.set(
+        "readUIntLE",
+        Func::from(|t, c, o, l| {
+            let bits = int_bits(&c, l)?;
+            read_buf(
+                &t,
+                &c,
+                &Opt(Some(o)),
+                Endian::Little,
+                bits,
+                false,
+                false,
+                false,
+            )
+        }),
+    )?;
     prototype.set("subarray", Func::from(subarray))?;
     prototype.set(PredefinedAtom::ToString, Func::from(to_string))?;
     prototype.set("write", Func::from(write))?;
@@ -626,6 +1053,14 @@ pub(crate) fn set_prototype<'js>(ctx: &Ctx<'js>, constructor: Object<'js>) -> Re
         "writeBigInt64LE",
         Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Little, 64, true, false, true)),
     )?;
+    prototype.set(
+        "writeBigUInt64BE",
+        Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Big, 64, false, false, true)),
+    )?;
+    prototype.set(
+        "writeBigUInt64LE",
+        Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Little, 64, false, false, true)),
+    )?;
     prototype.set(
         "writeDoubleBE",
         Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Big, 64, true, true, false)),
@@ -662,6 +1097,40 @@ pub(crate) fn set_prototype<'js>(ctx: &Ctx<'js>, constructor: Object<'js>) -> Re
         "writeInt32LE",
         Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Little, 32, true, false, false)),
     )?;
+    prototype.set(
+        "writeIntBE",
+        Func::from(|t, c, v, o, l| {
+            let bits = int_bits(&c, l)?;
+            write_buf(
+                &t,
+                &c,
+                &v,
+                &Opt(Some(o)),
+                Endian::Big,
+                bits,
+                true,
+                false,
+                false,
+            )
+        }),
+    )?;
+    prototype.set(
+        "writeIntLE",
+        Func::from(|t, c, v, o, l| {
+            let bits = int_bits(&c, l)?;
+            write_buf(
+                &t,
+                &c,
+                &v,
+                &Opt(Some(o)),
+                Endian::Little,
+                bits,
+                true,
+                false,
+                false,
+            )
+        }),
+    )?;
     prototype.set(
         "writeUInt8",
         Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Little, 8, false, false, false)),
@@ -682,6 +1151,48 @@ pub(crate) fn set_prototype<'js>(ctx: &Ctx<'js>, constructor: Object<'js>) -> Re
         "writeUInt32LE",
         Func::from(|t, c, v, o| write_buf(&t, &c, &v, &o, Endian::Little, 32, false, false, false)),
     )?;
+    prototype.set(
+        "writeUIntBE",
+        Func::from(|t, c, v, o, l| {
+            let bits = int_bits(&c, l)?;
+            write_buf(
+                &t,
+                &c,
+                &v,
+                &Opt(Some(o)),
+                Endian::Big,
+                bits,
+                false,
+                false,
+                false,
+            )
+        }),
+    )?;
+    prototype.set(
+        "writeUIntLE",
+        Func::from(|t, c, v, o, l| {
+            let bits = int_bits(&c, l)?;
+            write_buf(
+                &t,
+                &c,
+                &v,
+                &Opt(Some(o)),
+                Endian::Little,
+                bits,
+                false,
+                false,
+                false,
+            )
+        }),
+    )?;
+    // node also names the unsigned methods readUint8, writeBigUint64LE and so on
+    for name in prototype.keys::<String>() {
+        let name = name?;
+        if name.contains("UInt") {
+            let method: Value = prototype.get(name.as_str())?;
+            prototype.set(name.replace("UInt", "Uint"), method)?;
+        }
+    }
     //not assessable from js
     prototype.prop(PredefinedAtom::Meta, stringify!(Buffer))?;
 
@@ -893,4 +1404,58 @@ mod tests {
         })
         .await;
     }
+
+    #[tokio::test]
+    async fn test_read_write_search() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                super::super::init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<BufferModule>(ctx.clone(), "buffer")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { Buffer } from 'buffer';
+
+                        export async function test() {
+                            const buffer = Buffer.alloc(16);
+                            const view = buffer.subarray(4);
+                            view.writeUInt16BE(0xbeef, 0);
+                            view.writeIntLE(-2, 2, 3);
+                            view.writeBigUInt64LE(0xffffffffffffffffn, 4);
+                            const results = [
+                                buffer.readUInt16BE(4).toString(16),
+                                buffer.readIntLE(6, 3),
+                                buffer.readUint8(5).toString(16),
+                                view.readBigUInt64LE(4),
+                                view.readBigInt64BE(4),
+                                buffer.indexOf(0xef),
+                                buffer.lastIndexOf(Buffer.from([0xff, 0xff])),
+                                buffer.includes('beef', 'hex'),
+                                Buffer.compare(Buffer.from('a'), Buffer.from('b')),
+                                Buffer.from('abc').compare(Buffer.from('xbc'), 1, 3, 1),
+                                Buffer.from('ab').equals(Buffer.from('ab')),
+                                Buffer.alloc(5).fill('ab', 1).toString('latin1', 1),
+                                Buffer.from([0xfb, 0xff]).toString('base64url'),
+                                Buffer.from('-_8', 'base64url').toString('hex'),
+                                Buffer.from('é', 'latin1').length,
+                            ];
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "beef|-2|ef|18446744073709551615|-1|5|14|true|-1|0|true|abab|-_8|fbff|1"
+                );
+            })
+        })
+        .await;
+    }
 }
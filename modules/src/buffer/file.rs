@@ -6,7 +6,7 @@ use rsquickjs::{
 
 use super::blob::Blob;
 
-#[rsquickjs::class]
+#[rsquickjs::class(extends = Blob)]
 #[derive(Trace, Clone, rsquickjs::JsLifetime)]
 pub struct File {
     #[qjs(skip_trace)]
@@ -71,8 +71,8 @@ impl File {
         self.blob.slice(start, end, content_type)
     }
 
-    pub async fn text(&mut self) -> String {
-        self.blob.text().await
+    pub async fn text(&mut self, ctx: Ctx<'_>) -> Result<String> {
+        self.blob.text(ctx).await
     }
 
     #[qjs(rename = "arrayBuffer")]
@@ -84,6 +84,10 @@ impl File {
         self.blob.bytes(ctx).await
     }
 
+    pub fn stream<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        self.blob.stream(ctx)
+    }
+
     #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
     pub fn to_string_tag(&self) -> &'static str {
         stringify!(File)
@@ -3,7 +3,6 @@ use crate::utils::{
     primordials::{BasePrimordials, Primordial},
 };
 use rsquickjs::{
-    atom::PredefinedAtom,
     function::Constructor,
     module::{Declarations, Exports, ModuleDef},
     prelude::Func,
@@ -75,16 +74,8 @@ pub fn init<'js>(ctx: &Ctx<'js>) -> Result<()> {
 
     BufferPrimordials::init(ctx)?;
 
-    // Blob
-    if let Some(constructor) = Class::<Blob>::create_constructor(ctx)? {
-        constructor.prop(
-            PredefinedAtom::SymbolHasInstance,
-            Func::from(Blob::has_instance),
-        )?;
-        globals.set(stringify!(Blob), constructor)?;
-    }
-
-    // File
+    // Blob, then File which extends it like in the spec
+    Class::<Blob>::define(&globals)?;
     Class::<File>::define(&globals)?;
 
     //init primordials
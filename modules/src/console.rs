@@ -1,71 +1,414 @@
-use std::io::{stderr, stdout, IsTerminal, Write};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use xmas_vsys::{stdio::color_env_override, StdStream};
 
+use crate::permissions::get_stdio;
 use crate::utils::{
     console::{build_formatted_string, FormatOptions, NEWLINE},
+    json::{escape::escape_json_string, stringify::json_stringify},
     module::{export_default, ModuleInfo},
+    primordials::{BasePrimordials, Primordial},
 };
 use rsquickjs::{
     module::{Declarations, Exports, ModuleDef},
-    prelude::{Func, Rest},
-    Class, Ctx, Object, Result, Value,
+    prelude::{Func, Opt, Rest, This},
+    runtime::UserDataGuard,
+    CatchResultExt, Class, Ctx, Exception, Function, Object, Result, Value,
 };
 
+const DEFAULT_LABEL: &str = "default";
+const GROUP_INDENTATION: &str = "  ";
+const CLEAR_SCREEN: &str = "\x1b[1;1H\x1b[0J";
+
 #[derive(Debug, Clone, PartialEq, Eq, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
 pub enum LogType {
     Stdio,
     Trace,
+    /// One JSON object per call on stdio, for log aggregators
+    Json,
 }
 
-#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
-#[rsquickjs::class]
-pub struct Console {}
+impl FromStr for LogType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" | "stdio" => Ok(Self::Stdio),
+            "trace" | "tracing" => Ok(Self::Trace),
+            "json" => Ok(Self::Json),
+            _ => Err(["Invalid log format: ", s].concat()),
+        }
+    }
+}
+
+/// Per context state behind `console.group`, `console.count` and `console.time`.
+#[derive(Default, rsquickjs::JsLifetime)]
+struct ConsoleState {
+    group_depth: Cell<usize>,
+    counts: RefCell<HashMap<String, usize>>,
+    timers: RefCell<HashMap<String, Instant>>,
+}
+
+/// Severity of a console call, deciding the stream and the tracing level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Info,
+    Debug,
+    Trace,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn stream(self) -> StdStream {
+        match self {
+            Level::Info => StdStream::Stdout,
+            _ => StdStream::Stderr,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// The writable streams of a `new Console(stdout, stderr)`, any objects with a `write`
+/// method such as `process.stdout`.
+#[derive(rsquickjs::class::Trace)]
+struct ConsoleStreams<'js> {
+    stdout: Object<'js>,
+    stderr: Object<'js>,
+    /// `colorMode` of the options, `None` for "auto"
+    #[qjs(skip_trace)]
+    color_mode: Option<bool>,
+}
+
+impl<'js> ConsoleStreams<'js> {
+    fn stream(&self, level: Level) -> &Object<'js> {
+        match level.stream() {
+            StdStream::Stdout => &self.stdout,
+            _ => &self.stderr,
+        }
+    }
+
+    fn color(&self, level: Level) -> Result<bool> {
+        if let Some(color) = self.color_mode {
+            return Ok(color);
+        }
+        Ok(color_env_override().unwrap_or(is_tty(self.stream(level))?))
+    }
+
+    fn write(&self, level: Level, message: String) -> Result<()> {
+        let stream = self.stream(level);
+        let write: Function = stream.get("write")?;
+        write.call((This(stream.clone()), message))
+    }
+}
+
+/// Where console output goes: the context's stdio (or tracing, depending on the
+/// [`LogType`]), or the streams of a `Console` instance along with its own state.
+#[derive(Clone, Copy)]
+enum Output<'a, 'js> {
+    Context,
+    Streams(&'a ConsoleStreams<'js>, &'a ConsoleState),
+}
+
+impl<'js> Output<'_, 'js> {
+    fn with_state<R>(self, ctx: &Ctx<'_>, f: impl FnOnce(&ConsoleState) -> R) -> R {
+        match self {
+            Output::Context => f(&console_state(ctx)),
+            Output::Streams(_, state) => f(state),
+        }
+    }
+
+    /// The [`LogType`] of the context, `Console` streams always get text
+    fn log_type(self, ctx: &Ctx<'_>) -> LogType {
+        match self {
+            Output::Context => ctx.userdata::<LogType>().unwrap().clone(),
+            Output::Streams(..) => LogType::Stdio,
+        }
+    }
+
+    /// Whether values are formatted with ANSI colors
+    fn color(self, ctx: &Ctx<'_>, level: Level) -> Result<bool> {
+        match self {
+            _ if self.log_type(ctx) != LogType::Stdio => Ok(false),
+            Output::Context => Ok(get_stdio(ctx).has_colors(level.stream())),
+            Output::Streams(streams, _) => streams.color(level),
+        }
+    }
 
-impl Default for Console {
-    fn default() -> Self {
-        Self::new()
+    /// Formats `args` and writes them at `level`
+    fn log(self, ctx: &Ctx<'js>, level: Level, args: Rest<Value<'js>>) -> Result<()> {
+        if let Output::Context = self {
+            report_to_inspector(ctx, level, &args);
+        }
+        if self.log_type(ctx) == LogType::Json {
+            let message = format_message(false, true, ctx, Rest(args.0.clone()))?;
+            return write_json_line(ctx, level, &message, &args);
+        }
+        let color = self.color(ctx, level)?;
+        let message = format_message(color, true, ctx, args)?;
+        self.write(ctx, level, message)
+    }
+
+    /// Writes an already formatted message, indented by the current group
+    fn write(self, ctx: &Ctx<'js>, level: Level, message: String) -> Result<()> {
+        let log_type = self.log_type(ctx);
+        if log_type == LogType::Json {
+            return write_json_line(ctx, level, &message, &[]);
+        }
+        let depth = self.with_state(ctx, |state| state.group_depth.get());
+        let mut message = indent_group(depth, message);
+        if log_type == LogType::Trace {
+            let module_name = get_modeule_name_helper(ctx.clone());
+            match level {
+                Level::Info => tracing::info!(module = module_name, "{}", message),
+                Level::Debug => tracing::debug!(module = module_name, "{}", message),
+                Level::Trace => tracing::trace!(module = module_name, "{}", message),
+                Level::Warn => tracing::warn!(module = module_name, "{}", message),
+                Level::Error => tracing::error!(module = module_name, "{}", message),
+            }
+            return Ok(());
+        }
+        message.push(NEWLINE);
+        match self {
+            Output::Context => {
+                // we don't care if output is interrupted
+                let _ = get_stdio(ctx).write(level.stream(), message.as_bytes());
+                Ok(())
+            }
+            Output::Streams(streams, _) => streams.write(level, message),
+        }
     }
 }
 
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct Console<'js> {
+    streams: Option<ConsoleStreams<'js>>,
+    #[qjs(skip_trace)]
+    state: ConsoleState,
+}
+
 #[rsquickjs::methods(rename_all = "camelCase")]
-impl Console {
+impl<'js> Console<'js> {
+    /// `new Console(stdout, stderr?)` or `new Console({ stdout, stderr?, colorMode? })`,
+    /// without streams it writes to the process stdio like the global console
     #[qjs(constructor)]
-    pub fn new() -> Self {
-        // We ignore the parameters for now since we don't support stream
-        Self {}
+    pub fn new(ctx: Ctx<'js>, stdout: Opt<Value<'js>>, stderr: Opt<Value<'js>>) -> Result<Self> {
+        let mut color_mode = None;
+        let (stdout, stderr) = match stdout.0.and_then(|stdout| stdout.into_object()) {
+            Some(options) if !options.get::<_, Value>("write")?.is_function() => {
+                let mode: Value = options.get("colorMode")?;
+                if let Some(mode) = mode.as_bool() {
+                    color_mode = Some(mode);
+                } else if !mode.is_undefined()
+                    && mode
+                        .as_string()
+                        .and_then(|mode| mode.to_string().ok())
+                        .as_deref()
+                        != Some("auto")
+                {
+                    return Err(Exception::throw_type(
+                        &ctx,
+                        "The \"colorMode\" option must be one of true, false or 'auto'",
+                    ));
+                }
+                (options.get("stdout")?, options.get("stderr")?)
+            }
+            stdout => (stdout, stderr.0.and_then(|stderr| stderr.into_object())),
+        };
+
+        let streams = match stdout {
+            Some(stdout) => {
+                let stderr = stderr.unwrap_or_else(|| stdout.clone());
+                check_writable(&ctx, &stdout, "stdout")?;
+                check_writable(&ctx, &stderr, "stderr")?;
+                Some(ConsoleStreams {
+                    stdout,
+                    stderr,
+                    color_mode,
+                })
+            }
+            None => None,
+        };
+        Ok(Self {
+            streams,
+            state: ConsoleState::default(),
+        })
     }
 
-    pub fn log<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log(ctx, args)
+    pub fn log(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Info, args)
     }
 
-    pub fn clear<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        clear(ctx, args)
+    pub fn clear(&self, ctx: Ctx<'js>) -> Result<()> {
+        clear(self.output(), ctx)
+    }
+    pub fn debug(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Debug, args)
     }
-    pub fn debug<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log_debug(ctx, args)
+    pub fn info(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Info, args)
     }
-    pub fn info<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log(ctx, args)
+    pub fn trace(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Trace, args)
     }
-    pub fn trace<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log_trace(ctx, args)
+    pub fn error(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Error, args)
     }
-    pub fn error<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log_error(ctx, args)
+    pub fn warn(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        self.output().log(&ctx, Level::Warn, args)
     }
-    pub fn warn<'js>(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-        log_warn(ctx, args)
+    pub fn assert(&self, ctx: Ctx<'js>, expression: bool, args: Rest<Value<'js>>) -> Result<()> {
+        log_assert(self.output(), ctx, expression, args)
     }
-    pub fn assert<'js>(
+    pub fn table(
         &self,
         ctx: Ctx<'js>,
-        expression: bool,
+        data: Value<'js>,
+        properties: Opt<Vec<String>>,
+    ) -> Result<()> {
+        table(self.output(), ctx, data, properties)
+    }
+    pub fn group(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        group(self.output(), ctx, args)
+    }
+    pub fn group_collapsed(&self, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+        group(self.output(), ctx, args)
+    }
+    pub fn group_end(&self, ctx: Ctx<'js>) {
+        group_end(self.output(), ctx)
+    }
+    pub fn count(&self, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+        count(self.output(), ctx, label)
+    }
+    pub fn count_reset(&self, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+        count_reset(self.output(), ctx, label)
+    }
+    pub fn time(&self, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+        time(self.output(), ctx, label)
+    }
+    pub fn time_log(
+        &self,
+        ctx: Ctx<'js>,
+        label: Opt<String>,
         args: Rest<Value<'js>>,
     ) -> Result<()> {
-        log_assert(ctx, expression, args)
+        time_log(self.output(), ctx, label, args)
+    }
+    pub fn time_end(&self, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+        time_end(self.output(), ctx, label)
+    }
+    pub fn dir(&self, ctx: Ctx<'js>, value: Value<'js>, options: Opt<Object<'js>>) -> Result<()> {
+        dir(self.output(), ctx, value, options)
+    }
+}
+
+impl<'js> Console<'js> {
+    fn output(&self) -> Output<'_, 'js> {
+        match &self.streams {
+            Some(streams) => Output::Streams(streams, &self.state),
+            None => Output::Context,
+        }
     }
 }
 
+/// Writes a console call as one line of JSON
+fn write_json_line<'js>(
+    ctx: &Ctx<'js>,
+    level: Level,
+    message: &str,
+    args: &[Value<'js>],
+) -> Result<()> {
+    let mut line = json_line(ctx, level, message, args)?;
+    line.push(NEWLINE);
+    // we don't care if output is interrupted
+    let _ = get_stdio(ctx).write(level.stream(), line.as_bytes());
+    Ok(())
+}
+
+/// The JSON object of a console call: its level, module, timestamp in milliseconds since
+/// the epoch, formatted message and arguments
+fn json_line<'js>(
+    ctx: &Ctx<'js>,
+    level: Level,
+    message: &str,
+    args: &[Value<'js>],
+) -> Result<String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let mut line = String::from("{\"level\":\"");
+    line.push_str(level.name());
+    line.push_str("\",\"module\":");
+    push_json_string(&mut line, &get_modeule_name_helper(ctx.clone()));
+    line.push_str(",\"timestamp\":");
+    line.push_str(itoa::Buffer::new().format(timestamp));
+    line.push_str(",\"message\":");
+    push_json_string(&mut line, message);
+    line.push_str(",\"args\":[");
+    for (i, arg) in args.iter().enumerate() {
+        if i != 0 {
+            line.push(',');
+        }
+        push_json_arg(&mut line, ctx, arg.clone())?;
+    }
+    line.push_str("]}");
+    Ok(line)
+}
+
+/// Pushes `value` as JSON, or as its formatted string when JSON can't represent it,
+/// like errors, functions and cyclic objects
+fn push_json_arg<'js>(result: &mut String, ctx: &Ctx<'js>, value: Value<'js>) -> Result<()> {
+    if !(value.is_error() || value.is_function() || value.is_symbol() || value.is_undefined()) {
+        if let Ok(Some(json)) = json_stringify(ctx, value.clone()).catch(ctx) {
+            result.push_str(&json);
+            return Ok(());
+        }
+    }
+    let formatted = format_message(false, false, ctx, Rest(vec![value]))?;
+    push_json_string(result, &formatted);
+    Ok(())
+}
+
+fn push_json_string(result: &mut String, value: &str) {
+    result.push('"');
+    escape_json_string(result, value.as_bytes());
+    result.push('"');
+}
+
+fn check_writable(ctx: &Ctx<'_>, stream: &Object<'_>, name: &str) -> Result<()> {
+    if !stream.get::<_, Value>("write")?.is_function() {
+        return Err(Exception::throw_type(
+            ctx,
+            &["Console expects a writable stream instance for ", name].concat(),
+        ));
+    }
+    Ok(())
+}
+
+fn is_tty(stream: &Object<'_>) -> Result<bool> {
+    Ok(stream
+        .get::<_, Value>("isTTY")?
+        .as_bool()
+        .unwrap_or_default())
+}
+
 fn get_modeule_name_helper(ctx: Ctx<'_>) -> String {
     ctx.script_or_module_name(1)
         .map(|a| a.to_string())
@@ -74,17 +417,7 @@ fn get_modeule_name_helper(ctx: Ctx<'_>) -> String {
 }
 
 pub fn log<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => write_log(stdout(), &ctx, args),
-            LogType::Trace => {
-                let module_name = get_modeule_name_helper(ctx.clone());
-                format_log(false, true, &ctx, args).map(|msg| {
-                    tracing::info!(module = module_name, "{}", msg);
-                })
-            }
-        })
-        .unwrap()
+    Output::Context.log(&ctx, Level::Info, args)
 }
 
 pub fn log_fatal<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
@@ -92,88 +425,93 @@ pub fn log_fatal<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
 }
 
 pub fn log_error<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => write_log(stderr(), &ctx, args),
-            LogType::Trace => {
-                let module_name = get_modeule_name_helper(ctx.clone());
-                format_log(false, true, &ctx, args).map(|msg| {
-                    tracing::error!(module = module_name, "{}", msg);
-                })
-            }
-        })
-        .unwrap()
+    Output::Context.log(&ctx, Level::Error, args)
 }
 
 fn log_warn<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => write_log(stderr(), &ctx, args),
-            LogType::Trace => {
-                let module_name = get_modeule_name_helper(ctx.clone());
-                format_log(false, true, &ctx, args).map(|msg| {
-                    tracing::warn!(module = module_name, "{}", msg);
-                })
-            }
-        })
-        .unwrap()
+    Output::Context.log(&ctx, Level::Warn, args)
 }
 
 fn log_debug<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => write_log(stderr(), &ctx, args),
-            LogType::Trace => {
-                let module_name = get_modeule_name_helper(ctx.clone());
-                format_log(false, true, &ctx, args).map(|msg| {
-                    tracing::debug!(module = module_name, "{}", msg);
-                })
-            }
-        })
-        .unwrap()
+    Output::Context.log(&ctx, Level::Debug, args)
 }
 
 fn log_trace<'js>(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => write_log(stderr(), &ctx, args),
-            LogType::Trace => {
-                let module_name = get_modeule_name_helper(ctx.clone());
-                format_log(false, true, &ctx, args).map(|msg| {
-                    tracing::trace!(module = module_name, "{}", msg);
-                })
-            }
-        })
-        .unwrap()
+    Output::Context.log(&ctx, Level::Trace, args)
 }
 
-fn log_assert<'js>(ctx: Ctx<'js>, expression: bool, args: Rest<Value<'js>>) -> Result<()> {
+fn log_assert<'js>(
+    out: Output<'_, 'js>,
+    ctx: Ctx<'js>,
+    expression: bool,
+    args: Rest<Value<'js>>,
+) -> Result<()> {
     if !expression {
-        log_error(ctx, args)
+        out.log(&ctx, Level::Error, args)
     } else {
         Ok(())
     }
 }
 
-fn clear<'js>(ctx: Ctx<'js>, _args: Rest<Value<'js>>) -> Result<()> {
-    ctx.userdata::<LogType>()
-        .map(|log_type| match *log_type {
-            LogType::Stdio => {
-                let _ = stdout().write_all(b"\x1b[1;1H\x1b[0J");
+/// Clears the screen, only when writing to a terminal so piped output stays clean
+fn clear(out: Output<'_, '_>, ctx: Ctx<'_>) -> Result<()> {
+    match out {
+        _ if out.log_type(&ctx) != LogType::Stdio => {
+            // no op
+        }
+        Output::Context => {
+            let stdio = get_stdio(&ctx);
+            if (stdio.is_terminal)(StdStream::Stdout) {
+                let _ = stdio.write(StdStream::Stdout, CLEAR_SCREEN.as_bytes());
             }
-            LogType::Trace => {
-                // no op
+        }
+        Output::Streams(streams, _) => {
+            if is_tty(&streams.stdout)? {
+                let write: Function = streams.stdout.get("write")?;
+                write.call::<_, ()>((This(streams.stdout.clone()), CLEAR_SCREEN))?;
             }
-        })
-        .unwrap();
+        }
+    }
     Ok(())
 }
 
+/// Mirrors a console call to a connected DevTools frontend.
+fn report_to_inspector<'js>(ctx: &Ctx<'js>, level: Level, args: &[Value<'js>]) {
+    #[cfg(feature = "inspector")]
+    crate::inspector::console_api_called(
+        ctx,
+        match level {
+            Level::Info => "log",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+            Level::Warn => "warning",
+            Level::Error => "error",
+        },
+        args,
+    );
+    #[cfg(not(feature = "inspector"))]
+    let _ = (ctx, level, args);
+}
+
 fn format_log<'js>(
     color: bool,
     newline: bool,
     ctx: &Ctx<'js>,
     args: Rest<Value<'js>>,
+) -> Result<String> {
+    let result = format_message(color, newline, ctx, args)?;
+    let depth = ctx
+        .userdata::<ConsoleState>()
+        .map(|state| state.group_depth.get())
+        .unwrap_or_default();
+    Ok(indent_group(depth, result))
+}
+
+fn format_message<'js>(
+    color: bool,
+    newline: bool,
+    ctx: &Ctx<'js>,
+    args: Rest<Value<'js>>,
 ) -> Result<String> {
     let mut result = String::new();
     let mut options = FormatOptions::new(ctx, color, newline)?;
@@ -181,16 +519,342 @@ fn format_log<'js>(
     Ok(result)
 }
 
-pub fn write_log<'js, T>(mut output: T, ctx: &Ctx<'js>, args: Rest<Value<'js>>) -> Result<()>
-where
-    T: Write + IsTerminal,
-{
-    let is_tty = output.is_terminal();
-    let mut log = format_log(is_tty, true, ctx, args)?;
+fn indent_group(depth: usize, message: String) -> String {
+    if depth == 0 {
+        return message;
+    }
+
+    let indentation = GROUP_INDENTATION.repeat(depth);
+    let mut result = String::with_capacity(message.len() + indentation.len());
+    for (i, line) in message.split(NEWLINE).enumerate() {
+        if i != 0 {
+            result.push(NEWLINE);
+        }
+        result.push_str(&indentation);
+        result.push_str(line);
+    }
+    result
+}
+
+fn console_state<'a>(ctx: &'a Ctx<'_>) -> UserDataGuard<'a, ConsoleState> {
+    ctx.userdata::<ConsoleState>().unwrap()
+}
+
+fn group<'js>(out: Output<'_, 'js>, ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+    if !args.is_empty() {
+        out.log(&ctx, Level::Info, args)?;
+    }
+    out.with_state(&ctx, |state| {
+        state.group_depth.set(state.group_depth.get() + 1)
+    });
+    Ok(())
+}
+
+fn group_end(out: Output<'_, '_>, ctx: Ctx<'_>) {
+    out.with_state(&ctx, |state| {
+        state
+            .group_depth
+            .set(state.group_depth.get().saturating_sub(1))
+    });
+}
+
+fn count<'js>(out: Output<'_, 'js>, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let count = out.with_state(&ctx, |state| {
+        let mut counts = state.counts.borrow_mut();
+        let count = counts.entry(label.clone()).or_default();
+        *count += 1;
+        *count
+    });
+    let mut buffer = itoa::Buffer::new();
+    out.write(
+        &ctx,
+        Level::Info,
+        [&label, ": ", buffer.format(count)].concat(),
+    )
+}
+
+fn count_reset<'js>(out: Output<'_, 'js>, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let existed = out.with_state(&ctx, |state| {
+        match state.counts.borrow_mut().get_mut(&label) {
+            Some(count) => {
+                *count = 0;
+                true
+            }
+            None => false,
+        }
+    });
+    if !existed {
+        return out.write(
+            &ctx,
+            Level::Warn,
+            ["Count for '", &label, "' does not exist"].concat(),
+        );
+    }
+    Ok(())
+}
+
+fn time<'js>(out: Output<'_, 'js>, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let exists = out.with_state(&ctx, |state| {
+        let mut timers = state.timers.borrow_mut();
+        if timers.contains_key(&label) {
+            true
+        } else {
+            timers.insert(label.clone(), Instant::now());
+            false
+        }
+    });
+    if exists {
+        return out.write(
+            &ctx,
+            Level::Warn,
+            ["Label '", &label, "' already exists for console.time()"].concat(),
+        );
+    }
+    Ok(())
+}
+
+fn time_log<'js>(
+    out: Output<'_, 'js>,
+    ctx: Ctx<'js>,
+    label: Opt<String>,
+    args: Rest<Value<'js>>,
+) -> Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let started = out.with_state(&ctx, |state| state.timers.borrow().get(&label).copied());
+    let Some(started) = started else {
+        return out.write(
+            &ctx,
+            Level::Warn,
+            ["No such label '", &label, "' for console.timeLog()"].concat(),
+        );
+    };
+
+    let mut message = [&label, ": ", &format_duration(started.elapsed())].concat();
+    if !args.is_empty() {
+        let color = out.color(&ctx, Level::Info)?;
+        message.push(' ');
+        message.push_str(&format_message(color, true, &ctx, args)?);
+    }
+    out.write(&ctx, Level::Info, message)
+}
+
+fn time_end<'js>(out: Output<'_, 'js>, ctx: Ctx<'js>, label: Opt<String>) -> Result<()> {
+    let label = label.0.unwrap_or_else(|| DEFAULT_LABEL.into());
+    let started = out.with_state(&ctx, |state| state.timers.borrow_mut().remove(&label));
+    let Some(started) = started else {
+        return out.write(
+            &ctx,
+            Level::Warn,
+            ["No such label '", &label, "' for console.timeEnd()"].concat(),
+        );
+    };
+    let message = [&label, ": ", &format_duration(started.elapsed())].concat();
+    out.write(&ctx, Level::Info, message)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_secs_f64() * 1000.0;
+    if ms < 1000.0 {
+        format!("{:.3}ms", ms)
+    } else if ms < 60_000.0 {
+        format!("{:.3}s", ms / 1000.0)
+    } else {
+        format!("{:.3}min", ms / 60_000.0)
+    }
+}
+
+fn dir<'js>(
+    out: Output<'_, 'js>,
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    options: Opt<Object<'js>>,
+) -> Result<()> {
+    let mut color = out.color(&ctx, Level::Info)?;
+    let mut max_depth = None;
+
+    if let Some(options) = options.0 {
+        let depth: Value = options.get("depth")?;
+        if depth.is_null() {
+            max_depth = Some(usize::MAX);
+        } else if let Some(depth) = depth.as_number() {
+            // `depth` counts the levels below the inspected value
+            max_depth = Some(if depth.is_finite() {
+                (depth.max(0.0) as usize).saturating_add(1)
+            } else {
+                usize::MAX
+            });
+        }
+        if let Some(colors) = options.get::<_, Option<bool>>("colors")? {
+            color = colors && out.log_type(&ctx) == LogType::Stdio;
+        }
+    }
+
+    let mut format_options = FormatOptions::new(&ctx, color, true)?;
+    if let Some(max_depth) = max_depth {
+        format_options = format_options.with_max_depth(max_depth);
+    }
+    let mut result = String::new();
+    build_formatted_string(&mut result, &ctx, Rest(vec![value]), &mut format_options)?;
+    out.write(&ctx, Level::Info, result)
+}
+
+fn table<'js>(
+    out: Output<'_, 'js>,
+    ctx: Ctx<'js>,
+    data: Value<'js>,
+    properties: Opt<Vec<String>>,
+) -> Result<()> {
+    let Some(obj) = data.as_object().filter(|_| !data.is_function()) else {
+        return out.log(&ctx, Level::Info, Rest(vec![data]));
+    };
+
+    let mut entries = Vec::new();
+    let mut index_header = "(index)";
+    {
+        let primordials = BasePrimordials::get(&ctx)?;
+        if obj.is_instance_of(&primordials.constructor_map) {
+            index_header = "(iteration index)";
+            let pairs: Vec<(Value, Value)> =
+                primordials.function_array_from.call((data.clone(),))?;
+            for (index, (key, value)) in pairs.into_iter().enumerate() {
+                entries.push((index.to_string(), Some(key), value));
+            }
+        } else if obj.is_instance_of(&primordials.constructor_set) {
+            index_header = "(iteration index)";
+            let values: Vec<Value> = primordials.function_array_from.call((data.clone(),))?;
+            for (index, value) in values.into_iter().enumerate() {
+                entries.push((index.to_string(), None, value));
+            }
+        } else {
+            for key in obj.keys::<String>() {
+                let key = key?;
+                let value = obj.get(key.as_str())?;
+                entries.push((key, None, value));
+            }
+        }
+    }
+
+    let mut cell_options = FormatOptions::new(&ctx, false, true)?.with_max_depth(0);
+    let mut format_cell = |value: Value<'js>| -> Result<String> {
+        if let Some(string) = value.as_string() {
+            return Ok(["'", &string.to_string()?, "'"].concat());
+        }
+        let mut result = String::new();
+        build_formatted_string(&mut result, &ctx, Rest(vec![value]), &mut cell_options)?;
+        Ok(result)
+    };
+
+    let has_keys = entries.iter().any(|(_, key, _)| key.is_some());
+    let mut columns: Vec<String> = properties.0.clone().unwrap_or_default();
+    let mut rows = Vec::with_capacity(entries.len());
+    let mut has_values = false;
+    for (index, key, value) in entries {
+        let key = key.map(&mut format_cell).transpose()?;
+        let mut cells = HashMap::new();
+        let mut primitive = None;
+        match value.as_object().filter(|_| !value.is_function()) {
+            Some(row) => {
+                for column in row.keys::<String>() {
+                    let column = column?;
+                    if let Some(properties) = &properties.0 {
+                        if !properties.contains(&column) {
+                            continue;
+                        }
+                    } else if !columns.contains(&column) {
+                        columns.push(column.clone());
+                    }
+                    let cell = format_cell(row.get(column.as_str())?)?;
+                    cells.insert(column, cell);
+                }
+            }
+            None => {
+                has_values = true;
+                primitive = Some(format_cell(value.clone())?);
+            }
+        }
+        rows.push((index, key, cells, primitive));
+    }
+
+    let mut header = vec![index_header.to_string()];
+    if has_keys {
+        header.push("Key".into());
+    }
+    header.extend(columns.iter().cloned());
+    if has_values {
+        header.push("Values".into());
+    }
+
+    let rows = rows
+        .into_iter()
+        .map(|(index, key, mut cells, primitive)| {
+            let mut row = vec![index];
+            if has_keys {
+                row.push(key.unwrap_or_default());
+            }
+            row.extend(
+                columns
+                    .iter()
+                    .map(|column| cells.remove(column).unwrap_or_default()),
+            );
+            if has_values {
+                row.push(primitive.unwrap_or_default());
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+
+    out.write(&ctx, Level::Info, render_table(&header, &rows))
+}
+
+fn render_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let border = |left: char, middle: char, right: char| {
+        let mut line = String::new();
+        line.push(left);
+        for (i, width) in widths.iter().enumerate() {
+            if i != 0 {
+                line.push(middle);
+            }
+            line.push_str(&"─".repeat(width + 2));
+        }
+        line.push(right);
+        line
+    };
+    let render_row = |cells: &[String]| {
+        let mut line = String::from("│");
+        for (cell, width) in cells.iter().zip(&widths) {
+            line.push(' ');
+            line.push_str(cell);
+            line.push_str(&" ".repeat(width - cell.chars().count() + 1));
+            line.push('│');
+        }
+        line
+    };
+
+    let mut lines = vec![border('┌', '┬', '┐'), render_row(header)];
+    lines.push(border('├', '┼', '┤'));
+    lines.extend(rows.iter().map(|row| render_row(row)));
+    lines.push(border('└', '┴', '┘'));
+    lines.join("\n")
+}
+
+/// Formats `args` and writes them to `stream` of the context's stdio vtable
+pub fn write_log<'js>(stream: StdStream, ctx: &Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+    let stdio = get_stdio(ctx);
+    let mut log = format_log(stdio.has_colors(stream), true, ctx, args)?;
     log.push(NEWLINE);
 
     // we don't care if output is interrupted
-    let _ = output.write_all(log.as_bytes());
+    let _ = stdio.write(stream, log.as_bytes());
     Ok(())
 }
 
@@ -221,20 +885,79 @@ impl From<ConsoleModule> for ModuleInfo<ConsoleModule> {
     }
 }
 
-pub fn init(ctx: &Ctx<'_>, log_type: LogType) -> Result<()> {
+pub fn init<'js>(ctx: &Ctx<'js>, log_type: LogType) -> Result<()> {
     ctx.store_userdata(log_type)?;
+    ctx.store_userdata(ConsoleState::default())?;
     let globals = ctx.globals();
 
     let console = Object::new(ctx.clone())?;
 
-    console.set("assert", Func::from(log_assert))?;
-    console.set("clear", Func::from(clear))?;
+    console.set(
+        "assert",
+        Func::from(|ctx: Ctx<'js>, expression: bool, args: Rest<Value<'js>>| {
+            log_assert(Output::Context, ctx, expression, args)
+        }),
+    )?;
+    console.set(
+        "clear",
+        Func::from(|ctx: Ctx<'js>| clear(Output::Context, ctx)),
+    )?;
+    console.set(
+        "count",
+        Func::from(|ctx: Ctx<'js>, label: Opt<String>| count(Output::Context, ctx, label)),
+    )?;
+    console.set(
+        "countReset",
+        Func::from(|ctx: Ctx<'js>, label: Opt<String>| count_reset(Output::Context, ctx, label)),
+    )?;
     console.set("debug", Func::from(log_debug))?;
+    console.set(
+        "dir",
+        Func::from(
+            |ctx: Ctx<'js>, value: Value<'js>, options: Opt<Object<'js>>| {
+                dir(Output::Context, ctx, value, options)
+            },
+        ),
+    )?;
     console.set("error", Func::from(log_error))?;
+    let group_fn = Function::new(ctx.clone(), |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+        group(Output::Context, ctx, args)
+    })?;
+    console.set("group", group_fn.clone())?;
+    console.set("groupCollapsed", group_fn)?;
+    console.set(
+        "groupEnd",
+        Func::from(|ctx: Ctx<'js>| group_end(Output::Context, ctx)),
+    )?;
     console.set("info", Func::from(log))?;
     console.set("log", Func::from(log))?;
+    console.set(
+        "table",
+        Func::from(
+            |ctx: Ctx<'js>, data: Value<'js>, properties: Opt<Vec<String>>| {
+                table(Output::Context, ctx, data, properties)
+            },
+        ),
+    )?;
+    console.set(
+        "time",
+        Func::from(|ctx: Ctx<'js>, label: Opt<String>| time(Output::Context, ctx, label)),
+    )?;
+    console.set(
+        "timeEnd",
+        Func::from(|ctx: Ctx<'js>, label: Opt<String>| time_end(Output::Context, ctx, label)),
+    )?;
+    console.set(
+        "timeLog",
+        Func::from(
+            |ctx: Ctx<'js>, label: Opt<String>, args: Rest<Value<'js>>| {
+                time_log(Output::Context, ctx, label, args)
+            },
+        ),
+    )?;
     console.set("trace", Func::from(log_trace))?;
     console.set("warn", Func::from(log_warn))?;
+    Class::<Console>::define(&console)?;
 
     globals.set("console", console)?;
 
@@ -277,10 +1000,97 @@ mod tests {
         console.trace("Trace message", 3.14);
         console.assert(true, "This should not log");
         console.assert(false, "This should log an error");
+
+        console.group("Group");
+        console.count();
+        console.count();
+        console.countReset();
+        console.groupEnd();
+        console.time("timer");
+        console.timeLog("timer", { step: 1 });
+        console.timeEnd("timer");
+        console.table([{ a: 1, b: "x" }, { a: 2, c: [1] }, 3]);
+        console.dir({ a: { b: { c: {} } } }, { depth: 0 });
     "#,
             )
         })
         .await
         .unwrap()
     }
+
+    #[tokio::test]
+    async fn test_console_streams() {
+        use super::{init, LogType};
+        use rsquickjs::{AsyncContext, AsyncRuntime};
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        ctx.with(|ctx| -> rsquickjs::Result<()> {
+            crate::utils::primordials::BasePrimordials::init(&ctx)?;
+            init(&ctx, LogType::Stdio)?;
+            let output: String = ctx.eval(
+                r#"
+        const chunks = [];
+        const stream = { isTTY: true, write(chunk) { chunks.push(chunk); } };
+        const logger = new console.Console({ stdout: stream, colorMode: false });
+        logger.log("a", 1);
+        logger.group("g");
+        logger.error("e", 2);
+        logger.groupEnd();
+        logger.count();
+        console.group();
+        logger.count();
+        console.groupEnd();
+        chunks.join("")
+    "#,
+            )?;
+            assert_eq!(output, "a 1\ng\n  e 2\ndefault: 1\ndefault: 2\n");
+            Ok(())
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_line() {
+        use super::{init, json_line, Level, LogType};
+        use rsquickjs::{AsyncContext, AsyncRuntime, Value};
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        ctx.with(|ctx| -> rsquickjs::Result<()> {
+            crate::utils::primordials::BasePrimordials::init(&ctx)?;
+            init(&ctx, LogType::Json)?;
+            let args: Vec<Value> =
+                ctx.eval(r#"const cyclic = {}; cyclic.self = cyclic; ["a\"b", { n: 1 }, cyclic]"#)?;
+            let line = json_line(&ctx, Level::Warn, "message", &args)?;
+            assert!(line.starts_with(r#"{"level":"warn","module":"#));
+            assert!(line.contains(r#","timestamp":"#));
+            // cyclic values fall back to their formatted string
+            assert!(line.contains(r#","message":"message","args":["a\"b",{"n":1},""#));
+            assert!(line.contains("[Circular]") && line.ends_with(r#""]}"#));
+            Ok(())
+        })
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_table() {
+        let header = ["(index)", "a", "Values"].map(String::from);
+        let rows = vec![
+            vec!["0".to_string(), "1".to_string(), String::new()],
+            vec!["1".to_string(), String::new(), "'long'".to_string()],
+        ];
+        assert_eq!(
+            super::render_table(&header, &rows),
+            [
+                "┌─────────┬───┬────────┐",
+                "│ (index) │ a │ Values │",
+                "├─────────┼───┼────────┤",
+                "│ 0       │ 1 │        │",
+                "│ 1       │   │ 'long' │",
+                "└─────────┴───┴────────┘",
+            ]
+            .join("\n")
+        );
+    }
 }
@@ -7,6 +7,7 @@ use std::slice;
 use std::sync::LazyLock;
 
 use crate::buffer::Buffer;
+use crate::permissions::get_random;
 use crate::utils::bytes::{ERROR_MSG_ARRAY_BUFFER_DETACHED, ERROR_MSG_NOT_ARRAY_BUFFER};
 use crate::utils::ctx::CtxExtension;
 use crate::utils::encoding::{bytes_to_b64_string, bytes_to_hex_string};
@@ -16,8 +17,7 @@ use crate::utils::{
     module::{export_default, ModuleInfo},
     result::ResultExt,
 };
-use rand::Rng;
-use ring::rand::{SecureRandom, SystemRandom};
+use ring::rand::SystemRandom;
 use rsquickjs::prelude::Async;
 use rsquickjs::{
     atom::PredefinedAtom,
@@ -31,6 +31,7 @@ use subtle::{
     subtle_export_key, subtle_generate_key, subtle_import_key, subtle_sign, subtle_unwrap_key,
     subtle_verify, subtle_wrap_key, CryptoKey, SubtleCrypto,
 };
+use xmas_vsys::RandomVTable;
 
 use self::{
     crc32::{Crc32, Crc32c},
@@ -57,25 +58,39 @@ fn encoded_bytes<'js>(ctx: Ctx<'js>, bytes: &[u8], encoding: &str) -> Result<Val
 }
 
 #[inline]
-pub fn random_byte_array(length: usize) -> Vec<u8> {
+pub fn random_byte_array(random: &RandomVTable, length: usize) -> Vec<u8> {
     let mut vec = vec![0; length];
-    SYSTEM_RANDOM.fill(&mut vec).unwrap();
+    (random.fill)(&mut vec);
     vec
 }
 
 fn get_random_bytes(ctx: Ctx, length: usize) -> Result<Value> {
-    let random_bytes = random_byte_array(length);
+    let random_bytes = random_byte_array(&get_random(&ctx), length);
     Buffer(random_bytes).into_js(&ctx)
 }
 
-fn get_random_int(first: i64, second: Opt<i64>) -> Result<i64> {
-    let mut rng = rand::rng();
-    let random_number = match second.0 {
-        Some(max) => rng.random_range(first..max),
-        None => rng.random_range(0..first),
+fn get_random_int(ctx: Ctx<'_>, first: i64, second: Opt<i64>) -> Result<i64> {
+    let (min, max) = match second.0 {
+        Some(max) => (first, max),
+        None => (0, first),
     };
+    if max <= min {
+        return Err(Exception::throw_range(
+            &ctx,
+            "The \"max\" argument must be greater than \"min\"",
+        ));
+    }
 
-    Ok(random_number)
+    // rejection sampling keeps the distribution uniform
+    let random = get_random(&ctx);
+    let span = max.abs_diff(min);
+    let zone = u64::MAX - u64::MAX % span;
+    loop {
+        let value = random.next_u64();
+        if value < zone {
+            return Ok(min.wrapping_add((value % span) as i64));
+        }
+    }
 }
 
 fn random_fill<'js>(ctx: Ctx<'js>, obj: Object<'js>, args: Rest<Value<'js>>) -> Result<()> {
@@ -129,9 +144,7 @@ fn random_fill_sync<'js>(
 
         let bytes = unsafe { slice::from_raw_parts_mut(raw.ptr.as_ptr(), source_length) };
 
-        SYSTEM_RANDOM
-            .fill(&mut bytes[start + source_offset..end - source_offset])
-            .unwrap();
+        (get_random(&ctx).fill)(&mut bytes[start + source_offset..end - source_offset]);
     }
 
     Ok(obj)
@@ -165,14 +178,15 @@ fn get_random_values<'js>(ctx: Ctx<'js>, obj: Object<'js>) -> Result<Object<'js>
             std::slice::from_raw_parts_mut(raw.ptr.as_ptr().add(source_offset), source_length)
         };
 
-        SYSTEM_RANDOM.fill(bytes).unwrap()
+        (get_random(&ctx).fill)(bytes)
     }
 
     Ok(obj)
 }
 
-fn uuidv4() -> String {
-    let uuid = rand::random::<u128>() & 0xFFFFFFFFFFFF4FFFBFFFFFFFFFFFFFFF | 0x40008000000000000000;
+fn uuidv4(ctx: Ctx<'_>) -> String {
+    let uuid =
+        get_random(&ctx).next_u128() & 0xFFFFFFFFFFFF4FFFBFFFFFFFFFFFFFFF | 0x40008000000000000000;
 
     static HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
     let bytes = uuid.to_be_bytes();
@@ -262,6 +276,11 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     globals.set("crypto", crypto)?;
 
+    // `Math.random()` draws from the same vtable as `crypto`
+    let math: Object = globals.get("Math")?;
+    let random = get_random(ctx);
+    math.set("random", Func::from(move || random.next_f64()))?;
+
     Ok(())
 }
 
@@ -339,3 +358,43 @@ impl From<CryptoModule> for ModuleInfo<CryptoModule> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use xmas_vsys::Vsys;
+
+    use crate::utils::test::test_async_with;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seeded_random() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                let seeded = || Arc::new(Vsys::builder().random(RandomVTable::seeded(7)).build());
+                crate::permissions::init(ctx.clone(), seeded()).unwrap();
+                init(&ctx).unwrap();
+
+                let script = r#"[
+                    Math.random(),
+                    crypto.randomUUID(),
+                    crypto.randomInt(10, 20),
+                    Array.from(crypto.getRandomValues(new Uint8Array(4))).join(),
+                ].join('|')"#;
+                let first: String = ctx.eval(script).unwrap();
+                crate::permissions::init(ctx.clone(), seeded()).unwrap();
+                init(&ctx).unwrap();
+                let again: String = ctx.eval(script).unwrap();
+                assert_eq!(first, again);
+
+                let parts: Vec<&str> = first.split('|').collect();
+                assert!((0.0..1.0).contains(&parts[0].parse::<f64>().unwrap()));
+                assert_eq!(&parts[1][14..15], "4");
+                assert!((10..20).contains(&parts[2].parse::<i64>().unwrap()));
+            })
+        })
+        .await;
+    }
+}
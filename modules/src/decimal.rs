@@ -0,0 +1,297 @@
+//! `xmas:decimal`, exact decimal arithmetic for when the rounding of `number` is not
+//! acceptable, such as with money.
+//!
+//! A `Decimal` holds up to 28 significant digits, backed by `rust_decimal`. The method
+//! names follow the TC39 decimal proposal. Operands may be decimals, strings, numbers or
+//! bigints, numbers convert through their shortest representation so `0.1` is exactly
+//! one tenth.
+use std::cmp::Ordering;
+
+use rsquickjs::{
+    atom::PredefinedAtom,
+    function::Opt,
+    module::{Declarations, Exports, ModuleDef},
+    Class, Coerced, Ctx, Exception, Result, Value,
+};
+use rust_decimal::{Decimal as RustDecimal, RoundingStrategy};
+
+use crate::utils::{
+    module::{export_default, ModuleInfo},
+    result::ResultExt,
+};
+
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct Decimal {
+    #[qjs(skip_trace)]
+    value: RustDecimal,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Decimal {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        Ok(Self {
+            value: to_decimal(&ctx, &value)?,
+        })
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(Decimal)
+    }
+
+    pub fn add(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = to_decimal(&ctx, &other)?;
+        checked(&ctx, self.value.checked_add(other))
+    }
+
+    pub fn subtract(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = to_decimal(&ctx, &other)?;
+        checked(&ctx, self.value.checked_sub(other))
+    }
+
+    pub fn multiply(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = to_decimal(&ctx, &other)?;
+        checked(&ctx, self.value.checked_mul(other))
+    }
+
+    pub fn divide(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = to_decimal(&ctx, &other)?;
+        if other.is_zero() {
+            return Err(Exception::throw_range(&ctx, "Division by zero"));
+        }
+        checked(&ctx, self.value.checked_div(other))
+    }
+
+    pub fn remainder(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = to_decimal(&ctx, &other)?;
+        if other.is_zero() {
+            return Err(Exception::throw_range(&ctx, "Division by zero"));
+        }
+        checked(&ctx, self.value.checked_rem(other))
+    }
+
+    pub fn negate(&self) -> Self {
+        Self { value: -self.value }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            value: self.value.abs(),
+        }
+    }
+
+    /// Rounds to `decimal_places` (0 by default), ties to even unless `rounding_mode`
+    /// says otherwise.
+    pub fn round(
+        &self,
+        ctx: Ctx<'js>,
+        decimal_places: Opt<u32>,
+        rounding_mode: Opt<String>,
+    ) -> Result<Self> {
+        let strategy = match rounding_mode.as_deref() {
+            None | Some("halfEven") => RoundingStrategy::MidpointNearestEven,
+            Some("halfExpand") => RoundingStrategy::MidpointAwayFromZero,
+            Some("halfTrunc") => RoundingStrategy::MidpointTowardZero,
+            Some("ceil") => RoundingStrategy::ToPositiveInfinity,
+            Some("floor") => RoundingStrategy::ToNegativeInfinity,
+            Some("expand") => RoundingStrategy::AwayFromZero,
+            Some("trunc") => RoundingStrategy::ToZero,
+            Some(mode) => {
+                return Err(Exception::throw_range(
+                    &ctx,
+                    &["Invalid rounding mode: ", mode].concat(),
+                ))
+            }
+        };
+        Ok(Self {
+            value: self
+                .value
+                .round_dp_with_strategy(decimal_places.0.unwrap_or_default(), strategy),
+        })
+    }
+
+    pub fn compare(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<i32> {
+        let other = to_decimal(&ctx, &other)?;
+        Ok(match self.value.cmp(&other) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    pub fn equals(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<bool> {
+        Ok(self.value == to_decimal(&ctx, &other)?)
+    }
+
+    pub fn less_than(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<bool> {
+        Ok(self.value < to_decimal(&ctx, &other)?)
+    }
+
+    /// Formats with exactly `digits` decimal places, ties away from zero like
+    /// `Number.prototype.toFixed`.
+    pub fn to_fixed(&self, digits: Opt<u32>) -> String {
+        let digits = digits.0.unwrap_or_default();
+        let value = self
+            .value
+            .round_dp_with_strategy(digits, RoundingStrategy::MidpointAwayFromZero);
+        format!("{:.*}", digits as usize, value)
+    }
+
+    pub fn to_string(&self) -> String {
+        self.value.to_string()
+    }
+
+    #[qjs(rename = PredefinedAtom::ToJSON)]
+    pub fn to_json(&self) -> String {
+        self.value.to_string()
+    }
+
+    /// The nearest `number`, which may lose precision.
+    pub fn to_number(&self) -> f64 {
+        self.value.to_string().parse().unwrap_or_default()
+    }
+
+    /// Throws, so a decimal is never silently mixed into `number` arithmetic.
+    pub fn value_of(&self, ctx: Ctx<'js>) -> Result<()> {
+        Err(Exception::throw_type(
+            &ctx,
+            "Decimal can't be converted to a number, use toNumber() or toString()",
+        ))
+    }
+}
+
+fn checked(ctx: &Ctx<'_>, value: Option<RustDecimal>) -> Result<Decimal> {
+    let value = value.or_throw_range(ctx, "Decimal overflow")?;
+    Ok(Decimal { value })
+}
+
+fn to_decimal<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<RustDecimal> {
+    if let Some(int) = value.as_int() {
+        return Ok(int.into());
+    }
+    if let Some(number) = value.as_float() {
+        if !number.is_finite() {
+            return Err(Exception::throw_range(
+                ctx,
+                "Decimal can't represent NaN or Infinity",
+            ));
+        }
+        return parse(ctx, &number.to_string());
+    }
+    if value.is_string() || value.is_big_int() {
+        let Coerced(value) = value.get::<Coerced<String>>()?;
+        return parse(ctx, &value);
+    }
+    if let Ok(decimal) = Class::<Decimal>::from_value(value) {
+        return Ok(decimal.borrow().value);
+    }
+    Err(Exception::throw_type(
+        ctx,
+        "Expected a Decimal, string, number or bigint",
+    ))
+}
+
+fn parse(ctx: &Ctx<'_>, value: &str) -> Result<RustDecimal> {
+    let value = value.trim();
+    if value.contains(['e', 'E']) {
+        RustDecimal::from_scientific(value)
+    } else {
+        RustDecimal::from_str_exact(value)
+    }
+    .or_throw_range(ctx, "")
+}
+
+pub struct DecimalModule;
+
+impl ModuleDef for DecimalModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare(stringify!(Decimal))?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            Class::<Decimal>::define(default)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<DecimalModule> for ModuleInfo<DecimalModule> {
+    fn from(val: DecimalModule) -> Self {
+        ModuleInfo {
+            name: "xmas:decimal",
+            module: val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_decimal() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                ModuleEvaluator::eval_rust::<DecimalModule>(ctx.clone(), "xmas:decimal")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { Decimal } from 'xmas:decimal';
+                        export async function test() {
+                            const sum = new Decimal(0.1).add(0.2);
+                            const errors = [];
+                            for (const f of [
+                                () => new Decimal(1).divide(0),
+                                () => new Decimal(NaN),
+                                () => new Decimal('abc'),
+                                () => new Decimal(1) + 1,
+                                () => new Decimal(1).add({}),
+                            ]) {
+                                try {
+                                    f();
+                                } catch (e) {
+                                    errors.push(e.constructor.name);
+                                }
+                            }
+                            return [
+                                sum.toString(),
+                                sum.equals('0.3'),
+                                new Decimal('19.99').multiply(3).toString(),
+                                new Decimal(10).divide(4).toString(),
+                                new Decimal('7').remainder(3).toString(),
+                                new Decimal('2.5').round().toString(),
+                                new Decimal('2.5').round(0, 'halfExpand').toString(),
+                                new Decimal('-1.005').toFixed(2),
+                                new Decimal(12345678901234567890n).subtract(1).toString(),
+                                new Decimal('1.5e3').negate().abs().compare(1500),
+                                new Decimal('1.25').lessThan(new Decimal('1.3')),
+                                new Decimal('0.5').toNumber(),
+                                JSON.stringify({ total: new Decimal('4.20') }),
+                                errors.join(','),
+                            ].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "0.3|true|59.97|2.5|1|2|3|-1.01|12345678901234567889|0|true|0.5|{\"total\":\"4.20\"}|RangeError,RangeError,RangeError,TypeError,TypeError"
+                );
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,717 @@
+use std::cell::RefCell;
+
+use crate::utils::{
+    error::ErrorExtensions,
+    module::{export_default, ModuleInfo},
+    object::ObjectExt,
+    result::ResultExt,
+};
+use rsquickjs::{
+    class::{Trace, Tracer},
+    module::{Declarations, Exports, ModuleDef},
+    prelude::{Func, Opt, Rest, This},
+    CatchResultExt, Class, Ctx, Exception, Function, JsLifetime, Object, Promise, Result, Symbol,
+    Undefined, Value,
+};
+use tracing::error;
+
+#[derive(Clone, PartialEq)]
+enum ChannelName<'js> {
+    String(String),
+    Symbol(Symbol<'js>),
+}
+
+impl<'js> ChannelName<'js> {
+    fn from_value(ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        if let Some(name) = value.as_string() {
+            return Ok(Self::String(name.to_string()?));
+        }
+        if let Some(sym) = value.into_symbol() {
+            return Ok(Self::Symbol(sym));
+        }
+        Err(Exception::throw_type(
+            ctx,
+            "The \"name\" argument must be of type string or symbol",
+        ))
+    }
+
+    fn into_value(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        Ok(match self {
+            Self::String(name) => rsquickjs::String::from_str(ctx.clone(), &name)?.into_value(),
+            Self::Symbol(sym) => sym.into_value(),
+        })
+    }
+}
+
+impl<'js> Trace<'js> for ChannelName<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        if let Self::Symbol(sym) = self {
+            tracer.mark(sym);
+        }
+    }
+}
+
+#[derive(Default)]
+struct ChannelRegistry<'js> {
+    channels: Vec<(ChannelName<'js>, Class<'js, Channel<'js>>)>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for ChannelRegistry<'js> {
+    type Changed<'to> = ChannelRegistry<'to>;
+}
+
+#[rsquickjs::class]
+#[derive(Clone)]
+pub struct Channel<'js> {
+    name: ChannelName<'js>,
+    subscribers: Vec<Function<'js>>,
+    stores: Vec<(Object<'js>, Option<Function<'js>>)>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for Channel<'js> {
+    type Changed<'to> = Channel<'to>;
+}
+
+impl<'js> Trace<'js> for Channel<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.name.trace(tracer);
+        self.subscribers.trace(tracer);
+        self.stores.trace(tracer);
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Channel<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, name: Value<'js>) -> Result<Self> {
+        Ok(Self {
+            name: ChannelName::from_value(&ctx, name)?,
+            subscribers: Vec::new(),
+            stores: Vec::new(),
+        })
+    }
+
+    #[qjs(get)]
+    pub fn name(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.name.clone().into_value(&ctx)
+    }
+
+    #[qjs(get)]
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty() || !self.stores.is_empty()
+    }
+
+    pub fn subscribe(&mut self, subscriber: Function<'js>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn unsubscribe(&mut self, subscriber: Function<'js>) -> bool {
+        match self.subscribers.iter().position(|s| s == &subscriber) {
+            Some(index) => {
+                self.subscribers.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn bind_store(&mut self, store: Object<'js>, transform: Opt<Function<'js>>) {
+        self.stores.retain(|(s, _)| s != &store);
+        self.stores.push((store, transform.0));
+    }
+
+    pub fn unbind_store(&mut self, store: Object<'js>) -> bool {
+        let len = self.stores.len();
+        self.stores.retain(|(s, _)| s != &store);
+        len != self.stores.len()
+    }
+
+    pub fn publish(this: This<Class<'js, Self>>, ctx: Ctx<'js>, message: Value<'js>) -> Result<()> {
+        // Clone first so subscribers may (un)subscribe while being notified
+        let (subscribers, name) = {
+            let borrow = this.borrow();
+            (borrow.subscribers.clone(), borrow.name.clone())
+        };
+        if subscribers.is_empty() {
+            return Ok(());
+        }
+        let name = name.into_value(&ctx)?;
+        for subscriber in subscribers {
+            // A failing subscriber must not break the publisher nor the other subscribers
+            if let Err(err) = subscriber
+                .call::<_, ()>((message.clone(), name.clone()))
+                .catch(&ctx)
+            {
+                error!("Error in diagnostics_channel subscriber: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run_stores(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        data: Value<'js>,
+        func: Function<'js>,
+        this_arg: Opt<Value<'js>>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let this_arg = this_arg
+            .0
+            .unwrap_or_else(|| Undefined.into_value(ctx.clone()));
+        let stores = this.borrow().stores.clone();
+
+        let channel = this.0.clone();
+        let publish_data = data.clone();
+        let mut run = Function::new(ctx.clone(), move |ctx: Ctx<'js>| -> Result<Value<'js>> {
+            Self::publish(This(channel.clone()), ctx, publish_data.clone())?;
+            func.call((This(this_arg.clone()), Rest(args.0.clone())))
+        })?;
+
+        for (store, transform) in stores {
+            let next = run;
+            let data = data.clone();
+            run = Function::new(ctx.clone(), move |ctx: Ctx<'js>| -> Result<Value<'js>> {
+                let context = match &transform {
+                    Some(transform) => {
+                        match transform.call::<_, Value>((data.clone(),)).catch(&ctx) {
+                            Ok(context) => context,
+                            Err(err) => {
+                                error!("Error in diagnostics_channel store transform: {}", err);
+                                return next.call(());
+                            }
+                        }
+                    }
+                    None => data.clone(),
+                };
+                let run: Function = store.get("run")?;
+                run.call((This(store.clone()), context, next.clone()))
+            })?;
+        }
+
+        run.call(())
+    }
+}
+
+fn get_or_create_channel<'js>(
+    ctx: &Ctx<'js>,
+    name: Value<'js>,
+) -> Result<Class<'js, Channel<'js>>> {
+    let name = ChannelName::from_value(ctx, name)?;
+    let binding = ctx.userdata::<RefCell<ChannelRegistry>>().or_throw(ctx)?;
+    let mut registry = binding.borrow_mut();
+
+    if let Some((_, channel)) = registry.channels.iter().find(|(n, _)| n == &name) {
+        return Ok(channel.clone());
+    }
+
+    let channel = Class::instance(
+        ctx.clone(),
+        Channel {
+            name: name.clone(),
+            subscribers: Vec::new(),
+            stores: Vec::new(),
+        },
+    )?;
+    registry.channels.push((name, channel.clone()));
+    Ok(channel)
+}
+
+fn channel<'js>(ctx: Ctx<'js>, name: Value<'js>) -> Result<Class<'js, Channel<'js>>> {
+    get_or_create_channel(&ctx, name)
+}
+
+fn has_subscribers<'js>(ctx: Ctx<'js>, name: Value<'js>) -> Result<bool> {
+    Ok(get_or_create_channel(&ctx, name)?
+        .borrow()
+        .has_subscribers())
+}
+
+fn subscribe<'js>(ctx: Ctx<'js>, name: Value<'js>, subscriber: Function<'js>) -> Result<()> {
+    get_or_create_channel(&ctx, name)?
+        .borrow_mut()
+        .subscribe(subscriber);
+    Ok(())
+}
+
+fn unsubscribe<'js>(ctx: Ctx<'js>, name: Value<'js>, subscriber: Function<'js>) -> Result<bool> {
+    Ok(get_or_create_channel(&ctx, name)?
+        .borrow_mut()
+        .unsubscribe(subscriber))
+}
+
+#[rsquickjs::class]
+#[derive(Clone, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+pub struct TracingChannel<'js> {
+    start: Class<'js, Channel<'js>>,
+    end: Class<'js, Channel<'js>>,
+    async_start: Class<'js, Channel<'js>>,
+    async_end: Class<'js, Channel<'js>>,
+    error: Class<'js, Channel<'js>>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> TracingChannel<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, name_or_channels: Value<'js>) -> Result<Self> {
+        if let Some(prefix) = name_or_channels.as_string() {
+            let prefix = prefix.to_string()?;
+            let channel = |event: &str| {
+                let name = ["tracing:", &prefix, ":", event].concat();
+                let name = rsquickjs::String::from_str(ctx.clone(), &name)?.into_value();
+                get_or_create_channel(&ctx, name)
+            };
+            return Ok(Self {
+                start: channel("start")?,
+                end: channel("end")?,
+                async_start: channel("asyncStart")?,
+                async_end: channel("asyncEnd")?,
+                error: channel("error")?,
+            });
+        }
+
+        let channels = name_or_channels.into_object_or_throw(&ctx, "nameOrChannels")?;
+        let get = |event: &'static str| -> Result<Class<'js, Channel<'js>>> {
+            let channel: Value = channels.get_required(event, "nameOrChannels")?;
+            Class::<Channel>::from_value(&channel).map_err(|_| {
+                Exception::throw_type(
+                    &ctx,
+                    &[
+                        "The \"nameOrChannels.",
+                        event,
+                        "\" property must be a Channel",
+                    ]
+                    .concat(),
+                )
+            })
+        };
+        Ok(Self {
+            start: get("start")?,
+            end: get("end")?,
+            async_start: get("asyncStart")?,
+            async_end: get("asyncEnd")?,
+            error: get("error")?,
+        })
+    }
+
+    #[qjs(get)]
+    pub fn start(&self) -> Class<'js, Channel<'js>> {
+        self.start.clone()
+    }
+
+    #[qjs(get)]
+    pub fn end(&self) -> Class<'js, Channel<'js>> {
+        self.end.clone()
+    }
+
+    #[qjs(get)]
+    pub fn async_start(&self) -> Class<'js, Channel<'js>> {
+        self.async_start.clone()
+    }
+
+    #[qjs(get)]
+    pub fn async_end(&self) -> Class<'js, Channel<'js>> {
+        self.async_end.clone()
+    }
+
+    #[qjs(get)]
+    pub fn error(&self) -> Class<'js, Channel<'js>> {
+        self.error.clone()
+    }
+
+    #[qjs(get)]
+    pub fn has_subscribers(&self) -> bool {
+        self.channels()
+            .iter()
+            .any(|(_, channel)| channel.borrow().has_subscribers())
+    }
+
+    pub fn subscribe(&self, handlers: Object<'js>) -> Result<()> {
+        for (event, channel) in self.channels() {
+            if let Some(handler) = handlers.get_optional::<_, Function>(event)? {
+                channel.borrow_mut().subscribe(handler);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, handlers: Object<'js>) -> Result<bool> {
+        let mut done = true;
+        for (event, channel) in self.channels() {
+            if let Some(handler) = handlers.get_optional::<_, Function>(event)? {
+                done &= channel.borrow_mut().unsubscribe(handler);
+            }
+        }
+        Ok(done)
+    }
+
+    pub fn trace_sync(
+        &self,
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        context: Opt<Object<'js>>,
+        this_arg: Opt<Value<'js>>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let this_arg = this_arg
+            .0
+            .unwrap_or_else(|| Undefined.into_value(ctx.clone()));
+        if !self.has_subscribers() {
+            return func.call((This(this_arg), args));
+        }
+        let context = context_or_default(&ctx, context)?;
+        let (end, error) = (self.end.clone(), self.error.clone());
+        let traced_context = context.clone();
+
+        let traced = Function::new(ctx.clone(), move |ctx: Ctx<'js>| -> Result<Value<'js>> {
+            let result = func
+                .call::<_, Value>((This(this_arg.clone()), Rest(args.0.clone())))
+                .catch(&ctx);
+            let result = match result {
+                Ok(result) => {
+                    traced_context.set("result", result.clone())?;
+                    Ok(result)
+                }
+                Err(err) => {
+                    let err = err.into_value(&ctx)?;
+                    traced_context.set("error", err.clone())?;
+                    Channel::publish(
+                        This(error.clone()),
+                        ctx.clone(),
+                        traced_context.clone().into_value(),
+                    )?;
+                    Err(ctx.throw(err))
+                }
+            };
+            Channel::publish(This(end.clone()), ctx, traced_context.clone().into_value())?;
+            result
+        })?;
+
+        Channel::run_stores(
+            This(self.start.clone()),
+            ctx.clone(),
+            context.into_value(),
+            traced,
+            Opt(None),
+            Rest(Vec::new()),
+        )
+    }
+
+    pub fn trace_promise(
+        &self,
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        context: Opt<Object<'js>>,
+        this_arg: Opt<Value<'js>>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let this_arg = this_arg
+            .0
+            .unwrap_or_else(|| Undefined.into_value(ctx.clone()));
+        if !self.has_subscribers() {
+            return func.call((This(this_arg), args));
+        }
+        let context = context_or_default(&ctx, context)?;
+        let this = self.clone();
+        let traced_context = context.clone();
+
+        let traced = Function::new(ctx.clone(), move |ctx: Ctx<'js>| -> Result<Value<'js>> {
+            let result = func
+                .call::<_, Value>((This(this_arg.clone()), Rest(args.0.clone())))
+                .catch(&ctx);
+            let result = match result {
+                Ok(result) => this.settle_promise(&ctx, &traced_context, result),
+                Err(err) => {
+                    let err = err.into_value(&ctx)?;
+                    traced_context.set("error", err.clone())?;
+                    Channel::publish(
+                        This(this.error.clone()),
+                        ctx.clone(),
+                        traced_context.clone().into_value(),
+                    )?;
+                    Err(ctx.throw(err))
+                }
+            };
+            Channel::publish(
+                This(this.end.clone()),
+                ctx,
+                traced_context.clone().into_value(),
+            )?;
+            result
+        })?;
+
+        Channel::run_stores(
+            This(self.start.clone()),
+            ctx.clone(),
+            context.into_value(),
+            traced,
+            Opt(None),
+            Rest(Vec::new()),
+        )
+    }
+
+    pub fn trace_callback(
+        &self,
+        ctx: Ctx<'js>,
+        func: Function<'js>,
+        position: Opt<i32>,
+        context: Opt<Object<'js>>,
+        this_arg: Opt<Value<'js>>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let this_arg = this_arg
+            .0
+            .unwrap_or_else(|| Undefined.into_value(ctx.clone()));
+        if !self.has_subscribers() {
+            return func.call((This(this_arg), args));
+        }
+        let context = context_or_default(&ctx, context)?;
+        let mut args = args.0;
+
+        let position = position.0.unwrap_or(-1);
+        let index = if position < 0 {
+            args.len() as i32 + position
+        } else {
+            position
+        };
+        let callback = usize::try_from(index)
+            .ok()
+            .and_then(|index| args.get(index).map(|cb| (index, cb.clone())))
+            .and_then(|(index, cb)| cb.into_function().map(|cb| (index, cb)))
+            .ok_or_else(|| {
+                Exception::throw_type(&ctx, "The \"callback\" argument must be of type function")
+            })?;
+        let (index, callback) = callback;
+
+        let this = self.clone();
+        let callback_context = context.clone();
+        let wrapped = Function::new(
+            ctx.clone(),
+            move |ctx: Ctx<'js>,
+                  cb_this: This<Value<'js>>,
+                  cb_args: Rest<Value<'js>>|
+                  -> Result<Value<'js>> {
+                if let Some(err) = cb_args
+                    .first()
+                    .filter(|err| !err.is_null() && !err.is_undefined())
+                {
+                    callback_context.set("error", err.clone())?;
+                    Channel::publish(
+                        This(this.error.clone()),
+                        ctx.clone(),
+                        callback_context.clone().into_value(),
+                    )?;
+                } else if let Some(result) = cb_args.get(1) {
+                    callback_context.set("result", result.clone())?;
+                }
+                Channel::publish(
+                    This(this.async_start.clone()),
+                    ctx.clone(),
+                    callback_context.clone().into_value(),
+                )?;
+                let result = callback.call((cb_this, Rest(cb_args.0.clone())));
+                Channel::publish(
+                    This(this.async_end.clone()),
+                    ctx,
+                    callback_context.clone().into_value(),
+                )?;
+                result
+            },
+        )?;
+        args[index] = wrapped.into_value();
+
+        self.trace_sync(
+            ctx,
+            func,
+            Opt(Some(context)),
+            Opt(Some(this_arg)),
+            Rest(args),
+        )
+    }
+
+    #[qjs(skip)]
+    fn channels(&self) -> [(&'static str, &Class<'js, Channel<'js>>); 5] {
+        [
+            ("start", &self.start),
+            ("end", &self.end),
+            ("asyncStart", &self.async_start),
+            ("asyncEnd", &self.async_end),
+            ("error", &self.error),
+        ]
+    }
+
+    #[qjs(skip)]
+    fn settle_promise(
+        &self,
+        ctx: &Ctx<'js>,
+        context: &Object<'js>,
+        result: Value<'js>,
+    ) -> Result<Value<'js>> {
+        let promise = match result.into_promise() {
+            Some(promise) => promise,
+            None => {
+                let (promise, resolve, _) = Promise::new(ctx)?;
+                resolve.call::<_, ()>((result,))?;
+                promise
+            }
+        };
+
+        let resolved_context = context.clone();
+        let (async_start, async_end) = (self.async_start.clone(), self.async_end.clone());
+        let on_resolve = Function::new(
+            ctx.clone(),
+            move |ctx: Ctx<'js>, result: Value<'js>| -> Result<Value<'js>> {
+                resolved_context.set("result", result.clone())?;
+                let context = resolved_context.clone().into_value();
+                Channel::publish(This(async_start.clone()), ctx.clone(), context.clone())?;
+                Channel::publish(This(async_end.clone()), ctx, context)?;
+                Ok(result)
+            },
+        )?;
+
+        let rejected_context = context.clone();
+        let (error, async_start, async_end) = (
+            self.error.clone(),
+            self.async_start.clone(),
+            self.async_end.clone(),
+        );
+        let on_reject = Function::new(
+            ctx.clone(),
+            move |ctx: Ctx<'js>, err: Value<'js>| -> Result<Value<'js>> {
+                rejected_context.set("error", err.clone())?;
+                let context = rejected_context.clone().into_value();
+                Channel::publish(This(error.clone()), ctx.clone(), context.clone())?;
+                Channel::publish(This(async_start.clone()), ctx.clone(), context.clone())?;
+                Channel::publish(This(async_end.clone()), ctx.clone(), context)?;
+                Err(ctx.throw(err))
+            },
+        )?;
+
+        promise
+            .then()?
+            .call((This(promise.clone()), on_resolve, on_reject))
+    }
+}
+
+fn context_or_default<'js>(ctx: &Ctx<'js>, context: Opt<Object<'js>>) -> Result<Object<'js>> {
+    match context.0 {
+        Some(context) => Ok(context),
+        None => Object::new(ctx.clone()),
+    }
+}
+
+fn tracing_channel<'js>(
+    ctx: Ctx<'js>,
+    name_or_channels: Value<'js>,
+) -> Result<Class<'js, TracingChannel<'js>>> {
+    let channel = TracingChannel::new(ctx.clone(), name_or_channels)?;
+    Class::instance(ctx, channel)
+}
+
+pub struct DiagnosticsChannelModule;
+
+impl ModuleDef for DiagnosticsChannelModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare(stringify!(Channel))?;
+        declare.declare("channel")?;
+        declare.declare("hasSubscribers")?;
+        declare.declare("subscribe")?;
+        declare.declare("unsubscribe")?;
+        declare.declare("tracingChannel")?;
+        declare.declare("default")?;
+
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            Class::<Channel>::define(default)?;
+
+            default.set("channel", Func::from(channel))?;
+            default.set("hasSubscribers", Func::from(has_subscribers))?;
+            default.set("subscribe", Func::from(subscribe))?;
+            default.set("unsubscribe", Func::from(unsubscribe))?;
+            default.set("tracingChannel", Func::from(tracing_channel))?;
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
+
+impl From<DiagnosticsChannelModule> for ModuleInfo<DiagnosticsChannelModule> {
+    fn from(val: DiagnosticsChannelModule) -> Self {
+        ModuleInfo {
+            name: "diagnostics_channel",
+            module: val,
+        }
+    }
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let _ = ctx.store_userdata(RefCell::new(ChannelRegistry::default()));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_diagnostics_channel() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<DiagnosticsChannelModule>(
+                    ctx.clone(),
+                    "diagnostics_channel",
+                )
+                .await
+                .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import dc from 'diagnostics_channel';
+                        export async function test() {
+                            const seen = [];
+                            const ch = dc.channel('xmas:test');
+                            const onMessage = (message, name) => seen.push(`${name}:${message.n}`);
+
+                            seen.push(dc.hasSubscribers('xmas:test'));
+                            dc.subscribe('xmas:test', onMessage);
+                            seen.push(ch === dc.channel('xmas:test'));
+                            ch.publish({ n: 1 });
+                            seen.push(dc.unsubscribe('xmas:test', onMessage));
+                            ch.publish({ n: 2 });
+
+                            const tc = dc.tracingChannel('op');
+                            tc.subscribe({
+                                start: () => seen.push('start'),
+                                end: () => seen.push('end'),
+                                asyncEnd: (ctx) => seen.push(`asyncEnd:${ctx.result}`),
+                            });
+                            seen.push(tc.traceSync((a, b) => a + b, {}, undefined, 1, 2));
+                            await tc.tracePromise(async () => 'done', {});
+
+                            return seen.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "false|true|xmas:test:1|true|start|end|3|start|end|asyncEnd:done"
+                );
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,166 @@
+//! Process wide settings of the DNS caches behind `fetch` and `https`, and
+//! of the address order `lookup` uses by default.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+use rsquickjs::{Ctx, Exception, Object, Result};
+
+use super::lookup::{LookupOrder, ERROR_MSG_OPTIONS_ORDER};
+use crate::utils::object::ObjectExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DnsCacheOptions {
+    /// Most host names kept by each cache
+    pub max_entries: usize,
+    /// How long resolved addresses are kept, the vtable reports no record TTLs
+    pub ttl: Duration,
+    /// Order of the addresses of a host, and the default of `lookup`
+    pub order: LookupOrder,
+}
+
+impl DnsCacheOptions {
+    pub const DEFAULT: Self = Self {
+        max_entries: 128,
+        ttl: Duration::from_secs(300),
+        order: LookupOrder::Verbatim,
+    };
+}
+
+impl Default for DnsCacheOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static OPTIONS: RwLock<DnsCacheOptions> = RwLock::new(DnsCacheOptions::DEFAULT);
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn options() -> DnsCacheOptions {
+    *OPTIONS.read().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Replace the options, dropping what the caches hold
+pub fn set_options(options: DnsCacheOptions) {
+    *OPTIONS.write().unwrap_or_else(|err| err.into_inner()) = options;
+    flush();
+}
+
+/// Entries cached in an older generation are stale
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Acquire)
+}
+
+pub fn flush() {
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+pub fn set_default_result_order(ctx: Ctx<'_>, order: String) -> Result<()> {
+    let order = LookupOrder::from_name(&order)
+        .ok_or_else(|| Exception::throw_type(&ctx, ERROR_MSG_OPTIONS_ORDER))?;
+    set_options(DnsCacheOptions { order, ..options() });
+    Ok(())
+}
+
+pub fn get_default_result_order() -> &'static str {
+    options().order.name()
+}
+
+/// Set `maxEntries` and `ttl`, in seconds, of the DNS caches
+pub fn set_cache_options<'js>(ctx: Ctx<'js>, cache_options: Object<'js>) -> Result<()> {
+    let mut new_options = options();
+    if let Some(max_entries) = cache_options.get_optional::<_, f64>("maxEntries")? {
+        if max_entries < 1.0 || max_entries.fract() != 0.0 {
+            return Err(Exception::throw_range(
+                &ctx,
+                "The value of \"maxEntries\" must be a positive integer",
+            ));
+        }
+        new_options.max_entries = max_entries as usize;
+    }
+    if let Some(ttl) = cache_options.get_optional::<_, f64>("ttl")? {
+        if !(ttl >= 0.0 && ttl.is_finite()) {
+            return Err(Exception::throw_range(
+                &ctx,
+                "The value of \"ttl\" must be a non-negative number",
+            ));
+        }
+        new_options.ttl = Duration::from_secs_f64(ttl);
+    }
+    set_options(new_options);
+    Ok(())
+}
+
+pub fn get_cache_options(ctx: Ctx<'_>) -> Result<Object<'_>> {
+    let options = options();
+    let object = Object::new(ctx)?;
+    object.set("maxEntries", options.max_entries)?;
+    object.set("ttl", options.ttl.as_secs_f64())?;
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::primordials::{BasePrimordials, Primordial};
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::super::DnsModule;
+    use super::*;
+
+    #[test]
+    fn test_sort_by_family() {
+        let mut addrs = [6, 4, 6, 4];
+        LookupOrder::Ipv4First.sort_by_family(&mut addrs, |family| *family == 4);
+        assert_eq!(addrs, [4, 4, 6, 6]);
+        LookupOrder::Ipv6First.sort_by_family(&mut addrs, |family| *family == 4);
+        assert_eq!(addrs, [6, 6, 4, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_cache_options() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<DnsModule>(ctx.clone(), "dns")
+                    .await
+                    .unwrap();
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import dns from 'dns';
+
+                        export async function test() {
+                            const defaults = dns.getCacheOptions();
+                            dns.setCacheOptions({ maxEntries: 16, ttl: 1.5 });
+                            const { maxEntries, ttl } = dns.getCacheOptions();
+                            dns.setCacheOptions(defaults);
+                            dns.flushCache();
+
+                            let invalid;
+                            try {
+                                dns.setDefaultResultOrder('ipv5first');
+                            } catch (err) {
+                                invalid = err.name;
+                            }
+                            return [maxEntries, ttl, dns.promises.getDefaultResultOrder(), invalid].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+
+                assert_eq!(result, "16|1.5|verbatim|TypeError");
+                assert_eq!(options(), DnsCacheOptions::DEFAULT);
+            })
+        })
+        .await
+    }
+}
@@ -1,18 +1,23 @@
-use std::net::SocketAddr;
+use std::net::IpAddr;
 use std::result::Result as StdResult;
+use std::sync::Arc;
 
 use either::Either;
 use rsquickjs::{
     prelude::Opt, qjs, Ctx, Error, Exception, FromJs, Function, IntoJs, Null, Object, Result, Value,
 };
 
+use xmas_vsys::{NetVTable, VsysError};
+
+use super::cache;
 use crate::{
+    permissions::get_vsys,
     timers::{invoke_async_hook, register_finalization_registry, HookType},
     utils::{ctx::CtxExtension, provider::ProviderType, result::ResultExt},
 };
 
 const ERROR_MSG_OPTIONS_FAMILY: &str = "The argument 'family' must be one of: 0, 4, 6";
-const ERROR_MSG_OPTIONS_ORDER: &str =
+pub(super) const ERROR_MSG_OPTIONS_ORDER: &str =
     "The argument 'order' must be one of: 'verbatim', 'ipv4first', 'ipv6first'";
 
 pub fn lookup<'js>(
@@ -36,8 +41,12 @@ pub fn lookup<'js>(
     register_finalization_registry(&ctx, cb.clone().into_value(), uid)?;
     invoke_async_hook(&ctx, HookType::Init, ProviderType::GetAddrInfoReqWrap, uid)?;
 
+    let net = get_vsys(&ctx)
+        .map(|vsys| vsys.net.clone())
+        .unwrap_or_default();
+
     ctx.clone().spawn_exit(async move {
-        match lookup_host(&hostname, options.family, options.order).await {
+        match lookup_host(net, hostname, options.family, options.order).await {
             Ok(addrs) => {
                 invoke_async_hook(&ctx, HookType::Before, ProviderType::None, uid)?;
                 if options.all {
@@ -66,25 +75,32 @@ pub fn lookup<'js>(
 }
 
 async fn lookup_host(
-    hostname: &str,
+    net: Arc<NetVTable>,
+    hostname: String,
     family: i32,
     order: LookupOrder,
 ) -> StdResult<Vec<LookupValue>, std::io::Error> {
-    let mut addrs = tokio::net::lookup_host((hostname, 0))
-        .await?
+    let mut addrs: Vec<LookupValue> = tokio::task::spawn_blocking(move || (net.resolve)(&hostname))
+        .await
+        .map_err(std::io::Error::other)?
+        .map_err(|err| match err {
+            VsysError::Io(err) => err,
+            err => std::io::Error::other(err),
+        })?
+        .into_iter()
         .filter_map(|addr| {
             if matches!(family, 4 | 0) {
-                if let SocketAddr::V4(ipv4) = addr {
+                if let IpAddr::V4(ipv4) = addr {
                     return Some(LookupValue {
-                        address: ipv4.ip().to_string(),
+                        address: ipv4.to_string(),
                         family: 4,
                     });
                 }
             }
             if matches!(family, 6 | 0) {
-                if let SocketAddr::V6(ipv6) = addr {
+                if let IpAddr::V6(ipv6) = addr {
                     return Some(LookupValue {
-                        address: ipv6.ip().to_string(),
+                        address: ipv6.to_string(),
                         family: 6,
                     });
                 }
@@ -92,17 +108,8 @@ async fn lookup_host(
             None
         })
         .collect();
-    match order {
-        LookupOrder::Verbatim => Ok(addrs),
-        LookupOrder::Ipv4First => {
-            addrs.sort_by(|a, b| a.family.cmp(&b.family));
-            Ok(addrs)
-        }
-        LookupOrder::Ipv6First => {
-            addrs.sort_by(|a, b| b.family.cmp(&a.family));
-            Ok(addrs)
-        }
-    }
+    order.sort_by_family(&mut addrs, |addr| addr.family == 4);
+    Ok(addrs)
 }
 
 struct LookupValue {
@@ -126,6 +133,34 @@ pub enum LookupOrder {
     Ipv6First,
 }
 
+impl LookupOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "verbatim" => Some(Self::Verbatim),
+            "ipv4first" => Some(Self::Ipv4First),
+            "ipv6first" => Some(Self::Ipv6First),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Verbatim => "verbatim",
+            Self::Ipv4First => "ipv4first",
+            Self::Ipv6First => "ipv6first",
+        }
+    }
+
+    /// Stable sort of `addrs` into this order
+    pub fn sort_by_family<T>(self, addrs: &mut [T], is_ipv4: impl Fn(&T) -> bool) {
+        match self {
+            Self::Verbatim => {}
+            Self::Ipv4First => addrs.sort_by_key(|addr| !is_ipv4(addr)),
+            Self::Ipv6First => addrs.sort_by_key(|addr| is_ipv4(addr)),
+        }
+    }
+}
+
 pub struct LookupOptions {
     family: i32,
     all: bool,
@@ -137,7 +172,7 @@ impl Default for LookupOptions {
         Self {
             family: 0,
             all: false,
-            order: LookupOrder::Verbatim,
+            order: cache::options().order,
         }
     }
 }
@@ -146,7 +181,7 @@ impl<'js> FromJs<'js> for LookupOptions {
     fn from_js(ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
         let mut family = 0;
         let mut all = false;
-        let mut order = LookupOrder::Verbatim;
+        let mut order = cache::options().order;
 
         if let Some(v) = value.as_int() {
             if !matches!(v, 4 | 6 | 0) {
@@ -184,14 +219,8 @@ impl<'js> FromJs<'js> for LookupOptions {
 
             // Parse order
             if let Ok(order_value) = options.get::<_, String>("order") {
-                match order_value.as_str() {
-                    "verbatim" => order = LookupOrder::Verbatim,
-                    "ipv4first" => order = LookupOrder::Ipv4First,
-                    "ipv6first" => order = LookupOrder::Ipv6First,
-                    _ => {
-                        return Err(Exception::throw_type(ctx, ERROR_MSG_OPTIONS_ORDER));
-                    }
-                }
+                order = LookupOrder::from_name(&order_value)
+                    .ok_or_else(|| Exception::throw_type(ctx, ERROR_MSG_OPTIONS_ORDER))?;
             }
         } else if value.is_null() || value.is_undefined() {
             // Use default options
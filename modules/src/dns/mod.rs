@@ -2,24 +2,74 @@ use crate::utils::module::{export_default, ModuleInfo};
 use rsquickjs::{
     module::{Declarations, Exports, ModuleDef},
     prelude::Func,
-    Ctx, Result,
+    Ctx, Object, Result, Value,
 };
 
+pub mod cache;
 pub mod lookup;
+pub mod resolver;
+
+const RESOLVER_EXPORTS: [&str; 15] = [
+    "Resolver",
+    "getServers",
+    "setServers",
+    "resolve",
+    "resolve4",
+    "resolve6",
+    "resolveCname",
+    "resolveMx",
+    "resolveNs",
+    "resolvePtr",
+    "resolveSoa",
+    "resolveSrv",
+    "resolveTxt",
+    "reverse",
+    "promises",
+];
+
+const CACHE_EXPORTS: [&str; 5] = [
+    "setDefaultResultOrder",
+    "getDefaultResultOrder",
+    "setCacheOptions",
+    "getCacheOptions",
+    "flushCache",
+];
 
 pub struct DnsModule;
 
 impl ModuleDef for DnsModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare("lookup")?;
+        for name in RESOLVER_EXPORTS.into_iter().chain(CACHE_EXPORTS) {
+            declare.declare(name)?;
+        }
 
         declare.declare("default")?;
         Ok(())
     }
 
     fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        let resolvers = resolver::resolvers(ctx)?;
+        let promises: Object = resolvers.get("promises")?;
+        for object in [&resolvers, &promises] {
+            object.set(
+                "setDefaultResultOrder",
+                Func::from(cache::set_default_result_order),
+            )?;
+            object.set(
+                "getDefaultResultOrder",
+                Func::from(cache::get_default_result_order),
+            )?;
+        }
+        resolvers.set("setCacheOptions", Func::from(cache::set_cache_options))?;
+        resolvers.set("getCacheOptions", Func::from(cache::get_cache_options))?;
+        resolvers.set("flushCache", Func::from(cache::flush))?;
+
         export_default(ctx, exports, |default| {
             default.set("lookup", Func::from(lookup::lookup))?;
+            for name in RESOLVER_EXPORTS.into_iter().chain(CACHE_EXPORTS) {
+                default.set(name, resolvers.get::<_, Value>(name)?)?;
+            }
             Ok(())
         })?;
 
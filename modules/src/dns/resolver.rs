@@ -0,0 +1,664 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use rsquickjs::{
+    prelude::{Async, Func},
+    Constructor, Ctx, Error, Exception, FromJs, Function, IntoJs, Object, Result, Value,
+};
+use xmas_vsys::{DnsQuery, DnsRecord, RecordData, RecordType, VsysError};
+
+use crate::permissions::{check_net_permission, get_vsys, Callers};
+
+use super::lookup;
+
+const DNS_PORT: u16 = 53;
+
+/// Builds the `Resolver` classes and resolve functions of `dns` and `dns.promises`
+/// on top of the native `query`, `reverse` and `parseServers`
+const RESOLVER_SOURCE: &str = r#"
+((query, reverse, parseServers, lookup) => {
+    const RRTYPES = {
+        A: "resolve4",
+        AAAA: "resolve6",
+        CNAME: "resolveCname",
+        MX: "resolveMx",
+        NS: "resolveNs",
+        PTR: "resolvePtr",
+        SOA: "resolveSoa",
+        SRV: "resolveSrv",
+        TXT: "resolveTxt",
+    };
+    const METHODS = [...Object.values(RRTYPES), "resolve", "reverse"];
+    const DEFAULT = Symbol("default");
+
+    const invalidArgType = (name, expected) => {
+        const err = new TypeError(`The "${name}" argument must be ${expected}`);
+        err.code = "ERR_INVALID_ARG_TYPE";
+        return err;
+    };
+    const outOfRange = (name, value) => {
+        const err = new RangeError(
+            `The value of "${name}" is out of range. Received ${value}`
+        );
+        err.code = "ERR_OUT_OF_RANGE";
+        return err;
+    };
+    const cancelled = (syscall, hostname) => {
+        const err = new Error(`${syscall} ECANCELLED ${hostname}`);
+        err.code = "ECANCELLED";
+        err.syscall = syscall;
+        err.hostname = hostname;
+        return err;
+    };
+    const validateString = (name, value) => {
+        if (typeof value !== "string") {
+            throw invalidArgType(name, "of type string");
+        }
+    };
+
+    class PromiseResolver {
+        #servers = [];
+        #timeout;
+        #tries;
+        #pending = new Set();
+
+        constructor(options = {}) {
+            const { timeout = -1, tries = 4 } = options ?? {};
+            if (!Number.isInteger(timeout) || timeout < -1) {
+                throw outOfRange("options.timeout", timeout);
+            }
+            if (!Number.isInteger(tries) || tries < 1) {
+                throw outOfRange("options.tries", tries);
+            }
+            this.#timeout = timeout;
+            this.#tries = tries;
+        }
+
+        getServers() {
+            return [...this.#servers];
+        }
+
+        setServers(servers) {
+            if (!Array.isArray(servers)) {
+                throw invalidArgType("servers", "an instance of Array");
+            }
+            this.#servers = parseServers(servers);
+        }
+
+        cancel() {
+            for (const cancel of this.#pending) {
+                cancel();
+            }
+            this.#pending.clear();
+        }
+
+        #run(syscall, hostname, start) {
+            const options = {
+                servers: this.#servers,
+                timeout: this.#timeout,
+                tries: this.#tries,
+            };
+            return new Promise((resolve, reject) => {
+                const cancel = () => reject(cancelled(syscall, hostname));
+                this.#pending.add(cancel);
+                start(options)
+                    .then(resolve, reject)
+                    .finally(() => this.#pending.delete(cancel));
+            });
+        }
+
+        #query(hostname, rrtype, ttl = false) {
+            validateString("name", hostname);
+            const syscall = `query${rrtype[0]}${rrtype.slice(1).toLowerCase()}`;
+            return this.#run(syscall, hostname, (options) =>
+                query(hostname, rrtype, { ...options, ttl })
+            );
+        }
+
+        resolve(hostname, rrtype = "A") {
+            validateString("rrtype", rrtype);
+            const method = RRTYPES[rrtype];
+            if (!method) {
+                const err = new TypeError(
+                    `The argument 'rrtype' is invalid. Received '${rrtype}'`
+                );
+                err.code = "ERR_INVALID_ARG_VALUE";
+                throw err;
+            }
+            return this[method](hostname);
+        }
+
+        resolve4(hostname, options) {
+            return this.#query(hostname, "A", !!options?.ttl);
+        }
+
+        resolve6(hostname, options) {
+            return this.#query(hostname, "AAAA", !!options?.ttl);
+        }
+
+        resolveCname(hostname) {
+            return this.#query(hostname, "CNAME");
+        }
+
+        resolveMx(hostname) {
+            return this.#query(hostname, "MX");
+        }
+
+        resolveNs(hostname) {
+            return this.#query(hostname, "NS");
+        }
+
+        resolvePtr(hostname) {
+            return this.#query(hostname, "PTR");
+        }
+
+        async resolveSoa(hostname) {
+            const [soa] = await this.#query(hostname, "SOA");
+            return soa;
+        }
+
+        resolveSrv(hostname) {
+            return this.#query(hostname, "SRV");
+        }
+
+        resolveTxt(hostname) {
+            return this.#query(hostname, "TXT");
+        }
+
+        reverse(ip) {
+            validateString("ip", ip);
+            return this.#run("getHostByAddr", ip, (options) => reverse(ip, options));
+        }
+    }
+
+    const defaultPromiseResolver = new PromiseResolver();
+
+    class Resolver {
+        #resolver;
+
+        constructor(options) {
+            this.#resolver =
+                options === DEFAULT ? defaultPromiseResolver : new PromiseResolver(options);
+        }
+
+        getServers() {
+            return this.#resolver.getServers();
+        }
+
+        setServers(servers) {
+            this.#resolver.setServers(servers);
+        }
+
+        cancel() {
+            this.#resolver.cancel();
+        }
+
+        static {
+            for (const method of METHODS) {
+                this.prototype[method] = function (...args) {
+                    const callback = args.pop();
+                    if (typeof callback !== "function") {
+                        throw invalidArgType("callback", "of type function");
+                    }
+                    this.#resolver[method](...args).then(
+                        (result) => callback(null, result),
+                        (err) => callback(err)
+                    );
+                };
+            }
+        }
+    }
+
+    const namespace = (resolver) => {
+        const object = {
+            getServers: () => resolver.getServers(),
+            setServers: (servers) => resolver.setServers(servers),
+        };
+        for (const method of METHODS) {
+            object[method] = resolver[method].bind(resolver);
+        }
+        return object;
+    };
+
+    const promises = {
+        Resolver: PromiseResolver,
+        lookup(hostname, options = {}) {
+            return new Promise((resolve, reject) => {
+                lookup(hostname, options ?? {}, (err, address, family) => {
+                    if (err) reject(err);
+                    else resolve(options?.all ? address : { address, family });
+                });
+            });
+        },
+        ...namespace(defaultPromiseResolver),
+    };
+
+    return {
+        Resolver,
+        ...namespace(new Resolver(DEFAULT)),
+        promises,
+    };
+})
+"#;
+
+/// Name server settings and flags of one query, passed by the JS `Resolver`
+pub struct QueryOptions {
+    ttl: bool,
+    servers: Vec<SocketAddr>,
+    timeout: Option<Duration>,
+    tries: Option<usize>,
+}
+
+impl<'js> FromJs<'js> for QueryOptions {
+    fn from_js(ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let options = Object::from_js(ctx, value)?;
+        let servers = options
+            .get::<_, Option<Vec<String>>>("servers")?
+            .unwrap_or_default()
+            .iter()
+            .map(|server| parse_server(server).ok_or_else(|| invalid_ip(ctx, server)))
+            .collect::<Result<_>>()?;
+        let timeout = options
+            .get::<_, Option<i64>>("timeout")?
+            .and_then(|timeout| u64::try_from(timeout).ok())
+            .map(Duration::from_millis);
+        let tries = options
+            .get::<_, Option<u32>>("tries")?
+            .map(|tries| tries as usize);
+
+        Ok(Self {
+            ttl: options.get::<_, Option<bool>>("ttl")?.unwrap_or_default(),
+            servers,
+            timeout,
+            tries,
+        })
+    }
+}
+
+async fn query<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    hostname: String,
+    rrtype: String,
+    options: QueryOptions,
+) -> Result<Vec<Value<'js>>> {
+    let record_type = RecordType::from_name(&rrtype)
+        .ok_or_else(|| Exception::throw_type(&ctx, &["Invalid rrtype: ", &rrtype].concat()))?;
+    let name = record_type.name();
+    let syscall = ["query", &name[..1], &name[1..].to_ascii_lowercase()].concat();
+
+    let records = query_records(
+        &ctx,
+        &callers,
+        &syscall,
+        &hostname,
+        hostname.clone(),
+        record_type,
+        &options,
+    )
+    .await?;
+    records
+        .into_iter()
+        .map(|record| record_to_js(&ctx, record, options.ttl))
+        .collect()
+}
+
+async fn reverse(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    ip: String,
+    options: QueryOptions,
+) -> Result<Vec<String>> {
+    let addr: IpAddr = ip.parse().map_err(|_| invalid_ip(&ctx, &ip))?;
+
+    let records = query_records(
+        &ctx,
+        &callers,
+        "getHostByAddr",
+        &ip,
+        arpa_name(addr),
+        RecordType::Ptr,
+        &options,
+    )
+    .await?;
+    Ok(records
+        .into_iter()
+        .filter_map(|record| match record.data {
+            RecordData::Ptr(name) => Some(name),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Validate name servers given to `setServers`, returning them as `getServers` shows them
+fn parse_servers(ctx: Ctx<'_>, servers: Vec<String>) -> Result<Vec<String>> {
+    servers
+        .iter()
+        .map(|server| {
+            let addr = parse_server(server).ok_or_else(|| invalid_ip(&ctx, server))?;
+            Ok(if addr.port() == DNS_PORT {
+                addr.ip().to_string()
+            } else {
+                addr.to_string()
+            })
+        })
+        .collect()
+}
+
+/// Ask the net vtable for the `record_type` records of `name`, reporting
+/// errors as the `syscall` for `hostname` the way Node's c-ares binding does
+async fn query_records(
+    ctx: &Ctx<'_>,
+    callers: &Callers,
+    syscall: &str,
+    hostname: &str,
+    name: String,
+    record_type: RecordType,
+    options: &QueryOptions,
+) -> Result<Vec<DnsRecord>> {
+    let servers = options.servers.iter().map(|server| server.ip().to_string());
+    for host in std::iter::once(hostname.to_string()).chain(servers) {
+        if !check_net_permission(ctx, callers, &host) {
+            return Err(Exception::throw_message(
+                ctx,
+                "Permission denied. Cannot access the network",
+            ));
+        }
+    }
+    let vsys =
+        get_vsys(ctx).ok_or_else(|| Exception::throw_message(ctx, "Vsys not initialized"))?;
+
+    let net = vsys.net.clone();
+    let servers = options.servers.clone();
+    let (timeout, tries) = (options.timeout, options.tries);
+    let result = tokio::task::spawn_blocking(move || {
+        (net.query)(&DnsQuery {
+            name: &name,
+            record_type,
+            servers: &servers,
+            timeout,
+            tries,
+        })
+    })
+    .await
+    .map_err(|err| Exception::throw_message(ctx, &err.to_string()))?;
+
+    match result {
+        Ok(records) if !records.is_empty() => Ok(records),
+        Ok(_) => Err(dns_error(ctx, "ENODATA", syscall, hostname)),
+        Err(err) => Err(dns_error(ctx, error_code(&err), syscall, hostname)),
+    }
+}
+
+fn record_to_js<'js>(ctx: &Ctx<'js>, record: DnsRecord, ttl: bool) -> Result<Value<'js>> {
+    let address = |address: String| {
+        if !ttl {
+            return address.into_js(ctx);
+        }
+        let object = Object::new(ctx.clone())?;
+        object.set("address", address)?;
+        object.set("ttl", record.ttl)?;
+        Ok(object.into_value())
+    };
+
+    let object = Object::new(ctx.clone())?;
+    match record.data {
+        RecordData::A(ip) => return address(ip.to_string()),
+        RecordData::Aaaa(ip) => return address(ip.to_string()),
+        RecordData::Cname(name) | RecordData::Ns(name) | RecordData::Ptr(name) => {
+            return name.into_js(ctx)
+        }
+        RecordData::Txt(chunks) => return chunks.into_js(ctx),
+        RecordData::Mx { priority, exchange } => {
+            object.set("priority", priority)?;
+            object.set("exchange", exchange)?;
+        }
+        RecordData::Soa {
+            nsname,
+            hostmaster,
+            serial,
+            refresh,
+            retry,
+            expire,
+            minttl,
+        } => {
+            object.set("nsname", nsname)?;
+            object.set("hostmaster", hostmaster)?;
+            object.set("serial", serial)?;
+            object.set("refresh", refresh)?;
+            object.set("retry", retry)?;
+            object.set("expire", expire)?;
+            object.set("minttl", minttl)?;
+        }
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            name,
+        } => {
+            object.set("priority", priority)?;
+            object.set("weight", weight)?;
+            object.set("port", port)?;
+            object.set("name", name)?;
+        }
+    }
+    Ok(object.into_value())
+}
+
+/// Parse `1.1.1.1`, `1.1.1.1:53`, `::1` or `[::1]:53`
+fn parse_server(server: &str) -> Option<SocketAddr> {
+    if let Ok(addr) = server.parse() {
+        return Some(addr);
+    }
+    let ip = server
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(server);
+    Some(SocketAddr::new(ip.parse().ok()?, DNS_PORT))
+}
+
+/// The `in-addr.arpa` or `ip6.arpa` name of `addr`
+fn arpa_name(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets().iter().rev().map(u8::to_string);
+            octets
+                .chain(["in-addr.arpa".into()])
+                .collect::<Vec<_>>()
+                .join(".")
+        }
+        IpAddr::V6(ip) => {
+            let nibbles = ip
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|octet| [octet & 0xf, octet >> 4])
+                .map(|nibble| format!("{:x}", nibble));
+            nibbles
+                .chain(["ip6.arpa".into()])
+                .collect::<Vec<_>>()
+                .join(".")
+        }
+    }
+}
+
+fn error_code(err: &VsysError) -> &'static str {
+    match err {
+        VsysError::NotFound(_) => "ENOTFOUND",
+        VsysError::PermissionDenied(_) => "EREFUSED",
+        VsysError::InvalidArgument(_) => "EBADNAME",
+        VsysError::Io(err) => match err.kind() {
+            std::io::ErrorKind::TimedOut => "ETIMEOUT",
+            std::io::ErrorKind::ConnectionRefused => "ECONNREFUSED",
+            _ => "ESERVFAIL",
+        },
+        _ => "ESERVFAIL",
+    }
+}
+
+fn dns_error(ctx: &Ctx<'_>, code: &str, syscall: &str, hostname: &str) -> Error {
+    let create = || {
+        let message = [syscall, " ", code, " ", hostname].concat();
+        let err = Exception::from_message(ctx.clone(), &message)?;
+        err.set("code", code)?;
+        err.set("syscall", syscall)?;
+        err.set("hostname", hostname)?;
+        Ok(err.throw())
+    };
+    create().unwrap_or_else(|err| err)
+}
+
+fn invalid_ip(ctx: &Ctx<'_>, ip: &str) -> Error {
+    let create = || {
+        let message = ["Invalid IP address: ", ip].concat();
+        let constructor: Constructor = ctx.globals().get("TypeError")?;
+        let err: Object = constructor.construct((message,))?;
+        err.set("code", "ERR_INVALID_IP_ADDRESS")?;
+        Ok(ctx.throw(err.into_value()))
+    };
+    create().unwrap_or_else(|err| err)
+}
+
+/// The `Resolver` classes, resolve functions and `promises` namespace of the dns module
+pub fn resolvers<'js>(ctx: &Ctx<'js>) -> Result<Object<'js>> {
+    let init: Function = crate::utils::snapshot::eval(ctx, "dns_resolver", RESOLVER_SOURCE)?;
+    init.call((
+        Func::from(Async(query)),
+        Func::from(Async(reverse)),
+        Func::from(parse_servers),
+        Func::from(lookup::lookup),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use xmas_vsys::{Permissions, Vsys};
+
+    use crate::utils::primordials::{BasePrimordials, Primordial};
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::super::DnsModule;
+    use super::*;
+
+    #[test]
+    fn test_arpa_name() {
+        assert_eq!(
+            arpa_name("192.0.2.1".parse().unwrap()),
+            "1.2.0.192.in-addr.arpa"
+        );
+        assert_eq!(
+            arpa_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn test_parse_server() {
+        assert_eq!(parse_server("1.1.1.1"), Some("1.1.1.1:53".parse().unwrap()));
+        assert_eq!(
+            parse_server("[::1]:5353"),
+            Some("[::1]:5353".parse().unwrap())
+        );
+        assert_eq!(parse_server("[::1]"), Some("[::1]:53".parse().unwrap()));
+        assert_eq!(parse_server("example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolver() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                crate::permissions::init(
+                    ctx.clone(),
+                    Arc::new(
+                        Vsys::builder()
+                            .permissions(Permissions::allow_all())
+                            .build(),
+                    ),
+                );
+                ModuleEvaluator::eval_rust::<DnsModule>(ctx.clone(), "dns")
+                    .await
+                    .unwrap();
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { Resolver, promises } from 'dns';
+
+                        export async function test() {
+                            const resolver = new Resolver({ timeout: 100, tries: 1 });
+                            resolver.setServers(['127.0.0.1', '[::1]:5353']);
+                            const servers = resolver.getServers().join(',');
+
+                            let invalid;
+                            try {
+                                resolver.setServers(['example.com']);
+                            } catch (err) {
+                                invalid = err.code;
+                            }
+
+                            const pending = new promises.Resolver({ timeout: 100, tries: 1 });
+                            pending.setServers(['127.0.0.1:1']);
+                            const query = pending.resolve4('example.com');
+                            pending.cancel();
+                            const cancelled = await query.catch((err) => err.code);
+
+                            let rrtype;
+                            try {
+                                pending.resolve('example.com', 'ANY');
+                            } catch (err) {
+                                rrtype = err.code;
+                            }
+
+                            return [servers, invalid, cancelled, rrtype].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+
+                assert_eq!(
+                    result,
+                    "127.0.0.1,[::1]:5353|ERR_INVALID_IP_ADDRESS|ECANCELLED|ERR_INVALID_ARG_VALUE"
+                );
+            })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_resolve_permission_denied() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<DnsModule>(ctx.clone(), "dns")
+                    .await
+                    .unwrap();
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { resolve4 } from 'dns';
+
+                        export async function test() {
+                            return new Promise((resolve) => {
+                                resolve4('example.com', (err) => resolve(err.message));
+                            });
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+
+                assert!(result.contains("Permission denied"));
+            })
+        })
+        .await
+    }
+}
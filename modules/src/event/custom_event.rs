@@ -1,25 +1,38 @@
-use rsquickjs::{prelude::Opt, Ctx, IntoJs, Null, Result, Value};
+use rsquickjs::{
+    atom::PredefinedAtom,
+    prelude::{Opt, This},
+    Class, Coerced, Ctx, IntoJs, Null, Result, Value,
+};
 
 use crate::utils::object::ObjectExt;
 
+use super::event::Event;
+
+/// `CustomEvent`, an `Event` carrying a `detail` value. Its prototype inherits from
+/// `Event.prototype`, which reads the shared state through `event`.
 #[rsquickjs::class]
 #[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
 pub struct CustomEvent<'js> {
-    event_type: String,
+    pub(super) event: Event<'js>,
     detail: Option<Value<'js>>,
 }
 
-#[rsquickjs::methods]
+#[rsquickjs::methods(rename_all = "camelCase")]
 impl<'js> CustomEvent<'js> {
     #[qjs(constructor)]
-    pub fn new(event_type: String, options: Opt<Value<'js>>) -> Result<Self> {
+    pub fn new(
+        ctx: Ctx<'js>,
+        event_type: Coerced<String>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
         let mut detail = None;
-        if let Some(options) = options.0 {
+        if let Some(options) = &options.0 {
             if let Some(opt) = options.get_optional("detail")? {
                 detail = opt;
             }
         }
-        Ok(Self { event_type, detail })
+        let event = Event::new(ctx, event_type, options)?;
+        Ok(Self { event, detail })
     }
 
     #[qjs(get)]
@@ -30,8 +43,27 @@ impl<'js> CustomEvent<'js> {
         Null.into_js(&ctx)
     }
 
-    #[qjs(get, rename = "type")]
-    pub fn event_type(&self) -> String {
-        self.event_type.clone()
+    pub fn init_custom_event(
+        this: This<Class<'js, Self>>,
+        event_type: Coerced<String>,
+        bubbles: Opt<bool>,
+        cancelable: Opt<bool>,
+        detail: Opt<Value<'js>>,
+    ) {
+        let mut this = this.borrow_mut();
+        if this.event.dispatching {
+            return;
+        }
+        this.event.init(
+            event_type.0,
+            bubbles.0.unwrap_or_default(),
+            cancelable.0.unwrap_or_default(),
+        );
+        this.detail = detail.0;
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(CustomEvent)
     }
 }
@@ -1,59 +1,258 @@
-use rsquickjs::{prelude::Opt, Result, Value};
+use rsquickjs::{
+    atom::PredefinedAtom,
+    object::Property,
+    prelude::{Opt, This},
+    Class, Coerced, Ctx, Exception, Function, Object, Result, Value,
+};
 
 use crate::utils::object::ObjectExt;
 
+use super::custom_event::CustomEvent;
+
+pub const NONE: u8 = 0;
+pub const CAPTURING_PHASE: u8 = 1;
+pub const AT_TARGET: u8 = 2;
+pub const BUBBLING_PHASE: u8 = 3;
+
 #[rsquickjs::class]
 #[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
-pub struct Event {
-    event_type: String,
+pub struct Event<'js> {
+    pub(super) event_type: String,
     bubbles: bool,
     cancelable: bool,
     composed: bool,
+    time_stamp: f64,
+    pub(super) default_prevented: bool,
+    pub(super) stop_propagation: bool,
+    pub(super) stop_immediate_propagation: bool,
+    pub(super) in_passive_listener: bool,
+    pub(super) dispatching: bool,
+    pub(super) event_phase: u8,
+    pub(super) target: Option<Value<'js>>,
+    pub(super) current_target: Option<Value<'js>>,
 }
 
-#[rsquickjs::methods]
-impl Event {
+// The accessors take `this` as a value so they also work on `CustomEvent`, whose
+// prototype inherits from `Event.prototype`.
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Event<'js> {
     #[qjs(constructor)]
-    pub fn new(event_type: String, options: Opt<Value<'_>>) -> Result<Self> {
-        let mut bubbles = false;
-        let mut cancelable = false;
-        let mut composed = false;
+    pub fn new(
+        ctx: Ctx<'js>,
+        event_type: Coerced<String>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let mut event = Self::from_type(&ctx, &event_type.0);
         if let Some(options) = options.0 {
             if let Some(opt) = options.get_optional("bubbles")? {
-                bubbles = opt;
+                event.bubbles = opt;
             }
             if let Some(opt) = options.get_optional("cancelable")? {
-                cancelable = opt;
+                event.cancelable = opt;
             }
             if let Some(opt) = options.get_optional("composed")? {
-                composed = opt;
+                event.composed = opt;
             }
         }
-        Ok(Self {
-            event_type,
-            bubbles,
-            cancelable,
-            composed,
-        })
+        Ok(event)
     }
 
     #[qjs(get)]
-    pub fn bubbles(&self) -> bool {
-        self.bubbles
+    pub fn bubbles(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| event.bubbles)
     }
 
     #[qjs(get)]
-    pub fn cancelable(&self) -> bool {
-        self.cancelable
+    pub fn cancelable(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| event.cancelable)
     }
 
     #[qjs(get)]
-    pub fn composed(&self) -> bool {
-        self.composed
+    pub fn composed(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| event.composed)
     }
 
     #[qjs(get, rename = "type")]
-    pub fn event_type(&self) -> String {
-        self.event_type.clone()
+    pub fn event_type(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<String> {
+        with_event(&ctx, &this, |event| event.event_type.clone())
+    }
+
+    #[qjs(get)]
+    pub fn default_prevented(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| event.default_prevented)
+    }
+
+    #[qjs(get)]
+    pub fn event_phase(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<u8> {
+        with_event(&ctx, &this, |event| event.event_phase)
+    }
+
+    #[qjs(get)]
+    pub fn target(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let target = with_event(&ctx, &this, |event| event.target.clone())?;
+        Ok(target.unwrap_or_else(|| Value::new_null(ctx)))
+    }
+
+    #[qjs(get)]
+    pub fn src_element(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        Self::target(this, ctx)
+    }
+
+    #[qjs(get)]
+    pub fn current_target(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let target = with_event(&ctx, &this, |event| event.current_target.clone())?;
+        Ok(target.unwrap_or_else(|| Value::new_null(ctx)))
+    }
+
+    #[qjs(get)]
+    pub fn is_trusted(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        // only script created events exist, the runtime dispatches its own with `Event::from_type`
+        with_event(&ctx, &this, |_| false)
+    }
+
+    #[qjs(get)]
+    pub fn time_stamp(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<f64> {
+        with_event(&ctx, &this, |event| event.time_stamp)
+    }
+
+    #[qjs(get)]
+    pub fn cancel_bubble(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| event.stop_propagation)
+    }
+
+    #[qjs(set, rename = "cancelBubble")]
+    pub fn set_cancel_bubble(this: This<Value<'js>>, ctx: Ctx<'js>, value: bool) -> Result<()> {
+        with_event(&ctx, &this, |event| event.stop_propagation |= value)
+    }
+
+    #[qjs(get)]
+    pub fn return_value(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<bool> {
+        with_event(&ctx, &this, |event| !event.default_prevented)
+    }
+
+    #[qjs(set, rename = "returnValue")]
+    pub fn set_return_value(this: This<Value<'js>>, ctx: Ctx<'js>, value: bool) -> Result<()> {
+        with_event(&ctx, &this, |event| {
+            if !value {
+                event.prevent_default();
+            }
+        })
+    }
+
+    pub fn prevent_default(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<()> {
+        with_event(&ctx, &this, |event| event.prevent_default())
+    }
+
+    pub fn stop_propagation(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<()> {
+        with_event(&ctx, &this, |event| event.stop_propagation = true)
+    }
+
+    pub fn stop_immediate_propagation(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<()> {
+        with_event(&ctx, &this, |event| {
+            event.stop_propagation = true;
+            event.stop_immediate_propagation = true;
+        })
+    }
+
+    /// The targets the event passes through, only the target itself while it is dispatched
+    pub fn composed_path(this: This<Value<'js>>, ctx: Ctx<'js>) -> Result<Vec<Value<'js>>> {
+        with_event(&ctx, &this, |event| match &event.current_target {
+            Some(target) if event.dispatching => vec![target.clone()],
+            _ => vec![],
+        })
     }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(Event)
+    }
+}
+
+impl<'js> Event<'js> {
+    /// A new event with default options, like `new Event(type)`
+    pub fn from_type(ctx: &Ctx<'js>, event_type: &str) -> Self {
+        Self {
+            event_type: event_type.into(),
+            bubbles: false,
+            cancelable: false,
+            composed: false,
+            time_stamp: performance_now(ctx),
+            default_prevented: false,
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            in_passive_listener: false,
+            dispatching: false,
+            event_phase: NONE,
+            target: None,
+            current_target: None,
+        }
+    }
+
+    pub(super) fn init(&mut self, event_type: String, bubbles: bool, cancelable: bool) {
+        self.event_type = event_type;
+        self.bubbles = bubbles;
+        self.cancelable = cancelable;
+        self.default_prevented = false;
+        self.stop_propagation = false;
+        self.stop_immediate_propagation = false;
+        self.target = None;
+    }
+
+    fn prevent_default(&mut self) {
+        // passive listeners promised not to cancel the event
+        if self.cancelable && !self.in_passive_listener {
+            self.default_prevented = true;
+        }
+    }
+
+    pub(super) fn begin_dispatch(&mut self, target: Value<'js>) {
+        self.dispatching = true;
+        self.event_phase = AT_TARGET;
+        self.target = Some(target.clone());
+        self.current_target = Some(target);
+    }
+
+    pub(super) fn end_dispatch(&mut self) {
+        self.dispatching = false;
+        self.event_phase = NONE;
+        self.current_target = None;
+        self.stop_propagation = false;
+        self.stop_immediate_propagation = false;
+    }
+}
+
+/// Runs `f` on the `Event` state of an `Event` or `CustomEvent` instance
+pub fn with_event<'js, R>(
+    ctx: &Ctx<'js>,
+    value: &Value<'js>,
+    f: impl FnOnce(&mut Event<'js>) -> R,
+) -> Result<R> {
+    if let Ok(event) = Class::<Event>::from_value(value) {
+        return Ok(f(&mut event.borrow_mut()));
+    }
+    if let Ok(event) = Class::<CustomEvent>::from_value(value) {
+        return Ok(f(&mut event.borrow_mut().event));
+    }
+    Err(Exception::throw_type(ctx, "Value is not an Event"))
+}
+
+/// Sets `NONE`, `CAPTURING_PHASE`, `AT_TARGET` and `BUBBLING_PHASE` on `obj`
+pub fn add_phase_constants(obj: &Object<'_>) -> Result<()> {
+    for (key, value) in [
+        ("NONE", NONE),
+        ("CAPTURING_PHASE", CAPTURING_PHASE),
+        ("AT_TARGET", AT_TARGET),
+        ("BUBBLING_PHASE", BUBBLING_PHASE),
+    ] {
+        obj.prop(key, Property::from(value).enumerable())?;
+    }
+    Ok(())
+}
+
+fn performance_now(ctx: &Ctx<'_>) -> f64 {
+    ctx.globals()
+        .get::<_, Object>("performance")
+        .and_then(|performance| performance.get::<_, Function>("now"))
+        .and_then(|now| now.call(()))
+        .unwrap_or_default()
 }
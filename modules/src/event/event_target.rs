@@ -1,16 +1,22 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
 
 use rsquickjs::{
     class::{Trace, Tracer},
-    JsLifetime,
+    Class, Ctx, JsLifetime, Result,
 };
 
-use super::{Emitter, EventList, Events};
+use super::{emit_max_listeners_warning, event_defaults, Emitter, EventKey, EventList, Events};
 
 #[rsquickjs::class]
 #[derive(Clone)]
 pub struct EventTarget<'js> {
     pub events: Events<'js>,
+    /// Set with `events.setMaxListeners()`, the EventTarget has no method of its own
+    pub(crate) max_listeners: Option<usize>,
+    warned: HashSet<String>,
 }
 
 unsafe impl<'js> JsLifetime<'js> for EventTarget<'js> {
@@ -21,6 +27,30 @@ impl<'js> Emitter<'js> for EventTarget<'js> {
     fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
         self.events.clone()
     }
+
+    fn max_listeners(&self, ctx: &Ctx<'js>) -> usize {
+        self.max_listeners
+            .unwrap_or_else(|| event_defaults(ctx).max_listeners)
+    }
+
+    fn on_max_listeners_exceeded(
+        this: &Class<'js, Self>,
+        ctx: &Ctx<'js>,
+        event: &EventKey<'js>,
+        count: usize,
+    ) -> Result<()> {
+        let name = event.display_name();
+        if !this.borrow_mut().warned.insert(name.clone()) {
+            return Ok(());
+        }
+        let message = format!(
+            "Possible EventTarget memory leak detected. {} {} listeners added to EventTarget. MaxListeners is {}. Use events.setMaxListeners() to increase limit",
+            count,
+            name,
+            this.borrow().max_listeners(ctx)
+        );
+        emit_max_listeners_warning(ctx, this.clone().into_value(), event, count, &message)
+    }
 }
 
 impl<'js> Trace<'js> for EventTarget<'js> {
@@ -36,6 +66,8 @@ impl<'js> EventTarget<'js> {
         Self {
             #[allow(clippy::arc_with_non_send_sync)]
             events: Arc::new(RwLock::new(Vec::new())),
+            max_listeners: None,
+            warned: HashSet::new(),
         }
     }
 }
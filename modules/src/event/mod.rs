@@ -1,26 +1,212 @@
 use std::{
+    cell::Cell,
+    collections::HashSet,
     rc::Rc,
     sync::{Arc, RwLock},
 };
 
+use crate::exceptions::{DOMException, DOMExceptionName};
 use crate::utils::{
-    error::ErrorExtensions, module::ModuleInfo, object::ObjectExt, result::ResultExt,
+    ctx::report_uncaught_error,
+    error::ErrorExtensions,
+    module::ModuleInfo,
+    object::{CreateSymbol, ObjectExt},
+    result::ResultExt,
 };
 use rsquickjs::{
     class::{JsClass, OwnedBorrow, Trace, Tracer},
+    function::{Constructor, OnceFn},
     module::{Declarations, Exports, ModuleDef},
+    object::Accessor,
     prelude::{Func, Opt, Rest, This},
-    CatchResultExt, Class, Ctx, Function, JsLifetime, Object, Result, String as JsString, Symbol,
-    Value,
+    CatchResultExt, Class, Ctx, Exception, Function, JsLifetime, Object, Result,
+    String as JsString, Symbol, Undefined, Value,
 };
 use tracing::trace;
 
-use self::{custom_event::CustomEvent, event::Event, event_target::EventTarget};
+use self::{
+    custom_event::CustomEvent,
+    event::{with_event, Event},
+    event_target::EventTarget,
+};
 
 pub mod custom_event;
 pub mod event;
 pub mod event_target;
 
+/// Registered symbol description of `events.errorMonitor`.
+pub const ERROR_MONITOR_SYMBOL_DESCRIPTION: &str = "events.errorMonitor";
+/// Registered symbol description of `Symbol.for('nodejs.rejection')`.
+pub const CAPTURE_REJECTION_SYMBOL_DESCRIPTION: &str = "nodejs.rejection";
+
+/// `EventEmitter.defaultMaxListeners` and `EventEmitter.captureRejections` of a context.
+#[derive(Clone, Copy)]
+struct EventDefaults {
+    max_listeners: usize,
+    capture_rejections: bool,
+}
+
+impl Default for EventDefaults {
+    fn default() -> Self {
+        Self {
+            max_listeners: 10,
+            capture_rejections: false,
+        }
+    }
+}
+
+unsafe impl<'js> JsLifetime<'js> for EventDefaults {
+    type Changed<'to> = EventDefaults;
+}
+
+fn event_defaults(ctx: &Ctx<'_>) -> EventDefaults {
+    ctx.userdata::<Cell<EventDefaults>>()
+        .map(|defaults| defaults.get())
+        .unwrap_or_default()
+}
+
+fn update_event_defaults(ctx: &Ctx<'_>, update: impl FnOnce(&mut EventDefaults)) {
+    let mut defaults = event_defaults(ctx);
+    update(&mut defaults);
+    match ctx.userdata::<Cell<EventDefaults>>() {
+        Some(current) => current.set(defaults),
+        None => {
+            let _ = ctx.store_userdata(Cell::new(defaults));
+        }
+    }
+}
+
+// `events.once()` and `events.on()` are thin promise / async iterator adapters on top of
+// the emitter methods, so they are kept in JS to follow the spec'd iteration protocol.
+const EVENTS_HELPERS_SOURCE: &str = r#"
+(() => {
+    const addAbortListener = (signal, listener) => {
+        if (!signal) return () => {};
+        signal.addEventListener("abort", listener, { once: true });
+        return () => signal.removeEventListener("abort", listener);
+    };
+    const subscribe = (emitter, name, listener) => {
+        if (typeof emitter.on === "function") {
+            emitter.on(name, listener);
+            return () => emitter.removeListener(name, listener);
+        }
+        emitter.addEventListener(name, listener);
+        return () => emitter.removeEventListener(name, listener);
+    };
+    const abortError = (signal) => {
+        const err = new DOMException("The operation was aborted", "AbortError");
+        err.cause = signal.reason;
+        return err;
+    };
+
+    function once(emitter, name, options = {}) {
+        const { signal } = options;
+        if (signal && signal.aborted) {
+            return Promise.reject(abortError(signal));
+        }
+        return new Promise((resolve, reject) => {
+            let removeError = () => {};
+            let removeAbort = () => {};
+            const removeEvent = subscribe(emitter, name, function (...args) {
+                removeEvent();
+                removeError();
+                removeAbort();
+                resolve(args);
+            });
+            if (name !== "error" && typeof emitter.on === "function") {
+                removeError = subscribe(emitter, "error", (err) => {
+                    removeEvent();
+                    removeError();
+                    removeAbort();
+                    reject(err);
+                });
+            }
+            removeAbort = addAbortListener(signal, () => {
+                removeEvent();
+                removeError();
+                reject(abortError(signal));
+            });
+        });
+    }
+
+    function on(emitter, name, options = {}) {
+        const { signal } = options;
+        if (signal && signal.aborted) {
+            throw abortError(signal);
+        }
+        const events = [];
+        const pending = [];
+        let error = null;
+        let finished = false;
+
+        const cleanup = () => {
+            removeEvent();
+            removeError();
+            removeAbort();
+        };
+        const finish = () => {
+            finished = true;
+            cleanup();
+            while (pending.length > 0) {
+                pending.shift().resolve({ value: undefined, done: true });
+            }
+        };
+        const fail = (err) => {
+            finished = true;
+            cleanup();
+            if (pending.length > 0) {
+                pending.shift().reject(err);
+            } else {
+                error = err;
+            }
+        };
+
+        const removeEvent = subscribe(emitter, name, (...args) => {
+            if (pending.length > 0) {
+                pending.shift().resolve({ value: args, done: false });
+            } else {
+                events.push(args);
+            }
+        });
+        const removeError =
+            name !== "error" && typeof emitter.on === "function"
+                ? subscribe(emitter, "error", fail)
+                : () => {};
+        const removeAbort = addAbortListener(signal, () => fail(abortError(signal)));
+
+        return {
+            next() {
+                if (events.length > 0) {
+                    return Promise.resolve({ value: events.shift(), done: false });
+                }
+                if (error) {
+                    const err = error;
+                    error = null;
+                    return Promise.reject(err);
+                }
+                if (finished) {
+                    return Promise.resolve({ value: undefined, done: true });
+                }
+                return new Promise((resolve, reject) => pending.push({ resolve, reject }));
+            },
+            return() {
+                finish();
+                return Promise.resolve({ value: undefined, done: true });
+            },
+            throw(err) {
+                fail(err);
+                return Promise.reject(err);
+            },
+            [Symbol.asyncIterator]() {
+                return this;
+            },
+        };
+    }
+
+    return { once, on };
+})()
+"#;
+
 #[derive(Clone, Debug)]
 pub enum EventKey<'js> {
     Symbol(Symbol<'js>),
@@ -28,6 +214,18 @@ pub enum EventKey<'js> {
 }
 
 impl<'js> EventKey<'js> {
+    fn display_name(&self) -> String {
+        match self {
+            EventKey::String(name) => name.to_string(),
+            EventKey::Symbol(sym) => sym
+                .description()
+                .ok()
+                .and_then(|desc| desc.as_string().and_then(|desc| desc.to_string().ok()))
+                .map(|desc| ["Symbol(", &desc, ")"].concat())
+                .unwrap_or_else(|| "Symbol()".into()),
+        }
+    }
+
     fn from_value(ctx: &Ctx, value: Value<'js>) -> Result<Self> {
         if value.is_string() {
             let key: String = value.get()?;
@@ -52,8 +250,14 @@ impl PartialEq for EventKey<'_> {
 }
 
 pub struct EventItem<'js> {
-    callback: Function<'js>,
+    /// A function, or an object with a `handleEvent` method for `EventTarget` listeners
+    callback: Value<'js>,
     once: bool,
+    capture: bool,
+    passive: bool,
+    /// The state of the wrapper `rawListeners` returns for a `once` listener, created
+    /// when first asked for: `{ fired, wrapFn, target, type, listener }`
+    once_state: Option<Object<'js>>,
 }
 
 pub type EventList<'js> = Vec<(EventKey<'js>, Vec<EventItem<'js>>)>;
@@ -63,6 +267,9 @@ pub type Events<'js> = Arc<RwLock<EventList<'js>>>;
 #[derive(Clone)]
 pub struct EventEmitter<'js> {
     pub events: Events<'js>,
+    max_listeners: Option<usize>,
+    capture_rejections: bool,
+    warned: HashSet<String>,
 }
 
 unsafe impl<'js> JsLifetime<'js> for EventEmitter<'js> {
@@ -73,6 +280,34 @@ impl<'js> Emitter<'js> for EventEmitter<'js> {
     fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
         self.events.clone()
     }
+
+    fn max_listeners(&self, ctx: &Ctx<'js>) -> usize {
+        self.max_listeners
+            .unwrap_or_else(|| event_defaults(ctx).max_listeners)
+    }
+
+    fn capture_rejections(&self) -> bool {
+        self.capture_rejections
+    }
+
+    fn on_max_listeners_exceeded(
+        this: &Class<'js, Self>,
+        ctx: &Ctx<'js>,
+        event: &EventKey<'js>,
+        count: usize,
+    ) -> Result<()> {
+        let name = event.display_name();
+        if !this.borrow_mut().warned.insert(name.clone()) {
+            return Ok(());
+        }
+        let message = format!(
+            "Possible EventEmitter memory leak detected. {} {} listeners added to [EventEmitter]. MaxListeners is {}. Use emitter.setMaxListeners() to increase limit",
+            count,
+            name,
+            this.borrow().max_listeners(ctx)
+        );
+        emit_max_listeners_warning(ctx, this.clone().into_value(), event, count, &message)
+    }
 }
 
 impl<'js> Trace<'js> for EventEmitter<'js> {
@@ -81,15 +316,84 @@ impl<'js> Trace<'js> for EventEmitter<'js> {
     }
 }
 
-#[rsquickjs::methods]
+#[rsquickjs::methods(rename_all = "camelCase")]
 impl<'js> EventEmitter<'js> {
     #[qjs(constructor)]
+    pub fn ctor(ctx: Ctx<'js>, options: Opt<Object<'js>>) -> Result<Self> {
+        let mut emitter = Self::new();
+        emitter.capture_rejections = event_defaults(&ctx).capture_rejections;
+        if let Some(options) = options.0 {
+            if let Some(capture_rejections) = options.get_optional("captureRejections")? {
+                emitter.capture_rejections = capture_rejections;
+            }
+        }
+        Ok(emitter)
+    }
+
+    #[qjs(skip)]
     pub fn new() -> Self {
         Self {
             #[allow(clippy::arc_with_non_send_sync)]
             events: Arc::new(RwLock::new(Vec::new())),
+            max_listeners: None,
+            capture_rejections: false,
+            warned: HashSet::new(),
         }
     }
+
+    pub fn set_max_listeners(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        n: f64,
+    ) -> Result<Class<'js, Self>> {
+        this.borrow_mut().max_listeners = Some(to_max_listeners(&ctx, n)?);
+        Ok(this.0)
+    }
+
+    pub fn get_max_listeners(&self, ctx: Ctx<'js>) -> usize {
+        Emitter::max_listeners(self, &ctx)
+    }
+}
+
+impl Default for EventEmitter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Emits a `MaxListenersExceededWarning` for `emitter` through `process`
+fn emit_max_listeners_warning<'js>(
+    ctx: &Ctx<'js>,
+    emitter: Value<'js>,
+    event: &EventKey<'js>,
+    count: usize,
+    message: &str,
+) -> Result<()> {
+    let warning = Exception::from_message(ctx.clone(), message)?;
+    warning.set("name", "MaxListenersExceededWarning")?;
+    warning.set("emitter", emitter)?;
+    let event: Value = match event {
+        EventKey::Symbol(sym) => sym.clone().into_value(),
+        EventKey::String(str) => JsString::from_str(ctx.clone(), str)?.into(),
+    };
+    warning.set("type", event)?;
+    warning.set("count", count)?;
+    crate::process::warn(ctx, warning.into_object())
+}
+
+fn to_max_listeners(ctx: &Ctx<'_>, n: f64) -> Result<usize> {
+    if n.is_nan() || n < 0.0 {
+        return Err(Exception::throw_range(
+            ctx,
+            &[
+                "The value of \"n\" is out of range. It must be a non-negative number. Received ",
+                &n.to_string(),
+            ]
+            .concat(),
+        ));
+    }
+    // Infinity (and 0) disable the leak warning
+    Ok(if n.is_infinite() { 0 } else { n as usize })
 }
 
 pub trait EmitError<'js> {
@@ -126,6 +430,25 @@ where
         Ok(())
     }
 
+    /// Listener count per event above which a leak warning is printed, `0` disables it.
+    fn max_listeners(&self, _ctx: &Ctx<'js>) -> usize {
+        0
+    }
+
+    /// Whether rejected promises returned by listeners are routed back to the emitter.
+    fn capture_rejections(&self) -> bool {
+        false
+    }
+
+    fn on_max_listeners_exceeded(
+        _this: &Class<'js, Self>,
+        _ctx: &Ctx<'js>,
+        _event: &EventKey<'js>,
+        _count: usize,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn add_event_emitter_prototype(ctx: &Ctx<'js>) -> Result<Object<'js>> {
         let proto = Class::<Self>::prototype(ctx)?
             .or_throw_msg(ctx, "Prototype for EventEmitter not found")?;
@@ -150,6 +473,14 @@ where
 
         proto.set("eventNames", Func::from(Self::event_names))?;
 
+        proto.set("listeners", Func::from(Self::listeners))?;
+
+        proto.set("rawListeners", Func::from(Self::raw_listeners))?;
+
+        proto.set("listenerCount", Func::from(Self::listener_count))?;
+
+        proto.set("removeAllListeners", Func::from(Self::remove_all_listeners))?;
+
         proto.set("addListener", on)?;
 
         proto.set("removeListener", off)?;
@@ -162,7 +493,7 @@ where
             .or_throw_msg(ctx, "Prototype for EventTarget not found")?;
 
         let on = Function::new(ctx.clone(), Self::evt_add_event_listener)?;
-        let off = Function::new(ctx.clone(), Self::remove_event_listener)?;
+        let off = Function::new(ctx.clone(), Self::evt_remove_event_listener)?;
 
         proto.set("dispatchEvent", Func::from(Self::evt_dispatch_event))?;
 
@@ -183,6 +514,9 @@ where
 
             for item in items {
                 tracer.mark(&item.callback);
+                if let Some(state) = &item.once_state {
+                    tracer.mark(state);
+                }
             }
         }
     }
@@ -203,21 +537,59 @@ where
         event: Value<'js>,
         listener: Function<'js>,
     ) -> Result<Class<'js, Self>> {
-        let events = this.clone().borrow().get_event_list();
-        let mut events = events.write().or_throw(&ctx)?;
-
         let key = EventKey::from_value(&ctx, event)?;
-        if let Some(index) = events.iter_mut().position(|(k, _)| k == &key) {
+        let listener = listener.into_value();
+        Self::remove_item(&this, &ctx, &key, |item| {
+            item.callback == listener || is_once_wrapper(item, &listener)
+        })?;
+        Ok(this.0)
+    }
+
+    /// Removes the first listener of `key` matching `predicate`, returns whether the key
+    /// has no listeners left
+    fn remove_item(
+        this: &Class<'js, Self>,
+        ctx: &Ctx<'js>,
+        key: &EventKey<'js>,
+        predicate: impl Fn(&EventItem<'js>) -> bool,
+    ) -> Result<bool> {
+        let events = this.borrow().get_event_list();
+        let mut events = events.write().or_throw(ctx)?;
+
+        if let Some(index) = events.iter().position(|(k, _)| k == key) {
             let items = &mut events[index].1;
-            if let Some(pos) = items.iter().position(|item| item.callback == listener) {
+            if let Some(pos) = items.iter().position(predicate) {
                 items.remove(pos);
                 if items.is_empty() {
                     events.remove(index);
+                    return Ok(true);
                 }
             }
-        };
+        }
+        Ok(false)
+    }
 
-        Ok(this.0)
+    fn evt_remove_event_listener(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        event: Value<'js>,
+        listener: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<()> {
+        let key = EventKey::from_value(&ctx, event)?;
+        let capture = match options.0 {
+            Some(options) if options.is_object() => {
+                options.get_optional("capture")?.unwrap_or_default()
+            }
+            Some(options) => options.as_bool().unwrap_or_default(),
+            None => false,
+        };
+        if Self::remove_item(&this, &ctx, &key, |item| {
+            item.callback == listener && item.capture == capture
+        })? {
+            this.borrow_mut().on_event_changed(key, false)?;
+        }
+        Ok(())
     }
 
     fn add_event_listener_str(
@@ -272,16 +644,98 @@ where
         this: This<Class<'js, Self>>,
         ctx: Ctx<'js>,
         event: Value<'js>,
-        listener: Function<'js>,
-        options: Opt<Object<'js>>,
-    ) -> Result<Class<'js, Self>> {
-        let mut once = false;
-        if let Some(opt) = options.0 {
-            if let Some(once_opt) = opt.get("once")? {
-                once = once_opt;
+        listener: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<()> {
+        if listener.is_null() || listener.is_undefined() {
+            return Ok(());
+        }
+        if !listener.is_object() {
+            return Err(Exception::throw_type(
+                &ctx,
+                "The \"listener\" argument must be a function or an object",
+            ));
+        }
+
+        let mut item = EventItem {
+            callback: listener.clone(),
+            once: false,
+            capture: false,
+            passive: false,
+            once_state: None,
+        };
+        let mut signal: Option<Object<'js>> = None;
+        match options.0 {
+            Some(options) if options.is_object() => {
+                item.capture = options.get_optional("capture")?.unwrap_or_default();
+                item.once = options.get_optional("once")?.unwrap_or_default();
+                item.passive = options.get_optional("passive")?.unwrap_or_default();
+                signal = options.get_optional("signal")?;
+            }
+            Some(options) => item.capture = options.as_bool().unwrap_or_default(),
+            None => {}
+        }
+        let capture = item.capture;
+
+        if let Some(signal) = &signal {
+            if signal.get_optional("aborted")?.unwrap_or_default() {
+                return Ok(());
+            }
+        }
+
+        let key = EventKey::from_value(&ctx, event)?;
+        let (is_new, count) = {
+            let events = this.borrow().get_event_list();
+            let mut events = events.write().or_throw(&ctx)?;
+            match events.iter_mut().find(|(k, _)| k == &key) {
+                // the same listener can only be added once per capture flag
+                Some((_, items))
+                    if items
+                        .iter()
+                        .any(|i| i.callback == listener && i.capture == capture) =>
+                {
+                    return Ok(());
+                }
+                Some((_, items)) => {
+                    items.push(item);
+                    (false, items.len())
+                }
+                None => {
+                    events.push((key.clone(), vec![item]));
+                    (true, 1)
+                }
             }
+        };
+        let max_listeners = this.borrow().max_listeners(&ctx);
+        if max_listeners > 0 && count > max_listeners {
+            Self::on_max_listeners_exceeded(&this, &ctx, &key, count)?;
+        }
+        if is_new {
+            this.borrow_mut().on_event_changed(key.clone(), true)?;
         }
-        Self::add_event_listener(this, ctx, event, listener, false, once)
+
+        // `signal` removes the listener once it aborts
+        if let Some(signal) = signal {
+            let target = this.0;
+            let remove = Function::new(
+                ctx.clone(),
+                OnceFn::from(move |ctx| {
+                    struct Args<'js>(Ctx<'js>);
+                    let Args(ctx) = Args(ctx);
+                    if Self::remove_item(&target, &ctx, &key, |item| {
+                        item.callback == listener && item.capture == capture
+                    })? {
+                        target.borrow_mut().on_event_changed(key, false)?;
+                    }
+                    Ok::<_, rsquickjs::Error>(())
+                }),
+            )?;
+            let options = Object::new(ctx.clone())?;
+            options.set("once", true)?;
+            let add_event_listener: Function = signal.get("addEventListener")?;
+            add_event_listener.call::<_, ()>((This(signal), "abort", remove, options))?;
+        }
+        Ok(())
     }
 
     fn add_event_listener(
@@ -309,14 +763,24 @@ where
         };
 
         let item = EventItem {
-            callback: listener,
+            callback: listener.into_value(),
             once,
+            capture: false,
+            passive: false,
+            once_state: None,
         };
         if !prepend {
             items.push(item);
         } else {
             items.insert(0, item);
         }
+        let count = items.len();
+        drop(events);
+
+        let max_listeners = this2.borrow().max_listeners(&ctx);
+        if max_listeners > 0 && count > max_listeners {
+            Self::on_max_listeners_exceeded(&this2, &ctx, &key, count)?;
+        }
         if is_new {
             this2.borrow_mut().on_event_changed(key, true)?
         }
@@ -334,7 +798,6 @@ where
         Ok(has_key(self.get_event_list(), key))
     }
 
-    #[allow(dead_code)]
     fn get_listeners(&self, ctx: &Ctx<'js>, event: Value<'js>) -> Result<Vec<Function<'js>>> {
         let key = EventKey::from_value(ctx, event)?;
         Ok(find_all_listeners(self.get_event_list(), key))
@@ -360,8 +823,12 @@ where
         if let Some(index) = events.iter_mut().position(|(k, _)| k == &key) {
             let items = &mut events[index].1;
             let mut callbacks = Vec::with_capacity(items.len());
+            let mut fired = Vec::new();
             items.retain(|item: &EventItem<'_>| {
                 callbacks.push(item.callback.clone());
+                if let Some(state) = &item.once_state {
+                    fired.push(state.clone());
+                }
                 !item.once
             });
             if items.is_empty() {
@@ -369,14 +836,23 @@ where
                 this.borrow_mut().on_event_changed(key, false)?;
             }
             drop(events);
+            for state in fired {
+                state.set("fired", true)?;
+            }
+            let capture_rejections = this.borrow().capture_rejections();
             for callback in callbacks {
-                let args = args.iter().map(|arg| arg.to_owned()).collect();
-                let args = Rest(args);
-                let this = This(this.clone());
+                let Some(callback) = callback.into_function() else {
+                    continue;
+                };
+                let call_args = Rest(args.iter().map(|arg| arg.to_owned()).collect());
+                let call_this = This(this.clone());
                 if defer {
-                    callback.defer((this, args))?;
+                    callback.defer((call_this, call_args))?;
                 } else {
-                    callback.call::<_, ()>((this, args))?;
+                    let result = callback.call::<_, Value>((call_this, call_args))?;
+                    if capture_rejections {
+                        Self::capture_rejection(this.clone(), ctx, &key, result, &args)?;
+                    }
                 }
             }
         }
@@ -384,6 +860,53 @@ where
         Ok(())
     }
 
+    fn capture_rejection(
+        this: This<Class<'js, Self>>,
+        ctx: &Ctx<'js>,
+        key: &EventKey<'js>,
+        result: Value<'js>,
+        args: &Rest<Value<'js>>,
+    ) -> Result<()> {
+        let Some(promise) = result.into_promise() else {
+            return Ok(());
+        };
+        let event = match key {
+            EventKey::Symbol(sym) => sym.clone().into_value(),
+            EventKey::String(str) => JsString::from_str(ctx.clone(), str)?.into(),
+        };
+        let is_error_event = matches!(key, EventKey::String(name) if name.as_ref() == "error");
+        let args: Vec<Value<'js>> = args.iter().cloned().collect();
+        let emitter = this.0;
+
+        let on_rejected = Function::new(
+            ctx.clone(),
+            OnceFn::from(move |ctx, err| {
+                struct Args<'js>(Ctx<'js>, Value<'js>);
+                let Args(ctx, err) = Args(ctx, err);
+                let rejection_symbol =
+                    Symbol::for_description(&ctx, CAPTURE_REJECTION_SYMBOL_DESCRIPTION)?;
+                let handler: Option<Function> = emitter.get_optional(rejection_symbol)?;
+                if let Some(handler) = handler {
+                    let mut handler_args = vec![err, event];
+                    handler_args.extend(args);
+                    handler.call::<_, ()>((This(emitter), Rest(handler_args)))?;
+                } else if is_error_event {
+                    // an 'error' listener failing must not loop back into 'error'
+                    return Err(ctx.throw(err));
+                } else {
+                    Self::emit_str(This(emitter), &ctx, "error", vec![err], false)?;
+                }
+                Ok::<_, rsquickjs::Error>(())
+            }),
+        )?;
+
+        promise
+            .catch()?
+            .call::<_, ()>((This(promise.clone()), on_rejected))?;
+
+        Ok(())
+    }
+
     fn emit_str(
         this: This<Class<'js, Self>>,
         ctx: &Ctx<'js>,
@@ -400,17 +923,208 @@ where
         ctx: Ctx<'js>,
         event: Value<'js>,
         args: Rest<Value<'js>>,
-    ) -> Result<()> {
-        Self::do_emit(event, this, &ctx, args, false)
+    ) -> Result<bool> {
+        let key = EventKey::from_value(&ctx, event.clone())?;
+
+        if matches!(&key, EventKey::String(name) if name.as_ref() == "error") {
+            let error_monitor = Symbol::for_description(&ctx, ERROR_MONITOR_SYMBOL_DESCRIPTION)?;
+            Self::do_emit(
+                error_monitor.into_value(),
+                this.clone(),
+                &ctx,
+                Rest(args.0.clone()),
+                false,
+            )?;
+
+            if !this.borrow().has_listener_str("error") {
+                let err = args
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| Undefined.into_value(ctx.clone()));
+                if err.is_error() {
+                    return Err(ctx.throw(err));
+                }
+                let mut message = String::from("Unhandled error.");
+                if !err.is_undefined() {
+                    let desc: rsquickjs::Coerced<String> = err.get()?;
+                    message = [&message, " (", &desc.0, ")"].concat();
+                }
+                return Err(Exception::throw_message(&ctx, &message));
+            }
+        }
+
+        let had_listeners = has_key(this.borrow().get_event_list(), key);
+        Self::do_emit(event, this, &ctx, args, false)?;
+        Ok(had_listeners)
+    }
+
+    fn listeners(
+        this: This<OwnedBorrow<'js, Self>>,
+        ctx: Ctx<'js>,
+        event: Value<'js>,
+    ) -> Result<Vec<Function<'js>>> {
+        this.get_listeners(&ctx, event)
+    }
+
+    /// The listeners of `event`, with the ones added by `once` wrapped in a function
+    /// which removes them before calling them, the original being its `listener`.
+    fn raw_listeners(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        event: Value<'js>,
+    ) -> Result<Vec<Function<'js>>> {
+        let key = EventKey::from_value(&ctx, event.clone())?;
+        let events = this.borrow().get_event_list();
+        let mut events = events.write().or_throw(&ctx)?;
+        let Some((_, items)) = events.iter_mut().find(|(k, _)| k == &key) else {
+            return Ok(vec![]);
+        };
+        let mut listeners = Vec::with_capacity(items.len());
+        for item in items.iter_mut() {
+            let Some(callback) = item.callback.as_function() else {
+                continue;
+            };
+            if !item.once {
+                listeners.push(callback.clone());
+                continue;
+            }
+            let state = match &item.once_state {
+                Some(state) => state.clone(),
+                None => {
+                    let state =
+                        once_wrapper_state(&ctx, this.0.clone().into_value(), &event, callback)?;
+                    item.once_state = Some(state.clone());
+                    state
+                }
+            };
+            listeners.push(state.get("wrapFn")?);
+        }
+        Ok(listeners)
+    }
+
+    fn listener_count(
+        this: This<OwnedBorrow<'js, Self>>,
+        ctx: Ctx<'js>,
+        event: Value<'js>,
+        listener: Opt<Function<'js>>,
+    ) -> Result<usize> {
+        let listeners = this.get_listeners(&ctx, event)?;
+        Ok(match listener.0 {
+            Some(listener) => listeners.iter().filter(|cb| **cb == listener).count(),
+            None => listeners.len(),
+        })
+    }
+
+    fn remove_all_listeners(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        event: Opt<Value<'js>>,
+    ) -> Result<Class<'js, Self>> {
+        let events = this.borrow().get_event_list();
+        let removed = {
+            let mut events = events.write().or_throw(&ctx)?;
+            match event.0 {
+                Some(event) if !event.is_undefined() => {
+                    let key = EventKey::from_value(&ctx, event)?;
+                    match events.iter().position(|(k, _)| k == &key) {
+                        Some(index) => vec![events.remove(index).0],
+                        None => vec![],
+                    }
+                }
+                _ => events.drain(..).map(|(key, _)| key).collect(),
+            }
+        };
+        for key in removed {
+            this.borrow_mut().on_event_changed(key, false)?;
+        }
+        Ok(this.0)
     }
 
     fn evt_dispatch_event(
         this: This<Class<'js, Self>>,
         ctx: Ctx<'js>,
         event: Value<'js>,
-    ) -> Result<()> {
-        let event_type = event.get_optional("type")?.unwrap();
-        Self::do_emit(event_type, this, &ctx, Rest(vec![event]), false)
+    ) -> Result<bool> {
+        Self::dispatch_event(this, &ctx, event)
+    }
+
+    /// Dispatches an `Event` or `CustomEvent` to the listeners of its type, capture
+    /// listeners first. Returns `false` if a listener cancelled it.
+    fn dispatch_event(
+        this: This<Class<'js, Self>>,
+        ctx: &Ctx<'js>,
+        event: Value<'js>,
+    ) -> Result<bool> {
+        let target = this.0.clone().into_value();
+        let event_type = with_event(ctx, &event, |event| {
+            if event.dispatching {
+                return None;
+            }
+            event.begin_dispatch(target.clone());
+            Some(event.event_type.clone())
+        })?;
+        let Some(event_type) = event_type else {
+            let ex = DOMException::new_with_name(
+                ctx,
+                DOMExceptionName::InvalidStateError,
+                "The event is already being dispatched".into(),
+            )
+            .and_then(|ex| Class::instance(ctx.clone(), ex))?;
+            return Err(ctx.throw(ex.into_value()));
+        };
+
+        let key = EventKey::String(event_type.into());
+        let listeners: Vec<(Value<'js>, bool, bool, bool)> = {
+            let events = this.borrow().get_event_list();
+            let events = events.read().or_throw(ctx)?;
+            match events.iter().find(|(k, _)| k == &key) {
+                Some((_, items)) => items
+                    .iter()
+                    .map(|item| (item.callback.clone(), item.capture, item.once, item.passive))
+                    .collect(),
+                None => vec![],
+            }
+        };
+
+        'phases: for phase_capture in [true, false] {
+            for (callback, capture, once, passive) in &listeners {
+                if *capture != phase_capture {
+                    continue;
+                }
+                // listeners removed by an earlier listener are skipped
+                let matches =
+                    |item: &EventItem<'js>| item.callback == *callback && item.capture == *capture;
+                let registered = {
+                    let events = this.borrow().get_event_list();
+                    let events = events.read().or_throw(ctx)?;
+                    events
+                        .iter()
+                        .any(|(k, items)| k == &key && items.iter().any(matches))
+                };
+                if !registered {
+                    continue;
+                }
+                if *once && Self::remove_item(&this, ctx, &key, matches)? {
+                    this.borrow_mut().on_event_changed(key.clone(), false)?;
+                }
+
+                with_event(ctx, &event, |event| event.in_passive_listener = *passive)?;
+                let result = call_listener(ctx, callback, &target, &event);
+                with_event(ctx, &event, |event| event.in_passive_listener = false)?;
+                if let Err(err) = result.catch(ctx) {
+                    report_uncaught_error(ctx, err);
+                }
+
+                if with_event(ctx, &event, |event| event.stop_immediate_propagation)? {
+                    break 'phases;
+                }
+            }
+        }
+
+        with_event(ctx, &event, |event| {
+            event.end_dispatch();
+            !event.default_prevented
+        })
     }
 
     fn event_names(this: This<OwnedBorrow<'js, Self>>, ctx: Ctx<'js>) -> Result<Vec<Value<'js>>> {
@@ -438,12 +1152,81 @@ fn find_all_listeners<'js>(
     let events = events.read().unwrap();
     let items = events.iter().find(|(k, _)| k == &key);
     if let Some((_, callbacks)) = items {
-        callbacks.iter().map(|item| item.callback.clone()).collect()
+        callbacks
+            .iter()
+            .filter_map(|item| item.callback.as_function().cloned())
+            .collect()
     } else {
         vec![]
     }
 }
 
+fn is_once_wrapper<'js>(item: &EventItem<'js>, listener: &Value<'js>) -> bool {
+    item.once_state.as_ref().is_some_and(|state| {
+        state
+            .get::<_, Value>("wrapFn")
+            .is_ok_and(|wrap_fn| wrap_fn == *listener)
+    })
+}
+
+/// Creates the state of the wrapper of a `once` listener, like Node's `_onceWrap`. The
+/// wrapper is a bound JS function so the emitter it refers to stays visible to the GC.
+fn once_wrapper_state<'js>(
+    ctx: &Ctx<'js>,
+    target: Value<'js>,
+    event: &Value<'js>,
+    listener: &Function<'js>,
+) -> Result<Object<'js>> {
+    let state = Object::new(ctx.clone())?;
+    state.set("fired", false)?;
+    state.set("target", target)?;
+    state.set("type", event.clone())?;
+    state.set("listener", listener.clone())?;
+    let wrapper = Function::new(ctx.clone(), once_wrapper)?;
+    let bind: Function = wrapper.get("bind")?;
+    let wrap_fn: Function = bind.call((This(wrapper), state.clone()))?;
+    wrap_fn.set("listener", listener.clone())?;
+    state.set("wrapFn", wrap_fn)?;
+    Ok(state)
+}
+
+fn once_wrapper<'js>(state: This<Object<'js>>, args: Rest<Value<'js>>) -> Result<Value<'js>> {
+    let ctx = state.ctx().clone();
+    if state.get::<_, bool>("fired")? {
+        return Ok(Undefined.into_value(ctx));
+    }
+    state.set("fired", true)?;
+    let target: Object = state.get("target")?;
+    let remove_listener: Function = target.get("removeListener")?;
+    remove_listener.call::<_, ()>((
+        This(target.clone()),
+        state.get::<_, Value>("type")?,
+        state.get::<_, Function>("wrapFn")?,
+    ))?;
+    let listener: Function = state.get("listener")?;
+    listener.call((This(target), args))
+}
+
+/// Calls an `EventTarget` listener, either a function or an object's `handleEvent`
+fn call_listener<'js>(
+    ctx: &Ctx<'js>,
+    callback: &Value<'js>,
+    target: &Value<'js>,
+    event: &Value<'js>,
+) -> Result<()> {
+    if let Some(function) = callback.as_function() {
+        return function.call((This(target.clone()), event.clone()));
+    }
+    let handle_event: Option<Function> = callback.get_optional("handleEvent")?;
+    match handle_event {
+        Some(handle_event) => handle_event.call((This(callback.clone()), event.clone())),
+        None => Err(Exception::throw_type(
+            ctx,
+            "The listener has no handleEvent method",
+        )),
+    }
+}
+
 fn has_key<'js>(event_list: Arc<RwLock<EventList<'js>>>, key: EventKey<'js>) -> bool {
     event_list.read().unwrap().iter().any(|(k, _)| k == &key)
 }
@@ -458,6 +1241,12 @@ pub struct EventsModule;
 impl ModuleDef for EventsModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare(stringify!(EventEmitter))?;
+        declare.declare("once")?;
+        declare.declare("on")?;
+        declare.declare("errorMonitor")?;
+        declare.declare("captureRejectionSymbol")?;
+        declare.declare("getMaxListeners")?;
+        declare.declare("setMaxListeners")?;
         declare.declare("default")?;
 
         Ok(())
@@ -467,7 +1256,31 @@ impl ModuleDef for EventsModule {
         let ctor = Class::<EventEmitter>::create_constructor(ctx)?
             .expect("Can't create EventEmitter constructor");
         ctor.set(stringify!(EventEmitter), ctor.clone())?;
+
+        let helpers: Object = crate::utils::snapshot::eval(ctx, "events", EVENTS_HELPERS_SOURCE)?;
+        let once: Function = helpers.get("once")?;
+        let on: Function = helpers.get("on")?;
+        let error_monitor = Symbol::for_description(ctx, ERROR_MONITOR_SYMBOL_DESCRIPTION)?;
+        let capture_rejection_symbol =
+            Symbol::for_description(ctx, CAPTURE_REJECTION_SYMBOL_DESCRIPTION)?;
+        let get_max_listeners = Function::new(ctx.clone(), get_max_listeners)?;
+        let set_max_listeners = Function::new(ctx.clone(), set_max_listeners)?;
+
+        ctor.set("once", once.clone())?;
+        ctor.set("on", on.clone())?;
+        ctor.set("errorMonitor", error_monitor.clone())?;
+        ctor.set("captureRejectionSymbol", capture_rejection_symbol.clone())?;
+        ctor.set("getMaxListeners", get_max_listeners.clone())?;
+        ctor.set("setMaxListeners", set_max_listeners.clone())?;
+        define_default_accessors(&ctor)?;
+
         exports.export(stringify!(EventEmitter), ctor.clone())?;
+        exports.export("once", once)?;
+        exports.export("on", on)?;
+        exports.export("errorMonitor", error_monitor)?;
+        exports.export("captureRejectionSymbol", capture_rejection_symbol)?;
+        exports.export("getMaxListeners", get_max_listeners)?;
+        exports.export("setMaxListeners", set_max_listeners)?;
         exports.export("default", ctor)?;
 
         EventEmitter::add_event_emitter_prototype(ctx)?;
@@ -476,6 +1289,74 @@ impl ModuleDef for EventsModule {
     }
 }
 
+fn define_default_accessors(ctor: &Constructor<'_>) -> Result<()> {
+    ctor.prop(
+        "defaultMaxListeners",
+        Accessor::new(get_default_max_listeners, set_default_max_listeners).enumerable(),
+    )?;
+    ctor.prop(
+        "captureRejections",
+        Accessor::new(
+            get_default_capture_rejections,
+            set_default_capture_rejections,
+        )
+        .enumerable(),
+    )?;
+    Ok(())
+}
+
+fn get_default_max_listeners(ctx: Ctx<'_>) -> usize {
+    event_defaults(&ctx).max_listeners
+}
+
+fn set_default_max_listeners(ctx: Ctx<'_>, n: f64) -> Result<()> {
+    let max_listeners = to_max_listeners(&ctx, n)?;
+    update_event_defaults(&ctx, |defaults| defaults.max_listeners = max_listeners);
+    Ok(())
+}
+
+fn get_default_capture_rejections(ctx: Ctx<'_>) -> bool {
+    event_defaults(&ctx).capture_rejections
+}
+
+fn set_default_capture_rejections(ctx: Ctx<'_>, value: bool) {
+    update_event_defaults(&ctx, |defaults| defaults.capture_rejections = value);
+}
+
+fn get_max_listeners<'js>(ctx: Ctx<'js>, emitter: Value<'js>) -> Result<usize> {
+    if let Ok(emitter) = Class::<EventEmitter>::from_value(&emitter) {
+        return Ok(emitter.borrow().get_max_listeners(ctx));
+    }
+    if let Ok(target) = Class::<EventTarget>::from_value(&emitter) {
+        return Ok(target.borrow().max_listeners(&ctx));
+    }
+    Err(Exception::throw_type(
+        &ctx,
+        "The \"emitter\" argument must be an instance of EventEmitter or EventTarget",
+    ))
+}
+
+fn set_max_listeners<'js>(ctx: Ctx<'js>, n: f64, emitters: Rest<Value<'js>>) -> Result<()> {
+    let max_listeners = to_max_listeners(&ctx, n)?;
+    if emitters.is_empty() {
+        update_event_defaults(&ctx, |defaults| defaults.max_listeners = max_listeners);
+        return Ok(());
+    }
+    for emitter in emitters.iter() {
+        if let Ok(emitter) = Class::<EventEmitter>::from_value(emitter) {
+            emitter.borrow_mut().max_listeners = Some(max_listeners);
+        } else if let Ok(target) = Class::<EventTarget>::from_value(emitter) {
+            target.borrow_mut().max_listeners = Some(max_listeners);
+        } else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "The \"eventTargets\" argument must be an instance of EventEmitter or EventTarget",
+            ));
+        }
+    }
+    Ok(())
+}
+
 impl From<EventsModule> for ModuleInfo<EventsModule> {
     fn from(val: EventsModule) -> Self {
         ModuleInfo {
@@ -494,5 +1375,285 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     EventTarget::add_event_target_prototype(ctx)?;
 
+    let event_proto =
+        Class::<Event>::prototype(ctx)?.or_throw_msg(ctx, "Prototype for Event not found")?;
+    let event_ctor: Object = globals.get(stringify!(Event))?;
+    event::add_phase_constants(&event_proto)?;
+    event::add_phase_constants(&event_ctor)?;
+
+    let custom_event_proto = Class::<CustomEvent>::prototype(ctx)?
+        .or_throw_msg(ctx, "Prototype for CustomEvent not found")?;
+    custom_event_proto.set_prototype(Some(&event_proto))?;
+    let custom_event_ctor: Object = globals.get(stringify!(CustomEvent))?;
+    custom_event_ctor.set_prototype(Some(&event_ctor))?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{
+        primordials::{BasePrimordials, Primordial},
+        test::{call_test, test_async_with, ModuleEvaluator},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_emitter_helpers() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<EventsModule>(ctx.clone(), "events")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { EventEmitter, once, on, errorMonitor } from 'events';
+                        export async function test() {
+                            const results = [];
+                            const emitter = new EventEmitter();
+
+                            Promise.resolve().then(() => emitter.emit('ready', 1, 2));
+                            results.push((await once(emitter, 'ready')).join(','));
+
+                            emitter.on(errorMonitor, () => results.push('monitor'));
+                            emitter.on('error', (err) => results.push(err.message));
+                            emitter.emit('error', new Error('boom'));
+
+                            const listener = (value) => results.push('raw:' + value);
+                            emitter.once('raw', listener);
+                            const [wrapper] = emitter.rawListeners('raw');
+                            results.push(wrapper !== listener && wrapper.listener === listener);
+                            results.push(emitter.rawListeners('raw')[0] === wrapper);
+                            results.push(emitter.listeners('raw')[0] === listener);
+                            wrapper(1);
+                            wrapper(2);
+                            results.push(emitter.listenerCount('raw'));
+                            emitter.once('raw', listener);
+                            emitter.removeListener('raw', emitter.rawListeners('raw')[0]);
+                            results.push(emitter.emit('raw'));
+
+                            const iterator = on(emitter, 'tick');
+                            emitter.emit('tick', 'a');
+                            emitter.emit('tick', 'b');
+                            for await (const [value] of iterator) {
+                                results.push(value);
+                                if (value === 'b') break;
+                            }
+                            results.push(emitter.listenerCount('tick'));
+
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "1,2|monitor|boom|true|true|true|raw:1|0|false|a|b|0"
+                );
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_event_target_dispatch() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        export async function test() {
+                            const results = [];
+                            const target = new EventTarget();
+                            const listener = (e) => results.push('bubble:' + e.eventPhase);
+                            target.addEventListener('ping', listener);
+                            target.addEventListener('ping', listener);
+                            target.addEventListener('ping', () => results.push('capture'), true);
+                            target.addEventListener('ping', () => results.push('once'), { once: true });
+                            target.addEventListener('ping', {
+                                handleEvent(e) {
+                                    results.push(e.composedPath()[0] === target);
+                                },
+                            });
+                            target.addEventListener('ping', (e) => e.preventDefault(), { passive: true });
+                            target.dispatchEvent(new Event('ping'));
+                            target.removeEventListener('ping', listener);
+                            results.push(target.dispatchEvent(new Event('ping', { cancelable: true })));
+
+                            // any EventTarget with an `aborted` flag works as a signal
+                            const signal = new EventTarget();
+                            signal.aborted = false;
+                            target.addEventListener('pong', () => results.push('pong'), { signal });
+                            target.dispatchEvent(new Event('pong'));
+                            signal.dispatchEvent(new Event('abort'));
+                            target.dispatchEvent(new Event('pong'));
+
+                            target.addEventListener('cancel', (e) => {
+                                e.preventDefault();
+                                e.stopImmediatePropagation();
+                            });
+                            target.addEventListener('cancel', () => results.push('unreachable'));
+                            const custom = new CustomEvent('cancel', { cancelable: true, detail: 42 });
+                            results.push(target.dispatchEvent(custom));
+                            results.push(custom instanceof Event, custom.detail, custom.type);
+                            results.push(custom.target === target, custom.currentTarget, custom.eventPhase);
+
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "capture|bubble:2|once|true|capture|true|true|pong|false|true|42|cancel|true|null|0"
+                );
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_unhandled_error_event_throws() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<EventsModule>(ctx.clone(), "events")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { EventEmitter } from 'events';
+                        export async function test() {
+                            const emitter = new EventEmitter();
+                            try {
+                                emitter.emit('error', new Error('unhandled'));
+                            } catch (err) {
+                                return err.message;
+                            }
+                            return 'no error';
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(result, "unhandled");
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_max_listeners_warning() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+                crate::process::init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<EventsModule>(ctx.clone(), "events")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { EventEmitter } from 'events';
+                        export async function test() {
+                            const results = [];
+                            const emitter = new EventEmitter();
+                            process.on('warning', (warning) => {
+                                const { name, count, type, message } = warning;
+                                results.push([name, count, type, warning.emitter === emitter, message].join(':'));
+                            });
+                            emitter.setMaxListeners(1);
+                            emitter.on('tick', () => {});
+                            emitter.on('tick', () => {});
+                            emitter.on('tick', () => {});
+                            process.emitWarning('custom', 'CustomWarning');
+                            results.push('sync');
+                            await null;
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "sync|MaxListenersExceededWarning:2:tick:true:Possible EventEmitter memory leak detected. 2 tick listeners added to [EventEmitter]. MaxListeners is 1. Use emitter.setMaxListeners() to increase limit|CustomWarning:::false:custom"
+                );
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_defaults_are_per_context() {
+        for (source, expected) in [
+            (
+                r#"
+                    import { EventEmitter, getMaxListeners, setMaxListeners } from 'events';
+                    export async function test() {
+                        EventEmitter.captureRejections = true;
+                        setMaxListeners(3);
+                        const emitter = new EventEmitter();
+                        const target = new EventTarget();
+                        setMaxListeners(5, target);
+                        return [
+                            getMaxListeners(emitter),
+                            EventEmitter.captureRejections,
+                            getMaxListeners(target),
+                        ].join(',');
+                    }
+                "#,
+                "3,true,5",
+            ),
+            (
+                r#"
+                    import { EventEmitter } from 'events';
+                    export async function test() {
+                        return EventEmitter.defaultMaxListeners + ',' + EventEmitter.captureRejections;
+                    }
+                "#,
+                "10,false",
+            ),
+        ] {
+            test_async_with(move |ctx| {
+                Box::pin(async move {
+                    BasePrimordials::init(&ctx).unwrap();
+                    init(&ctx).unwrap();
+                    ModuleEvaluator::eval_rust::<EventsModule>(ctx.clone(), "events")
+                        .await
+                        .unwrap();
+
+                    let module = ModuleEvaluator::eval_js(ctx.clone(), "test", source)
+                        .await
+                        .unwrap();
+                    let result = call_test::<String, _>(&ctx, &module, ()).await;
+                    assert_eq!(result, expected);
+                })
+            })
+            .await;
+        }
+    }
+}
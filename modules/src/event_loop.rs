@@ -0,0 +1,148 @@
+//! When a script is done.
+//!
+//! Like Node.js, the runtime keeps going while referenced work is pending: promise jobs,
+//! futures spawned on the runtime and the timers that are not unref'd. Futures spawned
+//! with [`CtxExtension::spawn_unref`](crate::utils::ctx::CtxExtension::spawn_unref)
+//! don't count, they are dropped once nothing else is left. `process.exit()` ends the
+//! process right away.
+use std::{
+    cell::Cell,
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::Arc,
+};
+
+use rsquickjs::{AsyncContext, AsyncRuntime, Ctx, JsLifetime, Result};
+use tokio::{
+    select,
+    sync::{watch, Notify},
+};
+
+use crate::utils::result::ResultExt;
+
+/// The unreferenced futures of a context and the signal dropping them
+struct EventLoop {
+    unref_tasks: Cell<usize>,
+    shutdown: watch::Sender<bool>,
+    /// Notified when the referenced work left may have run out
+    work_changed: Arc<Notify>,
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        Self {
+            unref_tasks: Cell::new(0),
+            shutdown: watch::channel(false).0,
+            work_changed: Arc::new(Notify::new()),
+        }
+    }
+}
+
+unsafe impl<'js> JsLifetime<'js> for EventLoop {
+    type Changed<'to> = EventLoop;
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let _ = ctx.store_userdata(EventLoop::default());
+    Ok(())
+}
+
+/// Counts an unreferenced future, returning the receiver that tells it to stop
+pub(crate) fn track_unref(ctx: &Ctx<'_>) -> Result<watch::Receiver<bool>> {
+    if ctx.userdata::<EventLoop>().is_none() {
+        init(ctx)?;
+    }
+    let event_loop = ctx.userdata::<EventLoop>().or_throw(ctx)?;
+    event_loop.unref_tasks.set(event_loop.unref_tasks.get() + 1);
+    event_loop.work_changed.notify_one();
+    Ok(event_loop.shutdown.subscribe())
+}
+
+pub(crate) fn untrack_unref(ctx: &Ctx<'_>) {
+    if let Some(event_loop) = ctx.userdata::<EventLoop>() {
+        let unref_tasks = event_loop.unref_tasks.get();
+        event_loop.unref_tasks.set(unref_tasks.saturating_sub(1));
+        event_loop.work_changed.notify_one();
+    }
+}
+
+fn unref_tasks(ctx: &Ctx<'_>) -> usize {
+    ctx.userdata::<EventLoop>()
+        .map_or(0, |event_loop| event_loop.unref_tasks.get())
+}
+
+fn stop_unref(ctx: &Ctx<'_>) {
+    if let Some(event_loop) = ctx.userdata::<EventLoop>() {
+        event_loop.shutdown.send_replace(true);
+    }
+}
+
+/// Whether anything but unreferenced futures is left to run
+async fn has_referenced_work(runtime: &AsyncRuntime, context: &AsyncContext) -> bool {
+    let metrics = runtime.metrics().await;
+    let unref_tasks = context.with(|ctx| unref_tasks(&ctx)).await;
+    metrics.job_pending || metrics.background_tasks > unref_tasks
+}
+
+fn work_changed(ctx: &Ctx<'_>) -> Result<Arc<Notify>> {
+    if ctx.userdata::<EventLoop>().is_none() {
+        init(ctx)?;
+    }
+    let event_loop = ctx.userdata::<EventLoop>().or_throw(ctx)?;
+    Ok(event_loop.work_changed.clone())
+}
+
+/// Runs the runtime until it is idle, notifying `work_changed` after every step as that is
+/// where spawned futures complete and queued promise jobs run
+async fn idle(runtime: &AsyncRuntime, work_changed: &Notify) {
+    let mut idle = pin!(runtime.idle());
+    poll_fn(|cx| {
+        let poll = idle.as_mut().poll(cx);
+        work_changed.notify_one();
+        poll
+    })
+    .await
+}
+
+async fn until_only_unref_left(
+    runtime: &AsyncRuntime,
+    context: &AsyncContext,
+    work_changed: &Notify,
+) {
+    while has_referenced_work(runtime, context).await {
+        work_changed.notified().await;
+    }
+}
+
+#[cfg(feature = "event")]
+async fn emit_before_exit(context: &AsyncContext) -> bool {
+    context
+        .with(|ctx| crate::process::emit_before_exit(&ctx))
+        .await
+}
+
+#[cfg(not(feature = "event"))]
+async fn emit_before_exit(_context: &AsyncContext) -> bool {
+    false
+}
+
+/// Runs the work spawned on `runtime` until only unreferenced work is left, then drops
+/// that. `beforeExit` is emitted on `process` each time the work runs out, and the
+/// runtime keeps going while its listeners schedule more.
+pub async fn run_until_drained(runtime: &AsyncRuntime, context: &AsyncContext) {
+    let Ok(work_changed) = context.with(|ctx| work_changed(&ctx)).await else {
+        runtime.idle().await;
+        return;
+    };
+    loop {
+        select! {
+            _ = idle(runtime, &work_changed) => {}
+            _ = until_only_unref_left(runtime, context, &work_changed) => {}
+        }
+        if !(emit_before_exit(context).await && has_referenced_work(runtime, context).await) {
+            break;
+        }
+    }
+    context.with(|ctx| stop_unref(&ctx)).await;
+    runtime.idle().await;
+}
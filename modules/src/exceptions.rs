@@ -1,4 +1,5 @@
 use crate::utils::{
+    ctx::report_uncaught_error,
     options::Undefined,
     primordials::{BasePrimordials, Primordial},
 };
@@ -7,8 +8,8 @@ use rsquickjs::{
     class::JsClass,
     function::{Constructor, Opt},
     object::Property,
-    prelude::This,
-    Class, Coerced, Ctx, Exception, IntoJs, Object, Result, Value,
+    prelude::{Func, This},
+    CaughtError, Class, Coerced, Ctx, Exception, IntoJs, Object, Result, Value,
 };
 
 #[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
@@ -289,5 +290,12 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     let error_prototype = &BasePrimordials::get(ctx)?.prototype_error;
     dom_ex_proto.set_prototype(Some(error_prototype))?;
 
+    globals.set("reportError", Func::from(report_error))?;
+
     Ok(())
 }
+
+/// Reports an error like an uncaught exception, without throwing it
+fn report_error<'js>(ctx: Ctx<'js>, error: Value<'js>) {
+    report_uncaught_error(&ctx, CaughtError::Value(error));
+}
@@ -171,7 +171,7 @@ impl<'js> Body<'js> {
             BodyVariant::Provided(provided) => {
                 if let Some(blob) = provided.as_object().and_then(Class::<Blob>::from_object) {
                     let blob = blob.borrow();
-                    blob.get_bytes()
+                    blob.get_bytes().or_throw(ctx)?
                 } else {
                     let bytes = ObjectBytes::from(ctx, provided)?;
                     bytes.try_into().or_throw(ctx)?
@@ -0,0 +1,521 @@
+use std::{
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hyper::Uri;
+use rsquickjs::{prelude::Opt, Array, Class, Ctx, Exception, JsLifetime, Object, Result};
+
+use crate::utils::primordials::{BasePrimordials, Primordial};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    /// Expiry in seconds since the unix epoch, `None` for session cookies
+    expires: Option<i64>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<String>,
+}
+
+impl Cookie {
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        if self.secure && !secure {
+            return false;
+        }
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain_ok && path_match(path, &self.path)
+    }
+}
+
+/// Per-context cookie store consulted by `fetch` when `credentials: "include"` or an explicit
+/// `cookieJar` is passed. Cookies are kept in creation order as required by RFC 6265 5.4.
+#[rsquickjs::class]
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime, Default)]
+pub struct CookieJar {
+    #[qjs(skip_trace)]
+    cookies: Vec<Cookie>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl CookieJar {
+    #[qjs(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_cookies<'js>(&mut self, ctx: Ctx<'js>, url: Opt<String>) -> Result<Array<'js>> {
+        let now = unix_now();
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let cookies: Vec<&Cookie> = match url.0 {
+            Some(url) => {
+                let uri = parse_url(&ctx, &url)?;
+                self.matching_cookies(&uri)
+            }
+            None => self.cookies.iter().collect(),
+        };
+
+        let date_ctor = BasePrimordials::get(&ctx)?.constructor_date.clone();
+        let array = Array::new(ctx.clone())?;
+        for (i, cookie) in cookies.into_iter().enumerate() {
+            let obj = Object::new(ctx.clone())?;
+            obj.set("name", cookie.name.as_str())?;
+            obj.set("value", cookie.value.as_str())?;
+            obj.set("domain", cookie.domain.as_str())?;
+            obj.set("hostOnly", cookie.host_only)?;
+            obj.set("path", cookie.path.as_str())?;
+            if let Some(expires) = cookie.expires {
+                let date: Object = date_ctor.construct(((expires as f64) * 1000.0,))?;
+                obj.set("expires", date)?;
+            }
+            obj.set("secure", cookie.secure)?;
+            obj.set("httpOnly", cookie.http_only)?;
+            if let Some(same_site) = &cookie.same_site {
+                obj.set("sameSite", same_site.as_str())?;
+            }
+            array.set(i, obj)?;
+        }
+        Ok(array)
+    }
+
+    pub fn get_cookie_string(&mut self, ctx: Ctx<'_>, url: String) -> Result<String> {
+        let uri = parse_url(&ctx, &url)?;
+        Ok(self.cookie_header(&uri).unwrap_or_default())
+    }
+
+    pub fn set_cookie(&mut self, ctx: Ctx<'_>, set_cookie: String, url: String) -> Result<bool> {
+        let uri = parse_url(&ctx, &url)?;
+        Ok(self.store(&uri, &set_cookie, unix_now()))
+    }
+
+    pub fn delete_cookie(&mut self, name: String, domain: Opt<String>) -> bool {
+        let domain = domain.0.map(|domain| canonical_domain(&domain));
+        let len = self.cookies.len();
+        self.cookies.retain(|cookie| {
+            cookie.name != name || matches!(&domain, Some(domain) if *domain != cookie.domain)
+        });
+        len != self.cookies.len()
+    }
+
+    pub fn clear(&mut self, domain: Opt<String>) {
+        match domain.0 {
+            Some(domain) => {
+                let domain = canonical_domain(&domain);
+                self.cookies
+                    .retain(|cookie| !domain_match(&cookie.domain, &domain));
+            }
+            None => self.cookies.clear(),
+        }
+    }
+
+    #[qjs(get)]
+    pub fn size(&self) -> usize {
+        let now = unix_now();
+        self.cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .count()
+    }
+}
+
+impl CookieJar {
+    /// Value for the `cookie` request header, if any stored cookie applies to `uri`.
+    pub(crate) fn cookie_header(&mut self, uri: &Uri) -> Option<String> {
+        let now = unix_now();
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let mut cookies = self.matching_cookies(uri);
+        if cookies.is_empty() {
+            return None;
+        }
+        // Longer paths first, stable sort keeps creation order otherwise
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+
+        let mut header = String::new();
+        for cookie in cookies {
+            if !header.is_empty() {
+                header.push_str("; ");
+            }
+            if !cookie.name.is_empty() {
+                header.push_str(&cookie.name);
+                header.push('=');
+            }
+            header.push_str(&cookie.value);
+        }
+        Some(header)
+    }
+
+    /// Stores every `set-cookie` header of a response received from `uri`.
+    pub(crate) fn store_response<'a>(
+        &mut self,
+        uri: &Uri,
+        set_cookies: impl Iterator<Item = &'a str>,
+    ) {
+        let now = unix_now();
+        for set_cookie in set_cookies {
+            self.store(uri, set_cookie, now);
+        }
+    }
+
+    fn matching_cookies(&self, uri: &Uri) -> Vec<&Cookie> {
+        let Some(host) = uri.host() else {
+            return Vec::new();
+        };
+        let host = canonical_domain(host);
+        let secure = uri.scheme_str() == Some("https");
+        self.cookies
+            .iter()
+            .filter(|cookie| cookie.matches(&host, uri.path(), secure))
+            .collect()
+    }
+
+    fn store(&mut self, uri: &Uri, set_cookie: &str, now: i64) -> bool {
+        let Some(cookie) = parse_set_cookie(uri, set_cookie, now) else {
+            return false;
+        };
+
+        let existing = self.cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        });
+        let expired = cookie.is_expired(now);
+        match existing {
+            Some(index) if expired => {
+                self.cookies.remove(index);
+            }
+            Some(index) => self.cookies[index] = cookie,
+            None if expired => {}
+            None => self.cookies.push(cookie),
+        }
+        true
+    }
+}
+
+/// Jar used by `fetch` for `credentials: "include"` when no `cookieJar` option is given.
+pub(crate) struct DefaultCookieJar<'js>(pub Class<'js, CookieJar>);
+
+unsafe impl<'js> JsLifetime<'js> for DefaultCookieJar<'js> {
+    type Changed<'to> = DefaultCookieJar<'to>;
+}
+
+impl<'js> DefaultCookieJar<'js> {
+    pub fn get(ctx: &Ctx<'js>) -> Result<Class<'js, CookieJar>> {
+        if let Some(jar) = ctx.userdata::<Self>() {
+            return Ok(jar.0.clone());
+        }
+        let jar = Class::instance(ctx.clone(), CookieJar::default())?;
+        let _ = ctx.store_userdata(Self(jar.clone()));
+        Ok(jar)
+    }
+}
+
+fn parse_url(ctx: &Ctx<'_>, url: &str) -> Result<Uri> {
+    let uri = url
+        .parse::<Uri>()
+        .map_err(|_| Exception::throw_type(ctx, &["Invalid URL: ", url].concat()))?;
+    if uri.host().is_none() {
+        return Err(Exception::throw_type(ctx, &["Invalid URL: ", url].concat()));
+    }
+    Ok(uri)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn canonical_domain(domain: &str) -> String {
+    domain
+        .trim_start_matches('.')
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase()
+}
+
+// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.len() > domain.len()
+        && host.ends_with(domain)
+        && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+        && host.parse::<IpAddr>().is_err()
+}
+
+/// Whether `domain` is listed in the Public Suffix List, like `com` or `github.io`, so
+/// a cookie for it would be shared by unrelated sites.
+fn is_public_suffix(domain: &str) -> bool {
+    psl::suffix(domain.as_bytes())
+        .is_some_and(|suffix| suffix.is_known() && suffix.as_bytes() == domain.as_bytes())
+}
+
+// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    if request_path == cookie_path {
+        return true;
+    }
+    request_path.starts_with(cookie_path)
+        && (cookie_path.ends_with('/')
+            || request_path.as_bytes().get(cookie_path.len()) == Some(&b'/'))
+}
+
+fn default_path(uri: &Uri) -> String {
+    let path = uri.path();
+    if !path.starts_with('/') {
+        return "/".into();
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/".into(),
+        Some(index) => path[..index].into(),
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc6265#section-5.2
+fn parse_set_cookie(uri: &Uri, set_cookie: &str, now: i64) -> Option<Cookie> {
+    let host = canonical_domain(uri.host()?);
+    let secure_origin = uri.scheme_str() == Some("https");
+
+    let mut parts = set_cookie.split(';');
+    let pair = parts.next()?;
+    let (name, value) = match pair.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => ("", pair.trim()),
+    };
+    if name.is_empty() && value.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.into(),
+        value: value.into(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(uri),
+        expires: None,
+        secure: false,
+        http_only: false,
+        same_site: None,
+    };
+    let mut max_age = None;
+    let mut expires = None;
+
+    for attr in parts {
+        let (key, value) = match attr.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (attr.trim(), ""),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "expires" => expires = parse_cookie_date(value).or(expires),
+            "max-age" => {
+                if let Ok(seconds) = value.parse::<i64>() {
+                    max_age = Some(if seconds <= 0 {
+                        i64::MIN
+                    } else {
+                        now.saturating_add(seconds)
+                    });
+                }
+            }
+            "domain" if !value.is_empty() => {
+                let domain = canonical_domain(value);
+                if !domain_match(&host, &domain) {
+                    return None;
+                }
+                // https://www.rfc-editor.org/rfc/rfc6265#section-5.3 step 5
+                if is_public_suffix(&domain) {
+                    if domain != host {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = true;
+                    continue;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.into(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => cookie.same_site = Some(value.into()),
+            _ => {}
+        }
+    }
+
+    if cookie.secure && !secure_origin {
+        return None;
+    }
+    if cookie.name.starts_with("__Secure-") && !cookie.secure {
+        return None;
+    }
+    if cookie.name.starts_with("__Host-")
+        && (!cookie.secure || !cookie.host_only || cookie.path != "/")
+    {
+        return None;
+    }
+
+    cookie.expires = max_age.or(expires);
+    Some(cookie)
+}
+
+// https://www.rfc-editor.org/rfc/rfc6265#section-5.1.1
+fn parse_cookie_date(date: &str) -> Option<i64> {
+    let is_delimiter = |c: char| matches!(c, '\x09' | '\x20'..='\x2F' | '\x3B'..='\x40' | '\x5B'..='\x60' | '\x7B'..='\x7E');
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    for token in date.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = parse_digits(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(parsed) = parse_month(token) {
+                month = Some(parsed);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = parse_digits(token, 2, 4) {
+                year = Some(parsed);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    match year {
+        70..=99 => year += 1900,
+        0..=69 => year += 2000,
+        _ => {}
+    }
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month, day as i64);
+    Some(days * 86_400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64)
+}
+
+fn parse_digits(token: &str, min: usize, max: usize) -> Option<u32> {
+    let len = token.bytes().take_while(u8::is_ascii_digit).count();
+    if len < min || len > max {
+        return None;
+    }
+    token[..len].parse().ok()
+}
+
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut fields = token.splitn(3, ':');
+    let hour = fields.next().filter(|f| (1..=2).contains(&f.len()))?;
+    let minute = fields.next().filter(|f| (1..=2).contains(&f.len()))?;
+    let second = parse_digits(fields.next()?, 1, 2)?;
+    Some((hour.parse().ok()?, minute.parse().ok()?, second))
+}
+
+fn parse_month(token: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|month| *month == prefix)
+        .map(|index| index as i64 + 1)
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cookie_date() {
+        assert_eq!(
+            parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(1_445_412_480)
+        );
+        assert_eq!(
+            parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"),
+            Some(1_445_412_480)
+        );
+        assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_cookie_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let mut jar = CookieJar::default();
+        let uri: Uri = "https://www.example.com/app/login".parse().unwrap();
+        jar.store_response(
+            &uri,
+            [
+                "session=abc; Path=/; HttpOnly",
+                "theme=dark; Domain=example.com",
+                "tracking=1; Domain=other.com",
+                "shared=1; Domain=com",
+                "secret=1; Secure; Path=/",
+            ]
+            .into_iter(),
+        );
+        assert_eq!(jar.cookies.len(), 3);
+
+        let same: Uri = "https://www.example.com/app/home".parse().unwrap();
+        assert_eq!(
+            jar.cookie_header(&same).as_deref(),
+            Some("theme=dark; session=abc; secret=1")
+        );
+
+        let sub: Uri = "http://api.example.com/".parse().unwrap();
+        assert_eq!(jar.cookie_header(&sub), None);
+        let sub: Uri = "http://api.example.com/app/x".parse().unwrap();
+        assert_eq!(jar.cookie_header(&sub).as_deref(), Some("theme=dark"));
+
+        let suffix: Uri = "https://github.io/".parse().unwrap();
+        jar.store_response(&suffix, ["site=1; Domain=github.io"].into_iter());
+        let pages: Uri = "https://user.github.io/".parse().unwrap();
+        assert_eq!(jar.cookie_header(&pages), None);
+        assert_eq!(jar.cookie_header(&suffix).as_deref(), Some("site=1"));
+        jar.clear(Opt(Some("github.io".into())));
+
+        jar.store_response(&uri, ["session=; Max-Age=0; Path=/"].into_iter());
+        assert_eq!(
+            jar.cookie_header(&same).as_deref(),
+            Some("theme=dark; secret=1")
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{error::Error as StdError, io};
+
+use rsquickjs::{Ctx, Error, Object, Result, Value};
+
+use crate::http::dns_cache::DnsLookupError;
+use crate::utils::primordials::{BasePrimordials, Primordial};
+
+const FETCH_FAILED: &str = "fetch failed";
+
+/// Details attached as `cause` of the `TypeError` thrown by `fetch`, mirroring undici.
+#[derive(Debug, PartialEq)]
+pub(crate) struct FetchErrorCause {
+    pub code: &'static str,
+    pub message: String,
+    pub hostname: Option<String>,
+    pub syscall: Option<&'static str>,
+}
+
+impl FetchErrorCause {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            hostname: None,
+            syscall: None,
+        }
+    }
+
+    pub fn from_error(err: &(dyn StdError + 'static)) -> Self {
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if let Some(cause) = Self::from_known_error(err) {
+                return cause;
+            }
+            if let Some(io_err) = err.downcast_ref::<io::Error>() {
+                // Custom io errors hide the wrapped error from `source()`
+                if let Some(cause) = io_err.get_ref().and_then(|inner| {
+                    let inner: &(dyn StdError + 'static) = inner;
+                    Self::from_known_error(inner)
+                }) {
+                    return cause;
+                }
+                if let Some(code) = io_error_code(io_err.kind()) {
+                    let mut cause = Self::new(code, io_err.to_string());
+                    cause.syscall = Some("connect");
+                    return cause;
+                }
+            }
+            if let Some(hyper_err) = err.downcast_ref::<hyper::Error>() {
+                if hyper_err.is_timeout() {
+                    return Self::new("UND_ERR_HEADERS_TIMEOUT", hyper_err.to_string());
+                }
+                if hyper_err.is_incomplete_message() || hyper_err.is_closed() {
+                    return Self::new("UND_ERR_SOCKET", hyper_err.to_string());
+                }
+            }
+            source = err.source();
+        }
+        Self::new("UND_ERR", err.to_string())
+    }
+
+    fn from_known_error(err: &(dyn StdError + 'static)) -> Option<Self> {
+        if let Some(dns_err) = err.downcast_ref::<DnsLookupError>() {
+            let mut cause = Self::new("ENOTFOUND", dns_err.to_string());
+            cause.hostname = Some(dns_err.hostname.clone());
+            cause.syscall = Some("getaddrinfo");
+            return Some(cause);
+        }
+        if let Some(tls_err) = err.downcast_ref::<rustls::Error>() {
+            return Some(Self::new(tls_error_code(tls_err), tls_err.to_string()));
+        }
+        None
+    }
+
+    pub fn throw<'js>(self, ctx: &Ctx<'js>) -> Error {
+        match self.to_type_error(ctx) {
+            Ok(err) => ctx.throw(err),
+            Err(err) => err,
+        }
+    }
+
+    fn to_type_error<'js>(&self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        let primordials = BasePrimordials::get(ctx)?;
+
+        let cause: Object = primordials
+            .constructor_error
+            .construct((self.message.as_str(),))?;
+        cause.set("code", self.code)?;
+        if let Some(hostname) = &self.hostname {
+            cause.set("hostname", hostname.as_str())?;
+        }
+        if let Some(syscall) = self.syscall {
+            cause.set("syscall", syscall)?;
+        }
+
+        let err: Object = primordials
+            .constructor_type_error
+            .construct((FETCH_FAILED,))?;
+        err.set("cause", cause)?;
+        Ok(err.into_value())
+    }
+}
+
+fn io_error_code(kind: io::ErrorKind) -> Option<&'static str> {
+    Some(match kind {
+        io::ErrorKind::ConnectionRefused => "ECONNREFUSED",
+        io::ErrorKind::ConnectionReset => "ECONNRESET",
+        io::ErrorKind::ConnectionAborted => "ECONNABORTED",
+        io::ErrorKind::TimedOut => "UND_ERR_CONNECT_TIMEOUT",
+        io::ErrorKind::AddrNotAvailable => "EADDRNOTAVAIL",
+        io::ErrorKind::NetworkUnreachable => "ENETUNREACH",
+        io::ErrorKind::HostUnreachable => "EHOSTUNREACH",
+        io::ErrorKind::BrokenPipe => "EPIPE",
+        _ => return None,
+    })
+}
+
+fn tls_error_code(err: &rustls::Error) -> &'static str {
+    use rustls::CertificateError;
+
+    match err {
+        rustls::Error::InvalidCertificate(CertificateError::Expired) => "CERT_HAS_EXPIRED",
+        rustls::Error::InvalidCertificate(CertificateError::NotValidYet) => "CERT_NOT_YET_VALID",
+        rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer) => {
+            "UNABLE_TO_VERIFY_LEAF_SIGNATURE"
+        }
+        rustls::Error::InvalidCertificate(CertificateError::NotValidForName) => {
+            "ERR_TLS_CERT_ALTNAME_INVALID"
+        }
+        rustls::Error::InvalidCertificate(CertificateError::Revoked) => "CERT_REVOKED",
+        rustls::Error::InvalidCertificate(_) => "ERR_TLS_CERT_INVALID",
+        _ => "ERR_SSL_PROTOCOL_ERROR",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_error_cause() {
+        let dns = DnsLookupError::new(
+            "example.invalid",
+            io::Error::other("failed to lookup address information"),
+        );
+        let cause = FetchErrorCause::from_error(&dns);
+        assert_eq!(cause.code, "ENOTFOUND");
+        assert_eq!(cause.hostname.as_deref(), Some("example.invalid"));
+        assert_eq!(cause.syscall, Some("getaddrinfo"));
+
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(FetchErrorCause::from_error(&refused).code, "ECONNREFUSED");
+
+        let tls = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::Expired),
+        );
+        assert_eq!(FetchErrorCause::from_error(&tls).code, "CERT_HAS_EXPIRED");
+    }
+}
@@ -1,8 +1,15 @@
-use std::{collections::HashSet, convert::Infallible, sync::Arc, time::Instant};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    future::pending,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::abort::AbortSignal;
-use crate::http::agent::Agent;
-use crate::http::client::HyperClient;
+use crate::exceptions::{DOMException, DOMExceptionName};
+use crate::http::agent::{Agent, GlobalAgent};
+use crate::permissions::Callers;
 use crate::utils::encoding::bytes_from_b64;
 use crate::utils::{
     bytes::{bytes_to_typed_array, ObjectBytes},
@@ -16,11 +23,14 @@ use rsquickjs::{
     atom::PredefinedAtom,
     function::{Opt, This},
     prelude::{Async, Func},
-    Class, Coerced, Ctx, Exception, FromJs, Function, IntoJs, Object, Result, Value,
+    Class, Coerced, Ctx, Exception, FromJs, Function, IntoJs, Object, Result, Undefined, Value,
 };
-use tokio::{select, sync::Semaphore};
+use tokio::{select, sync::Semaphore, time::sleep_until};
+use tracing::Instrument;
 
 use super::{
+    cookie_jar::{CookieJar, DefaultCookieJar},
+    error::FetchErrorCause,
     headers::{Headers, HeadersGuard},
     response::Response,
     security::ensure_url_access,
@@ -38,25 +48,26 @@ const BLOCKED_PORTS: [u16; 83] = [
 
 const MAX_REDIRECT_COUNT: u32 = 20;
 
-pub fn init(global_client: HyperClient, globals: &Object) -> Result<()> {
+pub fn init(globals: &Object) -> Result<()> {
     let connections = Arc::new(Semaphore::new(500));
 
     globals.set(
         "fetch",
         Func::from(Async(move |ctx, resource, args| {
-            let global_client = global_client.clone();
+            let global_client = GlobalAgent::client(&ctx);
             let connections = connections.clone();
             let start = Instant::now();
             let options = get_fetch_options(&ctx, resource, args);
+            let callers = Callers::capture(&ctx);
 
             async move {
                 let lock = connections.acquire().await;
                 let options = options?;
 
-                let client = options
-                    .agent
-                    .map(|agent| agent.borrow().client())
-                    .unwrap_or(global_client);
+                let client = match options.agent {
+                    Some(agent) => agent.borrow().client(),
+                    None => global_client?,
+                };
 
                 // https://fetch.spec.whatwg.org/#scheme-fetch
                 if let Some((scheme, fragment)) = options.url.split_once(':') {
@@ -78,58 +89,87 @@ pub fn init(global_client: HyperClient, globals: &Object) -> Result<()> {
                 let method_string = options.method.to_string();
                 let method = options.method;
                 let mut abort_receiver = options.abort_receiver;
+                let deadline = options
+                    .timeout
+                    .map(|timeout| start + Duration::from_millis(timeout));
 
-                ensure_url_access(&ctx, &uri)?;
+                ensure_url_access(&ctx, &callers, &uri)?;
 
                 let mut redirect_count = 0;
                 let mut response_status = 0;
                 let (res, guard) = loop {
+                    let cookie = options
+                        .cookie_jar
+                        .as_ref()
+                        .and_then(|jar| jar.borrow_mut().cookie_header(&uri));
                     let (req, guard) = build_request(
                         &ctx,
                         &method,
                         &uri,
                         options.headers.as_ref(),
                         options.body.as_ref(),
+                        cookie.as_deref(),
                         &response_status,
                         &initial_uri,
                     )?;
 
-                    let res = if let Some(abort_receiver) = &abort_receiver {
-                        select! {
-                            res = client.request(req) => res.or_throw(&ctx)?,
-                            reason = abort_receiver.recv() => return Err(ctx.throw(reason)),
-                        }
-                    } else {
-                        client.request(req).await.or_throw(&ctx)?
+                    let span = tracing::info_span!("fetch", method = %method, url = %uri);
+                    let res = select! {
+                        res = client.request(req).instrument(span) => res,
+                        reason = wait_for_abort(abort_receiver.as_ref()) => {
+                            return Err(ctx.throw(reason))
+                        },
+                        _ = wait_for_deadline(deadline) => return Err(timeout_error(&ctx)),
                     };
+                    let res = res.map_err(|err| FetchErrorCause::from_error(&err).throw(&ctx))?;
+
+                    if let Some(jar) = &options.cookie_jar {
+                        let set_cookies = res
+                            .headers()
+                            .get_all(HeaderName::from_static("set-cookie"))
+                            .iter()
+                            .filter_map(|value| value.to_str().ok());
+                        jar.borrow_mut().store_response(&uri, set_cookies);
+                    }
 
                     let status = res.status();
-                    if status.is_redirection() {
-                        match res.headers().get(HeaderName::from_static("location")) {
-                            Some(location_headers) => {
-                                if let Ok(location_str) = location_headers.to_str() {
-                                    uri = location_str.parse().or_throw(&ctx)?;
-                                    ensure_url_access(&ctx, &uri)?;
-                                }
-                            }
-                            None => break (res, guard),
-                        };
-                    } else {
+                    if !status.is_redirection() {
+                        break (res, guard);
+                    }
+                    let Some(location) = res
+                        .headers()
+                        .get(HeaderName::from_static("location"))
+                        .and_then(|location| location.to_str().ok())
+                    else {
                         break (res, guard);
                     };
 
-                    if options.redirect == "manual" {
-                        break (res, guard);
-                    } else if options.redirect == "error" {
-                        return Err(Exception::throw_message(&ctx, "Unexpected redirect"));
+                    match options.redirect.as_str() {
+                        // hand the 3xx back untouched so callers can inspect `location`
+                        "manual" => break (res, guard),
+                        "error" => {
+                            return Err(FetchErrorCause::new(
+                                "UND_ERR_REDIRECT",
+                                "unexpected redirect",
+                            )
+                            .throw(&ctx))
+                        }
+                        _ => {}
                     }
 
                     redirect_count += 1;
-                    if redirect_count >= MAX_REDIRECT_COUNT {
-                        return Err(Exception::throw_message(&ctx, "Max retries exceeded"));
+                    if redirect_count > MAX_REDIRECT_COUNT {
+                        return Err(FetchErrorCause::new(
+                            "UND_ERR_REDIRECT",
+                            "redirect count exceeded",
+                        )
+                        .throw(&ctx));
                     }
 
-                    response_status = res.status().as_u16();
+                    uri = resolve_location(&ctx, &uri, location)?;
+                    ensure_url_access(&ctx, &callers, &uri)?;
+
+                    response_status = status.as_u16();
                 };
 
                 drop(lock);
@@ -150,6 +190,43 @@ pub fn init(global_client: HyperClient, globals: &Object) -> Result<()> {
     Ok(())
 }
 
+async fn wait_for_abort<'js>(
+    receiver: Option<&crate::utils::mc_oneshot::Receiver<Value<'js>>>,
+) -> Value<'js> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => pending().await,
+    }
+}
+
+async fn wait_for_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline.into()).await,
+        None => pending().await,
+    }
+}
+
+fn timeout_error(ctx: &Ctx<'_>) -> rsquickjs::Error {
+    let ex = match DOMException::new_with_name(
+        ctx,
+        DOMExceptionName::TimeoutError,
+        "The operation was aborted due to timeout".into(),
+    )
+    .and_then(|ex| Class::instance(ctx.clone(), ex))
+    {
+        Ok(ex) => ex,
+        Err(err) => return err,
+    };
+    ctx.throw(ex.into_value())
+}
+
+fn resolve_location(ctx: &Ctx<'_>, base: &Uri, location: &str) -> Result<Uri> {
+    // `location` may be relative to the URL that issued the redirect
+    let base = url::Url::parse(&base.to_string()).or_throw(ctx)?;
+    let location = base.join(location).or_throw(ctx)?;
+    location.as_str().parse().or_throw(ctx)
+}
+
 fn parse_data_url<'js>(ctx: &Ctx<'js>, data_url: &str, method: &Method) -> Result<Response<'js>> {
     let (mime_type, data) = data_url
         .split_once(',')
@@ -204,6 +281,7 @@ fn build_request(
     uri: &Uri,
     headers: Option<&Headers>,
     body: Option<&BodyBytes>,
+    cookie: Option<&str>,
     prev_status: &u16,
     initial_uri: &Uri,
 ) -> Result<(Request<BoxBody<Bytes, Infallible>>, HeadersGuard)> {
@@ -247,10 +325,19 @@ fn build_request(
             if !same_origin && is_cors_non_wildcard_request_header_name(header_name) {
                 continue;
             }
+            if let ("cookie", Some(cookie)) = (header_name, cookie) {
+                // explicit cookies go first, the jar fills in the rest
+                req = req.header(header_name, [value, "; ", cookie].concat());
+                continue;
+            }
             req = req.header(header_name, value)
         }
     }
 
+    if let (false, Some(cookie)) = (detected_headers.contains("cookie"), cookie) {
+        req = req.header("cookie", cookie);
+    }
+
     if !detected_headers.contains("user-agent") {
         // 谁叫我家用户喜欢写爬虫捏~
         req = req.header("user-agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_10_10) Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/500.50 (KHTML, like Gecko) Chrome/150.0.0.0 Safari/500.50");
@@ -336,6 +423,8 @@ struct FetchOptions<'js> {
     abort_receiver: Option<crate::utils::mc_oneshot::Receiver<Value<'js>>>,
     redirect: String,
     agent: Option<Class<'js, Agent>>,
+    cookie_jar: Option<Class<'js, CookieJar>>,
+    timeout: Option<u64>,
 }
 
 fn get_fetch_options<'js>(
@@ -352,6 +441,9 @@ fn get_fetch_options<'js>(
     let mut abort_receiver = None;
     let mut redirect = String::from("");
     let mut agent = None;
+    let mut credentials = None;
+    let mut cookie_jar = None;
+    let mut timeout = None;
 
     if let Some(obj) = resource.as_object() {
         let obj = obj.clone();
@@ -394,7 +486,8 @@ fn get_fetch_options<'js>(
         {
             let bytes = if let Ok(blob) = Class::<Blob>::from_value(&body_opt) {
                 let blob = blob.borrow();
-                let typed_array = bytes_to_typed_array(ctx.clone(), &blob.get_bytes())?;
+                let typed_array =
+                    bytes_to_typed_array(ctx.clone(), &blob.get_bytes().or_throw(ctx)?)?;
                 ObjectBytes::from(ctx, &typed_array)?
             } else {
                 ObjectBytes::from(ctx, &body_opt)?
@@ -417,7 +510,28 @@ fn get_fetch_options<'js>(
         if let Some(signal) =
             get_option::<Class<AbortSignal>>("signal", arg_opts.as_ref(), resource_opts.as_ref())?
         {
-            abort_receiver = Some(signal.borrow().sender.subscribe());
+            let signal = signal.borrow();
+            if signal.aborted {
+                // reject right away instead of racing the request against the signal
+                return Err(ctx.throw(
+                    signal
+                        .reason()
+                        .unwrap_or_else(|| Undefined.into_value(ctx.clone())),
+                ));
+            }
+            abort_receiver = Some(signal.sender.subscribe());
+        }
+
+        if let Some(timeout_opt) =
+            get_option::<f64>("timeout", arg_opts.as_ref(), resource_opts.as_ref())?
+        {
+            if !timeout_opt.is_finite() || timeout_opt < 0.0 {
+                return Err(Exception::throw_range(
+                    ctx,
+                    "The \"timeout\" option must be a non-negative finite number",
+                ));
+            }
+            timeout = Some(timeout_opt as u64);
         }
 
         if let Some(redirect_opt) =
@@ -438,8 +552,34 @@ fn get_fetch_options<'js>(
         {
             agent = Some(agent_opt);
         }
+
+        if let Some(credentials_opt) =
+            get_option::<String>("credentials", arg_opts.as_ref(), resource_opts.as_ref())?
+        {
+            if !matches!(credentials_opt.as_str(), "omit" | "same-origin" | "include") {
+                return Err(Exception::throw_type(
+                    ctx,
+                    &["Invalid credentials option: ", &credentials_opt].concat(),
+                ));
+            }
+            credentials = Some(credentials_opt);
+        }
+
+        cookie_jar = get_option::<Class<'js, CookieJar>>(
+            "cookieJar",
+            arg_opts.as_ref(),
+            resource_opts.as_ref(),
+        )?;
     }
 
+    // Cookies are opt-in: only an explicit jar or `credentials: "include"` enables them
+    let cookie_jar = match (credentials.as_deref(), cookie_jar) {
+        (Some("omit"), _) => None,
+        (_, Some(jar)) => Some(jar),
+        (Some("include"), None) => Some(DefaultCookieJar::get(ctx)?),
+        _ => None,
+    };
+
     let url = match url {
         Some(url) => url,
         None => return Err(Exception::throw_reference(ctx, "Missing required url")),
@@ -453,6 +593,8 @@ fn get_fetch_options<'js>(
         abort_receiver,
         redirect,
         agent,
+        cookie_jar,
+        timeout,
     })
 }
 
@@ -860,6 +1002,35 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_fetch_timeout() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(matchers::path("expect/slow/"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&mock_server)
+            .await;
+
+        test_async_with(|ctx| {
+            super::super::init(&ctx).unwrap();
+            Box::pin(async move {
+                let fetch: Function = ctx.globals().get("fetch").unwrap();
+                let options = Object::new(ctx.clone()).unwrap();
+                options.set("timeout", 20).unwrap();
+                let url = format!("http://{}/expect/slow/", mock_server.address());
+
+                let response_promise: Promise = fetch.call((url, options)).unwrap();
+                let response: Result<Class<Response>> = response_promise.into_future().await;
+                assert!(response.is_err());
+
+                let reason = ctx.catch();
+                let reason = Class::<DOMException>::from_value(&reason).unwrap();
+                assert_eq!(reason.borrow().name(), "TimeoutError");
+            })
+        })
+        .await;
+    }
+
     // #[tokio::test]
     // async fn test_fetch_tls() {
     //     let mock_server = llrt_test_tls::MockServer::start().await.unwrap();
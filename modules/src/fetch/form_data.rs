@@ -280,7 +280,7 @@ impl FormData {
                 FormValue::File(file) => {
                     let filename = file.name().clone();
                     let content_type = file.mime_type().clone();
-                    let bytes = file.get_blob().get_bytes();
+                    let bytes = file.get_blob().get_bytes()?;
                     write!(
                         body,
                         "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n"
@@ -289,7 +289,7 @@ impl FormData {
                     body.extend_from_slice(b"\r\n");
                 }
                 FormValue::Blob(blob) => {
-                    let bytes = blob.get_bytes();
+                    let bytes = blob.get_bytes()?;
                     let content_type = blob.mime_type();
                     write!(
                         body,
@@ -220,6 +220,11 @@ impl Headers {
 }
 
 impl Headers {
+    /// Drops a header regardless of the guard, for headers the runtime rewrote itself.
+    pub(crate) fn remove(&mut self, key: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
         self.headers.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
     }
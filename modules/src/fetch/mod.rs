@@ -1,16 +1,23 @@
 use crate::buffer::Blob;
-use crate::http::client::build_client;
+use crate::http::agent::GlobalAgent;
 use crate::utils::{
     class::CustomInspectExtension,
     primordials::{BasePrimordials, Primordial},
-    result::ResultExt,
 };
 use rsquickjs::{Class, Ctx, Result};
 use std::borrow::Cow;
 
-use self::{form_data::FormData, headers::Headers, request::Request, response::Response};
+use self::{
+    cookie_jar::{CookieJar, DefaultCookieJar},
+    form_data::FormData,
+    headers::Headers,
+    request::Request,
+    response::Response,
+};
 
 mod body;
+pub mod cookie_jar;
+mod error;
 pub mod fetch;
 pub mod form_data;
 pub mod headers;
@@ -46,8 +53,8 @@ pub fn init(ctx: &Ctx) -> Result<()> {
     BasePrimordials::init(ctx)?;
 
     //init eagerly
-    let client = build_client(None).or_throw(ctx)?;
-    fetch::init(client, &globals)?;
+    GlobalAgent::client(ctx)?;
+    fetch::init(&globals)?;
 
     Class::<FormData>::define(&globals)?;
 
@@ -55,5 +62,10 @@ pub fn init(ctx: &Ctx) -> Result<()> {
     Class::<Response>::define(&globals)?;
     Class::<Headers>::define_with_custom_inspect(&globals)?;
 
+    if let Some(constructor) = Class::<CookieJar>::create_constructor(ctx)? {
+        constructor.set("default", DefaultCookieJar::get(ctx)?)?;
+        globals.set(stringify!(CookieJar), constructor)?;
+    }
+
     Ok(())
 }
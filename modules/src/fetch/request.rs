@@ -280,7 +280,7 @@ impl<'js> Request<'js> {
                 drop(body_guard);
                 if let Some(blob) = provided.as_object().and_then(Class::<Blob>::from_object) {
                     let blob = blob.borrow();
-                    blob.get_bytes()
+                    blob.get_bytes().or_throw(ctx)?
                 } else {
                     let bytes = ObjectBytes::from(ctx, &provided)?;
                     bytes.as_bytes(ctx)?.to_vec()
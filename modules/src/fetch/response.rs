@@ -361,10 +361,11 @@ impl<'js> Response<'js> {
 
                     *body_mutex = BodyVariant::Cloned(Some(response.clone()));
 
-                    ctx.spawn_exit_simple(async move {
+                    // a clone nobody reads doesn't keep the script running
+                    ctx.spawn_unref(async move {
                         sender.process().await;
                         Ok(())
-                    });
+                    })?;
                     BodyVariant::Cloned(Some(response))
                 } else {
                     BodyVariant::Incoming(None)
@@ -493,6 +494,66 @@ impl<'js> Response<'js> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ContentCoding {
+    Zstd,
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+fn parse_content_codings(header: &str) -> Option<Vec<ContentCoding>> {
+    let mut codings = Vec::new();
+    for coding in header.split(',') {
+        let coding = coding.trim();
+        let coding = if coding.eq_ignore_ascii_case("zstd") {
+            ContentCoding::Zstd
+        } else if coding.eq_ignore_ascii_case("br") {
+            ContentCoding::Brotli
+        } else if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+            ContentCoding::Gzip
+        } else if coding.eq_ignore_ascii_case("deflate") {
+            ContentCoding::Deflate
+        } else if coding.is_empty() || coding.eq_ignore_ascii_case("identity") {
+            continue;
+        } else {
+            return None;
+        };
+        codings.push(coding);
+    }
+    Some(codings)
+}
+
+fn decode_content(coding: ContentCoding, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::with_capacity(bytes.len());
+    match coding {
+        ContentCoding::Zstd => {
+            crate::utils::compression::zstd::decoder(bytes)?.read_to_end(&mut data)?;
+        }
+        ContentCoding::Brotli => {
+            crate::utils::compression::brotli::decoder(bytes).read_to_end(&mut data)?;
+        }
+        ContentCoding::Gzip => {
+            crate::utils::compression::gz::decoder(bytes).read_to_end(&mut data)?;
+        }
+        ContentCoding::Deflate => {
+            // "deflate" should be zlib wrapped, but plenty of servers send a raw stream
+            if crate::utils::compression::zlib::decoder(bytes)
+                .read_to_end(&mut data)
+                .is_err()
+            {
+                data.clear();
+                crate::utils::compression::deflate::decoder(bytes).read_to_end(&mut data)?;
+            }
+        }
+    }
+    Ok(data)
+}
+
+fn has_response_body(method: &str, status: u16) -> bool {
+    method != "HEAD" && !matches!(status, 101 | 204 | 205 | 304)
+}
+
 #[allow(clippy::too_many_arguments)]
 impl<'js> Response<'js> {
     pub fn from_incoming(
@@ -505,18 +566,25 @@ impl<'js> Response<'js> {
         abort_receiver: Option<mc_oneshot::Receiver<Value<'js>>>,
         guard: HeadersGuard,
     ) -> Result<Self> {
+        let status = response.status();
         let response_headers = response.headers();
 
-        let mut content_encoding = None;
-        if let Some(content_encoding_header) =
-            response_headers.get(HeaderName::from_static("content-encoding"))
-        {
-            if let Ok(content_encoding_header) = content_encoding_header.to_str() {
-                content_encoding = Some(content_encoding_header.to_owned())
-            }
-        }
+        // The body is transparently decoded, so the exposed headers must no longer advertise
+        // the encoded representation. Unknown codings are passed through untouched.
+        let content_encoding = response_headers
+            .get(HeaderName::from_static("content-encoding"))
+            .and_then(|header| header.to_str().ok())
+            .filter(|header| {
+                has_response_body(&method, status.as_u16())
+                    && parse_content_codings(header).is_some_and(|codings| !codings.is_empty())
+            })
+            .map(|header| header.to_owned());
 
-        let headers = Headers::from_http_headers(response.headers(), guard)?;
+        let mut headers = Headers::from_http_headers(response_headers, guard)?;
+        if content_encoding.is_some() {
+            headers.remove("content-encoding");
+            headers.remove("content-length");
+        }
         let headers = Class::instance(ctx.clone(), headers)?;
 
         let status = response.status();
@@ -564,7 +632,7 @@ impl<'js> Response<'js> {
                 drop(body_guard);
                 if let Some(blob) = provided.as_object().and_then(Class::<Blob>::from_object) {
                     let blob = blob.borrow();
-                    blob.get_bytes()
+                    blob.get_bytes().or_throw(ctx)?
                 } else {
                     let bytes = ObjectBytes::from(ctx, &provided)?;
                     bytes.as_bytes(ctx)?.to_vec()
@@ -590,27 +658,20 @@ impl<'js> Response<'js> {
             body.collect().await.or_throw(ctx)?.to_bytes()
         };
 
-        if let Some(content_encoding) = self.content_encoding.as_deref() {
-            let mut data: Vec<u8> = Vec::with_capacity(bytes.len());
-            match content_encoding {
-                "zstd" => {
-                    crate::utils::compression::zstd::decoder(&bytes[..])?.read_to_end(&mut data)?
-                }
-                "br" => {
-                    crate::utils::compression::brotli::decoder(&bytes[..]).read_to_end(&mut data)?
-                }
-                "gzip" => {
-                    crate::utils::compression::gz::decoder(&bytes[..]).read_to_end(&mut data)?
-                }
-                "deflate" => {
-                    crate::utils::compression::zlib::decoder(&bytes[..]).read_to_end(&mut data)?
-                }
-                _ => return Err(Exception::throw_message(ctx, "Unsupported encoding")),
-            };
-            Ok(data)
-        } else {
-            Ok(bytes.to_vec())
+        let codings = self
+            .content_encoding
+            .as_deref()
+            .and_then(parse_content_codings)
+            .unwrap_or_default();
+
+        // Codings are listed in the order they were applied, so undo them back to front
+        let mut data = bytes.to_vec();
+        for coding in codings.into_iter().rev() {
+            data = decode_content(coding, &data).map_err(|err| {
+                Exception::throw_type(ctx, &["Decompression failed: ", &err.to_string()].concat())
+            })?;
         }
+        Ok(data)
     }
 
     fn get_headers(&self, ctx: &Ctx<'js>) -> Result<Headers> {
@@ -624,3 +685,34 @@ impl<'js> Response<'js> {
             .find_map(|(k, v)| (k == key).then(|| v.to_string())))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_codings() {
+        assert_eq!(
+            parse_content_codings("gzip"),
+            Some(vec![ContentCoding::Gzip])
+        );
+        assert_eq!(
+            parse_content_codings("deflate, BR"),
+            Some(vec![ContentCoding::Deflate, ContentCoding::Brotli])
+        );
+        assert_eq!(parse_content_codings("identity"), Some(vec![]));
+        assert_eq!(
+            parse_content_codings("x-gzip, zstd"),
+            Some(vec![ContentCoding::Gzip, ContentCoding::Zstd])
+        );
+        assert_eq!(parse_content_codings("compress"), None);
+    }
+
+    #[test]
+    fn test_has_response_body() {
+        assert!(has_response_body("GET", 200));
+        assert!(!has_response_body("HEAD", 200));
+        assert!(!has_response_body("GET", 204));
+        assert!(!has_response_body("GET", 304));
+    }
+}
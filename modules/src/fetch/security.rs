@@ -1,11 +1,11 @@
 use hyper::Uri;
 use rsquickjs::{Ctx, Error, Exception, Result};
 
-use crate::permissions;
+use crate::permissions::{self, Callers};
 
-pub fn ensure_url_access(ctx: &Ctx<'_>, uri: &Uri) -> Result<()> {
+pub fn ensure_url_access(ctx: &Ctx<'_>, callers: &Callers, uri: &Uri) -> Result<()> {
     let host = uri.host().unwrap_or_default();
-    if !permissions::check_net_permission(ctx, host) {
+    if !permissions::check_net_permission(ctx, callers, host) {
         return Err(url_restricted_error(ctx, "URL not allowed", uri));
     }
     // if let Some(allow_list) = HTTP_ALLOW_LIST.get() {
@@ -0,0 +1,186 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    mem::ManuallyDrop,
+    ptr::NonNull,
+    slice,
+};
+
+use libffi::{low::ffi_cif, middle::Closure};
+use rsquickjs::{
+    atom::PredefinedAtom, prelude::Rest, qjs, CatchResultExt, Ctx, Exception, Function, Object,
+    Persistent, Result, Value,
+};
+
+use super::{
+    check_permission,
+    types::{to_native, FfiType, RawValue, Signature},
+};
+
+/// State shared with the native trampoline of a [`JsCallback`].
+struct CallbackInfo {
+    ctx: NonNull<qjs::JSContext>,
+    function: Persistent<Function<'static>>,
+    signature: Signature,
+    /// How many calls of the trampoline are running, which use the closure and this state
+    running: Cell<usize>,
+}
+
+/// A libffi closure together with the state it borrows.
+struct NativeCallback {
+    closure: ManuallyDrop<Closure<'static>>,
+    info: NonNull<CallbackInfo>,
+}
+
+impl NativeCallback {
+    fn address(&self) -> u64 {
+        *self.closure.code_ptr() as usize as u64
+    }
+}
+
+impl Drop for NativeCallback {
+    fn drop(&mut self) {
+        // Collected while native code is calling it, the running calls still use both
+        if unsafe { self.info.as_ref() }.running.get() > 0 {
+            return;
+        }
+        unsafe {
+            // The closure borrows `info`, so it has to go first
+            ManuallyDrop::drop(&mut self.closure);
+            drop(Box::from_raw(self.info.as_ptr()));
+        }
+    }
+}
+
+/// A JavaScript function exposed to native code as a C function pointer.
+///
+/// The pointer stays valid until `close()` is called or the callback is garbage
+/// collected, and may only be invoked synchronously on the JavaScript thread.
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "JSCallback")]
+pub struct JsCallback {
+    #[qjs(skip_trace)]
+    native: RefCell<Option<NativeCallback>>,
+}
+
+impl JsCallback {
+    pub fn pointer(&self, ctx: &Ctx<'_>) -> Result<*mut c_void> {
+        match &*self.native.borrow() {
+            Some(native) => Ok(native.address() as usize as *mut c_void),
+            None => Err(Exception::throw_type(ctx, "JSCallback is closed")),
+        }
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl JsCallback {
+    #[qjs(constructor)]
+    pub fn new<'js>(
+        ctx: Ctx<'js>,
+        function: Function<'js>,
+        definition: Object<'js>,
+    ) -> Result<Self> {
+        check_permission(&ctx)?;
+        let signature = Signature::from_object(&ctx, &definition)?;
+        if signature.returns == FfiType::CString {
+            return Err(Exception::throw_type(
+                &ctx,
+                "'cstring' is not a valid callback return type",
+            ));
+        }
+
+        let cif = signature.cif();
+        let info = NonNull::from(Box::leak(Box::new(CallbackInfo {
+            ctx: ctx.as_raw(),
+            function: Persistent::save(&ctx, function),
+            signature,
+            running: Cell::new(0),
+        })));
+        let closure = Closure::new(cif, trampoline, unsafe { info.as_ref() });
+
+        Ok(Self {
+            native: RefCell::new(Some(NativeCallback {
+                closure: ManuallyDrop::new(closure),
+                info,
+            })),
+        })
+    }
+
+    #[qjs(get)]
+    pub fn ptr<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let address = self
+            .native
+            .borrow()
+            .as_ref()
+            .map_or(0, |native| native.address());
+        super::types::pointer_to_js(&ctx, address)
+    }
+
+    /// Frees the native function. It throws while the callback is running, as the
+    /// native code calling it still uses it.
+    pub fn close(&self, ctx: Ctx<'_>) -> Result<()> {
+        let mut native = self.native.borrow_mut();
+        if let Some(callback) = &*native {
+            if unsafe { callback.info.as_ref() }.running.get() > 0 {
+                return Err(Exception::throw_message(
+                    &ctx,
+                    "Cannot close a JSCallback while it is running",
+                ));
+            }
+        }
+        native.take();
+        Ok(())
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "JSCallback"
+    }
+}
+
+unsafe extern "C" fn trampoline(
+    _cif: &ffi_cif,
+    result: &mut u64,
+    args: *const *const c_void,
+    info: &CallbackInfo,
+) {
+    // Keeps `info` and the closure alive until this call returns
+    struct Running<'a>(&'a Cell<usize>);
+    impl Drop for Running<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() - 1);
+        }
+    }
+    info.running.set(info.running.get() + 1);
+    let _running = Running(&info.running);
+
+    let ctx = Ctx::from_raw(info.ctx);
+    let types = &info.signature.args;
+    let slots = slice::from_raw_parts(args, types.len());
+
+    let value = (|| {
+        let args = types
+            .iter()
+            .zip(slots)
+            .map(|(ty, slot)| RawValue::read(*ty, *slot).into_js(&ctx, *ty))
+            .collect::<Result<Vec<_>>>()?;
+        let function = info.function.clone().restore(&ctx)?;
+        function.call::<_, Value>((Rest(args),))
+    })()
+    .catch(&ctx);
+
+    let returns = info.signature.returns;
+    *result = 0;
+    match value {
+        Ok(value) if returns != FfiType::Void => {
+            let mut strings = Vec::new();
+            match to_native(&ctx, returns, &value, &mut strings).catch(&ctx) {
+                Ok(native) => native.write_result(result),
+                Err(err) => crate::utils::ctx::report_uncaught_error(&ctx, err),
+            }
+        }
+        Ok(_) => {}
+        // Exceptions can't unwind through native frames, so report them here
+        Err(err) => crate::utils::ctx::report_uncaught_error(&ctx, err),
+    }
+}
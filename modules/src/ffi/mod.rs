@@ -0,0 +1,349 @@
+//! `xmas:ffi` - calls into native libraries without writing a Rust extension.
+//!
+//! Every entry point requires the `ffi` permission, since a foreign call can do
+//! anything the host process can.
+
+mod callback;
+mod types;
+
+use std::{cell::RefCell, ffi::c_void, rc::Rc, slice};
+
+use libffi::middle::{Arg, Cif, CodePtr};
+use rsquickjs::{
+    atom::PredefinedAtom,
+    class::Trace,
+    module::{Declarations, Exports, ModuleDef},
+    prelude::{Func, Opt, Rest},
+    ArrayBuffer, Class, Ctx, Exception, Function, Object, Result, Value,
+};
+
+use self::{
+    callback::JsCallback,
+    types::{pointer_to_js, to_native, to_pointer, FfiType, NativeValue, RawValue, Signature},
+};
+use crate::{
+    permissions::check_ffi_permission,
+    utils::{
+        module::{export_default, ModuleInfo},
+        result::ResultExt,
+    },
+};
+
+#[cfg(target_os = "windows")]
+const SUFFIX: &str = "dll";
+#[cfg(target_os = "macos")]
+const SUFFIX: &str = "dylib";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SUFFIX: &str = "so";
+
+fn check_permission(ctx: &Ctx<'_>) -> Result<()> {
+    if !check_ffi_permission(ctx) {
+        return Err(Exception::throw_message(
+            ctx,
+            "Permission denied. FFI access is not allowed",
+        ));
+    }
+    Ok(())
+}
+
+/// A bound native function together with the library that owns it.
+struct ForeignSymbol {
+    library: Rc<RefCell<Option<libloading::Library>>>,
+    address: usize,
+    signature: Signature,
+    cif: Cif,
+}
+
+/// A library opened with `dlopen`.
+#[derive(Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "DynamicLibrary")]
+pub struct DynamicLibrary<'js> {
+    symbols: Object<'js>,
+    #[qjs(skip_trace)]
+    library: Rc<RefCell<Option<libloading::Library>>>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> DynamicLibrary<'js> {
+    #[qjs(get)]
+    pub fn symbols(&self) -> Object<'js> {
+        self.symbols.clone()
+    }
+
+    /// Unloads the library. Its symbols throw from then on.
+    pub fn close(&self, ctx: Ctx<'js>) -> Result<()> {
+        self.library
+            .try_borrow_mut()
+            .or_throw_msg(
+                &ctx,
+                "Cannot close a library while one of its symbols is running",
+            )?
+            .take();
+        Ok(())
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "DynamicLibrary"
+    }
+}
+
+fn dlopen<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    definitions: Object<'js>,
+) -> Result<Class<'js, DynamicLibrary<'js>>> {
+    check_permission(&ctx)?;
+
+    let library = unsafe { libloading::Library::new(&path) }
+        .or_throw_msg(&ctx, &["Failed to load '", &path, "'"].concat())?;
+
+    let mut bound = Vec::new();
+    for name in definitions.keys::<String>() {
+        let name = name?;
+        let definition: Object = definitions.get(&name)?;
+        let signature = Signature::from_object(&ctx, &definition)?;
+        let address = unsafe { library.get::<*mut c_void>(name.as_bytes()) }
+            .or_throw_msg(&ctx, &["Failed to find symbol '", &name, "'"].concat())?;
+        bound.push((name, *address as usize, signature));
+    }
+
+    let library = Rc::new(RefCell::new(Some(library)));
+    let symbols = Object::new(ctx.clone())?;
+    for (name, address, signature) in bound {
+        let symbol = Rc::new(ForeignSymbol {
+            library: library.clone(),
+            address,
+            cif: signature.cif(),
+            signature,
+        });
+        let function = Function::new(ctx.clone(), move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+            call_symbol(&ctx, &symbol, args.0)
+        })?
+        .with_name(&name)?;
+        symbols.set(name, function)?;
+    }
+
+    Class::instance(ctx, DynamicLibrary { symbols, library })
+}
+
+fn call_symbol<'js>(
+    ctx: &Ctx<'js>,
+    symbol: &ForeignSymbol,
+    args: Vec<Value<'js>>,
+) -> Result<Value<'js>> {
+    // Held for the whole call so the library can't be unloaded from a callback
+    let library = symbol.library.borrow();
+    if library.is_none() {
+        return Err(Exception::throw_message(ctx, "Library is closed"));
+    }
+
+    let types = &symbol.signature.args;
+    if args.len() < types.len() {
+        return Err(Exception::throw_type(
+            ctx,
+            &format!("Expected {} arguments, got {}", types.len(), args.len()),
+        ));
+    }
+    let mut strings = Vec::new();
+    let values = types
+        .iter()
+        .zip(&args)
+        .map(|(ty, value)| to_native(ctx, *ty, value, &mut strings))
+        .collect::<Result<Vec<_>>>()?;
+    let args: Vec<Arg> = values.iter().map(NativeValue::as_arg).collect();
+
+    let code = CodePtr::from_ptr(symbol.address as *const c_void);
+    let returns = symbol.signature.returns;
+    let raw = unsafe {
+        match returns {
+            FfiType::Void => {
+                symbol.cif.call::<()>(code, &args);
+                RawValue::Void
+            }
+            FfiType::F32 => RawValue::F32(symbol.cif.call(code, &args)),
+            FfiType::F64 => RawValue::F64(symbol.cif.call(code, &args)),
+            _ => RawValue::Int(symbol.cif.call(code, &args)),
+        }
+    };
+    drop(library);
+
+    raw.into_js(ctx, returns)
+}
+
+/// Returns the address of the memory backing an `ArrayBuffer` or typed array.
+fn ptr<'js>(ctx: Ctx<'js>, view: Value<'js>, offset: Opt<usize>) -> Result<Value<'js>> {
+    check_permission(&ctx)?;
+    if !view.is_object() {
+        return Err(Exception::throw_type(
+            &ctx,
+            "Expected an ArrayBuffer or TypedArray",
+        ));
+    }
+    let pointer = to_pointer(&ctx, &view)? as usize + offset.0.unwrap_or_default();
+    pointer_to_js(&ctx, pointer as u64)
+}
+
+/// Copies `length` bytes starting at `pointer + offset` into a new `ArrayBuffer`.
+fn to_array_buffer<'js>(
+    ctx: Ctx<'js>,
+    pointer: Value<'js>,
+    offset: Opt<usize>,
+    length: usize,
+) -> Result<ArrayBuffer<'js>> {
+    check_permission(&ctx)?;
+    let pointer = to_pointer(&ctx, &pointer)?;
+    if pointer.is_null() {
+        return Err(Exception::throw_type(
+            &ctx,
+            "Cannot read from a null pointer",
+        ));
+    }
+    let bytes = unsafe {
+        slice::from_raw_parts(
+            (pointer as *const u8).add(offset.0.unwrap_or_default()),
+            length,
+        )
+    };
+    ArrayBuffer::new_copy(ctx, bytes)
+}
+
+/// Reads a NUL-terminated string starting at `pointer + offset`.
+fn read_cstring<'js>(ctx: Ctx<'js>, pointer: Value<'js>, offset: Opt<usize>) -> Result<Value<'js>> {
+    check_permission(&ctx)?;
+    let pointer = to_pointer(&ctx, &pointer)?;
+    if pointer.is_null() {
+        return Ok(Value::new_null(ctx));
+    }
+    let address = pointer as usize + offset.0.unwrap_or_default();
+    RawValue::Int(address as u64).into_js(&ctx, FfiType::CString)
+}
+
+pub struct FfiModule;
+
+impl ModuleDef for FfiModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("dlopen")?;
+        declare.declare("suffix")?;
+        declare.declare("FFIType")?;
+        declare.declare("JSCallback")?;
+        declare.declare("ptr")?;
+        declare.declare("toArrayBuffer")?;
+        declare.declare("readCString")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            Class::<JsCallback>::define(default)?;
+
+            let ffi_types = Object::new(ctx.clone())?;
+            for name in FfiType::NAMES {
+                ffi_types.set(name, name)?;
+            }
+
+            default.set("dlopen", Func::from(dlopen))?;
+            default.set("suffix", SUFFIX)?;
+            default.set("FFIType", ffi_types)?;
+            default.set("ptr", Func::from(ptr))?;
+            default.set("toArrayBuffer", Func::from(to_array_buffer))?;
+            default.set("readCString", Func::from(read_cstring))?;
+            Ok(())
+        })
+    }
+}
+
+impl From<FfiModule> for ModuleInfo<FfiModule> {
+    fn from(val: FfiModule) -> Self {
+        ModuleInfo {
+            name: "xmas:ffi",
+            module: val,
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ffi() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::permissions::init(ctx.clone(), Arc::new(xmas_vsys::Vsys::new())).unwrap();
+                ModuleEvaluator::eval_rust::<FfiModule>(ctx.clone(), "xmas:ffi")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { dlopen, JSCallback, ptr, toArrayBuffer, readCString } from 'xmas:ffi';
+                        export async function test() {
+                            const libc = dlopen('libc.so.6', {
+                                abs: { args: ['i32'], returns: 'i32' },
+                                strlen: { args: ['cstring'], returns: 'usize' },
+                                getenv: { args: ['cstring'], returns: 'cstring' },
+                                qsort: { args: ['buffer', 'usize', 'usize', 'function'] },
+                            });
+                            const { abs, strlen, getenv, qsort } = libc.symbols;
+
+                            const values = new Int32Array([3, 1, 2]);
+                            const compare = new JSCallback(
+                                (a, b) => new Int32Array(toArrayBuffer(a, 0, 4))[0]
+                                    - new Int32Array(toArrayBuffer(b, 0, 4))[0],
+                                { args: ['pointer', 'pointer'], returns: 'i32' },
+                            );
+                            qsort(values, 3, 4, compare);
+                            compare.close();
+
+                            let closeError;
+                            const selfClosing = new JSCallback(() => {
+                                try {
+                                    selfClosing.close();
+                                } catch (e) {
+                                    closeError = e.message;
+                                }
+                                return 0;
+                            }, { args: ['pointer', 'pointer'], returns: 'i32' });
+                            qsort(new Int32Array([2, 1]), 2, 4, selfClosing);
+                            selfClosing.close();
+
+                            const bytes = new Uint8Array([104, 105, 0]);
+                            const result = [
+                                abs(-5),
+                                strlen('hello'),
+                                getenv('XMAS_FFI_UNSET_VARIABLE'),
+                                values.join(','),
+                                readCString(ptr(bytes)),
+                                compare.ptr,
+                                closeError,
+                            ];
+                            libc.close();
+                            try {
+                                abs(-1);
+                            } catch (e) {
+                                result.push(e.message);
+                            }
+                            return result.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "5|5||1,2,3|hi||Cannot close a JSCallback while it is running|Library is closed"
+                );
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    ptr,
+};
+
+use libffi::middle::{Arg, Type};
+use rsquickjs::{BigInt, Class, Coerced, Ctx, Exception, Object, Result, Value};
+
+use super::callback::JsCallback;
+use crate::utils::{bytes::ObjectBytes, object::ObjectExt, result::ResultExt};
+
+/// A value type in a foreign function signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FfiType {
+    Void,
+    Bool,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Pointer,
+    Buffer,
+    CString,
+    Function,
+}
+
+impl FfiType {
+    pub const NAMES: [&'static str; 16] = [
+        "void", "bool", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "f32", "f64",
+        "pointer", "buffer", "cstring", "function",
+    ];
+
+    pub fn parse(ctx: &Ctx<'_>, name: &str) -> Result<Self> {
+        Ok(match name {
+            "void" => Self::Void,
+            "bool" => Self::Bool,
+            "i8" | "int8_t" | "char" => Self::I8,
+            "u8" | "uint8_t" => Self::U8,
+            "i16" | "int16_t" => Self::I16,
+            "u16" | "uint16_t" => Self::U16,
+            "i32" | "int32_t" | "int" => Self::I32,
+            "u32" | "uint32_t" => Self::U32,
+            "i64" | "int64_t" => Self::I64,
+            "u64" | "uint64_t" => Self::U64,
+            #[cfg(target_pointer_width = "64")]
+            "isize" => Self::I64,
+            #[cfg(target_pointer_width = "64")]
+            "usize" => Self::U64,
+            #[cfg(target_pointer_width = "32")]
+            "isize" => Self::I32,
+            #[cfg(target_pointer_width = "32")]
+            "usize" => Self::U32,
+            "f32" | "float" => Self::F32,
+            "f64" | "double" => Self::F64,
+            "pointer" | "ptr" => Self::Pointer,
+            "buffer" => Self::Buffer,
+            "cstring" => Self::CString,
+            "function" | "callback" => Self::Function,
+            _ => {
+                return Err(Exception::throw_type(
+                    ctx,
+                    &["Unknown FFI type '", name, "'"].concat(),
+                ))
+            }
+        })
+    }
+
+    pub fn ffi_type(self) -> Type {
+        match self {
+            Self::Void => Type::void(),
+            Self::Bool | Self::U8 => Type::u8(),
+            Self::I8 => Type::i8(),
+            Self::I16 => Type::i16(),
+            Self::U16 => Type::u16(),
+            Self::I32 => Type::i32(),
+            Self::U32 => Type::u32(),
+            Self::I64 => Type::i64(),
+            Self::U64 => Type::u64(),
+            Self::F32 => Type::f32(),
+            Self::F64 => Type::f64(),
+            Self::Pointer | Self::Buffer | Self::CString | Self::Function => Type::pointer(),
+        }
+    }
+}
+
+/// The parameter and result types of a foreign function.
+pub struct Signature {
+    pub args: Vec<FfiType>,
+    pub returns: FfiType,
+}
+
+impl Signature {
+    /// Parses `{ args, returns }`, also accepting Deno's `{ parameters, result }`.
+    pub fn from_object(ctx: &Ctx<'_>, definition: &Object<'_>) -> Result<Self> {
+        let args: Option<Vec<String>> = match definition.get_optional("args")? {
+            Some(args) => Some(args),
+            None => definition.get_optional("parameters")?,
+        };
+        let returns: Option<String> = match definition.get_optional("returns")? {
+            Some(returns) => Some(returns),
+            None => definition.get_optional("result")?,
+        };
+
+        let args = args
+            .unwrap_or_default()
+            .iter()
+            .map(|name| {
+                let ty = FfiType::parse(ctx, name)?;
+                if ty == FfiType::Void {
+                    return Err(Exception::throw_type(
+                        ctx,
+                        "'void' is not a valid argument type",
+                    ));
+                }
+                Ok(ty)
+            })
+            .collect::<Result<_>>()?;
+        let returns = match returns {
+            Some(name) => FfiType::parse(ctx, &name)?,
+            None => FfiType::Void,
+        };
+        Ok(Self { args, returns })
+    }
+
+    pub fn cif(&self) -> libffi::middle::Cif {
+        libffi::middle::Cif::new(
+            self.args.iter().map(|ty| ty.ffi_type()),
+            self.returns.ffi_type(),
+        )
+    }
+}
+
+/// An argument converted to its native representation.
+pub enum NativeValue {
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Pointer(*mut c_void),
+}
+
+impl NativeValue {
+    pub fn as_arg(&self) -> Arg {
+        match self {
+            Self::I8(value) => Arg::new(value),
+            Self::U8(value) => Arg::new(value),
+            Self::I16(value) => Arg::new(value),
+            Self::U16(value) => Arg::new(value),
+            Self::I32(value) => Arg::new(value),
+            Self::U32(value) => Arg::new(value),
+            Self::I64(value) => Arg::new(value),
+            Self::U64(value) => Arg::new(value),
+            Self::F32(value) => Arg::new(value),
+            Self::F64(value) => Arg::new(value),
+            Self::Pointer(value) => Arg::new(value),
+        }
+    }
+
+    /// Writes a callback result into a libffi return slot.
+    ///
+    /// Integers narrower than a register are widened, as libffi expects.
+    ///
+    /// # Safety
+    /// `slot` must point to a return buffer of at least 8 bytes.
+    pub unsafe fn write_result(&self, slot: *mut u64) {
+        match *self {
+            Self::I8(value) => *slot = value as i64 as u64,
+            Self::U8(value) => *slot = value as u64,
+            Self::I16(value) => *slot = value as i64 as u64,
+            Self::U16(value) => *slot = value as u64,
+            Self::I32(value) => *slot = value as i64 as u64,
+            Self::U32(value) => *slot = value as u64,
+            Self::I64(value) => *slot = value as u64,
+            Self::U64(value) => *slot = value,
+            Self::F32(value) => *(slot as *mut f32) = value,
+            Self::F64(value) => *(slot as *mut f64) = value,
+            Self::Pointer(value) => *(slot as *mut *mut c_void) = value,
+        }
+    }
+}
+
+/// A value returned from native code, before conversion to JavaScript.
+pub enum RawValue {
+    Void,
+    Int(u64),
+    F32(f32),
+    F64(f64),
+}
+
+impl RawValue {
+    /// Reads a value of type `ty` from an argument slot handed to a callback.
+    ///
+    /// # Safety
+    /// `slot` must point to a valid value of type `ty`.
+    pub unsafe fn read(ty: FfiType, slot: *const c_void) -> Self {
+        match ty {
+            FfiType::Void => Self::Void,
+            FfiType::Bool | FfiType::U8 => Self::Int(*(slot as *const u8) as u64),
+            FfiType::I8 => Self::Int(*(slot as *const i8) as i64 as u64),
+            FfiType::I16 => Self::Int(*(slot as *const i16) as i64 as u64),
+            FfiType::U16 => Self::Int(*(slot as *const u16) as u64),
+            FfiType::I32 => Self::Int(*(slot as *const i32) as i64 as u64),
+            FfiType::U32 => Self::Int(*(slot as *const u32) as u64),
+            FfiType::I64 | FfiType::U64 => Self::Int(*(slot as *const u64)),
+            FfiType::F32 => Self::F32(*(slot as *const f32)),
+            FfiType::F64 => Self::F64(*(slot as *const f64)),
+            FfiType::Pointer | FfiType::Buffer | FfiType::CString | FfiType::Function => {
+                Self::Int(*(slot as *const *const c_void) as usize as u64)
+            }
+        }
+    }
+
+    pub fn into_js<'js>(self, ctx: &Ctx<'js>, ty: FfiType) -> Result<Value<'js>> {
+        let raw = match self {
+            Self::Void => return Ok(Value::new_undefined(ctx.clone())),
+            Self::F32(value) => return Ok(Value::new_float(ctx.clone(), value as f64)),
+            Self::F64(value) => return Ok(Value::new_float(ctx.clone(), value)),
+            Self::Int(raw) => raw,
+        };
+
+        Ok(match ty {
+            FfiType::Void => Value::new_undefined(ctx.clone()),
+            FfiType::Bool => Value::new_bool(ctx.clone(), raw as u8 != 0),
+            FfiType::I8 => Value::new_int(ctx.clone(), raw as i8 as i32),
+            FfiType::U8 => Value::new_int(ctx.clone(), raw as u8 as i32),
+            FfiType::I16 => Value::new_int(ctx.clone(), raw as i16 as i32),
+            FfiType::U16 => Value::new_int(ctx.clone(), raw as u16 as i32),
+            FfiType::I32 => Value::new_int(ctx.clone(), raw as i32),
+            FfiType::U32 => Value::new_number(ctx.clone(), raw as u32 as f64),
+            FfiType::I64 => Value::new_big_int(ctx.clone(), raw as i64),
+            FfiType::U64 => BigInt::from_u64(ctx.clone(), raw)?.into_value(),
+            FfiType::F32 => Value::new_float(ctx.clone(), f32::from_bits(raw as u32) as f64),
+            FfiType::F64 => Value::new_float(ctx.clone(), f64::from_bits(raw)),
+            FfiType::Pointer | FfiType::Buffer | FfiType::Function => pointer_to_js(ctx, raw)?,
+            FfiType::CString if raw == 0 => Value::new_null(ctx.clone()),
+            FfiType::CString => {
+                let string = unsafe { CStr::from_ptr(raw as usize as *const _) };
+                rsquickjs::String::from_str(ctx.clone(), &string.to_string_lossy())?.into_value()
+            }
+        })
+    }
+}
+
+/// Converts a JavaScript argument to the native representation of `ty`.
+///
+/// Strings passed as `cstring` are copied into `strings`, which must outlive the call.
+pub fn to_native<'js>(
+    ctx: &Ctx<'js>,
+    ty: FfiType,
+    value: &Value<'js>,
+    strings: &mut Vec<CString>,
+) -> Result<NativeValue> {
+    Ok(match ty {
+        FfiType::Void => NativeValue::U8(0),
+        FfiType::Bool => NativeValue::U8(value.get::<Coerced<bool>>()?.0 as u8),
+        FfiType::I8 => NativeValue::I8(to_number(ctx, value)? as i8),
+        FfiType::U8 => NativeValue::U8(to_number(ctx, value)? as u8),
+        FfiType::I16 => NativeValue::I16(to_number(ctx, value)? as i16),
+        FfiType::U16 => NativeValue::U16(to_number(ctx, value)? as u16),
+        FfiType::I32 => NativeValue::I32(to_number(ctx, value)? as i32),
+        FfiType::U32 => NativeValue::U32(to_number(ctx, value)? as u32),
+        FfiType::I64 => NativeValue::I64(to_i64(ctx, value)?),
+        FfiType::U64 => NativeValue::U64(to_i64(ctx, value)? as u64),
+        FfiType::F32 => NativeValue::F32(to_number(ctx, value)? as f32),
+        FfiType::F64 => NativeValue::F64(to_number(ctx, value)?),
+        FfiType::CString if value.is_string() => {
+            let string: String = value.get()?;
+            let string = CString::new(string)
+                .or_throw_type(ctx, "cstring arguments must not contain NUL bytes")?;
+            let pointer = string.as_ptr() as *mut c_void;
+            strings.push(string);
+            NativeValue::Pointer(pointer)
+        }
+        FfiType::Pointer | FfiType::Buffer | FfiType::CString | FfiType::Function => {
+            NativeValue::Pointer(to_pointer(ctx, value)?)
+        }
+    })
+}
+
+/// Resolves a pointer from `null`, a BigInt or number address, a `JSCallback`,
+/// or the backing store of an `ArrayBuffer`/typed array.
+pub fn to_pointer<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<*mut c_void> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(ptr::null_mut());
+    }
+    if value.is_big_int() || value.is_number() {
+        return Ok(to_i64(ctx, value)? as usize as *mut c_void);
+    }
+    if let Some(object) = value.as_object() {
+        if let Ok(callback) = Class::<JsCallback>::from_value(value) {
+            return callback.borrow().pointer(ctx);
+        }
+        if let Some(bytes) = ObjectBytes::from_array_buffer(object)? {
+            let (buffer, _, offset) = bytes
+                .get_array_buffer()?
+                .or_throw_type(ctx, "Expected a pointer")?;
+            let raw = buffer
+                .as_raw()
+                .or_throw_type(ctx, "ArrayBuffer is detached")?;
+            return Ok(unsafe { raw.ptr.as_ptr().add(offset) } as *mut c_void);
+        }
+    }
+    Err(Exception::throw_type(
+        ctx,
+        "Expected a pointer, null, ArrayBuffer or TypedArray",
+    ))
+}
+
+pub fn pointer_to_js<'js>(ctx: &Ctx<'js>, address: u64) -> Result<Value<'js>> {
+    if address == 0 {
+        return Ok(Value::new_null(ctx.clone()));
+    }
+    Ok(BigInt::from_u64(ctx.clone(), address)?.into_value())
+}
+
+fn to_number(ctx: &Ctx<'_>, value: &Value<'_>) -> Result<f64> {
+    if let Some(big_int) = value.as_big_int() {
+        return Ok(big_int.clone().to_i64()? as f64);
+    }
+    if let Some(value) = value.as_bool() {
+        return Ok(value as u8 as f64);
+    }
+    value.as_number().or_throw_type(ctx, "Expected a number")
+}
+
+fn to_i64(ctx: &Ctx<'_>, value: &Value<'_>) -> Result<i64> {
+    match value.as_big_int() {
+        Some(big_int) => big_int.clone().to_i64(),
+        None => Ok(to_number(ctx, value)? as i64),
+    }
+}
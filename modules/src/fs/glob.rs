@@ -0,0 +1,334 @@
+//! Glob patterns of `fs.glob()`
+//!
+//! Patterns are matched a path segment at a time: `*` and `?` match within a
+//! segment, `[abc]`/`[a-z]`/`[!abc]` match one character of a class, `**` matches
+//! any number of segments and `{a,b}` expands to each alternative. Like in shells,
+//! wildcards don't match names starting with a dot unless the pattern segment does.
+//! Patterns starting with `/` are matched from the root and give absolute paths.
+
+use std::path::{Path, PathBuf};
+
+use xmas_vsys::fs::{FileType, FsVTable};
+use xmas_vsys::VsysResult;
+
+/// A pattern split into its segments, one per alternative of its braces
+#[derive(Debug, Clone)]
+pub struct Glob {
+    alternatives: Vec<Vec<String>>,
+    /// The alternatives starting with `/`, their segments from the root
+    absolute: Vec<Vec<String>>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let mut alternatives = Vec::new();
+        let mut absolute = Vec::new();
+        for pattern in expand_braces(pattern) {
+            let segments = pattern
+                .split('/')
+                .filter(|segment| !segment.is_empty() && *segment != ".")
+                .map(str::to_string)
+                .collect();
+            if pattern.starts_with('/') {
+                absolute.push(segments);
+            } else {
+                alternatives.push(segments);
+            }
+        }
+        Self {
+            alternatives,
+            absolute,
+        }
+    }
+
+    /// The absolute alternatives as a pattern relative to the root
+    fn absolute_part(&self) -> Self {
+        Self {
+            alternatives: self.absolute.clone(),
+            absolute: Vec::new(),
+        }
+    }
+
+    /// Whether `path`, relative to the directory the pattern is matched in, matches
+    pub fn matches(&self, path: &[&str]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|segments| matches(segments, path, false))
+    }
+
+    /// Whether a path below `dir` may match, so `dir` is worth walking into
+    pub fn may_match_below(&self, dir: &[&str]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|segments| matches(segments, dir, true))
+    }
+}
+
+/// The directories the absolute patterns are walked from, the segments before their
+/// first wildcard, leaving out the last segment since it names what is matched
+pub fn roots(patterns: &[Glob]) -> Vec<PathBuf> {
+    root_segments(patterns)
+        .into_iter()
+        .map(|segments| Path::new("/").join(segments.iter().collect::<PathBuf>()))
+        .collect()
+}
+
+fn root_segments(patterns: &[Glob]) -> Vec<Vec<String>> {
+    let mut roots: Vec<Vec<String>> = patterns
+        .iter()
+        .flat_map(|glob| &glob.absolute)
+        .map(|segments| {
+            let literal = segments[..segments.len().saturating_sub(1)]
+                .iter()
+                .take_while(|segment| !segment.contains(['*', '?', '[', '\\']));
+            literal.cloned().collect()
+        })
+        .collect();
+    // a root below another one is walked with it
+    roots.sort();
+    roots.dedup_by(|root, parent| root.starts_with(parent));
+    roots
+}
+
+/// The paths below `cwd` matching any of `patterns` but none of `exclude`, relative to
+/// `cwd`, in the order the directories are walked. The absolute alternatives are walked
+/// from their [`roots`], their paths are absolute and only excluded by absolute ones.
+pub fn walk(
+    fs: &FsVTable,
+    cwd: &Path,
+    patterns: &[Glob],
+    exclude: &[Glob],
+) -> VsysResult<Vec<PathBuf>> {
+    let mut found = walk_below(fs, cwd, Vec::new(), patterns, exclude)?;
+
+    let root = Path::new("/");
+    let absolute: Vec<Glob> = patterns.iter().map(Glob::absolute_part).collect();
+    let exclude: Vec<Glob> = exclude.iter().map(Glob::absolute_part).collect();
+    for start in root_segments(patterns) {
+        // a root that doesn't exist matches nothing
+        if let Ok(paths) = walk_below(fs, root, start, &absolute, &exclude) {
+            found.extend(paths.into_iter().map(|path| root.join(path)));
+        }
+    }
+    Ok(found)
+}
+
+/// Walks the directories below `start`, the segments of a directory in `cwd`, which
+/// must exist
+fn walk_below(
+    fs: &FsVTable,
+    cwd: &Path,
+    start: Vec<String>,
+    patterns: &[Glob],
+    exclude: &[Glob],
+) -> VsysResult<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let depth = start.len();
+    let mut dirs = vec![start];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = match (fs.read_dir)(&cwd.join(dir.iter().collect::<PathBuf>())) {
+            Ok(entries) => entries,
+            // below the start, directories may go away
+            Err(_) if dir.len() > depth => continue,
+            Err(e) => return Err(e),
+        };
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let mut path = dir.clone();
+            path.push(entry.name);
+            let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+            if exclude.iter().any(|glob| glob.matches(&segments)) {
+                continue;
+            }
+            if patterns.iter().any(|glob| glob.matches(&segments)) {
+                found.push(path.iter().collect());
+            }
+            // symlinked directories aren't followed, so a link can't make the walk loop
+            if entry.file_type == FileType::Directory
+                && patterns.iter().any(|glob| glob.may_match_below(&segments))
+            {
+                subdirs.push(path);
+            }
+        }
+        dirs.extend(subdirs.into_iter().rev());
+    }
+    Ok(found)
+}
+
+/// Matches `path` against `pattern`, or only checks it may be the start of a matching
+/// path with `prefix`
+fn matches(pattern: &[String], path: &[&str], prefix: bool) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        // a directory is only worth walking into when the pattern goes on below it
+        (_, None) if prefix => !pattern.is_empty(),
+        (_, None) => pattern.iter().all(|segment| segment == "**"),
+        (None, Some(_)) => false,
+        (Some((segment, rest)), Some((name, path_rest))) if segment == "**" => {
+            // `**` matches no segment, or this one and maybe more
+            matches(rest, path, prefix)
+                || (!name.starts_with('.') && matches(pattern, path_rest, prefix))
+        }
+        (Some((segment, rest)), Some((name, path_rest))) => {
+            match_segment(segment, name) && matches(rest, path_rest, prefix)
+        }
+    }
+}
+
+/// Matches one path segment against one pattern segment
+fn match_segment(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_chars(&pattern, &name)
+}
+
+fn match_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_chars(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_chars(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some((&c, name_rest)) = name.split_first() else {
+                return false;
+            };
+            match match_class(rest, c) {
+                Some((matched, rest)) => matched && match_chars(rest, name_rest),
+                // an unclosed bracket is a literal one
+                None => c == '[' && match_chars(rest, name_rest),
+            }
+        }
+        Some(('\\', [escaped, rest @ ..])) => {
+            name.first() == Some(escaped) && match_chars(rest, &name[1..])
+        }
+        Some((&literal, rest)) => name.first() == Some(&literal) && match_chars(rest, &name[1..]),
+    }
+}
+
+/// Matches `c` against the class following a `[`, returning whether it matched and the
+/// pattern after the closing `]`, or `None` without one
+fn match_class(class: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, class) = match class.first() {
+        Some('!' | '^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        // a `]` first in the class is a literal one
+        if class[i] == ']' && i > 0 {
+            return Some((matched != negated, &class[i + 1..]));
+        }
+        if i + 2 < class.len() && class[i + 1] == '-' && class[i + 2] != ']' {
+            matched |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Expands `{a,b}` alternatives, nested ones included
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0;
+    let mut alternatives = Vec::new();
+    let mut start = open + 1;
+    for (i, c) in pattern[open..].char_indices().map(|(i, c)| (i + open, c)) {
+        match c {
+            '{' => depth += 1,
+            ',' if depth == 1 => {
+                alternatives.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            '}' if depth == 1 => {
+                alternatives.push(&pattern[start..i]);
+                let (head, tail) = (&pattern[..open], &pattern[i + 1..]);
+                return alternatives
+                    .into_iter()
+                    .flat_map(|alternative| expand_braces(&format!("{head}{alternative}{tail}")))
+                    .collect();
+            }
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    // unbalanced braces are literal
+    vec![pattern.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).matches(&path.split('/').collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(matches("*.js", "index.js"));
+        assert!(!matches("*.js", "lib/index.js"));
+        assert!(!matches("*.js", ".eslintrc.js"));
+        assert!(matches(".*.js", ".eslintrc.js"));
+        assert!(matches("src/**/*.ts", "src/a/b/c.ts"));
+        assert!(matches("src/**/*.ts", "src/c.ts"));
+        assert!(matches("**", "a/b"));
+        assert!(matches("file?.[a-c]", "file1.b"));
+        assert!(!matches("file?.[!a-c]", "file1.b"));
+        assert!(matches("{src,test}/*.{js,mjs}", "test/a.mjs"));
+        assert!(!matches("{src,test}/*.{js,mjs}", "lib/a.js"));
+        assert!(matches("./a/b", "a/b"));
+        assert!(!matches("/a/b", "a/b"));
+    }
+
+    #[test]
+    fn test_glob_roots() {
+        let patterns = [
+            Glob::new("/etc/*.conf"),
+            Glob::new("/etc/ssh/*"),
+            Glob::new("/{usr,opt}/lib/**/*.so"),
+            Glob::new("src/*.js"),
+        ];
+        assert_eq!(
+            roots(&patterns),
+            vec![
+                PathBuf::from("/etc"),
+                PathBuf::from("/opt/lib"),
+                PathBuf::from("/usr/lib")
+            ]
+        );
+        assert_eq!(roots(&[Glob::new("/*")]), vec![PathBuf::from("/")]);
+    }
+
+    #[test]
+    fn test_walk_absolute() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "").unwrap();
+        std::fs::write(dir.join("sub/b.txt"), "").unwrap();
+
+        let patterns = [Glob::new(&format!("{}/**/*.txt", dir.display()))];
+        let exclude = [Glob::new(&format!("{}/sub/*", dir.display()))];
+        let found = walk(&FsVTable::default(), Path::new("."), &patterns, &exclude).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found, vec![dir.join("a.txt")]);
+    }
+
+    #[test]
+    fn test_glob_may_match_below() {
+        let glob = Glob::new("src/*/index.js");
+        assert!(glob.may_match_below(&["src"]));
+        assert!(glob.may_match_below(&["src", "lib"]));
+        assert!(!glob.may_match_below(&["test"]));
+        assert!(!glob.may_match_below(&["src", "lib", "deep"]));
+    }
+}
@@ -0,0 +1,2041 @@
+//! Node.js-compatible filesystem module using vsys FsVTable
+//!
+//! All filesystem operations are delegated to the vsys virtual filesystem layer,
+//! enabling sandboxed execution and custom filesystem implementations.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::buffer::Buffer;
+use crate::permissions::{audit, check_capability, get_vsys, Callers};
+use crate::utils::module::{export_default, ModuleInfo};
+use crate::utils::object::ObjectExt;
+
+use either::Either;
+use rsquickjs::class::{Trace, Tracer};
+use rsquickjs::function::Opt;
+use rsquickjs::prelude::{Async, Func, This};
+use rsquickjs::promise::MaybePromise;
+use rsquickjs::JsLifetime;
+use rsquickjs::{
+    atom::PredefinedAtom,
+    module::{Declarations, Exports, ModuleDef},
+    Class, Coerced, Ctx, Error, Exception, FromJs, Function, IntoJs, Object, Result, Value,
+};
+use xmas_vsys::fs::{DirEntry, FileStat, FileType, FsVTable, OpenOptions};
+use xmas_vsys::{Capability, Vsys, VsysError, VsysResult};
+
+use glob::Glob;
+
+mod glob;
+mod stream;
+
+// Re-export constants
+pub const CONSTANT_F_OK: u32 = 0;
+pub const CONSTANT_R_OK: u32 = 4;
+pub const CONSTANT_W_OK: u32 = 2;
+pub const CONSTANT_X_OK: u32 = 1;
+pub const CONSTANT_COPYFILE_EXCL: u32 = 1;
+pub const CONSTANT_COPYFILE_FICLONE: u32 = 2;
+pub const CONSTANT_COPYFILE_FICLONE_FORCE: u32 = 4;
+
+// ============================================================================
+// Helper macros and functions
+// ============================================================================
+
+/// Get vsys and check `op` is allowed on `path` with `check`, one of
+/// [`Capability::FsRead`] or [`Capability::FsWrite`], return error if denied
+fn check_permission<'js>(
+    ctx: &Ctx<'js>,
+    callers: &Callers,
+    op: &str,
+    path: &Path,
+    check: fn(PathBuf) -> Capability,
+) -> Result<Arc<Vsys>> {
+    let vsys =
+        get_vsys(ctx).ok_or_else(|| Exception::throw_message(ctx, "Vsys not initialized"))?;
+
+    let allowed = check_capability(ctx, callers, &check(path.to_path_buf()));
+    audit(ctx, op, &path.to_string_lossy(), allowed);
+    if !allowed {
+        return Err(Exception::throw_message(
+            ctx,
+            "Permission denied. Cannot access the file",
+        ));
+    }
+
+    Ok(vsys)
+}
+
+/// Run a vtable operation on the blocking thread pool, so slow disk IO in the
+/// promise APIs doesn't stall timers and other tasks on the event loop
+async fn blocking<T, F>(ctx: &Ctx<'_>, vsys: Arc<Vsys>, op: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&FsVTable) -> VsysResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || op(vsys.fs()))
+        .await
+        .map_err(|e| Exception::throw_message(ctx, &e.to_string()))?
+        .map_err(|e| Exception::throw_message(ctx, &e.to_string()))
+}
+
+/// Node takes timestamps as seconds since the epoch, or as `Date`s
+fn to_system_time(ctx: &Ctx<'_>, time: Value<'_>) -> Result<SystemTime> {
+    let value = time.get::<Coerced<f64>>()?.0;
+    let secs = if time.is_object() {
+        value / 1000.0
+    } else {
+        value
+    };
+    if !secs.is_finite() {
+        return Err(Exception::throw_type(ctx, "Invalid time"));
+    }
+    let offset = Duration::from_secs_f64(secs.abs());
+    Ok(if secs < 0.0 {
+        UNIX_EPOCH - offset
+    } else {
+        UNIX_EPOCH + offset
+    })
+}
+
+fn already_exists(path: &Path) -> VsysError {
+    std::io::Error::new(
+        ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+    .into()
+}
+
+/// The entries of `path` with the directory each is in, and with `recursive` the entries
+/// below it too, walked breadth first as Node does. Symlinked directories aren't followed.
+fn read_dir_entries(
+    fs: &FsVTable,
+    path: &Path,
+    recursive: bool,
+) -> VsysResult<Vec<(PathBuf, DirEntry)>> {
+    let mut found = Vec::new();
+    let mut dirs = VecDeque::from([path.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        for entry in (fs.read_dir)(&dir)? {
+            if recursive && entry.file_type == FileType::Directory {
+                dirs.push_back(dir.join(&entry.name));
+            }
+            found.push((dir.clone(), entry));
+        }
+    }
+    Ok(found)
+}
+
+/// The result of `readdir`: names relative to `path`, or `Dirent`s
+fn read_dir_result<'js>(
+    ctx: &Ctx<'js>,
+    path: &Path,
+    entries: Vec<(PathBuf, DirEntry)>,
+    with_file_types: bool,
+) -> Result<Value<'js>> {
+    if with_file_types {
+        let arr = rsquickjs::Array::new(ctx.clone())?;
+        for (i, (parent, entry)) in entries.into_iter().enumerate() {
+            let dirent = Dirent {
+                name: entry.name,
+                parent_path: parent.to_string_lossy().into_owned(),
+                file_type: entry.file_type,
+            };
+            arr.set(i, Class::instance(ctx.clone(), dirent)?)?;
+        }
+        arr.into_js(ctx)
+    } else {
+        let names: Vec<String> = entries
+            .into_iter()
+            .map(|(parent, entry)| match parent.strip_prefix(path) {
+                Ok(dir) if !dir.as_os_str().is_empty() => {
+                    dir.join(entry.name).to_string_lossy().into_owned()
+                }
+                _ => entry.name,
+            })
+            .collect();
+        names.into_js(ctx)
+    }
+}
+
+/// Copy `src` to `dest` with `copyFile`, failing when `dest` exists with `COPYFILE_EXCL`
+fn copy_file(fs: &FsVTable, src: &Path, dest: &Path, mode: u32) -> VsysResult<()> {
+    if mode & CONSTANT_COPYFILE_EXCL != 0 && (fs.lstat)(dest).is_ok() {
+        return Err(already_exists(dest));
+    }
+    (fs.copy)(src, dest).map(|_| ())
+}
+
+/// Copy one entry for `cp`, returning the entries of `src` to copy next when it is a
+/// directory. Symlinks are copied as symlinks.
+fn cp_entry(
+    fs: &FsVTable,
+    src: &Path,
+    dest: &Path,
+    file_type: FileType,
+    force: bool,
+    error_on_exist: bool,
+) -> VsysResult<Vec<DirEntry>> {
+    if file_type == FileType::Directory {
+        (fs.create_dir_all)(dest)?;
+        return (fs.read_dir)(src);
+    }
+    if (fs.lstat)(dest).is_ok() {
+        if !force {
+            if error_on_exist {
+                return Err(already_exists(dest));
+            }
+            return Ok(Vec::new());
+        }
+        if file_type == FileType::Symlink {
+            (fs.remove_file)(dest)?;
+        }
+    }
+    if file_type == FileType::Symlink {
+        (fs.symlink)(&(fs.read_link)(src)?, dest)?;
+    } else {
+        (fs.copy)(src, dest)?;
+    }
+    Ok(Vec::new())
+}
+
+/// Check `cp` may copy `src`, which is a directory only when recursive and not into itself
+fn check_cp_source(
+    ctx: &Ctx<'_>,
+    src: &Path,
+    dest: &Path,
+    file_type: FileType,
+    recursive: bool,
+) -> Result<()> {
+    if file_type != FileType::Directory {
+        return Ok(());
+    }
+    if !recursive {
+        return Err(Exception::throw_message(
+            ctx,
+            &format!(
+                "Recursive option is required to copy a directory: {}",
+                src.display()
+            ),
+        ));
+    }
+    if dest.starts_with(src) {
+        return Err(Exception::throw_message(
+            ctx,
+            &format!(
+                "Cannot copy {} to a subdirectory of itself, {}",
+                src.display(),
+                dest.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The directory `fs.glob()` matches in, checked to be readable like the roots of the
+/// absolute patterns, and the patterns
+fn glob_setup(
+    ctx: &Ctx<'_>,
+    callers: &Callers,
+    pattern: Either<String, Vec<String>>,
+    options: Option<GlobOptions>,
+) -> Result<(Arc<Vsys>, PathBuf, Vec<Glob>, Vec<Glob>)> {
+    let options = options.unwrap_or_default();
+    let cwd = PathBuf::from(options.cwd.unwrap_or_else(|| ".".to_string()));
+    let vsys = check_permission(ctx, callers, "fs.glob", &cwd, Capability::FsRead)?;
+
+    let patterns = match pattern {
+        Either::Left(pattern) => vec![Glob::new(&pattern)],
+        Either::Right(patterns) => patterns.iter().map(|p| Glob::new(p)).collect(),
+    };
+    // absolute patterns are walked from their own directories
+    for root in glob::roots(&patterns) {
+        check_permission(ctx, callers, "fs.glob", &root, Capability::FsRead)?;
+    }
+    let exclude = options.exclude.iter().map(|p| Glob::new(p)).collect();
+    Ok((vsys, cwd, patterns, exclude))
+}
+
+fn iterator_result<'js, T: IntoJs<'js>>(ctx: &Ctx<'js>, value: Option<T>) -> Result<Object<'js>> {
+    let result = Object::new(ctx.clone())?;
+    result.set("done", value.is_none())?;
+    result.set("value", value)?;
+    Ok(result)
+}
+
+/// An async iterator object, iterating itself with `next`
+fn async_iterator<'js>(ctx: &Ctx<'js>, next: Function<'js>) -> Result<Object<'js>> {
+    let iterator = Object::new(ctx.clone())?;
+    iterator.set("next", next)?;
+    iterator.set(
+        PredefinedAtom::SymbolAsyncIterator,
+        Func::from(|this: This<Object<'js>>| this.0),
+    )?;
+    Ok(iterator)
+}
+
+// ============================================================================
+// Stats class
+// ============================================================================
+
+#[derive(Clone)]
+#[rsquickjs::class]
+pub struct Stats {
+    inner: FileStat,
+}
+
+impl<'js> Trace<'js> for Stats {
+    fn trace<'a>(&self, _: Tracer<'a, 'js>) {}
+}
+
+unsafe impl<'js> JsLifetime<'js> for Stats {
+    type Changed<'to> = Stats;
+}
+
+#[rsquickjs::methods]
+impl Stats {
+    #[qjs(get)]
+    pub fn size(&self) -> u64 {
+        self.inner.size
+    }
+
+    #[qjs(get)]
+    pub fn mode(&self) -> u32 {
+        self.inner.mode
+    }
+
+    #[qjs(get)]
+    pub fn uid(&self) -> u32 {
+        self.inner.uid
+    }
+
+    #[qjs(get)]
+    pub fn gid(&self) -> u32 {
+        self.inner.gid
+    }
+
+    #[qjs(rename = "isFile")]
+    pub fn is_file(&self) -> bool {
+        self.inner.is_file()
+    }
+
+    #[qjs(rename = "isDirectory")]
+    pub fn is_directory(&self) -> bool {
+        self.inner.is_dir()
+    }
+
+    #[qjs(rename = "isSymbolicLink")]
+    pub fn is_symbolic_link(&self) -> bool {
+        self.inner.is_symlink()
+    }
+
+    #[qjs(get)]
+    pub fn mtime(&self) -> Option<f64> {
+        self.inner.modified.map(|t| {
+            t.duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0)
+        })
+    }
+
+    #[qjs(get)]
+    pub fn atime(&self) -> Option<f64> {
+        self.inner.accessed.map(|t| {
+            t.duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0)
+        })
+    }
+
+    #[qjs(get)]
+    pub fn ctime(&self) -> Option<f64> {
+        self.inner.created.map(|t| {
+            t.duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0)
+        })
+    }
+
+    #[qjs(get)]
+    pub fn birthtime(&self) -> Option<f64> {
+        self.ctime()
+    }
+}
+
+// ============================================================================
+// Dirent class
+// ============================================================================
+
+#[derive(Clone)]
+#[rsquickjs::class]
+pub struct Dirent {
+    name: String,
+    parent_path: String,
+    file_type: FileType,
+}
+
+impl<'js> Trace<'js> for Dirent {
+    fn trace<'a>(&self, _: Tracer<'a, 'js>) {}
+}
+
+unsafe impl<'js> JsLifetime<'js> for Dirent {
+    type Changed<'to> = Dirent;
+}
+
+#[rsquickjs::methods]
+impl Dirent {
+    #[qjs(get)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The directory the entry is in
+    #[qjs(get, rename = "parentPath")]
+    pub fn parent_path(&self) -> &str {
+        &self.parent_path
+    }
+
+    /// Deprecated alias of `parentPath`
+    #[qjs(get)]
+    pub fn path(&self) -> &str {
+        &self.parent_path
+    }
+
+    #[qjs(rename = "isFile")]
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileType::File
+    }
+
+    #[qjs(rename = "isDirectory")]
+    pub fn is_directory(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+
+    #[qjs(rename = "isSymbolicLink")]
+    pub fn is_symbolic_link(&self) -> bool {
+        self.file_type == FileType::Symlink
+    }
+}
+
+// ============================================================================
+// FileHandle class
+// ============================================================================
+
+#[rsquickjs::class]
+pub struct FileHandle {
+    handle: Option<xmas_vsys::fs::FsHandle>,
+    #[allow(dead_code)]
+    path: String,
+}
+
+impl<'js> Trace<'js> for FileHandle {
+    fn trace<'a>(&self, _: Tracer<'a, 'js>) {}
+}
+
+unsafe impl<'js> JsLifetime<'js> for FileHandle {
+    type Changed<'to> = FileHandle;
+}
+
+#[rsquickjs::methods]
+impl FileHandle {
+    pub async fn read<'js>(
+        &mut self,
+        ctx: Ctx<'js>,
+        size: Opt<usize>,
+        position: Opt<u64>,
+    ) -> Result<Value<'js>> {
+        let handle = self
+            .handle
+            .as_mut()
+            .ok_or_else(|| Exception::throw_message(&ctx, "File handle is closed"))?;
+
+        if let Some(position) = position.0 {
+            handle
+                .seek(SeekFrom::Start(position))
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+        }
+        let size = size.0.unwrap_or(4096);
+        let mut buf = vec![0u8; size];
+
+        let n = handle
+            .read(&mut buf)
+            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+        buf.truncate(n);
+        Buffer(buf).into_js(&ctx)
+    }
+
+    pub async fn write<'js>(
+        &mut self,
+        ctx: Ctx<'js>,
+        data: Value<'js>,
+        position: Opt<u64>,
+    ) -> Result<usize> {
+        let handle = self
+            .handle
+            .as_mut()
+            .ok_or_else(|| Exception::throw_message(&ctx, "File handle is closed"))?;
+
+        if let Some(position) = position.0 {
+            handle
+                .seek(SeekFrom::Start(position))
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+        }
+
+        let bytes = crate::utils::bytes::ObjectBytes::from(&ctx, &data)?;
+        let buf = bytes.as_bytes(&ctx)?;
+
+        handle
+            .write(buf)
+            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+    }
+
+    pub async fn close(&mut self) -> Result<()> {
+        self.handle.take();
+        Ok(())
+    }
+
+    /// A `ReadableStream` of the rest of the file, read in chunks as it is pulled
+    #[qjs(rename = "readableWebStream")]
+    pub fn readable_web_stream<'js>(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+    ) -> Result<Object<'js>> {
+        stream::readable_web_stream(ctx, this.0)
+    }
+
+    pub fn stat<'js>(&self, ctx: Ctx<'js>) -> Result<Stats> {
+        let handle = self
+            .handle
+            .as_ref()
+            .ok_or_else(|| Exception::throw_message(&ctx, "File handle is closed"))?;
+
+        let stat = handle
+            .stat()
+            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+        Ok(Stats { inner: stat })
+    }
+}
+
+// ============================================================================
+// Dir class
+// ============================================================================
+
+#[rsquickjs::class]
+pub struct Dir {
+    entries: VecDeque<DirEntry>,
+    path: String,
+    closed: bool,
+}
+
+impl<'js> Trace<'js> for Dir {
+    fn trace<'a>(&self, _: Tracer<'a, 'js>) {}
+}
+
+unsafe impl<'js> JsLifetime<'js> for Dir {
+    type Changed<'to> = Dir;
+}
+
+#[rsquickjs::methods]
+impl Dir {
+    #[qjs(get)]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub async fn read(&mut self, ctx: Ctx<'_>) -> Result<Option<Dirent>> {
+        self.read_sync(ctx)
+    }
+
+    #[qjs(rename = "readSync")]
+    pub fn read_sync(&mut self, ctx: Ctx<'_>) -> Result<Option<Dirent>> {
+        if self.closed {
+            return Err(Exception::throw_message(
+                &ctx,
+                "Directory handle was closed",
+            ));
+        }
+        Ok(self.entries.pop_front().map(|entry| Dirent {
+            name: entry.name,
+            parent_path: self.path.clone(),
+            file_type: entry.file_type,
+        }))
+    }
+
+    pub async fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        Ok(())
+    }
+
+    #[qjs(rename = "closeSync")]
+    pub fn close_sync(&mut self) {
+        self.closed = true;
+    }
+
+    /// Iterate the entries left, closing the directory after the last one
+    #[qjs(rename = PredefinedAtom::SymbolAsyncIterator)]
+    pub fn iterator<'js>(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let dir = this.0;
+        let next = Function::new(ctx.clone(), move |ctx: Ctx<'js>| {
+            let mut dir = dir.try_borrow_mut()?;
+            let dirent = dir.read_sync(ctx.clone())?;
+            if dirent.is_none() {
+                dir.closed = true;
+            }
+            iterator_result(&ctx, dirent)
+        })?;
+        async_iterator(&ctx, next)
+    }
+}
+
+// ============================================================================
+// Options structs
+// ============================================================================
+
+pub struct ReadFileOptions {
+    pub encoding: Option<String>,
+}
+
+impl<'js> FromJs<'js> for ReadFileOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let encoding = obj.get_optional::<_, String>("encoding")?;
+        Ok(Self { encoding })
+    }
+}
+
+pub struct WriteFileOptions {
+    pub mode: Option<u32>,
+}
+
+impl<'js> FromJs<'js> for WriteFileOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let mode = obj.get_optional::<_, u32>("mode")?;
+        Ok(Self { mode })
+    }
+}
+
+pub struct MkdirOptions {
+    pub recursive: bool,
+    #[allow(dead_code)]
+    pub mode: Option<u32>,
+}
+
+impl Default for MkdirOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            mode: None,
+        }
+    }
+}
+
+impl<'js> FromJs<'js> for MkdirOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let recursive = obj.get_optional::<_, bool>("recursive")?.unwrap_or(false);
+        let mode = obj.get_optional::<_, u32>("mode")?;
+        Ok(Self { recursive, mode })
+    }
+}
+
+pub struct ReaddirOptions {
+    pub with_file_types: bool,
+    pub recursive: bool,
+}
+
+impl Default for ReaddirOptions {
+    fn default() -> Self {
+        Self {
+            with_file_types: false,
+            recursive: false,
+        }
+    }
+}
+
+impl<'js> FromJs<'js> for ReaddirOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let with_file_types = obj
+            .get_optional::<_, bool>("withFileTypes")?
+            .unwrap_or(false);
+        let recursive = obj.get_optional::<_, bool>("recursive")?.unwrap_or(false);
+        Ok(Self {
+            with_file_types,
+            recursive,
+        })
+    }
+}
+
+pub struct RmOptions {
+    pub recursive: bool,
+    pub force: bool,
+}
+
+impl Default for RmOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            force: false,
+        }
+    }
+}
+
+impl<'js> FromJs<'js> for RmOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let recursive = obj.get_optional::<_, bool>("recursive")?.unwrap_or(false);
+        let force = obj.get_optional::<_, bool>("force")?.unwrap_or(false);
+        Ok(Self { recursive, force })
+    }
+}
+
+pub struct CpOptions<'js> {
+    pub recursive: bool,
+    pub force: bool,
+    pub error_on_exist: bool,
+    pub filter: Option<Function<'js>>,
+}
+
+impl Default for CpOptions<'_> {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            force: true,
+            error_on_exist: false,
+            filter: None,
+        }
+    }
+}
+
+impl<'js> FromJs<'js> for CpOptions<'js> {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let recursive = obj.get_optional::<_, bool>("recursive")?.unwrap_or(false);
+        let force = obj.get_optional::<_, bool>("force")?.unwrap_or(true);
+        let error_on_exist = obj
+            .get_optional::<_, bool>("errorOnExist")?
+            .unwrap_or(false);
+        let filter = obj.get_optional::<_, Function>("filter")?;
+        Ok(Self {
+            recursive,
+            force,
+            error_on_exist,
+            filter,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct GlobOptions {
+    pub cwd: Option<String>,
+    pub exclude: Vec<String>,
+}
+
+impl<'js> FromJs<'js> for GlobOptions {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or(Error::new_from_js(value.type_name(), "Object"))?;
+        let cwd = obj.get_optional::<_, String>("cwd")?;
+        let exclude = obj
+            .get_optional::<_, Vec<String>>("exclude")?
+            .unwrap_or_default();
+        Ok(Self { cwd, exclude })
+    }
+}
+
+// ============================================================================
+// Async fs functions (for promises)
+// ============================================================================
+
+pub async fn access(ctx: Ctx<'_>, callers: Callers, path: String, mode: Opt<u32>) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.access",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+    let mode = mode.0.unwrap_or(CONSTANT_F_OK);
+
+    blocking(&ctx, vsys, move |fs| (fs.access)(Path::new(&path), mode)).await
+}
+
+pub async fn read_file(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<Either<String, ReadFileOptions>>,
+) -> Result<Value<'_>> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.readFile",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let bytes = blocking(&ctx, vsys, move |fs| (fs.read)(Path::new(&path))).await?;
+
+    let buffer = Buffer(bytes);
+
+    if let Some(opts) = options.0 {
+        let encoding = match opts {
+            Either::Left(enc) => Some(enc),
+            Either::Right(opts) => opts.encoding,
+        };
+        if let Some(enc) = encoding {
+            return buffer.to_string(&ctx, &enc).and_then(|s| s.into_js(&ctx));
+        }
+    }
+
+    buffer.into_js(&ctx)
+}
+
+pub async fn write_file<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    data: Value<'js>,
+    options: Opt<Either<String, WriteFileOptions>>,
+) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.writeFile",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+
+    let bytes = crate::utils::bytes::ObjectBytes::from(&ctx, &data)?;
+    let buf = bytes.as_bytes(&ctx)?.to_vec();
+
+    #[cfg(unix)]
+    let mode = match options.0 {
+        Some(Either::Right(opts)) => opts.mode,
+        _ => None,
+    };
+    #[cfg(not(unix))]
+    let _ = options;
+
+    blocking(&ctx, vsys, move |fs| {
+        let path = Path::new(&path);
+        (fs.write)(path, &buf)?;
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            (fs.set_mode)(path, mode)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+pub async fn rename(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    old_path: String,
+    new_path: String,
+) -> Result<()> {
+    check_permission(
+        &ctx,
+        &callers,
+        "fs.rename",
+        Path::new(&new_path),
+        Capability::FsWrite,
+    )?;
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.rename",
+        Path::new(&old_path),
+        Capability::FsWrite,
+    )?;
+
+    blocking(&ctx, vsys, move |fs| {
+        (fs.rename)(Path::new(&old_path), Path::new(&new_path))
+    })
+    .await
+}
+
+pub async fn read_dir<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    options: Opt<ReaddirOptions>,
+) -> Result<Value<'js>> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.readdir",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let options = options.0.unwrap_or_default();
+    let root = PathBuf::from(&path);
+    let recursive = options.recursive;
+    let entries = blocking(&ctx, vsys, move |fs| {
+        read_dir_entries(fs, Path::new(&path), recursive)
+    })
+    .await?;
+
+    read_dir_result(&ctx, &root, entries, options.with_file_types)
+}
+
+pub async fn mkdir(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<MkdirOptions>,
+) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.mkdir",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+    let opts = options.0.unwrap_or_default();
+
+    blocking(&ctx, vsys, move |fs| {
+        let path = Path::new(&path);
+        if opts.recursive {
+            (fs.create_dir_all)(path)?;
+        } else {
+            (fs.create_dir)(path)?;
+        }
+
+        #[cfg(unix)]
+        if let Some(mode) = opts.mode {
+            (fs.set_mode)(path, mode)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+pub async fn mkdtemp(ctx: Ctx<'_>, prefix: String) -> Result<String> {
+    let vsys =
+        get_vsys(&ctx).ok_or_else(|| Exception::throw_message(&ctx, "Vsys not initialized"))?;
+
+    let path = blocking(&ctx, vsys, move |fs| (fs.mkdtemp)(&prefix)).await?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub async fn rmfile(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<RmOptions>,
+) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.rm",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+    let opts = options.0.unwrap_or_default();
+
+    let result = blocking(&ctx, vsys, move |fs| {
+        if opts.recursive {
+            (fs.remove_dir_all)(Path::new(&path))
+        } else {
+            (fs.remove_file)(Path::new(&path))
+        }
+    })
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(_) if opts.force => Ok(()), // Ignore errors in force mode
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn rmdir(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.rmdir",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+
+    blocking(&ctx, vsys, move |fs| (fs.remove_dir)(Path::new(&path))).await
+}
+
+pub async fn stat_fn(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Stats> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.stat",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let stat = blocking(&ctx, vsys, move |fs| (fs.stat)(Path::new(&path))).await?;
+
+    Ok(Stats { inner: stat })
+}
+
+pub async fn lstat_fn(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Stats> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.lstat",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let stat = blocking(&ctx, vsys, move |fs| (fs.lstat)(Path::new(&path))).await?;
+
+    Ok(Stats { inner: stat })
+}
+
+pub async fn chmod(ctx: Ctx<'_>, callers: Callers, path: String, mode: u32) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.chmod",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+
+    blocking(&ctx, vsys, move |fs| (fs.set_mode)(Path::new(&path), mode)).await
+}
+
+pub async fn symlink(ctx: Ctx<'_>, callers: Callers, target: String, path: String) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.symlink",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+
+    blocking(&ctx, vsys, move |fs| {
+        (fs.symlink)(Path::new(&target), Path::new(&path))
+    })
+    .await
+}
+
+pub async fn open(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    flags: Opt<String>,
+    mode: Opt<u32>,
+) -> Result<FileHandle> {
+    let flags = flags.0.unwrap_or_else(|| "r".to_string());
+    let mut options = OpenOptions::new();
+
+    match flags.as_str() {
+        "r" => {
+            options = options.read(true);
+        }
+        "r+" => {
+            options = options.read(true).write(true);
+        }
+        "w" => {
+            options = options.write(true).create(true).truncate(true);
+        }
+        "w+" => {
+            options = options.read(true).write(true).create(true).truncate(true);
+        }
+        "a" => {
+            options = options.append(true).create(true);
+        }
+        "a+" => {
+            options = options.read(true).append(true).create(true);
+        }
+        "wx" | "xw" => {
+            options = options.write(true).create_new(true);
+        }
+        _ => {
+            options = options.read(true);
+        }
+    }
+
+    if let Some(m) = mode.0 {
+        options = options.mode(m);
+    }
+
+    let writes = options.write || options.append;
+    let capability = if writes {
+        Capability::FsWrite
+    } else {
+        Capability::FsRead
+    };
+    let vsys = check_permission(&ctx, &callers, "fs.open", Path::new(&path), capability)?;
+    if writes && options.read {
+        check_permission(
+            &ctx,
+            &callers,
+            "fs.open",
+            Path::new(&path),
+            Capability::FsRead,
+        )?;
+    }
+
+    let open_path = path.clone();
+    let handle = blocking(&ctx, vsys, move |fs| {
+        (fs.open)(Path::new(&open_path), &options)
+    })
+    .await?;
+
+    Ok(FileHandle {
+        handle: Some(handle),
+        path,
+    })
+}
+
+pub async fn append_file<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    data: Value<'js>,
+    options: Opt<Either<String, WriteFileOptions>>,
+) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.appendFile",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+
+    let bytes = crate::utils::bytes::ObjectBytes::from(&ctx, &data)?;
+    let buf = bytes.as_bytes(&ctx)?.to_vec();
+
+    #[cfg(unix)]
+    let mode = match options.0 {
+        Some(Either::Right(opts)) => opts.mode,
+        _ => None,
+    };
+    #[cfg(not(unix))]
+    let _ = options;
+
+    blocking(&ctx, vsys, move |fs| {
+        let path = Path::new(&path);
+        (fs.append)(path, &buf)?;
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            (fs.set_mode)(path, mode)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+pub async fn copy_file_fn(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    src: String,
+    dest: String,
+    mode: Opt<u32>,
+) -> Result<()> {
+    check_permission(
+        &ctx,
+        &callers,
+        "fs.copyFile",
+        Path::new(&dest),
+        Capability::FsWrite,
+    )?;
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.copyFile",
+        Path::new(&src),
+        Capability::FsRead,
+    )?;
+    let mode = mode.0.unwrap_or(0);
+
+    blocking(&ctx, vsys, move |fs| {
+        copy_file(fs, Path::new(&src), Path::new(&dest), mode)
+    })
+    .await
+}
+
+pub async fn cp<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    src: String,
+    dest: String,
+    options: Opt<CpOptions<'js>>,
+) -> Result<()> {
+    check_permission(
+        &ctx,
+        &callers,
+        "fs.cp",
+        Path::new(&dest),
+        Capability::FsWrite,
+    )?;
+    let vsys = check_permission(&ctx, &callers, "fs.cp", Path::new(&src), Capability::FsRead)?;
+    let opts = options.0.unwrap_or_default();
+
+    let mut pending = vec![(PathBuf::from(src), PathBuf::from(dest), None)];
+    while let Some((src, dest, file_type)) = pending.pop() {
+        if let Some(filter) = &opts.filter {
+            let keep = filter
+                .call::<_, MaybePromise>((
+                    src.to_string_lossy().into_owned(),
+                    dest.to_string_lossy().into_owned(),
+                ))?
+                .into_future::<Coerced<bool>>()
+                .await?;
+            if !keep.0 {
+                continue;
+            }
+        }
+        let file_type = match file_type {
+            Some(file_type) => file_type,
+            None => {
+                let path = src.clone();
+                let stat = blocking(&ctx, vsys.clone(), move |fs| (fs.lstat)(&path)).await?;
+                check_cp_source(&ctx, &src, &dest, stat.file_type, opts.recursive)?;
+                stat.file_type
+            }
+        };
+
+        let (entry_src, entry_dest) = (src.clone(), dest.clone());
+        let (force, error_on_exist) = (opts.force, opts.error_on_exist);
+        let entries = blocking(&ctx, vsys.clone(), move |fs| {
+            cp_entry(
+                fs,
+                &entry_src,
+                &entry_dest,
+                file_type,
+                force,
+                error_on_exist,
+            )
+        })
+        .await?;
+        pending.extend(entries.into_iter().rev().map(|entry| {
+            (
+                src.join(&entry.name),
+                dest.join(&entry.name),
+                Some(entry.file_type),
+            )
+        }));
+    }
+    Ok(())
+}
+
+pub async fn truncate(ctx: Ctx<'_>, callers: Callers, path: String, len: Opt<u64>) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.truncate",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+    let len = len.0.unwrap_or(0);
+
+    blocking(&ctx, vsys, move |fs| (fs.truncate)(Path::new(&path), len)).await
+}
+
+pub async fn utimes<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    atime: Value<'js>,
+    mtime: Value<'js>,
+) -> Result<()> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.utimes",
+        Path::new(&path),
+        Capability::FsWrite,
+    )?;
+    let accessed = to_system_time(&ctx, atime)?;
+    let modified = to_system_time(&ctx, mtime)?;
+
+    blocking(&ctx, vsys, move |fs| {
+        (fs.set_times)(Path::new(&path), accessed, modified)
+    })
+    .await
+}
+
+pub async fn link(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    existing_path: String,
+    new_path: String,
+) -> Result<()> {
+    check_permission(
+        &ctx,
+        &callers,
+        "fs.link",
+        Path::new(&new_path),
+        Capability::FsWrite,
+    )?;
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.link",
+        Path::new(&existing_path),
+        Capability::FsRead,
+    )?;
+
+    blocking(&ctx, vsys, move |fs| {
+        (fs.hard_link)(Path::new(&existing_path), Path::new(&new_path))
+    })
+    .await
+}
+
+pub async fn realpath(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<String> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.realpath",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let path = blocking(&ctx, vsys, move |fs| (fs.canonicalize)(Path::new(&path))).await?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub async fn opendir(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Dir> {
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.opendir",
+        Path::new(&path),
+        Capability::FsRead,
+    )?;
+
+    let read_path = path.clone();
+    let entries = blocking(&ctx, vsys, move |fs| (fs.read_dir)(Path::new(&read_path))).await?;
+
+    Ok(Dir {
+        entries: entries.into(),
+        path,
+        closed: false,
+    })
+}
+
+/// `fs.promises.glob()`, an async iterator of the matching paths
+pub fn glob_fn<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    pattern: Either<String, Vec<String>>,
+    options: Opt<GlobOptions>,
+) -> Result<Object<'js>> {
+    // the directories are walked on the first `next()`, each call then takes a path found
+    let walk = glob_setup(&ctx, &callers, pattern, options.0)?;
+    let walk = Rc::new(RefCell::new(Some(walk)));
+    let found = Rc::new(RefCell::new(VecDeque::new()));
+    let next = Function::new(
+        ctx.clone(),
+        Async(move |ctx: Ctx<'js>| {
+            let walk = walk.borrow_mut().take();
+            let found = found.clone();
+            async move {
+                if let Some((vsys, cwd, patterns, exclude)) = walk {
+                    let paths = blocking(&ctx, vsys, move |fs| {
+                        glob::walk(fs, &cwd, &patterns, &exclude)
+                    })
+                    .await?;
+                    found.borrow_mut().extend(paths);
+                }
+                let path = found.borrow_mut().pop_front();
+                iterator_result(&ctx, path.map(|path| path.to_string_lossy().into_owned()))
+            }
+        }),
+    )?;
+    async_iterator(&ctx, next)
+}
+
+// ============================================================================
+// Sync fs functions
+// ============================================================================
+
+pub fn access_sync(ctx: Ctx<'_>, callers: Callers, path: String, mode: Opt<u32>) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.access", path_obj, Capability::FsRead)?;
+    let mode = mode.0.unwrap_or(CONSTANT_F_OK);
+
+    (vsys.fs().access)(path_obj, mode).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn read_file_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<Either<String, ReadFileOptions>>,
+) -> Result<Value<'_>> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.readFile", path_obj, Capability::FsRead)?;
+
+    let bytes =
+        (vsys.fs().read)(path_obj).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    let buffer = Buffer(bytes);
+
+    if let Some(opts) = options.0 {
+        let encoding = match opts {
+            Either::Left(enc) => Some(enc),
+            Either::Right(opts) => opts.encoding,
+        };
+        if let Some(enc) = encoding {
+            return buffer.to_string(&ctx, &enc).and_then(|s| s.into_js(&ctx));
+        }
+    }
+
+    buffer.into_js(&ctx)
+}
+
+pub fn write_file_sync<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    data: Value<'js>,
+    options: Opt<Either<String, WriteFileOptions>>,
+) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.writeFile",
+        path_obj,
+        Capability::FsWrite,
+    )?;
+
+    let bytes = crate::utils::bytes::ObjectBytes::from(&ctx, &data)?;
+    let buf = bytes.as_bytes(&ctx)?;
+
+    (vsys.fs().write)(path_obj, buf).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    #[cfg(unix)]
+    if let Some(Either::Right(opts)) = options.0 {
+        if let Some(mode) = opts.mode {
+            (vsys.fs().set_mode)(path_obj, mode)
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = options;
+
+    Ok(())
+}
+
+pub fn rename_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    old_path: String,
+    new_path: String,
+) -> Result<()> {
+    let old = Path::new(&old_path);
+    let new = Path::new(&new_path);
+    check_permission(&ctx, &callers, "fs.rename", new, Capability::FsWrite)?;
+    let vsys = check_permission(&ctx, &callers, "fs.rename", old, Capability::FsWrite)?;
+
+    (vsys.fs().rename)(old, new).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn read_dir_sync<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    options: Opt<ReaddirOptions>,
+) -> Result<Value<'js>> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.readdir", path_obj, Capability::FsRead)?;
+
+    let options = options.0.unwrap_or_default();
+    let entries = read_dir_entries(vsys.fs(), path_obj, options.recursive)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    read_dir_result(&ctx, path_obj, entries, options.with_file_types)
+}
+
+pub fn mkdir_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<MkdirOptions>,
+) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.mkdir", path_obj, Capability::FsWrite)?;
+    let opts = options.0.unwrap_or_default();
+
+    let result = if opts.recursive {
+        (vsys.fs().create_dir_all)(path_obj)
+    } else {
+        (vsys.fs().create_dir)(path_obj)
+    };
+
+    result.map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    #[cfg(unix)]
+    if let Some(mode) = opts.mode {
+        (vsys.fs().set_mode)(path_obj, mode)
+            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+pub fn mkdtemp_sync(ctx: Ctx<'_>, prefix: String) -> Result<String> {
+    let vsys =
+        get_vsys(&ctx).ok_or_else(|| Exception::throw_message(&ctx, "Vsys not initialized"))?;
+
+    let path =
+        (vsys.fs().mkdtemp)(&prefix).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub fn rmfile_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    path: String,
+    options: Opt<RmOptions>,
+) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.rm", path_obj, Capability::FsWrite)?;
+    let opts = options.0.unwrap_or_default();
+
+    let result = if opts.recursive {
+        (vsys.fs().remove_dir_all)(path_obj)
+    } else {
+        (vsys.fs().remove_file)(path_obj)
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(_) if opts.force => Ok(()),
+        Err(e) => Err(Exception::throw_message(&ctx, &e.to_string())),
+    }
+}
+
+pub fn rmdir_sync(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.rmdir", path_obj, Capability::FsWrite)?;
+
+    (vsys.fs().remove_dir)(path_obj).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn stat_fn_sync(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Stats> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.stat", path_obj, Capability::FsRead)?;
+
+    let stat =
+        (vsys.fs().stat)(path_obj).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(Stats { inner: stat })
+}
+
+pub fn lstat_fn_sync(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Stats> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.lstat", path_obj, Capability::FsRead)?;
+
+    let stat =
+        (vsys.fs().lstat)(path_obj).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(Stats { inner: stat })
+}
+
+pub fn chmod_sync(ctx: Ctx<'_>, callers: Callers, path: String, mode: u32) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.chmod", path_obj, Capability::FsWrite)?;
+
+    (vsys.fs().set_mode)(path_obj, mode).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn symlink_sync(ctx: Ctx<'_>, callers: Callers, target: String, path: String) -> Result<()> {
+    let target_obj = Path::new(&target);
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.symlink", path_obj, Capability::FsWrite)?;
+
+    (vsys.fs().symlink)(target_obj, path_obj)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn append_file_sync<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    data: Value<'js>,
+    options: Opt<Either<String, WriteFileOptions>>,
+) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(
+        &ctx,
+        &callers,
+        "fs.appendFile",
+        path_obj,
+        Capability::FsWrite,
+    )?;
+
+    let bytes = crate::utils::bytes::ObjectBytes::from(&ctx, &data)?;
+    let buf = bytes.as_bytes(&ctx)?;
+
+    (vsys.fs().append)(path_obj, buf)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    #[cfg(unix)]
+    if let Some(Either::Right(opts)) = options.0 {
+        if let Some(mode) = opts.mode {
+            (vsys.fs().set_mode)(path_obj, mode)
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = options;
+
+    Ok(())
+}
+
+pub fn copy_file_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    src: String,
+    dest: String,
+    mode: Opt<u32>,
+) -> Result<()> {
+    let src = Path::new(&src);
+    let dest = Path::new(&dest);
+    check_permission(&ctx, &callers, "fs.copyFile", dest, Capability::FsWrite)?;
+    let vsys = check_permission(&ctx, &callers, "fs.copyFile", src, Capability::FsRead)?;
+
+    copy_file(vsys.fs(), src, dest, mode.0.unwrap_or(0))
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn cp_sync<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    src: String,
+    dest: String,
+    options: Opt<CpOptions<'js>>,
+) -> Result<()> {
+    check_permission(
+        &ctx,
+        &callers,
+        "fs.cp",
+        Path::new(&dest),
+        Capability::FsWrite,
+    )?;
+    let vsys = check_permission(&ctx, &callers, "fs.cp", Path::new(&src), Capability::FsRead)?;
+    let opts = options.0.unwrap_or_default();
+    let fs = vsys.fs();
+
+    let mut pending = vec![(PathBuf::from(src), PathBuf::from(dest), None)];
+    while let Some((src, dest, file_type)) = pending.pop() {
+        if let Some(filter) = &opts.filter {
+            let keep: Coerced<bool> = filter.call((
+                src.to_string_lossy().into_owned(),
+                dest.to_string_lossy().into_owned(),
+            ))?;
+            if !keep.0 {
+                continue;
+            }
+        }
+        let file_type = match file_type {
+            Some(file_type) => file_type,
+            None => {
+                let stat =
+                    (fs.lstat)(&src).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+                check_cp_source(&ctx, &src, &dest, stat.file_type, opts.recursive)?;
+                stat.file_type
+            }
+        };
+
+        let entries = cp_entry(fs, &src, &dest, file_type, opts.force, opts.error_on_exist)
+            .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+        pending.extend(entries.into_iter().rev().map(|entry| {
+            (
+                src.join(&entry.name),
+                dest.join(&entry.name),
+                Some(entry.file_type),
+            )
+        }));
+    }
+    Ok(())
+}
+
+pub fn truncate_sync(ctx: Ctx<'_>, callers: Callers, path: String, len: Opt<u64>) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.truncate", path_obj, Capability::FsWrite)?;
+
+    (vsys.fs().truncate)(path_obj, len.0.unwrap_or(0))
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn utimes_sync<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    path: String,
+    atime: Value<'js>,
+    mtime: Value<'js>,
+) -> Result<()> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.utimes", path_obj, Capability::FsWrite)?;
+    let accessed = to_system_time(&ctx, atime)?;
+    let modified = to_system_time(&ctx, mtime)?;
+
+    (vsys.fs().set_times)(path_obj, accessed, modified)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn link_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    existing_path: String,
+    new_path: String,
+) -> Result<()> {
+    let existing = Path::new(&existing_path);
+    let new = Path::new(&new_path);
+    check_permission(&ctx, &callers, "fs.link", new, Capability::FsWrite)?;
+    let vsys = check_permission(&ctx, &callers, "fs.link", existing, Capability::FsRead)?;
+
+    (vsys.fs().hard_link)(existing, new).map_err(|e| Exception::throw_message(&ctx, &e.to_string()))
+}
+
+pub fn realpath_sync(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<String> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.realpath", path_obj, Capability::FsRead)?;
+
+    let path = (vsys.fs().canonicalize)(path_obj)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+pub fn opendir_sync(ctx: Ctx<'_>, callers: Callers, path: String) -> Result<Dir> {
+    let path_obj = Path::new(&path);
+    let vsys = check_permission(&ctx, &callers, "fs.opendir", path_obj, Capability::FsRead)?;
+
+    let entries = (vsys.fs().read_dir)(path_obj)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(Dir {
+        entries: entries.into(),
+        path,
+        closed: false,
+    })
+}
+
+pub fn glob_sync(
+    ctx: Ctx<'_>,
+    callers: Callers,
+    pattern: Either<String, Vec<String>>,
+    options: Opt<GlobOptions>,
+) -> Result<Vec<String>> {
+    let (vsys, cwd, patterns, exclude) = glob_setup(&ctx, &callers, pattern, options.0)?;
+
+    let paths = glob::walk(vsys.fs(), &cwd, &patterns, &exclude)
+        .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?;
+
+    Ok(paths
+        .into_iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+// ============================================================================
+// Module definitions
+// ============================================================================
+
+pub struct FsPromisesModule;
+
+impl ModuleDef for FsPromisesModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("access")?;
+        declare.declare("open")?;
+        declare.declare("readFile")?;
+        declare.declare("writeFile")?;
+        declare.declare("rename")?;
+        declare.declare("readdir")?;
+        declare.declare("mkdir")?;
+        declare.declare("mkdtemp")?;
+        declare.declare("rm")?;
+        declare.declare("rmdir")?;
+        declare.declare("stat")?;
+        declare.declare("lstat")?;
+        declare.declare("constants")?;
+        declare.declare("chmod")?;
+        declare.declare("symlink")?;
+        declare.declare("appendFile")?;
+        declare.declare("copyFile")?;
+        declare.declare("cp")?;
+        declare.declare("truncate")?;
+        declare.declare("utimes")?;
+        declare.declare("link")?;
+        declare.declare("realpath")?;
+        declare.declare("opendir")?;
+        declare.declare("glob")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        let globals = ctx.globals();
+        Class::<Dir>::define(&globals)?;
+        Class::<Dirent>::define(&globals)?;
+        Class::<FileHandle>::define(&globals)?;
+        Class::<Stats>::define(&globals)?;
+
+        export_default(ctx, exports, |default| {
+            export_promises(ctx, default)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<FsPromisesModule> for ModuleInfo<FsPromisesModule> {
+    fn from(val: FsPromisesModule) -> Self {
+        ModuleInfo {
+            name: "fs/promises",
+            module: val,
+        }
+    }
+}
+
+pub struct FsModule;
+
+impl ModuleDef for FsModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("promises")?;
+        declare.declare("accessSync")?;
+        declare.declare("mkdirSync")?;
+        declare.declare("mkdtempSync")?;
+        declare.declare("readdirSync")?;
+        declare.declare("readFileSync")?;
+        declare.declare("rmdirSync")?;
+        declare.declare("rmSync")?;
+        declare.declare("statSync")?;
+        declare.declare("lstatSync")?;
+        declare.declare("writeFileSync")?;
+        declare.declare("constants")?;
+        declare.declare("chmodSync")?;
+        declare.declare("renameSync")?;
+        declare.declare("symlinkSync")?;
+        declare.declare("appendFileSync")?;
+        declare.declare("copyFileSync")?;
+        declare.declare("cpSync")?;
+        declare.declare("truncateSync")?;
+        declare.declare("utimesSync")?;
+        declare.declare("linkSync")?;
+        declare.declare("realpathSync")?;
+        declare.declare("opendirSync")?;
+        declare.declare("globSync")?;
+        declare.declare("createReadStream")?;
+        declare.declare("createWriteStream")?;
+        declare.declare("ReadStream")?;
+        declare.declare("WriteStream")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        let globals = ctx.globals();
+        Class::<Dir>::define(&globals)?;
+        Class::<Dirent>::define(&globals)?;
+        Class::<FileHandle>::define(&globals)?;
+        Class::<Stats>::define(&globals)?;
+
+        export_default(ctx, exports, |default| {
+            let promises = Object::new(ctx.clone())?;
+            export_promises(ctx, &promises)?;
+            export_constants(ctx, default)?;
+
+            default.set("promises", promises)?;
+            default.set("accessSync", Func::from(access_sync))?;
+            default.set("mkdirSync", Func::from(mkdir_sync))?;
+            default.set("mkdtempSync", Func::from(mkdtemp_sync))?;
+            default.set("readdirSync", Func::from(read_dir_sync))?;
+            default.set("readFileSync", Func::from(read_file_sync))?;
+            default.set("rmdirSync", Func::from(rmdir_sync))?;
+            default.set("rmSync", Func::from(rmfile_sync))?;
+            default.set("statSync", Func::from(stat_fn_sync))?;
+            default.set("lstatSync", Func::from(lstat_fn_sync))?;
+            default.set("writeFileSync", Func::from(write_file_sync))?;
+            default.set("chmodSync", Func::from(chmod_sync))?;
+            default.set("renameSync", Func::from(rename_sync))?;
+            default.set("symlinkSync", Func::from(symlink_sync))?;
+            default.set("appendFileSync", Func::from(append_file_sync))?;
+            default.set("copyFileSync", Func::from(copy_file_sync))?;
+            default.set("cpSync", Func::from(cp_sync))?;
+            default.set("truncateSync", Func::from(truncate_sync))?;
+            default.set("utimesSync", Func::from(utimes_sync))?;
+            default.set("linkSync", Func::from(link_sync))?;
+            default.set("realpathSync", Func::from(realpath_sync))?;
+            default.set("opendirSync", Func::from(opendir_sync))?;
+            default.set("globSync", Func::from(glob_sync))?;
+            stream::export_streams(ctx, default)?;
+            Ok(())
+        })
+    }
+}
+
+fn export_promises<'js>(ctx: &Ctx<'js>, exports: &Object<'js>) -> Result<()> {
+    export_constants(ctx, exports)?;
+    exports.set("access", Func::from(Async(access)))?;
+    exports.set("open", Func::from(Async(open)))?;
+    exports.set("readFile", Func::from(Async(read_file)))?;
+    exports.set("writeFile", Func::from(Async(write_file)))?;
+    exports.set("rename", Func::from(Async(rename)))?;
+    exports.set("readdir", Func::from(Async(read_dir)))?;
+    exports.set("mkdir", Func::from(Async(mkdir)))?;
+    exports.set("mkdtemp", Func::from(Async(mkdtemp)))?;
+    exports.set("rm", Func::from(Async(rmfile)))?;
+    exports.set("rmdir", Func::from(Async(rmdir)))?;
+    exports.set("stat", Func::from(Async(stat_fn)))?;
+    exports.set("lstat", Func::from(Async(lstat_fn)))?;
+    exports.set("chmod", Func::from(Async(chmod)))?;
+    exports.set("symlink", Func::from(Async(symlink)))?;
+    exports.set("appendFile", Func::from(Async(append_file)))?;
+    exports.set("copyFile", Func::from(Async(copy_file_fn)))?;
+    exports.set("cp", Func::from(Async(cp)))?;
+    exports.set("truncate", Func::from(Async(truncate)))?;
+    exports.set("utimes", Func::from(Async(utimes)))?;
+    exports.set("link", Func::from(Async(link)))?;
+    exports.set("realpath", Func::from(Async(realpath)))?;
+    exports.set("opendir", Func::from(Async(opendir)))?;
+    exports.set("glob", Func::from(glob_fn))?;
+    Ok(())
+}
+
+fn export_constants<'js>(ctx: &Ctx<'js>, exports: &Object<'js>) -> Result<()> {
+    let constants = Object::new(ctx.clone())?;
+    constants.set("F_OK", CONSTANT_F_OK)?;
+    constants.set("R_OK", CONSTANT_R_OK)?;
+    constants.set("W_OK", CONSTANT_W_OK)?;
+    constants.set("X_OK", CONSTANT_X_OK)?;
+    constants.set("COPYFILE_EXCL", CONSTANT_COPYFILE_EXCL)?;
+    constants.set("COPYFILE_FICLONE", CONSTANT_COPYFILE_FICLONE)?;
+    constants.set("COPYFILE_FICLONE_FORCE", CONSTANT_COPYFILE_FICLONE_FORCE)?;
+    exports.set("constants", constants)?;
+    Ok(())
+}
+
+impl From<FsModule> for ModuleInfo<FsModule> {
+    fn from(val: FsModule) -> Self {
+        ModuleInfo {
+            name: "fs",
+            module: val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xmas_vsys::Permissions;
+
+    use crate::utils::{
+        primordials::{BasePrimordials, Primordial},
+        test::{call_test, test_async_with, ModuleEvaluator},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cp_and_glob() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(dir.join("src/lib")).unwrap();
+        for file in [
+            "src/index.js",
+            "src/lib/a.js",
+            "src/lib/a.test.js",
+            "src/README.md",
+        ] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                crate::buffer::init(&ctx).unwrap();
+                let vsys = Vsys::builder()
+                    .permissions(Permissions::allow_all())
+                    .build();
+                crate::permissions::init(ctx.clone(), Arc::new(vsys)).unwrap();
+                ModuleEvaluator::eval_rust::<FsModule>(ctx.clone(), "fs")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import fs from 'fs';
+                        export async function test(dir) {
+                            const results = [];
+                            fs.cpSync(dir + '/src', dir + '/out', {
+                                recursive: true,
+                                filter: (src) => !src.endsWith('.test.js'),
+                            });
+                            results.push(fs.globSync('**/*.js', { cwd: dir + '/out' }).join(','));
+                            results.push(fs.globSync('{src,out}/*.md', { cwd: dir }).join(','));
+
+                            await fs.promises.cp(dir + '/src/index.js', dir + '/out/index.js', {
+                                errorOnExist: true,
+                                force: false,
+                            }).catch(() => results.push('exists'));
+
+                            const found = [];
+                            for await (const path of fs.promises.glob('out/**', { cwd: dir, exclude: ['out/lib'] })) {
+                                found.push(path);
+                            }
+                            results.push(found.join(','));
+
+                            const names = [];
+                            for await (const entry of fs.opendirSync(dir + '/out/lib')) {
+                                names.push(entry.name);
+                            }
+                            results.push(names.join(','));
+
+                            results.push(fs.readdirSync(dir + '/out', { recursive: true }).sort().join(','));
+                            const dirents = await fs.promises.readdir(dir + '/out', { recursive: true, withFileTypes: true });
+                            const nested = dirents.find((dirent) => dirent.name === 'a.js');
+                            results.push(nested.parentPath.endsWith('lib') && nested.path === nested.parentPath);
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let dir = dir.to_string_lossy().into_owned();
+                let result = call_test::<String, _>(&ctx, &module, (dir.clone(),)).await;
+                let sep = std::path::MAIN_SEPARATOR;
+                assert_eq!(
+                    result,
+                    format!(
+                        "index.js,lib{sep}a.js|out{sep}README.md,src{sep}README.md|exists|\
+                         out,out{sep}README.md,out{sep}index.js|a.js|\
+                         README.md,index.js,lib,lib{sep}a.js|true"
+                    )
+                );
+                std::fs::remove_dir_all(&dir).unwrap();
+            })
+        })
+        .await;
+    }
+}
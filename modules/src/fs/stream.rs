@@ -0,0 +1,464 @@
+//! File streams: `fs.createReadStream()`, `fs.createWriteStream()` and
+//! `FileHandle.readableWebStream()`
+//!
+//! Files are read and written a chunk at a time through a [`FileHandle`], so piping a
+//! large file through transforms never loads it whole.
+
+use rsquickjs::{
+    function::{Constructor, This},
+    prelude::Async,
+    Class, Ctx, Exception, Function, Object, Result, TypedArray, Value,
+};
+
+use crate::event::{Emitter, EventEmitter};
+
+use super::{open, FileHandle};
+
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+// The Node streams are kept in JS on top of `EventEmitter` and the promise `open()`, as
+// flowing and paused modes, piping and async iteration are all driven by listeners.
+const FS_STREAMS_SOURCE: &str = r#"
+((EventEmitter, open) => {
+    const kReadHighWaterMark = 64 * 1024;
+    const kWriteHighWaterMark = 16 * 1024;
+
+    const toOptions = (options) =>
+        typeof options === "string" ? { encoding: options } : options ?? {};
+
+    const outOfRange = (name, value) => {
+        const err = new RangeError(
+            `The value of "${name}" is out of range. Received ${value}`
+        );
+        err.code = "ERR_OUT_OF_RANGE";
+        return err;
+    };
+    const validatePosition = (name, value) => {
+        if (value !== undefined && !(Number.isSafeInteger(value) && value >= 0)) {
+            throw outOfRange(name, value);
+        }
+    };
+
+    // resolves to the handle of the stream, or `null` once it was destroyed with the error
+    const openHandle = (stream, flags, mode) =>
+        (mode === undefined ? open(stream.path, flags) : open(stream.path, flags, mode)).then(
+            (handle) => {
+                stream.pending = false;
+                if (!stream.destroyed) {
+                    stream.emit("open");
+                    stream.emit("ready");
+                }
+                return handle;
+            },
+            (err) => {
+                destroy(stream, err);
+                return null;
+            }
+        );
+
+    const destroy = (stream, err) => {
+        if (stream.destroyed) return stream;
+        stream.destroyed = true;
+        stream.errored = err ?? null;
+        stream._opened
+            .then((handle) => handle?.close())
+            .then(() => {
+                stream.closed = true;
+                if (err) stream.emit("error", err);
+                stream.emit("close");
+            });
+        return stream;
+    };
+
+    // the next chunk from the position of the stream, `null` past `end` or the end of file
+    const readChunk = async (stream, handle) => {
+        const size = Math.min(
+            stream.readableHighWaterMark,
+            stream.end - stream._position + 1
+        );
+        const chunk = size > 0 ? await handle.read(size, stream._position) : null;
+        if (!chunk || chunk.length === 0) {
+            stream.readableEnded = true;
+            stream.emit("end");
+            return null;
+        }
+        stream._position += chunk.length;
+        stream.bytesRead += chunk.length;
+        if (stream._decoder) return stream._decoder.decode(chunk, { stream: true });
+        return stream._encoding ? chunk.toString(stream._encoding) : chunk;
+    };
+
+    const flow = async (stream) => {
+        if (stream._reading) return;
+        stream._reading = true;
+        try {
+            const handle = await stream._opened;
+            while (
+                handle &&
+                stream.readableFlowing &&
+                !stream.destroyed &&
+                !stream.readableEnded
+            ) {
+                const chunk = await readChunk(stream, handle);
+                if (chunk === null) {
+                    destroy(stream);
+                } else {
+                    stream.emit("data", chunk);
+                }
+            }
+        } catch (err) {
+            destroy(stream, err);
+        } finally {
+            stream._reading = false;
+        }
+    };
+
+    class ReadStream extends EventEmitter {
+        constructor(path, options) {
+            super();
+            options = toOptions(options);
+            const { start, end = Infinity } = options;
+            validatePosition("start", start);
+            if (end !== Infinity) validatePosition("end", end);
+            if (start > end) throw outOfRange("start", start);
+
+            this.path = path;
+            this.start = start;
+            this.end = end;
+            this.bytesRead = 0;
+            this.pending = true;
+            this.destroyed = false;
+            this.closed = false;
+            this.errored = null;
+            this.readableEnded = false;
+            this.readableFlowing = null;
+            this.readableHighWaterMark = options.highWaterMark ?? kReadHighWaterMark;
+            this._position = start ?? 0;
+            this._reading = false;
+            this._encoding = null;
+            this._decoder = null;
+            if (options.encoding) this.setEncoding(options.encoding);
+            this._opened = openHandle(this, options.flags ?? "r", options.mode);
+        }
+
+        // like Node, a `data` listener switches the stream to flowing mode
+        on(event, listener) {
+            super.on(event, listener);
+            if (event === "data" && this.readableFlowing !== false) this.resume();
+            return this;
+        }
+
+        addListener(event, listener) {
+            return this.on(event, listener);
+        }
+
+        setEncoding(encoding) {
+            this._encoding = encoding;
+            // utf-8 sequences may be split between chunks
+            this._decoder = /^utf-?8$/i.test(encoding) ? new TextDecoder() : null;
+            return this;
+        }
+
+        pause() {
+            this.readableFlowing = false;
+            return this;
+        }
+
+        resume() {
+            this.readableFlowing = true;
+            flow(this);
+            return this;
+        }
+
+        isPaused() {
+            return this.readableFlowing === false;
+        }
+
+        pipe(destination, { end = true } = {}) {
+            this.on("data", (chunk) => {
+                if (destination.write(chunk) === false) {
+                    this.pause();
+                    destination.once("drain", () => this.resume());
+                }
+            });
+            if (end) this.once("end", () => destination.end());
+            destination.emit?.("pipe", this);
+            return destination;
+        }
+
+        destroy(err) {
+            return destroy(this, err);
+        }
+
+        close(callback) {
+            if (callback) this.once("close", callback);
+            return destroy(this);
+        }
+
+        async *[Symbol.asyncIterator]() {
+            this.readableFlowing = false;
+            const handle = await this._opened;
+            if (!handle) throw this.errored;
+            try {
+                let chunk;
+                while (!this.destroyed && (chunk = await readChunk(this, handle)) !== null) {
+                    yield chunk;
+                }
+            } finally {
+                destroy(this);
+            }
+        }
+    }
+
+    const writeChunk = async (stream, chunk) => {
+        const handle = await stream._opened;
+        if (handle && !stream.destroyed) {
+            let offset = 0;
+            while (offset < chunk.length) {
+                const rest = chunk.subarray(offset);
+                const written = await (stream._position === undefined
+                    ? handle.write(rest)
+                    : handle.write(rest, stream._position));
+                if (written === 0) throw new Error("Failed to write the whole chunk");
+                offset += written;
+                stream.bytesWritten += written;
+                if (stream._position !== undefined) stream._position += written;
+            }
+        }
+        stream.writableLength -= chunk.length;
+        if (stream.writableNeedDrain && stream.writableLength === 0) {
+            stream.writableNeedDrain = false;
+            stream.emit("drain");
+        }
+    };
+
+    class WriteStream extends EventEmitter {
+        constructor(path, options) {
+            super();
+            options = toOptions(options);
+            validatePosition("start", options.start);
+
+            this.path = path;
+            this.start = options.start;
+            this.bytesWritten = 0;
+            this.pending = true;
+            this.destroyed = false;
+            this.closed = false;
+            this.errored = null;
+            this.writableEnded = false;
+            this.writableFinished = false;
+            this.writableLength = 0;
+            this.writableNeedDrain = false;
+            this.writableHighWaterMark = options.highWaterMark ?? kWriteHighWaterMark;
+            this._encoding = options.encoding ?? "utf8";
+            this._position = options.start;
+            // writes are chained so they land in the file in order
+            this._writes = Promise.resolve();
+            this._opened = openHandle(this, options.flags ?? "w", options.mode);
+        }
+
+        write(chunk, encoding, callback) {
+            if (typeof encoding === "function") {
+                callback = encoding;
+                encoding = undefined;
+            }
+            if (this.writableEnded || this.destroyed) {
+                const err = this.destroyed
+                    ? new Error("Cannot call write after a stream was destroyed")
+                    : new Error("write after end");
+                err.code = this.destroyed
+                    ? "ERR_STREAM_DESTROYED"
+                    : "ERR_STREAM_WRITE_AFTER_END";
+                queueMicrotask(() => {
+                    callback?.(err);
+                    this.emit("error", err);
+                });
+                return false;
+            }
+            if (typeof chunk === "string") {
+                chunk = Buffer.from(chunk, encoding ?? this._encoding);
+            } else if (ArrayBuffer.isView(chunk) && !(chunk instanceof Uint8Array)) {
+                chunk = new Uint8Array(chunk.buffer, chunk.byteOffset, chunk.byteLength);
+            }
+
+            this.writableLength += chunk.length;
+            this._writes = this._writes
+                .then(() => writeChunk(this, chunk))
+                .then(
+                    () => callback?.(),
+                    (err) => {
+                        callback?.(err);
+                        destroy(this, err);
+                    }
+                );
+            const ok = this.writableLength < this.writableHighWaterMark;
+            if (!ok) this.writableNeedDrain = true;
+            return ok;
+        }
+
+        end(chunk, encoding, callback) {
+            if (typeof chunk === "function") {
+                callback = chunk;
+                chunk = undefined;
+            } else if (typeof encoding === "function") {
+                callback = encoding;
+                encoding = undefined;
+            }
+            if (chunk !== undefined && chunk !== null) this.write(chunk, encoding);
+            if (callback) this.once("finish", callback);
+            if (this.writableEnded) return this;
+
+            this.writableEnded = true;
+            this._writes
+                .then(() => this._opened)
+                .then(() => {
+                    if (this.destroyed) return;
+                    this.writableFinished = true;
+                    this.emit("finish");
+                    destroy(this);
+                });
+            return this;
+        }
+
+        destroy(err) {
+            return destroy(this, err);
+        }
+
+        close(callback) {
+            if (callback) this.once("close", callback);
+            return this.end();
+        }
+    }
+
+    return {
+        ReadStream,
+        WriteStream,
+        createReadStream: (path, options) => new ReadStream(path, options),
+        createWriteStream: (path, options) => new WriteStream(path, options),
+    };
+})
+"#;
+
+/// Export `ReadStream`, `WriteStream`, `createReadStream` and `createWriteStream`
+pub(super) fn export_streams<'js>(ctx: &Ctx<'js>, exports: &Object<'js>) -> Result<()> {
+    let event_emitter = Class::<EventEmitter>::create_constructor(ctx)?
+        .expect("Can't create EventEmitter constructor");
+    EventEmitter::add_event_emitter_prototype(ctx)?;
+
+    let init: Function = crate::utils::snapshot::eval(ctx, "fs_streams", FS_STREAMS_SOURCE)?;
+    let streams: Object = init.call((event_emitter, Function::new(ctx.clone(), Async(open))?))?;
+    for name in [
+        "ReadStream",
+        "WriteStream",
+        "createReadStream",
+        "createWriteStream",
+    ] {
+        exports.set(name, streams.get::<_, Value>(name)?)?;
+    }
+    Ok(())
+}
+
+/// A `ReadableStream` pulling chunks of `file` from its current position
+pub(super) fn readable_web_stream<'js>(
+    ctx: Ctx<'js>,
+    file: Class<'js, FileHandle>,
+) -> Result<Object<'js>> {
+    let Some(readable_stream) = ctx
+        .globals()
+        .get::<_, Option<Constructor>>("ReadableStream")?
+    else {
+        return Err(Exception::throw_type(
+            &ctx,
+            "ReadableStream is not supported",
+        ));
+    };
+
+    let pull = Function::new(ctx.clone(), move |controller: Object<'js>| {
+        let ctx = controller.ctx().clone();
+        let mut chunk = vec![0u8; STREAM_CHUNK_SIZE];
+        let len = {
+            let mut file = file.try_borrow_mut()?;
+            let handle = file
+                .handle
+                .as_mut()
+                .ok_or_else(|| Exception::throw_message(&ctx, "File handle is closed"))?;
+            handle
+                .read(&mut chunk)
+                .map_err(|e| Exception::throw_message(&ctx, &e.to_string()))?
+        };
+        if len == 0 {
+            let close: Function = controller.get("close")?;
+            return close.call::<_, ()>((This(controller),));
+        }
+        chunk.truncate(len);
+
+        let enqueue: Function = controller.get("enqueue")?;
+        enqueue.call::<_, ()>((This(controller), TypedArray::<u8>::new(ctx, chunk)?))
+    })?;
+
+    let source = Object::new(ctx)?;
+    source.set("pull", pull)?;
+    readable_stream.construct((source,))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use xmas_vsys::{Permissions, Vsys};
+
+    use crate::fs::FsModule;
+    use crate::utils::{
+        primordials::{BasePrimordials, Primordial},
+        test::{call_test, given_file, test_async_with, ModuleEvaluator},
+    };
+
+    #[tokio::test]
+    async fn test_file_streams() {
+        let path = given_file("0123456789").await;
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                crate::buffer::init(&ctx).unwrap();
+                crate::text::init(&ctx).unwrap();
+                let vsys = Vsys::builder()
+                    .permissions(Permissions::allow_all())
+                    .build();
+                crate::permissions::init(ctx.clone(), Arc::new(vsys)).unwrap();
+                ModuleEvaluator::eval_rust::<FsModule>(ctx.clone(), "fs")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import fs from 'fs';
+                        export async function test(path) {
+                            const results = [];
+                            const copy = path + '.copy';
+
+                            const input = fs.createReadStream(path, { start: 2, end: 7, highWaterMark: 4 });
+                            input.on('data', (chunk) => results.push(chunk.toString()));
+                            const output = input.pipe(fs.createWriteStream(copy));
+                            await new Promise((resolve) => output.on('close', resolve));
+                            results.push(output.bytesWritten);
+
+                            for await (const chunk of fs.createReadStream(copy, 'utf8')) {
+                                results.push(chunk);
+                            }
+                            fs.rmSync(copy);
+                            return results.join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let path = path.to_string_lossy().into_owned();
+                let result = call_test::<String, _>(&ctx, &module, (path,)).await;
+                assert_eq!(result, "2345|67|6|234567");
+            })
+        })
+        .await;
+    }
+}
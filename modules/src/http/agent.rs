@@ -1,35 +1,67 @@
-use std::convert::Infallible;
+use std::time::Duration;
 
-use super::dns_cache::CachedDnsResolver;
+use super::client::HyperClient;
+use super::pool::PoolOptions;
+use crate::permissions::get_vsys;
 use crate::utils::result::ResultExt;
 use crate::utils::{any_of::AnyOf4, bytes::ObjectBytes, object::ObjectExt};
-use bytes::Bytes;
-use http_body_util::combinators::BoxBody;
-use hyper_rustls::HttpsConnector;
-use hyper_util::client::legacy::{connect::HttpConnector, Client};
-use rsquickjs::{prelude::Opt, Ctx, Error, FromJs, Result, Value};
+use rsquickjs::{prelude::Opt, Class, Ctx, Error, Exception, FromJs, Object, Result, Value};
 
 #[rsquickjs::class]
-#[derive(rsquickjs::JsLifetime, rsquickjs::class::Trace)]
+#[derive(Clone, rsquickjs::JsLifetime, rsquickjs::class::Trace)]
 pub struct Agent {
     #[qjs(skip_trace)]
-    client: Client<HttpsConnector<HttpConnector<CachedDnsResolver>>, BoxBody<Bytes, Infallible>>,
+    client: HyperClient,
+    #[qjs(skip_trace)]
+    pool: PoolOptions,
 }
 
 impl Agent {
-    pub fn client(
-        &self,
-    ) -> Client<HttpsConnector<HttpConnector<CachedDnsResolver>>, BoxBody<Bytes, Infallible>> {
+    pub fn client(&self) -> HyperClient {
         self.client.clone()
     }
 }
 
+/// The agent `fetch` uses when given none, replaced by `setGlobalAgent`.
+#[derive(rsquickjs::JsLifetime)]
+pub struct GlobalAgent(Agent);
+
+impl GlobalAgent {
+    /// The client of the global agent, created on first use
+    pub fn client(ctx: &Ctx<'_>) -> Result<HyperClient> {
+        if let Some(agent) = ctx.userdata::<GlobalAgent>() {
+            return Ok(agent.0.client());
+        }
+        let agent = Agent::new(ctx.clone(), Opt(None))?;
+        let client = agent.client();
+        let _ = ctx.store_userdata(GlobalAgent(agent));
+        Ok(client)
+    }
+}
+
+pub fn get_global_agent<'js>(ctx: Ctx<'js>) -> Result<Class<'js, Agent>> {
+    GlobalAgent::client(&ctx)?;
+    let agent = ctx
+        .userdata::<GlobalAgent>()
+        .map(|agent| agent.0.clone())
+        .or_throw_msg(&ctx, "Global agent is in use")?;
+    Class::instance(ctx, agent)
+}
+
+pub fn set_global_agent<'js>(ctx: Ctx<'js>, agent: Class<'js, Agent>) -> Result<()> {
+    let agent = agent.borrow().clone();
+    ctx.store_userdata(GlobalAgent(agent))
+        .map_err(|_| Exception::throw_message(&ctx, "Global agent is in use"))?;
+    Ok(())
+}
+
 #[rsquickjs::methods(rename_all = "camelCase")]
 impl Agent {
     #[qjs(constructor)]
     pub fn new<'js>(ctx: Ctx<'js>, options: Opt<AgentOptions>) -> Result<Self> {
         let mut reject_unauthorized = true;
         let mut ca = None;
+        let mut pool = PoolOptions::default();
 
         if let Some(options) = options.0 {
             if let Some(opt_reject_unauthorized) = options.reject_unauthorized {
@@ -38,6 +70,7 @@ impl Agent {
             if let Some(opt_ca) = options.ca {
                 ca = Some(opt_ca);
             }
+            pool = options.pool;
         }
 
         let config =
@@ -46,16 +79,78 @@ impl Agent {
                 ca,
             })
             .or_throw_msg(&ctx, "Failed to build TLS config")?;
-        let client = super::client::build_client(Some(config))
+        let net = get_vsys(&ctx)
+            .map(|vsys| vsys.net.clone())
+            .unwrap_or_default();
+        let client = super::client::build_client(Some(config), net, &pool)
             .or_throw_msg(&ctx, "Failed to build HTTP client")?;
 
-        Ok(Self { client })
+        Ok(Self { client, pool })
+    }
+
+    #[qjs(get)]
+    pub fn keep_alive(&self) -> bool {
+        self.pool.keep_alive
+    }
+
+    #[qjs(get)]
+    pub fn max_sockets(&self) -> f64 {
+        limit(self.pool.max_sockets)
+    }
+
+    #[qjs(get)]
+    pub fn max_total_sockets(&self) -> f64 {
+        limit(self.pool.max_total_sockets)
     }
+
+    #[qjs(get)]
+    pub fn max_free_sockets(&self) -> f64 {
+        limit(Some(self.pool.max_free_sockets).filter(|max| *max != usize::MAX))
+    }
+
+    #[qjs(get)]
+    pub fn idle_timeout(&self) -> f64 {
+        self.pool
+            .idle_timeout
+            .map_or(f64::INFINITY, |timeout| timeout.as_millis() as f64)
+    }
+
+    /// Socket counts of the pool, `activeSockets` are those waiting for a response
+    pub fn stats<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let stats = self.client.stats();
+        let object = Object::new(ctx)?;
+        object.set("totalSockets", stats.sockets)?;
+        object.set("activeSockets", stats.active)?;
+        object.set("idleSockets", stats.idle)?;
+        object.set("pendingConnections", stats.pending)?;
+        Ok(object)
+    }
+}
+
+fn limit(max: Option<usize>) -> f64 {
+    max.map_or(f64::INFINITY, |max| max as f64)
 }
 
 pub struct AgentOptions {
     reject_unauthorized: Option<bool>,
     ca: Option<Vec<Vec<u8>>>,
+    pool: PoolOptions,
+}
+
+/// Read a socket count option, `Infinity` meaning no limit
+fn get_limit<'js>(ctx: &Ctx<'js>, obj: &Object<'js>, name: &str) -> Result<Option<usize>> {
+    match obj.get_optional::<_, f64>(name)? {
+        Some(max) if max == f64::INFINITY => Ok(None),
+        Some(max) if max >= 1.0 && max.fract() == 0.0 => Ok(Some(max as usize)),
+        Some(max) => Err(Exception::throw_range(
+            ctx,
+            &format!(
+                "The value of \"{}\" is out of range. Received {}",
+                name, max
+            ),
+        )),
+        None => Ok(None),
+    }
 }
 
 impl<'js> FromJs<'js> for AgentOptions {
@@ -82,9 +177,35 @@ impl<'js> FromJs<'js> for AgentOptions {
             })
             .transpose()?;
 
+        let mut pool = PoolOptions::default();
+        if let Some(keep_alive) = obj.get_optional::<_, bool>("keepAlive")? {
+            pool.keep_alive = keep_alive;
+        }
+        pool.max_sockets = get_limit(ctx, obj, "maxSockets")?;
+        pool.max_total_sockets = get_limit(ctx, obj, "maxTotalSockets")?;
+        if let Some(max) = get_limit(ctx, obj, "maxFreeSockets")? {
+            pool.max_free_sockets = max;
+        }
+        if let Some(timeout) = obj.get_optional::<_, f64>("idleTimeout")? {
+            pool.idle_timeout = match timeout {
+                timeout if timeout == f64::INFINITY => None,
+                timeout if timeout >= 0.0 => Some(Duration::from_millis(timeout as u64)),
+                timeout => {
+                    return Err(Exception::throw_range(
+                        ctx,
+                        &format!(
+                            "The value of \"idleTimeout\" is out of range. Received {}",
+                            timeout
+                        ),
+                    ))
+                }
+            };
+        }
+
         Ok(Self {
             reject_unauthorized,
             ca,
+            pool,
         })
     }
 }
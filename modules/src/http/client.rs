@@ -1,22 +1,49 @@
-use std::{convert::Infallible, sync::LazyLock};
+use std::{
+    convert::Infallible,
+    sync::{Arc, LazyLock},
+};
 
-use super::dns_cache::CachedDnsResolver;
+use super::connector::VsysConnector;
+use super::pool::{PoolOptions, PoolState, PoolStats};
 use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
+use hyper::{body::Incoming, Request, Response};
 use hyper_rustls::HttpsConnector;
 use hyper_util::{
-    client::legacy::{connect::HttpConnector, Client},
+    client::legacy::{Client, Error},
     rt::{TokioExecutor, TokioTimer},
 };
 use rustls::ClientConfig;
+use xmas_vsys::NetVTable;
 
 use crate::tls::config::{build_client_config, BuildClientConfigOptions};
 
-pub type HyperClient =
-    Client<HttpsConnector<HttpConnector<CachedDnsResolver>>, BoxBody<Bytes, Infallible>>;
+/// A pooling HTTP client, counting the sockets of its pool.
+#[derive(Clone)]
+pub struct HyperClient {
+    client: Client<HttpsConnector<VsysConnector>, BoxBody<Bytes, Infallible>>,
+    pool: Arc<PoolState>,
+}
+
+impl HyperClient {
+    pub async fn request(
+        &self,
+        req: Request<BoxBody<Bytes, Infallible>>,
+    ) -> Result<Response<Incoming>, Error> {
+        let _active = self.pool.begin_request();
+        self.client.request(req).await
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+}
 
+/// Builds a client whose connections go through `net`, pooled as `pool` says.
 pub fn build_client(
     tls_config: Option<ClientConfig>,
+    net: Arc<NetVTable>,
+    pool: &PoolOptions,
 ) -> Result<HyperClient, Box<dyn std::error::Error + Send + Sync>> {
     let config = if let Some(tls_config) = tls_config {
         tls_config
@@ -31,14 +58,24 @@ pub fn build_client(
         .with_tls_config(config)
         .https_or_http();
 
-    let mut cache_dns_connector = CachedDnsResolver::new().into_http_connector();
-    cache_dns_connector.enforce_http(false);
-
+    let state = Arc::new(PoolState::new(pool));
     let https = builder
         .enable_all_versions()
-        .wrap_connector(cache_dns_connector);
+        .wrap_connector(VsysConnector::new(net, state.clone()));
 
-    Ok(Client::builder(TokioExecutor::new())
+    let max_idle_per_host = if pool.keep_alive {
+        pool.max_free_sockets
+    } else {
+        0
+    };
+    let client = Client::builder(TokioExecutor::new())
         .pool_timer(TokioTimer::new())
-        .build(https))
+        .pool_idle_timeout(pool.idle_timeout)
+        .pool_max_idle_per_host(max_idle_per_host)
+        .build(https);
+
+    Ok(HyperClient {
+        client,
+        pool: state,
+    })
 }
@@ -0,0 +1,86 @@
+use std::{
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{self, Poll},
+};
+
+use hyper::Uri;
+use hyper_util::{client::legacy::connect::dns::Name, rt::TokioIo};
+use tokio::net::TcpStream;
+use tower_service::Service;
+use xmas_vsys::NetVTable;
+
+use super::dns_cache::{into_io_error, CachedDnsResolver};
+use super::pool::{PoolState, PooledStream};
+
+/// Opens client connections through the vsys network vtable, resolving host
+/// names with the shared DNS cache and waiting for the socket limits of `pool`.
+#[derive(Clone)]
+pub struct VsysConnector {
+    resolver: CachedDnsResolver,
+    net: Arc<NetVTable>,
+    pool: Arc<PoolState>,
+}
+
+impl VsysConnector {
+    pub fn new(net: Arc<NetVTable>, pool: Arc<PoolState>) -> Self {
+        Self {
+            resolver: CachedDnsResolver::new(net.clone()),
+            net,
+            pool,
+        }
+    }
+}
+
+impl Service<Uri> for VsysConnector {
+    type Response = TokioIo<PooledStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let mut resolver = self.resolver.clone();
+        let net = self.net.clone();
+        let pool = self.pool.clone();
+
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URI has no host"))?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+
+            let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+                Ok(ip) => vec![SocketAddr::new(ip, port)],
+                Err(_) => {
+                    let name = Name::from_str(host).map_err(io::Error::other)?;
+                    resolver
+                        .call(name)
+                        .await?
+                        .map(|addr| SocketAddr::new(addr.ip(), port))
+                        .collect()
+                }
+            };
+
+            let permits = pool.acquire(&format!("{}:{}", host, port)).await;
+            let stream = tokio::task::spawn_blocking(move || net.connect_any(&addrs))
+                .await
+                .map_err(io::Error::other)?
+                .map_err(into_io_error)?;
+            stream.set_nonblocking(true)?;
+            let stream = TcpStream::from_std(stream)?;
+            stream.set_nodelay(true)?;
+            Ok(TokioIo::new(PooledStream::new(stream, pool, permits)))
+        })
+    }
+}
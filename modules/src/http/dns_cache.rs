@@ -1,18 +1,57 @@
 use std::{
+    error::Error as StdError,
+    fmt,
     future::Future,
     io,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{self, Poll},
-    time::{Duration, Instant},
+    time::Instant,
     vec,
 };
 
-use hyper_util::client::legacy::connect::{dns::Name, HttpConnector};
+use hyper_util::client::legacy::connect::dns::Name;
 use quick_cache::sync::Cache;
 use tokio::sync::Semaphore;
 use tower_service::Service;
+use xmas_vsys::{NetVTable, VsysError};
+
+use crate::dns::cache as dns_cache;
+
+/// Failed host name resolution, kept distinguishable from connect errors once wrapped by hyper.
+#[derive(Debug)]
+pub struct DnsLookupError {
+    pub hostname: String,
+    source: io::Error,
+}
+
+impl DnsLookupError {
+    pub(crate) fn new(hostname: &str, source: io::Error) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            Self {
+                hostname: hostname.into(),
+                source,
+            },
+        )
+    }
+}
+
+impl fmt::Display for DnsLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "getaddrinfo ENOTFOUND {}", self.hostname)
+    }
+}
+
+impl StdError for DnsLookupError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
 
 #[derive(Clone)]
 pub struct SocketAddrs {
@@ -30,9 +69,16 @@ impl Iterator for SocketAddrs {
 #[derive(Clone)]
 struct CacheEntry {
     ttl: Instant,
+    generation: u64,
     addrs: SocketAddrs,
 }
 
+impl CacheEntry {
+    fn is_fresh(&self) -> bool {
+        self.ttl > Instant::now() && self.generation == dns_cache::generation()
+    }
+}
+
 #[derive(Clone)]
 struct CacheConcurrencyGuard {
     semaphore: Arc<Semaphore>,
@@ -47,11 +93,14 @@ impl CacheConcurrencyGuard {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Caches the addresses of host names, following the process wide settings
+/// of [`crate::dns::cache`].
+#[derive(Clone)]
 pub struct CachedDnsResolver {
     cache: Arc<Cache<Name, CacheConcurrencyGuard>>,
+    capacity: Arc<AtomicUsize>,
     concurrency: u8,
-    ttl: Duration,
+    net: Arc<NetVTable>,
 }
 
 impl Service<Name> for CachedDnsResolver {
@@ -64,9 +113,13 @@ impl Service<Name> for CachedDnsResolver {
     }
 
     fn call(&mut self, name: Name) -> Self::Future {
+        let options = dns_cache::options();
+        if self.capacity.swap(options.max_entries, Ordering::Relaxed) != options.max_entries {
+            self.cache.set_capacity(options.max_entries as u64);
+        }
         let cache = self.cache.clone();
         let permits = self.concurrency;
-        let ttl = self.ttl;
+        let net = self.net.clone();
 
         Box::pin(async move {
             let guard = match cache.get_value_or_guard_async(&name).await {
@@ -78,7 +131,7 @@ impl Service<Name> for CachedDnsResolver {
                 }
             };
             if let Some(entry) = guard.entry {
-                if entry.ttl > Instant::now() {
+                if entry.is_fresh() {
                     return Ok(entry.addrs);
                 }
             };
@@ -88,17 +141,29 @@ impl Service<Name> for CachedDnsResolver {
             let lock = semaphore2.acquire().await.unwrap();
 
             if let Some(item) = cache.get(&name).and_then(|guard| guard.entry) {
-                return Ok(item.addrs);
+                if item.is_fresh() {
+                    return Ok(item.addrs);
+                }
             }
 
-            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?;
-            let addrs = addrs.collect::<Vec<_>>();
+            let generation = dns_cache::generation();
+            let mut addrs = resolve(net, name.as_str())
+                .await
+                .map_err(|err| DnsLookupError::new(name.as_str(), err))?;
+            options
+                .order
+                .sort_by_family(&mut addrs, |addr| addr.is_ipv4());
+            let addrs = addrs
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect::<Vec<_>>();
             let addrs = SocketAddrs {
                 iter: addrs.into_iter(),
             };
             let addrs2 = addrs.clone();
             let entry = CacheEntry {
-                ttl: Instant::now() + ttl,
+                ttl: Instant::now() + options.ttl,
+                generation,
                 addrs,
             };
             cache.insert(
@@ -116,24 +181,39 @@ impl Service<Name> for CachedDnsResolver {
 
 impl Default for CachedDnsResolver {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(NetVTable::default()))
     }
 }
 
 impl CachedDnsResolver {
-    pub fn new() -> Self {
-        Self::with_options(128, 2, 300)
+    pub fn new(net: Arc<NetVTable>) -> Self {
+        Self::with_concurrency(net, 2)
     }
 
-    pub fn with_options(size: usize, concurrency: u8, ttl: u64) -> Self {
+    /// Create a resolver running at most `concurrency` lookups of a host at once
+    pub fn with_concurrency(net: Arc<NetVTable>, concurrency: u8) -> Self {
+        let capacity = dns_cache::options().max_entries;
         Self {
-            cache: Arc::new(Cache::new(size)),
+            cache: Arc::new(Cache::new(capacity)),
+            capacity: Arc::new(AtomicUsize::new(capacity)),
             concurrency,
-            ttl: Duration::from_secs(ttl),
+            net,
         }
     }
+}
+
+/// Resolves `host` through the vsys network vtable, off the async runtime.
+pub async fn resolve(net: Arc<NetVTable>, host: &str) -> io::Result<Vec<IpAddr>> {
+    let host = host.to_string();
+    tokio::task::spawn_blocking(move || (net.resolve)(&host))
+        .await
+        .map_err(io::Error::other)?
+        .map_err(into_io_error)
+}
 
-    pub fn into_http_connector(self) -> HttpConnector<Self> {
-        HttpConnector::<Self>::new_with_resolver(self)
+pub(crate) fn into_io_error(err: VsysError) -> io::Error {
+    match err {
+        VsysError::Io(err) => err,
+        err => io::Error::other(err),
     }
 }
@@ -1,18 +1,23 @@
 use crate::utils::module::{export_default, ModuleInfo};
 use rsquickjs::{
     module::{Declarations, Exports, ModuleDef},
+    prelude::Func,
     Class, Ctx, Result,
 };
 
 pub mod agent;
 pub mod client;
+pub mod connector;
 pub mod dns_cache;
+pub mod pool;
 
 pub struct HttpsModule;
 
 impl ModuleDef for HttpsModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare(stringify!(Agent))?;
+        declare.declare("getGlobalAgent")?;
+        declare.declare("setGlobalAgent")?;
         declare.declare("default")?;
         Ok(())
     }
@@ -20,6 +25,8 @@ impl ModuleDef for HttpsModule {
     fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
         export_default(ctx, exports, |default| {
             Class::<agent::Agent>::define(default)?;
+            default.set("getGlobalAgent", Func::from(agent::get_global_agent))?;
+            default.set("setGlobalAgent", Func::from(agent::set_global_agent))?;
 
             Ok(())
         })
@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{self, Poll},
+    time::Duration,
+};
+
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+/// Keep-alive pool tuning of a client.
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    /// Keep sockets open for later requests
+    pub keep_alive: bool,
+    /// Most idle sockets kept per host
+    pub max_free_sockets: usize,
+    /// How long an idle socket is kept, forever when `None`
+    pub idle_timeout: Option<Duration>,
+    /// Most sockets per host, idle ones included
+    pub max_sockets: Option<usize>,
+    /// Most sockets over all hosts, idle ones included
+    pub max_total_sockets: Option<usize>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            max_free_sockets: usize::MAX,
+            idle_timeout: Some(Duration::from_secs(90)),
+            max_sockets: None,
+            max_total_sockets: None,
+        }
+    }
+}
+
+/// Socket counts of a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Open sockets
+    pub sockets: usize,
+    /// Sockets with a request waiting for its response
+    pub active: usize,
+    /// Open sockets without one
+    pub idle: usize,
+    /// Connections waiting for a socket limit
+    pub pending: usize,
+}
+
+/// Socket limits and counters shared by a client and its connector.
+pub struct PoolState {
+    max_sockets: Option<usize>,
+    total: Option<Arc<Semaphore>>,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+    sockets: AtomicUsize,
+    active: AtomicUsize,
+    pending: AtomicUsize,
+}
+
+impl PoolState {
+    pub fn new(options: &PoolOptions) -> Self {
+        Self {
+            max_sockets: options.max_sockets,
+            total: options
+                .max_total_sockets
+                .map(|max| Arc::new(Semaphore::new(max))),
+            hosts: Mutex::default(),
+            sockets: AtomicUsize::new(0),
+            active: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let sockets = self.sockets.load(Ordering::Relaxed);
+        let active = self.active.load(Ordering::Relaxed).min(sockets);
+        PoolStats {
+            sockets,
+            active,
+            idle: sockets - active,
+            pending: self.pending.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Count a request as active until the returned guard drops
+    pub fn begin_request(&self) -> CountGuard<'_> {
+        CountGuard::new(&self.active)
+    }
+
+    /// Wait until the socket limits of `host` allow one more socket
+    pub async fn acquire(&self, host: &str) -> Vec<OwnedSemaphorePermit> {
+        let host = self.max_sockets.map(|max| {
+            let mut hosts = self.hosts.lock().unwrap_or_else(|err| err.into_inner());
+            // a semaphore only the map refers to has no sockets left
+            hosts.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            hosts
+                .entry(host.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(max)))
+                .clone()
+        });
+
+        let _pending = CountGuard::new(&self.pending);
+        let mut permits = Vec::with_capacity(2);
+        // take the host permit first, so waiting on a busy host holds no total permit
+        for semaphore in host.into_iter().chain(self.total.clone()) {
+            if let Ok(permit) = semaphore.acquire_owned().await {
+                permits.push(permit);
+            }
+        }
+        permits
+    }
+}
+
+/// Increments a counter, decrementing it again on drop.
+pub struct CountGuard<'a>(&'a AtomicUsize);
+
+impl<'a> CountGuard<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for CountGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A client socket, counted and holding its limit permits while open.
+pub struct PooledStream {
+    stream: TcpStream,
+    state: Arc<PoolState>,
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+impl PooledStream {
+    pub fn new(
+        stream: TcpStream,
+        state: Arc<PoolState>,
+        permits: Vec<OwnedSemaphorePermit>,
+    ) -> Self {
+        state.sockets.fetch_add(1, Ordering::Relaxed);
+        Self {
+            stream,
+            state,
+            _permits: permits,
+        }
+    }
+}
+
+impl Drop for PooledStream {
+    fn drop(&mut self) {
+        self.state.sockets.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Connection for PooledStream {
+    fn connected(&self) -> Connected {
+        self.stream.connected()
+    }
+}
+
+impl AsyncRead for PooledStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PooledStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pool_limits() {
+        let state = Arc::new(PoolState::new(&PoolOptions {
+            max_sockets: Some(1),
+            max_total_sockets: Some(2),
+            ..Default::default()
+        }));
+
+        let first = state.acquire("a:80").await;
+        assert_eq!(first.len(), 2);
+        let second = state.acquire("b:80").await;
+
+        let waiting = tokio::spawn({
+            let state = state.clone();
+            async move { state.acquire("a:80").await }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(state.stats().pending, 1);
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let third = waiting.await.unwrap();
+        assert_eq!(third.len(), 2);
+        assert_eq!(state.stats().pending, 0);
+        drop(second);
+
+        {
+            let _active = state.begin_request();
+            assert_eq!(state.stats().active, 0);
+        }
+        assert_eq!(state.stats(), PoolStats::default());
+    }
+}
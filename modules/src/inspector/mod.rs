@@ -0,0 +1,501 @@
+//! Chrome DevTools Protocol server behind `--inspect` and `--inspect-brk`.
+//!
+//! The frontend connects over a WebSocket and its messages are answered on
+//! the JavaScript thread whenever the script yields, see [`Inspector::run`].
+//! The `Runtime` domain (console, evaluation, object inspection) is
+//! supported. QuickJS has no debugger hooks, so breakpoints and stepping in
+//! the `Debugger` domain are rejected.
+
+mod runtime;
+mod websocket;
+
+use std::{
+    future::Future,
+    io::{self, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rsquickjs::{Array, CatchResultExt, Ctx, Object, Result, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:9229";
+
+const CONTEXT_ID: i32 = 1;
+
+/// The connected frontend, which messages from the JavaScript thread are sent to
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(0);
+/// Whether the frontend enabled the `Runtime` domain and wants console messages
+static RUNTIME_ENABLED: AtomicBool = AtomicBool::new(false);
+
+struct Session {
+    id: u64,
+    outgoing: mpsc::Sender<String>,
+}
+
+fn session() -> MutexGuard<'static, Option<Session>> {
+    SESSION.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// The debugging target described to frontends on `/json/list`
+#[derive(Clone)]
+struct Target {
+    addr: SocketAddr,
+    id: String,
+    title: String,
+    url: String,
+}
+
+pub struct Inspector {
+    target: Target,
+    incoming: UnboundedReceiver<String>,
+}
+
+impl Inspector {
+    /// Starts serving the inspector for the script at `path` on `addr`.
+    pub fn listen(addr: SocketAddr, path: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let url = std::path::absolute(path)
+            .map(|path| ["file://", &path.to_string_lossy()].concat())
+            .unwrap_or_else(|_| path.to_string());
+        let target = Target {
+            addr: listener.local_addr()?,
+            id: uuid::Uuid::new_v4().to_string(),
+            title: path.to_string(),
+            url,
+        };
+        let (sender, incoming) = unbounded_channel();
+
+        let server_target = target.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (target, sender) = (server_target.clone(), sender.clone());
+                thread::spawn(move || {
+                    if let Err(err) = serve(stream, &target, sender) {
+                        tracing::debug!("Inspector connection failed: {}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { target, incoming })
+    }
+
+    /// The WebSocket URL frontends connect to.
+    pub fn url(&self) -> String {
+        ["ws://", &self.target.websocket_path()].concat()
+    }
+
+    /// Answers the frontend until it asks the script to start, for `--inspect-brk`.
+    pub async fn wait_for_debugger(&mut self, ctx: &Ctx<'_>) {
+        while let Some(message) = self.incoming.recv().await {
+            if dispatch(ctx, &message) == Flow::Run {
+                return;
+            }
+        }
+    }
+
+    /// Drives `future` to completion, answering the frontend whenever it yields.
+    pub async fn run<F: Future>(&mut self, ctx: &Ctx<'_>, future: F) -> F::Output {
+        tokio::pin!(future);
+        loop {
+            tokio::select! {
+                output = &mut future => return output,
+                Some(message) = self.incoming.recv() => {
+                    dispatch(ctx, &message);
+                }
+            }
+        }
+    }
+}
+
+impl Target {
+    fn websocket_path(&self) -> String {
+        [&self.addr.to_string(), "/", &self.id].concat()
+    }
+
+    fn list_json(&self) -> String {
+        let websocket = self.websocket_path();
+        format!(
+            r#"[{{"description":"xmas instance","devtoolsFrontendUrl":"devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={ws}","devtoolsFrontendUrlCompat":"devtools://devtools/bundled/inspector.html?experiments=true&v8only=true&ws={ws}","id":"{id}","title":{title},"type":"node","url":{url},"webSocketDebuggerUrl":"ws://{ws}"}}]"#,
+            ws = websocket,
+            id = self.id,
+            title = json_string(&self.title),
+            url = json_string(&self.url),
+        )
+    }
+}
+
+fn version_json() -> String {
+    format!(
+        r#"{{"Browser":"xmas/{}","Protocol-Version":"1.1"}}"#,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Answers discovery requests, or runs a session when the request upgrades
+/// to the target's WebSocket.
+fn serve(stream: TcpStream, target: &Target, incoming: UnboundedSender<String>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    let request = websocket::read_request(&mut reader)?;
+    let json = "application/json; charset=UTF-8";
+    match (request.path.as_str(), request.websocket_key) {
+        (path, Some(key)) if path.strip_prefix('/') == Some(target.id.as_str()) => {
+            websocket::accept(&mut stream, &key)?;
+            run_session(reader, stream, incoming)
+        }
+        ("/json" | "/json/list", _) => {
+            websocket::write_response(&mut stream, "200 OK", json, &target.list_json())
+        }
+        ("/json/version", _) => {
+            websocket::write_response(&mut stream, "200 OK", json, &version_json())
+        }
+        _ => websocket::write_response(&mut stream, "404 Not Found", "text/plain", "Not found"),
+    }
+}
+
+/// Writes frames whole, so replies to pings don't interleave with messages.
+#[derive(Clone)]
+struct SharedStream(Arc<Mutex<TcpStream>>);
+
+impl Write for &SharedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).flush()
+    }
+}
+
+fn run_session(
+    mut reader: BufReader<TcpStream>,
+    stream: TcpStream,
+    incoming: UnboundedSender<String>,
+) -> io::Result<()> {
+    let stream = SharedStream(Arc::new(Mutex::new(stream)));
+    let (outgoing, messages) = mpsc::channel::<String>();
+    let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+    // a new frontend replaces the previous one
+    *session() = Some(Session { id, outgoing });
+
+    let writer = stream.clone();
+    thread::spawn(move || {
+        for message in messages {
+            if websocket::write_message(&mut &writer, &message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let result = loop {
+        match websocket::read_message(&mut reader, &mut &stream) {
+            Ok(Some(message)) => {
+                if incoming.send(message).is_err() {
+                    break Ok(());
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+
+    let mut session = session();
+    if session.as_ref().is_some_and(|session| session.id == id) {
+        *session = None;
+        RUNTIME_ENABLED.store(false, Ordering::Relaxed);
+    }
+    result
+}
+
+fn send(ctx: &Ctx<'_>, message: Object<'_>) -> Result<()> {
+    let Some(message) = ctx.json_stringify(message)? else {
+        return Ok(());
+    };
+    if let Some(session) = session().as_ref() {
+        let _ = session.outgoing.send(message.to_string()?);
+    }
+    Ok(())
+}
+
+fn send_event<'js>(ctx: &Ctx<'js>, method: &str, params: Object<'js>) -> Result<()> {
+    let event = Object::new(ctx.clone())?;
+    event.set("method", method)?;
+    event.set("params", params)?;
+    send(ctx, event)
+}
+
+/// Reports a `console` call to the frontend as `Runtime.consoleAPICalled`.
+pub fn console_api_called<'js>(ctx: &Ctx<'js>, kind: &str, args: &[Value<'js>]) {
+    if !RUNTIME_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let result = (|| {
+        let remote_args = Array::new(ctx.clone())?;
+        for (i, arg) in args.iter().enumerate() {
+            remote_args.set(i, runtime::remote_object(ctx, arg.clone(), false)?)?;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        let params = Object::new(ctx.clone())?;
+        params.set("type", kind)?;
+        params.set("args", remote_args)?;
+        params.set("executionContextId", CONTEXT_ID)?;
+        params.set("timestamp", timestamp)?;
+        send_event(ctx, "Runtime.consoleAPICalled", params)
+    })();
+    if let Err(err) = result.catch(ctx) {
+        tracing::debug!("Failed to report console call to the inspector: {}", err);
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Flow {
+    Continue,
+    /// The frontend sent `Runtime.runIfWaitingForDebugger`
+    Run,
+}
+
+/// A CDP error response
+struct ProtocolError {
+    code: i32,
+    message: String,
+}
+
+impl From<rsquickjs::Error> for ProtocolError {
+    fn from(err: rsquickjs::Error) -> Self {
+        Self {
+            code: -32602,
+            message: err.to_string(),
+        }
+    }
+}
+
+fn dispatch(ctx: &Ctx<'_>, message: &str) -> Flow {
+    let result = (|| {
+        let request = ctx.json_parse(message)?;
+        let Some(request) = request.as_object() else {
+            return Ok(Flow::Continue);
+        };
+        let id: Value = request.get("id")?;
+        let method: String = request.get("method")?;
+        let params = request
+            .get::<_, Option<Object>>("params")?
+            .unwrap_or(Object::new(ctx.clone())?);
+
+        let response = Object::new(ctx.clone())?;
+        response.set("id", id)?;
+        match handle(ctx, &method, &params) {
+            Ok(result) => response.set("result", result)?,
+            Err(err) => {
+                // a failed parameter lookup may leave an exception pending
+                let _ = ctx.catch();
+                let error = Object::new(ctx.clone())?;
+                error.set("code", err.code)?;
+                error.set("message", err.message)?;
+                response.set("error", error)?;
+            }
+        }
+        send(ctx, response)?;
+
+        Ok(match method.as_str() {
+            "Runtime.runIfWaitingForDebugger" => Flow::Run,
+            _ => Flow::Continue,
+        })
+    })();
+    result.catch(ctx).unwrap_or_else(|err| {
+        tracing::debug!("Invalid inspector message: {}", err);
+        Flow::Continue
+    })
+}
+
+fn handle<'js>(
+    ctx: &Ctx<'js>,
+    method: &str,
+    params: &Object<'js>,
+) -> std::result::Result<Object<'js>, ProtocolError> {
+    let empty = Object::new(ctx.clone())?;
+    match method {
+        "Runtime.enable" => {
+            RUNTIME_ENABLED.store(true, Ordering::Relaxed);
+            let context = Object::new(ctx.clone())?;
+            context.set("id", CONTEXT_ID)?;
+            context.set("origin", "")?;
+            context.set("name", "xmas")?;
+            context.set("uniqueId", CONTEXT_ID.to_string())?;
+            let aux_data = Object::new(ctx.clone())?;
+            aux_data.set("isDefault", true)?;
+            context.set("auxData", aux_data)?;
+            let params = Object::new(ctx.clone())?;
+            params.set("context", context)?;
+            send_event(ctx, "Runtime.executionContextCreated", params)?;
+            Ok(empty)
+        }
+        "Runtime.disable" => {
+            RUNTIME_ENABLED.store(false, Ordering::Relaxed);
+            Ok(empty)
+        }
+        "Runtime.evaluate" => Ok(runtime::evaluate(ctx, params)?),
+        "Runtime.callFunctionOn" => Ok(runtime::call_function_on(ctx, params)?),
+        "Runtime.getProperties" => Ok(runtime::get_properties(ctx, params)?),
+        "Runtime.releaseObjectGroup" => {
+            runtime::release_objects(ctx);
+            Ok(empty)
+        }
+        "Debugger.enable" => {
+            empty.set("debuggerId", "xmas")?;
+            Ok(empty)
+        }
+        // settings the frontend sends on connect, which have nothing to do here
+        "Runtime.runIfWaitingForDebugger"
+        | "Runtime.releaseObject"
+        | "Runtime.discardConsoleEntries"
+        | "Runtime.setAsyncCallStackDepth"
+        | "Runtime.addBinding"
+        | "Debugger.disable"
+        | "Debugger.setAsyncCallStackDepth"
+        | "Debugger.setPauseOnExceptions"
+        | "Debugger.setBlackboxPatterns"
+        | "Debugger.setBreakpointsActive"
+        | "Profiler.enable"
+        | "Profiler.disable"
+        | "Log.enable"
+        | "Log.disable" => Ok(empty),
+        method if method.starts_with("Debugger.") => Err(ProtocolError {
+            code: -32000,
+            message: "Breakpoints and stepping are not supported by QuickJS".into(),
+        }),
+        method => Err(ProtocolError {
+            code: -32601,
+            message: ["'", method, "' wasn't found"].concat(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::utils::test::test_async_with;
+
+    /// Connects to the inspector like a frontend, returning the response to
+    /// each of `requests`.
+    fn client(url: &str, requests: &[&str]) -> Vec<String> {
+        let (addr, path) = url.trim_start_matches("ws://").split_once('/').unwrap();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /{} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, addr
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            let mut byte = [0];
+            reader.read_exact(&mut byte).unwrap();
+            head.push(byte[0] as char);
+        }
+        assert!(head.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+        let mut responses = Vec::new();
+        for request in requests {
+            websocket::write_client_message(&mut stream, request).unwrap();
+            // skip events until the response to this request arrives
+            loop {
+                let message = websocket::read_message(&mut reader, &mut stream)
+                    .unwrap()
+                    .unwrap();
+                if message.starts_with(r#"{"id""#) {
+                    responses.push(message);
+                    break;
+                }
+            }
+        }
+        responses
+    }
+
+    #[tokio::test]
+    async fn test_inspector() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                let mut inspector =
+                    Inspector::listen("127.0.0.1:0".parse().unwrap(), "main.js").unwrap();
+                let url = inspector.url();
+
+                let mut discovery = TcpStream::connect(inspector.target.addr).unwrap();
+                write!(discovery, "GET /json/list HTTP/1.1\r\n\r\n").unwrap();
+                let mut list = String::new();
+                discovery.read_to_string(&mut list).unwrap();
+                assert!(list.contains(&["\"webSocketDebuggerUrl\":\"", &url, "\""].concat()));
+
+                ctx.globals().set("answer", 42).unwrap();
+                let client = tokio::task::spawn_blocking(move || {
+                    client(
+                        &url,
+                        &[
+                            r#"{"id":1,"method":"Runtime.enable"}"#,
+                            r#"{"id":2,"method":"Runtime.evaluate","params":{"expression":"answer + 1"}}"#,
+                            r#"{"id":3,"method":"Runtime.evaluate","params":{"expression":"({ a: [1] })"}}"#,
+                            r#"{"id":4,"method":"Runtime.getProperties","params":{"objectId":"0"}}"#,
+                            r#"{"id":5,"method":"Runtime.evaluate","params":{"expression":"missing"}}"#,
+                            r#"{"id":6,"method":"Debugger.setBreakpointByUrl","params":{"lineNumber":1}}"#,
+                        ],
+                    )
+                });
+                let responses = inspector.run(&ctx, client).await.unwrap();
+
+                assert_eq!(responses[0], r#"{"id":1,"result":{}}"#);
+                assert_eq!(
+                    responses[1],
+                    r#"{"id":2,"result":{"result":{"type":"number","description":"43","value":43}}}"#
+                );
+                assert_eq!(
+                    responses[2],
+                    r#"{"id":3,"result":{"result":{"type":"object","className":"Object","description":"Object","objectId":"0"}}}"#
+                );
+                assert!(responses[3].contains(r#""name":"a","#));
+                assert!(responses[3].contains(r#""subtype":"array","description":"Array(1)""#));
+                assert!(responses[4].contains(r#""exceptionDetails""#));
+                assert!(responses[4].contains("missing is not defined"));
+                assert!(responses[5].contains(r#""error":{"code":-32000"#));
+            })
+        })
+        .await;
+    }
+}
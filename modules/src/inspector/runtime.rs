@@ -0,0 +1,275 @@
+//! The CDP `Runtime` domain: evaluating expressions and describing the
+//! resulting values as `RemoteObject`s the frontend can expand.
+
+use std::cell::RefCell;
+
+use rsquickjs::{
+    context::EvalOptions,
+    function::{Rest, This},
+    Array, CatchResultExt, CaughtError, Ctx, Filter, Function, JsLifetime, Object, Persistent,
+    Result, Type, Value,
+};
+
+/// Objects handed to the frontend, addressed by their index as `objectId`
+#[derive(Default, JsLifetime)]
+struct RemoteObjects(RefCell<Vec<Persistent<Value<'static>>>>);
+
+fn register<'js>(ctx: &Ctx<'js>, value: Value<'js>) -> Result<String> {
+    if ctx.userdata::<RemoteObjects>().is_none() {
+        let _ = ctx.store_userdata(RemoteObjects::default());
+    }
+    let objects = ctx.userdata::<RemoteObjects>().unwrap();
+    let mut objects = objects.0.borrow_mut();
+    objects.push(Persistent::save(ctx, value));
+    Ok((objects.len() - 1).to_string())
+}
+
+fn lookup<'js>(ctx: &Ctx<'js>, object_id: &str) -> Result<Value<'js>> {
+    let value = object_id.parse::<usize>().ok().and_then(|index| {
+        let objects = ctx.userdata::<RemoteObjects>()?;
+        let value = objects.0.borrow().get(index).cloned();
+        value
+    });
+    match value {
+        Some(value) => value.restore(ctx),
+        None => Err(rsquickjs::Error::new_from_js_message(
+            "objectId",
+            "object",
+            ["Could not find object with given id ", object_id].concat(),
+        )),
+    }
+}
+
+/// Forgets every object handed out so far, for `Runtime.releaseObjectGroup`.
+pub fn release_objects(ctx: &Ctx<'_>) {
+    if let Some(objects) = ctx.userdata::<RemoteObjects>() {
+        objects.0.borrow_mut().clear();
+    }
+}
+
+/// Describes `value` as a CDP `RemoteObject`, registering objects so they can
+/// be expanded later unless `by_value` asks for a JSON copy.
+pub fn remote_object<'js>(
+    ctx: &Ctx<'js>,
+    value: Value<'js>,
+    by_value: bool,
+) -> Result<Object<'js>> {
+    let remote = Object::new(ctx.clone())?;
+    match value.type_of() {
+        Type::Uninitialized | Type::Undefined => {
+            remote.set("type", "undefined")?;
+        }
+        Type::Null => {
+            remote.set("type", "object")?;
+            remote.set("subtype", "null")?;
+            remote.set("value", value)?;
+        }
+        Type::Bool => {
+            remote.set("type", "boolean")?;
+            remote.set("value", value)?;
+        }
+        Type::Int | Type::Float => {
+            let number = value.as_number().unwrap_or_default();
+            remote.set("type", "number")?;
+            remote.set("description", number_description(number))?;
+            if number.is_finite() && !(number == 0.0 && number.is_sign_negative()) {
+                remote.set("value", value)?;
+            } else {
+                remote.set("unserializableValue", number_description(number))?;
+            }
+        }
+        Type::String => {
+            remote.set("type", "string")?;
+            remote.set("value", value)?;
+        }
+        Type::BigInt => {
+            let description = coerce_string(ctx, &value)?;
+            remote.set("type", "bigint")?;
+            remote.set("unserializableValue", [&description, "n"].concat())?;
+            remote.set("description", [&description, "n"].concat())?;
+        }
+        Type::Symbol => {
+            remote.set("type", "symbol")?;
+            remote.set("description", coerce_string(ctx, &value)?)?;
+            remote.set("objectId", register(ctx, value)?)?;
+        }
+        Type::Function | Type::Constructor => {
+            remote.set("type", "function")?;
+            remote.set("className", "Function")?;
+            remote.set("description", coerce_string(ctx, &value)?)?;
+            remote.set("objectId", register(ctx, value)?)?;
+        }
+        _ => {
+            let Some(object) = value.as_object() else {
+                remote.set("type", "undefined")?;
+                return Ok(remote);
+            };
+            let class_name = class_name(object);
+            remote.set("type", "object")?;
+            remote.set("className", &class_name)?;
+            if value.is_array() {
+                let length = value.as_array().map(Array::len).unwrap_or_default();
+                remote.set("subtype", "array")?;
+                remote.set(
+                    "description",
+                    [&class_name, "(", &length.to_string(), ")"].concat(),
+                )?;
+            } else if value.is_error() {
+                // QuickJS stacks only list the frames, without the message
+                let mut description = coerce_string(ctx, &value)?;
+                if let Ok(stack) = object.get::<_, String>("stack") {
+                    description.push('\n');
+                    description.push_str(stack.trim_end());
+                }
+                remote.set("subtype", "error")?;
+                remote.set("description", description)?;
+            } else if value.is_promise() {
+                remote.set("subtype", "promise")?;
+                remote.set("description", "Promise")?;
+            } else {
+                remote.set("description", &class_name)?;
+            }
+            if by_value {
+                remote.set("value", value)?;
+            } else {
+                remote.set("objectId", register(ctx, value)?)?;
+            }
+        }
+    }
+    Ok(remote)
+}
+
+fn number_description(number: f64) -> String {
+    if number.is_nan() {
+        "NaN".into()
+    } else if number.is_infinite() {
+        if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+        .into()
+    } else if number == 0.0 && number.is_sign_negative() {
+        "-0".into()
+    } else {
+        number.to_string()
+    }
+}
+
+fn coerce_string<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<String> {
+    let string: Function = ctx.globals().get("String")?;
+    string.call((value.clone(),))
+}
+
+fn class_name(object: &Object<'_>) -> String {
+    object
+        .get::<_, Object>("constructor")
+        .and_then(|constructor| constructor.get::<_, String>("name"))
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Object".into())
+}
+
+/// The result of running user code, with `exceptionDetails` when it threw.
+fn evaluation_result<'js>(
+    ctx: &Ctx<'js>,
+    value: std::result::Result<Value<'js>, CaughtError<'js>>,
+    by_value: bool,
+) -> Result<Object<'js>> {
+    let result = Object::new(ctx.clone())?;
+    match value {
+        Ok(value) => result.set("result", remote_object(ctx, value, by_value)?)?,
+        Err(err) => {
+            let exception = match err {
+                CaughtError::Exception(exception) => exception.into_value(),
+                CaughtError::Value(value) => value,
+                CaughtError::Error(err) => return Err(err),
+            };
+            let text = ["Uncaught ", &coerce_string(ctx, &exception)?].concat();
+            let exception = remote_object(ctx, exception, false)?;
+            let details = Object::new(ctx.clone())?;
+            details.set("exceptionId", 1)?;
+            details.set("text", text)?;
+            details.set("lineNumber", 0)?;
+            details.set("columnNumber", 0)?;
+            details.set("exception", exception.clone())?;
+            result.set("result", exception)?;
+            result.set("exceptionDetails", details)?;
+        }
+    }
+    Ok(result)
+}
+
+pub fn evaluate<'js>(ctx: &Ctx<'js>, params: &Object<'js>) -> Result<Object<'js>> {
+    let expression: String = params.get("expression")?;
+    let by_value = params
+        .get::<_, Option<bool>>("returnByValue")?
+        .unwrap_or_default();
+    let value = ctx
+        .eval_with_options::<Value, _>(
+            expression,
+            EvalOptions {
+                strict: false,
+                filename: Some("<inspector>".into()),
+                ..Default::default()
+            },
+        )
+        .catch(ctx);
+    evaluation_result(ctx, value, by_value)
+}
+
+/// Calls `functionDeclaration` with a registered object as `this`, which is
+/// how the frontend runs its helpers such as autocompletion.
+pub fn call_function_on<'js>(ctx: &Ctx<'js>, params: &Object<'js>) -> Result<Object<'js>> {
+    let declaration: String = params.get("functionDeclaration")?;
+    let by_value = params
+        .get::<_, Option<bool>>("returnByValue")?
+        .unwrap_or_default();
+    let this = match params.get::<_, Option<String>>("objectId")? {
+        Some(object_id) => lookup(ctx, &object_id)?,
+        None => ctx.globals().into_value(),
+    };
+    let mut args = Vec::new();
+    if let Some(arguments) = params.get::<_, Option<Array>>("arguments")? {
+        for argument in arguments.iter::<Object>() {
+            let argument = argument?;
+            args.push(match argument.get::<_, Option<String>>("objectId")? {
+                Some(object_id) => lookup(ctx, &object_id)?,
+                None => argument.get("value")?,
+            });
+        }
+    }
+
+    let value = ctx
+        .eval::<Function, _>(["(", &declaration, ")"].concat())
+        .and_then(|function| function.call::<_, Value>((This(this), Rest(args))))
+        .catch(ctx);
+    evaluation_result(ctx, value, by_value)
+}
+
+pub fn get_properties<'js>(ctx: &Ctx<'js>, params: &Object<'js>) -> Result<Object<'js>> {
+    let object_id: String = params.get("objectId")?;
+    let result = Array::new(ctx.clone())?;
+    if let Some(object) = lookup(ctx, &object_id)?.into_object() {
+        for key in object.own_keys::<String>(Filter::new().string()) {
+            let key = key?;
+            let value = object.get::<_, Value>(&key).catch(ctx);
+            let property = Object::new(ctx.clone())?;
+            property.set("name", &key)?;
+            property.set("configurable", true)?;
+            property.set("enumerable", true)?;
+            property.set("writable", true)?;
+            property.set("isOwn", true)?;
+            match value {
+                Ok(value) => property.set("value", remote_object(ctx, value, false)?)?,
+                Err(CaughtError::Error(err)) => return Err(err),
+                Err(_) => property.set("wasThrown", true)?,
+            }
+            result.set(result.len(), property)?;
+        }
+    }
+
+    let properties = Object::new(ctx.clone())?;
+    properties.set("result", result)?;
+    Ok(properties)
+}
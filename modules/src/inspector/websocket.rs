@@ -0,0 +1,185 @@
+//! The subset of RFC 6455 a DevTools frontend needs: the opening handshake,
+//! text frames in both directions, and ping/close control frames.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+
+use crate::utils::encoding::bytes_to_b64_string;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// DevTools messages are small, anything larger is a broken client
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// An HTTP request line and the headers the inspector looks at.
+pub struct Request {
+    pub path: String,
+    pub websocket_key: Option<String>,
+}
+
+pub fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let path = line.split(' ').nth(1).unwrap_or("/").to_string();
+
+    let mut websocket_key = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.trim().to_string());
+            }
+        }
+    }
+    Ok(Request {
+        path,
+        websocket_key,
+    })
+}
+
+pub fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+pub fn accept(stream: &mut TcpStream, key: &str) -> io::Result<()> {
+    let accept = digest(&SHA1_FOR_LEGACY_USE_ONLY, [key, GUID].concat().as_bytes());
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        bytes_to_b64_string(accept.as_ref())
+    )
+}
+
+/// Reads the next text message, answering pings on the way. `None` once the
+/// client closed the connection.
+pub fn read_message(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<Option<String>> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0; 2];
+        if let Err(err) = reader.read_exact(&mut head) {
+            return match err.kind() {
+                io::ErrorKind::UnexpectedEof => Ok(None),
+                _ => Err(err),
+            };
+        }
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len + message.len() as u64 > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "WebSocket message too large",
+            ));
+        }
+        let mut mask = [0; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; len as usize];
+        reader.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        match opcode {
+            OPCODE_TEXT | OPCODE_CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    return String::from_utf8(message)
+                        .map(Some)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+                }
+            }
+            OPCODE_PING => write_frame(writer, OPCODE_PONG, &payload)?,
+            OPCODE_CLOSE => {
+                let _ = write_frame(writer, OPCODE_CLOSE, &payload);
+                return Ok(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn write_message(writer: &mut impl Write, message: &str) -> io::Result<()> {
+    write_frame(writer, OPCODE_TEXT, message.as_bytes())
+}
+
+/// Writes an unmasked frame, as servers do.
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Writes a masked frame, as clients do.
+#[cfg(test)]
+pub fn write_client_message(writer: &mut impl Write, message: &str) -> io::Result<()> {
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let payload = message.as_bytes();
+    let mut frame = vec![0x80 | OPCODE_TEXT];
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4]),
+    );
+    writer.write_all(&frame)
+}
@@ -0,0 +1,142 @@
+//! Intl.Collator backed by the ICU4X collator.
+
+use std::rc::Rc;
+
+use icu::collator::{
+    options::{AlternateHandling, CaseLevel, CollatorOptions, Strength},
+    preferences::CollationNumericOrdering,
+    Collator as IcuCollator, CollatorBorrowed, CollatorPreferences,
+};
+use icu::locale::Locale;
+use rsquickjs::{
+    atom::PredefinedAtom, prelude::Opt, Coerced, Ctx, Exception, Function, Object, Result, Value,
+};
+
+use super::{parse_icu_locale, supported_locales};
+
+/// Intl.Collator for locale aware string comparison
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct Collator {
+    #[qjs(skip_trace)]
+    locale: Locale,
+    #[qjs(skip_trace)]
+    sensitivity: &'static str,
+    #[qjs(skip_trace)]
+    numeric: bool,
+    #[qjs(skip_trace)]
+    ignore_punctuation: bool,
+    #[qjs(skip_trace)]
+    collator: Rc<CollatorBorrowed<'static>>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl Collator {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'_>, locales: Opt<Value<'_>>, options: Opt<Object<'_>>) -> Result<Self> {
+        let locale = parse_icu_locale(&ctx, locales)?;
+
+        let mut sensitivity = "variant";
+        let mut numeric = false;
+        let mut ignore_punctuation = false;
+        if let Some(options_obj) = options.into_inner() {
+            if let Some(usage) = options_obj.get::<_, Option<String>>("usage")? {
+                if usage != "sort" && usage != "search" {
+                    return Err(Exception::throw_range(
+                        &ctx,
+                        &["Invalid usage: ", &usage].concat(),
+                    ));
+                }
+            }
+            if let Some(v) = options_obj.get::<_, Option<String>>("sensitivity")? {
+                sensitivity = match v.as_str() {
+                    "base" => "base",
+                    "accent" => "accent",
+                    "case" => "case",
+                    "variant" => "variant",
+                    _ => {
+                        return Err(Exception::throw_range(
+                            &ctx,
+                            &["Invalid sensitivity: ", &v].concat(),
+                        ))
+                    }
+                };
+            }
+            if let Ok(v) = options_obj.get::<_, bool>("numeric") {
+                numeric = v;
+            }
+            if let Ok(v) = options_obj.get::<_, bool>("ignorePunctuation") {
+                ignore_punctuation = v;
+            }
+        }
+
+        let mut collator_options = CollatorOptions::default();
+        collator_options.strength = Some(match sensitivity {
+            "base" | "case" => Strength::Primary,
+            "accent" => Strength::Secondary,
+            _ => Strength::Tertiary,
+        });
+        if sensitivity == "case" {
+            collator_options.case_level = Some(CaseLevel::On);
+        }
+        if ignore_punctuation {
+            collator_options.alternate_handling = Some(AlternateHandling::Shifted);
+        }
+        let mut preferences = CollatorPreferences::from(&locale);
+        if numeric {
+            preferences.numeric_ordering = Some(CollationNumericOrdering::True);
+        }
+        let collator = IcuCollator::try_new(preferences, collator_options)
+            .map_err(|_| Exception::throw_range(&ctx, "Locale data is not available"))?;
+
+        Ok(Self {
+            locale,
+            sensitivity,
+            numeric,
+            ignore_punctuation,
+            collator: Rc::new(collator),
+        })
+    }
+
+    /// The compare function, bound so it can be passed to `Array.prototype.sort`
+    #[qjs(get)]
+    pub fn compare<'js>(&self, ctx: Ctx<'js>) -> Result<Function<'js>> {
+        let collator = self.collator.clone();
+        Function::new(ctx, move |a: Coerced<String>, b: Coerced<String>| {
+            collator.compare(&a.0, &b.0) as i32
+        })
+    }
+
+    /// Return resolved options
+    #[qjs(rename = "resolvedOptions")]
+    pub fn resolved_options<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let obj = Object::new(ctx)?;
+
+        obj.set("locale", self.locale.to_string())?;
+        obj.set("usage", "sort")?;
+        obj.set("sensitivity", self.sensitivity)?;
+        obj.set("ignorePunctuation", self.ignore_punctuation)?;
+        obj.set("collation", "default")?;
+        obj.set("numeric", self.numeric)?;
+        obj.set("caseFirst", "false")?;
+
+        Ok(obj)
+    }
+
+    /// Return the locales that are supported out of the given ones
+    #[qjs(static, rename = "supportedLocalesOf")]
+    pub fn supported_locales_of(locales: Opt<Value<'_>>) -> Result<Vec<String>> {
+        supported_locales(locales)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Intl.Collator"
+    }
+}
+
+impl Collator {
+    pub fn compare_strings(&self, a: &str, b: &str) -> i32 {
+        self.collator.compare(a, b) as i32
+    }
+}
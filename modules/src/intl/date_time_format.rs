@@ -1,5 +1,6 @@
 //! Minimal Intl.DateTimeFormat implementation for timezone support.
 //! This provides just enough functionality to support dayjs and similar libraries.
+//! With the `intl-icu` feature the parts are formatted by ICU4X instead.
 
 use chrono::{DateTime, Datelike, Offset, TimeZone, Timelike, Utc};
 use chrono_tz::Tz;
@@ -7,6 +8,9 @@ use rsquickjs::{
     atom::PredefinedAtom, prelude::Opt, Array, Coerced, Ctx, Exception, Object, Result, Value,
 };
 
+#[cfg(feature = "intl-icu")]
+use super::date_time_icu::IcuDateTimeFormatter;
+
 /// Stores the resolved options for a DateTimeFormat instance
 #[derive(Clone, Debug)]
 pub struct DateTimeFormatOptions {
@@ -283,6 +287,30 @@ fn epoch_to_datetime(ctx: &Ctx<'_>, epoch_ms: f64, timezone: &Tz) -> Result<Date
 pub struct DateTimeFormat {
     #[qjs(skip_trace)]
     options: DateTimeFormatOptions,
+    #[cfg(feature = "intl-icu")]
+    #[qjs(skip_trace)]
+    formatter: Option<IcuDateTimeFormatter>,
+}
+
+impl DateTimeFormat {
+    /// Format the parts with ICU4X when it supports the options, chrono otherwise
+    fn format_parts(&self, local_dt: &DateTime<Tz>) -> Vec<FormatPart> {
+        #[cfg(feature = "intl-icu")]
+        if let Some(mut parts) = self
+            .formatter
+            .as_ref()
+            .and_then(|formatter| formatter.format_parts(local_dt))
+        {
+            if let Some(ref tz_name_opt) = self.options.timezone_name {
+                parts.push(FormatPart::literal(" "));
+                let tz_str = format_timezone_name(local_dt, &self.options.timezone, tz_name_opt);
+                parts.push(FormatPart::new("timeZoneName", tz_str));
+            }
+            return parts;
+        }
+
+        build_format_parts(local_dt, &self.options)
+    }
 }
 
 #[rsquickjs::methods(rename_all = "camelCase")]
@@ -348,14 +376,18 @@ impl DateTimeFormat {
             }
         }
 
-        Ok(Self { options: opts })
+        Ok(Self {
+            #[cfg(feature = "intl-icu")]
+            formatter: IcuDateTimeFormatter::try_new(&opts),
+            options: opts,
+        })
     }
 
     /// Format a date according to the locale and options
     pub fn format<'js>(&self, ctx: Ctx<'js>, date: Opt<Value<'js>>) -> Result<String> {
         let epoch_ms = parse_epoch_ms(&ctx, date)?;
         let local_dt = epoch_to_datetime(&ctx, epoch_ms, &self.options.timezone)?;
-        let parts = build_format_parts(&local_dt, &self.options);
+        let parts = self.format_parts(&local_dt);
         Ok(parts_to_string(&parts))
     }
 
@@ -364,7 +396,7 @@ impl DateTimeFormat {
     pub fn format_to_parts<'js>(&self, ctx: Ctx<'js>, date: Opt<Value<'js>>) -> Result<Array<'js>> {
         let epoch_ms = parse_epoch_ms(&ctx, date)?;
         let local_dt = epoch_to_datetime(&ctx, epoch_ms, &self.options.timezone)?;
-        let parts = build_format_parts(&local_dt, &self.options);
+        let parts = self.format_parts(&local_dt);
         parts_to_js_array(&ctx, parts)
     }
 
@@ -0,0 +1,274 @@
+//! ICU4X backend for Intl.DateTimeFormat.
+//!
+//! The component options are mapped onto the closest ICU4X field set, so the
+//! patterns, names and separators come from CLDR for the requested locale.
+//! Time zone names are still appended from chrono-tz.
+
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Timelike};
+use chrono_tz::Tz;
+use icu::calendar::Gregorian;
+use icu::datetime::fieldsets::builder::{DateFields, FieldSetBuilder};
+use icu::datetime::fieldsets::enums::CompositeDateTimeFieldSet;
+use icu::datetime::input::{Date, DateTime as IcuDateTime, Time};
+use icu::datetime::options::{Alignment, Length, SubsecondDigits, TimePrecision, YearStyle};
+use icu::datetime::{DateTimeFormatterPreferences, FixedCalendarDateTimeFormatter};
+use icu::locale::preferences::extensions::unicode::keywords::HourCycle;
+use icu::locale::Locale;
+use writeable::{Part, PartsWrite, Writeable};
+
+use super::date_time_format::{DateTimeFormatOptions, FormatPart};
+
+/// A Gregorian ICU4X formatter for the resolved DateTimeFormat options
+#[derive(Clone, Debug)]
+pub struct IcuDateTimeFormatter {
+    formatter: FixedCalendarDateTimeFormatter<Gregorian, CompositeDateTimeFieldSet>,
+}
+
+impl IcuDateTimeFormatter {
+    /// Load the formatter, `None` when the locale or the options have no ICU4X equivalent
+    pub fn try_new(options: &DateTimeFormatOptions) -> Option<Self> {
+        let locale = Locale::try_from_str(&options.locale).ok()?;
+        let mut prefs = DateTimeFormatterPreferences::from(&locale);
+        prefs.hour_cycle = Some(if options.hour12 {
+            HourCycle::H12
+        } else {
+            HourCycle::H23
+        });
+
+        let field_set = field_set(options).build_composite_datetime().ok()?;
+        let formatter = FixedCalendarDateTimeFormatter::try_new(prefs, field_set).ok()?;
+        Some(Self { formatter })
+    }
+
+    /// Format the local date time into parts
+    pub fn format_parts(&self, local_dt: &DateTime<Tz>) -> Option<Vec<FormatPart>> {
+        let date = Date::try_new_gregorian(
+            local_dt.year(),
+            local_dt.month() as u8,
+            local_dt.day() as u8,
+        )
+        .ok()?;
+        let time = Time::try_new(
+            local_dt.hour() as u8,
+            local_dt.minute() as u8,
+            local_dt.second() as u8,
+            local_dt.nanosecond() % 1_000_000_000,
+        )
+        .ok()?;
+
+        let mut sink = PartsSink::default();
+        self.formatter
+            .format(&IcuDateTime { date, time })
+            .write_to_parts(&mut sink)
+            .ok()?;
+        Some(sink.parts)
+    }
+}
+
+/// Map the component options onto a field set, with the ECMA-402 default of a
+/// numeric date when no component is requested
+fn field_set(options: &DateTimeFormatOptions) -> FieldSetBuilder {
+    let year = options.year.is_some();
+    let month = options.month.is_some();
+    let day = options.day.is_some();
+    let weekday = options.weekday.is_some();
+    let has_time = options.hour.is_some() || options.minute.is_some() || options.second.is_some();
+
+    // ICU4X has no field set for every combination, e.g. year and day without
+    // a month, so those fall back to the nearest superset
+    let date_fields = match (year, month, day, weekday) {
+        (false, false, false, false) if has_time => None,
+        (false, false, false, false) => Some(DateFields::YMD),
+        (false, false, false, true) => Some(DateFields::E),
+        (false, false, true, false) => Some(DateFields::D),
+        (false, false, true, true) => Some(DateFields::DE),
+        (false, true, false, false) => Some(DateFields::M),
+        (false, true, _, false) => Some(DateFields::MD),
+        (false, true, _, true) => Some(DateFields::MDE),
+        (true, false, false, false) => Some(DateFields::Y),
+        (true, true, false, false) => Some(DateFields::YM),
+        (true, _, _, false) => Some(DateFields::YMD),
+        (true, _, _, true) => Some(DateFields::YMDE),
+    };
+
+    let time_precision = if options.second.is_some() {
+        Some(
+            options
+                .fractional_second_digits
+                .and_then(SubsecondDigits::try_from_int)
+                .map_or(TimePrecision::Second, TimePrecision::Subsecond),
+        )
+    } else if options.minute.is_some() {
+        Some(TimePrecision::Minute)
+    } else if options.hour.is_some() {
+        Some(TimePrecision::Hour)
+    } else {
+        None
+    };
+
+    let mut builder = FieldSetBuilder::new();
+    builder.date_fields = date_fields;
+    builder.time_precision = time_precision;
+
+    if date_fields.is_some() {
+        // Text months pick the length, a long weekday alone asks for the long form
+        builder.length = Some(
+            match (options.month.as_deref(), options.weekday.as_deref()) {
+                (Some("long"), _) => Length::Long,
+                (Some("short" | "narrow"), _) => Length::Medium,
+                (None, Some("long")) => Length::Long,
+                (None, Some(_)) => Length::Medium,
+                _ => Length::Short,
+            },
+        );
+    }
+    let with_year = matches!(
+        date_fields,
+        Some(DateFields::Y | DateFields::YM | DateFields::YMD | DateFields::YMDE)
+    );
+    if with_year {
+        builder.year_style = Some(match options.year.as_deref() {
+            Some("2-digit") => YearStyle::Auto,
+            _ => YearStyle::Full,
+        });
+    }
+
+    // Minutes and seconds are always padded, so only these decide the alignment
+    let two_digit = [&options.month, &options.day, &options.hour]
+        .iter()
+        .any(|style| style.as_deref() == Some("2-digit"));
+    if two_digit {
+        builder.alignment = Some(Alignment::Column);
+    }
+
+    builder
+}
+
+/// Collects the written string into parts, using the outermost datetime part
+/// since numbers are annotated again by the decimal formatter
+#[derive(Default)]
+struct PartsSink {
+    parts: Vec<FormatPart>,
+    current: Option<Part>,
+}
+
+impl fmt::Write for PartsSink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let part_type = self.current.map_or("literal", |part| part.value);
+        match self.parts.last_mut() {
+            Some(last) if last.part_type == part_type => last.value.push_str(s),
+            _ => self.parts.push(FormatPart {
+                part_type,
+                value: s.to_string(),
+            }),
+        }
+        Ok(())
+    }
+}
+
+impl PartsWrite for PartsSink {
+    type SubPartsWrite = Self;
+
+    fn with_part(
+        &mut self,
+        part: Part,
+        mut f: impl FnMut(&mut Self::SubPartsWrite) -> fmt::Result,
+    ) -> fmt::Result {
+        if self.current.is_some() {
+            return f(self);
+        }
+        self.current = Some(part);
+        let result = f(self);
+        self.current = None;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn format(options: DateTimeFormatOptions) -> Vec<(&'static str, String)> {
+        // 2024-03-05 14:07:09 UTC
+        let local_dt = Utc
+            .timestamp_opt(1709647629, 0)
+            .unwrap()
+            .with_timezone(&options.timezone);
+        IcuDateTimeFormatter::try_new(&options)
+            .unwrap()
+            .format_parts(&local_dt)
+            .unwrap()
+            .into_iter()
+            .map(|part| (part.part_type, part.value))
+            .collect()
+    }
+
+    fn joined(parts: &[(&'static str, String)]) -> String {
+        parts.iter().map(|(_, value)| value.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_is_numeric_date() {
+        let parts = format(DateTimeFormatOptions::default());
+        assert_eq!(joined(&parts), "3/5/2024");
+        assert_eq!(parts[0], ("month", "3".to_string()));
+        assert_eq!(parts[1], ("literal", "/".to_string()));
+        assert_eq!(parts[4], ("year", "2024".to_string()));
+    }
+
+    #[test]
+    fn test_two_digit_date_and_time() {
+        let parts = format(DateTimeFormatOptions {
+            year: Some("numeric".to_string()),
+            month: Some("2-digit".to_string()),
+            day: Some("2-digit".to_string()),
+            hour: Some("2-digit".to_string()),
+            minute: Some("2-digit".to_string()),
+            second: Some("2-digit".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(joined(&parts), "03/05/2024, 14:07:09");
+        assert!(parts.contains(&("hour", "14".to_string())));
+    }
+
+    #[test]
+    fn test_hour12_day_period() {
+        let parts = format(DateTimeFormatOptions {
+            hour12: true,
+            hour: Some("numeric".to_string()),
+            minute: Some("2-digit".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(parts[0], ("hour", "2".to_string()));
+        assert_eq!(parts.last().unwrap(), &("dayPeriod", "PM".to_string()));
+    }
+
+    #[test]
+    fn test_locale_names() {
+        let parts = format(DateTimeFormatOptions {
+            locale: "de-DE".to_string(),
+            year: Some("numeric".to_string()),
+            month: Some("long".to_string()),
+            day: Some("numeric".to_string()),
+            weekday: Some("long".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(joined(&parts), "Dienstag, 5. März 2024");
+        assert!(parts.contains(&("month", "März".to_string())));
+    }
+
+    #[test]
+    fn test_local_time_zone() {
+        let parts = format(DateTimeFormatOptions {
+            timezone: chrono_tz::Asia::Tokyo,
+            hour: Some("2-digit".to_string()),
+            minute: Some("2-digit".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(joined(&parts), "23:07");
+    }
+}
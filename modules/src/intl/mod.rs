@@ -1,11 +1,28 @@
 //! Minimal Intl module for LLRT.
 //!
 //! Provides a subset of the `Intl` API focused on timezone support,
-//! enabling compatibility with libraries like dayjs.
+//! enabling compatibility with libraries like dayjs. With the `intl-icu`
+//! feature, `DateTimeFormat` formats with ICU4X and `NumberFormat`, `Collator`
+//! and `PluralRules` are backed by ICU4X.
 
 mod cldr_data;
+#[cfg(feature = "intl-icu")]
+mod collator;
 mod date_time_format;
+#[cfg(feature = "intl-icu")]
+mod date_time_icu;
+#[cfg(feature = "intl-icu")]
+mod number_format;
 mod pattern_formatter;
+#[cfg(feature = "intl-icu")]
+mod plural_rules;
+
+#[cfg(feature = "intl-icu")]
+pub use collator::Collator;
+#[cfg(feature = "intl-icu")]
+pub use number_format::NumberFormat;
+#[cfg(feature = "intl-icu")]
+pub use plural_rules::PluralRules;
 
 pub use date_time_format::{
     format_date_in_timezone, get_system_timezone, parse_to_locale_string_options, DateTimeFormat,
@@ -32,15 +49,106 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     // Add DateTimeFormat constructor
     Class::<DateTimeFormat>::define(&intl)?;
 
+    // Add the ICU4X backed constructors
+    #[cfg(feature = "intl-icu")]
+    {
+        Class::<NumberFormat>::define(&intl)?;
+        Class::<Collator>::define(&intl)?;
+        Class::<PluralRules>::define(&intl)?;
+    }
+
     // Set Intl global
     globals.set("Intl", intl)?;
 
     // Patch Date.prototype.toLocaleString to support timeZone option
     patch_date_to_locale_string(ctx)?;
 
+    // Patch Number.prototype.toLocaleString and String.prototype.localeCompare
+    #[cfg(feature = "intl-icu")]
+    patch_icu_prototypes(ctx)?;
+
+    Ok(())
+}
+
+/// Patch the prototype methods that take locales to use NumberFormat and Collator
+#[cfg(feature = "intl-icu")]
+fn patch_icu_prototypes(ctx: &Ctx<'_>) -> Result<()> {
+    let globals = ctx.globals();
+
+    let number_ctor: Constructor = globals.get("Number")?;
+    let number_proto: Object = number_ctor.get("prototype")?;
+    number_proto.set("toLocaleString", Func::from(number_to_locale_string))?;
+
+    let string_ctor: Constructor = globals.get("String")?;
+    let string_proto: Object = string_ctor.get("prototype")?;
+    string_proto.set("localeCompare", Func::from(string_locale_compare))?;
+
     Ok(())
 }
 
+/// Number.prototype.toLocaleString formatting with Intl.NumberFormat
+#[cfg(feature = "intl-icu")]
+fn number_to_locale_string<'js>(
+    ctx: Ctx<'js>,
+    this: This<Value<'js>>,
+    locales: Opt<Value<'js>>,
+    options: Opt<Object<'js>>,
+) -> Result<String> {
+    let value = this
+        .0
+        .get::<Coerced<f64>>()
+        .map_err(|_| Exception::throw_type(&ctx, "this is not a Number"))?;
+    let format = NumberFormat::new(ctx, locales, options)?;
+    Ok(format.format_number(value.0))
+}
+
+/// String.prototype.localeCompare comparing with Intl.Collator
+#[cfg(feature = "intl-icu")]
+fn string_locale_compare<'js>(
+    ctx: Ctx<'js>,
+    this: This<Coerced<String>>,
+    that: Coerced<String>,
+    locales: Opt<Value<'js>>,
+    options: Opt<Object<'js>>,
+) -> Result<i32> {
+    let collator = Collator::new(ctx, locales, options)?;
+    Ok(collator.compare_strings(&this.0 .0, &that.0))
+}
+
+/// Parse the locales argument into an ICU4X locale, defaulting to en-US
+#[cfg(feature = "intl-icu")]
+fn parse_icu_locale(ctx: &Ctx<'_>, locales: Opt<Value<'_>>) -> Result<icu::locale::Locale> {
+    let locale = parse_locale_arg(locales)?;
+    icu::locale::Locale::try_from_str(&locale).map_err(|_| {
+        Exception::throw_range(
+            ctx,
+            &["Incorrect locale information provided: ", &locale].concat(),
+        )
+    })
+}
+
+/// The canonical form of every valid locale in the locales argument
+#[cfg(feature = "intl-icu")]
+fn supported_locales(locales: Opt<Value<'_>>) -> Result<Vec<String>> {
+    let mut requested = Vec::new();
+    if let Some(val) = locales.into_inner() {
+        if let Some(s) = val.as_string() {
+            requested.push(s.to_string()?);
+        } else if let Some(arr) = val.as_array() {
+            for item in arr.iter::<Value>() {
+                if let Some(s) = item?.as_string() {
+                    requested.push(s.to_string()?);
+                }
+            }
+        }
+    }
+    Ok(requested
+        .iter()
+        .filter_map(|locale| icu::locale::Locale::try_from_str(locale).ok())
+        .map(|locale| locale.to_string())
+        .collect())
+}
+
 /// Patch Date.prototype.toLocaleString to support the timeZone option
 fn patch_date_to_locale_string(ctx: &Ctx<'_>) -> Result<()> {
     let globals = ctx.globals();
@@ -0,0 +1,464 @@
+//! Intl.NumberFormat backed by ICU4X decimal formatting.
+//!
+//! ICU4X handles the locale specific digits, separators and grouping. Rounding,
+//! percent and currency placement are done here.
+
+use icu::decimal::{
+    input::Decimal,
+    options::{DecimalFormatterOptions, GroupingStrategy},
+    DecimalFormatter,
+};
+use icu::locale::Locale;
+use rsquickjs::{
+    atom::PredefinedAtom, prelude::Opt, Coerced, Ctx, Exception, Object, Result, Value,
+};
+
+use super::{parse_icu_locale, supported_locales};
+
+/// Digit options shared by NumberFormat and PluralRules
+#[derive(Clone, Debug)]
+pub struct DigitOptions {
+    pub minimum_integer_digits: usize,
+    pub minimum_fraction_digits: usize,
+    pub maximum_fraction_digits: usize,
+    pub maximum_significant_digits: Option<usize>,
+}
+
+impl DigitOptions {
+    /// Parse the digit options, `default_fraction` is the (minimum, maximum) of the style
+    pub fn parse<'js>(
+        ctx: &Ctx<'js>,
+        options: Option<&Object<'js>>,
+        default_fraction: (usize, usize),
+    ) -> Result<Self> {
+        let mut digits = Self {
+            minimum_integer_digits: 1,
+            minimum_fraction_digits: default_fraction.0,
+            maximum_fraction_digits: default_fraction.1,
+            maximum_significant_digits: None,
+        };
+        let Some(options) = options else {
+            return Ok(digits);
+        };
+
+        if let Some(v) = digit_option(ctx, options, "minimumIntegerDigits", 1, 21)? {
+            digits.minimum_integer_digits = v;
+        }
+        let minimum = digit_option(ctx, options, "minimumFractionDigits", 0, 100)?;
+        let maximum = digit_option(ctx, options, "maximumFractionDigits", 0, 100)?;
+        if let Some(v) = minimum {
+            digits.minimum_fraction_digits = v;
+            digits.maximum_fraction_digits = digits.maximum_fraction_digits.max(v);
+        }
+        if let Some(v) = maximum {
+            if minimum.is_some_and(|minimum| minimum > v) {
+                return Err(Exception::throw_range(
+                    ctx,
+                    "maximumFractionDigits is less than minimumFractionDigits",
+                ));
+            }
+            digits.maximum_fraction_digits = v;
+            digits.minimum_fraction_digits = digits.minimum_fraction_digits.min(v);
+        }
+        digits.maximum_significant_digits =
+            digit_option(ctx, options, "maximumSignificantDigits", 1, 21)?;
+
+        Ok(digits)
+    }
+
+    /// Format a finite number as a plain decimal string like `-0012.50`, rounding half
+    /// away from zero on its shortest decimal representation like ICU does
+    pub fn to_decimal_string(&self, value: f64) -> String {
+        // Rust prints the shortest round-trip representation, without an exponent
+        let plain = value.abs().to_string();
+        let (int, frac) = plain.split_once('.').unwrap_or((&plain, ""));
+        let mut digits: Vec<u8> = [int.as_bytes(), frac.as_bytes()].concat();
+        let mut point = int.len() as isize;
+
+        let fraction_digits = match self.maximum_significant_digits {
+            Some(significant) => match digits.iter().position(|d| *d != b'0') {
+                Some(first) => first as isize + significant as isize - point,
+                None => 0,
+            },
+            None => self.maximum_fraction_digits as isize,
+        };
+
+        let keep = point + fraction_digits;
+        if keep < 0 {
+            digits.clear();
+        } else if (keep as usize) < digits.len() {
+            let round_up = digits[keep as usize] >= b'5';
+            digits.truncate(keep as usize);
+            if round_up {
+                let mut i = digits.len();
+                loop {
+                    if i == 0 {
+                        digits.insert(0, b'1');
+                        point += 1;
+                        break;
+                    }
+                    i -= 1;
+                    if digits[i] == b'9' {
+                        digits[i] = b'0';
+                    } else {
+                        digits[i] += 1;
+                        break;
+                    }
+                }
+            }
+        }
+        // Rounding to tens or more leaves places before the point to fill
+        while (digits.len() as isize) < point {
+            digits.push(b'0');
+        }
+
+        let split = point.clamp(0, digits.len() as isize) as usize;
+        let (int, frac) = digits.split_at(split);
+        let int = std::str::from_utf8(int)
+            .unwrap_or_default()
+            .trim_start_matches('0');
+        let mut frac = std::str::from_utf8(frac).unwrap_or_default().to_string();
+        if self.maximum_significant_digits.is_some() {
+            frac.truncate(frac.trim_end_matches('0').len());
+        } else {
+            frac.truncate(
+                frac.trim_end_matches('0')
+                    .len()
+                    .max(self.minimum_fraction_digits),
+            );
+            while frac.len() < self.minimum_fraction_digits {
+                frac.push('0');
+            }
+        }
+
+        let mut result = String::with_capacity(int.len() + frac.len() + 2);
+        if value < 0.0 {
+            result.push('-');
+        }
+        for _ in int.len()..self.minimum_integer_digits {
+            result.push('0');
+        }
+        result.push_str(int);
+        if !frac.is_empty() {
+            result.push('.');
+            result.push_str(&frac);
+        }
+        result
+    }
+
+    /// Format a finite number as an ICU4X decimal
+    pub fn to_decimal(&self, value: f64) -> Decimal {
+        self.to_decimal_string(value)
+            .parse()
+            .unwrap_or_else(|_| Decimal::from(0))
+    }
+
+    /// Add the digit options to a resolvedOptions object
+    pub fn set_resolved(&self, obj: &Object<'_>) -> Result<()> {
+        obj.set("minimumIntegerDigits", self.minimum_integer_digits)?;
+        obj.set("minimumFractionDigits", self.minimum_fraction_digits)?;
+        obj.set("maximumFractionDigits", self.maximum_fraction_digits)?;
+        if let Some(v) = self.maximum_significant_digits {
+            obj.set("maximumSignificantDigits", v)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read an integer option in the range `min..=max`
+fn digit_option<'js>(
+    ctx: &Ctx<'js>,
+    options: &Object<'js>,
+    name: &str,
+    min: usize,
+    max: usize,
+) -> Result<Option<usize>> {
+    let value: Value = options.get(name)?;
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    let Coerced(value) = value.get::<Coerced<f64>>()?;
+    if !value.is_finite() || value < min as f64 || value > max as f64 {
+        return Err(Exception::throw_range(
+            ctx,
+            &[name, " value is out of range"].concat(),
+        ));
+    }
+    Ok(Some(value.floor() as usize))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Style {
+    Decimal,
+    Percent,
+    Currency { code: String, display_code: bool },
+}
+
+/// Intl.NumberFormat with the decimal, percent and currency styles
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct NumberFormat {
+    #[qjs(skip_trace)]
+    locale: Locale,
+    #[qjs(skip_trace)]
+    style: Style,
+    #[qjs(skip_trace)]
+    digits: DigitOptions,
+    #[qjs(skip_trace)]
+    use_grouping: bool,
+    #[qjs(skip_trace)]
+    formatter: DecimalFormatter,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl NumberFormat {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'_>, locales: Opt<Value<'_>>, options: Opt<Object<'_>>) -> Result<Self> {
+        let locale = parse_icu_locale(&ctx, locales)?;
+        let options = options.into_inner();
+
+        let mut style = Style::Decimal;
+        let mut use_grouping = true;
+        if let Some(options_obj) = &options {
+            match options_obj
+                .get::<_, Option<String>>("style")?
+                .as_deref()
+                .unwrap_or("decimal")
+            {
+                "decimal" => {}
+                "percent" => style = Style::Percent,
+                "currency" => {
+                    let code = options_obj
+                        .get::<_, Option<String>>("currency")?
+                        .ok_or_else(|| {
+                            Exception::throw_type(
+                                &ctx,
+                                "Currency code is required with currency style",
+                            )
+                        })?;
+                    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_alphabetic()) {
+                        return Err(Exception::throw_range(
+                            &ctx,
+                            &["Invalid currency code: ", &code].concat(),
+                        ));
+                    }
+                    let display_code = match options_obj
+                        .get::<_, Option<String>>("currencyDisplay")?
+                        .as_deref()
+                    {
+                        None | Some("symbol") | Some("narrowSymbol") => false,
+                        Some("code") => true,
+                        Some(v) => {
+                            return Err(Exception::throw_range(
+                                &ctx,
+                                &["Unsupported currencyDisplay: ", v].concat(),
+                            ))
+                        }
+                    };
+                    style = Style::Currency {
+                        code: code.to_ascii_uppercase(),
+                        display_code,
+                    };
+                }
+                v => {
+                    return Err(Exception::throw_range(
+                        &ctx,
+                        &["Unsupported style: ", v].concat(),
+                    ))
+                }
+            }
+            if let Ok(v) = options_obj.get::<_, bool>("useGrouping") {
+                use_grouping = v;
+            }
+        }
+
+        let default_fraction = match &style {
+            Style::Decimal => (0, 3),
+            Style::Percent => (0, 0),
+            Style::Currency { code, .. } => {
+                let digits = currency_digits(code);
+                (digits, digits)
+            }
+        };
+        let digits = DigitOptions::parse(&ctx, options.as_ref(), default_fraction)?;
+
+        let mut formatter_options = DecimalFormatterOptions::default();
+        if !use_grouping {
+            formatter_options.grouping_strategy = Some(GroupingStrategy::Never);
+        }
+        let formatter = DecimalFormatter::try_new((&locale).into(), formatter_options)
+            .map_err(|_| Exception::throw_range(&ctx, "Locale data is not available"))?;
+
+        Ok(Self {
+            locale,
+            style,
+            digits,
+            use_grouping,
+            formatter,
+        })
+    }
+
+    /// Format a number according to the locale and options
+    pub fn format(&self, value: Coerced<f64>) -> String {
+        self.format_number(value.0)
+    }
+
+    /// Return resolved options
+    #[qjs(rename = "resolvedOptions")]
+    pub fn resolved_options<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let obj = Object::new(ctx)?;
+
+        obj.set("locale", self.locale.to_string())?;
+        obj.set("numberingSystem", "latn")?;
+        match &self.style {
+            Style::Decimal => obj.set("style", "decimal")?,
+            Style::Percent => obj.set("style", "percent")?,
+            Style::Currency { code, display_code } => {
+                obj.set("style", "currency")?;
+                obj.set("currency", code.as_str())?;
+                obj.set(
+                    "currencyDisplay",
+                    if *display_code { "code" } else { "symbol" },
+                )?;
+            }
+        }
+        self.digits.set_resolved(&obj)?;
+        obj.set("useGrouping", self.use_grouping)?;
+
+        Ok(obj)
+    }
+
+    /// Return the locales that are supported out of the given ones
+    #[qjs(static, rename = "supportedLocalesOf")]
+    pub fn supported_locales_of(locales: Opt<Value<'_>>) -> Result<Vec<String>> {
+        supported_locales(locales)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Intl.NumberFormat"
+    }
+}
+
+impl NumberFormat {
+    pub fn format_number(&self, value: f64) -> String {
+        if value.is_nan() {
+            return "NaN".to_string();
+        }
+        let value = if self.style == Style::Percent {
+            value * 100.0
+        } else {
+            value
+        };
+        let number = if value.is_infinite() {
+            if value < 0.0 { "-∞" } else { "∞" }.to_string()
+        } else {
+            self.formatter
+                .format_to_string(&self.digits.to_decimal(value))
+        };
+
+        let language = self.locale.id.language.as_str();
+        match &self.style {
+            Style::Decimal => number,
+            Style::Percent => {
+                if SPACED_SUFFIX_LANGUAGES.contains(&language) {
+                    [number.as_str(), "\u{a0}%"].concat()
+                } else {
+                    [number.as_str(), "%"].concat()
+                }
+            }
+            Style::Currency { code, display_code } => {
+                let symbol = if *display_code {
+                    code.as_str()
+                } else {
+                    currency_symbol(code, language)
+                };
+                let (sign, number) = match number.strip_prefix('-') {
+                    Some(number) => ("-", number),
+                    None => ("", number.as_str()),
+                };
+                if SPACED_SUFFIX_LANGUAGES.contains(&language) {
+                    [sign, number, "\u{a0}", symbol].concat()
+                } else if symbol.len() == 3 && symbol.bytes().all(|b| b.is_ascii_uppercase()) {
+                    [sign, symbol, "\u{a0}", number].concat()
+                } else {
+                    [sign, symbol, number].concat()
+                }
+            }
+        }
+    }
+}
+
+/// Languages that put percent and currency signs after the number, with a space
+const SPACED_SUFFIX_LANGUAGES: &[&str] = &[
+    "cs", "da", "de", "es", "fi", "fr", "it", "nb", "pl", "pt", "ru", "sv", "uk", "vi",
+];
+
+/// Minor unit digits of a currency, from ISO 4217
+fn currency_digits(code: &str) -> usize {
+    match code {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// Symbol of a currency, falling back to its code
+fn currency_symbol<'a>(code: &'a str, language: &str) -> &'a str {
+    match (code, language) {
+        ("USD", "en") => "$",
+        ("USD", _) => "US$",
+        ("EUR", _) => "€",
+        ("GBP", _) => "£",
+        ("JPY", "ja") => "￥",
+        ("JPY", _) => "¥",
+        ("CNY", "zh") => "¥",
+        ("CNY", _) => "CN¥",
+        ("INR", _) => "₹",
+        ("KRW", _) => "₩",
+        ("BRL", _) => "R$",
+        ("RUB", "ru") => "₽",
+        ("CAD", _) => "CA$",
+        ("AUD", _) => "A$",
+        ("ILS", _) => "₪",
+        ("VND", _) => "₫",
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(min: usize, max: usize) -> DigitOptions {
+        DigitOptions {
+            minimum_integer_digits: 1,
+            minimum_fraction_digits: min,
+            maximum_fraction_digits: max,
+            maximum_significant_digits: None,
+        }
+    }
+
+    #[test]
+    fn test_to_decimal_string() {
+        assert_eq!(digits(0, 3).to_decimal_string(1234.5678), "1234.568");
+        assert_eq!(digits(2, 2).to_decimal_string(1.005), "1.01");
+        assert_eq!(digits(2, 2).to_decimal_string(-0.5), "-0.50");
+        assert_eq!(digits(0, 0).to_decimal_string(9.5), "10");
+        assert_eq!(digits(0, 2).to_decimal_string(1.999), "2");
+        assert_eq!(
+            digits(0, 3).to_decimal_string(1e21),
+            "1000000000000000000000"
+        );
+
+        let mut significant = digits(0, 3);
+        significant.maximum_significant_digits = Some(2);
+        assert_eq!(significant.to_decimal_string(123456.0), "120000");
+        assert_eq!(significant.to_decimal_string(0.001234), "0.0012");
+
+        let mut padded = digits(0, 0);
+        padded.minimum_integer_digits = 3;
+        assert_eq!(padded.to_decimal_string(7.0), "007");
+    }
+}
@@ -0,0 +1,106 @@
+//! Intl.PluralRules backed by the ICU4X plural rules.
+
+use icu::locale::Locale;
+use icu::plurals::{PluralCategory, PluralRules as IcuPluralRules};
+use rsquickjs::{
+    atom::PredefinedAtom, prelude::Opt, Coerced, Ctx, Exception, Object, Result, Value,
+};
+
+use super::{number_format::DigitOptions, parse_icu_locale, supported_locales};
+
+/// Intl.PluralRules for cardinal and ordinal plural categories
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct PluralRules {
+    #[qjs(skip_trace)]
+    locale: Locale,
+    #[qjs(skip_trace)]
+    ordinal: bool,
+    #[qjs(skip_trace)]
+    digits: DigitOptions,
+    #[qjs(skip_trace)]
+    rules: IcuPluralRules,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl PluralRules {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'_>, locales: Opt<Value<'_>>, options: Opt<Object<'_>>) -> Result<Self> {
+        let locale = parse_icu_locale(&ctx, locales)?;
+        let options = options.into_inner();
+
+        let mut ordinal = false;
+        if let Some(options_obj) = &options {
+            match options_obj.get::<_, Option<String>>("type")?.as_deref() {
+                None | Some("cardinal") => {}
+                Some("ordinal") => ordinal = true,
+                Some(v) => {
+                    return Err(Exception::throw_range(
+                        &ctx,
+                        &["Invalid type: ", v].concat(),
+                    ))
+                }
+            }
+        }
+        let digits = DigitOptions::parse(&ctx, options.as_ref(), (0, 3))?;
+
+        let rules = if ordinal {
+            IcuPluralRules::try_new_ordinal((&locale).into())
+        } else {
+            IcuPluralRules::try_new_cardinal((&locale).into())
+        }
+        .map_err(|_| Exception::throw_range(&ctx, "Locale data is not available"))?;
+
+        Ok(Self {
+            locale,
+            ordinal,
+            digits,
+            rules,
+        })
+    }
+
+    /// Return the plural category of a number, like "one" or "other"
+    pub fn select(&self, value: Coerced<f64>) -> &'static str {
+        if !value.0.is_finite() {
+            return "other";
+        }
+        let decimal = self.digits.to_decimal(value.0);
+        category_name(self.rules.category_for(&decimal))
+    }
+
+    /// Return resolved options
+    #[qjs(rename = "resolvedOptions")]
+    pub fn resolved_options<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let obj = Object::new(ctx)?;
+
+        obj.set("locale", self.locale.to_string())?;
+        obj.set("type", if self.ordinal { "ordinal" } else { "cardinal" })?;
+        self.digits.set_resolved(&obj)?;
+        let categories: Vec<&str> = self.rules.categories().map(category_name).collect();
+        obj.set("pluralCategories", categories)?;
+
+        Ok(obj)
+    }
+
+    /// Return the locales that are supported out of the given ones
+    #[qjs(static, rename = "supportedLocalesOf")]
+    pub fn supported_locales_of(locales: Opt<Value<'_>>) -> Result<Vec<String>> {
+        supported_locales(locales)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Intl.PluralRules"
+    }
+}
+
+fn category_name(category: PluralCategory) -> &'static str {
+    match category {
+        PluralCategory::Zero => "zero",
+        PluralCategory::One => "one",
+        PluralCategory::Two => "two",
+        PluralCategory::Few => "few",
+        PluralCategory::Many => "many",
+        PluralCategory::Other => "other",
+    }
+}
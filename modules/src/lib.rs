@@ -14,6 +14,9 @@ pub mod crypto;
 #[cfg(feature = "event")]
 pub mod event;
 
+#[cfg(feature = "event")]
+pub mod process;
+
 #[cfg(feature = "console")]
 pub mod console;
 
@@ -41,11 +44,40 @@ pub mod url;
 #[cfg(feature = "intl")]
 pub mod intl;
 
+#[cfg(feature = "inspector")]
+pub mod inspector;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "decimal")]
+pub mod decimal;
+
+#[cfg(feature = "shell")]
+pub mod shell;
+
+#[cfg(feature = "temporal")]
+pub mod temporal;
+
+#[cfg(feature = "test-runner")]
+pub mod test_runner;
+
 pub mod async_hooks;
+pub mod diagnostics_channel;
+pub mod event_loop;
 pub mod hooking;
 pub mod module;
 pub mod navigator;
 pub mod serdeserclone;
+pub mod shadow_realm;
+pub mod source_map;
+pub mod structured_clone;
 pub mod text;
 pub mod timers;
 pub mod utils;
@@ -60,11 +92,15 @@ pub fn init(
     permissions::init(ctx.clone(), vsys)?;
     exceptions::init(ctx)?;
     async_hooks::init(ctx)?;
+    diagnostics_channel::init(ctx)?;
     text::init(ctx)?;
     serdeserclone::init(ctx)?;
+    structured_clone::init(ctx)?;
+    shadow_realm::init(ctx)?;
     module::module::init(ctx)?;
     buffer::init(ctx)?;
     timers::init(ctx)?;
+    event_loop::init(ctx)?;
 
     #[cfg(feature = "crypto")]
     {
@@ -77,6 +113,7 @@ pub fn init(
     #[cfg(feature = "event")]
     {
         event::init(ctx)?;
+        process::init(ctx)?;
     }
     #[cfg(feature = "abort")]
     {
@@ -98,5 +135,13 @@ pub fn init(
     {
         intl::init(ctx)?;
     }
+    #[cfg(feature = "temporal")]
+    {
+        temporal::init(ctx)?;
+    }
+    #[cfg(feature = "wasm")]
+    {
+        wasm::init(ctx)?;
+    }
     Ok(())
 }
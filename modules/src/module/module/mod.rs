@@ -5,6 +5,7 @@ use std::{
     rc::Rc,
 };
 
+use crate::path::dirname;
 use crate::utils::{
     ctx::CtxExt,
     module::{export_default, ModuleInfo},
@@ -118,7 +119,7 @@ impl ModuleDef for ModuleModule {
                 .map_or_else(HashSet::new, |v| v.get_list());
 
             default.set("builtinModules", module_list)?;
-            default.set("createRequire", Func::from(require::require))?;
+            default.set("createRequire", Func::from(require::create_require))?;
             default.set("isBuiltin", Func::from(is_builtin))?;
             default.set("registerHooks", Func::from(register_hooks))?;
 
@@ -177,7 +178,27 @@ pub fn init(ctx: &Ctx) -> Result<()> {
     .enumerable();
 
     globals.prop("exports", exports_accessor)?;
-    globals.set("require", Func::from(require::require))?;
+    globals.set("require", require::require_function(ctx, None)?)?;
+    globals.prop(
+        "__filename",
+        Accessor::new_get(|ctx| {
+            struct Args<'js>(Ctx<'js>);
+            let Args(ctx) = Args(ctx);
+            let name = ctx.get_script_or_module_name()?;
+            Ok::<_, Error>(name.trim_start_matches(CJS_IMPORT_PREFIX).to_string())
+        })
+        .configurable(),
+    )?;
+    globals.prop(
+        "__dirname",
+        Accessor::new_get(|ctx| {
+            struct Args<'js>(Ctx<'js>);
+            let Args(ctx) = Args(ctx);
+            let name = ctx.get_script_or_module_name()?;
+            Ok::<_, Error>(dirname(name.trim_start_matches(CJS_IMPORT_PREFIX)))
+        })
+        .configurable(),
+    )?;
 
     let module = Object::new(ctx.clone())?;
     module.prop("exports", exports_accessor)?;
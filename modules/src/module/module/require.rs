@@ -3,23 +3,100 @@ use std::{cell::RefCell, collections::HashSet, fs, rc::Rc};
 use crate::hooking::{invoke_async_hook, register_finalization_registry, HookType};
 use crate::utils::json::parse::json_parse;
 use crate::utils::{ctx::CtxExt, io::BYTECODE_FILE_EXT, provider::ProviderType};
-use rsquickjs::{atom::PredefinedAtom, qjs, Ctx, Filter, Function, Module, Object, Result, Value};
-use tokio::time::Instant;
+use rsquickjs::{
+    atom::PredefinedAtom, prelude::Func, qjs, Coerced, Ctx, Exception, Filter, Function, Module,
+    Object, Result, Value,
+};
 use tracing::{debug, info};
+use url::Url;
 
 use crate::module::package::resolver::require_resolve;
 use crate::module::CJS_IMPORT_PREFIX;
-use crate::{path::resolve_path, timers::poll_timers};
+use crate::{
+    path::resolve_path,
+    timers::{clock, poll_timers},
+};
 
 use super::{ModuleNames, RequireState};
 
+/// The global `require`, resolving relative to the calling module.
 pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
+    let base = ctx.get_script_or_module_name()?;
+    require_from(ctx, specifier, &base)
+}
+
+/// `require.resolve`, resolving relative to the calling module.
+pub fn resolve(ctx: Ctx<'_>, specifier: String) -> Result<String> {
+    let base = ctx.get_script_or_module_name()?;
+    resolve_from(&ctx, &specifier, &base)
+}
+
+/// Creates a `require` function, with its `resolve`, that resolves relative to `base`,
+/// or to the calling module when there is none.
+pub fn require_function<'js>(ctx: &Ctx<'js>, base: Option<String>) -> Result<Function<'js>> {
+    let Some(base) = base else {
+        let require = Function::new(ctx.clone(), Func::from(require))?.with_name("require")?;
+        require.set("resolve", Func::from(resolve))?;
+        return Ok(require);
+    };
+
+    let base: Rc<str> = base.into();
+    let resolve_base = base.clone();
+    let require = Function::new(ctx.clone(), move |ctx: Ctx<'js>, specifier: String| {
+        require_from(ctx, specifier, &base)
+    })?
+    .with_name("require")?;
+    let resolve = Function::new(ctx.clone(), move |ctx: Ctx<'js>, specifier: String| {
+        resolve_from(&ctx, &specifier, &resolve_base)
+    })?
+    .with_name("resolve")?;
+    require.set("resolve", resolve)?;
+    Ok(require)
+}
+
+/// `module.createRequire(filename)`, where `filename` is a path or a `file:` URL.
+pub fn create_require<'js>(ctx: Ctx<'js>, filename: Coerced<String>) -> Result<Function<'js>> {
+    let filename = filename.0;
+    let path = match Url::parse(&filename) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(|_| Exception::throw_type(&ctx, "The URL must be a file URL path"))?,
+        _ if crate::path::is_absolute(&filename) => filename,
+        _ => {
+            return Err(Exception::throw_type(
+                &ctx,
+                &[
+                    "The argument 'filename' must be a file URL object, file URL string, or absolute path string. Received '",
+                    &filename,
+                    "'",
+                ]
+                .concat(),
+            ))
+        },
+    };
+    require_function(&ctx, Some(path))
+}
+
+fn is_builtin_specifier(ctx: &Ctx<'_>, specifier: &str) -> bool {
+    let name = specifier.trim_start_matches("node:").trim_end_matches("/");
+    ctx.userdata::<ModuleNames>()
+        .is_some_and(|names| names.get_list().contains(name))
+}
+
+fn resolve_from(ctx: &Ctx<'_>, specifier: &str, base: &str) -> Result<String> {
+    if is_builtin_specifier(ctx, specifier) {
+        return Ok(specifier.to_string());
+    }
+    let base = base.trim_start_matches(CJS_IMPORT_PREFIX);
+    let abs_path = resolve_path([base].iter())?;
+    Ok(require_resolve(ctx, specifier, &abs_path, false)?.into_owned())
+}
+
+fn require_from<'js>(ctx: Ctx<'js>, specifier: String, base: &str) -> Result<Value<'js>> {
     let globals = ctx.globals();
     // let hooked_fn: Option<Function> = globals.get("__require_hook").ok();
 
-    struct Args<'js>(Ctx<'js>);
-    let Args(ctx) = Args(ctx);
-
     let module_list = ctx
         .userdata::<ModuleNames>()
         .map_or_else(HashSet::new, |v| v.get_list());
@@ -48,8 +125,7 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
             import_name = specifier.into();
             import_name.clone()
         } else {
-            let module_name = ctx.get_script_or_module_name()?;
-            let module_name = module_name.trim_start_matches(CJS_IMPORT_PREFIX);
+            let module_name = base.trim_start_matches(CJS_IMPORT_PREFIX);
             let abs_path = resolve_path([module_name].iter())?;
 
             let resolved_path = require_resolve(&ctx, &specifier, &abs_path, false)?.into_owned();
@@ -96,7 +172,8 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
 
     let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
 
-    let mut deadline = Instant::now();
+    let clock = clock(&ctx);
+    let mut deadline = (clock.monotonic_now)();
 
     let mut executing_timers = Vec::new();
 
@@ -110,8 +187,8 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
             break x?;
         }
 
-        if deadline < Instant::now() {
-            poll_timers(rt, &mut executing_timers, None, Some(&mut deadline))?;
+        if deadline <= (clock.monotonic_now)() {
+            poll_timers(rt, &mut executing_timers, Some(&mut deadline))?;
         }
 
         ctx.execute_pending_job();
@@ -146,28 +223,42 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
     let binding = ctx.userdata::<RefCell<RequireState>>().unwrap();
     let mut state = binding.borrow_mut();
 
+    // `export { value as "module.exports" }` overrides what `require` returns
+    if let Some(value) = imported_object.get::<_, Option<Value>>("module.exports")? {
+        state.cache.insert(import_name, value.clone());
+        return Ok(value);
+    }
+
     let props = imported_object.props::<String, Value>();
 
-    let default_export: Option<Value> = imported_object.get(PredefinedAtom::Default)?;
-    if let Some(default_export) = default_export {
-        //if default export is object attach all named exports to
-        if let Some(default_object) = default_export.as_object() {
-            for prop in props {
-                let (key, value) = prop?;
-                if !default_object.contains_key(&key)? {
-                    default_object.set(key, value)?;
+    if module_list.contains(import_name.as_ref()) {
+        let default_export: Option<Value> = imported_object.get(PredefinedAtom::Default)?;
+        if let Some(default_export) = default_export {
+            //if default export is object attach all named exports to
+            if let Some(default_object) = default_export.as_object() {
+                for prop in props {
+                    let (key, value) = prop?;
+                    if !default_object.contains_key(&key)? {
+                        default_object.set(key, value)?;
+                    }
                 }
+                let default_object = default_object.clone().into_value();
+                state.cache.insert(import_name, default_object.clone());
+                return Ok(default_object);
             }
-            let default_object = default_object.clone().into_value();
-            state.cache.insert(import_name, default_object.clone());
-            return Ok(default_object);
         }
     }
 
+    // ES modules are returned as their namespace, flagged so that transpiled
+    // callers pick `default` the same way they would for a CommonJS module
+    let has_default = imported_object.contains_key(PredefinedAtom::Default)?;
     for prop in props {
         let (key, value) = prop?;
         obj.set(key, value)?;
     }
+    if has_default && !obj.contains_key("__esModule")? {
+        obj.set("__esModule", true)?;
+    }
 
     let value = obj.into_value();
 
@@ -45,7 +45,9 @@ impl Default for ModuleBuilder {
 
         builder = builder.with_module(crate::module::module::ModuleModule);
         builder = builder.with_module(crate::async_hooks::AsyncHooksModule);
+        builder = builder.with_module(crate::diagnostics_channel::DiagnosticsChannelModule);
         builder = builder.with_module(crate::timers::TimersModule);
+        builder = builder.with_module(crate::timers::TimersPromisesModule);
         builder = builder.with_module(crate::buffer::BufferModule);
         builder = builder.with_module(crate::text::TextModule);
 
@@ -75,6 +77,28 @@ impl Default for ModuleBuilder {
         {
             builder = builder.with_module(crate::http::HttpsModule);
         }
+        #[cfg(feature = "ffi")]
+        {
+            builder = builder.with_module(crate::ffi::FfiModule);
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            builder = builder
+                .with_module(crate::sqlite::SqliteModule)
+                .with_module(crate::sqlite::XmasSqliteModule);
+        }
+        #[cfg(feature = "decimal")]
+        {
+            builder = builder.with_module(crate::decimal::DecimalModule);
+        }
+        #[cfg(feature = "shell")]
+        {
+            builder = builder.with_module(crate::shell::ShellModule);
+        }
+        #[cfg(feature = "test-runner")]
+        {
+            builder = builder.with_module(crate::test_runner::TestModule);
+        }
         #[cfg(feature = "fs")]
         {
             builder = builder
@@ -0,0 +1,110 @@
+//! On-disk cache of compiled modules.
+//!
+//! Modules are kept as QuickJS bytecode keyed by a hash of their name, their
+//! source and the build, so an unchanged module is loaded again without parsing
+//! it. A changed source hashes to a new entry and never reads stale bytecode.
+//! Entries are signed by the build that wrote them, since QuickJS can't safely
+//! read bytecode it didn't write itself.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use rsquickjs::{Ctx, Module, Result, WriteOptions};
+use tracing::info;
+
+use crate::utils::disk_cache::{self, read_signed_entry, write_signed_entry};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Caches the bytecode of file modules in [`cache_dir`].
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether compiled code is cached on disk, see [`set_enabled`].
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// `~/.xmas/bytecode`, where compiled modules are kept.
+pub fn cache_dir() -> PathBuf {
+    disk_cache::dir("bytecode")
+}
+
+/// Declares the module `name` from `source`, through the cache when it is enabled.
+pub fn declare<'js>(ctx: Ctx<'js>, name: &str, source: &[u8]) -> Result<Module<'js>> {
+    if !is_enabled() {
+        return Module::declare(ctx, name, source);
+    }
+    declare_cached(ctx, &cache_dir(), name, source)
+}
+
+fn declare_cached<'js>(
+    ctx: Ctx<'js>,
+    dir: &Path,
+    name: &str,
+    source: &[u8],
+) -> Result<Module<'js>> {
+    let path = dir.join(key(name, source));
+    if let Some(bytes) = read_signed_entry(&path) {
+        // SAFETY: the signature under the key of this build proves the entry was written
+        // below by `Module::write` of this very binary and wasn't altered since. Someone
+        // able to forge it could as well replace the binary.
+        if let Ok(module) = unsafe { ctx.read_module(&bytes) } {
+            return Ok(module);
+        }
+    }
+
+    let module = Module::declare(ctx, name, source)?;
+    // a module that can't be cached still runs, it is just parsed every time
+    if let Err(err) = module
+        .write(WriteOptions::default())
+        .map_err(io::Error::other)
+        .and_then(|bytes| write_signed_entry(&path, &bytes))
+    {
+        info!("Failed to cache bytecode of {}: {}", name, err);
+    }
+    Ok(module)
+}
+
+fn key(name: &str, source: &[u8]) -> String {
+    disk_cache::key(&[name.as_bytes(), source])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::utils::test::test_sync_with;
+
+    #[tokio::test]
+    async fn test_bytecode_cache() {
+        test_sync_with(|ctx| {
+            let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+            let source = b"export const a = 1 + 1;";
+
+            declare_cached(ctx.clone(), &dir, "a.js", source)?;
+            let path = dir.join(key("a.js", source));
+            let cached = fs::read(&path).unwrap();
+            assert!(!cached.is_empty());
+
+            // a hit is declared from the cached bytecode
+            let (module, promise) = declare_cached(ctx.clone(), &dir, "a.js", source)?.eval()?;
+            promise.finish::<()>()?;
+            assert_eq!(module.get::<_, i32>("a")?, 2);
+            assert_eq!(fs::read(&path).unwrap(), cached);
+
+            // another source is another entry
+            declare_cached(ctx.clone(), &dir, "a.js", b"export const a = 3;")?;
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+            fs::remove_dir_all(&dir).unwrap();
+            Ok(())
+        })
+        .await;
+    }
+}
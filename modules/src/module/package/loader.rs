@@ -1,10 +1,15 @@
-use std::{fs::File, io::Read};
-
 use rsquickjs::{loader::Loader, Ctx, Function, Module, Object, Result, Value};
 use tracing::info;
 
+#[cfg(feature = "http")]
+use super::remote;
+#[cfg(feature = "source")]
+use super::transpile;
+use super::{bytecode, meta, url_module};
 use crate::module::{CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX};
-use crate::permissions::get_vsys;
+use crate::permissions::{audit, get_vsys};
+#[cfg(feature = "source")]
+use crate::source_map::register_source_map;
 
 #[derive(Debug, Default)]
 pub struct PackageLoader;
@@ -16,31 +21,39 @@ impl PackageLoader {
         let export_object: Value = require.call((&cjs_specifier,))?;
         let mut module = String::with_capacity(name.len() + 512);
         module.push_str("const value = require(\"");
-
-        module.push_str(name);
-        module.push_str("\");export default value.default||value;");
+        for c in name.chars() {
+            if matches!(c, '"' | '\\') {
+                module.push('\\');
+            }
+            module.push(c);
+        }
+        // Transpiled ES modules flag themselves with `__esModule`, anything else
+        // is imported as a whole
+        module.push_str(
+            "\");export default value&&value.__esModule&&\"default\" in value?value.default:value;",
+        );
         if let Some(obj) = export_object.as_object() {
             let keys: Result<Vec<String>> = obj.keys().collect();
-            let keys = keys?;
+            let keys: Vec<String> = keys?
+                .into_iter()
+                .filter(|key| key != "default" && key != "__esModule" && is_identifier_name(key))
+                .collect();
 
             if !keys.is_empty() {
-                module.push_str("const{");
-
-                for p in keys.iter() {
-                    if p == "default" {
-                        continue;
-                    }
-                    module.push_str(p);
-                    module.push(',');
+                // Bind through generated locals so reserved words can still be exported
+                for (i, key) in keys.iter().enumerate() {
+                    module.push_str("const __cjs");
+                    module.push_str(&i.to_string());
+                    module.push_str("=value.");
+                    module.push_str(key);
+                    module.push(';');
                 }
-                module.truncate(module.len() - 1);
-                module.push_str("}=value;");
                 module.push_str("export{");
-                for p in keys.iter() {
-                    if p == "default" {
-                        continue;
-                    }
-                    module.push_str(p);
+                for (i, key) in keys.iter().enumerate() {
+                    module.push_str("__cjs");
+                    module.push_str(&i.to_string());
+                    module.push_str(" as ");
+                    module.push_str(key);
                     module.push(',');
                 }
                 module.truncate(module.len() - 1);
@@ -50,6 +63,18 @@ impl PackageLoader {
         Module::declare(ctx, name, module)
     }
 
+    /// Source of a module named by a URL rather than a path.
+    fn url_source(ctx: &Ctx<'_>, name: &str) -> Result<Option<Vec<u8>>> {
+        if url_module::is_url_module(name) {
+            return url_module::source(ctx, name).map(Some);
+        }
+        #[cfg(feature = "http")]
+        if remote::is_remote(name) {
+            return remote::source(ctx, name).map(Some);
+        }
+        Ok(None)
+    }
+
     fn normalize_name(name: &str) -> (bool, bool, &str, &str) {
         if !name.starts_with("__") {
             // If name doesn’t start with "__", return defaults
@@ -70,6 +95,20 @@ impl PackageLoader {
         (false, false, name, name)
     }
 
+    fn load_source<'js>(ctx: &Ctx<'js>, name: &str) -> Result<Module<'js>> {
+        if let Some(source) = Self::url_source(ctx, name)? {
+            let module = Module::declare(ctx.clone(), name, source)?;
+            module.meta::<Object>()?.set("url", name)?;
+            return Ok(module);
+        }
+        let (module, path) = Self::load_module(name, ctx)?;
+        if let Some(path) = path {
+            meta::init(ctx, &module, &path)?;
+        }
+
+        Ok(module)
+    }
+
     fn load_module<'js>(name: &str, ctx: &Ctx<'js>) -> Result<(Module<'js>, Option<String>)> {
         let ctx = ctx.clone();
 
@@ -80,19 +119,21 @@ impl PackageLoader {
         //json files can never be from CJS imports as they are handled by require
         if !from_cjs_import {
             if normalized_name.ends_with(".json") {
-                let mut file = File::open(path)?;
-                let prefix = "export default JSON.parse(`";
-                let suffix = "`);";
-                let mut json = String::with_capacity(prefix.len() + suffix.len());
-                json.push_str(prefix);
-                file.read_to_string(&mut json)?;
-                json.push_str(suffix);
+                // Parsed natively by require, which also shares the value with CJS
+                let mut json = String::with_capacity(path.len() + 32);
+                json.push_str("export default require(\"");
+                for c in path.chars() {
+                    if matches!(c, '"' | '\\') {
+                        json.push('\\');
+                    }
+                    json.push(c);
+                }
+                json.push_str("\");");
 
                 return Ok((Module::declare(ctx, path, json)?, None));
             }
             if is_cjs || normalized_name.ends_with(".cjs") {
-                let url = ["file://", path].concat();
-                return Ok((Self::load_cjs_module(path, ctx)?, Some(url)));
+                return Ok((Self::load_cjs_module(path, ctx)?, Some(path.into())));
             }
         }
 
@@ -106,33 +147,45 @@ impl PackageLoader {
                 format!("Failed to read file: {}", e),
             )
         })?;
-        let bytes = (vsys.fs.read)(std::path::Path::new(path)).map_err(|e| {
-            rsquickjs::Error::new_from_js_message(
-                "VfsError",
-                "Vec<u8>",
-                format!("Failed to read file: {}", e),
-            )
-        })?;
         let mut bytes: &[u8] = &bytes;
 
         if !from_cjs_import && bytes.starts_with(b"#!") {
             bytes = bytes.splitn(2, |&c| c == b'\n').nth(1).unwrap_or(bytes);
         }
 
-        let url = ["file://", path].concat();
-        Ok((Module::declare(ctx, normalized_name, bytes)?, Some(url)))
+        #[cfg(feature = "source")]
+        if !from_cjs_import && transpile::is_transpiled(path) {
+            let output = transpile::transpile(&ctx, path, bytes)?;
+            if let Some(map) = output.map {
+                register_source_map(&ctx, normalized_name, &map)?;
+            }
+            return Ok((
+                bytecode::declare(ctx, normalized_name, output.code.as_bytes())?,
+                Some(path.into()),
+            ));
+        }
+
+        Ok((
+            bytecode::declare(ctx, normalized_name, bytes)?,
+            Some(path.into()),
+        ))
     }
 }
 
 impl Loader for PackageLoader {
     fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> Result<Module<'js>> {
         info!("Try load '{}'", name);
-        let (module, url) = Self::load_module(name, ctx)?;
-        if let Some(url) = url {
-            let meta: Object = module.meta()?;
-            meta.prop("url", url)?;
-        }
-
-        Ok(module)
+        let module = Self::load_source(ctx, name);
+        audit(ctx, "module.load", name, module.is_ok());
+        module
     }
 }
+
+/// Whether `name` can be written as `value.name` and `export { x as name }`.
+fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
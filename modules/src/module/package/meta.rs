@@ -0,0 +1,169 @@
+//! `import.meta` of modules loaded from the filesystem.
+
+use rsquickjs::{object::Accessor, Ctx, Error, Function, Module, Object, Result, Value};
+use url::Url;
+
+use crate::{
+    permissions::get_vsys,
+    utils::{
+        primordials::{BasePrimordials, Primordial},
+        result::ResultExt,
+    },
+};
+
+/// Converts an absolute path to a `file:` URL, percent-encoding it as needed.
+pub fn file_url(path: &str) -> String {
+    match Url::from_file_path(path) {
+        Ok(url) => url.into(),
+        Err(_) => ["file://", path].concat(),
+    }
+}
+
+/// Populates `import.meta.url`, `import.meta.resolve()`, `import.meta.env` and
+/// `import.meta.args`, the arguments passed to the script, for `path`.
+pub fn init<'js>(ctx: &Ctx<'js>, module: &Module<'js>, path: &str) -> Result<()> {
+    let meta: Object = module.meta()?;
+    meta.set("url", file_url(path))?;
+
+    let referrer = path.to_string();
+    let resolve = Function::new(ctx.clone(), move |ctx: Ctx<'js>, specifier: String| {
+        resolve(&ctx, &referrer, &specifier)
+    })?
+    .with_name("resolve")?;
+    meta.set("resolve", resolve)?;
+
+    meta.prop(
+        "env",
+        Accessor::new_get(|ctx: Ctx<'js>| env(&ctx)).enumerable(),
+    )?;
+    meta.set("args", crate::process::script_args())?;
+    Ok(())
+}
+
+/// Resolves `specifier` relative to `referrer` through the vsys module loader.
+fn resolve(ctx: &Ctx<'_>, referrer: &str, specifier: &str) -> Result<String> {
+    // `node:` and other non-file URLs are already fully resolved
+    if let Ok(url) = Url::parse(specifier) {
+        if url.scheme() != "file" {
+            return Ok(specifier.into());
+        }
+    }
+
+    let vsys = get_vsys(ctx).or_throw_msg(ctx, "Vsys not initialized in context")?;
+    let resolved = (vsys.module_loader().resolve)(vsys.fs(), specifier, referrer, true)
+        .map_err(|err| throw_not_found(ctx, &err.to_string()))?;
+    if resolved.is_builtin {
+        return Ok(["node:", &resolved.path].concat());
+    }
+    Ok(file_url(&resolved.path))
+}
+
+fn throw_not_found(ctx: &Ctx<'_>, message: &str) -> Error {
+    let create = || -> Result<Value<'_>> {
+        let primordials = BasePrimordials::get(ctx)?;
+        let error: Object = primordials.constructor_error.construct((message,))?;
+        error.set("code", "ERR_MODULE_NOT_FOUND")?;
+        Ok(error.into_value())
+    };
+    match create() {
+        Ok(error) => ctx.throw(error),
+        Err(err) => err,
+    }
+}
+
+/// Environment variables the permissions allow the script to read.
+fn env<'js>(ctx: &Ctx<'js>) -> Result<Object<'js>> {
+    let env = Object::new(ctx.clone())?;
+    let Some(vsys) = get_vsys(ctx) else {
+        return Ok(env);
+    };
+    for (key, value) in std::env::vars_os() {
+        let (Some(key), Some(value)) = (key.to_str(), value.to_str()) else {
+            continue;
+        };
+        if vsys.permissions().check_env(key) {
+            env.set(key, value)?;
+        }
+    }
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rsquickjs::Module;
+    use xmas_vsys::{BlackOrWhiteList, Permissions, Vsys};
+
+    use crate::utils::{
+        primordials::{BasePrimordials, Primordial},
+        test::{call_test, test_async_with},
+    };
+
+    #[tokio::test]
+    async fn test_import_meta() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        tokio::fs::create_dir_all(dir.join("lib")).await.unwrap();
+        tokio::fs::write(dir.join("lib/dep.js"), "export default 1;")
+            .await
+            .unwrap();
+        let main = dir.join("main with space.js");
+        let main = main.to_string_lossy().to_string();
+        std::env::set_var("XMAS_IMPORT_META_VISIBLE", "yes");
+        std::env::set_var("XMAS_IMPORT_META_HIDDEN", "no");
+
+        test_async_with(move |ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                let vsys = Vsys::builder()
+                    .permissions(Permissions {
+                        env: BlackOrWhiteList::whitelist(vec!["XMAS_IMPORT_META_VISIBLE".into()]),
+                        ..Permissions::allow_all()
+                    })
+                    .build();
+                crate::permissions::init(ctx.clone(), Arc::new(vsys)).unwrap();
+
+                let module = Module::declare(
+                    ctx.clone(),
+                    main.as_str(),
+                    r#"
+                        export async function test() {
+                            let error;
+                            try {
+                                import.meta.resolve('./missing.js');
+                            } catch (e) {
+                                error = e.code;
+                            }
+                            return [
+                                import.meta.url,
+                                import.meta.resolve('./lib/dep'),
+                                import.meta.resolve('fs'),
+                                import.meta.resolve('node:path'),
+                                error,
+                                Object.keys(import.meta.env).join(','),
+                            ].join('|');
+                        }
+                    "#,
+                )
+                .unwrap();
+                super::init(&ctx, &module, &main).unwrap();
+                let (module, promise) = module.eval().unwrap();
+                promise.into_future::<()>().await.unwrap();
+
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                let dir = super::file_url(&dir.to_string_lossy());
+                assert_eq!(
+                    result,
+                    [
+                        &dir,
+                        "/main%20with%20space.js|",
+                        &dir,
+                        "/lib/dep.js|node:fs|node:path|ERR_MODULE_NOT_FOUND|XMAS_IMPORT_META_VISIBLE",
+                    ]
+                    .concat()
+                );
+            })
+        })
+        .await;
+    }
+}
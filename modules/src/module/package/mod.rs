@@ -1,2 +1,9 @@
+pub mod bytecode;
 pub mod loader;
+pub mod meta;
+#[cfg(feature = "http")]
+pub mod remote;
 pub mod resolver;
+#[cfg(feature = "source")]
+pub mod transpile;
+pub mod url_module;
@@ -0,0 +1,251 @@
+//! Deno-style `https:` imports.
+//!
+//! Modules are downloaded once into a per-user [`RemoteCache`] and pinned to the
+//! hash of that first download, so a changed remote file fails loudly instead of
+//! silently running different code. `--reload` downloads and pins them again.
+
+use std::{
+    convert::Infallible,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{header::LOCATION, Request, Uri};
+use rsquickjs::{Ctx, Error, Exception, Result};
+use url::Url;
+pub use xmas_vsys::remote::{is_remote, resolve};
+use xmas_vsys::{Capability, RemoteCache, Vsys};
+
+use crate::{
+    http::{client::build_client, pool::PoolOptions},
+    permissions::{audit, get_vsys},
+    utils::result::ResultExt,
+};
+
+const MAX_REDIRECT_COUNT: u32 = 20;
+
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+/// Downloads remote modules again instead of using their cached copies.
+pub fn set_reload(reload: bool) {
+    RELOAD.store(reload, Ordering::Relaxed);
+}
+
+/// `~/.xmas/remote`, where downloaded modules are kept.
+pub fn cache_dir() -> PathBuf {
+    home::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".xmas")
+        .join("remote")
+}
+
+/// Rejects importing `url` from `base` when the scope of `base` has no net
+/// access to its host, so a scoped dependency cannot pull in remote code.
+pub fn check_import_scope(ctx: &Ctx<'_>, base: &str, url: &str) -> Result<()> {
+    let Some(vsys) = get_vsys(ctx) else {
+        return Ok(());
+    };
+    let parsed = Url::parse(url).or_throw(ctx)?;
+    let host = parsed.host_str().unwrap_or_default();
+    if vsys.check_module(base, &Capability::Net(host.into())) {
+        return Ok(());
+    }
+    audit(ctx, "net.import", url, false);
+    Err(Error::new_resolving_message(
+        base,
+        url,
+        [
+            "The scope of the importing module has no net access to \"",
+            host,
+            "\"",
+        ]
+        .concat(),
+    ))
+}
+
+/// Source of the remote module at `url`, read from the cache or downloaded into it.
+pub fn source(ctx: &Ctx<'_>, url: &str) -> Result<Vec<u8>> {
+    let vsys = get_vsys(ctx).unwrap_or_else(|| Arc::new(Vsys::sandboxed()));
+    let host = Url::parse(url).or_throw(ctx)?;
+    let host = host.host_str().unwrap_or_default();
+    let allowed = vsys.check_net(host);
+    audit(ctx, "net.import", url, allowed);
+    if !allowed {
+        return Err(Exception::throw_message(
+            ctx,
+            &["Requires net access to \"", host, "\" to import ", url].concat(),
+        ));
+    }
+
+    let reload = RELOAD.load(Ordering::Relaxed);
+    fetch_cached(&cache_dir(), url, reload, |url| download(url, vsys)).or_throw(ctx)
+}
+
+/// Downloads `url` into the cache, replacing and re-pinning a cached copy when `reload` is set.
+pub fn cache(url: &str, reload: bool) -> io::Result<()> {
+    if !is_remote(url) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not an https: URL: {}", url),
+        ));
+    }
+    fetch_cached(&cache_dir(), url, reload, |url| {
+        download(url, Arc::new(Vsys::default()))
+    })?;
+    Ok(())
+}
+
+/// Reads `url` from the cache in `dir`, downloading it when it is missing or
+/// `reload` is set. The content has to match the integrity pinned for `url`.
+fn fetch_cached(
+    dir: &Path,
+    url: &str,
+    reload: bool,
+    download: impl FnOnce(&str) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    RemoteCache::new(dir)
+        .fetch(url, reload, download)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::InvalidData => io::Error::new(
+                err.kind(),
+                format!(
+                    "{}. Run `xmas cache --reload {}` to accept the new content",
+                    err, url
+                ),
+            ),
+            _ => err,
+        })
+}
+
+/// Downloads `url` on a separate thread, as module loading cannot await.
+fn download(url: &str, vsys: Arc<Vsys>) -> io::Result<Vec<u8>> {
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(download_async(&url, &vsys))
+    })
+    .join()
+    .map_err(|_| io::Error::other("Module download thread panicked"))?
+}
+
+async fn download_async(url: &str, vsys: &Vsys) -> io::Result<Vec<u8>> {
+    let client =
+        build_client(None, vsys.net.clone(), &PoolOptions::default()).map_err(io::Error::other)?;
+    let mut uri: Uri = url.parse().map_err(io::Error::other)?;
+
+    for _ in 0..=MAX_REDIRECT_COUNT {
+        let host = uri.host().unwrap_or_default();
+        if !vsys.check_net(host) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Requires net access to \"{}\" to import {}", host, url),
+            ));
+        }
+
+        let body: BoxBody<Bytes, Infallible> = BoxBody::new(Full::default());
+        let req = Request::get(uri.clone())
+            .header("accept", "application/javascript, text/javascript, */*")
+            .body(body)
+            .map_err(io::Error::other)?;
+        let res = client.request(req).await.map_err(io::Error::other)?;
+
+        let status = res.status();
+        if status.is_redirection() {
+            if let Some(location) = res.headers().get(LOCATION) {
+                // `location` may be relative to the URL that issued the redirect
+                let location = location.to_str().map_err(io::Error::other)?;
+                let base = Url::parse(&uri.to_string()).map_err(io::Error::other)?;
+                let location = base.join(location).map_err(io::Error::other)?;
+                uri = location.as_str().parse().map_err(io::Error::other)?;
+                continue;
+            }
+        }
+        if !status.is_success() {
+            return Err(io::Error::other(format!(
+                "Failed to download {}: {}",
+                url, status
+            )));
+        }
+
+        let body = res.into_body().collect().await.map_err(io::Error::other)?;
+        return Ok(body.to_bytes().to_vec());
+    }
+
+    Err(io::Error::other(format!(
+        "Too many redirects downloading {}",
+        url
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs};
+
+    use ring::digest::{digest, SHA256};
+
+    use super::*;
+    use crate::utils::encoding::bytes_to_hex_string;
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://example.com/lib/mod.js";
+        assert_eq!(
+            resolve(base, "./dep.js").as_deref(),
+            Some("https://example.com/lib/dep.js")
+        );
+        assert_eq!(
+            resolve(base, "/root.js").as_deref(),
+            Some("https://example.com/root.js")
+        );
+        assert_eq!(
+            resolve("/home/main.js", "https://deno.land/x/a.ts").as_deref(),
+            Some("https://deno.land/x/a.ts")
+        );
+        assert_eq!(resolve("/home/main.js", "./dep.js"), None);
+        assert_eq!(resolve(base, "fs"), None);
+    }
+
+    #[test]
+    fn test_fetch_cached() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let url = "https://example.com/mod.js";
+        let downloads = Cell::new(0);
+        let fetch = |reload, content: &'static [u8]| {
+            fetch_cached(&dir, url, reload, |_| {
+                downloads.set(downloads.get() + 1);
+                Ok(content.to_vec())
+            })
+        };
+
+        assert_eq!(
+            fetch(false, b"export default 1;").unwrap(),
+            b"export default 1;"
+        );
+        assert_eq!(fetch(false, b"ignored").unwrap(), b"export default 1;");
+        assert_eq!(downloads.get(), 1);
+
+        // a tampered cache no longer matches the pinned integrity
+        let key = bytes_to_hex_string(digest(&SHA256, url.as_bytes()).as_ref());
+        fs::write(dir.join(&key), "export default 2;").unwrap();
+        let err = fetch(false, b"ignored").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // reloading accepts and pins the new content
+        assert_eq!(
+            fetch(true, b"export default 3;").unwrap(),
+            b"export default 3;"
+        );
+        assert_eq!(fetch(false, b"ignored").unwrap(), b"export default 3;");
+        assert_eq!(downloads.get(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
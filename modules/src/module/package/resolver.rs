@@ -4,7 +4,7 @@ use std::{
     collections::HashMap,
     path::{Path, PathBuf},
     rc::Rc,
-    sync::{LazyLock, Mutex},
+    sync::{LazyLock, Mutex, RwLock},
 };
 
 use crate::permissions::get_vsys;
@@ -19,6 +19,8 @@ use tracing::{debug, info};
 use crate::module::{CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX};
 use crate::path;
 
+use super::url_module;
+
 const NODE: &str = "node";
 
 fn rc_string_to_cow<'a>(rc: Rc<String>) -> Cow<'a, str> {
@@ -84,18 +86,51 @@ static FILESYSTEM_ROOT: LazyLock<Box<str>> = LazyLock::new(|| {
     }
 });
 
+/// Specifier prefixes replaced before resolving, with what replaces them.
+static ALIASES: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// Sets the aliases of every resolution. An alias matches a specifier equal to it or
+/// starting with it and a `/`, one ending in `/` any specifier starting with it.
+pub fn set_aliases(aliases: Vec<(String, String)>) {
+    *ALIASES.write().unwrap() = aliases;
+}
+
+pub fn aliases() -> Vec<(String, String)> {
+    ALIASES.read().unwrap().clone()
+}
+
+/// `name` with its longest matching alias replaced.
+fn apply_alias(aliases: &[(String, String)], name: &str) -> Option<String> {
+    aliases
+        .iter()
+        .filter(|(alias, _)| match name.strip_prefix(alias.as_str()) {
+            Some(rest) => rest.is_empty() || alias.ends_with('/') || rest.starts_with('/'),
+            None => false,
+        })
+        .max_by_key(|(alias, _)| alias.len())
+        .map(|(alias, target)| [target.as_str(), &name[alias.len()..]].concat())
+}
+
 #[derive(Debug, Default)]
 pub struct PackageResolver;
 
 #[allow(clippy::manual_strip)]
 impl Resolver for PackageResolver {
     fn resolve(&mut self, ctx: &Ctx, base: &str, name: &str) -> Result<String> {
-        if name.starts_with(CJS_IMPORT_PREFIX) {
+        if name.starts_with(CJS_IMPORT_PREFIX) || url_module::is_url_module(name) {
             return Ok(name.to_string());
         }
 
+        let aliased = apply_alias(&ALIASES.read().unwrap(), name);
+        let name = aliased.as_deref().unwrap_or(name);
         let base = base.trim_start_matches(CJS_IMPORT_PREFIX);
 
+        #[cfg(feature = "http")]
+        if let Some(url) = super::remote::resolve(base, name) {
+            super::remote::check_import_scope(ctx, base, &url)?;
+            return Ok(url);
+        }
+
         debug!("Try resolve '{}' from '{}'", name, base);
 
         require_resolve(ctx, name, base, true).map(|name| name.into_owned())
@@ -667,8 +702,8 @@ fn load_package_exports<'a>(
         return Ok(sub_module.into());
     }
 
-    let (module_path, resolve_path, is_cjs) = package_exports_resolve(&package_json, name, is_esm)?;
-    let module_path = resolve_path.unwrap_or_else(|| module_path.to_string());
+    let (module_path, is_cjs) = package_exports_resolve(&package_json, name, is_esm)
+        .ok_or_else(|| Error::new_resolving(dir.to_string(), x.to_string()))?;
     let module_path = to_abs_path(correct_extensions(
         ctx,
         [dir, "/", scope, "/", &module_path].concat(),
@@ -727,8 +762,7 @@ fn load_package_self(ctx: &Ctx<'_>, x: &str, dir: &str, is_esm: bool) -> Result<
     //    "." + X.slice("name".length), `package.json` "exports", ["node", "require"])
     //    <a href="esm.md#resolver-algorithm-specification">defined in the ESM resolver</a>.
     // 6. RESOLVE_ESM_MATCH(MATCH)
-    if let Ok((path, resolve_path, _)) = package_exports_resolve(&package_json, name, is_esm) {
-        let path = resolve_path.unwrap_or_else(|| path.to_string());
+    if let Some((path, _)) = package_exports_resolve(&package_json, name, is_esm) {
         info!("❄️  load_package_self(2.c): {}", path);
         let dir = package_json_path.trim_end_matches("package.json");
         let module_path = correct_extensions(ctx, [dir, &path].concat());
@@ -746,125 +780,122 @@ fn get_name_and_scope(x: &str, n: usize) -> (&str, &str, bool) {
     }
 }
 
-// Implementation equivalent to PACKAGE_EXPORTS_RESOLVE including RESOLVE_ESM_MATCH
+// Implementation equivalent to PACKAGE_EXPORTS_RESOLVE including RESOLVE_ESM_MATCH.
+// Returns the target and whether it is CommonJS, or `None` when "exports" is
+// defined but has no target for `modules_name` under the active conditions.
 fn package_exports_resolve<'a>(
     package_json: &'a BorrowedValue<'a>,
     modules_name: &str,
     is_esm: bool,
-) -> Result<(&'a str, Option<String>, bool)> {
-    let ident = if is_esm { "import" } else { "require" };
-
-    let modules_name = if modules_name != "." {
-        &["./", modules_name].concat()
-    } else {
-        modules_name
-    };
+) -> Option<(Cow<'a, str>, bool)> {
+    let conditions = [NODE, if is_esm { "import" } else { "require" }, "default"];
 
-    let wildcard = if let Some(pos) = modules_name.rmatch_indices('/').nth(1) {
-        let (name, scope, _) = get_name_and_scope(modules_name, pos.0);
-        (Some(name), Some([scope, "/*"].concat()))
+    let subpath: Cow<'_, str> = if modules_name != "." {
+        ["./", modules_name].concat().into()
     } else {
-        (None, None)
+        modules_name.into()
     };
 
     if let BorrowedValue::Object(map) = package_json {
-        let is_cjs =
-            !matches!(map.get("type"), Some(BorrowedValue::String(ref _type)) if _type == "module");
-
-        if let Some(BorrowedValue::Object(exports)) = map.get("exports") {
-            if let Some(BorrowedValue::Object(name)) = exports.get(modules_name) {
-                // Check for exports -> name -> platform(browser or node) -> [import | require]
-                if let Some(BorrowedValue::Object(platform)) = name.get(NODE) {
-                    if let Some(BorrowedValue::String(ident)) = platform.get(ident) {
-                        return Ok((ident.as_ref(), None, is_cjs));
-                    }
-                }
-                // Check for exports -> name -> [import | require] -> default
-                if let Some(BorrowedValue::Object(ident)) = name.get(ident) {
-                    if let Some(BorrowedValue::String(default)) = ident.get("default") {
-                        return Ok((default.as_ref(), None, is_cjs));
-                    }
-                }
-                // Check for exports -> name -> platform(browser or node)
-                if let Some(BorrowedValue::String(platform)) = name.get(NODE) {
-                    return Ok((platform.as_ref(), None, is_cjs));
-                }
-                // Check for exports -> name -> [import | require]
-                if let Some(BorrowedValue::String(ident)) = name.get(ident) {
-                    return Ok((ident.as_ref(), None, is_cjs));
-                }
-                // Check for exports -> name -> default
-                if let Some(BorrowedValue::String(default)) = name.get("default") {
-                    return Ok((default.as_ref(), None, is_cjs));
-                }
-            }
-            // Check for wildcard pattern
-            if let Some(scope) = wildcard.1 {
-                // Check for exports -> scope -> platform(browser or node) -> [import | require]
-                if let Some(BorrowedValue::Object(name)) = exports.get(scope.as_str()) {
-                    if let Some(BorrowedValue::Object(platform)) = name.get(NODE) {
-                        if let Some(BorrowedValue::String(ident)) = platform.get(ident) {
-                            let resolve_star = replace_star(ident, wildcard.0.unwrap());
-                            return Ok((ident.as_ref(), Some(resolve_star), is_cjs));
-                        }
-                    }
-                    // Check for exports -> scope -> [import | require] -> default
-                    if let Some(BorrowedValue::Object(ident)) = name.get(ident) {
-                        if let Some(BorrowedValue::String(default)) = ident.get("default") {
-                            let resolve_star = replace_star(default, wildcard.0.unwrap());
-                            return Ok((default.as_ref(), Some(resolve_star), is_cjs));
-                        }
-                    }
-                    // Check for exports -> scope -> platform(browser or node)
-                    if let Some(BorrowedValue::String(platform)) = name.get(NODE) {
-                        let resolve_star = replace_star(platform, wildcard.0.unwrap());
-                        return Ok((platform.as_ref(), Some(resolve_star), is_cjs));
-                    }
-                    // Check for exports -> scope -> [import | require]
-                    if let Some(BorrowedValue::String(ident)) = name.get(ident) {
-                        let resolve_star = replace_star(ident, wildcard.0.unwrap());
-                        return Ok((ident.as_ref(), Some(resolve_star), is_cjs));
-                    }
-                    //  Check for exports -> scope -> default
-                    if let Some(BorrowedValue::String(default)) = name.get("default") {
-                        let resolve_star = replace_star(default, wildcard.0.unwrap());
-                        return Ok((default.as_ref(), Some(resolve_star), is_cjs));
-                    }
-                }
-            }
-            // Check for exports -> [import | require] -> default
-            if let Some(BorrowedValue::Object(ident)) = exports.get(ident) {
-                if let Some(BorrowedValue::String(default)) = ident.get("default") {
-                    return Ok((default.as_ref(), None, is_cjs));
-                }
-            }
-            // Check for exports -> [import | require]
-            if let Some(BorrowedValue::String(ident)) = exports.get(ident) {
-                return Ok((ident.as_ref(), None, is_cjs));
-            }
-            // [CJS only] Check for exports -> default
-            if !is_esm {
-                if let Some(BorrowedValue::String(default)) = exports.get("default") {
-                    return Ok((default.as_ref(), None, is_cjs));
+        let is_module =
+            matches!(map.get("type"), Some(BorrowedValue::String(ref _type)) if _type == "module");
+
+        if let Some(exports) = map.get("exports") {
+            let target = match exports {
+                // Subpath exports: { ".": ..., "./feature/*": ... }
+                BorrowedValue::Object(entries)
+                    if entries.keys().any(|key| key.starts_with('.')) =>
+                {
+                    exports_subpath_resolve(entries, &subpath, &conditions)
                 }
-            }
+                // Sugar for the main entry point: a target or conditions object
+                _ if subpath == "." => exports_target_resolve(exports, None, &conditions),
+                _ => None,
+            };
+            return target.map(|target| {
+                let is_cjs = if target.ends_with(".mjs") {
+                    false
+                } else {
+                    target.ends_with(".cjs") || !is_module
+                };
+                (target, is_cjs)
+            });
         }
         // Check for platform(browser or node) field
         if let Some(BorrowedValue::String(platform)) = map.get(NODE) {
-            return Ok((platform.as_ref(), None, is_cjs));
+            return Some((platform.as_ref().into(), !is_module));
         }
         // [ESM only] Check for module field
         if is_esm {
             if let Some(BorrowedValue::String(module)) = map.get("module") {
-                return Ok((module.as_ref(), None, is_cjs));
+                return Some((module.as_ref().into(), !is_module));
             }
         }
         // Check for main field
         if let Some(BorrowedValue::String(main)) = map.get("main") {
-            return Ok((main.as_ref(), None, is_cjs));
+            return Some((main.as_ref().into(), !is_module));
+        }
+    }
+    Some(("./index.js".into(), true))
+}
+
+// Matches `subpath` against the keys of "exports", preferring an exact key and
+// then the "*" pattern with the longest prefix.
+fn exports_subpath_resolve<'a>(
+    exports: &'a simd_json::borrowed::Object<'a>,
+    subpath: &str,
+    conditions: &[&str],
+) -> Option<Cow<'a, str>> {
+    if let Some(target) = exports.get(subpath) {
+        return exports_target_resolve(target, None, conditions);
+    }
+
+    let mut best: Option<(&str, &str, &str)> = None;
+    for key in exports.keys() {
+        let key: &str = key;
+        let Some((prefix, suffix)) = key.split_once('*') else {
+            continue;
+        };
+        if subpath.len() < prefix.len() + suffix.len()
+            || !subpath.starts_with(prefix)
+            || !subpath.ends_with(suffix)
+        {
+            continue;
+        }
+        if best.is_some_and(|(best_key, best_prefix, _)| {
+            (best_prefix.len(), best_key.len()) >= (prefix.len(), key.len())
+        }) {
+            continue;
         }
+        let star = &subpath[prefix.len()..subpath.len() - suffix.len()];
+        best = Some((key, prefix, star));
+    }
+
+    let (key, _, star) = best?;
+    exports_target_resolve(exports.get(key)?, Some(star), conditions)
+}
+
+// Resolves a target of "exports": a path, an array of fallbacks, or a nested
+// conditions object whose first matching condition, in package order, wins.
+fn exports_target_resolve<'a>(
+    target: &'a BorrowedValue<'a>,
+    star: Option<&str>,
+    conditions: &[&str],
+) -> Option<Cow<'a, str>> {
+    match target {
+        BorrowedValue::String(path) => Some(match star {
+            Some(star) => replace_star(path, star).into(),
+            None => path.as_ref().into(),
+        }),
+        BorrowedValue::Array(targets) => targets
+            .iter()
+            .find_map(|target| exports_target_resolve(target, star, conditions)),
+        BorrowedValue::Object(map) => map
+            .iter()
+            .filter(|(condition, _)| conditions.contains(&condition.as_ref()))
+            .find_map(|(_, target)| exports_target_resolve(target, star, conditions)),
+        _ => None,
     }
-    Ok(("./index.js", None, true))
 }
 
 fn replace_star(scope: &str, name: &str) -> String {
@@ -978,3 +1009,75 @@ fn correct_extensions<'a>(ctx: &Ctx<'_>, x: String) -> Cow<'a, str> {
     }
     x.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_alias, package_exports_resolve};
+
+    #[test]
+    fn test_apply_alias() {
+        let aliases = [
+            ("@/".to_string(), "/app/src/".to_string()),
+            ("@/lib".to_string(), "/app/lib".to_string()),
+            ("react".to_string(), "preact/compat".to_string()),
+        ];
+        let alias = |name| apply_alias(&aliases, name);
+
+        assert_eq!(alias("@/a.ts"), Some("/app/src/a.ts".into()));
+        assert_eq!(alias("@/lib/b"), Some("/app/lib/b".into()));
+        assert_eq!(alias("@/library"), Some("/app/src/library".into()));
+        assert_eq!(alias("react"), Some("preact/compat".into()));
+        assert_eq!(
+            alias("react/jsx-runtime"),
+            Some("preact/compat/jsx-runtime".into())
+        );
+        assert_eq!(alias("react-dom"), None);
+        assert_eq!(alias("./a"), None);
+    }
+
+    #[test]
+    fn test_package_exports_resolve() {
+        let mut package_json = br#"{
+            "type": "module",
+            "main": "./legacy.js",
+            "exports": {
+                ".": {
+                    "types": "./index.d.ts",
+                    "node": { "import": "./index.mjs", "require": "./index.cjs" },
+                    "default": "./index.js"
+                },
+                "./feature/*": [{ "require": "./cjs/*.js" }, "./esm/*.js"],
+                "./feature/internal/*": null,
+                "./package.json": "./package.json"
+            }
+        }"#
+        .to_vec();
+        let package_json = simd_json::to_borrowed_value(&mut package_json).unwrap();
+        let resolve = |name, is_esm| {
+            package_exports_resolve(&package_json, name, is_esm)
+                .map(|(path, is_cjs)| (path.into_owned(), is_cjs))
+        };
+
+        assert_eq!(resolve(".", true), Some(("./index.mjs".into(), false)));
+        assert_eq!(resolve(".", false), Some(("./index.cjs".into(), true)));
+        assert_eq!(
+            resolve("feature/a/b", false),
+            Some(("./cjs/a/b.js".into(), false))
+        );
+        assert_eq!(
+            resolve("feature/a", true),
+            Some(("./esm/a.js".into(), false))
+        );
+        assert_eq!(resolve("feature/internal/x", true), None);
+        assert_eq!(resolve("missing", true), None);
+
+        let mut package_json =
+            br#"{ "exports": { "import": "./a.mjs", "default": "./a.js" } }"#.to_vec();
+        let package_json = simd_json::to_borrowed_value(&mut package_json).unwrap();
+        assert_eq!(
+            package_exports_resolve(&package_json, ".", false)
+                .map(|(path, is_cjs)| (path.into_owned(), is_cjs)),
+            Some(("./a.js".into(), true))
+        );
+    }
+}
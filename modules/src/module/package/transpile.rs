@@ -0,0 +1,30 @@
+//! TypeScript and JSX modules, transpiled to JavaScript as they are loaded.
+//!
+//! The output goes through the transform cache of [`script::transform_cached`], so only
+//! changed files are transpiled again.
+
+use rsquickjs::{Ctx, Error, Result};
+
+use crate::script::{self, TransformOutput};
+
+/// Extensions of the modules which are transpiled before they are declared.
+pub const TRANSPILED_EXTENSIONS: &[&str] = &[".ts", ".mts", ".tsx", ".jsx"];
+
+pub fn is_transpiled(path: &str) -> bool {
+    TRANSPILED_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+/// The JavaScript of the module at `path` and its source map. Syntax errors are thrown
+/// as a `SyntaxError` with their diagnostics.
+pub fn transpile(ctx: &Ctx<'_>, path: &str, source: &[u8]) -> Result<TransformOutput> {
+    let source = std::str::from_utf8(source)
+        .map_err(|_| Error::new_from_js_message("Vec<u8>", "String", "Module is not UTF-8"))?;
+    let source_type = if path.ends_with(".tsx") {
+        "tsx"
+    } else if path.ends_with(".jsx") {
+        "jsx"
+    } else {
+        "ts"
+    };
+    script::transform_cached(path, source_type, source, None, None).map_err(|err| err.throw(ctx))
+}
@@ -0,0 +1,143 @@
+//! Modules imported from `data:` and `blob:` URLs instead of the filesystem.
+
+use rsquickjs::{Ctx, Error, Exception, Result};
+
+use crate::{
+    buffer::resolve_object_url,
+    utils::{encoding::bytes_from_b64, result::ResultExt},
+};
+
+pub fn is_url_module(specifier: &str) -> bool {
+    specifier.starts_with("data:") || specifier.starts_with("blob:")
+}
+
+/// Reads the source of a `data:` or `blob:` URL module, exporting JSON as its default.
+pub fn source(ctx: &Ctx<'_>, url: &str) -> Result<Vec<u8>> {
+    let (mime_type, bytes) = if let Some(data_url) = url.strip_prefix("data:") {
+        parse_data_url(ctx, data_url)?
+    } else {
+        let blob = resolve_object_url(ctx, url).ok_or_else(|| {
+            Error::new_loading_message(url, "Blob URL was revoked or never created")
+        })?;
+        // untyped blobs are assumed to hold a script
+        let mime_type = match blob.mime_type() {
+            mime_type if mime_type.is_empty() => "text/javascript".into(),
+            mime_type => mime_type,
+        };
+        (mime_type, blob.get_bytes().or_throw(ctx)?)
+    };
+
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    match essence.to_ascii_lowercase().as_str() {
+        "text/javascript"
+        | "application/javascript"
+        | "application/x-javascript"
+        | "text/ecmascript"
+        | "application/ecmascript" => Ok(bytes),
+        "application/json" => Ok([b"export default ".as_slice(), &bytes, b";"].concat()),
+        _ => Err(Exception::throw_type(
+            ctx,
+            &["Unknown module format: ", essence, " for URL ", url].concat(),
+        )),
+    }
+}
+
+/// Splits `[<mediatype>][;base64],<data>` into its media type and decoded bytes.
+fn parse_data_url(ctx: &Ctx<'_>, data_url: &str) -> Result<(String, Vec<u8>)> {
+    let (mime_type, data) = data_url
+        .split_once(',')
+        .ok_or_else(|| Exception::throw_type(ctx, "Invalid data URL format"))?;
+
+    let (mime_type, is_base64) = match mime_type.strip_suffix(";base64") {
+        Some(mime_type) => (mime_type, true),
+        None => (mime_type, false),
+    };
+    let mime_type = if mime_type.trim().is_empty() {
+        "text/plain".into()
+    } else {
+        mime_type.to_string()
+    };
+
+    let data = percent_decode(data);
+    let bytes = if is_base64 {
+        bytes_from_b64(data).or_throw(ctx)?
+    } else {
+        data
+    };
+    Ok((mime_type, bytes))
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok());
+            if let Some(value) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(byte);
+        i += 1;
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rsquickjs::{loader::Loader, Object};
+
+    use crate::{
+        buffer::{create_object_url, revoke_object_url, Blob},
+        module::package::loader::PackageLoader,
+        utils::test::test_async_with,
+    };
+
+    #[tokio::test]
+    async fn test_url_modules() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::permissions::init(ctx.clone(), Arc::new(xmas_vsys::Vsys::new())).unwrap();
+
+                let blob = Blob::from_bytes(b"export default 'blob';".to_vec(), None);
+                let blob_url = create_object_url(&ctx, blob).unwrap();
+
+                let mut loader = PackageLoader;
+                for (url, expected) in [
+                    ("data:text/javascript,export default 1 + 2", "3"),
+                    (
+                        "data:text/javascript;base64,ZXhwb3J0IGRlZmF1bHQgJ2InOw==",
+                        "\"b\"",
+                    ),
+                    (
+                        "data:application/json,%7B%22a%22%3A%5B1%5D%7D",
+                        "{\"a\":[1]}",
+                    ),
+                    (blob_url.as_str(), "\"blob\""),
+                ] {
+                    let (module, promise) = loader.load(&ctx, url).unwrap().eval().unwrap();
+                    promise.into_future::<()>().await.unwrap();
+
+                    let meta: Object = module.meta().unwrap();
+                    assert_eq!(meta.get::<_, String>("url").unwrap(), url);
+                    let value = module.get::<_, rsquickjs::Value>("default").unwrap();
+                    let json = ctx.json_stringify(value).unwrap().unwrap();
+                    assert_eq!(json.to_string().unwrap(), expected);
+                }
+
+                assert!(loader.load(&ctx, "data:text/plain,hello").is_err());
+                revoke_object_url(&ctx, &blob_url);
+                assert!(loader.load(&ctx, &blob_url).is_err());
+            })
+        })
+        .await;
+    }
+}
@@ -1,15 +1,56 @@
-use rsquickjs::{Ctx, Object, Result};
+use rsquickjs::{atom::PredefinedAtom, Ctx, Object, Result};
 
 fn get_user_agent() -> &'static str {
     concat!("Xmas.JS ", env!("CARGO_PKG_VERSION"))
 }
 
+/// The platform string browsers report, like `Linux x86_64` or `MacIntel`
+fn get_platform() -> String {
+    match std::env::consts::OS {
+        "macos" => "MacIntel".to_string(),
+        "windows" => "Win32".to_string(),
+        os => {
+            let mut os = os.to_string();
+            if let Some(first) = os.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            [os.as_str(), " ", std::env::consts::ARCH].concat()
+        }
+    }
+}
+
+/// The user's language as a BCP 47 tag, read from the POSIX locale variables
+fn get_language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            // en_US.UTF-8@euro -> en-US
+            let tag = value.split(['.', '@']).next().unwrap_or_default();
+            if tag.is_empty() || tag == "C" || tag == "POSIX" {
+                return None;
+            }
+            Some(tag.replace('_', "-"))
+        })
+        .unwrap_or_else(|| "en-US".to_string())
+}
+
 pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     let globals = ctx.globals();
 
     let navigator = Object::new(ctx.clone())?;
 
+    let language = get_language();
+    let hardware_concurrency = std::thread::available_parallelism().map_or(1, |n| n.get());
+
     navigator.set("userAgent", get_user_agent())?;
+    navigator.set("platform", get_platform())?;
+    navigator.set("hardwareConcurrency", hardware_concurrency)?;
+    navigator.set("languages", vec![language.clone()])?;
+    navigator.set("language", language)?;
+    navigator.set("onLine", true)?;
+    navigator.set(PredefinedAtom::SymbolToStringTag, "Navigator")?;
 
     globals.set("navigator", navigator)?;
 
@@ -10,14 +10,10 @@ use rsquickjs::{
     prelude::{Func, Rest},
     Ctx, Object, Result,
 };
+use xmas_vsys::path::{ParsedPath, PathStyle};
 
 pub struct PathModule;
 
-#[cfg(windows)]
-const DELIMITER: char = ';';
-#[cfg(not(windows))]
-const DELIMITER: char = ':';
-
 #[cfg(windows)]
 pub const CURRENT_DIR_STR: &str = ".\\";
 
@@ -154,81 +150,6 @@ pub fn basename(path: String, suffix: Opt<String>) -> String {
     name
 }
 
-fn extname(path: String) -> String {
-    let (_, ext) = name_extname(&path);
-    ext.to_string()
-}
-
-fn format(obj: Object) -> String {
-    let dir: String = obj.get("dir").unwrap_or_default();
-    let root: String = obj.get("root").unwrap_or_default();
-    let base: String = obj.get("base").unwrap_or_default();
-    let name: String = obj.get("name").unwrap_or_default();
-    let ext: String = obj.get("ext").unwrap_or_default();
-
-    let mut path = String::new();
-    if !dir.is_empty() {
-        path.push_str(&dir);
-        if !ends_with_sep(&dir) {
-            path.push(MAIN_SEPARATOR);
-        }
-    } else if !root.is_empty() {
-        path.push_str(&root);
-        if !ends_with_sep(&root) {
-            path.push(MAIN_SEPARATOR);
-        }
-    }
-    if !base.is_empty() {
-        path.push_str(&base);
-    } else {
-        path.push_str(&name);
-        if !ext.is_empty() {
-            if !ext.starts_with('.') {
-                path.push('.');
-            }
-            path.push_str(&ext);
-        }
-    }
-    path
-}
-
-fn parse(ctx: Ctx, path_str: String) -> Result<Object> {
-    let obj = Object::new(ctx)?;
-    let path = Path::new(&path_str);
-    let parent = path
-        .parent()
-        .map(|p| p.to_str().unwrap())
-        .unwrap_or_default();
-    let filename = path
-        .file_name()
-        .map(|n| n.to_str().unwrap())
-        .unwrap_or_default();
-
-    let (name, extension) = name_extname(filename);
-
-    let root = path
-        .components()
-        .next()
-        .and_then(|c| match c {
-            Component::Prefix(prefix) => prefix.as_os_str().to_str(),
-            Component::RootDir => c.as_os_str().to_str(),
-            _ => Some(""),
-        })
-        .unwrap_or_default();
-
-    obj.set("root", root)?;
-    obj.set("dir", parent)?;
-    obj.set("base", [name, extension].concat())?;
-    obj.set("ext", extension)?;
-    obj.set("name", name)?;
-
-    Ok(obj)
-}
-
-fn join(parts: Rest<String>) -> String {
-    join_path(parts.0.iter())
-}
-
 pub fn join_path<S, I>(parts: I) -> String
 where
     S: AsRef<str>,
@@ -469,10 +390,6 @@ where
     result
 }
 
-pub fn resolve(path: Rest<String>) -> Result<String> {
-    resolve_path(path.iter())
-}
-
 fn get_path_prefix(cwd: &Path) -> (String, std::iter::Peekable<std::path::Components<'_>>) {
     let mut components = cwd.components().peekable();
 
@@ -514,6 +431,91 @@ pub fn is_absolute(path: &str) -> bool {
     path.starts_with(MAIN_SEPARATOR)
 }
 
+fn cwd() -> Result<String> {
+    Ok(std::env::current_dir()?.to_string_lossy().into_owned())
+}
+
+fn format(style: PathStyle, obj: Object<'_>) -> String {
+    style.format(&ParsedPath {
+        root: obj.get("root").unwrap_or_default(),
+        dir: obj.get("dir").unwrap_or_default(),
+        base: obj.get("base").unwrap_or_default(),
+        ext: obj.get("ext").unwrap_or_default(),
+        name: obj.get("name").unwrap_or_default(),
+    })
+}
+
+fn parse<'js>(ctx: Ctx<'js>, style: PathStyle, path: &str) -> Result<Object<'js>> {
+    let parsed = style.parse(path);
+    let obj = Object::new(ctx)?;
+    obj.set("root", parsed.root)?;
+    obj.set("dir", parsed.dir)?;
+    obj.set("base", parsed.base)?;
+    obj.set("ext", parsed.ext)?;
+    obj.set("name", parsed.name)?;
+    Ok(obj)
+}
+
+/// Sets the functions of `path` handling paths of `style` on `obj`, as the module
+/// itself, `path.posix` and `path.win32` have them
+fn define_namespace<'js>(obj: &Object<'js>, style: PathStyle) -> Result<()> {
+    obj.set(
+        "basename",
+        Func::from(move |path: String, suffix: Opt<String>| {
+            style.basename(&path, suffix.0.as_deref())
+        }),
+    )?;
+    obj.set(
+        "dirname",
+        Func::from(move |path: String| style.dirname(&path)),
+    )?;
+    obj.set(
+        "extname",
+        Func::from(move |path: String| style.extname(&path)),
+    )?;
+    obj.set(
+        "format",
+        Func::from(move |parsed: Object<'js>| format(style, parsed)),
+    )?;
+    obj.set(
+        "parse",
+        Func::from(move |ctx: Ctx<'js>, path: String| parse(ctx, style, &path)),
+    )?;
+    obj.set(
+        "join",
+        Func::from(move |parts: Rest<String>| style.join(&parts.0)),
+    )?;
+    obj.set(
+        "resolve",
+        Func::from(move |parts: Rest<String>| -> Result<String> {
+            Ok(style.resolve(&cwd()?, &parts.0))
+        }),
+    )?;
+    obj.set(
+        "relative",
+        Func::from(move |from: String, to: String| -> Result<String> {
+            Ok(style.relative(&cwd()?, &from, &to))
+        }),
+    )?;
+    obj.set(
+        "normalize",
+        Func::from(move |path: String| style.normalize(&path)),
+    )?;
+    obj.set(
+        "isAbsolute",
+        Func::from(move |path: String| style.is_absolute(&path)),
+    )?;
+    obj.set(
+        "toNamespacedPath",
+        Func::from(move |path: String| -> Result<String> {
+            Ok(style.to_namespaced_path(&cwd()?, &path))
+        }),
+    )?;
+    obj.prop("delimiter", style.delimiter().to_string())?;
+    obj.prop("sep", style.sep().to_string())?;
+    Ok(())
+}
+
 impl ModuleDef for PathModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare("basename")?;
@@ -528,6 +530,9 @@ impl ModuleDef for PathModule {
         declare.declare("isAbsolute")?;
         declare.declare("delimiter")?;
         declare.declare("sep")?;
+        declare.declare("toNamespacedPath")?;
+        declare.declare("posix")?;
+        declare.declare("win32")?;
 
         declare.declare("default")?;
         Ok(())
@@ -535,18 +540,15 @@ impl ModuleDef for PathModule {
 
     fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
         export_default(ctx, exports, |default| {
-            default.set("dirname", Func::from(dirname::<String>))?;
-            default.set("basename", Func::from(basename))?;
-            default.set("extname", Func::from(extname))?;
-            default.set("format", Func::from(format))?;
-            default.set("parse", Func::from(parse))?;
-            default.set("join", Func::from(join))?;
-            default.set("relative", Func::from(relative::<String, String>))?;
-            default.set("resolve", Func::from(resolve))?;
-            default.set("normalize", Func::from(normalize::<String>))?;
-            default.set("isAbsolute", Func::from(|s: String| is_absolute(&s)))?;
-            default.prop("delimiter", DELIMITER.to_string())?;
-            default.prop("sep", MAIN_SEPARATOR.to_string())?;
+            let posix = Object::new(ctx.clone())?;
+            define_namespace(&posix, PathStyle::Posix)?;
+            let win32 = Object::new(ctx.clone())?;
+            define_namespace(&win32, PathStyle::Win32)?;
+            define_namespace(default, PathStyle::NATIVE)?;
+            for namespace in [default, &posix, &win32] {
+                namespace.set("posix", posix.clone())?;
+                namespace.set("win32", win32.clone())?;
+            }
             Ok(())
         })
     }
@@ -632,11 +634,12 @@ mod tests {
 
     #[test]
     fn test_extname() {
-        assert_eq!(extname("/usr/local/bin.txt".to_string()), ".txt");
-        assert_eq!(extname("/usr/local/bin".to_string()), "");
-        assert_eq!(extname("file.tar.gz".to_string()), ".gz");
-        assert_eq!(extname(".bashrc".to_string()), "");
-        assert_eq!(extname("".to_string()), "");
+        let extname = |path| PathStyle::NATIVE.extname(path);
+        assert_eq!(extname("/usr/local/bin.txt"), ".txt");
+        assert_eq!(extname("/usr/local/bin"), "");
+        assert_eq!(extname("file.tar.gz"), ".gz");
+        assert_eq!(extname(".bashrc"), "");
+        assert_eq!(extname(""), "");
     }
 
     #[test]
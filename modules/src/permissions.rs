@@ -6,12 +6,14 @@ use std::path::Path;
 use std::sync::Arc;
 
 use rsquickjs::class::{Trace, Tracer};
+use rsquickjs::function::{FromParam, ParamRequirement, ParamsAccessor};
 use rsquickjs::JsLifetime;
 
 // Re-export vsys types
 pub use xmas_vsys::fs::FsVTable;
 pub use xmas_vsys::permissions::{BlackOrWhiteList, Permissions};
 pub use xmas_vsys::Vsys;
+use xmas_vsys::{AuditEvent, Capability, RandomVTable, StdioVTable};
 
 /// Wrapper to store Vsys in JS context with required trait implementations
 #[derive(Clone)]
@@ -44,18 +46,198 @@ pub fn get_vsys(ctx: &rsquickjs::Ctx<'_>) -> Option<Arc<Vsys>> {
     ctx.userdata::<VsysContext>().map(|v| v.0.clone())
 }
 
-/// Helper to check filesystem permission from context
+/// Helper to record an operation in the audit sink of the Vsys in context, if any
+///
+/// The caller is the module of the JS function that called into Rust.
+pub fn audit(ctx: &rsquickjs::Ctx<'_>, op: &str, resource: &str, allowed: bool) {
+    let Some(vsys) = get_vsys(ctx) else {
+        return;
+    };
+    if vsys.audit.is_none() {
+        return;
+    }
+    let caller = ctx
+        .script_or_module_name(1)
+        .and_then(|name| name.to_string().ok());
+    vsys.audit(AuditEvent {
+        op,
+        resource,
+        allowed,
+        caller: caller.as_deref(),
+    });
+}
+
+/// Stack levels searched for scoped modules
+///
+/// QuickJS checks its stack size, 1 MiB by default, on every call, which fits far
+/// fewer frames than this. A frame still found this deep means the callers can't be
+/// told, and is denied.
+const MAX_STACK_DEPTH: isize = 8192;
+
+/// The modules with JS code on the stack when a native function was called, and
+/// those that started the async operation it runs in
+///
+/// Modules with a scope in [`Permissions::modules`] may only use what their
+/// scope allows, including through code of other modules they call, such as
+/// callbacks handed to them, and through timers and promise callbacks they
+/// schedule, which the async context of [`crate::async_hooks`] carries them to.
+/// Async functions run after their caller returned, so they take this as a
+/// parameter, captured like `Ctx` when they are called.
+#[derive(Debug, Clone, Default)]
+pub struct Callers {
+    modules: Vec<String>,
+    /// No module was found, so no scope can be ruled out
+    unknown: bool,
+}
+
+impl Callers {
+    /// Capture the callers on the current stack and in the current async context,
+    /// which only matters when the permissions of the Vsys in context have module
+    /// scopes
+    pub fn capture(ctx: &rsquickjs::Ctx<'_>) -> Self {
+        if !has_scopes(ctx) {
+            return Self::default();
+        }
+        let mut callers = Self::capture_stack(ctx);
+        callers.extend(&crate::async_hooks::current_callers(ctx));
+        if callers.modules.is_empty() {
+            // called by the host or from a job nothing scheduled from JS
+            callers.unknown = true;
+        }
+        callers
+    }
+
+    /// Capture the modules on the current stack only
+    pub(crate) fn capture_stack(ctx: &rsquickjs::Ctx<'_>) -> Self {
+        let mut callers = Self::default();
+        if !has_scopes(ctx) {
+            return callers;
+        }
+        // native frames have no name, so keep looking past them up to the bottom
+        for level in 0..=MAX_STACK_DEPTH {
+            let Some(name) = ctx.script_or_module_name(level) else {
+                continue;
+            };
+            if level == MAX_STACK_DEPTH {
+                callers.unknown = true;
+                break;
+            }
+            if let Ok(name) = name.to_string() {
+                if callers.modules.last() != Some(&name) {
+                    callers.modules.push(name);
+                }
+            }
+        }
+        callers.modules.sort();
+        callers.modules.dedup();
+        callers
+    }
+
+    /// Add the modules of `other`, the narrowest scope of both applies
+    pub(crate) fn extend(&mut self, other: &Callers) {
+        self.unknown |= other.unknown;
+        for module in &other.modules {
+            if !self.modules.contains(module) {
+                self.modules.push(module.clone());
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.modules.is_empty() && !self.unknown
+    }
+
+    /// Check the scopes of all callers allow `capability`
+    pub fn allows(&self, vsys: &Vsys, capability: &Capability) -> bool {
+        !self.unknown
+            && self
+                .modules
+                .iter()
+                .all(|module| vsys.check_module(module, capability))
+    }
+
+    /// Check the scopes of all callers allow FFI
+    pub fn allows_ffi(&self, vsys: &Vsys) -> bool {
+        !self.unknown
+            && self.modules.iter().all(|module| {
+                vsys.permissions()
+                    .for_module(module)
+                    .is_none_or(|scope| scope.ffi)
+            })
+    }
+
+    /// Check the scopes of all callers allow running subprocesses
+    pub fn allows_run(&self, vsys: &Vsys) -> bool {
+        !self.unknown
+            && self.modules.iter().all(|module| {
+                vsys.permissions()
+                    .for_module(module)
+                    .is_none_or(|scope| scope.run)
+            })
+    }
+}
+
+fn has_scopes(ctx: &rsquickjs::Ctx<'_>) -> bool {
+    get_vsys(ctx).is_some_and(|v| !v.permissions().modules.is_empty())
+}
+
+impl<'js> FromParam<'js> for Callers {
+    fn param_requirement() -> ParamRequirement {
+        ParamRequirement::none()
+    }
+
+    fn from_param<'a>(params: &mut ParamsAccessor<'a, 'js>) -> rsquickjs::Result<Self> {
+        Ok(Self::capture(params.ctx()))
+    }
+}
+
+/// Check `capability` for the Vsys in context and the scopes of `callers`
+pub fn check_capability(
+    ctx: &rsquickjs::Ctx<'_>,
+    callers: &Callers,
+    capability: &Capability,
+) -> bool {
+    get_vsys(ctx).is_some_and(|v| callers.allows(&v, capability) && v.check(capability))
+}
+
+/// Helper to check filesystem read and write permission from context
 pub fn check_fs_permission(ctx: &rsquickjs::Ctx<'_>, path: &Path) -> bool {
-    get_vsys(ctx)
-        .map(|v| v.permissions().check_fs(path))
-        .unwrap_or(false)
+    let callers = Callers::capture(ctx);
+    let allowed = [
+        Capability::FsRead(path.to_path_buf()),
+        Capability::FsWrite(path.to_path_buf()),
+    ]
+    .iter()
+    .all(|capability| check_capability(ctx, &callers, capability));
+    audit(ctx, "fs", &path.to_string_lossy(), allowed);
+    allowed
+}
+
+/// Helper to check filesystem read permission from context
+pub fn check_fs_read_permission(ctx: &rsquickjs::Ctx<'_>, path: &Path) -> bool {
+    let capability = Capability::FsRead(path.to_path_buf());
+    let allowed = check_capability(ctx, &Callers::capture(ctx), &capability);
+    audit(ctx, "fs.read", &path.to_string_lossy(), allowed);
+    allowed
 }
 
 /// Helper to check network permission from context  
-pub fn check_net_permission(ctx: &rsquickjs::Ctx<'_>, host: &str) -> bool {
-    get_vsys(ctx)
-        .map(|v| v.permissions().check_net(host))
-        .unwrap_or(false)
+pub fn check_net_permission(ctx: &rsquickjs::Ctx<'_>, callers: &Callers, host: &str) -> bool {
+    let allowed = check_capability(ctx, callers, &Capability::Net(host.to_string()));
+    audit(ctx, "net", host, allowed);
+    allowed
+}
+
+/// Helper to check FFI permission from context
+pub fn check_ffi_permission(ctx: &rsquickjs::Ctx<'_>) -> bool {
+    get_vsys(ctx).is_some_and(|v| v.permissions().ffi && Callers::capture(ctx).allows_ffi(&v))
+}
+
+/// Helper to check subprocess permission from context
+pub fn check_run_permission(ctx: &rsquickjs::Ctx<'_>, callers: &Callers, command: &str) -> bool {
+    let allowed = get_vsys(ctx).is_some_and(|v| v.permissions().run && callers.allows_run(&v));
+    audit(ctx, "run", command, allowed);
+    allowed
 }
 
 /// Helper to get FsVTable from context
@@ -64,6 +246,20 @@ pub fn get_fs(ctx: &rsquickjs::Ctx<'_>) -> Option<Arc<Vsys>> {
     get_vsys(ctx)
 }
 
+/// The randomness vtable of the Vsys in context, or the system generator without one
+pub fn get_random(ctx: &rsquickjs::Ctx<'_>) -> Arc<RandomVTable> {
+    get_vsys(ctx)
+        .map(|vsys| vsys.random.clone())
+        .unwrap_or_default()
+}
+
+/// The standard streams vtable of the Vsys in context, or the host's streams without one
+pub fn get_stdio(ctx: &rsquickjs::Ctx<'_>) -> Arc<StdioVTable> {
+    get_vsys(ctx)
+        .map(|vsys| vsys.stdio.clone())
+        .unwrap_or_default()
+}
+
 /// Execute a filesystem operation using the vtable from context
 /// This is a convenience macro-like function that handles the common pattern
 /// of getting vsys, checking permission, and calling the fs operation
@@ -75,7 +271,8 @@ where
         rsquickjs::Error::new_from_js("undefined", "Vsys not initialized in context")
     })?;
 
-    if !vsys.permissions().check_fs(path) {
+    let allowed = check_fs_permission(ctx, path);
+    if !allowed {
         return Err(rsquickjs::Exception::throw_message(
             ctx,
             "Permission denied",
@@ -84,3 +281,139 @@ where
 
     op(vsys.fs()).map_err(|e| rsquickjs::Exception::throw_message(ctx, &e.to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::{async_with, Function, Value};
+    use xmas_vsys::ModuleScope;
+
+    use crate::fs::FsModule;
+    use crate::utils::test::{given_file, given_runtime, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_module_scopes() {
+        let path = given_file("secret").await;
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                let vsys = Vsys::builder()
+                    .permissions(Permissions {
+                        modules: vec![ModuleScope {
+                            pattern: "node_modules/left-pad/".into(),
+                            permissions: Permissions::deny_all(),
+                        }],
+                        ..Permissions::allow_all()
+                    })
+                    .build();
+                init(ctx.clone(), Arc::new(vsys)).unwrap();
+                ModuleEvaluator::eval_rust::<FsModule>(ctx.clone(), "fs")
+                    .await
+                    .unwrap();
+
+                let source = r#"
+                    import fs from 'fs';
+                    export const readSync = (path) => fs.readFileSync(path, 'utf8');
+                    export const read = (path) => fs.promises.readFile(path, 'utf8');
+                    export const call = (f, path) => f(path);
+                "#;
+                let main = ModuleEvaluator::eval_js(ctx.clone(), "/app/main.js", source)
+                    .await
+                    .unwrap();
+                let dependency = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "/app/node_modules/left-pad/index.js",
+                    source,
+                )
+                .await
+                .unwrap();
+
+                let path = path.to_string_lossy().into_owned();
+                let main_read_sync: Function = main.get("readSync").unwrap();
+                let main_read: Function = main.get("read").unwrap();
+                assert_eq!(
+                    main_read_sync.call::<_, String>((&path,)).unwrap(),
+                    "secret"
+                );
+
+                let dependency_read_sync: Function = dependency.get("readSync").unwrap();
+                let dependency_read: Function = dependency.get("read").unwrap();
+                let dependency_call: Function = dependency.get("call").unwrap();
+                assert!(dependency_read_sync.call::<_, String>((&path,)).is_err());
+                ctx.catch();
+
+                // async functions check the callers captured when they were called
+                let promise: rsquickjs::Promise = dependency_read.call((&path,)).unwrap();
+                assert!(promise.into_future::<String>().await.is_err());
+                ctx.catch();
+                let promise: rsquickjs::Promise = main_read.call((&path,)).unwrap();
+                assert_eq!(promise.into_future::<String>().await.unwrap(), "secret");
+
+                // code of other modules called by the dependency is limited too
+                assert!(dependency_call
+                    .call::<_, String>((main_read_sync, &path))
+                    .is_err());
+                ctx.catch();
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_module_scopes_in_async_context() {
+        let path = given_file("secret").await;
+        let (rt, ctx) = given_runtime().await;
+        rt.set_promise_hook(Some(crate::async_hooks::promise_hook_tracker()))
+            .await;
+
+        async_with!(ctx => |ctx| {
+            let vsys = Vsys::builder()
+                .permissions(Permissions {
+                    modules: vec![ModuleScope {
+                        pattern: "node_modules/left-pad".into(),
+                        permissions: Permissions::deny_all(),
+                    }],
+                    ..Permissions::allow_all()
+                })
+                .build();
+            init(ctx.clone(), Arc::new(vsys)).unwrap();
+            crate::async_hooks::init(&ctx).unwrap();
+            let fs = ModuleEvaluator::eval_rust::<FsModule>(ctx.clone(), "fs")
+                .await
+                .unwrap();
+
+            // the native function runs as a promise callback, without JS on the stack
+            let source = r#"
+                import fs from 'fs';
+                export const read = (path) => Promise.resolve(path).then(fs.readFileSync);
+            "#;
+            let main = ModuleEvaluator::eval_js(ctx.clone(), "/app/main.js", source)
+                .await
+                .unwrap();
+            let dependency = ModuleEvaluator::eval_js(
+                ctx.clone(),
+                "/app/node_modules/left-pad/index.js",
+                source,
+            )
+            .await
+            .unwrap();
+
+            let path = path.to_string_lossy().into_owned();
+            let main_read: Function = main.get("read").unwrap();
+            let promise: rsquickjs::Promise = main_read.call((&path,)).unwrap();
+            assert!(promise.into_future::<Value>().await.is_ok());
+
+            let dependency_read: Function = dependency.get("read").unwrap();
+            let promise: rsquickjs::Promise = dependency_read.call((&path,)).unwrap();
+            assert!(promise.into_future::<Value>().await.is_err());
+            ctx.catch();
+
+            // callers that can't be told are denied
+            let default: rsquickjs::Object = fs.get("default").unwrap();
+            let read_file_sync: Function = default.get("readFileSync").unwrap();
+            assert!(read_file_sync.call::<_, Value>((&path,)).is_err());
+            ctx.catch();
+        })
+        .await;
+    }
+}
@@ -0,0 +1,599 @@
+//! The `process` global and its `unhandledRejection` / `uncaughtException` hooks.
+//!
+//! Rejections reported by the engine's promise rejection tracker are held until the
+//! current jobs have run, so a handler attached later in the same tick still counts.
+//! Whatever is left is emitted as `unhandledRejection`, then as `uncaughtException`,
+//! and when neither has a listener the [`UnhandledErrorMode`] decides whether the
+//! error is printed as a warning or ends the process.
+//!
+//! `process.memoryUsage()` reports the runtime's heap from the engine's own accounting.
+//!
+//! `process.argv` is whatever the embedder passed to [`set_argv`], for the CLI the runtime,
+//! the script and the arguments following it.
+//!
+//! `process.stdout` and `process.stderr` write through the stdio vtable of the Vsys
+//! in context, so embedders can capture them.
+//!
+//! `beforeExit` is emitted when the script ran out of work, see [`crate::event_loop`],
+//! and `exit` once before the process ends, with `process.exitCode` or the code given
+//! to `process.exit()`.
+//!
+//! `warning` is emitted after the current job for `process.emitWarning()` and the
+//! warnings of other modules, see [`warn`].
+use std::{
+    cell::RefCell,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use rsquickjs::{
+    class::{Trace, Tracer},
+    prelude::{Func, Opt, Rest, This},
+    CatchResultExt, CaughtError, Class, Coerced, Ctx, Exception, IntoJs, JsLifetime, Object,
+    Result, Value,
+};
+use xmas_vsys::StdStream;
+
+use crate::{
+    event::{Emitter, EventList, Events},
+    permissions::get_stdio,
+    utils::{
+        bytes::ObjectBytes,
+        console::{print_error, print_error_and_exit},
+        error::ErrorExtensions,
+        object::ObjectExt,
+        result::ResultExt,
+    },
+};
+
+static EXIT_ON_UNHANDLED: AtomicBool = AtomicBool::new(true);
+
+static ARGV: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// What happens to an uncaught exception or unhandled rejection nobody listens for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnhandledErrorMode {
+    /// Print the error and keep running.
+    Warn,
+    /// Print the error and exit with code 1, like Node.js does by default.
+    #[default]
+    Exit,
+}
+
+impl FromStr for UnhandledErrorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "exit" | "strict" | "throw" => Ok(Self::Exit),
+            _ => Err(["Invalid unhandled error mode: ", s].concat()),
+        }
+    }
+}
+
+pub fn set_unhandled_error_mode(mode: UnhandledErrorMode) {
+    EXIT_ON_UNHANDLED.store(mode == UnhandledErrorMode::Exit, Ordering::Relaxed);
+}
+
+pub fn unhandled_error_mode() -> UnhandledErrorMode {
+    if EXIT_ON_UNHANDLED.load(Ordering::Relaxed) {
+        UnhandledErrorMode::Exit
+    } else {
+        UnhandledErrorMode::Warn
+    }
+}
+
+/// Sets `process.argv` of the contexts initialized afterwards, the runtime and script
+/// first like in Node.js.
+pub fn set_argv(argv: Vec<String>) {
+    *ARGV.write().unwrap() = argv;
+}
+
+/// The arguments following the script, `process.argv` without its first two.
+pub fn script_args() -> Vec<String> {
+    ARGV.read().unwrap().iter().skip(2).cloned().collect()
+}
+
+#[rsquickjs::class]
+#[derive(Clone)]
+pub struct Process<'js> {
+    pub events: Events<'js>,
+    exiting: bool,
+}
+
+unsafe impl<'js> JsLifetime<'js> for Process<'js> {
+    type Changed<'to> = Process<'to>;
+}
+
+impl<'js> Emitter<'js> for Process<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.events.clone()
+    }
+}
+
+impl<'js> Trace<'js> for Process<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.trace_event_emitter(tracer);
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Process<'js> {
+    #[qjs(skip)]
+    pub fn new() -> Self {
+        Self {
+            #[allow(clippy::arc_with_non_send_sync)]
+            events: Arc::new(RwLock::new(Vec::new())),
+            exiting: false,
+        }
+    }
+
+    /// Ends the process after the `exit` listeners ran, with `code` or else
+    /// `process.exitCode`.
+    pub fn exit(ctx: Ctx<'js>, code: Opt<Value<'js>>) -> Result<()> {
+        if let Some(code) = code.0.filter(|code| !code.is_undefined()) {
+            if let Some(process) = get_process(&ctx) {
+                process.set("exitCode", code)?;
+            }
+        }
+        std::process::exit(emit_exit(&ctx))
+    }
+
+    /// Node's shape over the QuickJS heap. The engine can't see the rest of the
+    /// process, so `rss` and `heapTotal` are both the bytes it allocated.
+    pub fn memory_usage(ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let stats = ctx.memory_stats();
+        let usage = Object::new(ctx)?;
+        usage.set("rss", stats.malloc_size)?;
+        usage.set("heapTotal", stats.malloc_size)?;
+        usage.set("heapUsed", stats.memory_used_size)?;
+        usage.set("external", stats.binary_object_size)?;
+        usage.set("arrayBuffers", stats.binary_object_size)?;
+        Ok(usage)
+    }
+
+    /// Emits `warning` with an Error, or one made from a message named `type`, which
+    /// can also come in an options object with `code` and `detail`.
+    pub fn emit_warning(
+        ctx: Ctx<'js>,
+        warning: Value<'js>,
+        options: Opt<Value<'js>>,
+        code: Opt<String>,
+    ) -> Result<()> {
+        if let Some(error) = warning.as_object().filter(|_| warning.is_error()) {
+            return warn(&ctx, error.clone());
+        }
+        let Some(message) = warning.as_string() else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "The \"warning\" argument must be of type string or an instance of Error",
+            ));
+        };
+
+        let options_object = options.0.as_ref().and_then(Value::as_object).cloned();
+        let (kind, code, detail) = match options_object {
+            Some(options) => (
+                options.get_optional::<_, String>("type")?,
+                options.get_optional::<_, String>("code")?,
+                options.get_optional::<_, String>("detail")?,
+            ),
+            None => (options.0.and_then(|kind| kind.get().ok()), code.0, None),
+        };
+        let error = Exception::from_message(ctx.clone(), &message.to_string()?)?;
+        error.set("name", kind.as_deref().unwrap_or("Warning"))?;
+        if let Some(code) = code {
+            error.set("code", code)?;
+        }
+        if let Some(detail) = detail {
+            error.set("detail", detail)?;
+        }
+        warn(&ctx, error.into_object())
+    }
+}
+
+impl Default for Process<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rejected promises without a handler that have not been reported yet.
+#[derive(Default)]
+struct PendingRejections<'js>(Vec<(Value<'js>, Value<'js>)>);
+
+unsafe impl<'js> JsLifetime<'js> for PendingRejections<'js> {
+    type Changed<'to> = PendingRejections<'to>;
+}
+
+/// The handler to install with `AsyncRuntime::set_rejection_handler`.
+pub fn handle_rejection<'js>(
+    ctx: Ctx<'js>,
+    promise: Value<'js>,
+    reason: Value<'js>,
+    is_handled: bool,
+) {
+    if let Err(err) = track_rejection(&ctx, promise, reason, is_handled).catch(&ctx) {
+        tracing::error!("Promise rejection tracker failed: {:?}", err);
+    }
+}
+
+fn track_rejection<'js>(
+    ctx: &Ctx<'js>,
+    promise: Value<'js>,
+    reason: Value<'js>,
+    is_handled: bool,
+) -> Result<()> {
+    let pending = ctx.userdata::<RefCell<PendingRejections>>().or_throw(ctx)?;
+
+    if is_handled {
+        let mut pending = pending.borrow_mut();
+        if let Some(index) = pending.0.iter().position(|(p, _)| *p == promise) {
+            pending.0.remove(index);
+            return Ok(());
+        }
+        drop(pending);
+        // A handler was attached after the rejection had already been reported
+        if let Some(process) = get_process(ctx) {
+            if process.borrow().has_listener_str("rejectionHandled") {
+                Process::emit_str(This(process), ctx, "rejectionHandled", vec![promise], false)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let schedule = {
+        let mut pending = pending.borrow_mut();
+        pending.0.push((promise, reason));
+        pending.0.len() == 1
+    };
+    if schedule {
+        let ctx = ctx.clone();
+        ctx.clone().spawn(async move {
+            tokio::task::yield_now().await;
+            report_pending_rejections(&ctx);
+        });
+    }
+    Ok(())
+}
+
+fn report_pending_rejections(ctx: &Ctx<'_>) {
+    let Some(pending) = ctx.userdata::<RefCell<PendingRejections>>() else {
+        return;
+    };
+    let rejections = std::mem::take(&mut pending.borrow_mut().0);
+    drop(pending);
+
+    for (promise, reason) in rejections {
+        let result: Result<bool> = (|| {
+            if let Some(process) = get_process(ctx) {
+                if process.borrow().has_listener_str("unhandledRejection") {
+                    Process::emit_str(
+                        This(process),
+                        ctx,
+                        "unhandledRejection",
+                        vec![reason.clone(), promise],
+                        false,
+                    )?;
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })();
+        match result.catch(ctx) {
+            Ok(true) => {}
+            Ok(false) => report_uncaught(ctx, reason, "unhandledRejection"),
+            Err(err) => print_error_and_exit(ctx, err),
+        }
+    }
+}
+
+/// Reports an exception nothing caught, for example one thrown from a spawned task.
+pub fn handle_uncaught_exception<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) {
+    match err.into_value(ctx).catch(ctx) {
+        Ok(error) => report_uncaught(ctx, error, "uncaughtException"),
+        Err(err) => print_error_and_exit(ctx, err),
+    }
+}
+
+fn report_uncaught<'js>(ctx: &Ctx<'js>, error: Value<'js>, origin: &str) {
+    let result: Result<bool> = (|| {
+        if let Some(process) = get_process(ctx) {
+            if process.borrow().has_listener_str("uncaughtException") {
+                let origin = origin.into_js(ctx)?;
+                Process::emit_str(
+                    This(process),
+                    ctx,
+                    "uncaughtException",
+                    vec![error.clone(), origin],
+                    false,
+                )?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    })();
+
+    match result.catch(ctx) {
+        Ok(true) => {}
+        Ok(false) => match unhandled_error_mode() {
+            UnhandledErrorMode::Exit => print_error_and_exit(ctx, CaughtError::Value(error)),
+            UnhandledErrorMode::Warn => {
+                let prefix = if origin == "unhandledRejection" {
+                    "Uncaught (in promise)"
+                } else {
+                    "Uncaught"
+                };
+                let printed = prefix
+                    .into_js(ctx)
+                    .and_then(|prefix| print_error(ctx, Rest(vec![prefix, error])));
+                if let Err(err) = printed {
+                    tracing::error!("{}: {:?}", prefix, err);
+                }
+            }
+        },
+        Err(err) => print_error_and_exit(ctx, err),
+    }
+}
+
+/// Emits `beforeExit` on `process` when the script ran out of work, returning whether
+/// anything listened for it.
+pub fn emit_before_exit(ctx: &Ctx<'_>) -> bool {
+    let Some(process) = get_process(ctx) else {
+        return false;
+    };
+    if process.borrow().exiting || !process.borrow().has_listener_str("beforeExit") {
+        return false;
+    }
+    let result = exit_code(&process)
+        .into_js(ctx)
+        .and_then(|code| Process::emit_str(This(process), ctx, "beforeExit", vec![code], false));
+    if let Err(err) = result.catch(ctx) {
+        handle_uncaught_exception(ctx, err);
+    }
+    true
+}
+
+/// Emits `exit` on `process` the first time it is called, returning the code the
+/// process should exit with.
+pub fn emit_exit(ctx: &Ctx<'_>) -> i32 {
+    let Some(process) = get_process(ctx) else {
+        return 0;
+    };
+    let exiting = std::mem::replace(&mut process.borrow_mut().exiting, true);
+    if !exiting && process.borrow().has_listener_str("exit") {
+        let result = exit_code(&process).into_js(ctx).and_then(|code| {
+            Process::emit_str(This(process.clone()), ctx, "exit", vec![code], false)
+        });
+        if let Err(err) = result.catch(ctx) {
+            handle_uncaught_exception(ctx, err);
+        }
+    }
+    // listeners may still change it
+    exit_code(&process)
+}
+
+fn exit_code(process: &Class<'_, Process<'_>>) -> i32 {
+    process
+        .get::<_, Option<Coerced<i32>>>("exitCode")
+        .ok()
+        .flatten()
+        .map_or(0, |code| code.0)
+}
+
+/// Emits `warning` on `process` once the current job is done and logs it, like Node.js
+/// prints the warnings it emits.
+pub fn warn<'js>(ctx: &Ctx<'js>, warning: Object<'js>) -> Result<()> {
+    let name = warning.get::<_, Coerced<String>>("name")?;
+    let message = warning.get::<_, Coerced<String>>("message")?;
+    tracing::warn!("{}: {}", name.0, message.0);
+    if let Some(process) = get_process(ctx) {
+        Process::emit_str(
+            This(process),
+            ctx,
+            "warning",
+            vec![warning.into_value()],
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+fn get_process<'js>(ctx: &Ctx<'js>) -> Option<Class<'js, Process<'js>>> {
+    ctx.globals().get("process").ok()
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let _ = ctx.store_userdata(RefCell::new(PendingRejections::default()));
+
+    Process::add_event_emitter_prototype(ctx)?;
+
+    let process = Class::instance(ctx.clone(), Process::new())?;
+    process.set("stdout", std_stream(ctx, StdStream::Stdout, 1)?)?;
+    process.set("stderr", std_stream(ctx, StdStream::Stderr, 2)?)?;
+    process.set("argv", ARGV.read().unwrap().clone())?;
+    ctx.globals().set("process", process)?;
+
+    Ok(())
+}
+
+/// Updates what `process` captured at [`init`] and may have changed since, `argv` and
+/// whether the standard streams are terminals, for a context initialized ahead of time.
+pub fn refresh(ctx: &Ctx<'_>) -> Result<()> {
+    let Some(process) = get_process(ctx) else {
+        return Ok(());
+    };
+    process.set("argv", ARGV.read().unwrap().clone())?;
+    for (name, stream) in [("stdout", StdStream::Stdout), ("stderr", StdStream::Stderr)] {
+        let object: Object = process.get(name)?;
+        object.set("isTTY", (get_stdio(ctx).is_terminal)(stream))?;
+    }
+    Ok(())
+}
+
+/// Builds `process.stdout` or `process.stderr`, a minimal writable with `write`,
+/// `fd` and `isTTY`.
+fn std_stream<'js>(ctx: &Ctx<'js>, stream: StdStream, fd: u8) -> Result<Object<'js>> {
+    let object = Object::new(ctx.clone())?;
+    object.set("fd", fd)?;
+    object.set("isTTY", (get_stdio(ctx).is_terminal)(stream))?;
+    object.set(
+        "write",
+        Func::from(
+            move |ctx: Ctx<'js>, chunk: ObjectBytes<'js>, rest: Rest<Value<'js>>| -> Result<bool> {
+                get_stdio(&ctx)
+                    .write(stream, chunk.as_bytes(&ctx)?)
+                    .or_throw(&ctx)?;
+                // `write(chunk, encoding?, callback?)`
+                if let Some(callback) = rest.0.last().and_then(|value| value.as_function()) {
+                    callback.call::<_, ()>(())?;
+                }
+                Ok(true)
+            },
+        ),
+    )?;
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::{Object, Promise};
+    use xmas_vsys::{StdioCapture, StdioVTable, Vsys};
+
+    use crate::utils::test::test_async_with;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unhandled_rejection() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::utils::primordials::BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+
+                let result: Object = ctx
+                    .eval(
+                        r#"
+                        const result = { unhandled: [], handled: 0 };
+                        process.on("unhandledRejection", (reason, promise) => {
+                            result.unhandled.push(reason.message);
+                            promise.catch(() => {});
+                        });
+                        process.on("rejectionHandled", () => result.handled++);
+                        result
+                    "#,
+                    )
+                    .catch(&ctx)
+                    .unwrap();
+
+                let (handled, _, reject_handled) = Promise::new(&ctx).unwrap();
+                let (unhandled, _, reject_unhandled) = Promise::new(&ctx).unwrap();
+                let reason = |message: &str| {
+                    ctx.eval::<Value, _>(format!("new Error({:?})", message))
+                        .unwrap()
+                };
+                reject_handled.call::<_, ()>((reason("handled"),)).unwrap();
+                reject_unhandled
+                    .call::<_, ()>((reason("unhandled"),))
+                    .unwrap();
+
+                for (promise, message) in [(&handled, "handled"), (&unhandled, "unhandled")] {
+                    track_rejection(&ctx, promise.clone().into_value(), reason(message), false)
+                        .unwrap();
+                }
+                // Attaching a handler before the report is flushed drops the rejection
+                track_rejection(&ctx, handled.clone().into_value(), reason("handled"), true)
+                    .unwrap();
+
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+                let unhandled_messages: Vec<String> = result.get("unhandled").unwrap();
+                assert_eq!(unhandled_messages, vec!["unhandled".to_string()]);
+
+                // Attaching one after the report emits `rejectionHandled`
+                track_rejection(&ctx, unhandled.into_value(), reason("unhandled"), true).unwrap();
+                let handled_count: usize = result.get("handled").unwrap();
+                assert_eq!(handled_count, 1);
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_usage() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::utils::primordials::BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+
+                let usage: Object = ctx.eval("process.memoryUsage()").catch(&ctx).unwrap();
+                let heap_total: u64 = usage.get("heapTotal").unwrap();
+                let heap_used: u64 = usage.get("heapUsed").unwrap();
+                assert!(heap_used > 0);
+                assert!(heap_total > 0);
+                for key in ["rss", "external", "arrayBuffers"] {
+                    assert!(usage.contains_key(key).unwrap());
+                }
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_std_streams() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                let capture = Arc::new(StdioCapture::default());
+                let vsys = Vsys::builder()
+                    .stdio(StdioVTable::captured(capture.clone()))
+                    .build();
+                crate::permissions::init(ctx.clone(), Arc::new(vsys)).unwrap();
+                init(&ctx).unwrap();
+
+                let flushed: bool = ctx
+                    .eval(
+                        r#"
+                        let flushed = false;
+                        process.stdout.write("out");
+                        process.stderr.write(new Uint8Array([101, 114, 114]), () => flushed = true);
+                        flushed && !process.stdout.isTTY
+                    "#,
+                    )
+                    .catch(&ctx)
+                    .unwrap();
+                assert!(flushed);
+
+                let (stdout, stderr) = capture.take();
+                assert_eq!(stdout, b"out");
+                assert_eq!(stderr, b"err");
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_argv() {
+        let argv = ["xmas", "/app/cli.js", "--name", "x"];
+        set_argv(argv.map(String::from).to_vec());
+        assert_eq!(script_args(), ["--name", "x"]);
+
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::utils::primordials::BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+                let argv: String = ctx.eval("process.argv.join(' ')").unwrap();
+                assert_eq!(argv, "xmas /app/cli.js --name x");
+
+                // a context initialized ahead of time takes the arguments given later
+                set_argv(["xmas", "/app/cli.js"].map(String::from).to_vec());
+                refresh(&ctx).unwrap();
+                let argv: String = ctx.eval("process.argv.join(' ')").unwrap();
+                assert_eq!(argv, "xmas /app/cli.js");
+            })
+        })
+        .await;
+    }
+}
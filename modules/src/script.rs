@@ -1,24 +1,290 @@
+use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
 
-use oxc::allocator::Allocator;
-use oxc::ast::ast::Program;
+use oxc::allocator::{Allocator, Vec as ArenaVec};
+use oxc::ast::ast::{Decorator, Program, Statement};
+use oxc::ast_visit::{walk, Visit};
 use oxc::codegen::{Codegen, CodegenOptions, CommentOptions};
+use oxc::isolated_declarations::{IsolatedDeclarations, IsolatedDeclarationsOptions};
 use oxc::parser::{ParseOptions, Parser, ParserReturn};
 use oxc::semantic::SemanticBuilder;
-use oxc::span::SourceType;
-use oxc::transformer::{BabelOptions, TransformOptions, Transformer};
+use oxc::span::{GetSpan, SourceType};
+use oxc::transformer::{TransformOptions, Transformer};
+use rsquickjs::context::EvalOptions;
 use rsquickjs::prelude::{Func, Rest};
 
-use crate::utils::result::ResultExt;
+use crate::utils::{disk_cache, result::ResultExt};
+
+/// How JSX is compiled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsxOptions {
+    /// Import the factories from `<import_source>/jsx-runtime` instead of calling the
+    /// pragma, like `"jsx": "react-jsx"` in tsconfig.json.
+    pub automatic: bool,
+    /// The package of the automatic runtime, `react` by default.
+    pub import_source: Option<String>,
+    /// The factory of elements of the classic runtime, `_jsx.createElement` by default.
+    pub pragma: Option<String>,
+    /// The factory of fragments of the classic runtime, `_jsx.Fragment` by default.
+    pub pragma_frag: Option<String>,
+}
+
+/// Which decorators a transform compiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Decorators {
+    /// TypeScript's `experimentalDecorators`, which NestJS, MobX and TypeORM are built on.
+    #[default]
+    Legacy,
+    /// Decorators of the TC39 proposal. The transform can't compile them yet, so sources
+    /// using them are rejected instead of failing to parse in the engine.
+    Tc39,
+}
+
+impl FromStr for Decorators {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "legacy" | "experimental" => Ok(Self::Legacy),
+            "tc39" | "standard" => Ok(Self::Tc39),
+            _ => Err(["Invalid decorators: ", s].concat()),
+        }
+    }
+}
+
+/// What a transform does besides stripping types, given to `scriptTransform` or set as
+/// the default by the command line and config.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptTransformOptions {
+    /// The ECMAScript version or engines to lower syntax for, e.g. `es2015` or
+    /// `chrome80,safari13`. All syntax is lowered without one.
+    pub target: Option<String>,
+    pub decorators: Decorators,
+    /// Emit the design-time types of decorated members, like `emitDecoratorMetadata`.
+    pub emit_decorator_metadata: bool,
+    pub jsx: JsxOptions,
+}
+
+impl ScriptTransformOptions {
+    fn to_oxc(&self) -> rsquickjs::Result<TransformOptions> {
+        let mut to = match &self.target {
+            Some(target) => TransformOptions::from_target(target).map_err(|e| {
+                rsquickjs::Error::new_from_js_message("string", "target", e.to_string())
+            })?,
+            None => TransformOptions::enable_all(),
+        };
+        to.decorator.legacy = self.decorators == Decorators::Legacy;
+        to.decorator.emit_decorator_metadata = self.emit_decorator_metadata;
+        to.jsx.development = false;
+        if self.jsx.automatic {
+            to.jsx.runtime = oxc::transformer::JsxRuntime::Automatic;
+            to.jsx.import_source = self.jsx.import_source.clone();
+        } else {
+            to.jsx.runtime = oxc::transformer::JsxRuntime::Classic;
+            to.jsx.pragma = Some(
+                self.jsx
+                    .pragma
+                    .clone()
+                    .unwrap_or_else(|| "_jsx.createElement".into()),
+            );
+            to.jsx.pragma_frag = Some(
+                self.jsx
+                    .pragma_frag
+                    .clone()
+                    .unwrap_or_else(|| "_jsx.Fragment".into()),
+            );
+        }
+        Ok(to)
+    }
+}
+
+static DEFAULT_OPTIONS: RwLock<ScriptTransformOptions> = RwLock::new(ScriptTransformOptions {
+    target: None,
+    decorators: Decorators::Legacy,
+    emit_decorator_metadata: false,
+    jsx: JsxOptions {
+        automatic: false,
+        import_source: None,
+        pragma: None,
+        pragma_frag: None,
+    },
+});
+
+/// Sets the options of the transforms given none, such as those of modules transpiled
+/// when loaded.
+pub fn set_default_options(options: ScriptTransformOptions) {
+    *DEFAULT_OPTIONS.write().unwrap() = options;
+}
+
+pub fn default_options() -> ScriptTransformOptions {
+    DEFAULT_OPTIONS.read().unwrap().clone()
+}
+
 pub fn allocator() -> Allocator {
     oxc::allocator::Allocator::default()
 }
+/// A problem the parser found, located in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// `error`, `warning` or `advice`.
+    pub severity: &'static str,
+    pub help: Option<String>,
+    pub labels: Vec<DiagnosticLabel>,
+    /// The lines around the first label with the label underlined, empty without labels.
+    pub code_frame: String,
+}
+
+/// A span of the source a [`Diagnostic`] points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticLabel {
+    /// Byte offsets of the span.
+    pub start: u32,
+    pub end: u32,
+    /// One based line and column of the start.
+    pub line: u32,
+    pub column: u32,
+    pub message: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(error: &oxc::diagnostics::OxcDiagnostic, source: &str) -> Self {
+        let labels: Vec<DiagnosticLabel> = error
+            .labels
+            .iter()
+            .flatten()
+            .map(|label| {
+                let start = label.offset().min(source.len());
+                let end = (start + label.len()).min(source.len());
+                let (line, column) = line_column(source, start);
+                DiagnosticLabel {
+                    start: start as u32,
+                    end: end as u32,
+                    line,
+                    column,
+                    message: label.label().map(String::from),
+                }
+            })
+            .collect();
+        let code_frame = labels
+            .first()
+            .map(|label| code_frame(source, label))
+            .unwrap_or_default();
+        Self {
+            message: error.message.to_string(),
+            severity: match error.severity {
+                oxc::diagnostics::Severity::Error => "error",
+                oxc::diagnostics::Severity::Warning => "warning",
+                oxc::diagnostics::Severity::Advice => "advice",
+            },
+            help: error.help.as_ref().map(|help| help.to_string()),
+            labels,
+            code_frame,
+        }
+    }
+
+    /// `path:line:column: message` and the code frame, like a compiler prints errors.
+    pub fn render(&self, path: &str) -> String {
+        let mut text = String::from(path);
+        if let Some(label) = self.labels.first() {
+            let _ = write!(text, ":{}:{}", label.line, label.column);
+        }
+        let _ = write!(text, ": {}", self.message);
+        if !self.code_frame.is_empty() {
+            text.push('\n');
+            text.push_str(&self.code_frame);
+        }
+        if let Some(help) = &self.help {
+            let _ = write!(text, "\nhelp: {}", help);
+        }
+        text
+    }
+}
+
+impl<'js> rsquickjs::IntoJs<'js> for Diagnostic {
+    fn into_js(self, ctx: &rsquickjs::Ctx<'js>) -> rsquickjs::Result<rsquickjs::Value<'js>> {
+        let obj = rsquickjs::Object::new(ctx.clone())?;
+        obj.set("message", self.message)?;
+        obj.set("severity", self.severity)?;
+        obj.set("help", self.help)?;
+        let labels = rsquickjs::Array::new(ctx.clone())?;
+        for (index, label) in self.labels.into_iter().enumerate() {
+            let span = rsquickjs::Object::new(ctx.clone())?;
+            span.set("start", label.start)?;
+            span.set("end", label.end)?;
+            span.set("line", label.line)?;
+            span.set("column", label.column)?;
+            span.set("message", label.message)?;
+            labels.set(index, span)?;
+        }
+        obj.set("labels", labels)?;
+        obj.set("codeFrame", self.code_frame)?;
+        Ok(obj.into_value())
+    }
+}
+
+/// The one based line and column of the byte `offset` of `source`.
+fn line_column(source: &str, offset: usize) -> (u32, u32) {
+    let mut offset = offset;
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line as u32, column as u32)
+}
+
+/// The line of `label` after the one before it, with the span underlined:
+///
+/// ```text
+///   1 | let a = 1;
+/// > 2 | const b: = 2;
+///     |          ^ here
+/// ```
+fn code_frame(source: &str, label: &DiagnosticLabel) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let line = label.line as usize;
+    let first = line.saturating_sub(1).max(1);
+    let width = line.to_string().len();
+    let mut frame = String::new();
+    for number in first..=line.min(lines.len()) {
+        let marker = if number == line { '>' } else { ' ' };
+        let _ = writeln!(frame, "{marker} {number:>width$} | {}", lines[number - 1]);
+    }
+    let text = lines.get(line - 1).copied().unwrap_or_default();
+    let column = label.column as usize - 1;
+    let spanned = source
+        .get(label.start as usize..label.end as usize)
+        .unwrap_or_default();
+    // a span running past the line is underlined up to its end
+    let rest = text.chars().count().saturating_sub(column).max(1);
+    let underline = spanned.chars().take_while(|c| *c != '\n').count();
+    let underline = underline.clamp(1, rest);
+    let _ = write!(
+        frame,
+        "  {:width$} | {}{}",
+        "",
+        " ".repeat(column),
+        "^".repeat(underline)
+    );
+    if let Some(message) = &label.message {
+        let _ = write!(frame, " {}", message);
+    }
+    frame
+}
+
+/// Parses `source` as `source_type`: `js`, `mjs`, `cjs`, `ts`, `tsx` or `jsx`. A source
+/// with syntax errors gives their diagnostics.
 pub fn parse<'x>(
     source_type: &'x str,
     source: &'x str,
     allocator: &'x Allocator,
-) -> Option<Program<'x>> {
+) -> Result<Program<'x>, Vec<Diagnostic>> {
     let source_type = match source_type {
         "mjs" => SourceType::mjs(),
         "cjs" => SourceType::cjs(),
@@ -29,7 +295,6 @@ pub fn parse<'x>(
     };
     let ParserReturn {
         program,
-        module_record,
         errors,
         panicked,
         ..
@@ -39,48 +304,124 @@ pub fn parse<'x>(
             ..ParseOptions::default()
         })
         .parse();
-    if panicked {
-        println!("Parser panicked");
-        return None;
-    } else {
-        if !errors.is_empty() {
-            println!("Parser Errors:");
-            for error in errors {
-                let error = error.with_source_code(source.to_string());
-                println!("{error:?}");
-            }
+    if !panicked && errors.is_empty() {
+        return Ok(program);
+    }
+    let mut diagnostics: Vec<Diagnostic> = errors
+        .iter()
+        .map(|error| Diagnostic::new(error, source))
+        .collect();
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic {
+            message: "Parser panicked".into(),
+            severity: "error",
+            help: None,
+            labels: Vec::new(),
+            code_frame: String::new(),
+        });
+    }
+    Err(diagnostics)
+}
+
+/// The `SyntaxError` of `diagnostics` of the source at `path`, with the diagnostics as
+/// its `diagnostics` and the code frame of the first one as its `codeFrame`.
+pub fn throw_diagnostics<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    path: &str,
+    diagnostics: Vec<Diagnostic>,
+) -> rsquickjs::Error {
+    let create = || -> rsquickjs::Result<rsquickjs::Value<'js>> {
+        let constructor: rsquickjs::function::Constructor = ctx
+            .globals()
+            .get(rsquickjs::atom::PredefinedAtom::SyntaxError)?;
+        let first = diagnostics.first();
+        let message = first.map_or_else(String::new, |diagnostic| {
+            let render = diagnostic.render(path);
+            render.lines().next().unwrap_or_default().to_string()
+        });
+        let error: rsquickjs::Object = constructor.construct((message,))?;
+        let code_frame = first
+            .map(|diagnostic| diagnostic.code_frame.clone())
+            .filter(|code_frame| !code_frame.is_empty());
+        error.set("codeFrame", code_frame)?;
+        error.set("diagnostics", diagnostics.clone())?;
+        Ok(error.into_value())
+    };
+    match create() {
+        Ok(error) => ctx.throw(error),
+        Err(err) => err,
+    }
+}
+
+/// What minifying a transform does besides removing whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinifyOptions {
+    /// Rename local identifiers to short names.
+    pub mangle: bool,
+    /// Fold constants, drop dead code and shorten expressions.
+    pub compress: bool,
+    /// Keep the `name` of functions and classes, for code reading `function.name`.
+    pub keep_names: bool,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        Self {
+            mangle: true,
+            compress: true,
+            keep_names: false,
         }
-        return Some(program);
     }
 }
 
+/// The JavaScript of a transform, with the source map of the output as JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformOutput {
+    pub code: String,
+    pub map: Option<String>,
+}
+
+/// Transforms `ast` parsed from `source_path`, minified with `minify`. Without `options`,
+/// the [`default_options`] apply.
 pub fn transform<'x>(
     source_path: &str,
-    options: Option<BabelOptions>,
-    minify: bool,
+    options: Option<&ScriptTransformOptions>,
+    minify: Option<MinifyOptions>,
     allocator: &'x Allocator,
     mut ast: Program<'x>,
-) -> rsquickjs::Result<String> {
-    let scoping = SemanticBuilder::new().build(&ast).semantic.into_scoping();
-    let transform_options = if let Some(babel) = options {
-        TransformOptions::try_from(&babel).map_err(|e| {
-            tracing::error!("Failed to convert Babel options: {:?}", e);
-            rsquickjs::Error::new_from_js("TypeError", "Failed to convert Babel options")
-        })?
-    } else {
-        let mut to = TransformOptions::enable_all();
-        to.jsx.development = false;
-        to.jsx.runtime = oxc::transformer::JsxRuntime::Classic;
-        to.jsx.pragma = Some("_jsx.createElement".to_string());
-        to.jsx.pragma_frag = Some("_jsx.Fragment".to_string());
-        to
+) -> rsquickjs::Result<TransformOutput> {
+    let options = match options {
+        Some(options) => options.clone(),
+        None => default_options(),
     };
+    if options.decorators == Decorators::Tc39 {
+        let mut decorators = DecoratorSpans(Vec::new());
+        decorators.visit_program(&ast);
+        if let Some(start) = decorators.0.first() {
+            let line = ast.source_text[..*start as usize].matches('\n').count() + 1;
+            return Err(rsquickjs::Error::new_from_js_message(
+                "decorator",
+                "JavaScript",
+                format!(
+                    "{}:{}: TC39 decorators aren't supported yet, as oxc can't compile them; \
+                     use legacy decorators (experimentalDecorators)",
+                    source_path, line
+                ),
+            ));
+        }
+    }
+    let scoping = SemanticBuilder::new().build(&ast).semantic.into_scoping();
+    let transform_options = options.to_oxc()?;
     let trans = Transformer::new(&allocator, Path::new(source_path), &transform_options)
         .build_with_scoping(scoping, &mut ast);
+    let mut mangled = None;
+    if let Some(minify) = minify {
+        mangled = minify_program(minify, &allocator, &mut ast);
+    }
     let codegen = Codegen::new().with_options(CodegenOptions {
         single_quote: false,
-        minify,
-        comments: if minify {
+        minify: minify.is_some(),
+        comments: if minify.is_some() {
             CommentOptions::default()
         } else {
             CommentOptions::disabled()
@@ -90,23 +431,249 @@ pub fn transform<'x>(
         indent_width: 2,
         initial_indent: 0,
     });
-    let output = codegen.build(&ast);
-    return Ok(output.code);
+    let output = codegen.with_scoping(mangled).build(&ast);
+    Ok(TransformOutput {
+        code: output.code,
+        map: output.map.map(|map| map.to_json_string()),
+    })
 }
 
-pub fn script_transform<'js>(
-    ctx: rsquickjs::Ctx<'js>,
-    rest: Rest<rsquickjs::Value<'js>>,
-) -> rsquickjs::Result<String> {
-    let allocator = oxc::allocator::Allocator::default();
+/// Emits the `.d.ts` of `program`, parsed from `source`, the way TypeScript does with
+/// `isolatedDeclarations`: types are read off the annotations alone, so an export whose
+/// type would have to be inferred gives a diagnostic instead.
+pub fn declarations<'x>(
+    source: &str,
+    allocator: &'x Allocator,
+    program: &Program<'x>,
+) -> Result<String, Vec<Diagnostic>> {
+    let ret = IsolatedDeclarations::new(
+        allocator,
+        IsolatedDeclarationsOptions {
+            strip_internal: false,
+        },
+    )
+    .build(program);
+    if !ret.errors.is_empty() {
+        return Err(ret
+            .errors
+            .iter()
+            .map(|error| Diagnostic::new(error, source))
+            .collect());
+    }
+    Ok(Codegen::new().build(&ret.program).code)
+}
+
+/// `~/.xmas/transform`, where the output of [`transform_cached`] is kept.
+pub fn transform_cache_dir() -> PathBuf {
+    disk_cache::dir("transform")
+}
+
+/// Compresses and mangles `program` in place, returning the scoping with the mangled names
+/// for the codegen.
+fn minify_program<'x>(
+    options: MinifyOptions,
+    allocator: &'x Allocator,
+    program: &mut Program<'x>,
+) -> Option<oxc::semantic::Scoping> {
+    use oxc::minifier::{
+        CompressOptions, CompressOptionsKeepNames, MangleOptions, MangleOptionsKeepNames, Minifier,
+        MinifierOptions,
+    };
+
+    let (compress_keep_names, mangle_keep_names) = if options.keep_names {
+        (
+            CompressOptionsKeepNames::all_true(),
+            MangleOptionsKeepNames::all_true(),
+        )
+    } else {
+        (
+            CompressOptionsKeepNames::all_false(),
+            MangleOptionsKeepNames::all_false(),
+        )
+    };
+    let minifier = Minifier::new(MinifierOptions {
+        mangle: options.mangle.then(|| MangleOptions {
+            keep_names: mangle_keep_names,
+            ..MangleOptions::default()
+        }),
+        compress: options.compress.then(|| CompressOptions {
+            keep_names: compress_keep_names,
+            ..CompressOptions::smallest()
+        }),
+    });
+    minifier.minify(allocator, program).scoping
+}
+
+/// Why [`transform_cached`] failed.
+#[derive(Debug)]
+pub enum TransformError {
+    /// The source at `path` has syntax errors.
+    Parse {
+        path: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Transform(rsquickjs::Error),
+}
+
+impl TransformError {
+    /// Throws a `SyntaxError` with the diagnostics of a parse error, see
+    /// [`throw_diagnostics`].
+    pub fn throw(self, ctx: &rsquickjs::Ctx<'_>) -> rsquickjs::Error {
+        match self {
+            Self::Parse { path, diagnostics } => throw_diagnostics(ctx, &path, diagnostics),
+            Self::Transform(err) => err,
+        }
+    }
+}
+
+impl From<rsquickjs::Error> for TransformError {
+    fn from(err: rsquickjs::Error) -> Self {
+        Self::Transform(err)
+    }
+}
+
+/// Parses and transforms `source` like [`transform`], reusing the output
+/// for the same path, source type, options, minification and source from the disk cache,
+/// so unchanged sources aren't parsed again.
+pub fn transform_cached(
+    source_path: &str,
+    source_type: &str,
+    source: &str,
+    options: Option<&ScriptTransformOptions>,
+    minify: Option<MinifyOptions>,
+) -> Result<TransformOutput, TransformError> {
+    transform_cached_in(
+        &transform_cache_dir(),
+        source_path,
+        source_type,
+        source,
+        options,
+        minify,
+    )
+}
+
+fn transform_cached_in(
+    dir: &Path,
+    source_path: &str,
+    source_type: &str,
+    source: &str,
+    options: Option<&ScriptTransformOptions>,
+    minify: Option<MinifyOptions>,
+) -> Result<TransformOutput, TransformError> {
+    let options = options.cloned().unwrap_or_else(default_options);
+    let options_key = format!("{:?} {:?}", options, minify);
+    let entry = dir.join(disk_cache::key(&[
+        source_path.as_bytes(),
+        source_type.as_bytes(),
+        options_key.as_bytes(),
+        source.as_bytes(),
+    ]));
+    let map_entry = entry.with_extension("map");
+    if let Ok(code) = std::fs::read_to_string(&entry) {
+        let map = std::fs::read_to_string(&map_entry).ok();
+        return Ok(TransformOutput { code, map });
+    }
+
+    let allocator = allocator();
+    let program =
+        parse(source_type, source, &allocator).map_err(|diagnostics| TransformError::Parse {
+            path: source_path.into(),
+            diagnostics,
+        })?;
+    let output = transform(source_path, Some(&options), minify, &allocator, program)?;
+    // the map goes first, so a cached output always finds its map
+    let written = match &output.map {
+        Some(map) => disk_cache::write_entry(&map_entry, map.as_bytes()),
+        None => Ok(()),
+    }
+    .and_then(|_| disk_cache::write_entry(&entry, output.code.as_bytes()));
+    // what can't be cached is still returned, it is just transformed every time
+    if let Err(err) = written {
+        tracing::info!("Failed to cache the transform of {}: {}", source_path, err);
+    }
+    Ok(output)
+}
+
+/// The global holding the counters of [`instrument_coverage`].
+pub const COVERAGE_GLOBAL: &str = "__xmas_coverage";
 
+/// Instruments a module for line coverage: every statement first increments its counter
+/// in `globalThis.__xmas_coverage`, a `Uint32Array` with a slot per statement which must
+/// exist before the module is evaluated.
+///
+/// No lines are added, so a source map of `source` still holds for the instrumented code.
+/// Returns the code with the one based line and column of the statement of each counter.
+pub fn instrument_coverage(source: &str) -> Option<(String, Vec<(u32, u32)>)> {
+    let allocator = allocator();
+    let program = parse("mjs", source, &allocator).ok()?;
+    let mut starts = StatementStarts(Vec::new());
+    starts.visit_program(&program);
+    let mut starts = starts.0;
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut code = String::with_capacity(source.len() + starts.len() * 24);
+    let mut positions = Vec::with_capacity(starts.len());
+    let (mut copied, mut line, mut line_start) = (0, 1, 0);
+    for (index, start) in starts.into_iter().enumerate() {
+        let start = start as usize;
+        for (offset, _) in source[copied..start].match_indices('\n') {
+            line += 1;
+            line_start = copied + offset + 1;
+        }
+        code.push_str(&source[copied..start]);
+        let _ = write!(code, "{COVERAGE_GLOBAL}[{index}]++;");
+        positions.push((line, (start - line_start) as u32 + 1));
+        copied = start;
+    }
+    code.push_str(&source[copied..]);
+    Some((code, positions))
+}
+
+/// Collects where the statements which do something when reached begin.
+/// Where the decorators of a program start.
+struct DecoratorSpans(Vec<u32>);
+
+impl<'a> Visit<'a> for DecoratorSpans {
+    fn visit_decorator(&mut self, decorator: &Decorator<'a>) {
+        self.0.push(decorator.span.start);
+    }
+}
+
+struct StatementStarts(Vec<u32>);
+
+impl<'a> Visit<'a> for StatementStarts {
+    fn visit_statements(&mut self, statements: &ArenaVec<'a, Statement<'a>>) {
+        for statement in statements {
+            let counted = match statement {
+                // hoisted or resolved before the module runs
+                Statement::ImportDeclaration(_)
+                | Statement::ExportAllDeclaration(_)
+                | Statement::FunctionDeclaration(_)
+                | Statement::EmptyStatement(_) => false,
+                Statement::ExportNamedDeclaration(export) => export.declaration.is_some(),
+                _ => true,
+            };
+            if counted {
+                self.0.push(statement.span().start);
+            }
+        }
+        walk::walk_statements(self, statements);
+    }
+}
+
+/// Transforms the arguments of `scriptTransform` and `scriptEval`.
+fn transform_args<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    rest: &Rest<rsquickjs::Value<'js>>,
+) -> rsquickjs::Result<TransformOutput> {
     // 0 th param should be the source code
     // 1 th optional param should be the source type: "js", "mjs", "cjs", "ts", "tsx", "jsx"
     // by default it is "tsx"
-    // 2 th optional param should be babel options in json
-    // 3 th optional param should be minify boolean
+    // 2 th optional param should be the transform options object
+    // 3 th optional param should be minify, a boolean or `{ mangle, compress, keepNames }`
     let source = if let Some(v) = rest.get(0) {
-        v.as_string().or_throw(&ctx)?.to_string().or_throw(&ctx)?
+        v.as_string().or_throw(ctx)?.to_string().or_throw(ctx)?
     } else {
         return Err(rsquickjs::Error::new_from_js(
             "TypeError",
@@ -114,52 +681,133 @@ pub fn script_transform<'js>(
         ));
     };
     let source_type = if let Some(v) = rest.get(1) {
-        v.as_string().or_throw(&ctx)?.to_string().or_throw(&ctx)?
+        v.as_string().or_throw(ctx)?.to_string().or_throw(ctx)?
     } else {
         "tsx".to_string()
     };
 
-    let parsed = parse(&source_type, &source, &allocator);
-    if let None = parsed {
-        return Err(rsquickjs::Error::new_from_js(
-            "Error",
-            "Failed to parse source code",
-        ));
-    } else {
-        let ast = parsed.unwrap();
-        let babel_options = if let Some(v) = rest.get(2) {
-            // let json_str = v.as_string().or_throw(ctx)?.to_string().or_throw(ctx)?;
-            // let babel_opts: BabelOptions = serde_json::from_str(json_str).map_err(|e| {
-            //     rsquickjs::Error::new_from_js(
-            //         "TypeError",
-            //         format!("Failed to parse babel options: {}", e),
-            //     )
-            // })?;
-            // Some(babel_opts)
-            tracing::warn!("Custom Babel options are not yet supported, using default options");
-            None
-        } else {
-            None
-        };
-        let minify = if let Some(v) = rest.get(3) {
-            v.as_bool().or_throw(&ctx)?
+    let options = match rest.get(2) {
+        Some(v) => transform_options_from_js(ctx, v)?,
+        None => default_options(),
+    };
+    let minify = match rest.get(3) {
+        Some(v) => minify_options_from_js(ctx, v)?,
+        None => None,
+    };
+    transform_cached(
+        &format!("<transformed>.{}", source_type),
+        &source_type,
+        &source,
+        Some(&options),
+        minify,
+    )
+    .map_err(|err| err.throw(ctx))
+}
+
+/// `scriptTransform(source, sourceType?, options?, minify?)`, returning `{ code, map }`
+/// with the source map as JSON.
+pub fn script_transform<'js>(
+    ctx: rsquickjs::Ctx<'js>,
+    rest: Rest<rsquickjs::Value<'js>>,
+) -> rsquickjs::Result<rsquickjs::Object<'js>> {
+    let output = transform_args(&ctx, &rest)?;
+    let result = rsquickjs::Object::new(ctx)?;
+    result.set("code", output.code)?;
+    result.set("map", output.map)?;
+    Ok(result)
+}
+
+/// Reads the `minify` argument of `scriptTransform`: `true` for [`MinifyOptions::default`],
+/// or an object whose missing fields keep their defaults.
+fn minify_options_from_js<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    value: &rsquickjs::Value<'js>,
+) -> rsquickjs::Result<Option<MinifyOptions>> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    if let Some(minify) = value.as_bool() {
+        return Ok(minify.then(MinifyOptions::default));
+    }
+    let obj = value.as_object().or_throw(ctx)?;
+    let defaults = MinifyOptions::default();
+    Ok(Some(MinifyOptions {
+        mangle: obj
+            .get_optional::<_, bool>("mangle")?
+            .unwrap_or(defaults.mangle),
+        compress: obj
+            .get_optional::<_, bool>("compress")?
+            .unwrap_or(defaults.compress),
+        keep_names: obj
+            .get_optional::<_, bool>("keepNames")?
+            .unwrap_or(defaults.keep_names),
+    }))
+}
+
+/// Reads the options object of `scriptTransform`, whose missing fields keep their
+/// defaults: `{ target, decorators: "legacy" | "tc39", experimentalDecorators,
+/// emitDecoratorMetadata, jsx: { runtime, importSource, pragma, pragmaFrag } }`.
+fn transform_options_from_js<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    value: &rsquickjs::Value<'js>,
+) -> rsquickjs::Result<ScriptTransformOptions> {
+    let mut options = default_options();
+    if value.is_undefined() || value.is_null() {
+        return Ok(options);
+    }
+    let obj = value
+        .as_object()
+        .ok_or(rsquickjs::Error::new_from_js(value.type_name(), "Object"))?;
+    if let Some(target) = obj.get_optional::<_, String>("target")? {
+        options.target = Some(target);
+    }
+    if let Some(decorators) = obj.get_optional::<_, String>("decorators")? {
+        options.decorators = decorators
+            .parse()
+            .map_err(|e: String| rsquickjs::Exception::throw_type(ctx, &e))?;
+    }
+    if let Some(legacy) = obj.get_optional::<_, bool>("experimentalDecorators")? {
+        options.decorators = if legacy {
+            Decorators::Legacy
         } else {
-            false
+            Decorators::Tc39
         };
-        return Ok(transform(
-            &format!("<transformed>.{}", source_type),
-            babel_options,
-            minify,
-            &allocator,
-            ast,
-        )?);
     }
+    if let Some(metadata) = obj.get_optional::<_, bool>("emitDecoratorMetadata")? {
+        options.emit_decorator_metadata = metadata;
+    }
+    if let Some(jsx) = obj.get_optional::<_, rsquickjs::Object>("jsx")? {
+        if let Some(runtime) = jsx.get_optional::<_, String>("runtime")? {
+            options.jsx.automatic = match runtime.as_str() {
+                "automatic" => true,
+                "classic" => false,
+                _ => {
+                    return Err(rsquickjs::Exception::throw_type(
+                        ctx,
+                        &["Invalid JSX runtime: ", &runtime].concat(),
+                    ))
+                }
+            };
+        }
+        if let Some(import_source) = jsx.get_optional::<_, String>("importSource")? {
+            options.jsx.import_source = Some(import_source);
+        }
+        if let Some(pragma) = jsx.get_optional::<_, String>("pragma")? {
+            options.jsx.pragma = Some(pragma);
+        }
+        if let Some(pragma_frag) = jsx.get_optional::<_, String>("pragmaFrag")? {
+            options.jsx.pragma_frag = Some(pragma_frag);
+        }
+    }
+    Ok(options)
 }
 
+/// `scriptValidate(source, sourceType?)`, returning the diagnostics of the syntax errors
+/// of `source`, none when it parses.
 fn script_validate<'js>(
     ctx: rsquickjs::Ctx<'js>,
     rest: Rest<rsquickjs::Value<'js>>,
-) -> rsquickjs::Result<bool> {
+) -> rsquickjs::Result<Vec<Diagnostic>> {
     let allocator = oxc::allocator::Allocator::default();
 
     // 0 th param should be the source code
@@ -179,27 +827,70 @@ fn script_validate<'js>(
         "tsx".to_string()
     };
 
-    let parsed = parse(&source_type, &source, &allocator);
-    if let None = parsed {
-        return Ok(false);
-    }
-    Ok(true)
+    Ok(parse(&source_type, &source, &allocator)
+        .err()
+        .unwrap_or_default())
+}
+
+/// `scriptDeclarations(source, sourceType?)`, returning the `.d.ts` of `source`. Throws a
+/// `SyntaxError` with the diagnostics when it doesn't parse or an export lacks the
+/// annotations its declaration needs.
+fn script_declarations<'js>(
+    ctx: rsquickjs::Ctx<'js>,
+    rest: Rest<rsquickjs::Value<'js>>,
+) -> rsquickjs::Result<String> {
+    let allocator = oxc::allocator::Allocator::default();
+
+    // 0 th param should be the source code
+    // 1 th optional param should be the source type: "ts" or "tsx", by default it is "ts"
+    let source = if let Some(v) = rest.get(0) {
+        v.as_string().or_throw(&ctx)?.to_string().or_throw(&ctx)?
+    } else {
+        return Err(rsquickjs::Error::new_from_js(
+            "TypeError",
+            "First argument 'source' is required",
+        ));
+    };
+    let source_type = if let Some(v) = rest.get(1) {
+        v.as_string().or_throw(&ctx)?.to_string().or_throw(&ctx)?
+    } else {
+        "ts".to_string()
+    };
+
+    let path = format!("<declarations>.{}", source_type);
+    parse(&source_type, &source, &allocator)
+        .and_then(|program| declarations(&source, &allocator, &program))
+        .map_err(|diagnostics| throw_diagnostics(&ctx, &path, diagnostics))
 }
 
 fn script_eval<'js>(
     ctx: rsquickjs::Ctx<'js>,
     rest: Rest<rsquickjs::Value<'js>>,
 ) -> rsquickjs::Result<rsquickjs::Promise<'js>> {
-    let transformed = script_transform(ctx.clone(), rest)?;
-    ctx.eval_promise::<_>(transformed.as_bytes())
+    static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+    let output = transform_args(&ctx, &rest)?;
+    // a name per eval, so the stack of an error thrown later still finds its map
+    let eval = EVALS.fetch_add(1, Ordering::Relaxed);
+    ctx.eval_with_options(
+        output.code,
+        EvalOptions {
+            promise: true,
+            filename: Some(format!("<scriptEval {}>.js", eval)),
+            source_map: output.map,
+            ..Default::default()
+        },
+    )
 }
 
 pub fn init(ctx: &rsquickjs::Ctx<'_>) -> rsquickjs::Result<()> {
     let globals = ctx.globals();
     // transform input script from jsx/ts/tsx to js
     globals.set("scriptTransform", Func::from(script_transform))?;
-    // try to parse input script, return false if failed
+    // try to parse input script, return the diagnostics of its syntax errors
     globals.set("scriptValidate", Func::from(script_validate))?;
+    // emit the type declarations of input script
+    globals.set("scriptDeclarations", Func::from(script_declarations))?;
     // validate and transform input script, evaluate if success, throw exception if failed
     globals.set("scriptEval", Func::from(script_eval))?;
     Ok(())
@@ -228,7 +919,195 @@ mod test {
         "#;
         let allocator = oxc::allocator::Allocator::default();
         let ast = super::parse("tsx", source, &allocator).unwrap();
-        let r = super::transform("example.tsx", None, false, &allocator, ast).unwrap();
-        println!("Transformed JS:\n{}", r);
+        let r = super::transform("example.tsx", None, None, &allocator, ast).unwrap();
+        println!("Transformed JS:\n{}", r.code);
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let allocator = oxc::allocator::Allocator::default();
+        let source = "let a = 1;\nconst b: = 2;\n";
+        let diagnostics = super::parse("ts", source, &allocator).unwrap_err();
+        let label = &diagnostics[0].labels[0];
+        assert_eq!((label.line, label.column), (2, 10));
+        assert_eq!(
+            diagnostics[0].code_frame.lines().collect::<Vec<_>>()[..2],
+            ["  1 | let a = 1;", "> 2 | const b: = 2;"]
+        );
+        assert!(diagnostics[0].code_frame.contains("\n    |          ^"));
+        assert!(diagnostics[0].render("a.ts").starts_with("a.ts:2:10: "));
+    }
+
+    #[tokio::test]
+    async fn test_script_transform_global() {
+        use rsquickjs::CatchResultExt;
+
+        crate::utils::test::test_sync_with(|ctx| {
+            super::init(&ctx)?;
+            let result = ctx
+                .eval::<String, _>(
+                    r#"
+                const { code, map } = scriptTransform("const a: number = 1;", "ts");
+                const [diagnostic] = scriptValidate("let a = 1;\nconst b: = 2;", "ts");
+                let error;
+                try {
+                    scriptTransform("let = ;", "js");
+                } catch (e) {
+                    error = e;
+                }
+                [
+                    typeof code,
+                    code.includes(": number"),
+                    JSON.parse(map).version,
+                    scriptValidate("let a = 1;").length,
+                    diagnostic.severity,
+                    diagnostic.labels[0].line,
+                    diagnostic.labels[0].column,
+                    error.name,
+                    error.diagnostics.length > 0,
+                    typeof error.codeFrame,
+                ].join()
+            "#,
+                )
+                .catch(&ctx)
+                .unwrap();
+            assert_eq!(
+                result,
+                "string,false,3,0,error,2,10,SyntaxError,true,string"
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[test]
+    fn test_declarations() {
+        let allocator = oxc::allocator::Allocator::default();
+        let source = "export function add(a: number, b: number): number { return a + b; }\nconst secret = 1;\n";
+        let program = super::parse("ts", source, &allocator).unwrap();
+        let dts = super::declarations(source, &allocator, &program).unwrap();
+        assert!(
+            dts.contains("export declare function add(a: number, b: number): number;"),
+            "{dts}"
+        );
+        assert!(!dts.contains("secret"), "{dts}");
+
+        let source = "export function add(a: number, b: number) { return a + b; }\n";
+        let program = super::parse("ts", source, &allocator).unwrap();
+        let diagnostics = super::declarations(source, &allocator, &program).unwrap_err();
+        assert_eq!(diagnostics[0].labels[0].line, 1);
+    }
+
+    #[test]
+    fn test_transform_options() {
+        let source = r#"
+        function dec(target: any) {}
+        @dec
+        class Greeter {}
+        export const name = globalThis.name ?? "world";
+        export const el = <b>{name}</b>;
+        "#;
+        let options = super::ScriptTransformOptions {
+            target: Some("es2019".into()),
+            decorators: super::Decorators::Legacy,
+            emit_decorator_metadata: false,
+            jsx: super::JsxOptions {
+                automatic: true,
+                import_source: Some("preact".into()),
+                ..Default::default()
+            },
+        };
+        let allocator = oxc::allocator::Allocator::default();
+        let ast = super::parse("tsx", source, &allocator).unwrap();
+        let r = super::transform("example.tsx", Some(&options), None, &allocator, ast)
+            .unwrap()
+            .code;
+        assert!(!r.contains("@dec"), "{r}");
+        assert!(!r.contains("??"), "{r}");
+        assert!(r.contains("preact/jsx-runtime"), "{r}");
+        let source_without_jsx = &source[..source.find("export const el").unwrap()];
+
+        let options = super::ScriptTransformOptions {
+            decorators: super::Decorators::Tc39,
+            ..Default::default()
+        };
+        let allocator = oxc::allocator::Allocator::default();
+        let ast = super::parse("ts", source_without_jsx, &allocator).unwrap();
+        let err = super::transform("a.ts", Some(&options), None, &allocator, ast).unwrap_err();
+        assert!(err.to_string().contains("a.ts:3"), "{err}");
+
+        let options = super::ScriptTransformOptions {
+            target: Some("es1".into()),
+            ..Default::default()
+        };
+        let allocator = oxc::allocator::Allocator::default();
+        let ast = super::parse("ts", "let a = 1;", &allocator).unwrap();
+        assert!(super::transform("a.ts", Some(&options), None, &allocator, ast).is_err());
+    }
+
+    #[test]
+    fn test_minify_options() {
+        let source = "function greet(name) { const message = 'hello ' + name; return message; }\nglobalThis.greet = greet;\n";
+        let minify = |minify: super::MinifyOptions| {
+            let allocator = oxc::allocator::Allocator::default();
+            let ast = super::parse("js", source, &allocator).unwrap();
+            super::transform("a.js", None, Some(minify), &allocator, ast)
+                .unwrap()
+                .code
+        };
+
+        let r = minify(super::MinifyOptions::default());
+        assert!(!r.contains("message"), "{r}");
+        let r = minify(super::MinifyOptions {
+            mangle: false,
+            compress: false,
+            ..Default::default()
+        });
+        assert!(r.contains("message"), "{r}");
+        let r = minify(super::MinifyOptions {
+            keep_names: true,
+            ..Default::default()
+        });
+        assert!(r.contains("greet"), "{r}");
+    }
+
+    #[test]
+    fn test_transform_cache() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let source = "const answer: number = 42;\nexport default answer;\n";
+
+        let output =
+            super::transform_cached_in(&dir, "/app/answer.ts", "ts", source, None, None).unwrap();
+        assert!(!output.code.contains(": number"));
+        assert!(output.map.as_ref().unwrap().contains("/app/answer.ts"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        // a hit is read back as it was written, other options are another entry
+        let cached =
+            super::transform_cached_in(&dir, "/app/answer.ts", "ts", source, None, None).unwrap();
+        assert_eq!(cached, output);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        super::transform_cached_in(
+            &dir,
+            "/app/answer.ts",
+            "ts",
+            source,
+            None,
+            Some(Default::default()),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 4);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_instrument_coverage() {
+        let source = "import a from 'a';\nfunction f(x) {\n  if (x) {\n    return 1;\n  }\n  return 2;\n}\nf(a);\n";
+        let (code, positions) = super::instrument_coverage(source).unwrap();
+        assert_eq!(positions, [(3, 3), (4, 5), (6, 3), (8, 1)]);
+        assert_eq!(code.lines().count(), source.lines().count());
+        assert!(code.contains("  __xmas_coverage[0]++;if (x) {"));
+        assert!(code.contains("__xmas_coverage[3]++;f(a);"));
     }
 }
@@ -20,14 +20,5 @@ pub fn init<'js>(ctx: &Ctx<'js>) -> Result<()> {
         ),
     )?;
 
-    globals.set(
-        "structuredClone",
-        Func::from(
-            |ctx: Ctx<'js>, value: rsquickjs::Value<'js>| -> Result<rsquickjs::Value<'js>> {
-                value.structured_clone(ctx)
-            },
-        ),
-    )?;
-
     Ok(())
 }
@@ -0,0 +1,209 @@
+//! `ShadowRealm`, from the TC39 proposal: code evaluated in a fresh realm with its
+//! own globals, for isolating plugins inside a single runtime.
+//!
+//! Only primitives and callables cross the boundary. A function is wrapped into one
+//! of the receiving realm, and whatever it throws is reported as a `TypeError` of the
+//! caller, so no object of one realm is ever reachable from the other.
+use rsquickjs::{
+    atom::PredefinedAtom, context::EvalOptions, prelude::Rest, CatchResultExt, CaughtError, Class,
+    Ctx, Exception, Function, Module, Object, Promise, Result, Value,
+};
+
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct ShadowRealm<'js> {
+    realm: Ctx<'js>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> ShadowRealm<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>) -> Result<Self> {
+        Ok(Self {
+            realm: ctx.new_realm()?,
+        })
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(ShadowRealm)
+    }
+
+    pub fn evaluate(&self, ctx: Ctx<'js>, source: Value<'js>) -> Result<Value<'js>> {
+        let Some(source) = source.as_string() else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "ShadowRealm.prototype.evaluate requires a string",
+            ));
+        };
+        let value = self
+            .realm
+            .eval_with_options::<Value, _>(
+                source.to_string()?,
+                EvalOptions {
+                    strict: false,
+                    filename: Some("<shadow-realm>".into()),
+                    ..Default::default()
+                },
+            )
+            .catch(&self.realm);
+        match value {
+            Ok(value) => wrap(&ctx, value),
+            Err(err) => Err(rethrow(&ctx, err)),
+        }
+    }
+
+    pub fn import_value(
+        &self,
+        ctx: Ctx<'js>,
+        specifier: String,
+        export_name: Value<'js>,
+    ) -> Result<Promise<'js>> {
+        let Some(export_name) = export_name.as_string() else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "ShadowRealm.prototype.importValue requires a string export name",
+            ));
+        };
+        let export_name = export_name.to_string()?;
+        let import = Module::import(&self.realm, specifier.as_str());
+        let realm = self.realm.clone();
+        let caller = ctx.clone();
+        Promise::wrap_future(&ctx, async move {
+            let namespace = match import {
+                Ok(import) => import.into_future::<Object>().await,
+                Err(err) => Err(err),
+            };
+            let namespace = namespace
+                .catch(&realm)
+                .map_err(|err| rethrow(&caller, err))?;
+            if !namespace.contains_key(export_name.as_str())? {
+                return Err(Exception::throw_type(
+                    &caller,
+                    &[&specifier, " has no export named ", &export_name].concat(),
+                ));
+            }
+            wrap(&caller, namespace.get(export_name.as_str())?)
+        })
+    }
+}
+
+/// Passes `value` into the realm of `ctx`, wrapping functions and refusing objects.
+fn wrap<'js>(ctx: &Ctx<'js>, value: Value<'js>) -> Result<Value<'js>> {
+    if !value.is_object() {
+        return Ok(value);
+    }
+    let Some(target) = value.as_function().cloned() else {
+        return Err(Exception::throw_type(
+            ctx,
+            "Only primitives and callables can cross a ShadowRealm boundary",
+        ));
+    };
+
+    let name = target.get::<_, Option<String>>("name")?.unwrap_or_default();
+    let length = target
+        .get::<_, Option<usize>>("length")?
+        .unwrap_or_default();
+    Function::new(
+        ctx.clone(),
+        move |caller: Ctx<'js>, args: Rest<Value<'js>>| -> Result<Value<'js>> {
+            let realm = target.ctx().clone();
+            let args = args
+                .0
+                .into_iter()
+                .map(|arg| wrap(&realm, arg))
+                .collect::<Result<Vec<_>>>()?;
+            match target.call::<_, Value>((Rest(args),)).catch(&realm) {
+                Ok(value) => wrap(&caller, value),
+                Err(err) => Err(rethrow(&caller, err)),
+            }
+        },
+    )?
+    .with_name(name)?
+    .with_length(length)
+    .map(Function::into_value)
+}
+
+/// Rethrows an error of the other realm as a `TypeError` of `ctx`, keeping a
+/// `SyntaxError` of `evaluate` recognisable.
+fn rethrow<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) -> rsquickjs::Error {
+    let (name, message) = match &err {
+        CaughtError::Exception(exception) => (
+            exception.get::<_, Option<String>>("name").ok().flatten(),
+            exception.message().unwrap_or_default(),
+        ),
+        _ => (None, err.to_string()),
+    };
+    match name.as_deref() {
+        Some("SyntaxError") => Exception::throw_syntax(ctx, &message),
+        Some(name) => Exception::throw_type(ctx, &[name, ": ", &message].concat()),
+        None => Exception::throw_type(ctx, &message),
+    }
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    Class::<ShadowRealm>::define(&ctx.globals())
+}
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::{CatchResultExt, Object, Promise};
+
+    use crate::utils::test::test_async_with;
+
+    #[tokio::test]
+    async fn test_shadow_realm() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                super::init(&ctx).unwrap();
+
+                let result: Object = ctx
+                    .eval(
+                        r#"
+                        globalThis.leaked = "outer";
+                        const realm = new ShadowRealm();
+                        const add = realm.evaluate("globalThis.count = 0; (a, b) => { count++; return a + b; }");
+                        const errors = [];
+                        for (const source of ["[]", "throw new RangeError('boom')", "("]) {
+                            try {
+                                realm.evaluate(source);
+                            } catch (err) {
+                                errors.push(err.constructor.name + ": " + err.message);
+                            }
+                        }
+                        ({
+                            sum: add(1, 2),
+                            count: realm.evaluate("count"),
+                            leaked: realm.evaluate("typeof leaked"),
+                            isolated: (realm.evaluate("Object.prototype.polluted = 1"), ({}).polluted === undefined),
+                            name: realm.evaluate("(function named(a) {})").name,
+                            calls: realm.evaluate("(f) => f(20)")((x) => x + 1),
+                            errors,
+                        })
+                    "#,
+                    )
+                    .catch(&ctx)
+                    .unwrap();
+
+                assert_eq!(result.get::<_, i32>("sum").unwrap(), 3);
+                assert_eq!(result.get::<_, i32>("count").unwrap(), 1);
+                assert_eq!(result.get::<_, String>("leaked").unwrap(), "undefined");
+                assert!(result.get::<_, bool>("isolated").unwrap());
+                assert_eq!(result.get::<_, String>("name").unwrap(), "named");
+                assert_eq!(result.get::<_, i32>("calls").unwrap(), 21);
+                let errors: Vec<String> = result.get("errors").unwrap();
+                assert_eq!(errors.len(), 3);
+                assert!(errors[0].starts_with("TypeError: "));
+                assert_eq!(errors[1], "TypeError: RangeError: boom");
+                assert!(errors[2].starts_with("SyntaxError: "));
+
+                let missing: Promise = ctx
+                    .eval("new ShadowRealm().importValue('./missing.js', 'value')")
+                    .catch(&ctx)
+                    .unwrap();
+                assert!(missing.into_future::<()>().await.catch(&ctx).is_err());
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,311 @@
+//! `xmas:shell` - runs commands with the cross platform shell used by `xmas exec`.
+//!
+//! Commands are parsed and run by deno_task_shell, so `&&`, pipes, redirects and
+//! builtins like `cp` or `rm` behave the same on every platform. Every call requires
+//! the `run` permission.
+
+use std::{collections::HashMap, ffi::OsString, future::pending, path::PathBuf, pin::pin};
+
+use deno_task_shell::{KillSignal, ShellPipeReader, ShellState, SignalKind};
+use rsquickjs::{
+    module::{Declarations, Exports, ModuleDef},
+    prelude::{Async, Func, Opt},
+    Class, Coerced, Ctx, Exception, Function, Object, Result, TypedArray, Undefined, Value,
+};
+use tokio::{select, sync::mpsc};
+
+use crate::{
+    abort::AbortSignal,
+    permissions::{check_run_permission, Callers},
+    utils::{
+        error::ErrorExtensions,
+        mc_oneshot,
+        module::{export_default, ModuleInfo},
+    },
+};
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Default)]
+struct ExecOptions<'js> {
+    cwd: Option<PathBuf>,
+    env: HashMap<OsString, OsString>,
+    abort_receiver: Option<mc_oneshot::Receiver<Value<'js>>>,
+    on_stdout: Option<Function<'js>>,
+    on_stderr: Option<Function<'js>>,
+}
+
+impl<'js> ExecOptions<'js> {
+    fn from_object(ctx: &Ctx<'js>, options: Option<Object<'js>>) -> Result<Self> {
+        let mut exec_options = Self::default();
+        let Some(options) = options else {
+            return Ok(exec_options);
+        };
+
+        exec_options.cwd = options.get::<_, Option<String>>("cwd")?.map(PathBuf::from);
+        if let Some(env) = options.get::<_, Option<Object>>("env")? {
+            for prop in env.props::<String, Coerced<String>>() {
+                let (key, value) = prop?;
+                exec_options.env.insert(key.into(), value.0.into());
+            }
+        }
+        if let Some(signal) = options.get::<_, Option<Class<AbortSignal>>>("signal")? {
+            let signal = signal.borrow();
+            if signal.aborted {
+                return Err(ctx.throw(
+                    signal
+                        .reason()
+                        .unwrap_or_else(|| Undefined.into_value(ctx.clone())),
+                ));
+            }
+            exec_options.abort_receiver = Some(signal.sender.subscribe());
+        }
+        exec_options.on_stdout = options.get("onStdout")?;
+        exec_options.on_stderr = options.get("onStderr")?;
+        Ok(exec_options)
+    }
+}
+
+/// The output of a running command, handed to the callbacks as it arrives
+struct Execution<'js> {
+    ctx: Ctx<'js>,
+    kill_signal: KillSignal,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    on_stdout: Option<Function<'js>>,
+    on_stderr: Option<Function<'js>>,
+    /// Why the command was killed, which the promise rejects with
+    failure: Option<Value<'js>>,
+}
+
+impl<'js> Execution<'js> {
+    fn push(&mut self, stream: Stream, chunk: Vec<u8>) {
+        let (output, callback) = match stream {
+            Stream::Stdout => (&mut self.stdout, &self.on_stdout),
+            Stream::Stderr => (&mut self.stderr, &self.on_stderr),
+        };
+        output.extend_from_slice(&chunk);
+        let Some(callback) = callback.clone() else {
+            return;
+        };
+        if self.failure.is_some() {
+            return;
+        }
+        let result = TypedArray::<u8>::new(self.ctx.clone(), chunk)
+            .and_then(|chunk| callback.call::<_, ()>((chunk,)));
+        if let Err(err) = result {
+            let reason = err
+                .into_value(&self.ctx)
+                .unwrap_or_else(|_| Undefined.into_value(self.ctx.clone()));
+            self.fail(reason);
+        }
+    }
+
+    /// Kill the command, rejecting with the first `reason`
+    fn fail(&mut self, reason: Value<'js>) {
+        self.kill_signal.send(SignalKind::SIGTERM);
+        self.failure.get_or_insert(reason);
+    }
+
+    fn into_result(self, code: i32) -> Result<Object<'js>> {
+        if let Some(reason) = self.failure {
+            return Err(self.ctx.throw(reason));
+        }
+        let result = Object::new(self.ctx.clone())?;
+        result.set("code", code)?;
+        result.set("stdout", String::from_utf8_lossy(&self.stdout).into_owned())?;
+        result.set("stderr", String::from_utf8_lossy(&self.stderr).into_owned())?;
+        Ok(result)
+    }
+}
+
+/// Run `command`, resolving to `{ code, stdout, stderr }` once it exits.
+///
+/// `onStdout` and `onStderr` receive the output as `Uint8Array` chunks while the
+/// command runs; if one throws, the command is killed and the promise rejects with
+/// the error. Aborting `signal` kills the command the same way.
+async fn exec<'js>(
+    ctx: Ctx<'js>,
+    callers: Callers,
+    command: String,
+    options: Opt<Object<'js>>,
+) -> Result<Object<'js>> {
+    if !check_run_permission(&ctx, &callers, &command) {
+        return Err(Exception::throw_message(
+            &ctx,
+            "Permission denied. Running shell commands is not allowed",
+        ));
+    }
+    let options = ExecOptions::from_object(&ctx, options.0)?;
+    let list = deno_task_shell::parser::parse(&command).map_err(|err| {
+        Exception::throw_syntax(
+            &ctx,
+            &["Invalid shell command: ", &err.to_string()].concat(),
+        )
+    })?;
+
+    let mut cwd = std::env::current_dir()?;
+    if let Some(dir) = options.cwd {
+        cwd = cwd.join(dir);
+    }
+    let mut env_vars = std::env::vars_os().collect::<HashMap<_, _>>();
+    env_vars.extend(options.env);
+
+    let kill_signal = KillSignal::default();
+    let state = ShellState::new(env_vars, cwd, HashMap::new(), kill_signal.clone());
+    // scripts have no terminal to hand over, so stdin is closed right away
+    let (stdin, _) = deno_task_shell::pipe();
+    let (stdout_reader, stdout_writer) = deno_task_shell::pipe();
+    let (stderr_reader, stderr_writer) = deno_task_shell::pipe();
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    read_pipe(Stream::Stdout, stdout_reader, sender.clone());
+    read_pipe(Stream::Stderr, stderr_reader, sender);
+
+    let mut execution = Execution {
+        ctx: ctx.clone(),
+        kill_signal,
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        on_stdout: options.on_stdout,
+        on_stderr: options.on_stderr,
+        failure: None,
+    };
+    let abort_receiver = options.abort_receiver;
+    let mut execute = pin!(deno_task_shell::execute_with_pipes(
+        list,
+        state,
+        stdin,
+        stdout_writer,
+        stderr_writer,
+    ));
+    let code = loop {
+        select! {
+            code = &mut execute => break code,
+            Some((stream, chunk)) = receiver.recv() => execution.push(stream, chunk),
+            reason = wait_for_abort(abort_receiver.as_ref()), if execution.failure.is_none() => {
+                execution.fail(reason)
+            }
+        }
+    };
+    // the readers finish once the last writer is dropped with the shell
+    while let Some((stream, chunk)) = receiver.recv().await {
+        execution.push(stream, chunk);
+    }
+
+    execution.into_result(code)
+}
+
+fn read_pipe(
+    stream: Stream,
+    mut reader: ShellPipeReader,
+    sender: mpsc::UnboundedSender<(Stream, Vec<u8>)>,
+) {
+    tokio::task::spawn_blocking(move || {
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        while let Ok(read @ 1..) = reader.read(&mut buf) {
+            if sender.send((stream, buf[..read].to_vec())).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+async fn wait_for_abort<'js>(receiver: Option<&mc_oneshot::Receiver<Value<'js>>>) -> Value<'js> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => pending().await,
+    }
+}
+
+pub struct ShellModule;
+
+impl ModuleDef for ShellModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("exec")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            default.set("exec", Func::from(Async(exec)))?;
+            Ok(())
+        })
+    }
+}
+
+impl From<ShellModule> for ModuleInfo<ShellModule> {
+    fn from(val: ShellModule) -> Self {
+        ModuleInfo {
+            name: "xmas:shell",
+            module: val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use xmas_vsys::{Permissions, Vsys};
+
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exec() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::abort::init(&ctx).unwrap();
+                crate::permissions::init(
+                    ctx.clone(),
+                    Arc::new(
+                        Vsys::builder()
+                            .permissions(Permissions::allow_all())
+                            .build(),
+                    ),
+                )
+                .unwrap();
+                ModuleEvaluator::eval_rust::<ShellModule>(ctx.clone(), "xmas:shell")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { exec } from 'xmas:shell';
+                        export async function test() {
+                            const chunks = [];
+                            const ok = await exec('echo $GREETING && echo oops 1>&2 && exit 3', {
+                                env: { GREETING: 'hello' },
+                                onStdout: (chunk) => chunks.push(chunk instanceof Uint8Array),
+                            });
+
+                            const controller = new AbortController();
+                            const aborted = await exec('echo ready && sleep 10', {
+                                signal: controller.signal,
+                                onStdout: () => controller.abort('stop'),
+                            }).catch((reason) => reason);
+
+                            return [ok.code, ok.stdout, ok.stderr, chunks.every(Boolean), aborted].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(result, "3|hello\n|oops\n|true|stop");
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,438 @@
+//! Source map support for runtime stack traces.
+//!
+//! Scripts produced by the bundler or the TypeScript transform are evaluated
+//! from generated code, so the engine reports positions in that output. Source
+//! maps registered through [`register_source_map`] are used to rewrite those
+//! positions back to the original file, line and column, both for
+//! `Error.prototype.stack` and for stacks printed by the host. Code evaluated
+//! with `EvalOptions::source_map` reaches them through [`source_map_handler`].
+use std::{cell::RefCell, collections::HashMap};
+
+use rsquickjs::{
+    atom::PredefinedAtom,
+    function::{Func, This},
+    prelude::Rest,
+    runtime::SourceMapHandler,
+    Array, CatchResultExt, Ctx, Function, JsLifetime, Object, Result, Value,
+};
+use simd_json::prelude::*;
+
+use crate::utils::result::ResultExt;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A single decoded mapping segment. Lines and columns are zero based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    generated_column: u32,
+    source: Option<(u32, u32, u32)>,
+}
+
+/// An original position resolved from a generated one. Lines and columns are
+/// one based, matching the positions reported in stack traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalPosition<'a> {
+    pub source: &'a str,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    /// Parses a version 3 source map.
+    pub fn parse(json: &str) -> std::result::Result<Self, String> {
+        let mut bytes = json.as_bytes().to_vec();
+        let map = simd_json::to_borrowed_value(&mut bytes).map_err(|e| e.to_string())?;
+
+        if map.get_u64("version") != Some(3) {
+            return Err("Unsupported source map version".into());
+        }
+        if map.get("sections").is_some() {
+            return Err("Indexed source maps are not supported".into());
+        }
+
+        let source_root = map.get_str("sourceRoot").unwrap_or_default();
+        let sources = map
+            .get_array("sources")
+            .map(|sources| {
+                sources
+                    .iter()
+                    .map(|source| {
+                        join_source_root(source_root, source.as_str().unwrap_or_default())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mappings = map
+            .get_str("mappings")
+            .ok_or_else(|| "Source map is missing mappings".to_string())?;
+
+        Ok(Self {
+            sources,
+            lines: decode_mappings(mappings)?,
+        })
+    }
+
+    /// Resolves a one based generated `line` and `column` to its original position.
+    pub fn lookup(&self, line: u32, column: u32) -> Option<OriginalPosition<'_>> {
+        let segments = self.lines.get(line.checked_sub(1)? as usize)?;
+        let column = column.saturating_sub(1);
+        let index = segments.partition_point(|segment| segment.generated_column <= column);
+        let (source, line, column) = segments.get(index.checked_sub(1)?)?.source?;
+
+        Some(OriginalPosition {
+            source: self.sources.get(source as usize)?,
+            line: line + 1,
+            column: column + 1,
+        })
+    }
+}
+
+fn join_source_root(root: &str, source: &str) -> String {
+    if root.is_empty() || source.starts_with('/') || source.contains("://") {
+        return source.into();
+    }
+    if root.ends_with('/') {
+        [root, source].concat()
+    } else {
+        [root, "/", source].concat()
+    }
+}
+
+fn decode_vlq(bytes: &mut std::iter::Peekable<std::slice::Iter<'_, u8>>) -> Option<i64> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.next()?;
+        let digit = BASE64_ALPHABET.iter().position(|c| *c == byte)? as i64;
+        result += (digit & 0x1f) << shift;
+        if digit & 0x20 == 0 {
+            break;
+        }
+        shift += 5;
+        if shift > 60 {
+            return None;
+        }
+    }
+    let value = result >> 1;
+    Some(if result & 1 == 1 { -value } else { value })
+}
+
+fn decode_mappings(mappings: &str) -> std::result::Result<Vec<Vec<Segment>>, String> {
+    let invalid = || "Invalid source map mappings".to_string();
+    let apply = |base: &mut i64, delta: i64| -> std::result::Result<u32, String> {
+        *base += delta;
+        u32::try_from(*base).map_err(|_| invalid())
+    };
+
+    let mut lines = Vec::new();
+    let (mut source, mut source_line, mut source_column) = (0i64, 0i64, 0i64);
+
+    for line in mappings.split(';') {
+        let mut segments = Vec::new();
+        let mut generated_column = 0i64;
+
+        for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+            let mut bytes = segment.as_bytes().iter().peekable();
+            let mut fields = [0i64; 5];
+            let mut count = 0;
+            while bytes.peek().is_some() {
+                if count == fields.len() {
+                    return Err(invalid());
+                }
+                fields[count] = decode_vlq(&mut bytes).ok_or_else(invalid)?;
+                count += 1;
+            }
+
+            let column = apply(&mut generated_column, fields[0])?;
+            let source = match count {
+                1 => None,
+                // The optional fifth field indexes `names`, which stack traces don't use
+                4 | 5 => Some((
+                    apply(&mut source, fields[1])?,
+                    apply(&mut source_line, fields[2])?,
+                    apply(&mut source_column, fields[3])?,
+                )),
+                _ => return Err(invalid()),
+            };
+            segments.push(Segment {
+                generated_column: column,
+                source,
+            });
+        }
+
+        segments.sort_by_key(|segment| segment.generated_column);
+        lines.push(segments);
+    }
+
+    Ok(lines)
+}
+
+#[derive(Default, JsLifetime)]
+struct SourceMaps(RefCell<HashMap<String, SourceMap>>);
+
+/// Registers the source map for the generated script `filename`, replacing any
+/// previously registered one.
+///
+/// The first registration installs an `Error.prepareStackTrace` hook, unless the
+/// script environment already defines one, so that `error.stack` reports
+/// original positions.
+pub fn register_source_map(ctx: &Ctx<'_>, filename: &str, json: &str) -> Result<()> {
+    let map = SourceMap::parse(json).or_throw_msg(ctx, "Invalid source map")?;
+    if ctx.userdata::<SourceMaps>().is_none() {
+        ctx.store_userdata(SourceMaps::default())?;
+        let error: Object = ctx.globals().get(PredefinedAtom::Error)?;
+        if error.get::<_, Value>("prepareStackTrace")?.is_undefined() {
+            error.set("prepareStackTrace", Func::from(prepare_stack_trace))?;
+        }
+    }
+    let maps = ctx.userdata::<SourceMaps>().or_throw(ctx)?;
+    maps.0.borrow_mut().insert(filename.into(), map);
+    Ok(())
+}
+
+/// Returns the handler to install with `set_source_map_handler`.
+///
+/// An invalid source map is reported and ignored rather than failing the evaluation.
+pub fn source_map_handler() -> SourceMapHandler {
+    Box::new(|ctx, filename, json| {
+        if let Err(err) = register_source_map(&ctx, filename, json).catch(&ctx) {
+            tracing::warn!("Ignoring source map for {}: {}", filename, err);
+        }
+        Ok(())
+    })
+}
+
+/// Resolves a generated position against the registered source maps, returning
+/// the original `(source, line, column)` when one is known.
+pub fn original_position(
+    ctx: &Ctx<'_>,
+    filename: &str,
+    line: u32,
+    column: u32,
+) -> Option<(String, u32, u32)> {
+    let maps = ctx.userdata::<SourceMaps>()?;
+    let maps = maps.0.borrow();
+    let position = maps.get(filename)?.lookup(line, column)?;
+    Some((position.source.into(), position.line, position.column))
+}
+
+/// Rewrites every `file:line:column` stack frame location in `stack` that has a
+/// registered source map.
+pub fn remap_stack(ctx: &Ctx<'_>, stack: &str) -> String {
+    if ctx.userdata::<SourceMaps>().is_none() {
+        return stack.into();
+    }
+    remap_stack_with(stack, |filename, line, column| {
+        original_position(ctx, filename, line, column)
+    })
+}
+
+fn remap_stack_with(
+    stack: &str,
+    resolve: impl Fn(&str, u32, u32) -> Option<(String, u32, u32)>,
+) -> String {
+    let mut result = String::with_capacity(stack.len());
+    for (index, line) in stack.split('\n').enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        match remap_frame(line, &resolve) {
+            Some(frame) => result.push_str(&frame),
+            None => result.push_str(line),
+        }
+    }
+    result
+}
+
+fn remap_frame(
+    frame: &str,
+    resolve: impl Fn(&str, u32, u32) -> Option<(String, u32, u32)>,
+) -> Option<String> {
+    let trimmed = frame.trim_start();
+    let rest = trimmed.strip_prefix("at ")?;
+    let indent = &frame[..frame.len() - trimmed.len()];
+
+    // Frames look like `at name (file:line:column)` or `at file:line:column`
+    let (prefix, location, suffix) = match rest.strip_suffix(')') {
+        Some(inner) => {
+            let open = inner.rfind(" (")?;
+            (&inner[..open + 2], &inner[open + 2..], ")")
+        }
+        None => ("", rest, ""),
+    };
+
+    let (location_without_column, column) = location.rsplit_once(':')?;
+    let (filename, line) = location_without_column.rsplit_once(':')?;
+    let (source, line, column) = resolve(filename, line.parse().ok()?, column.parse().ok()?)?;
+
+    Some(format!(
+        "{indent}at {prefix}{source}:{line}:{column}{suffix}"
+    ))
+}
+
+fn prepare_stack_trace<'js>(
+    ctx: Ctx<'js>,
+    _error: Value<'js>,
+    call_sites: Array<'js>,
+    _rest: Rest<Value<'js>>,
+) -> Result<String> {
+    let mut stack = String::new();
+    for call_site in call_sites.iter::<Object>() {
+        let call_site = call_site?;
+        let call = |name: &str| -> Result<Value<'js>> {
+            let function: Function = call_site.get(name)?;
+            function.call((This(call_site.clone()),))
+        };
+
+        let function_name = call("getFunctionName")?
+            .as_string()
+            .map(|s| s.to_string())
+            .transpose()?
+            .filter(|s| !s.is_empty());
+        let filename = call("getFileName")?
+            .as_string()
+            .map(|s| s.to_string())
+            .transpose()?;
+        let line = call("getLineNumber")?.as_number().unwrap_or_default() as u32;
+        let column = call("getColumnNumber")?.as_number().unwrap_or_default() as u32;
+
+        let location = match filename {
+            Some(filename) if line > 0 => match original_position(&ctx, &filename, line, column) {
+                Some((source, line, column)) => format!("{source}:{line}:{column}"),
+                None => format!("{filename}:{line}:{column}"),
+            },
+            Some(filename) => filename,
+            None => "native".into(),
+        };
+
+        stack.push_str("    at ");
+        match function_name {
+            Some(name) => {
+                stack.push_str(&name);
+                stack.push_str(" (");
+                stack.push_str(&location);
+                stack.push(')');
+            }
+            None => stack.push_str(&location),
+        }
+        stack.push('\n');
+    }
+    Ok(stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::context::EvalOptions;
+
+    use super::*;
+
+    // A bundle with one line of prelude, mapping generated lines 2 and 3 to
+    // lines 1 and 2 of `src/index.ts`.
+    const MAP: &str = r#"{
+        "version": 3,
+        "sources": ["src/index.ts"],
+        "names": [],
+        "mappings": ";AAAA,IAAI;AACJ,MAAM"
+    }"#;
+
+    #[test]
+    fn test_decode_vlq() {
+        let decode = |s: &str| decode_vlq(&mut s.as_bytes().iter().peekable());
+        assert_eq!(decode("A"), Some(0));
+        assert_eq!(decode("C"), Some(1));
+        assert_eq!(decode("D"), Some(-1));
+        assert_eq!(decode("gB"), Some(16));
+        assert_eq!(decode("2H"), Some(123));
+        assert_eq!(decode("!"), None);
+    }
+
+    #[test]
+    fn test_source_map_lookup() {
+        let map = SourceMap::parse(MAP).unwrap();
+        assert_eq!(map.lookup(1, 1), None);
+        assert_eq!(
+            map.lookup(2, 6),
+            Some(OriginalPosition {
+                source: "src/index.ts",
+                line: 1,
+                column: 5,
+            })
+        );
+        assert_eq!(
+            map.lookup(3, 7),
+            Some(OriginalPosition {
+                source: "src/index.ts",
+                line: 2,
+                column: 7,
+            })
+        );
+        assert_eq!(map.lookup(4, 1), None);
+        assert!(SourceMap::parse(r#"{"version":2,"mappings":""}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eval_with_source_map() {
+        let (rt, ctx) = crate::utils::test::given_runtime().await;
+        rt.set_source_map_handler(Some(source_map_handler())).await;
+        ctx.with(|ctx| {
+            ctx.eval_with_options::<(), _>(
+                "void 0;\nvoid 1;\nvoid 2;",
+                EvalOptions {
+                    filename: Some("index.js".into()),
+                    source_map: Some(MAP.into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                original_position(&ctx, "index.js", 3, 7),
+                Some(("src/index.ts".into(), 2, 7))
+            );
+            let error: Object = ctx.globals().get(PredefinedAtom::Error).unwrap();
+            assert!(error
+                .get::<_, Value>("prepareStackTrace")
+                .unwrap()
+                .is_function());
+
+            // a broken map doesn't keep the code from running
+            let value: i32 = ctx
+                .eval_with_options(
+                    "1 + 1",
+                    EvalOptions {
+                        filename: Some("broken.js".into()),
+                        source_map: Some("{".into()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(value, 2);
+            assert_eq!(original_position(&ctx, "broken.js", 1, 1), None);
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_remap_stack() {
+        let map = SourceMap::parse(MAP).unwrap();
+        let resolve = |filename: &str, line, column| {
+            if filename != "index.js" {
+                return None;
+            }
+            let position = map.lookup(line, column)?;
+            Some((position.source.to_string(), position.line, position.column))
+        };
+        let stack =
+            "    at main (index.js:3:7)\n    at index.js:2:6\n    at <eval> (other.js:1:1)\n";
+        assert_eq!(
+            remap_stack_with(stack, resolve),
+            "    at main (src/index.ts:2:7)\n    at src/index.ts:1:5\n    at <eval> (other.js:1:1)\n"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, path::Path, rc::Rc, time::Duration};
+
+use rsquickjs::{atom::PredefinedAtom, prelude::Opt, Ctx, Exception, Object, Result, Value};
+use rusqlite::{Connection, OpenFlags};
+
+use super::{statement::StatementSync, throw_sqlite_error};
+use crate::{
+    permissions::{check_fs_permission, check_fs_read_permission},
+    utils::{object::ObjectExt, result::ResultExt},
+};
+
+/// A connection shared between a database and the statements prepared from it.
+pub type SharedConnection = Rc<RefCell<Option<Connection>>>;
+
+/// Runs `f` against the open connection, throwing if the database was closed.
+pub fn with_connection<R>(
+    ctx: &Ctx<'_>,
+    connection: &SharedConnection,
+    f: impl FnOnce(&Connection) -> Result<R>,
+) -> Result<R> {
+    let connection = connection.borrow();
+    let connection = connection
+        .as_ref()
+        .or_throw_msg(ctx, "database is not open")?;
+    f(connection)
+}
+
+fn is_memory(path: &str) -> bool {
+    path.is_empty() || path == ":memory:"
+}
+
+/// A synchronous SQLite database handle, as in `node:sqlite`.
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct DatabaseSync {
+    #[qjs(skip_trace)]
+    connection: SharedConnection,
+    path: String,
+    read_only: bool,
+    foreign_keys: bool,
+    timeout: Option<u64>,
+}
+
+impl DatabaseSync {
+    fn open_connection(&self, ctx: &Ctx<'_>) -> Result<Connection> {
+        let check = if self.read_only {
+            check_fs_read_permission
+        } else {
+            check_fs_permission
+        };
+        if !is_memory(&self.path) && !check(ctx, Path::new(&self.path)) {
+            return Err(Exception::throw_message(
+                ctx,
+                "Permission denied. Cannot access the file",
+            ));
+        }
+
+        let mut flags = OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        if self.read_only {
+            flags |= OpenFlags::SQLITE_OPEN_READ_ONLY;
+        } else {
+            flags |= OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+        }
+        let path = if is_memory(&self.path) {
+            ":memory:"
+        } else {
+            &self.path
+        };
+
+        let connection =
+            Connection::open_with_flags(path, flags).map_err(|err| throw_sqlite_error(ctx, err))?;
+        connection
+            .pragma_update(None, "foreign_keys", self.foreign_keys)
+            .map_err(|err| throw_sqlite_error(ctx, err))?;
+        if let Some(timeout) = self.timeout {
+            connection
+                .busy_timeout(Duration::from_millis(timeout))
+                .map_err(|err| throw_sqlite_error(ctx, err))?;
+        }
+        Ok(connection)
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl DatabaseSync {
+    #[qjs(constructor)]
+    pub fn new<'js>(ctx: Ctx<'js>, path: String, options: Opt<Object<'js>>) -> Result<Self> {
+        let options = options.0;
+        let option = |name: &str| -> Result<Option<Value<'js>>> {
+            match &options {
+                Some(options) => options.get_optional(name),
+                None => Ok(None),
+            }
+        };
+        let flag = |name: &str, default: bool| -> Result<bool> {
+            Ok(option(name)?
+                .and_then(|value| value.as_bool())
+                .unwrap_or(default))
+        };
+
+        let database = Self {
+            connection: Rc::new(RefCell::new(None)),
+            path,
+            read_only: flag("readOnly", false)?,
+            foreign_keys: flag("enableForeignKeyConstraints", true)?,
+            timeout: option("timeout")?
+                .and_then(|value| value.as_number())
+                .filter(|timeout| *timeout >= 0.0)
+                .map(|timeout| timeout as u64),
+        };
+        if flag("open", true)? {
+            database.open(ctx)?;
+        }
+        Ok(database)
+    }
+
+    pub fn open(&self, ctx: Ctx<'_>) -> Result<()> {
+        if self.connection.borrow().is_some() {
+            return Err(Exception::throw_message(&ctx, "database is already open"));
+        }
+        let connection = self.open_connection(&ctx)?;
+        self.connection.replace(Some(connection));
+        Ok(())
+    }
+
+    pub fn close(&self, ctx: Ctx<'_>) -> Result<()> {
+        let connection = self
+            .connection
+            .take()
+            .or_throw_msg(&ctx, "database is not open")?;
+        connection
+            .close()
+            .map_err(|(_, err)| throw_sqlite_error(&ctx, err))
+    }
+
+    pub fn exec(&self, ctx: Ctx<'_>, sql: String) -> Result<()> {
+        with_connection(&ctx, &self.connection, |connection| {
+            connection
+                .execute_batch(&sql)
+                .map_err(|err| throw_sqlite_error(&ctx, err))
+        })
+    }
+
+    pub fn prepare(&self, ctx: Ctx<'_>, sql: String) -> Result<StatementSync> {
+        with_connection(&ctx, &self.connection, |connection| {
+            connection
+                .prepare_cached(&sql)
+                .map_err(|err| throw_sqlite_error(&ctx, err))?;
+            Ok(())
+        })?;
+        Ok(StatementSync::new(self.connection.clone(), sql))
+    }
+
+    pub fn location<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        with_connection(&ctx, &self.connection, |_| Ok(()))?;
+        if is_memory(&self.path) {
+            return Ok(Value::new_null(ctx));
+        }
+        Ok(rsquickjs::String::from_str(ctx, &self.path)?.into_value())
+    }
+
+    #[qjs(get)]
+    pub fn is_open(&self) -> bool {
+        self.connection.borrow().is_some()
+    }
+
+    #[qjs(get)]
+    pub fn is_transaction(&self, ctx: Ctx<'_>) -> Result<bool> {
+        with_connection(&ctx, &self.connection, |connection| {
+            Ok(!connection.is_autocommit())
+        })
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "DatabaseSync"
+    }
+}
@@ -0,0 +1,243 @@
+//! `node:sqlite` (also available as `xmas:sqlite`), backed by a bundled SQLite.
+
+mod database;
+mod statement;
+
+use rsquickjs::{
+    module::{Declarations, Exports, ModuleDef},
+    BigInt, Class, Ctx, Error, Exception, Object, Result, TypedArray, Value,
+};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+
+use self::{database::DatabaseSync, statement::StatementSync};
+use crate::utils::{
+    bytes::ObjectBytes,
+    module::{export_default, ModuleInfo},
+    primordials::{BasePrimordials, Primordial},
+};
+
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Throws `err` as an `Error` carrying node's `ERR_SQLITE_ERROR` code.
+pub fn throw_sqlite_error(ctx: &Ctx<'_>, err: rusqlite::Error) -> Error {
+    let (errcode, errstr) = match &err {
+        rusqlite::Error::SqliteFailure(failure, _) => (
+            failure.extended_code,
+            rusqlite::ffi::code_to_str(failure.extended_code),
+        ),
+        _ => (rusqlite::ffi::SQLITE_ERROR, "SQL logic error"),
+    };
+
+    let create = || -> Result<Value<'_>> {
+        let primordials = BasePrimordials::get(ctx)?;
+        let error: Object = primordials
+            .constructor_error
+            .construct((err.to_string(),))?;
+        error.set("code", "ERR_SQLITE_ERROR")?;
+        error.set("errcode", errcode)?;
+        error.set("errstr", errstr)?;
+        Ok(error.into_value())
+    };
+    match create() {
+        Ok(error) => ctx.throw(error),
+        Err(err) => err,
+    }
+}
+
+/// Converts a JavaScript value to something that can be bound to a statement parameter.
+pub fn to_sql<'js>(ctx: &Ctx<'js>, value: &Value<'js>, index: usize) -> Result<SqlValue> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(SqlValue::Null);
+    }
+    if let Some(value) = value.as_int() {
+        return Ok(SqlValue::Integer(value as i64));
+    }
+    if let Some(value) = value.as_number() {
+        if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER as f64 {
+            return Ok(SqlValue::Integer(value as i64));
+        }
+        return Ok(SqlValue::Real(value));
+    }
+    if let Some(value) = value.as_big_int() {
+        return Ok(SqlValue::Integer(value.clone().to_i64()?));
+    }
+    if let Some(value) = value.as_bool() {
+        return Ok(SqlValue::Integer(value as i64));
+    }
+    if let Some(value) = value.as_string() {
+        return Ok(SqlValue::Text(value.to_string()?));
+    }
+    if let Some(object) = value.as_object() {
+        if let Some(bytes) = ObjectBytes::from_array_buffer(object)? {
+            return Ok(SqlValue::Blob(bytes.into_bytes(ctx)?));
+        }
+    }
+    Err(Exception::throw_type(
+        ctx,
+        &format!("Provided value cannot be bound to SQLite parameter {index}."),
+    ))
+}
+
+/// Converts a column value to JavaScript, as `node:sqlite` does.
+pub fn from_sql<'js>(
+    ctx: &Ctx<'js>,
+    value: ValueRef<'_>,
+    read_big_ints: bool,
+) -> Result<Value<'js>> {
+    Ok(match value {
+        ValueRef::Null => Value::new_null(ctx.clone()),
+        ValueRef::Integer(value) if read_big_ints => Value::new_big_int(ctx.clone(), value),
+        ValueRef::Integer(value) => integer_to_js(ctx, value)?,
+        ValueRef::Real(value) => Value::new_float(ctx.clone(), value),
+        ValueRef::Text(text) => {
+            rsquickjs::String::from_str(ctx.clone(), &String::from_utf8_lossy(text))?.into_value()
+        }
+        ValueRef::Blob(bytes) => TypedArray::<u8>::new(ctx.clone(), bytes)?.into_value(),
+    })
+}
+
+pub fn integer_to_js<'js>(ctx: &Ctx<'js>, value: i64) -> Result<Value<'js>> {
+    if value.abs() > MAX_SAFE_INTEGER {
+        return Err(Exception::throw_range(
+            ctx,
+            &format!("Value is too large to be represented as a JavaScript number: {value}"),
+        ));
+    }
+    Ok(Value::new_number(ctx.clone(), value as f64))
+}
+
+pub fn row_id_to_js<'js>(ctx: &Ctx<'js>, value: i64, read_big_ints: bool) -> Result<Value<'js>> {
+    if read_big_ints {
+        return Ok(BigInt::from_i64(ctx.clone(), value)?.into_value());
+    }
+    integer_to_js(ctx, value)
+}
+
+pub struct SqliteModule;
+
+impl ModuleDef for SqliteModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare(stringify!(DatabaseSync))?;
+        declare.declare(stringify!(StatementSync))?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            Class::<DatabaseSync>::define(default)?;
+            Class::<StatementSync>::define(default)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<SqliteModule> for ModuleInfo<SqliteModule> {
+    fn from(val: SqliteModule) -> Self {
+        ModuleInfo {
+            name: "sqlite",
+            module: val,
+        }
+    }
+}
+
+/// `xmas:sqlite`, an alias of `node:sqlite`.
+pub struct XmasSqliteModule;
+
+impl ModuleDef for XmasSqliteModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        SqliteModule::declare(declare)
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        SqliteModule::evaluate(ctx, exports)
+    }
+}
+
+impl From<XmasSqliteModule> for ModuleInfo<XmasSqliteModule> {
+    fn from(val: XmasSqliteModule) -> Self {
+        ModuleInfo {
+            name: "xmas:sqlite",
+            module: val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sqlite() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                crate::permissions::init(ctx.clone(), Arc::new(xmas_vsys::Vsys::new())).unwrap();
+                ModuleEvaluator::eval_rust::<SqliteModule>(ctx.clone(), "sqlite")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { DatabaseSync } from 'sqlite';
+                        export async function test() {
+                            const db = new DatabaseSync(':memory:');
+                            db.exec('CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score REAL, data BLOB)');
+
+                            const insert = db.prepare('INSERT INTO t (name, score, data) VALUES (?, ?, ?)');
+                            const first = insert.run('alice', 1.5, new Uint8Array([1, 2]));
+                            insert.run('bob', 2, null);
+
+                            db.exec('BEGIN');
+                            const inTransaction = db.isTransaction;
+                            db.prepare('INSERT INTO t (name) VALUES ($name)').run({ name: 'carol' });
+                            db.exec('ROLLBACK');
+
+                            const rows = db.prepare('SELECT * FROM t ORDER BY id').all();
+                            const named = db.prepare('SELECT name FROM t WHERE id = :id').get({ ':id': 2 });
+                            const missing = db.prepare('SELECT name FROM t WHERE id = ?').get(99);
+
+                            const count = db.prepare('SELECT count(*) AS n FROM t');
+                            count.setReadBigInts(true);
+                            const countType = typeof count.get().n;
+
+                            let error;
+                            try {
+                                db.exec('SELECT * FROM nope');
+                            } catch (e) {
+                                error = e.code;
+                            }
+                            db.close();
+
+                            return [
+                                first.changes,
+                                first.lastInsertRowid,
+                                inTransaction,
+                                rows.map((row) => `${row.id}:${row.name}:${row.score}:${row.data}`).join(','),
+                                named.name,
+                                missing,
+                                countType,
+                                error,
+                                db.isOpen,
+                            ].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "1|1|true|1:alice:1.5:1,2,2:bob:2:null|bob||bigint|ERR_SQLITE_ERROR|false"
+                );
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,199 @@
+use rsquickjs::{
+    atom::PredefinedAtom,
+    prelude::{Rest, This},
+    Array, Ctx, Exception, Function, Object, Result, Value,
+};
+use rusqlite::Statement;
+
+use super::{
+    database::{with_connection, SharedConnection},
+    from_sql, row_id_to_js, throw_sqlite_error, to_sql,
+};
+use crate::utils::{bytes::ObjectBytes, object::ObjectExt, result::ResultExt};
+
+/// A statement prepared with `DatabaseSync.prototype.prepare`.
+///
+/// Only the SQL is kept; the compiled statement lives in the connection's
+/// statement cache, so it never outlives a closed database.
+#[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct StatementSync {
+    #[qjs(skip_trace)]
+    connection: SharedConnection,
+    source: String,
+    read_big_ints: bool,
+    allow_bare_named_parameters: bool,
+}
+
+impl StatementSync {
+    pub fn new(connection: SharedConnection, source: String) -> Self {
+        Self {
+            connection,
+            source,
+            read_big_ints: false,
+            allow_bare_named_parameters: true,
+        }
+    }
+
+    /// Binds positional values, or a leading object of named values, to `statement`.
+    fn bind<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        statement: &mut Statement<'_>,
+        params: Vec<Value<'js>>,
+    ) -> Result<()> {
+        let mut params = params.into_iter().peekable();
+        let named = match params.peek() {
+            Some(value) if is_named_parameters(value)? => {
+                params.next().and_then(Value::into_object)
+            }
+            _ => None,
+        };
+
+        for index in 1..=statement.parameter_count() {
+            let value = match statement.parameter_name(index) {
+                Some(name) if !name.starts_with('?') => {
+                    let named = named
+                        .as_ref()
+                        .or_throw_type(ctx, "Named parameters require an object argument")?;
+                    let value = match named.get_optional::<_, Value>(name)? {
+                        Some(value) => Some(value),
+                        None if self.allow_bare_named_parameters => {
+                            named.get_optional(&name[1..])?
+                        }
+                        None => None,
+                    };
+                    value.or_throw_type(
+                        ctx,
+                        &["Missing named parameter \"", &name[1..], "\""].concat(),
+                    )?
+                }
+                _ => params
+                    .next()
+                    .unwrap_or_else(|| Value::new_undefined(ctx.clone())),
+            };
+            statement
+                .raw_bind_parameter(index, to_sql(ctx, &value, index)?)
+                .map_err(|err| throw_sqlite_error(ctx, err))?;
+        }
+        Ok(())
+    }
+
+    /// Runs the statement and collects up to `limit` rows as objects.
+    fn query<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        params: Vec<Value<'js>>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Object<'js>>> {
+        with_connection(ctx, &self.connection, |connection| {
+            let mut statement = connection
+                .prepare_cached(&self.source)
+                .map_err(|err| throw_sqlite_error(ctx, err))?;
+            self.bind(ctx, &mut statement, params)?;
+
+            let columns: Vec<String> = statement
+                .column_names()
+                .into_iter()
+                .map(String::from)
+                .collect();
+            let mut rows = statement.raw_query();
+            let mut result = Vec::new();
+            while let Some(row) = rows.next().map_err(|err| throw_sqlite_error(ctx, err))? {
+                let object = Object::new(ctx.clone())?;
+                for (index, name) in columns.iter().enumerate() {
+                    let value = row
+                        .get_ref(index)
+                        .map_err(|err| throw_sqlite_error(ctx, err))?;
+                    object.set(name.as_str(), from_sql(ctx, value, self.read_big_ints)?)?;
+                }
+                result.push(object);
+                if limit.is_some_and(|limit| result.len() >= limit) {
+                    break;
+                }
+            }
+            Ok(result)
+        })
+    }
+}
+
+fn is_named_parameters(value: &Value<'_>) -> Result<bool> {
+    let Some(object) = value.as_object() else {
+        return Ok(false);
+    };
+    Ok(!object.is_array() && ObjectBytes::from_array_buffer(object)?.is_none())
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl StatementSync {
+    #[qjs(constructor)]
+    pub fn constructor(ctx: Ctx<'_>) -> Result<Self> {
+        Err(Exception::throw_type(&ctx, "Illegal constructor"))
+    }
+
+    pub fn run<'js>(&self, ctx: Ctx<'js>, params: Rest<Value<'js>>) -> Result<Object<'js>> {
+        let (changes, last_insert_rowid) = with_connection(&ctx, &self.connection, |connection| {
+            let mut statement = connection
+                .prepare_cached(&self.source)
+                .map_err(|err| throw_sqlite_error(&ctx, err))?;
+            self.bind(&ctx, &mut statement, params.0)?;
+
+            let mut rows = statement.raw_query();
+            while rows
+                .next()
+                .map_err(|err| throw_sqlite_error(&ctx, err))?
+                .is_some()
+            {}
+            Ok((connection.changes(), connection.last_insert_rowid()))
+        })?;
+
+        let result = Object::new(ctx.clone())?;
+        result.set(
+            "changes",
+            row_id_to_js(&ctx, changes as i64, self.read_big_ints)?,
+        )?;
+        result.set(
+            "lastInsertRowid",
+            row_id_to_js(&ctx, last_insert_rowid, self.read_big_ints)?,
+        )?;
+        Ok(result)
+    }
+
+    pub fn get<'js>(&self, ctx: Ctx<'js>, params: Rest<Value<'js>>) -> Result<Value<'js>> {
+        Ok(match self.query(&ctx, params.0, Some(1))?.pop() {
+            Some(row) => row.into_value(),
+            None => Value::new_undefined(ctx),
+        })
+    }
+
+    pub fn all<'js>(&self, ctx: Ctx<'js>, params: Rest<Value<'js>>) -> Result<Vec<Object<'js>>> {
+        self.query(&ctx, params.0, None)
+    }
+
+    pub fn iterate<'js>(&self, ctx: Ctx<'js>, params: Rest<Value<'js>>) -> Result<Value<'js>> {
+        let rows = Array::new(ctx.clone())?;
+        for (index, row) in self.query(&ctx, params.0, None)?.into_iter().enumerate() {
+            rows.set(index, row)?;
+        }
+        let values: Function = rows.get("values")?;
+        values.call((This(rows),))
+    }
+
+    pub fn set_read_big_ints(&mut self, enabled: bool) {
+        self.read_big_ints = enabled;
+    }
+
+    pub fn set_allow_bare_named_parameters(&mut self, enabled: bool) {
+        self.allow_bare_named_parameters = enabled;
+    }
+
+    #[qjs(get, rename = "sourceSQL")]
+    pub fn source_sql(&self) -> String {
+        self.source.clone()
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "StatementSync"
+    }
+}
@@ -0,0 +1,602 @@
+//! [Structured clone](https://html.spec.whatwg.org/multipage/structured-data.html) support.
+//!
+//! Values are serialized into a [`StructuredData`] tree that owns all of its data and does not
+//! reference the originating context, so it can be moved to another runtime (e.g. a worker)
+//! before being deserialized again. [`post_message`] sends it over a [`channel`] for that.
+//!
+//! [`channel`]: rsquickjs::channel::channel
+use std::collections::HashMap;
+
+use rsquickjs::{
+    atom::PredefinedAtom,
+    channel::Sender,
+    function::{Constructor, Func, Opt, This},
+    Array, ArrayBuffer, Class, Coerced, Ctx, Function, Object, Result, Type, Value,
+};
+
+use crate::{
+    exceptions::{DOMException, DOMExceptionName},
+    utils::primordials::{BasePrimordials, Primordial},
+};
+
+const ERROR_NAMES: [&str; 7] = [
+    "Error",
+    "EvalError",
+    "RangeError",
+    "ReferenceError",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+];
+
+/// A serialized JavaScript value. Objects are stored by index into [`StructuredData`] so shared
+/// and circular references survive a round trip.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializedValue {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    BigInt(String),
+    String(String),
+    Object(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrayBufferViewKind {
+    Int8Array,
+    Uint8Array,
+    Uint8ClampedArray,
+    Int16Array,
+    Uint16Array,
+    Int32Array,
+    Uint32Array,
+    Float32Array,
+    Float64Array,
+    BigInt64Array,
+    BigUint64Array,
+    DataView,
+}
+
+impl ArrayBufferViewKind {
+    const ALL: [Self; 12] = [
+        Self::Int8Array,
+        Self::Uint8Array,
+        Self::Uint8ClampedArray,
+        Self::Int16Array,
+        Self::Uint16Array,
+        Self::Int32Array,
+        Self::Uint32Array,
+        Self::Float32Array,
+        Self::Float64Array,
+        Self::BigInt64Array,
+        Self::BigUint64Array,
+        Self::DataView,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Int8Array => "Int8Array",
+            Self::Uint8Array => "Uint8Array",
+            Self::Uint8ClampedArray => "Uint8ClampedArray",
+            Self::Int16Array => "Int16Array",
+            Self::Uint16Array => "Uint16Array",
+            Self::Int32Array => "Int32Array",
+            Self::Uint32Array => "Uint32Array",
+            Self::Float32Array => "Float32Array",
+            Self::Float64Array => "Float64Array",
+            Self::BigInt64Array => "BigInt64Array",
+            Self::BigUint64Array => "BigUint64Array",
+            Self::DataView => "DataView",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == tag)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializedObject {
+    Object(Vec<(String, SerializedValue)>),
+    Array {
+        length: u32,
+        entries: Vec<(String, SerializedValue)>,
+    },
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Date(f64),
+    RegExp {
+        source: String,
+        flags: String,
+    },
+    Map(Vec<(SerializedValue, SerializedValue)>),
+    Set(Vec<SerializedValue>),
+    ArrayBuffer(Vec<u8>),
+    ArrayBufferView {
+        kind: ArrayBufferViewKind,
+        /// Index of the backing [`SerializedObject::ArrayBuffer`]
+        buffer: usize,
+        byte_offset: usize,
+        /// Element count, or byte length for a `DataView`
+        length: usize,
+    },
+    Error {
+        name: &'static str,
+        message: Option<String>,
+        stack: Option<String>,
+        cause: Option<SerializedValue>,
+    },
+}
+
+/// Context independent result of serializing a value with the structured clone algorithm.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructuredData {
+    root: SerializedValue,
+    objects: Vec<SerializedObject>,
+}
+
+impl StructuredData {
+    /// Serializes `value`, throwing a `DataCloneError` for values that can't be cloned.
+    pub fn serialize<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<Self> {
+        let primordials = BasePrimordials::get(ctx)?;
+        let mut serializer = Serializer {
+            ctx,
+            primordials: &primordials,
+            memory: HashMap::new(),
+            objects: Vec::new(),
+        };
+        let root = serializer.serialize(value)?;
+        Ok(Self {
+            root,
+            objects: serializer.objects,
+        })
+    }
+
+    /// Recreates the serialized value in `ctx`.
+    pub fn deserialize<'js>(&self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        let primordials = BasePrimordials::get(ctx)?;
+        let globals = ctx.globals();
+        let mut values = Vec::with_capacity(self.objects.len());
+
+        // containers are created empty first so cyclic references can be resolved when filling
+        for object in &self.objects {
+            let value = match object {
+                SerializedObject::Object(_) => Object::new(ctx.clone())?.into_value(),
+                SerializedObject::Array { length, .. } => {
+                    let array = Array::new(ctx.clone())?;
+                    array.set(PredefinedAtom::Length, *length)?;
+                    array.into_value()
+                }
+                SerializedObject::Boolean(value) => {
+                    primordials.constructor_object.call((*value,))?
+                }
+                SerializedObject::Number(value) => {
+                    primordials.constructor_object.call((*value,))?
+                }
+                SerializedObject::String(value) => {
+                    primordials.constructor_object.call((value.as_str(),))?
+                }
+                SerializedObject::Date(time) => primordials.constructor_date.construct((*time,))?,
+                SerializedObject::RegExp { source, flags } => primordials
+                    .constructor_regexp
+                    .construct((source.as_str(), flags.as_str()))?,
+                SerializedObject::Map(_) => primordials.constructor_map.construct(())?,
+                SerializedObject::Set(_) => primordials.constructor_set.construct(())?,
+                SerializedObject::ArrayBuffer(bytes) => {
+                    ArrayBuffer::new(ctx.clone(), bytes.clone())?.into_value()
+                }
+                SerializedObject::ArrayBufferView { .. } => Value::new_undefined(ctx.clone()),
+                SerializedObject::Error {
+                    name,
+                    message,
+                    stack,
+                    ..
+                } => {
+                    let constructor: Constructor = globals.get(*name)?;
+                    let error: Object = match message {
+                        Some(message) => constructor.construct((message.as_str(),))?,
+                        None => constructor.construct(())?,
+                    };
+                    if let Some(stack) = stack {
+                        error.set(PredefinedAtom::Stack, stack.as_str())?;
+                    }
+                    error.into_value()
+                }
+            };
+            values.push(value);
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            if let SerializedObject::ArrayBufferView {
+                kind,
+                buffer,
+                byte_offset,
+                length,
+            } = object
+            {
+                let constructor: Constructor = globals.get(kind.as_str())?;
+                values[index] =
+                    constructor.construct((values[*buffer].clone(), *byte_offset, *length))?;
+            }
+        }
+
+        let resolve = |value: &SerializedValue| -> Result<Value<'js>> {
+            Ok(match value {
+                SerializedValue::Object(index) => values[*index].clone(),
+                value => deserialize_primitive(ctx, value)?,
+            })
+        };
+        for (object, value) in self.objects.iter().zip(&values) {
+            match object {
+                SerializedObject::Object(entries) | SerializedObject::Array { entries, .. } => {
+                    let target = value.as_object().unwrap();
+                    for (key, value) in entries {
+                        target.set(key.as_str(), resolve(value)?)?;
+                    }
+                }
+                SerializedObject::Map(entries) => {
+                    let set: Function = primordials.prototype_map.get("set")?;
+                    for (key, entry) in entries {
+                        set.call::<_, ()>((This(value.clone()), resolve(key)?, resolve(entry)?))?;
+                    }
+                }
+                SerializedObject::Set(entries) => {
+                    let add: Function = primordials.prototype_set.get("add")?;
+                    for entry in entries {
+                        add.call::<_, ()>((This(value.clone()), resolve(entry)?))?;
+                    }
+                }
+                SerializedObject::Error {
+                    cause: Some(cause), ..
+                } => {
+                    value.as_object().unwrap().set("cause", resolve(cause)?)?;
+                }
+                _ => {}
+            }
+        }
+
+        resolve(&self.root)
+    }
+}
+
+fn deserialize_primitive<'js>(ctx: &Ctx<'js>, value: &SerializedValue) -> Result<Value<'js>> {
+    Ok(match value {
+        SerializedValue::Undefined | SerializedValue::Object(_) => {
+            Value::new_undefined(ctx.clone())
+        }
+        SerializedValue::Null => Value::new_null(ctx.clone()),
+        SerializedValue::Bool(value) => Value::new_bool(ctx.clone(), *value),
+        SerializedValue::Number(value) => Value::new_number(ctx.clone(), *value),
+        SerializedValue::String(value) => {
+            rsquickjs::String::from_str(ctx.clone(), value)?.into_value()
+        }
+        SerializedValue::BigInt(value) => {
+            let big_int: Function = ctx.globals().get("BigInt")?;
+            big_int.call((value.as_str(),))?
+        }
+    })
+}
+
+struct Serializer<'a, 'js> {
+    ctx: &'a Ctx<'js>,
+    primordials: &'a BasePrimordials<'js>,
+    memory: HashMap<Value<'js>, usize>,
+    objects: Vec<SerializedObject>,
+}
+
+impl<'js> Serializer<'_, 'js> {
+    // https://html.spec.whatwg.org/multipage/structured-data.html#structuredserializeinternal
+    fn serialize(&mut self, value: &Value<'js>) -> Result<SerializedValue> {
+        Ok(match value.type_of() {
+            Type::Uninitialized | Type::Undefined => SerializedValue::Undefined,
+            Type::Null => SerializedValue::Null,
+            Type::Bool => SerializedValue::Bool(value.as_bool().unwrap_or_default()),
+            Type::Int | Type::Float => {
+                SerializedValue::Number(value.as_number().unwrap_or_default())
+            }
+            Type::String => SerializedValue::String(value.get()?),
+            Type::BigInt => SerializedValue::BigInt(value.get::<Coerced<String>>()?.0),
+            Type::Array | Type::Exception | Type::Object => {
+                SerializedValue::Object(self.serialize_object(value)?)
+            }
+            _ => {
+                return Err(data_clone_error(
+                    self.ctx,
+                    &[value.type_name(), " could not be cloned"].concat(),
+                ))
+            }
+        })
+    }
+
+    fn serialize_object(&mut self, value: &Value<'js>) -> Result<usize> {
+        if let Some(index) = self.memory.get(value) {
+            return Ok(*index);
+        }
+        let obj = value.as_object().unwrap();
+        let primordials = self.primordials;
+
+        // reserve the slot before recursing so cycles resolve to this index
+        let index = self.objects.len();
+        self.memory.insert(value.clone(), index);
+        self.objects.push(SerializedObject::Object(Vec::new()));
+
+        let object = if value.is_array() {
+            SerializedObject::Array {
+                length: obj.get(PredefinedAtom::Length)?,
+                entries: self.serialize_entries(obj)?,
+            }
+        } else if value.is_error() {
+            let name: Option<Coerced<String>> = obj.get(PredefinedAtom::Name)?;
+            let name = name
+                .and_then(|name| ERROR_NAMES.into_iter().find(|n| *n == name.0))
+                .unwrap_or("Error");
+            let cause = match obj.contains_key("cause")? {
+                true => Some(self.serialize(&obj.get("cause")?)?),
+                false => None,
+            };
+            SerializedObject::Error {
+                name,
+                message: obj
+                    .get::<_, Option<Coerced<String>>>(PredefinedAtom::Message)?
+                    .map(|message| message.0),
+                stack: obj
+                    .get::<_, Option<Coerced<String>>>(PredefinedAtom::Stack)?
+                    .map(|s| s.0),
+                cause,
+            }
+        } else if let Some(buffer) = ArrayBuffer::from_object(obj.clone()) {
+            let bytes = buffer
+                .as_bytes()
+                .ok_or_else(|| data_clone_error(self.ctx, "ArrayBuffer is detached"))?;
+            SerializedObject::ArrayBuffer(bytes.to_vec())
+        } else if primordials
+            .function_array_buffer_is_view
+            .call::<_, bool>((value.clone(),))?
+        {
+            let tag: String = obj.get(PredefinedAtom::SymbolToStringTag)?;
+            let kind = ArrayBufferViewKind::from_tag(&tag).ok_or_else(|| {
+                data_clone_error(self.ctx, &[&tag, " could not be cloned"].concat())
+            })?;
+            let buffer: Value = obj.get("buffer")?;
+            let length = match kind {
+                ArrayBufferViewKind::DataView => obj.get("byteLength")?,
+                _ => obj.get(PredefinedAtom::Length)?,
+            };
+            SerializedObject::ArrayBufferView {
+                kind,
+                buffer: self.serialize_object(&buffer)?,
+                byte_offset: obj.get("byteOffset")?,
+                length,
+            }
+        } else if obj.is_instance_of(&primordials.constructor_date) {
+            let get_time: Function = primordials.prototype_date.get("getTime")?;
+            SerializedObject::Date(get_time.call((This(obj.clone()),))?)
+        } else if obj.is_instance_of(&primordials.constructor_regexp) {
+            SerializedObject::RegExp {
+                source: obj.get("source")?,
+                flags: obj.get("flags")?,
+            }
+        } else if obj.is_instance_of(&primordials.constructor_map) {
+            let entries: Vec<(Value, Value)> =
+                primordials.function_array_from.call((value.clone(),))?;
+            let mut serialized = Vec::with_capacity(entries.len());
+            for (key, value) in entries {
+                serialized.push((self.serialize(&key)?, self.serialize(&value)?));
+            }
+            SerializedObject::Map(serialized)
+        } else if obj.is_instance_of(&primordials.constructor_set) {
+            let entries: Vec<Value> = primordials.function_array_from.call((value.clone(),))?;
+            let mut serialized = Vec::with_capacity(entries.len());
+            for entry in entries {
+                serialized.push(self.serialize(&entry)?);
+            }
+            SerializedObject::Set(serialized)
+        } else if obj.is_instance_of(&primordials.constructor_bool) {
+            SerializedObject::Boolean(self.primitive_value(&primordials.constructor_bool, obj)?)
+        } else if obj.is_instance_of(&primordials.constructor_number) {
+            SerializedObject::Number(self.primitive_value(&primordials.constructor_number, obj)?)
+        } else if obj.is_instance_of(&primordials.constructor_string) {
+            SerializedObject::String(self.primitive_value(&primordials.constructor_string, obj)?)
+        } else {
+            if value.is_proxy() {
+                return Err(data_clone_error(self.ctx, "Proxy could not be cloned"));
+            }
+            if let Some(tag) = obj.get::<_, Option<String>>(PredefinedAtom::SymbolToStringTag)? {
+                if matches!(
+                    tag.as_str(),
+                    "WeakMap" | "WeakSet" | "WeakRef" | "FinalizationRegistry"
+                ) {
+                    return Err(data_clone_error(
+                        self.ctx,
+                        &[&tag, " could not be cloned"].concat(),
+                    ));
+                }
+            }
+            SerializedObject::Object(self.serialize_entries(obj)?)
+        };
+
+        self.objects[index] = object;
+        Ok(index)
+    }
+
+    fn serialize_entries(&mut self, obj: &Object<'js>) -> Result<Vec<(String, SerializedValue)>> {
+        let keys: Vec<String> = obj.keys().collect::<Result<_>>()?;
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value: Value = obj.get(key.as_str())?;
+            entries.push((key, self.serialize(&value)?));
+        }
+        Ok(entries)
+    }
+
+    fn primitive_value<T: rsquickjs::FromJs<'js>>(
+        &self,
+        constructor: &Constructor<'js>,
+        obj: &Object<'js>,
+    ) -> Result<T> {
+        let prototype: Object = constructor.get(PredefinedAtom::Prototype)?;
+        let value_of: Function = prototype.get(PredefinedAtom::ValueOf)?;
+        value_of.call((This(obj.clone()),))
+    }
+}
+
+fn data_clone_error(ctx: &Ctx<'_>, message: &str) -> rsquickjs::Error {
+    let ex =
+        match DOMException::new_with_name(ctx, DOMExceptionName::DataCloneError, message.into())
+            .and_then(|ex| Class::instance(ctx.clone(), ex))
+        {
+            Ok(ex) => ex,
+            Err(err) => return err,
+        };
+    ctx.throw(ex.into_value())
+}
+
+/// Clones `value` and detaches every `ArrayBuffer` listed in `options.transfer`.
+pub fn structured_clone<'js>(
+    ctx: Ctx<'js>,
+    value: Value<'js>,
+    options: Opt<Object<'js>>,
+) -> Result<Value<'js>> {
+    let mut transfer: Vec<ArrayBuffer> = Vec::new();
+    if let Some(list) = options.0 {
+        let list: Option<Vec<Value>> = list.get("transfer")?;
+        for item in list.unwrap_or_default() {
+            let buffer = ArrayBuffer::from_value(item)
+                .ok_or_else(|| data_clone_error(&ctx, "Value not transferable"))?;
+            if transfer.contains(&buffer) {
+                return Err(data_clone_error(
+                    &ctx,
+                    "ArrayBuffer is listed in the transfer list more than once",
+                ));
+            }
+            transfer.push(buffer);
+        }
+    }
+
+    let data = StructuredData::serialize(&ctx, &value)?;
+    for buffer in &mut transfer {
+        buffer.detach();
+    }
+    data.deserialize(&ctx)
+}
+
+/// Serializes `value` and sends it to the runtime receiving from `sender`, like `postMessage`,
+/// where [`StructuredData::deserialize`] recreates it.
+///
+/// As with a closed port the message is dropped if the receiver is gone.
+pub fn post_message<'js>(
+    ctx: &Ctx<'js>,
+    sender: &Sender<StructuredData>,
+    value: &Value<'js>,
+) -> Result<()> {
+    let data = StructuredData::serialize(ctx, value)?;
+    let _ = sender.send(data);
+    Ok(())
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    ctx.globals()
+        .set("structuredClone", Func::from(structured_clone))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::{given_runtime, test_sync_with};
+    use rsquickjs::{channel::channel, CatchResultExt, Value};
+
+    use super::{post_message, StructuredData};
+
+    #[tokio::test]
+    async fn test_structured_clone() {
+        test_sync_with(|ctx| {
+            crate::exceptions::init(&ctx)?;
+            super::init(&ctx)?;
+            let result = ctx
+                .eval::<String, _>(
+                    r#"
+                const results = [];
+                const source = {
+                    date: new Date(0),
+                    regexp: /a+b/gi,
+                    map: new Map([[1, { one: true }]]),
+                    set: new Set(['a', 'b']),
+                    big: 10n ** 20n,
+                    list: [1, , 3],
+                };
+                source.self = source;
+                const clone = structuredClone(source);
+                results.push(clone !== source, clone.self === clone);
+                results.push(clone.date.getTime(), clone.regexp.flags, clone.regexp.test('AAB'));
+                results.push(clone.map.get(1).one, [...clone.set].join(''), clone.big);
+                results.push(clone.list.length, 1 in clone.list);
+
+                const buffer = new ArrayBuffer(8);
+                const bytes = new Uint8Array(buffer, 2, 4);
+                bytes[0] = 7;
+                const view = structuredClone({ buffer, bytes }, { transfer: [buffer] });
+                results.push(view.bytes.buffer === view.buffer, view.bytes[0], buffer.byteLength);
+
+                const error = structuredClone(new RangeError('out', { cause: 'why' }));
+                results.push(error instanceof RangeError, error.message, error.cause);
+
+                try {
+                    structuredClone({ fn() {} });
+                } catch (e) {
+                    results.push(e.name);
+                }
+                results.join(',')
+            "#,
+                )
+                .catch(&ctx)
+                .unwrap();
+            assert_eq!(
+                result,
+                "true,true,0,gi,true,true,ab,100000000000000000000,3,false,true,7,0,true,out,why,DataCloneError"
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_post_message() {
+        let (sender, mut receiver) = channel::<StructuredData>();
+
+        let task = tokio::spawn(async move {
+            let (_rt, ctx) = given_runtime().await;
+            ctx.with(|ctx| {
+                crate::exceptions::init(&ctx).unwrap();
+                let value: Value = ctx
+                    .eval("const a = { date: new Date(0), bytes: new Uint8Array([1, 2]) }; a.self = a; a")
+                    .unwrap();
+                post_message(&ctx, &sender, &value).unwrap();
+
+                let function: Value = ctx.eval("() => {}").unwrap();
+                assert!(post_message(&ctx, &sender, &function).is_err());
+                ctx.catch();
+            })
+            .await;
+        });
+
+        let (_rt, ctx) = given_runtime().await;
+        let data = receiver.recv().await.unwrap();
+        task.await.unwrap();
+        assert!(receiver.recv().await.is_none());
+
+        ctx.with(|ctx| {
+            let value = data.deserialize(&ctx).unwrap();
+            ctx.globals().set("received", value).unwrap();
+            let check: bool = ctx
+                .eval(
+                    "received.self === received && received.date.getTime() === 0 && received.bytes[1] === 2",
+                )
+                .catch(&ctx)
+                .unwrap();
+            assert!(check);
+        })
+        .await;
+    }
+}
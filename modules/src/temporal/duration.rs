@@ -0,0 +1,450 @@
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, prelude::Rest, Class, Ctx, Exception, Result, Value,
+};
+
+use super::{
+    invalid_string, parse::parse_duration, round, throw_value_of, to_integer, RoundingMode,
+    RoundingOptions, Unit,
+};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+const FIELD_NAMES: [&str; 10] = [
+    "years",
+    "months",
+    "weeks",
+    "days",
+    "hours",
+    "minutes",
+    "seconds",
+    "milliseconds",
+    "microseconds",
+    "nanoseconds",
+];
+
+/// Units of the fields after `weeks`, from the largest
+const TIME_UNITS: [Unit; 7] = [
+    Unit::Day,
+    Unit::Hour,
+    Unit::Minute,
+    Unit::Second,
+    Unit::Millisecond,
+    Unit::Microsecond,
+    Unit::Nanosecond,
+];
+
+/// Seconds of a duration can't reach 2^53, so they stay exact as numbers
+const MAX_SECONDS: i128 = 1 << 53;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct Duration {
+    pub(super) years: i64,
+    pub(super) months: i64,
+    pub(super) weeks: i64,
+    pub(super) days: i64,
+    pub(super) hours: i64,
+    pub(super) minutes: i64,
+    pub(super) seconds: i64,
+    pub(super) milliseconds: i64,
+    pub(super) microseconds: i64,
+    pub(super) nanoseconds: i64,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Duration {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, args: Rest<Value<'js>>) -> Result<Self> {
+        let mut fields = [0; 10];
+        for ((field, name), value) in fields.iter_mut().zip(FIELD_NAMES).zip(args.0.iter()) {
+            if value.is_undefined() {
+                continue;
+            }
+            let number = value
+                .as_number()
+                .or_throw_type(&ctx, "Duration fields must be numbers")?;
+            *field = to_integer(&ctx, number, name)?;
+        }
+        Self::from_fields(fields).validate(&ctx)
+    }
+
+    /// A duration from a `Duration`, an ISO 8601 string or an object of fields
+    #[qjs(static)]
+    pub fn from(ctx: Ctx<'js>, item: Value<'js>) -> Result<Self> {
+        Self::from_value(&ctx, &item)
+    }
+
+    /// Compares durations without years, months or weeks
+    #[qjs(static)]
+    pub fn compare(ctx: Ctx<'js>, one: Value<'js>, two: Value<'js>) -> Result<i32> {
+        let one = Self::from_value(&ctx, &one)?;
+        let two = Self::from_value(&ctx, &two)?;
+        if one.has_calendar_units() || two.has_calendar_units() {
+            return Err(relative_to_required(&ctx));
+        }
+        Ok(one.time_nanoseconds().cmp(&two.time_nanoseconds()) as i32)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Temporal.Duration"
+    }
+
+    #[qjs(get, rename = "years")]
+    pub fn get_years(&self) -> i64 {
+        self.years
+    }
+
+    #[qjs(get, rename = "months")]
+    pub fn get_months(&self) -> i64 {
+        self.months
+    }
+
+    #[qjs(get, rename = "weeks")]
+    pub fn get_weeks(&self) -> i64 {
+        self.weeks
+    }
+
+    #[qjs(get, rename = "days")]
+    pub fn get_days(&self) -> i64 {
+        self.days
+    }
+
+    #[qjs(get, rename = "hours")]
+    pub fn get_hours(&self) -> i64 {
+        self.hours
+    }
+
+    #[qjs(get, rename = "minutes")]
+    pub fn get_minutes(&self) -> i64 {
+        self.minutes
+    }
+
+    #[qjs(get, rename = "seconds")]
+    pub fn get_seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    #[qjs(get, rename = "milliseconds")]
+    pub fn get_milliseconds(&self) -> i64 {
+        self.milliseconds
+    }
+
+    #[qjs(get, rename = "microseconds")]
+    pub fn get_microseconds(&self) -> i64 {
+        self.microseconds
+    }
+
+    #[qjs(get, rename = "nanoseconds")]
+    pub fn get_nanoseconds(&self) -> i64 {
+        self.nanoseconds
+    }
+
+    #[qjs(get)]
+    pub fn sign(&self) -> i32 {
+        self.fields()
+            .into_iter()
+            .find(|field| *field != 0)
+            .map_or(0, |field| field.signum() as i32)
+    }
+
+    #[qjs(get)]
+    pub fn blank(&self) -> bool {
+        self.sign() == 0
+    }
+
+    /// A copy with the fields of `fields` replaced
+    pub fn with(&self, ctx: Ctx<'js>, fields: Value<'js>) -> Result<Self> {
+        self.with_fields(&ctx, &fields, false)
+    }
+
+    pub fn negated(&self) -> Self {
+        Self::from_fields(self.fields().map(|field| -field))
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_fields(self.fields().map(i64::abs))
+    }
+
+    pub fn add(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = Self::from_value(&ctx, &other)?;
+        self.add_duration(&ctx, &other)
+    }
+
+    pub fn subtract(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<Self> {
+        let other = Self::from_value(&ctx, &other)?;
+        self.add_duration(&ctx, &other.negated())
+    }
+
+    /// Rounds and balances a duration without years, months or weeks, like
+    /// `round({ largestUnit: 'hour', smallestUnit: 'minute' })`
+    pub fn round(&self, ctx: Ctx<'js>, options: Value<'js>) -> Result<Self> {
+        if options.is_undefined() {
+            return Err(Exception::throw_type(&ctx, "Options are required"));
+        }
+        let options = RoundingOptions::from_value(&ctx, Some(options), RoundingMode::HalfExpand)?;
+        let smallest = options.smallest_unit.unwrap_or(Unit::Nanosecond);
+        let largest = options
+            .largest_unit
+            .unwrap_or_else(|| self.largest_unit().max(smallest));
+        if self.has_calendar_units() || largest > Unit::Day {
+            return Err(relative_to_required(&ctx));
+        }
+        if largest < smallest {
+            return Err(Exception::throw_range(
+                &ctx,
+                "largestUnit can't be smaller than smallestUnit",
+            ));
+        }
+        let nanoseconds =
+            options.round_nanoseconds(&ctx, self.time_nanoseconds(), Unit::Nanosecond)?;
+        Self::from_nanoseconds(nanoseconds, largest).validate(&ctx)
+    }
+
+    /// The length of a duration without years, months or weeks in `unit`
+    pub fn total(&self, ctx: Ctx<'js>, unit: Value<'js>) -> Result<f64> {
+        let unit = match unit.as_object() {
+            Some(options) => options.get_required::<_, Value>("unit", "options")?,
+            None => unit,
+        };
+        let options = RoundingOptions::from_value(&ctx, Some(unit), RoundingMode::Trunc)?;
+        let length = options
+            .smallest_unit
+            .and_then(Unit::nanoseconds)
+            .filter(|_| !self.has_calendar_units())
+            .ok_or_else(|| relative_to_required(&ctx))?;
+        let nanoseconds = self.time_nanoseconds();
+        Ok((nanoseconds / length) as f64 + (nanoseconds % length) as f64 / length as f64)
+    }
+
+    /// The ISO 8601 form, like `P1DT12H` or `PT0.5S`
+    pub fn to_string(&self) -> String {
+        let Duration {
+            years,
+            months,
+            weeks,
+            days,
+            hours,
+            minutes,
+            seconds,
+            milliseconds,
+            microseconds,
+            nanoseconds,
+        } = self.abs();
+
+        let mut string = String::from(if self.sign() < 0 { "-P" } else { "P" });
+        for (value, designator) in [(years, 'Y'), (months, 'M'), (weeks, 'W'), (days, 'D')] {
+            if value != 0 {
+                string.push_str(&value.to_string());
+                string.push(designator);
+            }
+        }
+
+        let subseconds =
+            milliseconds as i128 * 1_000_000 + microseconds as i128 * 1_000 + nanoseconds as i128;
+        let seconds = seconds as i128 + subseconds / 1_000_000_000;
+        let fraction = subseconds % 1_000_000_000;
+        if hours != 0 || minutes != 0 || seconds != 0 || fraction != 0 || self.blank() {
+            string.push('T');
+            for (value, designator) in [(hours, 'H'), (minutes, 'M')] {
+                if value != 0 {
+                    string.push_str(&value.to_string());
+                    string.push(designator);
+                }
+            }
+            if seconds != 0 || fraction != 0 || self.blank() {
+                string.push_str(&seconds.to_string());
+                if fraction != 0 {
+                    let fraction = format!("{fraction:09}");
+                    string.push('.');
+                    string.push_str(fraction.trim_end_matches('0'));
+                }
+                string.push('S');
+            }
+        }
+        string
+    }
+
+    #[qjs(rename = PredefinedAtom::ToJSON)]
+    pub fn to_json(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn to_locale_string(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn value_of(&self, ctx: Ctx<'js>) -> Result<()> {
+        Err(throw_value_of(&ctx, stringify!(Duration)))
+    }
+}
+
+impl Duration {
+    fn fields(&self) -> [i64; 10] {
+        [
+            self.years,
+            self.months,
+            self.weeks,
+            self.days,
+            self.hours,
+            self.minutes,
+            self.seconds,
+            self.milliseconds,
+            self.microseconds,
+            self.nanoseconds,
+        ]
+    }
+
+    fn from_fields(fields: [i64; 10]) -> Self {
+        let [years, months, weeks, days, hours, minutes, seconds, milliseconds, microseconds, nanoseconds] =
+            fields;
+        Self {
+            years,
+            months,
+            weeks,
+            days,
+            hours,
+            minutes,
+            seconds,
+            milliseconds,
+            microseconds,
+            nanoseconds,
+        }
+    }
+
+    pub(super) fn from_value<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<Self> {
+        if let Ok(duration) = Class::<Self>::from_value(value) {
+            return Ok(*duration.borrow());
+        }
+        if let Some(string) = value.as_string() {
+            let string = string.to_string()?;
+            return parse_duration(&string)
+                .ok_or_else(|| invalid_string(ctx, &string))?
+                .validate(ctx);
+        }
+        if value.is_object() {
+            return Self::default().with_fields(ctx, value, true);
+        }
+        Err(Exception::throw_type(
+            ctx,
+            "Expected a Temporal.Duration, string or object",
+        ))
+    }
+
+    fn with_fields<'js>(&self, ctx: &Ctx<'js>, fields: &Value<'js>, new: bool) -> Result<Self> {
+        let mut values = self.fields();
+        let mut any = false;
+        for (field, name) in values.iter_mut().zip(FIELD_NAMES) {
+            if let Some(value) = fields.get_optional::<_, f64>(name)? {
+                *field = to_integer(ctx, value, name)?;
+                any = true;
+            }
+        }
+        if !any {
+            let message = if new {
+                "A duration needs at least one field"
+            } else {
+                "with() needs at least one field"
+            };
+            return Err(Exception::throw_type(ctx, message));
+        }
+        Self::from_fields(values).validate(ctx)
+    }
+
+    /// Rejects mixed signs and durations too long to represent
+    pub(super) fn validate(self, ctx: &Ctx<'_>) -> Result<Self> {
+        let fields = self.fields();
+        if fields.iter().any(|field| *field > 0) && fields.iter().any(|field| *field < 0) {
+            return Err(Exception::throw_range(
+                ctx,
+                "Duration fields must all have the same sign",
+            ));
+        }
+        let calendar_out_of_range = fields[..3]
+            .iter()
+            .any(|field| field.unsigned_abs() >= 1 << 32);
+        let seconds = self.time_nanoseconds() / super::NS_PER_SECOND;
+        if calendar_out_of_range || seconds.abs() >= MAX_SECONDS {
+            return Err(Exception::throw_range(ctx, "Duration out of range"));
+        }
+        Ok(self)
+    }
+
+    pub(super) fn has_calendar_units(&self) -> bool {
+        self.years != 0 || self.months != 0 || self.weeks != 0
+    }
+
+    /// Days and smaller units as nanoseconds, with days of 24 hours
+    pub(super) fn time_nanoseconds(&self) -> i128 {
+        self.fields()[3..]
+            .iter()
+            .zip(TIME_UNITS)
+            .map(|(field, unit)| *field as i128 * unit.nanoseconds().unwrap_or_default())
+            .sum()
+    }
+
+    /// Nanoseconds as a duration with no unit larger than `largest`, or than days
+    pub(super) fn from_nanoseconds(nanoseconds: i128, largest: Unit) -> Self {
+        let mut fields = [0; 10];
+        let mut rest = nanoseconds;
+        for (field, unit) in fields[3..].iter_mut().zip(TIME_UNITS) {
+            if unit <= largest || unit == Unit::Nanosecond {
+                let length = unit.nanoseconds().unwrap_or(1);
+                *field = (rest / length) as i64;
+                rest %= length;
+            }
+        }
+        Self::from_fields(fields)
+    }
+
+    /// The largest unit with a non zero field
+    pub(super) fn largest_unit(&self) -> Unit {
+        let units = [Unit::Year, Unit::Month, Unit::Week]
+            .into_iter()
+            .chain(TIME_UNITS);
+        self.fields()
+            .into_iter()
+            .zip(units)
+            .find(|(field, _)| *field != 0)
+            .map_or(Unit::Nanosecond, |(_, unit)| unit)
+    }
+
+    fn add_duration(&self, ctx: &Ctx<'_>, other: &Self) -> Result<Self> {
+        if self.has_calendar_units() || other.has_calendar_units() {
+            return Err(relative_to_required(ctx));
+        }
+        let largest = self.largest_unit().max(other.largest_unit());
+        let nanoseconds = self.time_nanoseconds() + other.time_nanoseconds();
+        Self::from_nanoseconds(nanoseconds, largest).validate(ctx)
+    }
+
+    /// Rounds nanoseconds for `until` and `since`, then balances them up to the largest unit
+    pub(super) fn from_difference(
+        ctx: &Ctx<'_>,
+        nanoseconds: i128,
+        options: &RoundingOptions,
+        default_largest: Unit,
+    ) -> Result<Self> {
+        let smallest = options.smallest_unit.unwrap_or(Unit::Nanosecond);
+        let largest = options
+            .largest_unit
+            .unwrap_or_else(|| default_largest.max(smallest));
+        if largest < smallest {
+            return Err(Exception::throw_range(
+                ctx,
+                "largestUnit can't be smaller than smallestUnit",
+            ));
+        }
+        let length = smallest
+            .nanoseconds()
+            .or_throw_range(ctx, "smallestUnit can't be a calendar unit here")?;
+        let nanoseconds = round(nanoseconds, length * options.increment, options.mode);
+        Self::from_nanoseconds(nanoseconds, largest).validate(ctx)
+    }
+}
+
+fn relative_to_required(ctx: &Ctx<'_>) -> rsquickjs::Error {
+    Exception::throw_range(
+        ctx,
+        "Years, months and weeks need a relativeTo date, which is not supported",
+    )
+}
@@ -0,0 +1,229 @@
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, function::Opt, Class, Ctx, Exception, Result, Value,
+};
+
+use super::{
+    check_instant, epoch_nanoseconds, format_date, format_offset, format_time, from_bigint,
+    invalid_string, local_date_time, offset_seconds, parse::parse_date_time, throw_value_of,
+    to_bigint, to_time_zone, utc_date_time, Duration, RoundingMode, RoundingOptions, Unit,
+    ZonedDateTime, NS_PER_SECOND,
+};
+use crate::utils::object::ObjectExt;
+
+/// An exact point in time, without a time zone or calendar.
+#[derive(Clone, Copy, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct Instant {
+    #[qjs(skip_trace)]
+    pub(super) nanoseconds: i128,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> Instant {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, epoch_nanoseconds: Value<'js>) -> Result<Self> {
+        let nanoseconds = from_bigint(&ctx, &epoch_nanoseconds)?;
+        Ok(Self::from_nanoseconds(check_instant(&ctx, nanoseconds)?))
+    }
+
+    /// An instant from an `Instant`, a `ZonedDateTime` or a string with an offset
+    #[qjs(static)]
+    pub fn from(ctx: Ctx<'js>, item: Value<'js>) -> Result<Self> {
+        Self::from_value(&ctx, &item)
+    }
+
+    #[qjs(static)]
+    pub fn from_epoch_milliseconds(ctx: Ctx<'js>, epoch_milliseconds: f64) -> Result<Self> {
+        if epoch_milliseconds.fract() != 0.0 || !epoch_milliseconds.is_finite() {
+            return Err(Exception::throw_range(
+                &ctx,
+                "epochMilliseconds must be an integer",
+            ));
+        }
+        let nanoseconds = epoch_milliseconds as i128 * 1_000_000;
+        Ok(Self::from_nanoseconds(check_instant(&ctx, nanoseconds)?))
+    }
+
+    #[qjs(static)]
+    pub fn from_epoch_nanoseconds(ctx: Ctx<'js>, epoch_nanoseconds: Value<'js>) -> Result<Self> {
+        Self::new(ctx, epoch_nanoseconds)
+    }
+
+    #[qjs(static)]
+    pub fn compare(ctx: Ctx<'js>, one: Value<'js>, two: Value<'js>) -> Result<i32> {
+        let one = Self::from_value(&ctx, &one)?;
+        let two = Self::from_value(&ctx, &two)?;
+        Ok(one.nanoseconds.cmp(&two.nanoseconds) as i32)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Temporal.Instant"
+    }
+
+    #[qjs(get)]
+    pub fn epoch_milliseconds(&self) -> i64 {
+        self.nanoseconds.div_euclid(1_000_000) as i64
+    }
+
+    #[qjs(get)]
+    pub fn epoch_nanoseconds(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        to_bigint(&ctx, self.nanoseconds)
+    }
+
+    /// Adds hours and smaller units, days and longer depend on a time zone
+    pub fn add(&self, ctx: Ctx<'js>, duration: Value<'js>) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?;
+        self.add_duration(&ctx, &duration)
+    }
+
+    pub fn subtract(&self, ctx: Ctx<'js>, duration: Value<'js>) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?;
+        self.add_duration(&ctx, &duration.negated())
+    }
+
+    /// The duration from this instant to `other`, in seconds unless `largestUnit` says
+    /// otherwise
+    pub fn until(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other)?;
+        self.difference(&ctx, other.nanoseconds - self.nanoseconds, options)
+    }
+
+    pub fn since(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other)?;
+        self.difference(&ctx, self.nanoseconds - other.nanoseconds, options)
+    }
+
+    /// Rounds to `smallestUnit`, an hour at most
+    pub fn round(&self, ctx: Ctx<'js>, options: Value<'js>) -> Result<Self> {
+        let options = RoundingOptions::from_value(&ctx, Some(options), RoundingMode::HalfExpand)?;
+        match options.smallest_unit {
+            Some(unit) if unit <= Unit::Hour => {}
+            Some(_) => return Err(Exception::throw_range(&ctx, "smallestUnit is too large")),
+            None => return Err(Exception::throw_range(&ctx, "smallestUnit is required")),
+        }
+        let nanoseconds = options.round_nanoseconds(&ctx, self.nanoseconds, Unit::Nanosecond)?;
+        Ok(Self::from_nanoseconds(check_instant(&ctx, nanoseconds)?))
+    }
+
+    pub fn equals(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<bool> {
+        Ok(self.nanoseconds == Self::from_value(&ctx, &other)?.nanoseconds)
+    }
+
+    #[qjs(rename = "toZonedDateTimeISO")]
+    pub fn to_zoned_date_time_iso(
+        &self,
+        ctx: Ctx<'js>,
+        time_zone: Value<'js>,
+    ) -> Result<ZonedDateTime> {
+        let time_zone = to_time_zone(&ctx, &time_zone)?;
+        Ok(ZonedDateTime::from_parts(self.nanoseconds, time_zone))
+    }
+
+    /// The time in UTC, or in the `timeZone` option with its offset
+    pub fn to_string(&self, ctx: Ctx<'js>, options: Opt<Value<'js>>) -> Result<String> {
+        let time_zone = match options.0 {
+            Some(options) => options.get_optional::<_, Value>("timeZone")?,
+            None => None,
+        };
+        let Some(time_zone) = time_zone else {
+            let date_time = utc_date_time(&ctx, self.nanoseconds)?;
+            return Ok([
+                format_date(&date_time.date()),
+                "T".into(),
+                format_time(&date_time.time()),
+                "Z".into(),
+            ]
+            .concat());
+        };
+        let time_zone = to_time_zone(&ctx, &time_zone)?;
+        let date_time = local_date_time(&ctx, &time_zone, self.nanoseconds)?;
+        Ok([
+            format_date(&date_time.date()),
+            "T".into(),
+            format_time(&date_time.time()),
+            format_offset(offset_seconds(&time_zone, self.nanoseconds)),
+        ]
+        .concat())
+    }
+
+    #[qjs(rename = PredefinedAtom::ToJSON)]
+    pub fn to_json(&self, ctx: Ctx<'js>) -> Result<String> {
+        self.to_string(ctx, Opt(None))
+    }
+
+    pub fn to_locale_string(&self, ctx: Ctx<'js>) -> Result<String> {
+        self.to_string(ctx, Opt(None))
+    }
+
+    pub fn value_of(&self, ctx: Ctx<'js>) -> Result<()> {
+        Err(throw_value_of(&ctx, stringify!(Instant)))
+    }
+}
+
+impl Instant {
+    pub(super) fn from_nanoseconds(nanoseconds: i128) -> Self {
+        Self { nanoseconds }
+    }
+
+    pub(super) fn from_value<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<Self> {
+        if let Ok(instant) = Class::<Self>::from_value(value) {
+            return Ok(*instant.borrow());
+        }
+        if let Ok(zoned) = Class::<ZonedDateTime>::from_value(value) {
+            return Ok(Self::from_nanoseconds(zoned.borrow().nanoseconds));
+        }
+        let Some(string) = value.as_string() else {
+            return Err(Exception::throw_type(
+                ctx,
+                "Expected a Temporal.Instant or string",
+            ));
+        };
+        let string = string.to_string()?;
+        let parsed = parse_date_time(&string).ok_or_else(|| invalid_string(ctx, &string))?;
+        // without an offset the string names no exact time
+        let (Some(time), Some(offset)) = (parsed.time, parsed.offset) else {
+            return Err(invalid_string(ctx, &string));
+        };
+        let local = epoch_nanoseconds(&parsed.date.and_time(time));
+        let nanoseconds = local - offset as i128 * NS_PER_SECOND;
+        Ok(Self::from_nanoseconds(check_instant(ctx, nanoseconds)?))
+    }
+
+    fn add_duration(&self, ctx: &Ctx<'_>, duration: &Duration) -> Result<Self> {
+        if duration.has_calendar_units() || duration.days != 0 {
+            return Err(Exception::throw_range(
+                ctx,
+                "Instants can't add years, months, weeks or days, use a ZonedDateTime",
+            ));
+        }
+        let nanoseconds = self.nanoseconds + duration.time_nanoseconds();
+        Ok(Self::from_nanoseconds(check_instant(ctx, nanoseconds)?))
+    }
+
+    fn difference<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        nanoseconds: i128,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let options = RoundingOptions::from_value(ctx, options.0, RoundingMode::Trunc)?;
+        if options.largest_unit > Some(Unit::Hour) || options.smallest_unit > Some(Unit::Hour) {
+            return Err(Exception::throw_range(
+                ctx,
+                "Differences of instants are in hours at most",
+            ));
+        }
+        Duration::from_difference(ctx, nanoseconds, &options, Unit::Second)
+    }
+}
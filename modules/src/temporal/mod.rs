@@ -0,0 +1,699 @@
+//! The TC39 `Temporal` global: `Temporal.Instant`, `Temporal.ZonedDateTime`,
+//! `Temporal.PlainDate`, `Temporal.Duration` and `Temporal.Now`, on the ISO 8601
+//! calendar.
+//!
+//! Dates are backed by `chrono` and time zones by `chrono-tz`, so the range of dates is
+//! the one of `chrono`, about 262000 years either side of year 0. Exact times are
+//! nanoseconds since the epoch held in an `i128`, exposed as bigints. Durations with
+//! years, months or weeks only apply to dates, `relativeTo` is not supported.
+
+mod duration;
+mod instant;
+mod parse;
+mod plain_date;
+mod zoned_date_time;
+
+pub use duration::Duration;
+pub use instant::Instant;
+pub use plain_date::PlainDate;
+pub use zoned_date_time::ZonedDateTime;
+
+use chrono::{
+    DateTime, Datelike, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+    Timelike,
+};
+use chrono_tz::Tz;
+use rsquickjs::{
+    atom::PredefinedAtom, function::Opt, prelude::Func, Class, Coerced, Ctx, Exception, Function,
+    Object, Result, Value,
+};
+
+use crate::utils::{object::ObjectExt, result::ResultExt, time::now_nanos};
+
+pub(crate) const NS_PER_SECOND: i128 = 1_000_000_000;
+pub(crate) const NS_PER_DAY: i128 = 86_400 * NS_PER_SECOND;
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let temporal = Object::new(ctx.clone())?;
+    Class::<Duration>::define(&temporal)?;
+    Class::<Instant>::define(&temporal)?;
+    Class::<PlainDate>::define(&temporal)?;
+    Class::<ZonedDateTime>::define(&temporal)?;
+
+    let now = Object::new(ctx.clone())?;
+    now.set("instant", Func::from(now_instant))?;
+    now.set("plainDateISO", Func::from(now_plain_date))?;
+    now.set("timeZoneId", Func::from(now_time_zone_id))?;
+    now.set("zonedDateTimeISO", Func::from(now_zoned_date_time))?;
+    now.set(PredefinedAtom::SymbolToStringTag, "Temporal.Now")?;
+    temporal.set("Now", now)?;
+    temporal.set(PredefinedAtom::SymbolToStringTag, "Temporal")?;
+
+    ctx.globals().set("Temporal", temporal)?;
+    Ok(())
+}
+
+fn now_instant() -> Instant {
+    Instant::from_nanoseconds(now_nanos() as i128)
+}
+
+fn now_time_zone_id() -> &'static str {
+    system_time_zone().name()
+}
+
+fn now_zoned_date_time<'js>(ctx: Ctx<'js>, time_zone: Opt<Value<'js>>) -> Result<ZonedDateTime> {
+    let time_zone = optional_time_zone(&ctx, time_zone)?;
+    Ok(ZonedDateTime::from_parts(now_nanos() as i128, time_zone))
+}
+
+fn now_plain_date<'js>(ctx: Ctx<'js>, time_zone: Opt<Value<'js>>) -> Result<PlainDate> {
+    let time_zone = optional_time_zone(&ctx, time_zone)?;
+    let date = local_date_time(&ctx, &time_zone, now_nanos() as i128)?.date();
+    Ok(PlainDate::from_date(date))
+}
+
+fn optional_time_zone<'js>(ctx: &Ctx<'js>, time_zone: Opt<Value<'js>>) -> Result<Tz> {
+    match time_zone.0 {
+        Some(time_zone) if !time_zone.is_undefined() => to_time_zone(ctx, &time_zone),
+        _ => Ok(system_time_zone()),
+    }
+}
+
+/// Units of time, from the smallest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Unit {
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.strip_suffix('s').unwrap_or(name) {
+            "nanosecond" => Self::Nanosecond,
+            "microsecond" => Self::Microsecond,
+            "millisecond" => Self::Millisecond,
+            "second" => Self::Second,
+            "minute" => Self::Minute,
+            "hour" => Self::Hour,
+            "day" => Self::Day,
+            "week" => Self::Week,
+            "month" => Self::Month,
+            "year" => Self::Year,
+            _ => return None,
+        })
+    }
+
+    /// Length in nanoseconds, with days of 24 hours. Calendar units vary in length.
+    pub fn nanoseconds(self) -> Option<i128> {
+        Some(match self {
+            Self::Nanosecond => 1,
+            Self::Microsecond => 1_000,
+            Self::Millisecond => 1_000_000,
+            Self::Second => NS_PER_SECOND,
+            Self::Minute => 60 * NS_PER_SECOND,
+            Self::Hour => 3600 * NS_PER_SECOND,
+            Self::Day => NS_PER_DAY,
+            Self::Week | Self::Month | Self::Year => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoundingMode {
+    Ceil,
+    Floor,
+    Expand,
+    Trunc,
+    HalfCeil,
+    HalfFloor,
+    HalfExpand,
+    HalfTrunc,
+    HalfEven,
+}
+
+impl RoundingMode {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ceil" => Self::Ceil,
+            "floor" => Self::Floor,
+            "expand" => Self::Expand,
+            "trunc" => Self::Trunc,
+            "halfCeil" => Self::HalfCeil,
+            "halfFloor" => Self::HalfFloor,
+            "halfExpand" => Self::HalfExpand,
+            "halfTrunc" => Self::HalfTrunc,
+            "halfEven" => Self::HalfEven,
+            _ => return None,
+        })
+    }
+}
+
+/// Rounds `value` to a multiple of `increment`
+pub(crate) fn round(value: i128, increment: i128, mode: RoundingMode) -> i128 {
+    let quotient = value.div_euclid(increment);
+    let floor = quotient * increment;
+    let remainder = value - floor;
+    if remainder == 0 {
+        return value;
+    }
+    let negative = value < 0;
+    // below zero the floor is nearer, above zero the ceiling
+    let nearer = (remainder * 2 - increment).signum();
+    let up = match mode {
+        RoundingMode::Ceil => true,
+        RoundingMode::Floor => false,
+        RoundingMode::Expand => !negative,
+        RoundingMode::Trunc => negative,
+        _ if nearer != 0 => nearer > 0,
+        RoundingMode::HalfCeil => true,
+        RoundingMode::HalfFloor => false,
+        RoundingMode::HalfExpand => !negative,
+        RoundingMode::HalfTrunc => negative,
+        RoundingMode::HalfEven => quotient % 2 != 0,
+    };
+    if up {
+        floor + increment
+    } else {
+        floor
+    }
+}
+
+/// The options of `round`, `until` and `since`.
+pub(crate) struct RoundingOptions {
+    pub largest_unit: Option<Unit>,
+    pub smallest_unit: Option<Unit>,
+    pub mode: RoundingMode,
+    pub increment: i128,
+}
+
+impl RoundingOptions {
+    /// Reads an options object, or a string naming the smallest unit
+    pub fn from_value<'js>(
+        ctx: &Ctx<'js>,
+        options: Option<Value<'js>>,
+        mode: RoundingMode,
+    ) -> Result<Self> {
+        let mut rounding = Self {
+            largest_unit: None,
+            smallest_unit: None,
+            mode,
+            increment: 1,
+        };
+        let Some(options) = options.filter(|options| !options.is_undefined()) else {
+            return Ok(rounding);
+        };
+        if let Some(unit) = options.as_string() {
+            rounding.smallest_unit = Some(unit_from_name(ctx, &unit.to_string()?)?);
+            return Ok(rounding);
+        }
+        if !options.is_object() {
+            return Err(Exception::throw_type(ctx, "Options must be an object"));
+        }
+
+        if let Some(unit) = options.get_optional::<_, String>("largestUnit")? {
+            if unit != "auto" {
+                rounding.largest_unit = Some(unit_from_name(ctx, &unit)?);
+            }
+        }
+        if let Some(unit) = options.get_optional::<_, String>("smallestUnit")? {
+            rounding.smallest_unit = Some(unit_from_name(ctx, &unit)?);
+        }
+        if let Some(mode) = options.get_optional::<_, String>("roundingMode")? {
+            rounding.mode = RoundingMode::from_name(&mode)
+                .or_throw_range(ctx, &["Invalid rounding mode: ", &mode].concat())?;
+        }
+        if let Some(increment) = options.get_optional::<_, f64>("roundingIncrement")? {
+            if !(1.0..=1e9).contains(&increment) {
+                return Err(Exception::throw_range(
+                    ctx,
+                    "roundingIncrement must be between 1 and 1e9",
+                ));
+            }
+            rounding.increment = increment as i128;
+        }
+        if let (Some(largest), Some(smallest)) = (rounding.largest_unit, rounding.smallest_unit) {
+            if largest < smallest {
+                return Err(Exception::throw_range(
+                    ctx,
+                    "largestUnit can't be smaller than smallestUnit",
+                ));
+            }
+        }
+        Ok(rounding)
+    }
+
+    /// Rounds nanoseconds to the smallest unit, which has to be a unit of time
+    pub fn round_nanoseconds(&self, ctx: &Ctx<'_>, value: i128, default: Unit) -> Result<i128> {
+        let unit = self.smallest_unit.unwrap_or(default);
+        let length = unit
+            .nanoseconds()
+            .or_throw_range(ctx, "smallestUnit can't be a calendar unit here")?;
+        Ok(round(value, length * self.increment, self.mode))
+    }
+}
+
+fn unit_from_name(ctx: &Ctx<'_>, name: &str) -> Result<Unit> {
+    Unit::from_name(name).or_throw_range(ctx, &["Invalid unit: ", name].concat())
+}
+
+/// Whether the `overflow` option is `reject` rather than the default `constrain`
+pub(crate) fn overflow_reject<'js>(ctx: &Ctx<'js>, options: &Opt<Value<'js>>) -> Result<bool> {
+    let Some(options) = options.0.as_ref() else {
+        return Ok(false);
+    };
+    match options.get_optional::<_, String>("overflow")?.as_deref() {
+        None | Some("constrain") => Ok(false),
+        Some("reject") => Ok(true),
+        Some(overflow) => Err(Exception::throw_range(
+            ctx,
+            &["Invalid overflow: ", overflow].concat(),
+        )),
+    }
+}
+
+/// Which exact time a wall-clock time that is skipped or repeated resolves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Disambiguation {
+    Compatible,
+    Earlier,
+    Later,
+    Reject,
+}
+
+impl Disambiguation {
+    pub fn from_options<'js>(ctx: &Ctx<'js>, options: &Opt<Value<'js>>) -> Result<Self> {
+        let Some(options) = options.0.as_ref() else {
+            return Ok(Self::Compatible);
+        };
+        match options
+            .get_optional::<_, String>("disambiguation")?
+            .as_deref()
+        {
+            None | Some("compatible") => Ok(Self::Compatible),
+            Some("earlier") => Ok(Self::Earlier),
+            Some("later") => Ok(Self::Later),
+            Some("reject") => Ok(Self::Reject),
+            Some(disambiguation) => Err(Exception::throw_range(
+                ctx,
+                &["Invalid disambiguation: ", disambiguation].concat(),
+            )),
+        }
+    }
+}
+
+/// Truncates a number to an integer like `Temporal` does for fields
+pub(crate) fn to_integer(ctx: &Ctx<'_>, value: f64, name: &str) -> Result<i64> {
+    if !value.is_finite() {
+        return Err(Exception::throw_range(
+            ctx,
+            &["The value of \"", name, "\" must be a finite number"].concat(),
+        ));
+    }
+    Ok(value.trunc() as i64)
+}
+
+pub(crate) fn to_bigint<'js>(ctx: &Ctx<'js>, value: i128) -> Result<Value<'js>> {
+    // bigints only convert from 64 bits natively
+    let constructor: Function = ctx.globals().get("BigInt")?;
+    constructor.call((value.to_string(),))
+}
+
+pub(crate) fn from_bigint<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<i128> {
+    if !value.is_big_int() {
+        return Err(Exception::throw_type(ctx, "Expected a bigint"));
+    }
+    let Coerced(value) = value.get::<Coerced<String>>()?;
+    value.parse().or_throw_range(ctx, "")
+}
+
+/// Rejects exact times out of the supported range
+pub(crate) fn check_instant(ctx: &Ctx<'_>, nanoseconds: i128) -> Result<i128> {
+    utc_date_time(ctx, nanoseconds)?;
+    Ok(nanoseconds)
+}
+
+pub(crate) fn utc_date_time(ctx: &Ctx<'_>, nanoseconds: i128) -> Result<NaiveDateTime> {
+    checked_utc_date_time(nanoseconds).or_throw_range(ctx, "Instant out of range")
+}
+
+fn checked_utc_date_time(nanoseconds: i128) -> Option<NaiveDateTime> {
+    let seconds = i64::try_from(nanoseconds.div_euclid(NS_PER_SECOND)).ok()?;
+    let nanos = nanoseconds.rem_euclid(NS_PER_SECOND) as u32;
+    DateTime::from_timestamp(seconds, nanos).map(|date_time| date_time.naive_utc())
+}
+
+/// Nanoseconds since the epoch of a date and time read as UTC
+pub(crate) fn epoch_nanoseconds(date_time: &NaiveDateTime) -> i128 {
+    let date_time = date_time.and_utc();
+    date_time.timestamp() as i128 * NS_PER_SECOND + date_time.timestamp_subsec_nanos() as i128
+}
+
+pub(crate) fn offset_seconds(time_zone: &Tz, nanoseconds: i128) -> i32 {
+    checked_utc_date_time(nanoseconds).map_or(0, |date_time| {
+        time_zone
+            .offset_from_utc_datetime(&date_time)
+            .fix()
+            .local_minus_utc()
+    })
+}
+
+pub(crate) fn local_date_time(
+    ctx: &Ctx<'_>,
+    time_zone: &Tz,
+    nanoseconds: i128,
+) -> Result<NaiveDateTime> {
+    let offset = offset_seconds(time_zone, nanoseconds) as i128 * NS_PER_SECOND;
+    utc_date_time(ctx, nanoseconds + offset)
+}
+
+/// The exact time of a wall-clock time in `time_zone`
+pub(crate) fn local_to_epoch_nanoseconds(
+    ctx: &Ctx<'_>,
+    time_zone: &Tz,
+    local: &NaiveDateTime,
+    disambiguation: Disambiguation,
+) -> Result<i128> {
+    let local_nanoseconds = epoch_nanoseconds(local);
+    let nanoseconds = match time_zone.from_local_datetime(local) {
+        LocalResult::Single(date_time) => epoch_nanoseconds(&date_time.naive_utc()),
+        LocalResult::Ambiguous(earlier, later) => match disambiguation {
+            Disambiguation::Compatible | Disambiguation::Earlier => {
+                epoch_nanoseconds(&earlier.naive_utc())
+            }
+            Disambiguation::Later => epoch_nanoseconds(&later.naive_utc()),
+            Disambiguation::Reject => {
+                return Err(Exception::throw_range(
+                    ctx,
+                    "The local time is ambiguous in the time zone",
+                ))
+            }
+        },
+        // skipped, like when clocks spring forward, so move it by the change of offset
+        LocalResult::None => {
+            let before = offset_seconds(time_zone, local_nanoseconds - NS_PER_DAY);
+            let after = offset_seconds(time_zone, local_nanoseconds + NS_PER_DAY);
+            let offset = match disambiguation {
+                Disambiguation::Compatible | Disambiguation::Later => before,
+                Disambiguation::Earlier => after,
+                Disambiguation::Reject => {
+                    return Err(Exception::throw_range(
+                        ctx,
+                        "The local time doesn't exist in the time zone",
+                    ))
+                }
+            };
+            local_nanoseconds - offset as i128 * NS_PER_SECOND
+        }
+    };
+    check_instant(ctx, nanoseconds)
+}
+
+pub(crate) fn system_time_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+pub(crate) fn parse_time_zone(ctx: &Ctx<'_>, name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .ok()
+        .or_throw_range(ctx, &["Invalid time zone: ", name].concat())
+}
+
+/// A time zone from its name or a `ZonedDateTime`
+pub(crate) fn to_time_zone<'js>(ctx: &Ctx<'js>, value: &Value<'js>) -> Result<Tz> {
+    if let Some(name) = value.as_string() {
+        return parse_time_zone(ctx, &name.to_string()?);
+    }
+    if let Ok(zoned) = Class::<ZonedDateTime>::from_value(value) {
+        return Ok(zoned.borrow().time_zone);
+    }
+    Err(Exception::throw_type(ctx, "Time zone must be a string"))
+}
+
+pub(crate) fn invalid_string(ctx: &Ctx<'_>, string: &str) -> rsquickjs::Error {
+    Exception::throw_range(ctx, &["Invalid ISO 8601 string: ", string].concat())
+}
+
+/// Date fields of an object, `month` or `monthCode` and `day` are required
+pub(crate) fn date_from_fields<'js>(
+    ctx: &Ctx<'js>,
+    fields: &Value<'js>,
+    base: Option<NaiveDate>,
+    reject: bool,
+) -> Result<NaiveDate> {
+    let field = |name: &str, current: Option<u32>| -> Result<Option<i64>> {
+        match fields.get_optional::<_, f64>(name)? {
+            Some(value) => to_integer(ctx, value, name).map(Some),
+            None => Ok(current.map(i64::from)),
+        }
+    };
+    let year = match fields.get_optional::<_, f64>("year")? {
+        Some(year) => to_integer(ctx, year, "year")?,
+        None => base
+            .map(|base| base.year() as i64)
+            .or_throw_type(ctx, "year is required")?,
+    };
+    let mut month = field("month", base.map(|base| base.month()))?;
+    if let Some(code) = fields.get_optional::<_, String>("monthCode")? {
+        let from_code = code
+            .strip_prefix('M')
+            .filter(|number| number.len() == 2)
+            .and_then(|number| number.parse::<i64>().ok())
+            .filter(|number| (1..=12).contains(number))
+            .or_throw_range(ctx, &["Invalid monthCode: ", &code].concat())?;
+        if fields.get_optional::<_, f64>("month")?.is_some() && month != Some(from_code) {
+            return Err(Exception::throw_range(ctx, "month and monthCode disagree"));
+        }
+        month = Some(from_code);
+    }
+    let month = month.or_throw_type(ctx, "month or monthCode is required")?;
+    let day = field("day", base.map(|base| base.day()))?.or_throw_type(ctx, "day is required")?;
+    regulate_date(ctx, year, month, day, reject)
+}
+
+/// A date from possibly out of range fields, clamped unless `reject`
+pub(crate) fn regulate_date(
+    ctx: &Ctx<'_>,
+    year: i64,
+    month: i64,
+    day: i64,
+    reject: bool,
+) -> Result<NaiveDate> {
+    let out_of_range = || Exception::throw_range(ctx, "Date out of range");
+    if reject && !(1..=12).contains(&month) {
+        return Err(out_of_range());
+    }
+    let year = i32::try_from(year).map_err(|_| out_of_range())?;
+    let month = month.clamp(1, 12) as u32;
+    let last_day = days_in_month(year, month).ok_or_else(out_of_range)? as i64;
+    if reject && !(1..=last_day).contains(&day) {
+        return Err(out_of_range());
+    }
+    NaiveDate::from_ymd_opt(year, month, day.clamp(1, last_day) as u32).ok_or_else(out_of_range)
+}
+
+pub(crate) fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = first.checked_add_months(chrono::Months::new(1))?;
+    Some((next - first).num_days() as u32)
+}
+
+/// Time fields of an object, missing ones come from `base`
+pub(crate) fn time_from_fields<'js>(
+    ctx: &Ctx<'js>,
+    fields: &Value<'js>,
+    base: NaiveTime,
+    reject: bool,
+) -> Result<NaiveTime> {
+    let nanosecond = base.nanosecond() as i64;
+    let field = |name: &str, current: i64, max: i64| -> Result<u32> {
+        let value = match fields.get_optional::<_, f64>(name)? {
+            Some(value) => to_integer(ctx, value, name)?,
+            None => current,
+        };
+        if reject && !(0..=max).contains(&value) {
+            return Err(Exception::throw_range(
+                ctx,
+                &["The value of \"", name, "\" is out of range"].concat(),
+            ));
+        }
+        Ok(value.clamp(0, max) as u32)
+    };
+    let hour = field("hour", base.hour() as i64, 23)?;
+    let minute = field("minute", base.minute() as i64, 59)?;
+    let second = field("second", base.second() as i64, 59)?;
+    let millisecond = field("millisecond", nanosecond / 1_000_000, 999)?;
+    let microsecond = field("microsecond", nanosecond / 1_000 % 1_000, 999)?;
+    let nanosecond = field("nanosecond", nanosecond % 1_000, 999)?;
+    let nanos = millisecond * 1_000_000 + microsecond * 1_000 + nanosecond;
+    Ok(NaiveTime::from_hms_nano_opt(hour, minute, second, nanos).unwrap_or(base))
+}
+
+pub(crate) fn format_date(date: &NaiveDate) -> String {
+    let year = date.year();
+    if (0..=9999).contains(&year) {
+        format!("{year:04}-{:02}-{:02}", date.month(), date.day())
+    } else {
+        let sign = if year < 0 { '-' } else { '+' };
+        format!(
+            "{sign}{:06}-{:02}-{:02}",
+            year.unsigned_abs(),
+            date.month(),
+            date.day()
+        )
+    }
+}
+
+pub(crate) fn format_time(time: &NaiveTime) -> String {
+    let mut string = format!(
+        "{:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second()
+    );
+    let nanos = time.nanosecond();
+    if nanos != 0 {
+        let fraction = format!("{nanos:09}");
+        string.push('.');
+        string.push_str(fraction.trim_end_matches('0'));
+    }
+    string
+}
+
+pub(crate) fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    let mut string = format!("{sign}{:02}:{:02}", seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        string.push_str(&format!(":{:02}", seconds % 60));
+    }
+    string
+}
+
+/// `valueOf` of every Temporal class, so `<` and `+` don't silently compare strings
+pub(crate) fn throw_value_of(ctx: &Ctx<'_>, class: &str) -> rsquickjs::Error {
+    Exception::throw_type(
+        ctx,
+        &[
+            "Temporal.",
+            class,
+            " can't be converted to a primitive, use compare() or equals()",
+        ]
+        .concat(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    #[test]
+    fn test_round() {
+        assert_eq!(round(15, 10, RoundingMode::HalfExpand), 20);
+        assert_eq!(round(-15, 10, RoundingMode::HalfExpand), -20);
+        assert_eq!(round(-15, 10, RoundingMode::HalfEven), -20);
+        assert_eq!(round(25, 10, RoundingMode::HalfEven), 20);
+        assert_eq!(round(-19, 10, RoundingMode::Trunc), -10);
+        assert_eq!(round(-11, 10, RoundingMode::Floor), -20);
+        assert_eq!(round(11, 10, RoundingMode::HalfFloor), 10);
+    }
+
+    #[tokio::test]
+    async fn test_temporal() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                init(&ctx).unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        export async function test() {
+                            const date = Temporal.PlainDate.from('2024-01-31');
+                            const duration = Temporal.Duration.from({ hours: 1, minutes: 90 });
+                            const instant = Temporal.Instant.from('2024-03-10T06:59:59.5Z');
+                            const zoned = instant.toZonedDateTimeISO('America/New_York');
+                            const errors = [];
+                            for (const f of [
+                                () => date < date,
+                                () => Temporal.PlainDate.from('2024-02-30', { overflow: 'reject' }),
+                                () => Temporal.Instant.from('2024-01-01T00:00'),
+                                () => new Temporal.Duration(1, -1),
+                            ]) {
+                                try {
+                                    f();
+                                } catch (e) {
+                                    errors.push(e.constructor.name);
+                                }
+                            }
+                            return [
+                                date.add({ months: 1 }).toString(),
+                                date.add('P1M', { overflow: 'constrain' }).dayOfWeek,
+                                date.until('2025-03-01', { largestUnit: 'years' }).toString(),
+                                Temporal.PlainDate.compare(date, '2024-02-01'),
+                                date.with({ day: 1 }).equals('2024-01-01'),
+                                duration.toString(),
+                                duration.round({ largestUnit: 'hour' }).toString(),
+                                duration.total('minutes'),
+                                Temporal.Duration.from('-PT1.5S').negated().toString(),
+                                instant.add({ seconds: 1 }).epochMilliseconds,
+                                instant.epochNanoseconds,
+                                zoned.toString(),
+                                zoned.add({ seconds: 1 }).toString(),
+                                zoned.add({ days: 1 }).hoursInDay,
+                                zoned.startOfDay().toString(),
+                                Temporal.ZonedDateTime.from('2024-03-10T02:30[America/New_York]').hour,
+                                Temporal.Instant.fromEpochMilliseconds(0).until(instant, { largestUnit: 'hours', smallestUnit: 'hours' }).hours,
+                                typeof Temporal.Now.instant().epochNanoseconds,
+                                JSON.stringify({ date }),
+                                errors.join(','),
+                            ].join('|');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    [
+                        "2024-02-29",
+                        "4",
+                        "P1Y1M1D",
+                        "-1",
+                        "true",
+                        "PT1H90M",
+                        "PT2H30M",
+                        "150",
+                        "PT1.5S",
+                        "1710054000500",
+                        "1710053999500000000",
+                        "2024-03-10T01:59:59.5-05:00[America/New_York]",
+                        "2024-03-10T03:00:00.5-04:00[America/New_York]",
+                        "24",
+                        "2024-03-10T00:00:00-05:00[America/New_York]",
+                        "3",
+                        "475014",
+                        "bigint",
+                        "{\"date\":\"2024-01-31\"}",
+                        "TypeError,RangeError,RangeError,RangeError",
+                    ]
+                    .join("|")
+                );
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,340 @@
+//! The ISO 8601 and RFC 9557 strings `Temporal` reads, like
+//! `2024-03-10T02:30:00-05:00[America/New_York]` and `P1Y2M3DT4H5M6.5S`.
+
+use chrono::{NaiveDate, NaiveTime};
+
+use super::duration::Duration;
+
+pub struct ParsedDateTime {
+    pub date: NaiveDate,
+    /// `None` for a date without a time
+    pub time: Option<NaiveTime>,
+    /// Seconds ahead of UTC, `Some(0)` for `Z`
+    pub offset: Option<i32>,
+    /// The offset was `Z`, which says nothing about the local time
+    pub utc: bool,
+    /// The bracketed time zone annotation
+    pub time_zone: Option<String>,
+}
+
+pub fn parse_date_time(input: &str) -> Option<ParsedDateTime> {
+    let (main, annotations) = input.split_at(input.find('[').unwrap_or(input.len()));
+    let time_zone = parse_annotations(annotations)?;
+
+    let mut cursor = Cursor(main);
+    let date = cursor.date()?;
+    let mut time = None;
+    let mut offset = None;
+    let mut utc = false;
+    if cursor.eat_any(&['T', 't', ' ']).is_some() {
+        time = Some(cursor.time()?);
+        if cursor.eat_any(&['Z', 'z']).is_some() {
+            offset = Some(0);
+            utc = true;
+        } else if !cursor.0.is_empty() {
+            offset = Some(cursor.offset()?);
+        }
+    }
+    if !cursor.0.is_empty() {
+        return None;
+    }
+
+    Some(ParsedDateTime {
+        date,
+        time,
+        offset,
+        utc,
+        time_zone,
+    })
+}
+
+/// Reads the time zone out of the annotations, rejecting calendars other than ISO 8601
+fn parse_annotations(mut annotations: &str) -> Option<Option<String>> {
+    let mut time_zone = None;
+    while !annotations.is_empty() {
+        let end = annotations.find(']')?;
+        let annotation = annotations.strip_prefix('[')?[..end - 1].trim_start_matches('!');
+        annotations = &annotations[end + 1..];
+
+        match annotation.split_once('=') {
+            Some(("u-ca", calendar)) if calendar != "iso8601" => return None,
+            Some(_) => {}
+            None if time_zone.is_none() && !annotation.is_empty() => {
+                time_zone = Some(annotation.to_string());
+            }
+            None => return None,
+        }
+    }
+    Some(time_zone)
+}
+
+/// A UTC offset like `+05:30`, in seconds
+pub fn parse_offset(input: &str) -> Option<i32> {
+    let mut cursor = Cursor(input);
+    let offset = cursor.offset()?;
+    cursor.0.is_empty().then_some(offset)
+}
+
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let mut cursor = Cursor(input);
+    let negative = match cursor.eat_any(&['+', '-', '\u{2212}']) {
+        Some('+') | None => false,
+        Some(_) => true,
+    };
+    cursor.eat_any(&['P', 'p'])?;
+
+    let mut duration = Duration::default();
+    let mut components = 0;
+    let mut in_time = false;
+    let mut last_unit = 0;
+    let mut fraction = None;
+    while !cursor.0.is_empty() {
+        if !in_time && cursor.eat_any(&['T', 't']).is_some() {
+            in_time = true;
+            if cursor.0.is_empty() {
+                return None;
+            }
+            continue;
+        }
+        // a fraction has to be on the last component
+        if fraction.is_some() {
+            return None;
+        }
+
+        let value = cursor.number()?;
+        fraction = cursor.fraction();
+        let designator = cursor.eat_any(&['Y', 'M', 'W', 'D', 'H', 'S'])?;
+        let designator = designator.to_ascii_uppercase();
+        let unit = match (in_time, designator) {
+            (false, 'Y') => 1,
+            (false, 'M') => 2,
+            (false, 'W') => 3,
+            (false, 'D') => 4,
+            (true, 'H') => 5,
+            (true, 'M') => 6,
+            (true, 'S') => 7,
+            _ => return None,
+        };
+        if unit <= last_unit || (fraction.is_some() && unit < 5) {
+            return None;
+        }
+        last_unit = unit;
+        components += 1;
+
+        let field = match unit {
+            1 => &mut duration.years,
+            2 => &mut duration.months,
+            3 => &mut duration.weeks,
+            4 => &mut duration.days,
+            5 => &mut duration.hours,
+            6 => &mut duration.minutes,
+            _ => &mut duration.seconds,
+        };
+        *field = value;
+
+        // spread the fraction over the smaller units, PT1.5H is PT1H30M
+        if let Some(nanos) = fraction {
+            let unit_seconds: i128 = match unit {
+                5 => 3600,
+                6 => 60,
+                _ => 1,
+            };
+            let mut rest = nanos as i128 * unit_seconds;
+            if unit == 5 {
+                duration.minutes = (rest / 60_000_000_000) as i64;
+                rest %= 60_000_000_000;
+            }
+            if unit <= 6 {
+                duration.seconds = (rest / 1_000_000_000) as i64;
+                rest %= 1_000_000_000;
+            }
+            duration.milliseconds = (rest / 1_000_000) as i64;
+            duration.microseconds = (rest / 1_000 % 1_000) as i64;
+            duration.nanoseconds = (rest % 1_000) as i64;
+        }
+    }
+    if components == 0 {
+        return None;
+    }
+
+    Some(if negative {
+        duration.negated()
+    } else {
+        duration
+    })
+}
+
+struct Cursor<'a>(&'a str);
+
+impl Cursor<'_> {
+    fn eat(&mut self, char: char) -> bool {
+        match self.0.strip_prefix(char) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn eat_any(&mut self, chars: &[char]) -> Option<char> {
+        let char = self.0.chars().next().filter(|char| chars.contains(char))?;
+        self.0 = &self.0[char.len_utf8()..];
+        Some(char)
+    }
+
+    fn digits(&mut self, count: usize) -> Option<u32> {
+        let digits = self.0.get(..count)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        self.0 = &self.0[count..];
+        digits.parse().ok()
+    }
+
+    fn starts_with_digit(&self) -> bool {
+        self.0
+            .bytes()
+            .next()
+            .is_some_and(|byte| byte.is_ascii_digit())
+    }
+
+    /// A whole number of any length
+    fn number(&mut self) -> Option<i64> {
+        let length = self.0.bytes().take_while(u8::is_ascii_digit).count();
+        let number = self.0.get(..length).filter(|digits| !digits.is_empty())?;
+        self.0 = &self.0[length..];
+        number.parse().ok()
+    }
+
+    /// Up to 9 digits after `.` or `,`, as nanoseconds
+    fn fraction(&mut self) -> Option<u32> {
+        let rest = self.0.strip_prefix(['.', ','])?;
+        let length = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if !(1..=9).contains(&length) {
+            return None;
+        }
+        let nanos = rest[..length].parse::<u32>().ok()? * 10u32.pow(9 - length as u32);
+        self.0 = &rest[length..];
+        Some(nanos)
+    }
+
+    fn date(&mut self) -> Option<NaiveDate> {
+        let year = match self.eat_any(&['+', '-', '\u{2212}']) {
+            Some(sign) => {
+                let year = self.digits(6)? as i32;
+                match sign {
+                    '+' => year,
+                    // -000000 is not a year
+                    _ if year == 0 => return None,
+                    _ => -year,
+                }
+            }
+            None => self.digits(4)? as i32,
+        };
+        let extended = self.eat('-');
+        let month = self.digits(2)?;
+        if extended && !self.eat('-') {
+            return None;
+        }
+        let day = self.digits(2)?;
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    fn time(&mut self) -> Option<NaiveTime> {
+        let hour = self.digits(2)?;
+        let (mut minute, mut second, mut nanos) = (0, 0, 0);
+        let extended = self.eat(':');
+        if extended || self.starts_with_digit() {
+            minute = self.digits(2)?;
+            if (extended && self.eat(':')) || (!extended && self.starts_with_digit()) {
+                // a leap second reads as the last second of the minute
+                second = self.digits(2)?.min(59);
+                nanos = self.fraction().unwrap_or_default();
+            }
+        }
+        NaiveTime::from_hms_nano_opt(hour, minute, second, nanos)
+    }
+
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.eat_any(&['+', '-', '\u{2212}'])? {
+            '+' => 1,
+            _ => -1,
+        };
+        let hours = self.digits(2)? as i32;
+        let mut seconds = hours * 3600;
+        let extended = self.eat(':');
+        if extended || self.starts_with_digit() {
+            seconds += self.digits(2)? as i32 * 60;
+            if (extended && self.eat(':')) || (!extended && self.starts_with_digit()) {
+                seconds += self.digits(2)? as i32;
+            }
+        }
+        if hours > 23 {
+            return None;
+        }
+        Some(sign * seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Timelike};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_date_time() {
+        let parsed =
+            parse_date_time("2024-03-10T02:30:15.25-05:00[America/New_York][u-ca=iso8601]")
+                .unwrap();
+        assert_eq!((parsed.date.year(), parsed.date.month()), (2024, 3));
+        let time = parsed.time.unwrap();
+        assert_eq!(
+            (time.hour(), time.second(), time.nanosecond()),
+            (2, 15, 250_000_000)
+        );
+        assert_eq!(parsed.offset, Some(-5 * 3600));
+        assert_eq!(parsed.time_zone.as_deref(), Some("America/New_York"));
+
+        let parsed = parse_date_time("+010000-01-01T00:00Z").unwrap();
+        assert_eq!(parsed.date.year(), 10000);
+        assert!(parsed.utc);
+
+        assert!(parse_date_time("20240229").is_some());
+        assert!(parse_date_time("2023-02-29").is_none());
+        assert!(parse_date_time("2024-01-01[u-ca=hebrew]").is_none());
+        assert!(parse_date_time("2024-01-01T25:00").is_none());
+
+        assert_eq!(parse_offset("-0530"), Some(-(5 * 3600 + 30 * 60)));
+        assert!(parse_offset("+05:30Z").is_none());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let duration = parse_duration("-P1Y2M3W4DT5H6M7.008009010S").unwrap();
+        assert_eq!(
+            duration,
+            Duration {
+                years: -1,
+                months: -2,
+                weeks: -3,
+                days: -4,
+                hours: -5,
+                minutes: -6,
+                seconds: -7,
+                milliseconds: -8,
+                microseconds: -9,
+                nanoseconds: -10,
+            }
+        );
+
+        let duration = parse_duration("PT1.5H").unwrap();
+        assert_eq!((duration.hours, duration.minutes), (1, 30));
+
+        assert!(parse_duration("P").is_none());
+        assert!(parse_duration("PT").is_none());
+        assert!(parse_duration("P1D2Y").is_none());
+        assert!(parse_duration("PT1.5H2M").is_none());
+    }
+}
@@ -0,0 +1,364 @@
+use std::cmp::Ordering;
+
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta};
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, function::Opt, Class, Ctx, Exception, Result, Value,
+};
+
+use super::{
+    date_from_fields, days_in_month, format_date, invalid_string, local_date_time,
+    local_to_epoch_nanoseconds, overflow_reject, parse::parse_date_time, regulate_date,
+    throw_value_of, to_integer, to_time_zone, Disambiguation, Duration, RoundingMode,
+    RoundingOptions, Unit, ZonedDateTime, NS_PER_DAY,
+};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+/// A calendar date, without a time or time zone.
+#[derive(Clone, Copy, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct PlainDate {
+    #[qjs(skip_trace)]
+    pub(super) date: NaiveDate,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> PlainDate {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, year: f64, month: f64, day: f64) -> Result<Self> {
+        let year = to_integer(&ctx, year, "year")?;
+        let month = to_integer(&ctx, month, "month")?;
+        let day = to_integer(&ctx, day, "day")?;
+        Ok(Self::from_date(regulate_date(
+            &ctx, year, month, day, true,
+        )?))
+    }
+
+    /// A date from a `PlainDate`, a `ZonedDateTime`, an ISO 8601 string or an object of
+    /// fields, out of range fields are clamped unless `overflow` is `reject`
+    #[qjs(static)]
+    pub fn from(ctx: Ctx<'js>, item: Value<'js>, options: Opt<Value<'js>>) -> Result<Self> {
+        let reject = overflow_reject(&ctx, &options)?;
+        Self::from_value(&ctx, &item, reject)
+    }
+
+    #[qjs(static)]
+    pub fn compare(ctx: Ctx<'js>, one: Value<'js>, two: Value<'js>) -> Result<i32> {
+        let one = Self::from_value(&ctx, &one, false)?;
+        let two = Self::from_value(&ctx, &two, false)?;
+        Ok(one.date.cmp(&two.date) as i32)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Temporal.PlainDate"
+    }
+
+    #[qjs(get)]
+    pub fn calendar_id(&self) -> &'static str {
+        "iso8601"
+    }
+
+    #[qjs(get)]
+    pub fn year(&self) -> i32 {
+        self.date.year()
+    }
+
+    #[qjs(get)]
+    pub fn month(&self) -> u32 {
+        self.date.month()
+    }
+
+    #[qjs(get)]
+    pub fn month_code(&self) -> String {
+        month_code(&self.date)
+    }
+
+    #[qjs(get)]
+    pub fn day(&self) -> u32 {
+        self.date.day()
+    }
+
+    #[qjs(get)]
+    pub fn day_of_week(&self) -> u32 {
+        self.date.weekday().number_from_monday()
+    }
+
+    #[qjs(get)]
+    pub fn day_of_year(&self) -> u32 {
+        self.date.ordinal()
+    }
+
+    #[qjs(get)]
+    pub fn week_of_year(&self) -> u32 {
+        self.date.iso_week().week()
+    }
+
+    #[qjs(get)]
+    pub fn year_of_week(&self) -> i32 {
+        self.date.iso_week().year()
+    }
+
+    #[qjs(get)]
+    pub fn days_in_week(&self) -> u32 {
+        7
+    }
+
+    #[qjs(get)]
+    pub fn days_in_month(&self) -> u32 {
+        days_in_month(self.date.year(), self.date.month()).unwrap_or_default()
+    }
+
+    #[qjs(get)]
+    pub fn days_in_year(&self) -> u32 {
+        days_in_year(&self.date)
+    }
+
+    #[qjs(get)]
+    pub fn months_in_year(&self) -> u32 {
+        12
+    }
+
+    #[qjs(get)]
+    pub fn in_leap_year(&self) -> bool {
+        days_in_year(&self.date) == 366
+    }
+
+    /// A copy with the fields of `fields` replaced
+    pub fn with(
+        &self,
+        ctx: Ctx<'js>,
+        fields: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let reject = overflow_reject(&ctx, &options)?;
+        let date = date_from_fields(&ctx, &fields, Some(self.date), reject)?;
+        Ok(Self::from_date(date))
+    }
+
+    pub fn add(
+        &self,
+        ctx: Ctx<'js>,
+        duration: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?;
+        let reject = overflow_reject(&ctx, &options)?;
+        Ok(Self::from_date(add_to_date(
+            &ctx, &self.date, &duration, reject,
+        )?))
+    }
+
+    pub fn subtract(
+        &self,
+        ctx: Ctx<'js>,
+        duration: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?.negated();
+        let reject = overflow_reject(&ctx, &options)?;
+        Ok(Self::from_date(add_to_date(
+            &ctx, &self.date, &duration, reject,
+        )?))
+    }
+
+    /// The duration from this date to `other`, in days unless `largestUnit` says otherwise
+    pub fn until(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other, false)?;
+        let largest = largest_date_unit(&ctx, options)?;
+        Ok(date_difference(&self.date, &other.date, largest))
+    }
+
+    pub fn since(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other, false)?;
+        let largest = largest_date_unit(&ctx, options)?;
+        Ok(date_difference(&other.date, &self.date, largest).negated())
+    }
+
+    pub fn equals(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<bool> {
+        Ok(self.date == Self::from_value(&ctx, &other, false)?.date)
+    }
+
+    /// The start of this date in a time zone, given by name or as `{ timeZone }`
+    pub fn to_zoned_date_time(&self, ctx: Ctx<'js>, item: Value<'js>) -> Result<ZonedDateTime> {
+        let time_zone = if item.is_object() {
+            item.get_required::<_, Value>("timeZone", "options")?
+        } else {
+            item
+        };
+        let time_zone = to_time_zone(&ctx, &time_zone)?;
+        let local = self.date.and_time(NaiveTime::MIN);
+        let nanoseconds =
+            local_to_epoch_nanoseconds(&ctx, &time_zone, &local, Disambiguation::Compatible)?;
+        Ok(ZonedDateTime::from_parts(nanoseconds, time_zone))
+    }
+
+    pub fn to_string(&self) -> String {
+        format_date(&self.date)
+    }
+
+    #[qjs(rename = PredefinedAtom::ToJSON)]
+    pub fn to_json(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn to_locale_string(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn value_of(&self, ctx: Ctx<'js>) -> Result<()> {
+        Err(throw_value_of(&ctx, stringify!(PlainDate)))
+    }
+}
+
+impl PlainDate {
+    pub(super) fn from_date(date: NaiveDate) -> Self {
+        Self { date }
+    }
+
+    fn from_value<'js>(ctx: &Ctx<'js>, value: &Value<'js>, reject: bool) -> Result<Self> {
+        if let Ok(plain_date) = Class::<Self>::from_value(value) {
+            return Ok(*plain_date.borrow());
+        }
+        if let Ok(zoned) = Class::<ZonedDateTime>::from_value(value) {
+            let zoned = zoned.borrow();
+            let local = local_date_time(ctx, &zoned.time_zone, zoned.nanoseconds)?;
+            return Ok(Self::from_date(local.date()));
+        }
+        if let Some(string) = value.as_string() {
+            let string = string.to_string()?;
+            // a UTC time names no local date
+            let parsed = parse_date_time(&string)
+                .filter(|parsed| !parsed.utc)
+                .ok_or_else(|| invalid_string(ctx, &string))?;
+            return Ok(Self::from_date(parsed.date));
+        }
+        if value.is_object() {
+            return Ok(Self::from_date(date_from_fields(ctx, value, None, reject)?));
+        }
+        Err(Exception::throw_type(
+            ctx,
+            "Expected a Temporal.PlainDate, string or object",
+        ))
+    }
+}
+
+pub(super) fn month_code(date: &NaiveDate) -> String {
+    format!("M{:02}", date.month())
+}
+
+pub(super) fn days_in_year(date: &NaiveDate) -> u32 {
+    if NaiveDate::from_yo_opt(date.year(), 366).is_some() {
+        366
+    } else {
+        365
+    }
+}
+
+/// Adds years and months, clamping the day unless `reject`, then weeks, days and the
+/// whole days of the smaller units
+pub(super) fn add_to_date(
+    ctx: &Ctx<'_>,
+    date: &NaiveDate,
+    duration: &Duration,
+    reject: bool,
+) -> Result<NaiveDate> {
+    let months = date.month0() as i64 + duration.years * 12 + duration.months;
+    let year = date.year() as i64 + months.div_euclid(12);
+    let date = regulate_date(
+        ctx,
+        year,
+        months.rem_euclid(12) + 1,
+        date.day() as i64,
+        reject,
+    )?;
+    let days = duration.weeks as i128 * 7 + duration.time_nanoseconds() / NS_PER_DAY;
+    i64::try_from(days)
+        .ok()
+        .and_then(TimeDelta::try_days)
+        .and_then(|days| date.checked_add_signed(days))
+        .or_throw_range(ctx, "Date out of range")
+}
+
+/// The duration from `one` to `two` with no unit larger than `largest`
+pub(super) fn date_difference(one: &NaiveDate, two: &NaiveDate, largest: Unit) -> Duration {
+    let mut duration = Duration::default();
+    if largest < Unit::Month {
+        let days = (*two - *one).num_days();
+        if largest == Unit::Week {
+            duration.weeks = days / 7;
+        }
+        duration.days = if largest == Unit::Week {
+            days % 7
+        } else {
+            days
+        };
+        return duration;
+    }
+
+    let sign = if two < one { -1 } else { 1 };
+    let shift = |months: i64| {
+        let months = one.month0() as i64 + months;
+        (
+            one.year() as i64 + months.div_euclid(12),
+            months.rem_euclid(12) + 1,
+        )
+    };
+    let mut months =
+        (two.year() as i64 - one.year() as i64) * 12 + two.month() as i64 - one.month() as i64;
+    // step back while the unclamped day lands past `two`
+    loop {
+        let (year, month) = shift(months);
+        let candidate = (year, month, one.day() as i64);
+        let target = (two.year() as i64, two.month() as i64, two.day() as i64);
+        let surpasses = match candidate.cmp(&target) {
+            Ordering::Greater => sign > 0,
+            Ordering::Less => sign < 0,
+            Ordering::Equal => false,
+        };
+        if !surpasses {
+            break;
+        }
+        months -= sign;
+    }
+
+    let (year, month) = shift(months);
+    let last_day = days_in_month(year as i32, month as u32).unwrap_or(28);
+    let intermediate =
+        NaiveDate::from_ymd_opt(year as i32, month as u32, one.day().min(last_day)).unwrap_or(*one);
+    duration.days = (*two - intermediate).num_days();
+    if largest == Unit::Year {
+        duration.years = months / 12;
+        duration.months = months % 12;
+    } else {
+        duration.months = months;
+    }
+    duration
+}
+
+/// The largest unit of a difference of dates, rounding of it is not supported
+fn largest_date_unit<'js>(ctx: &Ctx<'js>, options: Opt<Value<'js>>) -> Result<Unit> {
+    let options = RoundingOptions::from_value(ctx, options.0, RoundingMode::Trunc)?;
+    if options.smallest_unit.is_some_and(|unit| unit != Unit::Day) || options.increment != 1 {
+        return Err(Exception::throw_range(
+            ctx,
+            "Rounding differences of dates is not supported",
+        ));
+    }
+    match options.largest_unit {
+        Some(unit) if unit < Unit::Day => Err(Exception::throw_range(
+            ctx,
+            "largestUnit of a date difference can't be smaller than a day",
+        )),
+        unit => Ok(unit.unwrap_or(Unit::Day)),
+    }
+}
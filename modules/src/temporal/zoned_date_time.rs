@@ -0,0 +1,611 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use chrono_tz::Tz;
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, function::Opt, Class, Ctx, Exception, Result, Value,
+};
+
+use super::{
+    check_instant, date_from_fields, days_in_month, epoch_nanoseconds, format_date, format_offset,
+    format_time, from_bigint, invalid_string, local_date_time, local_to_epoch_nanoseconds,
+    offset_seconds, overflow_reject,
+    parse::{parse_date_time, parse_offset},
+    parse_time_zone,
+    plain_date::{add_to_date, date_difference, days_in_year, month_code},
+    round, throw_value_of, time_from_fields, to_bigint, to_time_zone, utc_date_time,
+    Disambiguation, Duration, Instant, PlainDate, RoundingMode, RoundingOptions, Unit,
+    NS_PER_SECOND,
+};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+/// An exact time in a time zone, on the ISO 8601 calendar.
+#[derive(Clone, Copy, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct ZonedDateTime {
+    #[qjs(skip_trace)]
+    pub(super) nanoseconds: i128,
+    #[qjs(skip_trace)]
+    pub(super) time_zone: Tz,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> ZonedDateTime {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, epoch_nanoseconds: Value<'js>, time_zone: String) -> Result<Self> {
+        let nanoseconds = check_instant(&ctx, from_bigint(&ctx, &epoch_nanoseconds)?)?;
+        Ok(Self::from_parts(
+            nanoseconds,
+            parse_time_zone(&ctx, &time_zone)?,
+        ))
+    }
+
+    /// A zoned date time from a `ZonedDateTime`, a string with a bracketed time zone or
+    /// an object of fields with a `timeZone`
+    #[qjs(static)]
+    pub fn from(ctx: Ctx<'js>, item: Value<'js>, options: Opt<Value<'js>>) -> Result<Self> {
+        Self::from_value(&ctx, &item, &options)
+    }
+
+    #[qjs(static)]
+    pub fn compare(ctx: Ctx<'js>, one: Value<'js>, two: Value<'js>) -> Result<i32> {
+        let one = Self::from_value(&ctx, &one, &Opt(None))?;
+        let two = Self::from_value(&ctx, &two, &Opt(None))?;
+        Ok(one.nanoseconds.cmp(&two.nanoseconds) as i32)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "Temporal.ZonedDateTime"
+    }
+
+    #[qjs(get)]
+    pub fn calendar_id(&self) -> &'static str {
+        "iso8601"
+    }
+
+    #[qjs(get)]
+    pub fn time_zone_id(&self) -> &'static str {
+        self.time_zone.name()
+    }
+
+    #[qjs(get)]
+    pub fn year(&self, ctx: Ctx<'js>) -> Result<i32> {
+        Ok(self.local(&ctx)?.year())
+    }
+
+    #[qjs(get)]
+    pub fn month(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.month())
+    }
+
+    #[qjs(get)]
+    pub fn month_code(&self, ctx: Ctx<'js>) -> Result<String> {
+        Ok(month_code(&self.local(&ctx)?.date()))
+    }
+
+    #[qjs(get)]
+    pub fn day(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.day())
+    }
+
+    #[qjs(get)]
+    pub fn hour(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.hour())
+    }
+
+    #[qjs(get)]
+    pub fn minute(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.minute())
+    }
+
+    #[qjs(get)]
+    pub fn second(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.second())
+    }
+
+    #[qjs(get)]
+    pub fn millisecond(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.nanosecond() / 1_000_000)
+    }
+
+    #[qjs(get)]
+    pub fn microsecond(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.nanosecond() / 1_000 % 1_000)
+    }
+
+    #[qjs(get)]
+    pub fn nanosecond(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.nanosecond() % 1_000)
+    }
+
+    #[qjs(get)]
+    pub fn day_of_week(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.weekday().number_from_monday())
+    }
+
+    #[qjs(get)]
+    pub fn day_of_year(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.ordinal())
+    }
+
+    #[qjs(get)]
+    pub fn week_of_year(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(self.local(&ctx)?.iso_week().week())
+    }
+
+    #[qjs(get)]
+    pub fn year_of_week(&self, ctx: Ctx<'js>) -> Result<i32> {
+        Ok(self.local(&ctx)?.iso_week().year())
+    }
+
+    #[qjs(get)]
+    pub fn days_in_week(&self) -> u32 {
+        7
+    }
+
+    #[qjs(get)]
+    pub fn days_in_month(&self, ctx: Ctx<'js>) -> Result<u32> {
+        let local = self.local(&ctx)?;
+        Ok(days_in_month(local.year(), local.month()).unwrap_or_default())
+    }
+
+    #[qjs(get)]
+    pub fn days_in_year(&self, ctx: Ctx<'js>) -> Result<u32> {
+        Ok(days_in_year(&self.local(&ctx)?.date()))
+    }
+
+    #[qjs(get)]
+    pub fn months_in_year(&self) -> u32 {
+        12
+    }
+
+    #[qjs(get)]
+    pub fn in_leap_year(&self, ctx: Ctx<'js>) -> Result<bool> {
+        Ok(days_in_year(&self.local(&ctx)?.date()) == 366)
+    }
+
+    /// Hours from the start of this day to the start of the next one, 23 or 25 on days
+    /// the offset changes
+    #[qjs(get)]
+    pub fn hours_in_day(&self, ctx: Ctx<'js>) -> Result<f64> {
+        let (start, end) = self.day_bounds(&ctx)?;
+        Ok((end - start) as f64 / (3600 * NS_PER_SECOND) as f64)
+    }
+
+    #[qjs(get)]
+    pub fn offset(&self) -> String {
+        format_offset(offset_seconds(&self.time_zone, self.nanoseconds))
+    }
+
+    #[qjs(get)]
+    pub fn offset_nanoseconds(&self) -> i64 {
+        offset_seconds(&self.time_zone, self.nanoseconds) as i64 * NS_PER_SECOND as i64
+    }
+
+    #[qjs(get)]
+    pub fn epoch_milliseconds(&self) -> i64 {
+        self.nanoseconds.div_euclid(1_000_000) as i64
+    }
+
+    #[qjs(get)]
+    pub fn epoch_nanoseconds(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        to_bigint(&ctx, self.nanoseconds)
+    }
+
+    /// A copy with the date and time fields of `fields` replaced, keeping the offset when
+    /// it is still valid
+    pub fn with(
+        &self,
+        ctx: Ctx<'js>,
+        fields: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        if !fields.is_object() {
+            return Err(Exception::throw_type(&ctx, "Fields must be an object"));
+        }
+        let reject = overflow_reject(&ctx, &options)?;
+        let local = self.local(&ctx)?;
+        let date = date_from_fields(&ctx, &fields, Some(local.date()), reject)?;
+        let time = time_from_fields(&ctx, &fields, local.time(), reject)?;
+        let offset = match fields.get_optional::<_, String>("offset")? {
+            Some(offset) => parse_offset(&offset)
+                .or_throw_range(&ctx, &["Invalid offset: ", &offset].concat())?,
+            None => offset_seconds(&self.time_zone, self.nanoseconds),
+        };
+        let nanoseconds = resolve(
+            &ctx,
+            &self.time_zone,
+            &date.and_time(time),
+            Some(offset),
+            OffsetOption::from_options(&ctx, &options, OffsetOption::Prefer)?,
+            Disambiguation::from_options(&ctx, &options)?,
+        )?;
+        Ok(Self::from_parts(nanoseconds, self.time_zone))
+    }
+
+    pub fn with_time_zone(&self, ctx: Ctx<'js>, time_zone: Value<'js>) -> Result<Self> {
+        let time_zone = to_time_zone(&ctx, &time_zone)?;
+        Ok(Self::from_parts(self.nanoseconds, time_zone))
+    }
+
+    /// Adds years, months, weeks and days to the wall-clock time, then smaller units to
+    /// the exact time
+    pub fn add(
+        &self,
+        ctx: Ctx<'js>,
+        duration: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?;
+        self.add_duration(&ctx, &duration, &options)
+    }
+
+    pub fn subtract(
+        &self,
+        ctx: Ctx<'js>,
+        duration: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let duration = Duration::from_value(&ctx, &duration)?.negated();
+        self.add_duration(&ctx, &duration, &options)
+    }
+
+    /// The duration from this time to `other`, in hours unless `largestUnit` says
+    /// otherwise
+    pub fn until(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other, &Opt(None))?;
+        self.difference(&ctx, &other, options)
+    }
+
+    pub fn since(
+        &self,
+        ctx: Ctx<'js>,
+        other: Value<'js>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let other = Self::from_value(&ctx, &other, &Opt(None))?;
+        Ok(other.difference(&ctx, self, options)?.negated())
+    }
+
+    /// Rounds the wall-clock time to `smallestUnit`, a day at most
+    pub fn round(&self, ctx: Ctx<'js>, options: Value<'js>) -> Result<Self> {
+        let options = RoundingOptions::from_value(&ctx, Some(options), RoundingMode::HalfExpand)?;
+        let smallest = options
+            .smallest_unit
+            .or_throw_range(&ctx, "smallestUnit is required")?;
+        if smallest > Unit::Day {
+            return Err(Exception::throw_range(&ctx, "smallestUnit is too large"));
+        }
+        if smallest == Unit::Day {
+            let (start, end) = self.day_bounds(&ctx)?;
+            let nanoseconds = start + round(self.nanoseconds - start, end - start, options.mode);
+            return Ok(Self::from_parts(nanoseconds, self.time_zone));
+        }
+
+        let local = epoch_nanoseconds(&self.local(&ctx)?);
+        let local = options.round_nanoseconds(&ctx, local, Unit::Nanosecond)?;
+        let nanoseconds = resolve(
+            &ctx,
+            &self.time_zone,
+            &utc_date_time(&ctx, local)?,
+            Some(offset_seconds(&self.time_zone, self.nanoseconds)),
+            OffsetOption::Prefer,
+            Disambiguation::Compatible,
+        )?;
+        Ok(Self::from_parts(nanoseconds, self.time_zone))
+    }
+
+    pub fn start_of_day(&self, ctx: Ctx<'js>) -> Result<Self> {
+        let (start, _) = self.day_bounds(&ctx)?;
+        Ok(Self::from_parts(start, self.time_zone))
+    }
+
+    /// Same exact time and same time zone
+    pub fn equals(&self, ctx: Ctx<'js>, other: Value<'js>) -> Result<bool> {
+        let other = Self::from_value(&ctx, &other, &Opt(None))?;
+        Ok(self.nanoseconds == other.nanoseconds && self.time_zone == other.time_zone)
+    }
+
+    pub fn to_instant(&self) -> Instant {
+        Instant::from_nanoseconds(self.nanoseconds)
+    }
+
+    pub fn to_plain_date(&self, ctx: Ctx<'js>) -> Result<PlainDate> {
+        Ok(PlainDate::from_date(self.local(&ctx)?.date()))
+    }
+
+    pub fn to_string(&self, ctx: Ctx<'js>) -> Result<String> {
+        let local = self.local(&ctx)?;
+        Ok([
+            format_date(&local.date()),
+            "T".into(),
+            format_time(&local.time()),
+            self.offset(),
+            ["[", self.time_zone.name(), "]"].concat(),
+        ]
+        .concat())
+    }
+
+    #[qjs(rename = PredefinedAtom::ToJSON)]
+    pub fn to_json(&self, ctx: Ctx<'js>) -> Result<String> {
+        self.to_string(ctx)
+    }
+
+    pub fn to_locale_string(&self, ctx: Ctx<'js>) -> Result<String> {
+        self.to_string(ctx)
+    }
+
+    pub fn value_of(&self, ctx: Ctx<'js>) -> Result<()> {
+        Err(throw_value_of(&ctx, stringify!(ZonedDateTime)))
+    }
+}
+
+impl ZonedDateTime {
+    pub(super) fn from_parts(nanoseconds: i128, time_zone: Tz) -> Self {
+        Self {
+            nanoseconds,
+            time_zone,
+        }
+    }
+
+    fn from_value<'js>(
+        ctx: &Ctx<'js>,
+        value: &Value<'js>,
+        options: &Opt<Value<'js>>,
+    ) -> Result<Self> {
+        if let Ok(zoned) = Class::<Self>::from_value(value) {
+            return Ok(*zoned.borrow());
+        }
+        let reject = overflow_reject(ctx, options)?;
+        let disambiguation = Disambiguation::from_options(ctx, options)?;
+        let offset_option = OffsetOption::from_options(ctx, options, OffsetOption::Reject)?;
+
+        if let Some(string) = value.as_string() {
+            let string = string.to_string()?;
+            let parsed = parse_date_time(&string).ok_or_else(|| invalid_string(ctx, &string))?;
+            let time_zone = parsed
+                .time_zone
+                .as_deref()
+                .ok_or_else(|| invalid_string(ctx, &string))?;
+            let time_zone = parse_time_zone(ctx, time_zone)?;
+            let local = parsed.date.and_time(parsed.time.unwrap_or(NaiveTime::MIN));
+            // `Z` fixes the exact time, the local time follows from the time zone
+            let nanoseconds = if parsed.utc {
+                check_instant(ctx, epoch_nanoseconds(&local))?
+            } else {
+                resolve(
+                    ctx,
+                    &time_zone,
+                    &local,
+                    parsed.offset,
+                    offset_option,
+                    disambiguation,
+                )?
+            };
+            return Ok(Self::from_parts(nanoseconds, time_zone));
+        }
+
+        if value.is_object() {
+            let time_zone = value.get_required::<_, Value>("timeZone", "fields")?;
+            let time_zone = to_time_zone(ctx, &time_zone)?;
+            let date = date_from_fields(ctx, value, None, reject)?;
+            let time = time_from_fields(ctx, value, NaiveTime::MIN, reject)?;
+            let offset = match value.get_optional::<_, String>("offset")? {
+                Some(offset) => Some(
+                    parse_offset(&offset)
+                        .or_throw_range(ctx, &["Invalid offset: ", &offset].concat())?,
+                ),
+                None => None,
+            };
+            let nanoseconds = resolve(
+                ctx,
+                &time_zone,
+                &date.and_time(time),
+                offset,
+                offset_option,
+                disambiguation,
+            )?;
+            return Ok(Self::from_parts(nanoseconds, time_zone));
+        }
+
+        Err(Exception::throw_type(
+            ctx,
+            "Expected a Temporal.ZonedDateTime, string or object",
+        ))
+    }
+
+    fn local(&self, ctx: &Ctx<'_>) -> Result<NaiveDateTime> {
+        local_date_time(ctx, &self.time_zone, self.nanoseconds)
+    }
+
+    /// The exact times this day and the next one start
+    fn day_bounds(&self, ctx: &Ctx<'_>) -> Result<(i128, i128)> {
+        let date = self.local(ctx)?.date();
+        let next = date
+            .checked_add_signed(TimeDelta::days(1))
+            .or_throw_range(ctx, "Date out of range")?;
+        Ok((
+            start_of_day(ctx, &self.time_zone, &date)?,
+            start_of_day(ctx, &self.time_zone, &next)?,
+        ))
+    }
+
+    fn add_duration<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        duration: &Duration,
+        options: &Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let reject = overflow_reject(ctx, options)?;
+        let date_part = Duration {
+            years: duration.years,
+            months: duration.months,
+            weeks: duration.weeks,
+            days: duration.days,
+            ..Duration::default()
+        };
+        let time_part = Duration {
+            years: 0,
+            months: 0,
+            weeks: 0,
+            days: 0,
+            ..*duration
+        };
+
+        let mut nanoseconds = self.nanoseconds;
+        if date_part != Duration::default() {
+            let local = self.local(ctx)?;
+            let date = add_to_date(ctx, &local.date(), &date_part, reject)?;
+            nanoseconds = local_to_epoch_nanoseconds(
+                ctx,
+                &self.time_zone,
+                &date.and_time(local.time()),
+                Disambiguation::Compatible,
+            )?;
+        }
+        let nanoseconds = check_instant(ctx, nanoseconds + time_part.time_nanoseconds())?;
+        Ok(Self::from_parts(nanoseconds, self.time_zone))
+    }
+
+    /// Days and longer units count on the wall clock, hours and shorter on the exact time
+    fn difference<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        other: &Self,
+        options: Opt<Value<'js>>,
+    ) -> Result<Duration> {
+        let options = RoundingOptions::from_value(ctx, options.0, RoundingMode::Trunc)?;
+        let smallest = options.smallest_unit.unwrap_or(Unit::Nanosecond);
+        let largest = options
+            .largest_unit
+            .unwrap_or_else(|| Unit::Hour.max(smallest));
+        let nanoseconds = other.nanoseconds - self.nanoseconds;
+        if largest <= Unit::Hour {
+            return Duration::from_difference(ctx, nanoseconds, &options, Unit::Hour);
+        }
+        if smallest > Unit::Hour {
+            return Err(Exception::throw_range(
+                ctx,
+                "Rounding differences to days or longer is not supported",
+            ));
+        }
+        if self.time_zone != other.time_zone {
+            return Err(Exception::throw_range(
+                ctx,
+                "Differences in days or longer need the same time zone",
+            ));
+        }
+        let sign = nanoseconds.signum();
+        if sign == 0 {
+            return Ok(Duration::default());
+        }
+
+        let start = self.local(ctx)?;
+        let mut end_date = other.local(ctx)?.date();
+        // move the end a day closer while the date part overshoots the exact time
+        let (date_part, rest) = loop {
+            let date_part = date_difference(&start.date(), &end_date, largest);
+            let intermediate = add_to_date(ctx, &start.date(), &date_part, false)?;
+            let intermediate = local_to_epoch_nanoseconds(
+                ctx,
+                &self.time_zone,
+                &intermediate.and_time(start.time()),
+                Disambiguation::Compatible,
+            )?;
+            let rest = other.nanoseconds - intermediate;
+            if rest.signum() != -sign {
+                break (date_part, rest);
+            }
+            end_date = end_date
+                .checked_sub_signed(TimeDelta::days(sign as i64))
+                .or_throw_range(ctx, "Date out of range")?;
+        };
+
+        let time_options = RoundingOptions {
+            largest_unit: Some(Unit::Hour),
+            ..options
+        };
+        let time_part = Duration::from_difference(ctx, rest, &time_options, Unit::Hour)?;
+        Duration {
+            years: date_part.years,
+            months: date_part.months,
+            weeks: date_part.weeks,
+            days: date_part.days,
+            ..time_part
+        }
+        .validate(ctx)
+    }
+}
+
+/// How an explicit UTC offset is weighed against the time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OffsetOption {
+    Use,
+    Ignore,
+    Prefer,
+    Reject,
+}
+
+impl OffsetOption {
+    fn from_options<'js>(ctx: &Ctx<'js>, options: &Opt<Value<'js>>, default: Self) -> Result<Self> {
+        let Some(options) = options.0.as_ref() else {
+            return Ok(default);
+        };
+        match options.get_optional::<_, String>("offset")?.as_deref() {
+            None => Ok(default),
+            Some("use") => Ok(Self::Use),
+            Some("ignore") => Ok(Self::Ignore),
+            Some("prefer") => Ok(Self::Prefer),
+            Some("reject") => Ok(Self::Reject),
+            Some(offset) => Err(Exception::throw_range(
+                ctx,
+                &["Invalid offset option: ", offset].concat(),
+            )),
+        }
+    }
+}
+
+/// The exact time of a wall-clock time with an optional offset in `time_zone`
+fn resolve(
+    ctx: &Ctx<'_>,
+    time_zone: &Tz,
+    local: &NaiveDateTime,
+    offset: Option<i32>,
+    option: OffsetOption,
+    disambiguation: Disambiguation,
+) -> Result<i128> {
+    if let Some(offset) = offset {
+        let nanoseconds = epoch_nanoseconds(local) - offset as i128 * NS_PER_SECOND;
+        match option {
+            OffsetOption::Use => return check_instant(ctx, nanoseconds),
+            OffsetOption::Ignore => {}
+            OffsetOption::Prefer | OffsetOption::Reject => {
+                if offset_seconds(time_zone, nanoseconds) == offset {
+                    return check_instant(ctx, nanoseconds);
+                }
+                if option == OffsetOption::Reject {
+                    return Err(Exception::throw_range(
+                        ctx,
+                        &[
+                            "The offset ",
+                            &format_offset(offset),
+                            " is not valid in ",
+                            time_zone.name(),
+                        ]
+                        .concat(),
+                    ));
+                }
+            }
+        }
+    }
+    local_to_epoch_nanoseconds(ctx, time_zone, local, disambiguation)
+}
+
+fn start_of_day(ctx: &Ctx<'_>, time_zone: &Tz, date: &NaiveDate) -> Result<i128> {
+    let local = date.and_time(NaiveTime::MIN);
+    local_to_epoch_nanoseconds(ctx, time_zone, &local, Disambiguation::Compatible)
+}
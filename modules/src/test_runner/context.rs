@@ -0,0 +1,148 @@
+use rsquickjs::{
+    atom::PredefinedAtom,
+    class::Trace,
+    prelude::{Opt, Rest, This},
+    Class, Ctx, Exception, JsLifetime, Promise, Result, Value,
+};
+
+use super::{
+    mock::MockTracker,
+    runner::{run_test, EachHooks, Mode, TestDef},
+};
+use crate::utils::ctx::CtxExtension;
+
+/// The `t` argument passed to a test function.
+#[derive(Trace, JsLifetime)]
+#[rsquickjs::class]
+pub struct TestContext<'js> {
+    #[qjs(skip_trace)]
+    name: String,
+    #[qjs(skip_trace)]
+    depth: usize,
+    #[qjs(skip_trace)]
+    diagnostics: Vec<String>,
+    #[qjs(skip_trace)]
+    skip: Option<String>,
+    #[qjs(skip_trace)]
+    todo: Option<String>,
+    #[qjs(skip_trace)]
+    failed_subtests: usize,
+    mock: Class<'js, MockTracker<'js>>,
+    /// Subtests that have not finished yet; they run one after another.
+    pending: Vec<Promise<'js>>,
+    last: Option<Promise<'js>>,
+}
+
+impl<'js> TestContext<'js> {
+    pub fn new(ctx: &Ctx<'js>, name: &str, depth: usize) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            depth,
+            diagnostics: Vec::new(),
+            skip: None,
+            todo: None,
+            failed_subtests: 0,
+            mock: Class::instance(ctx.clone(), MockTracker::default())?,
+            pending: Vec::new(),
+            last: None,
+        })
+    }
+
+    pub fn mock(&self) -> &Class<'js, MockTracker<'js>> {
+        &self.mock
+    }
+
+    pub fn diagnostics(&self) -> &[String] {
+        &self.diagnostics
+    }
+
+    pub fn skipped(&self) -> Option<String> {
+        self.skip.clone()
+    }
+
+    pub fn todo_reason(&self) -> Option<String> {
+        self.todo.clone()
+    }
+
+    pub fn failed_subtests(&self) -> usize {
+        self.failed_subtests
+    }
+
+    /// Waits for every subtest started with `t.test()`, including ones started meanwhile.
+    pub async fn wait_subtests(this: &Class<'js, Self>) -> Result<()> {
+        loop {
+            let pending = std::mem::take(&mut this.borrow_mut().pending);
+            if pending.is_empty() {
+                return Ok(());
+            }
+            for promise in pending {
+                promise.into_future::<()>().await?;
+            }
+        }
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> TestContext<'js> {
+    #[qjs(constructor)]
+    pub fn constructor(ctx: Ctx<'js>) -> Result<Self> {
+        Err(Exception::throw_type(&ctx, "Illegal constructor"))
+    }
+
+    #[qjs(get)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[qjs(get, rename = "mock")]
+    pub fn mock_tracker(&self) -> Class<'js, MockTracker<'js>> {
+        self.mock.clone()
+    }
+
+    pub fn diagnostic(&mut self, message: String) {
+        self.diagnostics.push(message);
+    }
+
+    pub fn skip(&mut self, message: Opt<String>) {
+        self.skip = Some(message.0.unwrap_or_default());
+    }
+
+    pub fn todo(&mut self, message: Opt<String>) {
+        self.todo = Some(message.0.unwrap_or_default());
+    }
+
+    /// `t.test([name][, options][, fn])` runs a subtest after the previous one finished.
+    pub fn test(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        args: Rest<Value<'js>>,
+    ) -> Result<Promise<'js>> {
+        let (def, promise) = TestDef::parse(&ctx, args, Mode::Run)?;
+        let (depth, last) = {
+            let context = this.borrow();
+            (context.depth + 1, context.last.clone())
+        };
+
+        let parent = this.0.clone();
+        let task_ctx = ctx.clone();
+        ctx.spawn_exit_simple(async move {
+            if let Some(last) = last {
+                last.into_future::<()>().await?;
+            }
+            if !run_test(&task_ctx, &def, depth, &EachHooks::default(), None).await? {
+                parent.borrow_mut().failed_subtests += 1;
+            }
+            Ok(())
+        });
+
+        let mut context = this.borrow_mut();
+        context.pending.push(promise.clone());
+        context.last = Some(promise.clone());
+        Ok(promise)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "TestContext"
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use crate::source_map::SourceMap;
+
+/// Line coverage of the original sources, merged over the instrumented scripts of a run.
+#[derive(Default)]
+pub struct Coverage {
+    /// Lines holding the start of a statement, with whether one of them ran.
+    files: BTreeMap<String, BTreeMap<u32, bool>>,
+}
+
+impl Coverage {
+    /// Adds the counters of an instrumented script, with `positions` as returned by the
+    /// instrumentation and `source_map` the map of the script, read from `dir`.
+    pub fn add(
+        &mut self,
+        dir: &Path,
+        source_map: &SourceMap,
+        positions: &[(u32, u32)],
+        hits: &[u32],
+    ) {
+        for (&(line, column), &hits) in positions.iter().zip(hits) {
+            let Some(original) = source_map.lookup(line, column) else {
+                continue;
+            };
+            let covered = self
+                .files
+                .entry(display_path(dir, original.source))
+                .or_default()
+                .entry(original.line)
+                .or_default();
+            *covered |= hits > 0;
+        }
+    }
+
+    /// Keeps the files for which `keep` holds, to leave out dependencies and the tests.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.files.retain(|file, _| keep(file));
+    }
+
+    /// A table of the covered lines per file, in the format of the spec reporter.
+    pub fn report(&self) -> String {
+        let mut rows = Vec::with_capacity(self.files.len() + 1);
+        let (mut total, mut covered) = (0, 0);
+        for (file, lines) in &self.files {
+            let hit = lines.values().filter(|covered| **covered).count();
+            total += lines.len();
+            covered += hit;
+            let uncovered = lines
+                .iter()
+                .filter(|(_, covered)| !**covered)
+                .map(|(line, _)| *line);
+            rows.push((file.as_str(), percent(hit, lines.len()), ranges(uncovered)));
+        }
+        rows.push(("all files", percent(covered, total), String::new()));
+
+        let width = rows
+            .iter()
+            .map(|(file, ..)| file.len())
+            .max()
+            .unwrap_or_default();
+        let mut text = String::from("ℹ start of coverage report\n");
+        let _ = writeln!(text, "ℹ {:width$} | line % | uncovered lines", "file");
+        for (file, percent, uncovered) in rows {
+            let _ = writeln!(text, "ℹ {file:width$} | {percent:>6.2} | {uncovered}");
+        }
+        text.push_str("ℹ end of coverage report\n");
+        text
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    covered as f64 * 100.0 / total as f64
+}
+
+/// Joins consecutive line numbers, as in `3-5 9`.
+fn ranges(lines: impl Iterator<Item = u32>) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    let ranges: Vec<String> = ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect();
+    ranges.join(" ")
+}
+
+/// Resolves a source of a map against the directory of the map, relative to the working
+/// directory when it is inside it.
+fn display_path(dir: &Path, source: &str) -> String {
+    if source.contains("://") {
+        return source.into();
+    }
+    let path = dir.join(source);
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+    let relative = std::env::current_dir()
+        .and_then(std::fs::canonicalize)
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(PathBuf::from));
+    relative.unwrap_or(path).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_coverage_report() {
+        // a.js lines 1 to 4 map to lines 1 to 4 of /src/a.ts
+        let map = SourceMap::parse(
+            r#"{"version":3,"sources":["/src/a.ts"],"names":[],"mappings":"AAAA;AACA;AACA;AACA"}"#,
+        )
+        .unwrap();
+        let mut coverage = Coverage::default();
+        let positions = [(1, 1), (2, 1), (3, 1), (4, 1)];
+        coverage.add(Path::new("/out"), &map, &positions, &[1, 0, 0, 2]);
+        coverage.add(Path::new("/out"), &map, &positions[..1], &[0]);
+
+        let report = coverage.report();
+        assert!(report.contains("ℹ /src/a.ts |  50.00 | 2-3\n"), "{report}");
+        assert!(report.contains("ℹ all files |  50.00 | \n"), "{report}");
+
+        coverage.retain(|file| !file.ends_with(".ts"));
+        assert!(coverage.report().contains("ℹ all files | 100.00 |"));
+    }
+}
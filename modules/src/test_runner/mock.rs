@@ -0,0 +1,209 @@
+use rsquickjs::{
+    atom::PredefinedAtom,
+    class::Trace,
+    prelude::{Opt, Rest, This},
+    Class, Ctx, Error, Exception, Function, JsLifetime, Object, Result, Value,
+};
+
+/// Call history and behaviour of one mock function, exposed as `fn.mock`.
+#[derive(Trace, JsLifetime)]
+#[rsquickjs::class]
+pub struct MockFunctionContext<'js> {
+    calls: Vec<Object<'js>>,
+    original: Option<Function<'js>>,
+    implementation: Option<Function<'js>>,
+    once: Vec<Function<'js>>,
+    /// Object whose method was replaced by `mock.method()`, with the replaced value.
+    target: Option<(Object<'js>, Value<'js>)>,
+    #[qjs(skip_trace)]
+    key: String,
+}
+
+impl<'js> MockFunctionContext<'js> {
+    fn new(original: Option<Function<'js>>, implementation: Option<Function<'js>>) -> Self {
+        Self {
+            calls: Vec::new(),
+            original,
+            implementation,
+            once: Vec::new(),
+            target: None,
+            key: String::new(),
+        }
+    }
+
+    fn next_implementation(&mut self) -> Option<Function<'js>> {
+        if self.once.is_empty() {
+            self.implementation.clone()
+        } else {
+            Some(self.once.remove(0))
+        }
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> MockFunctionContext<'js> {
+    #[qjs(constructor)]
+    pub fn constructor(ctx: Ctx<'js>) -> Result<Self> {
+        Err(Exception::throw_type(&ctx, "Illegal constructor"))
+    }
+
+    #[qjs(get)]
+    pub fn calls(&self) -> Vec<Object<'js>> {
+        self.calls.clone()
+    }
+
+    pub fn call_count(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn mock_implementation(&mut self, implementation: Function<'js>) {
+        self.implementation = Some(implementation);
+    }
+
+    pub fn mock_implementation_once(&mut self, implementation: Function<'js>) {
+        self.once.push(implementation);
+    }
+
+    pub fn reset_calls(&mut self) {
+        self.calls.clear();
+    }
+
+    pub fn restore(&mut self) -> Result<()> {
+        self.implementation = self.original.clone();
+        self.once.clear();
+        if let Some((target, previous)) = self.target.take() {
+            target.set(self.key.as_str(), previous)?;
+        }
+        Ok(())
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "MockFunctionContext"
+    }
+}
+
+/// Creates a function that records its calls into `context`.
+fn create_mock<'js>(
+    ctx: &Ctx<'js>,
+    context: Class<'js, MockFunctionContext<'js>>,
+) -> Result<Function<'js>> {
+    let state = context.clone();
+    let function = Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>,
+              this: This<Value<'js>>,
+              args: Rest<Value<'js>>|
+              -> Result<Value<'js>> {
+            let implementation = state.borrow_mut().next_implementation();
+            let call = Object::new(ctx.clone())?;
+            call.set("arguments", args.0.clone())?;
+            call.set("this", this.0.clone())?;
+
+            let result = match implementation {
+                Some(implementation) => implementation.call((This(this.0), Rest(args.0))),
+                None => Ok(Value::new_undefined(ctx.clone())),
+            };
+            match result {
+                Ok(value) => {
+                    call.set("result", value.clone())?;
+                    state.borrow_mut().calls.push(call);
+                    Ok(value)
+                }
+                Err(Error::Exception) => {
+                    let error = ctx.catch();
+                    call.set("error", error.clone())?;
+                    state.borrow_mut().calls.push(call);
+                    Err(ctx.throw(error))
+                }
+                Err(err) => Err(err),
+            }
+        },
+    )?;
+    function.set("mock", context)?;
+    Ok(function)
+}
+
+/// `mock` of `node:test`, also available per test as `t.mock`.
+#[derive(Trace, JsLifetime, Default)]
+#[rsquickjs::class]
+pub struct MockTracker<'js> {
+    mocks: Vec<Class<'js, MockFunctionContext<'js>>>,
+}
+
+impl<'js> MockTracker<'js> {
+    fn track(
+        &mut self,
+        ctx: &Ctx<'js>,
+        context: MockFunctionContext<'js>,
+    ) -> Result<(Class<'js, MockFunctionContext<'js>>, Function<'js>)> {
+        let context = Class::instance(ctx.clone(), context)?;
+        self.mocks.push(context.clone());
+        let function = create_mock(ctx, context.clone())?;
+        Ok((context, function))
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> MockTracker<'js> {
+    #[qjs(constructor)]
+    pub fn constructor(ctx: Ctx<'js>) -> Result<Self> {
+        Err(Exception::throw_type(&ctx, "Illegal constructor"))
+    }
+
+    /// `mock.fn([original[, implementation]])`
+    #[qjs(rename = "fn")]
+    pub fn mock_fn(
+        &mut self,
+        ctx: Ctx<'js>,
+        original: Opt<Function<'js>>,
+        implementation: Opt<Function<'js>>,
+    ) -> Result<Function<'js>> {
+        let implementation = implementation.0.or_else(|| original.0.clone());
+        let context = MockFunctionContext::new(original.0, implementation);
+        Ok(self.track(&ctx, context)?.1)
+    }
+
+    /// `mock.method(object, methodName[, implementation])`
+    pub fn method(
+        &mut self,
+        ctx: Ctx<'js>,
+        object: Object<'js>,
+        name: String,
+        implementation: Opt<Function<'js>>,
+    ) -> Result<Function<'js>> {
+        let previous: Value = object.get(name.as_str())?;
+        let original = previous.as_function().cloned().ok_or_else(|| {
+            Exception::throw_type(
+                &ctx,
+                &["The property '", &name, "' is not a function"].concat(),
+            )
+        })?;
+        let implementation = implementation.0.unwrap_or_else(|| original.clone());
+
+        let mut context = MockFunctionContext::new(Some(original), Some(implementation));
+        context.target = Some((object.clone(), previous));
+        context.key = name.clone();
+        let (_, function) = self.track(&ctx, context)?;
+        object.set(name, function.clone())?;
+        Ok(function)
+    }
+
+    pub fn restore_all(&self) -> Result<()> {
+        for mock in &self.mocks {
+            mock.borrow_mut().restore()?;
+        }
+        Ok(())
+    }
+
+    pub fn reset(&mut self) -> Result<()> {
+        self.restore_all()?;
+        self.mocks.clear();
+        Ok(())
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "MockTracker"
+    }
+}
@@ -0,0 +1,250 @@
+//! `node:test`: `test`/`describe` with hooks and mocks, reported as spec or TAP.
+//!
+//! Top-level tests are queued while the module body registers them and run one
+//! after another on the event loop once it yields.
+
+mod context;
+mod coverage;
+mod mock;
+mod reporter;
+mod runner;
+
+use rsquickjs::{
+    module::{Declarations, Exports, ModuleDef},
+    prelude::Rest,
+    Class, Ctx, Function, Module, Object, Result, Value,
+};
+
+pub use self::{
+    coverage::Coverage,
+    reporter::{set_test_reporter, test_reporter, TestReporter},
+    runner::has_failures,
+};
+use self::{
+    mock::MockTracker,
+    reporter::Reporter,
+    runner::{register, register_hook, HookKind, Mode},
+};
+use crate::utils::module::ModuleInfo;
+
+/// Creates `test` or `describe`, along with their `skip`, `todo` and `only` variants.
+fn test_function<'js>(ctx: &Ctx<'js>, suite: bool) -> Result<Function<'js>> {
+    let function = Function::new(ctx.clone(), move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+        register(ctx, args, suite, Mode::Run)
+    })?;
+    // `only` has no effect since every test runs anyway
+    for (name, mode) in [
+        ("skip", Mode::Skip),
+        ("todo", Mode::Todo),
+        ("only", Mode::Run),
+    ] {
+        let variant = Function::new(ctx.clone(), move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+            register(ctx, args, suite, mode)
+        })?;
+        function.set(name, variant)?;
+    }
+    Ok(function)
+}
+
+fn hook_function<'js>(ctx: &Ctx<'js>, kind: HookKind) -> Result<Function<'js>> {
+    Function::new(ctx.clone(), move |ctx: Ctx<'js>, hook: Function<'js>| {
+        register_hook(ctx, kind, hook)
+    })
+}
+
+const EXPORTS: [&str; 9] = [
+    "test",
+    "it",
+    "describe",
+    "suite",
+    "before",
+    "after",
+    "beforeEach",
+    "afterEach",
+    "mock",
+];
+
+/// Makes the reporter collect its output instead of printing it, so files run side by side
+/// don't interleave. Must be called before `node:test` is first imported.
+pub fn buffer_output(ctx: &Ctx<'_>) -> Result<()> {
+    runner::init(ctx, Reporter::buffered())
+}
+
+/// The output collected since the last call, see [`buffer_output`].
+pub fn take_output(ctx: &Ctx<'_>) -> Result<String> {
+    runner::take_output(ctx)
+}
+
+/// Imports `node:test` and exposes its exports as globals, for `xmas test`.
+pub async fn install_globals(ctx: &Ctx<'_>) -> Result<()> {
+    let exports: Object = Module::import(ctx, "node:test")?.into_future().await?;
+    let globals = ctx.globals();
+    for name in EXPORTS {
+        globals.set(name, exports.get::<_, Value>(name)?)?;
+    }
+    Ok(())
+}
+
+pub struct TestModule;
+
+impl ModuleDef for TestModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        for name in EXPORTS {
+            declare.declare(name)?;
+        }
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        runner::init(ctx, Reporter::default())?;
+
+        let test = test_function(ctx, false)?;
+        let describe = test_function(ctx, true)?;
+        let values = [
+            test.clone().into_value(),
+            test.clone().into_value(),
+            describe.clone().into_value(),
+            describe.into_value(),
+            hook_function(ctx, HookKind::Before)?.into_value(),
+            hook_function(ctx, HookKind::After)?.into_value(),
+            hook_function(ctx, HookKind::BeforeEach)?.into_value(),
+            hook_function(ctx, HookKind::AfterEach)?.into_value(),
+            Class::instance(ctx.clone(), MockTracker::default())?.into_value(),
+        ];
+
+        // The default export is `test` itself, carrying everything else
+        for (name, value) in EXPORTS.into_iter().zip(values) {
+            test.set(name, value.clone())?;
+            exports.export(name, value)?;
+        }
+        exports.export("default", test)?;
+        Ok(())
+    }
+}
+
+impl From<TestModule> for ModuleInfo<TestModule> {
+    fn from(val: TestModule) -> Self {
+        ModuleInfo {
+            name: "test",
+            module: val,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::utils::{
+        primordials::{BasePrimordials, Primordial},
+        test::{call_test, test_async_with, ModuleEvaluator},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_test_runner() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                BasePrimordials::init(&ctx).unwrap();
+                crate::permissions::init(ctx.clone(), Arc::new(xmas_vsys::Vsys::new())).unwrap();
+                runner::init(&ctx, Reporter::capture(TestReporter::Tap)).unwrap();
+                ModuleEvaluator::eval_rust::<TestModule>(ctx.clone(), "test")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "main",
+                    r#"
+                        import nodeTest, { describe, it, beforeEach, afterEach, mock } from 'test';
+
+                        const log = [];
+                        describe('math', () => {
+                            beforeEach(() => log.push('before'));
+                            afterEach(() => log.push('after'));
+                            it('adds', () => {
+                                if (1 + 1 !== 2) throw new Error('bad');
+                            });
+                            it.skip('skipped');
+                            it('later', { todo: 'not yet' }, () => {
+                                throw new Error('ignored');
+                            });
+                        });
+
+                        nodeTest('fails', () => {
+                            throw new Error('boom');
+                        });
+
+                        nodeTest('callback', (t, done) => {
+                            done();
+                        });
+
+                        const last = nodeTest('subtests', async (t) => {
+                            await t.test('inner', () => {});
+                            t.diagnostic('note');
+
+                            const calculator = { add: (a, b) => a + b };
+                            const add = t.mock.method(calculator, 'add');
+                            calculator.add(1, 2);
+                            log.push(`${add.mock.callCount()}:${add.mock.calls[0].result}`);
+
+                            const fn = mock.fn();
+                            fn.mock.mockImplementationOnce(() => 'once');
+                            log.push(`${fn()}:${fn()}`);
+                        });
+
+                        export async function test() {
+                            await last;
+                            return log.join(',');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(result, "before,after,before,after,1:3,once:undefined");
+
+                let mut output = runner::output(&ctx).unwrap();
+                for _ in 0..100 {
+                    if output.contains("# duration_ms") {
+                        break;
+                    }
+                    tokio::task::yield_now().await;
+                    output = runner::output(&ctx).unwrap();
+                }
+
+                for line in [
+                    "TAP version 13",
+                    "# Subtest: math",
+                    "    ok 1 - adds",
+                    "    ok 2 - skipped # SKIP",
+                    "    ok 3 - later # TODO not yet",
+                    "    1..3",
+                    "ok 1 - math",
+                    "not ok 2 - fails",
+                    "  code: 'ERR_TEST_FAILURE'",
+                    "ok 3 - callback",
+                    "    ok 1 - inner",
+                    "# note",
+                    "ok 4 - subtests",
+                    "1..4",
+                    "# tests 7",
+                    "# suites 1",
+                    "# pass 4",
+                    "# fail 1",
+                    "# skipped 1",
+                    "# todo 1",
+                ] {
+                    assert!(
+                        output.lines().any(|l| l == line),
+                        "missing {line:?} in:\n{output}"
+                    );
+                }
+                assert!(has_failures());
+            })
+        })
+        .await;
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    fmt::Write as _,
+    io::{stdout, IsTerminal, Write},
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::utils::console::Color;
+
+static TAP: AtomicBool = AtomicBool::new(false);
+
+/// Output format of the test runner, as selected by `--test-reporter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestReporter {
+    /// Human readable tree, the default of Node.js.
+    #[default]
+    Spec,
+    /// Test Anything Protocol, version 13.
+    Tap,
+}
+
+impl FromStr for TestReporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "spec" => Ok(Self::Spec),
+            "tap" => Ok(Self::Tap),
+            _ => Err(["Invalid test reporter: ", s].concat()),
+        }
+    }
+}
+
+pub fn set_test_reporter(reporter: TestReporter) {
+    TAP.store(reporter == TestReporter::Tap, Ordering::Relaxed);
+}
+
+pub fn test_reporter() -> TestReporter {
+    if TAP.load(Ordering::Relaxed) {
+        TestReporter::Tap
+    } else {
+        TestReporter::Spec
+    }
+}
+
+/// How a test or suite finished. Failures carry the formatted error.
+#[derive(Clone)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(String),
+    Todo(String),
+}
+
+pub struct Stats {
+    pub tests: usize,
+    pub suites: usize,
+    pub pass: usize,
+    pub fail: usize,
+    pub skipped: usize,
+    pub todo: usize,
+    pub started: Instant,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            tests: 0,
+            suites: 0,
+            pass: 0,
+            fail: 0,
+            skipped: 0,
+            todo: 0,
+            started: Instant::now(),
+        }
+    }
+}
+
+pub struct Reporter {
+    kind: TestReporter,
+    /// Number of finished items per nesting level, used for TAP test points.
+    counters: Vec<usize>,
+    header: bool,
+    color: bool,
+    /// Collects the output instead of printing it, for tests and parallel runs.
+    buffer: Option<String>,
+}
+
+impl Default for Reporter {
+    fn default() -> Self {
+        let kind = test_reporter();
+        Self {
+            kind,
+            counters: Vec::new(),
+            header: false,
+            color: kind == TestReporter::Spec && stdout().is_terminal(),
+            buffer: None,
+        }
+    }
+}
+
+impl Reporter {
+    /// Collects the output, to be printed in one piece with [`Reporter::take_output`].
+    pub fn buffered() -> Self {
+        Self {
+            buffer: Some(String::new()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn capture(kind: TestReporter) -> Self {
+        Self {
+            kind,
+            color: false,
+            ..Self::buffered()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn output(&self) -> &str {
+        self.buffer.as_deref().unwrap_or_default()
+    }
+
+    pub fn take_output(&mut self) -> String {
+        self.buffer.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn start(&mut self, depth: usize, name: &str, suite: bool) {
+        self.counters.resize(depth + 2, 0);
+        self.counters[depth + 1] = 0;
+
+        let mut line = String::new();
+        match self.kind {
+            TestReporter::Tap => {
+                self.tap_header(&mut line);
+                indent(&mut line, depth, 4);
+                line.push_str("# Subtest: ");
+                line.push_str(name);
+            }
+            TestReporter::Spec if suite => {
+                indent(&mut line, depth, 2);
+                line.push_str("▶ ");
+                line.push_str(name);
+            }
+            TestReporter::Spec => return,
+        }
+        line.push('\n');
+        self.write(&line);
+    }
+
+    pub fn end(
+        &mut self,
+        depth: usize,
+        name: &str,
+        outcome: &Outcome,
+        duration: Duration,
+        diagnostics: &[String],
+        suite: bool,
+    ) {
+        let children = self.counters.get(depth + 1).copied().unwrap_or_default();
+        self.counters.resize(depth + 1, 0);
+        self.counters[depth] += 1;
+        let number = self.counters[depth];
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+
+        let mut text = String::new();
+        match self.kind {
+            TestReporter::Tap => {
+                for diagnostic in diagnostics {
+                    indent(&mut text, depth, 4);
+                    let _ = writeln!(text, "# {diagnostic}");
+                }
+                if children > 0 {
+                    indent(&mut text, depth + 1, 4);
+                    let _ = writeln!(text, "1..{children}");
+                }
+                indent(&mut text, depth, 4);
+                let status = match outcome {
+                    Outcome::Fail(_) => "not ok",
+                    _ => "ok",
+                };
+                let _ = write!(text, "{status} {number} - {name}");
+                match outcome {
+                    Outcome::Skip(reason) => directive(&mut text, "SKIP", reason),
+                    Outcome::Todo(reason) => directive(&mut text, "TODO", reason),
+                    _ => {}
+                }
+                text.push('\n');
+
+                indent(&mut text, depth, 4);
+                text.push_str("  ---\n");
+                indent(&mut text, depth, 4);
+                let _ = writeln!(text, "  duration_ms: {duration_ms:.3}");
+                if suite {
+                    indent(&mut text, depth, 4);
+                    text.push_str("  type: 'suite'\n");
+                }
+                if let Outcome::Fail(error) = outcome {
+                    indent(&mut text, depth, 4);
+                    text.push_str("  failureType: 'testCodeFailure'\n");
+                    let mut lines = error.lines();
+                    let message = lines.next().unwrap_or_default();
+                    indent(&mut text, depth, 4);
+                    let _ = writeln!(text, "  error: '{}'", message.replace('\'', "''"));
+                    indent(&mut text, depth, 4);
+                    text.push_str("  code: 'ERR_TEST_FAILURE'\n");
+                    let mut stack = lines
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .peekable();
+                    if stack.peek().is_some() {
+                        indent(&mut text, depth, 4);
+                        text.push_str("  stack: |-\n");
+                        for line in stack {
+                            indent(&mut text, depth, 4);
+                            let _ = writeln!(text, "    {line}");
+                        }
+                    }
+                }
+                indent(&mut text, depth, 4);
+                text.push_str("  ...\n");
+            }
+            TestReporter::Spec => {
+                indent(&mut text, depth, 2);
+                let (symbol, color) = match outcome {
+                    Outcome::Pass if suite => ("▶", None),
+                    Outcome::Pass | Outcome::Todo(_) => ("✔", Some(Color::GREEN)),
+                    Outcome::Fail(_) => ("✖", Some(Color::RED)),
+                    Outcome::Skip(_) => ("﹣", Some(Color::CYAN)),
+                };
+                self.paint(&mut text, color, &[symbol, " ", name].concat());
+                let _ = write!(text, " ({duration_ms:.3}ms)");
+                match outcome {
+                    Outcome::Skip(reason) => directive(&mut text, "SKIP", reason),
+                    Outcome::Todo(reason) => directive(&mut text, "TODO", reason),
+                    _ => {}
+                }
+                text.push('\n');
+
+                if let Outcome::Fail(error) = outcome {
+                    for line in error.lines() {
+                        indent(&mut text, depth + 1, 2);
+                        text.push_str(line);
+                        text.push('\n');
+                    }
+                }
+                for diagnostic in diagnostics {
+                    indent(&mut text, depth + 1, 2);
+                    let _ = writeln!(text, "ℹ {diagnostic}");
+                }
+            }
+        }
+        self.write(&text);
+    }
+
+    pub fn summary(&mut self, stats: &Stats) {
+        let mut text = String::new();
+        let prefix = match self.kind {
+            TestReporter::Tap => {
+                self.tap_header(&mut text);
+                let _ = writeln!(text, "1..{}", self.counters.first().copied().unwrap_or(0));
+                "# "
+            }
+            TestReporter::Spec => "ℹ ",
+        };
+        let duration_ms = stats.started.elapsed().as_secs_f64() * 1000.0;
+        for (label, value) in [
+            ("tests", stats.tests),
+            ("suites", stats.suites),
+            ("pass", stats.pass),
+            ("fail", stats.fail),
+            ("cancelled", 0),
+            ("skipped", stats.skipped),
+            ("todo", stats.todo),
+        ] {
+            let _ = writeln!(text, "{prefix}{label} {value}");
+        }
+        let _ = writeln!(text, "{prefix}duration_ms {duration_ms:.3}");
+        self.counters.clear();
+        self.write(&text);
+    }
+
+    fn tap_header(&mut self, text: &mut String) {
+        if !self.header {
+            self.header = true;
+            text.push_str("TAP version 13\n");
+        }
+    }
+
+    fn paint(&self, text: &mut String, color: Option<Color>, value: &str) {
+        match color {
+            Some(color) if self.color => {
+                text.push_str(color.as_ref());
+                text.push_str(value);
+                text.push_str(Color::RESET.as_ref());
+            }
+            _ => text.push_str(value),
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        match &mut self.buffer {
+            Some(buffer) => buffer.push_str(text),
+            None => {
+                let mut stdout = stdout().lock();
+                let _ = stdout.write_all(text.as_bytes());
+                let _ = stdout.flush();
+            }
+        }
+    }
+}
+
+fn indent(text: &mut String, depth: usize, width: usize) {
+    text.push_str(&" ".repeat(depth * width));
+}
+
+fn directive(text: &mut String, kind: &str, reason: &str) {
+    text.push_str(" # ");
+    text.push_str(kind);
+    if !reason.is_empty() {
+        text.push(' ');
+        text.push_str(reason);
+    }
+}
@@ -0,0 +1,578 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use rsquickjs::{
+    prelude::{Opt, Rest},
+    Class, Ctx, Error, Function, JsLifetime, Object, Promise, Result, Value,
+};
+
+use super::{
+    context::TestContext,
+    reporter::{Outcome, Reporter, Stats},
+};
+use crate::{
+    source_map::remap_stack,
+    utils::{
+        console::{format_plain, print_error},
+        ctx::CtxExtension,
+        error::ErrorExtensions,
+        object::ObjectExt,
+        result::ResultExt,
+    },
+};
+
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Whether any test failed so far, used to pick the exit code.
+pub fn has_failures() -> bool {
+    FAILED.load(Ordering::Relaxed)
+}
+
+/// Variant of `test()` or `describe()` that was called.
+#[derive(Clone, Copy)]
+pub enum Mode {
+    Run,
+    Skip,
+    Todo,
+}
+
+#[derive(Default)]
+pub struct TestOptions {
+    pub skip: Option<String>,
+    pub todo: Option<String>,
+    pub timeout: Option<Duration>,
+}
+
+impl TestOptions {
+    fn from_object(options: &Object<'_>) -> Result<Self> {
+        Ok(Self {
+            skip: reason(options.get_optional("skip")?)?,
+            todo: reason(options.get_optional("todo")?)?,
+            timeout: options
+                .get_optional::<_, f64>("timeout")?
+                .filter(|timeout| timeout.is_finite() && *timeout >= 0.0)
+                .map(|timeout| Duration::from_millis(timeout as u64)),
+        })
+    }
+}
+
+/// `skip` and `todo` accept either a boolean or a message.
+fn reason(value: Option<Value<'_>>) -> Result<Option<String>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    if let Some(message) = value.as_string() {
+        return Ok(Some(message.to_string()?));
+    }
+    Ok(match value.as_bool() {
+        Some(false) => None,
+        _ if value.is_undefined() || value.is_null() => None,
+        _ => Some(String::new()),
+    })
+}
+
+pub struct TestDef<'js> {
+    pub name: String,
+    pub options: TestOptions,
+    pub func: Option<Function<'js>>,
+    pub resolve: Function<'js>,
+}
+
+impl<'js> TestDef<'js> {
+    /// Parses the `([name][, options][, fn])` arguments shared by `test()` and `describe()`.
+    pub fn parse(
+        ctx: &Ctx<'js>,
+        args: Rest<Value<'js>>,
+        mode: Mode,
+    ) -> Result<(Self, Promise<'js>)> {
+        let mut name = None;
+        let mut options = TestOptions::default();
+        let mut func = None;
+        for value in args.0 {
+            if let Some(value) = value.as_string() {
+                name = Some(value.to_string()?);
+            } else if let Some(value) = value.as_function() {
+                func = Some(value.clone());
+            } else if let Some(value) = value.as_object() {
+                options = TestOptions::from_object(value)?;
+            }
+        }
+
+        let name = match (name, &func) {
+            (Some(name), _) => name,
+            (None, Some(func)) => func
+                .get_optional::<_, String>("name")?
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "<anonymous>".into()),
+            (None, None) => "<anonymous>".into(),
+        };
+        match mode {
+            Mode::Run => {}
+            Mode::Skip => {
+                options.skip.get_or_insert_with(String::new);
+            }
+            Mode::Todo => {
+                options.todo.get_or_insert_with(String::new);
+            }
+        }
+
+        let (promise, resolve, _) = Promise::new(ctx)?;
+        Ok((
+            Self {
+                name,
+                options,
+                func,
+                resolve,
+            },
+            promise,
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct Hooks<'js> {
+    before: Vec<Function<'js>>,
+    after: Vec<Function<'js>>,
+    before_each: Vec<Function<'js>>,
+    after_each: Vec<Function<'js>>,
+}
+
+#[derive(Clone, Copy)]
+pub enum HookKind {
+    Before,
+    After,
+    BeforeEach,
+    AfterEach,
+}
+
+/// `beforeEach`/`afterEach` hooks inherited from the enclosing suites.
+#[derive(Clone, Default)]
+pub struct EachHooks<'js> {
+    before: Vec<Function<'js>>,
+    after: Vec<Function<'js>>,
+}
+
+impl<'js> EachHooks<'js> {
+    /// Outer `beforeEach` hooks run first, outer `afterEach` hooks run last.
+    fn extend(&self, hooks: &Hooks<'js>) -> Self {
+        let mut before = self.before.clone();
+        before.extend(hooks.before_each.iter().cloned());
+        let mut after = hooks.after_each.clone();
+        after.extend(self.after.iter().cloned());
+        Self { before, after }
+    }
+}
+
+pub struct Suite<'js> {
+    def: TestDef<'js>,
+    items: Vec<Item<'js>>,
+    hooks: Hooks<'js>,
+    /// Promise returned by an async `describe()` callback.
+    setup: Option<Promise<'js>>,
+    error: Option<String>,
+}
+
+pub enum Item<'js> {
+    Test(TestDef<'js>),
+    Suite(Rc<RefCell<Suite<'js>>>),
+}
+
+pub struct TestState<'js> {
+    root: Hooks<'js>,
+    /// Suites whose callback is currently registering children.
+    stack: Vec<Rc<RefCell<Suite<'js>>>>,
+    /// Top-level tests and suites waiting to run.
+    queue: VecDeque<Item<'js>>,
+    running: bool,
+    stats: Stats,
+    reporter: Reporter,
+}
+
+unsafe impl<'js> JsLifetime<'js> for TestState<'js> {
+    type Changed<'to> = TestState<'to>;
+}
+
+pub fn init(ctx: &Ctx<'_>, reporter: Reporter) -> Result<()> {
+    if ctx.userdata::<RefCell<TestState>>().is_none() {
+        let _ = ctx.store_userdata(RefCell::new(TestState {
+            root: Hooks::default(),
+            stack: Vec::new(),
+            queue: VecDeque::new(),
+            running: false,
+            stats: Stats::default(),
+            reporter,
+        }));
+    }
+    Ok(())
+}
+
+fn with_state<'js, R>(ctx: &Ctx<'js>, f: impl FnOnce(&mut TestState<'js>) -> R) -> Result<R> {
+    let state = ctx.userdata::<RefCell<TestState>>().or_throw(ctx)?;
+    let mut state = state.borrow_mut();
+    Ok(f(&mut state))
+}
+
+#[cfg(test)]
+pub fn output(ctx: &Ctx<'_>) -> Result<String> {
+    with_state(ctx, |state| state.reporter.output().to_string())
+}
+
+pub fn take_output(ctx: &Ctx<'_>) -> Result<String> {
+    with_state(ctx, |state| state.reporter.take_output())
+}
+
+/// Registers a test or suite, scheduling top-level ones on the event loop.
+pub fn register<'js>(
+    ctx: Ctx<'js>,
+    args: Rest<Value<'js>>,
+    suite: bool,
+    mode: Mode,
+) -> Result<Promise<'js>> {
+    let (def, promise) = TestDef::parse(&ctx, args, mode)?;
+    let item = if suite {
+        let func = def.func.clone();
+        let suite = Rc::new(RefCell::new(Suite {
+            def,
+            items: Vec::new(),
+            hooks: Hooks::default(),
+            setup: None,
+            error: None,
+        }));
+        if let Some(func) = func {
+            with_state(&ctx, |state| state.stack.push(suite.clone()))?;
+            let result = func.call::<_, Value>(());
+            with_state(&ctx, |state| state.stack.pop())?;
+            let mut suite = suite.borrow_mut();
+            match result {
+                Ok(value) => suite.setup = value.into_promise(),
+                Err(err) => suite.error = Some(failure(&ctx, err)),
+            }
+        }
+        Item::Suite(suite)
+    } else {
+        Item::Test(def)
+    };
+
+    let start = with_state(&ctx, |state| match state.stack.last() {
+        Some(parent) => {
+            parent.borrow_mut().items.push(item);
+            false
+        }
+        None => {
+            state.queue.push_back(item);
+            !std::mem::replace(&mut state.running, true)
+        }
+    })?;
+    if start {
+        ctx.spawn_exit_simple(drive(ctx.clone()));
+    }
+    Ok(promise)
+}
+
+pub fn register_hook<'js>(ctx: Ctx<'js>, kind: HookKind, hook: Function<'js>) -> Result<()> {
+    with_state(&ctx, |state| {
+        let mut parent = state.stack.last().map(|suite| suite.borrow_mut());
+        let hooks = match &mut parent {
+            Some(suite) => &mut suite.hooks,
+            None => &mut state.root,
+        };
+        match kind {
+            HookKind::Before => hooks.before.push(hook),
+            HookKind::After => hooks.after.push(hook),
+            HookKind::BeforeEach => hooks.before_each.push(hook),
+            HookKind::AfterEach => hooks.after_each.push(hook),
+        }
+    })
+}
+
+/// Runs queued top-level items until the queue stays empty, then prints the summary.
+async fn drive(ctx: Ctx<'_>) -> Result<()> {
+    // Let the rest of the module register its tests and hooks first
+    tokio::task::yield_now().await;
+
+    let (before, after, each) = with_state(&ctx, |state| {
+        (
+            state.root.before.clone(),
+            state.root.after.clone(),
+            EachHooks::default().extend(&state.root),
+        )
+    })?;
+    let inherited = run_hooks(&ctx, &before, None)
+        .await
+        .err()
+        .map(|err| Outcome::Fail(failure(&ctx, err)));
+
+    loop {
+        let item = with_state(&ctx, |state| state.queue.pop_front())?;
+        match item {
+            Some(item) => {
+                run_item(&ctx, item, 0, &each, inherited.clone()).await?;
+            }
+            None => {
+                tokio::task::yield_now().await;
+                if with_state(&ctx, |state| state.queue.is_empty())? {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Err(err) = run_hooks(&ctx, &after, None).await {
+        FAILED.store(true, Ordering::Relaxed);
+        let error = err.into_value(&ctx)?;
+        print_error(&ctx, Rest(vec![error]))?;
+    }
+    with_state(&ctx, |state| {
+        state.reporter.summary(&state.stats);
+        state.stats = Stats::default();
+        state.running = false;
+    })
+}
+
+fn run_item<'a, 'js>(
+    ctx: &'a Ctx<'js>,
+    item: Item<'js>,
+    depth: usize,
+    each: &'a EachHooks<'js>,
+    inherited: Option<Outcome>,
+) -> Pin<Box<dyn Future<Output = Result<bool>> + 'a>> {
+    Box::pin(async move {
+        match item {
+            Item::Test(test) => run_test(ctx, &test, depth, each, inherited).await,
+            Item::Suite(suite) => run_suite(ctx, suite, depth, each, inherited).await,
+        }
+    })
+}
+
+async fn run_suite<'js>(
+    ctx: &Ctx<'js>,
+    suite: Rc<RefCell<Suite<'js>>>,
+    depth: usize,
+    each: &EachHooks<'js>,
+    inherited: Option<Outcome>,
+) -> Result<bool> {
+    let started = Instant::now();
+    let (name, skip, setup) = {
+        let suite = suite.borrow();
+        (
+            suite.def.name.clone(),
+            suite.def.options.skip.clone(),
+            suite.setup.clone(),
+        )
+    };
+    with_state(ctx, |state| state.reporter.start(depth, &name, true))?;
+
+    let (mut error, skip) = match inherited {
+        Some(Outcome::Fail(error)) => (Some(error), skip),
+        Some(Outcome::Skip(reason)) => (None, skip.or(Some(reason))),
+        _ => (suite.borrow().error.clone(), skip),
+    };
+    if let (None, Some(setup)) = (&error, setup) {
+        if let Err(err) = setup.into_future::<Value>().await {
+            error = Some(failure(ctx, err));
+        }
+    }
+
+    let (before, after, items, children) = {
+        let mut suite = suite.borrow_mut();
+        (
+            suite.hooks.before.clone(),
+            suite.hooks.after.clone(),
+            std::mem::take(&mut suite.items),
+            each.extend(&suite.hooks),
+        )
+    };
+    if error.is_none() && skip.is_none() {
+        if let Err(err) = run_hooks(ctx, &before, None).await {
+            error = Some(failure(ctx, err));
+        }
+    }
+
+    // Children of a failed or skipped suite inherit its outcome
+    let inherited = match (&error, &skip) {
+        (Some(error), _) => Some(Outcome::Fail(error.clone())),
+        (None, Some(reason)) => Some(Outcome::Skip(reason.clone())),
+        (None, None) => None,
+    };
+    let mut passed = error.is_none();
+    for item in items {
+        passed &= run_item(ctx, item, depth + 1, &children, inherited.clone()).await?;
+    }
+
+    if error.is_none() && skip.is_none() {
+        if let Err(err) = run_hooks(ctx, &after, None).await {
+            error = Some(failure(ctx, err));
+            passed = false;
+        }
+    }
+    if error.is_some() {
+        FAILED.store(true, Ordering::Relaxed);
+    }
+
+    let outcome = match (error, skip) {
+        (Some(error), _) => Outcome::Fail(error),
+        (None, Some(reason)) => Outcome::Skip(reason),
+        (None, None) if !passed => Outcome::Fail("a subtest failed".into()),
+        (None, None) => Outcome::Pass,
+    };
+    with_state(ctx, |state| {
+        state.stats.suites += 1;
+        state
+            .reporter
+            .end(depth, &name, &outcome, started.elapsed(), &[], true);
+    })?;
+    suite.borrow().def.resolve.call::<_, ()>(())?;
+    Ok(passed)
+}
+
+/// Runs a single test, its `beforeEach`/`afterEach` hooks and its subtests.
+///
+/// Returns whether the test passed; a failing test never rejects its promise.
+pub async fn run_test<'js>(
+    ctx: &Ctx<'js>,
+    test: &TestDef<'js>,
+    depth: usize,
+    each: &EachHooks<'js>,
+    inherited: Option<Outcome>,
+) -> Result<bool> {
+    let started = Instant::now();
+    with_state(ctx, |state| state.reporter.start(depth, &test.name, false))?;
+
+    let mut diagnostics = Vec::new();
+    let outcome = if let Some(outcome) = inherited {
+        outcome
+    } else if let Some(reason) = &test.options.skip {
+        Outcome::Skip(reason.clone())
+    } else {
+        let context = Class::instance(ctx.clone(), TestContext::new(ctx, &test.name, depth)?)?;
+        let body = execute(ctx, test, &context, each);
+        let result = match test.options.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, body)
+                .await
+                .unwrap_or_else(|_| Err(format!("test timed out after {}ms", timeout.as_millis()))),
+            None => body.await,
+        };
+
+        let context = context.borrow();
+        context.mock().borrow_mut().reset()?;
+        diagnostics = context.diagnostics().to_vec();
+        let failed = context.failed_subtests();
+        match (
+            result,
+            context.skipped(),
+            context.todo_reason().or(test.options.todo.clone()),
+        ) {
+            (_, Some(reason), _) => Outcome::Skip(reason),
+            (_, None, Some(reason)) => Outcome::Todo(reason),
+            (Err(error), None, None) => Outcome::Fail(error),
+            (Ok(()), None, None) if failed == 1 => Outcome::Fail("1 subtest failed".into()),
+            (Ok(()), None, None) if failed > 1 => {
+                Outcome::Fail(format!("{failed} subtests failed"))
+            }
+            (Ok(()), None, None) => Outcome::Pass,
+        }
+    };
+
+    let passed = !matches!(outcome, Outcome::Fail(_));
+    if !passed {
+        FAILED.store(true, Ordering::Relaxed);
+    }
+    with_state(ctx, |state| {
+        let stats = &mut state.stats;
+        stats.tests += 1;
+        match &outcome {
+            Outcome::Pass => stats.pass += 1,
+            Outcome::Fail(_) => stats.fail += 1,
+            Outcome::Skip(_) => stats.skipped += 1,
+            Outcome::Todo(_) => stats.todo += 1,
+        }
+        state.reporter.end(
+            depth,
+            &test.name,
+            &outcome,
+            started.elapsed(),
+            &diagnostics,
+            false,
+        );
+    })?;
+    test.resolve.call::<_, ()>(())?;
+    Ok(passed)
+}
+
+async fn execute<'js>(
+    ctx: &Ctx<'js>,
+    test: &TestDef<'js>,
+    context: &Class<'js, TestContext<'js>>,
+    each: &EachHooks<'js>,
+) -> std::result::Result<(), String> {
+    let t = context.clone().into_value();
+    run_hooks(ctx, &each.before, Some(t.clone()))
+        .await
+        .map_err(|err| failure(ctx, err))?;
+
+    let body = async {
+        if let Some(func) = &test.func {
+            if func.get::<_, usize>("length")? >= 2 {
+                // Callback style: `test(name, (t, done) => ...)`
+                let (promise, resolve, reject) = Promise::new(ctx)?;
+                let done = Function::new(ctx.clone(), move |err: Opt<Value<'js>>| -> Result<()> {
+                    match err.0.filter(|err| !err.is_undefined() && !err.is_null()) {
+                        Some(err) => reject.call((err,)),
+                        None => resolve.call(()),
+                    }
+                })?;
+                func.call::<_, ()>((t.clone(), done))?;
+                promise.into_future::<Value>().await?;
+            } else {
+                settle(func.call((t.clone(),))?).await?;
+            }
+        }
+        TestContext::wait_subtests(context).await
+    }
+    .await
+    .map_err(|err| failure(ctx, err));
+
+    let after = run_hooks(ctx, &each.after, Some(t))
+        .await
+        .map_err(|err| failure(ctx, err));
+    body.and(after)
+}
+
+async fn settle(value: Value<'_>) -> Result<()> {
+    if let Some(promise) = value.into_promise() {
+        promise.into_future::<Value>().await?;
+    }
+    Ok(())
+}
+
+async fn run_hooks<'js>(
+    ctx: &Ctx<'js>,
+    hooks: &[Function<'js>],
+    context: Option<Value<'js>>,
+) -> Result<()> {
+    for hook in hooks {
+        let context = context
+            .clone()
+            .unwrap_or_else(|| Value::new_undefined(ctx.clone()));
+        settle(hook.call((context,))?).await?;
+    }
+    Ok(())
+}
+
+/// Formats a thrown value for the reporter, with source-mapped stack frames.
+fn failure(ctx: &Ctx<'_>, err: Error) -> String {
+    let message = err
+        .into_value(ctx)
+        .and_then(|value| format_plain(ctx.clone(), false, Rest(vec![value])));
+    match message {
+        Ok(message) => remap_stack(ctx, &message),
+        Err(err) => err.to_string(),
+    }
+}
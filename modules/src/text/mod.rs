@@ -1,17 +1,21 @@
 pub mod text_decoder;
+pub mod text_decoder_stream;
 pub mod text_encoder;
+pub mod text_encoder_stream;
 
 use crate::utils::{
     console::format_plain,
     module::{export_default, ModuleInfo},
 };
 use rsquickjs::{
-    function::Func,
+    function::{Constructor, Func, This},
     module::{Declarations, Exports, ModuleDef},
-    Class, Ctx, Function, Object, Result,
+    Class, Ctx, Exception, Function, IntoJs, Object, Result,
 };
 use text_decoder::TextDecoder;
+use text_decoder_stream::TextDecoderStream;
 use text_encoder::TextEncoder;
+use text_encoder_stream::TextEncoderStream;
 
 fn inherits<'js>(ctor: Function<'js>, super_ctor: Function<'js>) -> Result<()> {
     let super_proto: Object<'js> = super_ctor.get("prototype")?;
@@ -21,6 +25,34 @@ fn inherits<'js>(ctor: Function<'js>, super_ctor: Function<'js>) -> Result<()> {
     Ok(())
 }
 
+fn new_transform_stream<'js>(
+    ctx: &Ctx<'js>,
+    transform: Function<'js>,
+    flush: Option<Function<'js>>,
+) -> Result<Object<'js>> {
+    let Some(transform_stream) = ctx
+        .globals()
+        .get::<_, Option<Constructor>>("TransformStream")?
+    else {
+        return Err(Exception::throw_type(
+            ctx,
+            "TransformStream is not supported",
+        ));
+    };
+
+    let transformer = Object::new(ctx.clone())?;
+    transformer.set("transform", transform)?;
+    if let Some(flush) = flush {
+        transformer.set("flush", flush)?;
+    }
+    transform_stream.construct((transformer,))
+}
+
+fn enqueue<'js>(controller: &Object<'js>, chunk: impl IntoJs<'js>) -> Result<()> {
+    let enqueue: Function = controller.get("enqueue")?;
+    enqueue.call((This(controller.clone()), chunk))
+}
+
 pub struct TextModule;
 
 impl ModuleDef for TextModule {
@@ -67,6 +99,8 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     Class::<TextEncoder>::define(&globals)?;
     Class::<TextDecoder>::define(&globals)?;
+    Class::<TextEncoderStream>::define(&globals)?;
+    Class::<TextDecoderStream>::define(&globals)?;
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-use crate::utils::encoding::Encoder;
+use crate::utils::encoding::{Encoder, StreamDecoder};
 use crate::utils::{bytes::ObjectBytes, object::ObjectExt, result::ResultExt};
 use rsquickjs::{atom::PredefinedAtom, function::Opt, Ctx, Object, Result};
 
@@ -6,7 +6,7 @@ use rsquickjs::{atom::PredefinedAtom, function::Opt, Ctx, Object, Result};
 #[derive(rsquickjs::class::Trace, rsquickjs::JsLifetime)]
 pub struct TextDecoder {
     #[qjs(skip_trace)]
-    encoder: Encoder,
+    decoder: StreamDecoder,
     fatal: bool,
     ignore_bom: bool,
 }
@@ -30,7 +30,7 @@ impl<'js> TextDecoder {
         }
 
         Ok(TextDecoder {
-            encoder,
+            decoder: StreamDecoder::new(encoder, !fatal, ignore_bom),
             fatal,
             ignore_bom,
         })
@@ -38,7 +38,7 @@ impl<'js> TextDecoder {
 
     #[qjs(get)]
     fn encoding(&self) -> &str {
-        self.encoder.as_label()
+        self.decoder.encoder().as_label()
     }
 
     #[qjs(get)]
@@ -56,21 +56,19 @@ impl<'js> TextDecoder {
         stringify!(TextDecoder)
     }
 
-    pub fn decode(&self, ctx: Ctx<'js>, bytes: ObjectBytes<'js>) -> Result<String> {
-        let bytes = bytes.as_bytes(&ctx)?;
-        let start_pos = if !self.ignore_bom {
-            match (&self.encoder, bytes.get(..3)) {
-                (Encoder::Utf16le, Some([0xFF, 0xFE, ..])) => 2,
-                (Encoder::Utf16be, Some([0xFE, 0xFF, ..])) => 2,
-                (Encoder::Utf8, Some([0xEF, 0xBB, 0xBF])) => 3,
-                _ => 0,
-            }
-        } else {
-            0
+    pub fn decode(
+        &mut self,
+        ctx: Ctx<'js>,
+        bytes: ObjectBytes<'js>,
+        options: Opt<Object<'js>>,
+    ) -> Result<String> {
+        let stream = match options.0 {
+            Some(options) => options.get_optional("stream")?.unwrap_or_default(),
+            None => false,
         };
 
-        self.encoder
-            .encode_to_string(&bytes[start_pos..], !self.fatal)
+        self.decoder
+            .decode(bytes.as_bytes(&ctx)?, stream)
             .or_throw_type(&ctx, "")
     }
 }
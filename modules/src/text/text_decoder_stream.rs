@@ -0,0 +1,116 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, function::Opt, Ctx, Function, Object, Result, Value,
+};
+
+use super::{enqueue, new_transform_stream};
+use crate::utils::{
+    bytes::ObjectBytes,
+    encoding::{Encoder, StreamDecoder},
+    object::ObjectExt,
+    result::ResultExt,
+};
+
+/// [`TextDecoderStream`](https://developer.mozilla.org/en-US/docs/Web/API/TextDecoderStream)
+/// decoding byte chunks into strings. Multi-byte sequences split across chunks are kept until
+/// the rest of the sequence arrives.
+#[derive(Clone, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct TextDecoderStream<'js> {
+    #[qjs(skip_trace)]
+    encoder: Encoder,
+    fatal: bool,
+    ignore_bom: bool,
+    transform: Object<'js>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> TextDecoderStream<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>, label: Opt<String>, options: Opt<Object<'js>>) -> Result<Self> {
+        let mut fatal = false;
+        let mut ignore_bom = false;
+
+        let encoder = Encoder::from_optional_str(label.as_deref()).or_throw_range(&ctx, "")?;
+
+        if let Some(options) = options.0 {
+            if let Some(opt) = options.get_optional("fatal")? {
+                fatal = opt;
+            }
+            if let Some(opt) = options.get_optional("ignoreBOM")? {
+                ignore_bom = opt;
+            }
+        }
+
+        let decoder = Rc::new(RefCell::new(StreamDecoder::new(
+            encoder.clone(),
+            !fatal,
+            ignore_bom,
+        )));
+
+        let transform = Function::new(ctx.clone(), {
+            let decoder = decoder.clone();
+            move |chunk: ObjectBytes<'js>, controller: Object<'js>| -> Result<()> {
+                let ctx = controller.ctx().clone();
+                let output = decoder
+                    .borrow_mut()
+                    .decode(chunk.as_bytes(&ctx)?, true)
+                    .or_throw_type(&ctx, "")?;
+                if output.is_empty() {
+                    return Ok(());
+                }
+                enqueue(&controller, output)
+            }
+        })?;
+
+        let flush = Function::new(ctx.clone(), move |controller: Object<'js>| -> Result<()> {
+            let ctx = controller.ctx().clone();
+            let output = decoder
+                .borrow_mut()
+                .decode(&[], false)
+                .or_throw_type(&ctx, "")?;
+            if output.is_empty() {
+                return Ok(());
+            }
+            enqueue(&controller, output)
+        })?;
+
+        Ok(Self {
+            encoder,
+            fatal,
+            ignore_bom,
+            transform: new_transform_stream(&ctx, transform, Some(flush))?,
+        })
+    }
+
+    #[qjs(get)]
+    fn encoding(&self) -> &str {
+        self.encoder.as_label()
+    }
+
+    #[qjs(get)]
+    fn fatal(&self) -> bool {
+        self.fatal
+    }
+
+    #[qjs(get, rename = "ignoreBOM")]
+    fn ignore_bom(&self) -> bool {
+        self.ignore_bom
+    }
+
+    #[qjs(get)]
+    pub fn readable(&self) -> Result<Value<'js>> {
+        self.transform.get("readable")
+    }
+
+    #[qjs(get)]
+    pub fn writable(&self) -> Result<Value<'js>> {
+        self.transform.get("writable")
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(TextDecoderStream)
+    }
+}
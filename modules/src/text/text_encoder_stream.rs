@@ -0,0 +1,57 @@
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, Coerced, Ctx, Function, Object, Result, TypedArray, Value,
+};
+
+use super::{enqueue, new_transform_stream};
+
+/// [`TextEncoderStream`](https://developer.mozilla.org/en-US/docs/Web/API/TextEncoderStream)
+/// encoding string chunks into UTF-8 `Uint8Array` chunks.
+#[derive(Clone, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct TextEncoderStream<'js> {
+    transform: Object<'js>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> TextEncoderStream<'js> {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'js>) -> Result<Self> {
+        let transform = Function::new(
+            ctx.clone(),
+            |chunk: Coerced<String>, controller: Object<'js>| -> Result<()> {
+                if chunk.0.is_empty() {
+                    return Ok(());
+                }
+                let ctx = controller.ctx().clone();
+                enqueue(
+                    &controller,
+                    TypedArray::<u8>::new(ctx, chunk.0.into_bytes())?,
+                )
+            },
+        )?;
+
+        Ok(Self {
+            transform: new_transform_stream(&ctx, transform, None)?,
+        })
+    }
+
+    #[qjs(get)]
+    fn encoding(&self) -> &str {
+        "utf-8"
+    }
+
+    #[qjs(get)]
+    pub fn readable(&self) -> Result<Value<'js>> {
+        self.transform.get("readable")
+    }
+
+    #[qjs(get)]
+    pub fn writable(&self) -> Result<Value<'js>> {
+        self.transform.get("writable")
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(TextEncoderStream)
+    }
+}
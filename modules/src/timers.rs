@@ -1,29 +1,30 @@
 use std::{
-    pin::{pin, Pin},
     ptr::NonNull,
     rc::Rc,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        LazyLock, Mutex, MutexGuard,
+        Arc, LazyLock, Mutex, MutexGuard,
     },
     time::Duration,
 };
 
 pub use crate::hooking::{invoke_async_hook, register_finalization_registry, HookType};
-use crate::utils::{
-    module::{export_default, ModuleInfo},
-    provider::ProviderType,
+use crate::{
+    permissions::get_vsys,
+    utils::{
+        module::{export_default, ModuleInfo},
+        provider::ProviderType,
+    },
 };
 use rsquickjs::{
+    atom::PredefinedAtom,
+    class::{Trace, Tracer},
     module::{Declarations, Exports, ModuleDef},
-    prelude::{Func, Opt},
-    qjs, Ctx, Exception, Function, Persistent, Result, Value,
-};
-use tokio::{
-    select,
-    sync::Notify,
-    time::{Instant, Sleep},
+    prelude::{Func, Opt, This},
+    qjs, Class, Ctx, Exception, Function, JsLifetime, Object, Persistent, Result, Value,
 };
+use tokio::{select, sync::Notify};
+use xmas_vsys::ClockVTable;
 
 static TIMER_ID: AtomicUsize = AtomicUsize::new(0);
 static RT_TIMER_STATE: LazyLock<Mutex<Vec<RuntimeTimerState>>> =
@@ -33,18 +34,20 @@ pub struct RuntimeTimerState {
     timers: Vec<Timeout>,
     rt: *mut qjs::JSRuntime,
     running: bool,
-    deadline: Instant,
+    deadline: Duration,
     notify: Rc<Notify>,
+    clock: Arc<ClockVTable>,
 }
 impl RuntimeTimerState {
-    fn new(rt: *mut qjs::JSRuntime) -> Self {
-        let deadline = Instant::now() + Duration::from_secs(86400 * 365 * 30);
+    fn new(rt: *mut qjs::JSRuntime, clock: Arc<ClockVTable>) -> Self {
+        let deadline = (clock.monotonic_now)() + Duration::from_secs(86400 * 365 * 30);
         Self {
             timers: Default::default(),
             rt,
             deadline,
             running: false,
             notify: Default::default(),
+            clock,
         }
     }
 }
@@ -54,26 +57,88 @@ unsafe impl Send for RuntimeTimerState {}
 #[derive(Clone)]
 pub struct Timeout {
     callback: Option<Persistent<Function<'static>>>,
-    deadline: Instant,
+    deadline: Duration,
     raw_ctx: NonNull<qjs::JSContext>,
     id: usize,
     repeating: bool,
     interval: u64,
+    /// Whether the timer is left out of what keeps the timer loop, and the process, going
+    unref: bool,
 }
 
 impl Default for Timeout {
     fn default() -> Self {
         Self {
             callback: None,
-            deadline: Instant::now(),
+            deadline: Duration::ZERO,
             raw_ctx: NonNull::dangling(),
             id: 0,
             repeating: false,
             interval: 0,
+            unref: false,
         }
     }
 }
 
+/// What `setTimeout`, `setInterval` and `setImmediate` return. It converts to the id of
+/// the timer, so code passing it around as a number keeps working.
+#[derive(Clone)]
+#[rsquickjs::class(rename = "Timeout")]
+pub struct TimerHandle {
+    id: usize,
+    has_ref: bool,
+}
+
+impl<'js> Trace<'js> for TimerHandle {
+    fn trace<'a>(&self, _: Tracer<'a, 'js>) {}
+}
+
+unsafe impl<'js> JsLifetime<'js> for TimerHandle {
+    type Changed<'to> = TimerHandle;
+}
+
+#[rsquickjs::methods]
+impl TimerHandle {
+    #[qjs(rename = "ref")]
+    pub fn ref_timer<'js>(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        this.borrow_mut().has_ref = true;
+        set_timer_ref(&ctx, this.borrow().id, true)?;
+        Ok(this.0)
+    }
+
+    /// Lets the process exit while the timer is pending
+    pub fn unref<'js>(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        this.borrow_mut().has_ref = false;
+        set_timer_ref(&ctx, this.borrow().id, false)?;
+        Ok(this.0)
+    }
+
+    #[qjs(rename = "hasRef")]
+    pub fn has_ref(&self) -> bool {
+        self.has_ref
+    }
+
+    /// Restarts the delay of a pending timer from now
+    pub fn refresh<'js>(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        refresh_timer(&ctx, this.borrow().id)?;
+        Ok(this.0)
+    }
+
+    pub fn close<'js>(this: This<Class<'js, Self>>, ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        clear_timer(&ctx, this.borrow().id);
+        Ok(this.0)
+    }
+
+    #[qjs(rename = PredefinedAtom::SymbolToPrimitive)]
+    pub fn to_primitive(&self) -> usize {
+        self.id
+    }
+}
+
+fn timer_handle<'js>(ctx: &Ctx<'js>, id: usize) -> Result<Class<'js, TimerHandle>> {
+    Class::instance(ctx.clone(), TimerHandle { id, has_ref: true })
+}
+
 fn queue_microtask<'js>(_ctx: Ctx<'js>, cb: Function<'js>) -> Result<()> {
     // SAFETY: Since it checks in advance whether it is an Function type, we can always get a pointer to the Function.
     let uid = unsafe { qjs::JS_VALUE_GET_PTR(cb.as_raw()) } as usize;
@@ -102,10 +167,11 @@ pub fn set_timeout_interval<'js>(
     // This is due to the specifications of the Node.js event loop.
     // The event loop specifications are completely different from those of Node.js,
     // but to make them the same, `setImmedaite()` is executed before any delay setting of `setTimeout()`.
+    let now = (clock(ctx).monotonic_now)();
     let (repeating, deadline) = match provider_type {
-        ProviderType::Immediate => (false, Instant::now() - Duration::from_secs(600)), // before any setTimeout(fn, delay)
-        ProviderType::Timeout => (false, Instant::now() + Duration::from_millis(delay)),
-        ProviderType::Interval => (true, Instant::now() + Duration::from_millis(delay)),
+        ProviderType::Immediate => (false, Duration::ZERO), // before any setTimeout(fn, delay)
+        ProviderType::Timeout => (false, now + Duration::from_millis(delay)),
+        ProviderType::Interval => (true, now + Duration::from_millis(delay)),
         _ => {
             return Err(Exception::throw_type(
                 ctx,
@@ -128,6 +194,7 @@ pub fn set_timeout_interval<'js>(
         id,
         repeating,
         interval: delay,
+        unref: false,
     };
 
     let rt_ptr = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
@@ -135,20 +202,80 @@ pub fn set_timeout_interval<'js>(
     let mut rt_timer = RT_TIMER_STATE.lock().unwrap();
     let state = get_timer_state(&mut rt_timer, rt_ptr);
     state.timers.push(timeout);
-    let task_running = state.running;
-    if task_running {
+    schedule(ctx, rt_ptr, rt_timer, deadline)?;
+
+    Ok(id)
+}
+
+/// Wakes the timer loop up for a timer due at `deadline`, starting the loop when it
+/// isn't running
+fn schedule(
+    ctx: &Ctx<'_>,
+    rt: *mut qjs::JSRuntime,
+    mut rt_timers: MutexGuard<Vec<RuntimeTimerState>>,
+    deadline: Duration,
+) -> Result<()> {
+    let state = get_timer_state(&mut rt_timers, rt);
+    if state.running {
         if deadline < state.deadline {
             state.deadline = deadline;
             state.notify.notify_one();
         }
-    } else {
-        state.running = true;
-        let timer_abort = state.notify.clone();
-        drop(rt_timer);
-        create_spawn_loop(rt_ptr, ctx, timer_abort, deadline)?;
+        return Ok(());
     }
+    state.running = true;
+    // unreferenced timers left over by the previous loop may be due earlier
+    let deadline = state
+        .timers
+        .iter()
+        .map(|timeout| timeout.deadline)
+        .fold(deadline, Duration::min);
+    let timer_abort = state.notify.clone();
+    drop(rt_timers);
+    create_spawn_loop(rt, ctx, timer_abort, deadline)
+}
 
-    Ok(id)
+/// Sets whether the timer `id` keeps the timer loop going. The loop ends once only
+/// unreferenced timers are left, which then only fire while another timer keeps it
+/// going.
+pub fn set_timer_ref(ctx: &Ctx<'_>, id: usize, has_ref: bool) -> Result<()> {
+    let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt);
+    let Some(timeout) = state.timers.iter_mut().find(|t| t.id == id) else {
+        return Ok(());
+    };
+    timeout.unref = !has_ref;
+    if has_ref {
+        let deadline = timeout.deadline;
+        return schedule(ctx, rt, rt_timers, deadline);
+    }
+    // let the loop check whether anything still keeps it going
+    state.notify.notify_one();
+    Ok(())
+}
+
+fn refresh_timer(ctx: &Ctx<'_>, id: usize) -> Result<()> {
+    let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt);
+    let now = (state.clock.monotonic_now)();
+    let Some(timeout) = state
+        .timers
+        .iter_mut()
+        .find(|t| t.id == id && t.callback.is_some())
+    else {
+        return Ok(());
+    };
+    // immediates stay due right away
+    if timeout.deadline != Duration::ZERO {
+        timeout.deadline = now + Duration::from_millis(timeout.interval);
+    }
+    let deadline = timeout.deadline;
+    if timeout.unref {
+        return Ok(());
+    }
+    schedule(ctx, rt, rt_timers, deadline)
 }
 
 fn get_timer_state<'a>(
@@ -162,23 +289,31 @@ fn get_timer_state<'a>(
 }
 
 fn clear_timeout_interval(ctx: Ctx<'_>, id: Opt<Value>) -> Result<()> {
-    if let Some(id) = id.0.and_then(|v| v.as_number()) {
-        let id = id as usize;
-        let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
-        let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
-
-        let state = get_timer_state(&mut rt_timers, rt);
-        if let Some(timeout) = state.timers.iter_mut().find(|t| t.id == id) {
-            let _ = timeout.callback.take();
-            timeout.repeating = false;
-            timeout.deadline = Instant::now() - Duration::from_secs(1);
-            state.notify.notify_one()
-        }
+    let id =
+        id.0.and_then(|value| match Class::<TimerHandle>::from_value(&value) {
+            Ok(handle) => Some(handle.borrow().id),
+            Err(_) => value.as_number().map(|id| id as usize),
+        });
+    if let Some(id) = id {
+        clear_timer(&ctx, id);
     }
 
     Ok(())
 }
 
+fn clear_timer(ctx: &Ctx<'_>, id: usize) {
+    let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+
+    let state = get_timer_state(&mut rt_timers, rt);
+    if let Some(timeout) = state.timers.iter_mut().find(|t| t.id == id) {
+        let _ = timeout.callback.take();
+        timeout.repeating = false;
+        timeout.deadline = Duration::ZERO;
+        state.notify.notify_one()
+    }
+}
+
 pub struct TimersModule;
 
 impl ModuleDef for TimersModule {
@@ -187,8 +322,10 @@ impl ModuleDef for TimersModule {
         declare.declare("clearTimeout")?;
         declare.declare("setInterval")?;
         declare.declare("setImmediate")?;
+        declare.declare("clearImmediate")?;
         declare.declare("clearInterval")?;
         declare.declare("queueMicrotask")?;
+        declare.declare("promises")?;
         declare.declare("default")?;
         Ok(())
     }
@@ -203,12 +340,14 @@ impl ModuleDef for TimersModule {
                 "setInterval",
                 "clearInterval",
                 "setImmediate",
+                "clearImmediate",
                 "queueMicrotask",
             ];
             for func_name in functions {
                 let function: Function = globals.get(func_name)?;
                 default.set(func_name, function)?;
             }
+            default.set("promises", timers_promises(ctx)?)?;
             Ok(())
         })?;
 
@@ -225,19 +364,181 @@ impl From<TimersModule> for ModuleInfo<TimersModule> {
     }
 }
 
+/// `timers/promises`, built on the global timers. An aborted `signal` clears the timer
+/// and rejects with an `AbortError`, and `ref: false` unrefs it.
+const TIMERS_PROMISES_SOURCE: &str = r#"
+((setTimeout, clearTimeout, setImmediate, clearImmediate, setInterval, clearInterval) => {
+    const abortError = (signal) => {
+        const err = new DOMException("The operation was aborted", "AbortError");
+        err.cause = signal.reason;
+        return err;
+    };
+    const validateOptions = (options) => {
+        if (options === null || typeof options !== "object") {
+            const err = new TypeError('The "options" argument must be of type object');
+            err.code = "ERR_INVALID_ARG_TYPE";
+            throw err;
+        }
+        return options;
+    };
+
+    function wait(start, clear, value, options = {}) {
+        let signal, ref;
+        try {
+            ({ signal, ref = true } = validateOptions(options));
+        } catch (err) {
+            return Promise.reject(err);
+        }
+        if (signal && signal.aborted) {
+            return Promise.reject(abortError(signal));
+        }
+        return new Promise((resolve, reject) => {
+            const onAbort = () => {
+                clear(timer);
+                reject(abortError(signal));
+            };
+            const timer = start(() => {
+                if (signal) signal.removeEventListener("abort", onAbort);
+                resolve(value);
+            });
+            if (!ref) timer.unref();
+            if (signal) signal.addEventListener("abort", onAbort, { once: true });
+        });
+    }
+
+    const timeout = (delay, value, options) =>
+        wait((callback) => setTimeout(callback, delay ?? 1), clearTimeout, value, options);
+    const immediate = (value, options) => wait(setImmediate, clearImmediate, value, options);
+
+    // ticks the consumer hasn't caught up with are yielded right away
+    async function* interval(delay, value, options = {}) {
+        const { signal, ref = true } = validateOptions(options);
+        if (signal && signal.aborted) throw abortError(signal);
+        let pending = 0;
+        let wake = null;
+        const notify = () => {
+            if (wake) wake();
+        };
+        const onTick = () => {
+            pending++;
+            notify();
+        };
+        const timer = setInterval(onTick, delay ?? 1);
+        if (!ref) timer.unref();
+        if (signal) signal.addEventListener("abort", notify, { once: true });
+        try {
+            while (true) {
+                if (pending === 0 && !(signal && signal.aborted)) {
+                    await new Promise((resolve) => (wake = resolve));
+                    wake = null;
+                }
+                if (signal && signal.aborted) throw abortError(signal);
+                pending--;
+                yield value;
+            }
+        } finally {
+            clearInterval(timer);
+            if (signal) signal.removeEventListener("abort", notify);
+        }
+    }
+
+    const scheduler = {
+        wait: (delay, options) => timeout(delay, undefined, options),
+        yield: () => immediate(),
+    };
+
+    return { setTimeout: timeout, setImmediate: immediate, setInterval: interval, scheduler };
+})
+"#;
+
+fn timers_promises<'js>(ctx: &Ctx<'js>) -> Result<Object<'js>> {
+    let globals = ctx.globals();
+    let init: Function =
+        crate::utils::snapshot::eval(ctx, "timers_promises", TIMERS_PROMISES_SOURCE)?;
+    init.call((
+        globals.get::<_, Function>("setTimeout")?,
+        globals.get::<_, Function>("clearTimeout")?,
+        globals.get::<_, Function>("setImmediate")?,
+        globals.get::<_, Function>("clearImmediate")?,
+        globals.get::<_, Function>("setInterval")?,
+        globals.get::<_, Function>("clearInterval")?,
+    ))
+}
+
+pub struct TimersPromisesModule;
+
+impl ModuleDef for TimersPromisesModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("setTimeout")?;
+        declare.declare("setImmediate")?;
+        declare.declare("setInterval")?;
+        declare.declare("scheduler")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        let promises = timers_promises(ctx)?;
+        export_default(ctx, exports, |default| {
+            for name in ["setTimeout", "setImmediate", "setInterval", "scheduler"] {
+                default.set(name, promises.get::<_, Value>(name)?)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl From<TimersPromisesModule> for ModuleInfo<TimersPromisesModule> {
+    fn from(val: TimersPromisesModule) -> Self {
+        ModuleInfo {
+            name: "timers/promises",
+            module: val,
+        }
+    }
+}
+
+/// The clock of the vsys stored in `ctx`, or the system clock without one.
+pub fn clock(ctx: &Ctx<'_>) -> Arc<ClockVTable> {
+    get_vsys(ctx)
+        .map(|vsys| vsys.clock.clone())
+        .unwrap_or_default()
+}
+
 pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     let rt_ptr = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+    let clock = clock(ctx);
 
     let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
-    rt_timers.push(RuntimeTimerState::new(rt_ptr));
+    rt_timers.push(RuntimeTimerState::new(rt_ptr, clock.clone()));
 
     let globals = ctx.globals();
 
+    // `Date.now()` and `performance.now()` read the same clock as the timers
+    let date: Object = globals.get("Date")?;
+    let now_clock = clock.clone();
+    date.set("now", Func::from(move || now_clock.now_millis()))?;
+
+    let performance = match globals.get::<_, Option<Object>>("performance")? {
+        Some(performance) => performance,
+        None => {
+            let performance = Object::new(ctx.clone())?;
+            globals.set("performance", performance.clone())?;
+            performance
+        }
+    };
+    let origin = (clock.monotonic_now)();
+    performance.set("timeOrigin", clock.now_millis())?;
+    performance.set(
+        "now",
+        Func::from(move || (clock.monotonic_now)().saturating_sub(origin).as_secs_f64() * 1000.0),
+    )?;
+
     globals.set(
         "setTimeout",
         Func::from(move |ctx, cb, delay: Opt<f64>| {
             let delay = delay.unwrap_or(0.).max(0.) as u64;
-            set_timeout_interval(&ctx, cb, delay, ProviderType::Timeout)
+            let id = set_timeout_interval(&ctx, cb, delay, ProviderType::Timeout)?;
+            timer_handle(&ctx, id)
         }),
     )?;
 
@@ -245,7 +546,8 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
         "setInterval",
         Func::from(move |ctx, cb, delay: Opt<f64>| {
             let delay = delay.unwrap_or(0.).max(0.) as u64;
-            set_timeout_interval(&ctx, cb, delay, ProviderType::Interval)
+            let id = set_timeout_interval(&ctx, cb, delay, ProviderType::Interval)?;
+            timer_handle(&ctx, id)
         }),
     )?;
 
@@ -255,9 +557,14 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     globals.set(
         "setImmediate",
-        Func::from(move |ctx, cb| set_timeout_interval(&ctx, cb, 0, ProviderType::Immediate)),
+        Func::from(move |ctx, cb| {
+            let id = set_timeout_interval(&ctx, cb, 0, ProviderType::Immediate)?;
+            timer_handle(&ctx, id)
+        }),
     )?;
 
+    globals.set("clearImmediate", Func::from(clear_timeout_interval))?;
+
     globals.set("queueMicrotask", Func::from(queue_microtask))?;
 
     Ok(())
@@ -268,21 +575,23 @@ fn create_spawn_loop(
     rt: *mut qjs::JSRuntime,
     ctx: &Ctx<'_>,
     timer_abort: Rc<Notify>,
-    deadline: Instant,
+    deadline: Duration,
 ) -> Result<()> {
     use crate::utils::ctx::CtxExtension;
+    let clock = clock(ctx);
     ctx.spawn_exit_simple(async move {
-        let mut sleep = pin!(tokio::time::sleep_until(deadline));
+        let mut deadline = deadline;
 
         let mut executing_timers: Vec<Option<ExecutingTimer>> = Default::default();
 
         loop {
+            let sleep = (clock.sleep)(deadline.saturating_sub((clock.monotonic_now)()));
             select! {
                 _ = timer_abort.notified() => {}
-                _ = sleep.as_mut() => {}
+                _ = sleep => {}
             }
 
-            if !poll_timers(rt, &mut executing_timers, Some(&mut sleep), None)? {
+            if !poll_timers(rt, &mut executing_timers, Some(&mut deadline))? {
                 break;
             }
         }
@@ -293,7 +602,7 @@ fn create_spawn_loop(
 }
 
 pub struct ExecutingTimer(
-    Instant,
+    Duration,
     NonNull<qjs::JSContext>,
     Persistent<Function<'static>>,
 );
@@ -303,21 +612,20 @@ unsafe impl Send for ExecutingTimer {}
 pub fn poll_timers(
     rt: *mut qjs::JSRuntime,
     call_vec: &mut Vec<Option<ExecutingTimer>>,
-    sleep: Option<&mut Pin<&mut Sleep>>,
-    deadline: Option<&mut Instant>,
+    deadline: Option<&mut Duration>,
 ) -> Result<bool> {
     static MIN_SLEEP: Duration = Duration::from_millis(4);
     static FAR_FUTURE: Duration = Duration::from_secs(84200 * 365 * 30);
 
     let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
     let state = get_timer_state(&mut rt_timers, rt);
-    let now = Instant::now();
+    let now = (state.clock.monotonic_now)();
 
     let mut had_items = false;
     let mut lowest = now + FAR_FUTURE;
     state.timers.retain_mut(|timeout| {
         had_items = true;
-        if timeout.deadline < now {
+        if timeout.deadline <= now {
             let ctx = timeout.raw_ctx;
             if let Some(cb) = timeout.callback.take() {
                 if !timeout.repeating {
@@ -339,15 +647,13 @@ pub fn poll_timers(
         true
     });
 
-    let has_items = !state.timers.is_empty();
+    // unreferenced timers alone don't keep the loop going
+    let has_refs = state.timers.iter().any(|timeout| !timeout.unref);
 
     if had_items {
-        if lowest - now < MIN_SLEEP {
+        if lowest.saturating_sub(now) < MIN_SLEEP {
             lowest = now + MIN_SLEEP;
         }
-        if let Some(sleep) = sleep {
-            sleep.as_mut().reset(lowest);
-        }
         if let Some(deadline) = deadline {
             *deadline = lowest;
         }
@@ -384,13 +690,13 @@ pub fn poll_timers(
     }
     call_vec.clear();
 
-    if !has_items {
+    if !has_refs {
         let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
         let state = get_timer_state(&mut rt_timers, rt);
-        let is_empty = state.timers.is_empty();
-        state.running = !is_empty;
+        let has_refs = state.timers.iter().any(|timeout| !timeout.unref);
+        state.running = has_refs;
 
-        return Ok(!is_empty);
+        return Ok(has_refs);
     }
     Ok(true)
 }
@@ -551,4 +857,104 @@ mod tests {
         })
         .await;
     }
+
+    #[cfg(feature = "abort")]
+    #[tokio::test]
+    async fn test_timers_promises() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                init(&ctx).unwrap();
+                crate::exceptions::init(&ctx).unwrap();
+                crate::abort::init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<TimersPromisesModule>(ctx.clone(), "timers/promises")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test_timersPromises",
+                    r#"
+                        import { setTimeout as sleep, setImmediate, setInterval } from 'timers/promises';
+                        export async function test() {
+                            const results = [];
+                            results.push(await sleep(10, 'timeout'));
+                            results.push(await setImmediate('immediate'));
+
+                            const controller = new AbortController();
+                            const aborted = sleep(10000, 'late', { signal: controller.signal })
+                                .catch((err) => err.name);
+                            controller.abort();
+                            results.push(await aborted);
+
+                            let ticks = 0;
+                            for await (const value of setInterval(5, 'tick')) {
+                                results.push(value);
+                                if (++ticks === 2) break;
+                            }
+
+                            const timer = globalThis.setTimeout(() => {}, 10000);
+                            results.push(timer.unref().hasRef(), typeof +timer);
+                            clearTimeout(timer);
+                            return results.join(',');
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "timeout,immediate,AbortError,tick,tick,false,number"
+                );
+            })
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_virtual_clock() {
+        use std::sync::atomic::AtomicU64;
+        use std::time::SystemTime;
+
+        // sleeping fast-forwards the virtual clock instead of waiting
+        static VIRTUAL_MILLIS: AtomicU64 = AtomicU64::new(0);
+        let clock = ClockVTable {
+            now: || {
+                SystemTime::UNIX_EPOCH
+                    + Duration::from_millis(1_000_000 + VIRTUAL_MILLIS.load(Ordering::SeqCst))
+            },
+            monotonic_now: || Duration::from_millis(VIRTUAL_MILLIS.load(Ordering::SeqCst)),
+            sleep: |duration| {
+                VIRTUAL_MILLIS.fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+                Box::pin(std::future::ready(()))
+            },
+        };
+        let vsys = xmas_vsys::Vsys::builder().clock(clock).build();
+
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::permissions::init(ctx.clone(), Arc::new(vsys)).unwrap();
+                init(&ctx).unwrap();
+
+                assert_eq!(ctx.eval::<f64, _>("Date.now()").unwrap(), 1_000_000.0);
+                assert_eq!(ctx.eval::<f64, _>("performance.now()").unwrap(), 0.0);
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test_virtualClock",
+                    r#"
+                        export async function test() {
+                            await new Promise((resolve) => setTimeout(resolve, 60000));
+                            return [Date.now(), performance.now()];
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<Vec<f64>, _>(&ctx, &module, ()).await;
+                assert_eq!(result, vec![1_060_000.0, 60000.0]);
+            })
+        })
+        .await;
+    }
 }
@@ -1,5 +1,6 @@
 #![allow(clippy::inherent_to_string)]
 pub mod url_class;
+pub mod url_pattern;
 pub mod url_search_params;
 
 use std::{path::PathBuf, str::FromStr};
@@ -18,6 +19,7 @@ use rsquickjs::{
 use url::{quirks, Url};
 
 use self::url_class::{url_to_http_options, URL};
+use self::url_pattern::URLPattern;
 use self::url_search_params::URLSearchParams;
 
 pub fn domain_to_unicode(domain: &str) -> String {
@@ -136,6 +138,7 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     Class::<URLSearchParams>::define(&globals)?;
     Class::<URL>::define(&globals)?;
+    Class::<URLPattern>::define(&globals)?;
 
     Ok(())
 }
@@ -146,6 +149,7 @@ impl ModuleDef for UrlModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare(stringify!(URL))?;
         declare.declare(stringify!(URLSearchParams))?;
+        declare.declare(stringify!(URLPattern))?;
         declare.declare("urlToHttpOptions")?;
         declare.declare("domainToUnicode")?;
         declare.declare("domainToASCII")?;
@@ -161,10 +165,12 @@ impl ModuleDef for UrlModule {
         BasePrimordials::init(ctx)?;
         let url: Constructor = globals.get(stringify!(URL))?;
         let url_search_params: Constructor = globals.get(stringify!(URLSearchParams))?;
+        let url_pattern: Constructor = globals.get(stringify!(URLPattern))?;
 
         export_default(ctx, exports, |default| {
             default.set(stringify!(URL), url)?;
             default.set(stringify!(URLSearchParams), url_search_params)?;
+            default.set(stringify!(URLPattern), url_pattern)?;
             default.set("urlToHttpOptions", Func::from(url_to_http_options))?;
             default.set(
                 "domainToUnicode",
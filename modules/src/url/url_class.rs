@@ -8,6 +8,7 @@ use rsquickjs::{
 use url::{quirks, Url};
 
 use super::{convert_trailing_space, url_search_params::URLSearchParams};
+use crate::buffer::{create_object_url, revoke_object_url, Blob, File};
 
 /// Naively checks for hostname delimiter, a colon ":", that's *probably* not
 /// part of an IPv6 address
@@ -254,6 +255,27 @@ impl<'js> URL<'js> {
         Self::new(ctx, input, base).is_ok()
     }
 
+    /// `URL.createObjectURL(blob)` registers a blob so it can be imported by URL.
+    #[qjs(static, rename = "createObjectURL")]
+    pub fn create_object_url(ctx: Ctx<'js>, object: Value<'js>) -> Result<String> {
+        let blob = if let Ok(blob) = Class::<Blob>::from_value(&object) {
+            blob.borrow().clone()
+        } else if let Ok(file) = Class::<File>::from_value(&object) {
+            file.borrow().get_blob()
+        } else {
+            return Err(Exception::throw_type(
+                &ctx,
+                "The \"obj\" argument must be an instance of Blob",
+            ));
+        };
+        create_object_url(&ctx, blob)
+    }
+
+    #[qjs(static, rename = "revokeObjectURL")]
+    pub fn revoke_object_url(ctx: Ctx<'js>, url: Coerced<String>) {
+        revoke_object_url(&ctx, &url)
+    }
+
     #[qjs(static)]
     pub fn parse(ctx: Ctx<'js>, input: Value<'js>, base: Opt<Value<'js>>) -> Result<Value<'js>> {
         Self::new(ctx.clone(), input, base)
@@ -0,0 +1,1694 @@
+//! [`URLPattern`](https://urlpattern.spec.whatwg.org/) implementation.
+//!
+//! Patterns are tokenized and compiled to regular expression sources in Rust, matching is then
+//! delegated to the engine's `RegExp` so user supplied regexp groups behave exactly like in JS.
+use rsquickjs::{
+    atom::PredefinedAtom,
+    class::{Trace, Tracer},
+    function::{Opt, This},
+    Array, Coerced, Ctx, Exception, Function, Object, Result, Value,
+};
+use url::{quirks, Url};
+
+use crate::utils::primordials::{BasePrimordials, Primordial};
+
+const SPECIAL_SCHEMES: [&str; 6] = ["ftp", "file", "http", "https", "ws", "wss"];
+const FULL_WILDCARD_REGEXP: &str = ".*";
+const DUMMY_URL: &str = "https://dummy.invalid";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TokenType {
+    Open,
+    Close,
+    Regexp,
+    Name,
+    Char,
+    EscapedChar,
+    OtherModifier,
+    Asterisk,
+    End,
+    InvalidChar,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenType,
+    /// Byte offset of the token in the input
+    index: usize,
+    value: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TokenizePolicy {
+    Strict,
+    Lenient,
+}
+
+// https://urlpattern.spec.whatwg.org/#tokenize
+fn tokenize(ctx: &Ctx<'_>, input: &str, policy: TokenizePolicy) -> Result<Vec<Token>> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let byte_at = |pos: usize| chars.get(pos).map_or(input.len(), |(i, _)| *i);
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index].1;
+        let scanned = match c {
+            '*' => Ok((TokenType::Asterisk, c.to_string(), index + 1)),
+            '+' | '?' => Ok((TokenType::OtherModifier, c.to_string(), index + 1)),
+            '{' => Ok((TokenType::Open, c.to_string(), index + 1)),
+            '}' => Ok((TokenType::Close, c.to_string(), index + 1)),
+            '\\' => match chars.get(index + 1) {
+                Some((_, escaped)) => Ok((TokenType::EscapedChar, escaped.to_string(), index + 2)),
+                None => Err("Trailing escape character"),
+            },
+            ':' => {
+                let start = index + 1;
+                let mut pos = start;
+                while pos < chars.len() && is_valid_name_code_point(chars[pos].1, pos == start) {
+                    pos += 1;
+                }
+                if pos == start {
+                    Err("Missing parameter name")
+                } else {
+                    let value = input[byte_at(start)..byte_at(pos)].to_string();
+                    Ok((TokenType::Name, value, pos))
+                }
+            }
+            '(' => scan_regexp(&chars, index + 1).map(|end| {
+                let value = input[byte_at(index + 1)..byte_at(end - 1)].to_string();
+                (TokenType::Regexp, value, end)
+            }),
+            _ => Ok((TokenType::Char, c.to_string(), index + 1)),
+        };
+
+        match scanned {
+            Ok((kind, value, next)) => {
+                tokens.push(Token {
+                    kind,
+                    index: byte_at(index),
+                    value,
+                });
+                index = next;
+            }
+            Err(msg) if policy == TokenizePolicy::Strict => {
+                return Err(Exception::throw_type(
+                    ctx,
+                    &[msg, " in pattern '", input, "'"].concat(),
+                ));
+            }
+            Err(_) => {
+                tokens.push(Token {
+                    kind: TokenType::InvalidChar,
+                    index: byte_at(index),
+                    value: c.to_string(),
+                });
+                index += 1;
+            }
+        }
+    }
+
+    tokens.push(Token {
+        kind: TokenType::End,
+        index: input.len(),
+        value: String::new(),
+    });
+    Ok(tokens)
+}
+
+/// Scans a regexp group starting right after its `(`, returning the position after the `)`.
+fn scan_regexp(chars: &[(usize, char)], start: usize) -> std::result::Result<usize, &'static str> {
+    const INVALID: &str = "Invalid regexp group";
+
+    let mut depth = 1;
+    let mut pos = start;
+    while pos < chars.len() {
+        let c = chars[pos].1;
+        if !c.is_ascii() || (pos == start && c == '?') {
+            return Err(INVALID);
+        }
+        match c {
+            '\\' => {
+                match chars.get(pos + 1) {
+                    Some((_, escaped)) if escaped.is_ascii() => {}
+                    _ => return Err(INVALID),
+                }
+                pos += 2;
+                continue;
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    pos += 1;
+                    break;
+                }
+            }
+            '(' => {
+                depth += 1;
+                // nested groups must be non-capturing
+                if !matches!(chars.get(pos + 1), Some((_, '?'))) {
+                    return Err(INVALID);
+                }
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    if depth != 0 || pos - start <= 1 {
+        return Err(INVALID);
+    }
+    Ok(pos)
+}
+
+fn is_valid_name_code_point(c: char, first: bool) -> bool {
+    if first {
+        c.is_alphabetic() || c == '$' || c == '_'
+    } else {
+        c.is_alphanumeric() || matches!(c, '$' | '_' | '\u{200C}' | '\u{200D}')
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PartType {
+    FixedText,
+    Regexp,
+    SegmentWildcard,
+    FullWildcard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PartModifier {
+    None,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+impl PartModifier {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Optional => "?",
+            Self::ZeroOrMore => "*",
+            Self::OneOrMore => "+",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Part {
+    kind: PartType,
+    value: String,
+    modifier: PartModifier,
+    name: String,
+    prefix: String,
+    suffix: String,
+}
+
+struct PatternOptions {
+    delimiter: &'static str,
+    prefix: &'static str,
+    ignore_case: bool,
+}
+
+impl PatternOptions {
+    const DEFAULT: Self = Self {
+        delimiter: "",
+        prefix: "",
+        ignore_case: false,
+    };
+    const HOSTNAME: Self = Self {
+        delimiter: ".",
+        prefix: "",
+        ignore_case: false,
+    };
+    const PATHNAME: Self = Self {
+        delimiter: "/",
+        prefix: "/",
+        ignore_case: false,
+    };
+
+    fn with_ignore_case(self, ignore_case: bool) -> Self {
+        Self {
+            ignore_case,
+            ..self
+        }
+    }
+
+    fn segment_wildcard_regexp(&self) -> String {
+        ["[^", &escape_regexp_string(self.delimiter), "]+?"].concat()
+    }
+}
+
+type EncodingCallback = fn(&Ctx<'_>, &str) -> Result<String>;
+
+// https://urlpattern.spec.whatwg.org/#parse-a-pattern-string
+struct PatternParser<'a, 'js> {
+    ctx: &'a Ctx<'js>,
+    tokens: Vec<Token>,
+    encode: EncodingCallback,
+    options: &'a PatternOptions,
+    segment_wildcard_regexp: String,
+    parts: Vec<Part>,
+    pending_fixed_value: String,
+    index: usize,
+    next_numeric_name: usize,
+}
+
+impl<'a, 'js> PatternParser<'a, 'js> {
+    fn parse(
+        ctx: &'a Ctx<'js>,
+        input: &str,
+        options: &'a PatternOptions,
+        encode: EncodingCallback,
+    ) -> Result<Vec<Part>> {
+        let mut parser = Self {
+            ctx,
+            tokens: tokenize(ctx, input, TokenizePolicy::Strict)?,
+            encode,
+            options,
+            segment_wildcard_regexp: options.segment_wildcard_regexp(),
+            parts: Vec::new(),
+            pending_fixed_value: String::new(),
+            index: 0,
+            next_numeric_name: 0,
+        };
+
+        while parser.index < parser.tokens.len() {
+            let char_token = parser.try_consume(TokenType::Char);
+            let name_token = parser.try_consume(TokenType::Name);
+            let regexp_or_wildcard = parser.try_consume_regexp_or_wildcard(name_token.as_ref());
+            if name_token.is_some() || regexp_or_wildcard.is_some() {
+                let mut prefix = char_token.map(|t| t.value).unwrap_or_default();
+                if prefix != options.prefix {
+                    parser.pending_fixed_value.push_str(&prefix);
+                    prefix.clear();
+                }
+                parser.maybe_add_part_from_pending_fixed_value()?;
+                let modifier = parser.try_consume_modifier();
+                parser.add_part(&prefix, name_token, regexp_or_wildcard, "", modifier)?;
+                continue;
+            }
+
+            let fixed_token = char_token.or_else(|| parser.try_consume(TokenType::EscapedChar));
+            if let Some(fixed_token) = fixed_token {
+                parser.pending_fixed_value.push_str(&fixed_token.value);
+                continue;
+            }
+
+            if parser.try_consume(TokenType::Open).is_some() {
+                let prefix = parser.consume_text();
+                let name_token = parser.try_consume(TokenType::Name);
+                let regexp_or_wildcard = parser.try_consume_regexp_or_wildcard(name_token.as_ref());
+                let suffix = parser.consume_text();
+                parser.consume_required(TokenType::Close)?;
+                let modifier = parser.try_consume_modifier();
+                parser.add_part(&prefix, name_token, regexp_or_wildcard, &suffix, modifier)?;
+                continue;
+            }
+
+            parser.maybe_add_part_from_pending_fixed_value()?;
+            parser.consume_required(TokenType::End)?;
+        }
+
+        Ok(parser.parts)
+    }
+
+    fn try_consume(&mut self, kind: TokenType) -> Option<Token> {
+        let token = self.tokens.get(self.index)?;
+        if token.kind != kind {
+            return None;
+        }
+        self.index += 1;
+        Some(token.clone())
+    }
+
+    fn try_consume_modifier(&mut self) -> Option<Token> {
+        self.try_consume(TokenType::OtherModifier)
+            .or_else(|| self.try_consume(TokenType::Asterisk))
+    }
+
+    fn try_consume_regexp_or_wildcard(&mut self, name_token: Option<&Token>) -> Option<Token> {
+        let token = self.try_consume(TokenType::Regexp);
+        if name_token.is_none() && token.is_none() {
+            return self.try_consume(TokenType::Asterisk);
+        }
+        token
+    }
+
+    fn consume_required(&mut self, kind: TokenType) -> Result<Token> {
+        match self.try_consume(kind) {
+            Some(token) => Ok(token),
+            None => {
+                let index = self.tokens.get(self.index).map_or(0, |t| t.index);
+                Err(Exception::throw_type(
+                    self.ctx,
+                    &format!("Unexpected token in pattern at index {}", index),
+                ))
+            }
+        }
+    }
+
+    fn consume_text(&mut self) -> String {
+        let mut result = String::new();
+        while let Some(token) = self
+            .try_consume(TokenType::Char)
+            .or_else(|| self.try_consume(TokenType::EscapedChar))
+        {
+            result.push_str(&token.value);
+        }
+        result
+    }
+
+    fn maybe_add_part_from_pending_fixed_value(&mut self) -> Result<()> {
+        if self.pending_fixed_value.is_empty() {
+            return Ok(());
+        }
+        let value = (self.encode)(self.ctx, &std::mem::take(&mut self.pending_fixed_value))?;
+        self.parts.push(Part {
+            kind: PartType::FixedText,
+            value,
+            modifier: PartModifier::None,
+            name: String::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+        });
+        Ok(())
+    }
+
+    fn add_part(
+        &mut self,
+        prefix: &str,
+        name_token: Option<Token>,
+        regexp_or_wildcard: Option<Token>,
+        suffix: &str,
+        modifier_token: Option<Token>,
+    ) -> Result<()> {
+        let modifier = match modifier_token.as_ref().map(|t| t.value.as_str()) {
+            Some("?") => PartModifier::Optional,
+            Some("*") => PartModifier::ZeroOrMore,
+            Some("+") => PartModifier::OneOrMore,
+            _ => PartModifier::None,
+        };
+
+        if name_token.is_none() && regexp_or_wildcard.is_none() && modifier == PartModifier::None {
+            self.pending_fixed_value.push_str(prefix);
+            return Ok(());
+        }
+        self.maybe_add_part_from_pending_fixed_value()?;
+
+        if name_token.is_none() && regexp_or_wildcard.is_none() {
+            if prefix.is_empty() {
+                return Ok(());
+            }
+            let value = (self.encode)(self.ctx, prefix)?;
+            self.parts.push(Part {
+                kind: PartType::FixedText,
+                value,
+                modifier,
+                name: String::new(),
+                prefix: String::new(),
+                suffix: String::new(),
+            });
+            return Ok(());
+        }
+
+        let mut value = match &regexp_or_wildcard {
+            None => self.segment_wildcard_regexp.clone(),
+            Some(token) if token.kind == TokenType::Asterisk => FULL_WILDCARD_REGEXP.into(),
+            Some(token) => token.value.clone(),
+        };
+        let mut kind = PartType::Regexp;
+        if value == self.segment_wildcard_regexp {
+            kind = PartType::SegmentWildcard;
+            value.clear();
+        } else if value == FULL_WILDCARD_REGEXP {
+            kind = PartType::FullWildcard;
+            value.clear();
+        }
+
+        let name = match name_token {
+            Some(token) => token.value,
+            None => {
+                let name = self.next_numeric_name.to_string();
+                self.next_numeric_name += 1;
+                name
+            }
+        };
+        if self.parts.iter().any(|part| part.name == name) {
+            return Err(Exception::throw_type(
+                self.ctx,
+                &["Duplicate name '", &name, "' in pattern"].concat(),
+            ));
+        }
+
+        self.parts.push(Part {
+            kind,
+            value,
+            modifier,
+            name,
+            prefix: (self.encode)(self.ctx, prefix)?,
+            suffix: (self.encode)(self.ctx, suffix)?,
+        });
+        Ok(())
+    }
+}
+
+fn escape_regexp_string(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(
+            c,
+            '.' | '+'
+                | '*'
+                | '?'
+                | '^'
+                | '$'
+                | '{'
+                | '}'
+                | '('
+                | ')'
+                | '['
+                | ']'
+                | '|'
+                | '/'
+                | '\\'
+        ) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn escape_pattern_string(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '+' | '*' | '?' | ':' | '{' | '}' | '(' | ')' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+// https://urlpattern.spec.whatwg.org/#generate-a-regular-expression-and-name-list
+fn generate_regexp(parts: &[Part], options: &PatternOptions) -> (String, Vec<String>) {
+    let mut result = String::from("^");
+    let mut names = Vec::new();
+
+    for part in parts {
+        let modifier = part.modifier.as_str();
+        if part.kind == PartType::FixedText {
+            if part.modifier == PartModifier::None {
+                result.push_str(&escape_regexp_string(&part.value));
+            } else {
+                result.push_str("(?:");
+                result.push_str(&escape_regexp_string(&part.value));
+                result.push(')');
+                result.push_str(modifier);
+            }
+            continue;
+        }
+
+        names.push(part.name.clone());
+        let value = match part.kind {
+            PartType::SegmentWildcard => options.segment_wildcard_regexp(),
+            PartType::FullWildcard => FULL_WILDCARD_REGEXP.into(),
+            _ => part.value.clone(),
+        };
+        let single = matches!(part.modifier, PartModifier::None | PartModifier::Optional);
+
+        if part.prefix.is_empty() && part.suffix.is_empty() {
+            if single {
+                result.push_str(&["(", &value, ")", modifier].concat());
+            } else {
+                result.push_str(&["((?:", &value, ")", modifier, ")"].concat());
+            }
+            continue;
+        }
+
+        let prefix = escape_regexp_string(&part.prefix);
+        let suffix = escape_regexp_string(&part.suffix);
+        if single {
+            result.push_str(&["(?:", &prefix, "(", &value, ")", &suffix, ")", modifier].concat());
+            continue;
+        }
+        result.push_str(
+            &[
+                "(?:", &prefix, "((?:", &value, ")(?:", &suffix, &prefix, "(?:", &value, "))*)",
+                &suffix, ")",
+            ]
+            .concat(),
+        );
+        if part.modifier == PartModifier::ZeroOrMore {
+            result.push('?');
+        }
+    }
+
+    result.push('$');
+    (result, names)
+}
+
+// https://urlpattern.spec.whatwg.org/#generate-a-pattern-string
+fn generate_pattern_string(parts: &[Part], options: &PatternOptions) -> String {
+    let mut result = String::new();
+    let segment_wildcard_regexp = options.segment_wildcard_regexp();
+
+    for (index, part) in parts.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|i| parts.get(i));
+        let next = parts.get(index + 1);
+        let modifier = part.modifier.as_str();
+
+        if part.kind == PartType::FixedText {
+            if part.modifier == PartModifier::None {
+                result.push_str(&escape_pattern_string(&part.value));
+            } else {
+                result
+                    .push_str(&["{", &escape_pattern_string(&part.value), "}", modifier].concat());
+            }
+            continue;
+        }
+
+        let custom_name = !part.name.starts_with(|c: char| c.is_ascii_digit());
+        let mut needs_grouping =
+            !part.suffix.is_empty() || (!part.prefix.is_empty() && part.prefix != options.prefix);
+        if !needs_grouping
+            && custom_name
+            && part.kind == PartType::SegmentWildcard
+            && part.modifier == PartModifier::None
+        {
+            if let Some(next) = next.filter(|n| n.prefix.is_empty() && n.suffix.is_empty()) {
+                needs_grouping = if next.kind == PartType::FixedText {
+                    next.value
+                        .chars()
+                        .next()
+                        .is_some_and(|c| is_valid_name_code_point(c, false))
+                } else {
+                    next.name.starts_with(|c: char| c.is_ascii_digit())
+                };
+            }
+        }
+        if !needs_grouping && part.prefix.is_empty() {
+            if let Some(previous) = previous {
+                needs_grouping = previous.kind == PartType::FixedText
+                    && !options.prefix.is_empty()
+                    && previous.value.ends_with(options.prefix);
+            }
+        }
+
+        if needs_grouping {
+            result.push('{');
+        }
+        result.push_str(&escape_pattern_string(&part.prefix));
+        if custom_name {
+            result.push(':');
+            result.push_str(&part.name);
+        }
+        match part.kind {
+            PartType::Regexp => result.push_str(&["(", &part.value, ")"].concat()),
+            PartType::SegmentWildcard if !custom_name => {
+                result.push_str(&["(", &segment_wildcard_regexp, ")"].concat())
+            }
+            PartType::FullWildcard => {
+                let plain_asterisk = !custom_name
+                    && (previous.is_none_or(|p| {
+                        p.kind == PartType::FixedText || p.modifier != PartModifier::None
+                    }) || needs_grouping
+                        || !part.prefix.is_empty());
+                if plain_asterisk {
+                    result.push('*');
+                } else {
+                    result.push_str(&["(", FULL_WILDCARD_REGEXP, ")"].concat());
+                }
+            }
+            _ => {}
+        }
+        if part.kind == PartType::SegmentWildcard
+            && custom_name
+            && part
+                .suffix
+                .chars()
+                .next()
+                .is_some_and(|c| is_valid_name_code_point(c, false))
+        {
+            result.push('\\');
+        }
+        result.push_str(&escape_pattern_string(&part.suffix));
+        if needs_grouping {
+            result.push('}');
+        }
+        result.push_str(modifier);
+    }
+    result
+}
+
+fn invalid(ctx: &Ctx<'_>, component: &str, value: &str) -> rsquickjs::Error {
+    Exception::throw_type(ctx, &["Invalid ", component, " '", value, "'"].concat())
+}
+
+fn dummy_url() -> Url {
+    Url::parse(DUMMY_URL).unwrap()
+}
+
+fn canonicalize_protocol(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    match Url::parse(&[value, "://dummy.invalid"].concat()) {
+        Ok(url) => Ok(url.scheme().into()),
+        Err(_) => Err(invalid(ctx, "protocol", value)),
+    }
+}
+
+fn canonicalize_username(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let mut url = dummy_url();
+    quirks::set_username(&mut url, value).map_err(|_| invalid(ctx, "username", value))?;
+    Ok(url.username().into())
+}
+
+fn canonicalize_password(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let mut url = dummy_url();
+    quirks::set_password(&mut url, value).map_err(|_| invalid(ctx, "password", value))?;
+    Ok(url.password().unwrap_or_default().into())
+}
+
+fn canonicalize_hostname(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let mut url = dummy_url();
+    quirks::set_hostname(&mut url, value).map_err(|_| invalid(ctx, "hostname", value))?;
+    Ok(url.host_str().unwrap_or_default().into())
+}
+
+fn canonicalize_ipv6_hostname(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() || matches!(c, '[' | ']' | ':'))
+    {
+        return Err(invalid(ctx, "IPv6 hostname", value));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
+fn canonicalize_port(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    canonicalize_port_for_protocol(ctx, value, None)
+}
+
+fn canonicalize_port_for_protocol(
+    ctx: &Ctx<'_>,
+    value: &str,
+    protocol: Option<&str>,
+) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let port = value
+        .parse::<u16>()
+        .ok()
+        .filter(|_| value.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or_else(|| invalid(ctx, "port", value))?;
+    if protocol.and_then(default_port) == Some(port) {
+        return Ok(String::new());
+    }
+    Ok(port.to_string())
+}
+
+fn canonicalize_pathname(_ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    // the URL parser always produces a leading slash, so relative parts are prefixed and stripped
+    let leading_slash = value.starts_with('/');
+    let mut url = dummy_url();
+    if leading_slash {
+        quirks::set_pathname(&mut url, value);
+    } else {
+        quirks::set_pathname(&mut url, &["/-", value].concat());
+    }
+    let path = url.path();
+    Ok(if leading_slash { path } else { &path[2..] }.into())
+}
+
+fn canonicalize_opaque_pathname(ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    match Url::parse(&["xmas:", value].concat()) {
+        Ok(url) => Ok(url.path().into()),
+        Err(_) => Err(invalid(ctx, "pathname", value)),
+    }
+}
+
+fn canonicalize_search(_ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let mut url = dummy_url();
+    quirks::set_search(&mut url, value);
+    Ok(url.query().unwrap_or_default().into())
+}
+
+fn canonicalize_hash(_ctx: &Ctx<'_>, value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    let mut url = dummy_url();
+    quirks::set_hash(&mut url, value);
+    Ok(url.fragment().unwrap_or_default().into())
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+fn is_ipv6_hostname_pattern(value: &str) -> bool {
+    value.starts_with('[') || value.starts_with("{[") || value.starts_with("\\[")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InitType {
+    Pattern,
+    Url,
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+struct UrlPatternInit {
+    protocol: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    hostname: Option<String>,
+    port: Option<String>,
+    pathname: Option<String>,
+    search: Option<String>,
+    hash: Option<String>,
+    base_url: Option<String>,
+}
+
+impl UrlPatternInit {
+    fn from_object(obj: &Object<'_>) -> Result<Self> {
+        let get = |key: &str| -> Result<Option<String>> {
+            Ok(obj.get::<_, Option<Coerced<String>>>(key)?.map(|v| v.0))
+        };
+        Ok(Self {
+            protocol: get("protocol")?,
+            username: get("username")?,
+            password: get("password")?,
+            hostname: get("hostname")?,
+            port: get("port")?,
+            pathname: get("pathname")?,
+            search: get("search")?,
+            hash: get("hash")?,
+            base_url: get("baseURL")?,
+        })
+    }
+
+    fn set(&mut self, state: ParserState, value: String) {
+        let field = match state {
+            ParserState::Protocol => &mut self.protocol,
+            ParserState::Username => &mut self.username,
+            ParserState::Password => &mut self.password,
+            ParserState::Hostname => &mut self.hostname,
+            ParserState::Port => &mut self.port,
+            ParserState::Pathname => &mut self.pathname,
+            ParserState::Search => &mut self.search,
+            ParserState::Hash => &mut self.hash,
+            _ => return,
+        };
+        *field = Some(value);
+    }
+}
+
+// https://urlpattern.spec.whatwg.org/#process-a-urlpatterninit
+fn process_init(
+    ctx: &Ctx<'_>,
+    init: &UrlPatternInit,
+    kind: InitType,
+    defaults: Option<&str>,
+) -> Result<UrlPatternInit> {
+    let default = || defaults.map(String::from);
+    let mut result = UrlPatternInit {
+        protocol: default(),
+        username: default(),
+        password: default(),
+        hostname: default(),
+        port: default(),
+        pathname: default(),
+        search: default(),
+        hash: default(),
+        base_url: None,
+    };
+    let base_string = |value: &str| match kind {
+        InitType::Pattern => escape_pattern_string(value),
+        InitType::Url => value.to_string(),
+    };
+
+    let mut base_url = None;
+    if let Some(base) = &init.base_url {
+        let base = Url::parse(base).map_err(|_| invalid(ctx, "base URL", base))?;
+        let has = |fields: &[&Option<String>]| fields.iter().any(|f| f.is_some());
+
+        if init.protocol.is_none() {
+            result.protocol = Some(base_string(base.scheme()));
+        }
+        if kind != InitType::Pattern
+            && !has(&[&init.protocol, &init.hostname, &init.port, &init.username])
+        {
+            result.username = Some(base_string(base.username()));
+        }
+        if kind != InitType::Pattern
+            && !has(&[
+                &init.protocol,
+                &init.hostname,
+                &init.port,
+                &init.username,
+                &init.password,
+            ])
+        {
+            result.password = Some(base_string(base.password().unwrap_or_default()));
+        }
+        if !has(&[&init.protocol, &init.hostname]) {
+            result.hostname = Some(base_string(base.host_str().unwrap_or_default()));
+        }
+        if !has(&[&init.protocol, &init.hostname, &init.port]) {
+            result.port = Some(base.port().map(|p| p.to_string()).unwrap_or_default());
+        }
+        if !has(&[&init.protocol, &init.hostname, &init.port, &init.pathname]) {
+            result.pathname = Some(base_string(base.path()));
+        }
+        if !has(&[
+            &init.protocol,
+            &init.hostname,
+            &init.port,
+            &init.pathname,
+            &init.search,
+        ]) {
+            result.search = Some(base_string(base.query().unwrap_or_default()));
+        }
+        if !has(&[
+            &init.protocol,
+            &init.hostname,
+            &init.port,
+            &init.pathname,
+            &init.search,
+            &init.hash,
+        ]) {
+            result.hash = Some(base_string(base.fragment().unwrap_or_default()));
+        }
+        base_url = Some(base);
+    }
+
+    let process = |value: &str, canonicalize: EncodingCallback| -> Result<String> {
+        match kind {
+            InitType::Pattern => Ok(value.to_string()),
+            InitType::Url => canonicalize(ctx, value),
+        }
+    };
+
+    if let Some(protocol) = &init.protocol {
+        let protocol = protocol.strip_suffix(':').unwrap_or(protocol);
+        result.protocol = Some(process(protocol, canonicalize_protocol)?);
+    }
+    if let Some(username) = &init.username {
+        result.username = Some(process(username, canonicalize_username)?);
+    }
+    if let Some(password) = &init.password {
+        result.password = Some(process(password, canonicalize_password)?);
+    }
+    if let Some(hostname) = &init.hostname {
+        result.hostname = Some(process(hostname, canonicalize_hostname)?);
+    }
+    if let Some(port) = &init.port {
+        result.port = Some(match kind {
+            InitType::Pattern => port.clone(),
+            InitType::Url => canonicalize_port_for_protocol(ctx, port, result.protocol.as_deref())?,
+        });
+    }
+    if let Some(pathname) = &init.pathname {
+        let mut pathname = pathname.clone();
+        if let Some(base) = base_url.as_ref().filter(|base| !base.cannot_be_a_base()) {
+            if !is_absolute_pathname(&pathname, kind) {
+                let base_path = base_string(base.path());
+                if let Some(slash) = base_path.rfind('/') {
+                    pathname.insert_str(0, &base_path[..=slash]);
+                }
+            }
+        }
+        let special = result
+            .protocol
+            .as_deref()
+            .is_none_or(|p| p.is_empty() || SPECIAL_SCHEMES.contains(&p));
+        result.pathname = Some(match special {
+            true => process(&pathname, canonicalize_pathname)?,
+            false => process(&pathname, canonicalize_opaque_pathname)?,
+        });
+    }
+    if let Some(search) = &init.search {
+        let search = search.strip_prefix('?').unwrap_or(search);
+        result.search = Some(process(search, canonicalize_search)?);
+    }
+    if let Some(hash) = &init.hash {
+        let hash = hash.strip_prefix('#').unwrap_or(hash);
+        result.hash = Some(process(hash, canonicalize_hash)?);
+    }
+    Ok(result)
+}
+
+fn is_absolute_pathname(input: &str, kind: InitType) -> bool {
+    if input.starts_with('/') {
+        return true;
+    }
+    kind == InitType::Pattern && (input.starts_with("\\/") || input.starts_with("{/"))
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ParserState {
+    Init,
+    Protocol,
+    Authority,
+    Username,
+    Password,
+    Hostname,
+    Port,
+    Pathname,
+    Search,
+    Hash,
+    Done,
+}
+
+// https://urlpattern.spec.whatwg.org/#constructor-string-parsing
+struct ConstructorStringParser<'a, 'js> {
+    ctx: &'a Ctx<'js>,
+    input: &'a str,
+    tokens: Vec<Token>,
+    result: UrlPatternInit,
+    component_start: usize,
+    token_index: usize,
+    token_increment: usize,
+    group_depth: usize,
+    hostname_ipv6_bracket_depth: usize,
+    protocol_matches_special_scheme: bool,
+    state: ParserState,
+}
+
+impl<'a, 'js> ConstructorStringParser<'a, 'js> {
+    fn parse(ctx: &'a Ctx<'js>, input: &'a str) -> Result<UrlPatternInit> {
+        let mut parser = Self {
+            ctx,
+            input,
+            tokens: tokenize(ctx, input, TokenizePolicy::Lenient)?,
+            result: UrlPatternInit::default(),
+            component_start: 0,
+            token_index: 0,
+            token_increment: 1,
+            group_depth: 0,
+            hostname_ipv6_bracket_depth: 0,
+            protocol_matches_special_scheme: false,
+            state: ParserState::Init,
+        };
+
+        while parser.token_index < parser.tokens.len() {
+            parser.token_increment = 1;
+
+            if parser.tokens[parser.token_index].kind == TokenType::End {
+                if parser.state == ParserState::Init {
+                    parser.rewind();
+                    if parser.is_hash_prefix() {
+                        parser.change_state(ParserState::Hash, 1);
+                    } else if parser.is_search_prefix() {
+                        parser.change_state(ParserState::Search, 1);
+                    } else {
+                        parser.change_state(ParserState::Pathname, 0);
+                    }
+                    parser.token_index += parser.token_increment;
+                    continue;
+                }
+                if parser.state == ParserState::Authority {
+                    parser.rewind_and_set_state(ParserState::Hostname);
+                    parser.token_index += parser.token_increment;
+                    continue;
+                }
+                parser.change_state(ParserState::Done, 0);
+                break;
+            }
+
+            if parser.group_depth > 0 {
+                if parser.is_token(TokenType::Close) {
+                    parser.group_depth -= 1;
+                } else {
+                    parser.token_index += parser.token_increment;
+                    continue;
+                }
+            }
+            if parser.is_token(TokenType::Open) {
+                parser.group_depth += 1;
+                parser.token_index += parser.token_increment;
+                continue;
+            }
+
+            match parser.state {
+                ParserState::Init => {
+                    if parser.is_non_special_pattern_char(parser.token_index, ":") {
+                        parser.rewind_and_set_state(ParserState::Protocol);
+                    }
+                }
+                ParserState::Protocol => {
+                    if parser.is_non_special_pattern_char(parser.token_index, ":") {
+                        parser.compute_protocol_matches_special_scheme()?;
+                        let mut next_state = ParserState::Pathname;
+                        let mut skip = 1;
+                        if parser.next_is_authority_slashes() {
+                            next_state = ParserState::Authority;
+                            skip = 3;
+                        } else if parser.protocol_matches_special_scheme {
+                            next_state = ParserState::Authority;
+                        }
+                        parser.change_state(next_state, skip);
+                    }
+                }
+                ParserState::Authority => {
+                    if parser.is_non_special_pattern_char(parser.token_index, "@") {
+                        parser.rewind_and_set_state(ParserState::Username);
+                    } else if parser.is_non_special_pattern_char(parser.token_index, "/")
+                        || parser.is_search_prefix()
+                        || parser.is_hash_prefix()
+                    {
+                        parser.rewind_and_set_state(ParserState::Hostname);
+                    }
+                }
+                ParserState::Username => {
+                    if parser.is_non_special_pattern_char(parser.token_index, ":") {
+                        parser.change_state(ParserState::Password, 1);
+                    } else if parser.is_non_special_pattern_char(parser.token_index, "@") {
+                        parser.change_state(ParserState::Hostname, 1);
+                    }
+                }
+                ParserState::Password => {
+                    if parser.is_non_special_pattern_char(parser.token_index, "@") {
+                        parser.change_state(ParserState::Hostname, 1);
+                    }
+                }
+                ParserState::Hostname => {
+                    if parser.is_non_special_pattern_char(parser.token_index, "[") {
+                        parser.hostname_ipv6_bracket_depth += 1;
+                    } else if parser.is_non_special_pattern_char(parser.token_index, "]") {
+                        parser.hostname_ipv6_bracket_depth =
+                            parser.hostname_ipv6_bracket_depth.saturating_sub(1);
+                    } else if parser.is_non_special_pattern_char(parser.token_index, ":")
+                        && parser.hostname_ipv6_bracket_depth == 0
+                    {
+                        parser.change_state(ParserState::Port, 1);
+                    } else if parser.is_non_special_pattern_char(parser.token_index, "/") {
+                        parser.change_state(ParserState::Pathname, 0);
+                    } else if parser.is_search_prefix() {
+                        parser.change_state(ParserState::Search, 1);
+                    } else if parser.is_hash_prefix() {
+                        parser.change_state(ParserState::Hash, 1);
+                    }
+                }
+                ParserState::Port => {
+                    if parser.is_non_special_pattern_char(parser.token_index, "/") {
+                        parser.change_state(ParserState::Pathname, 0);
+                    } else if parser.is_search_prefix() {
+                        parser.change_state(ParserState::Search, 1);
+                    } else if parser.is_hash_prefix() {
+                        parser.change_state(ParserState::Hash, 1);
+                    }
+                }
+                ParserState::Pathname => {
+                    if parser.is_search_prefix() {
+                        parser.change_state(ParserState::Search, 1);
+                    } else if parser.is_hash_prefix() {
+                        parser.change_state(ParserState::Hash, 1);
+                    }
+                }
+                ParserState::Search => {
+                    if parser.is_hash_prefix() {
+                        parser.change_state(ParserState::Hash, 1);
+                    }
+                }
+                ParserState::Hash | ParserState::Done => {}
+            }
+            parser.token_index += parser.token_increment;
+        }
+
+        if parser.result.hostname.is_some() && parser.result.port.is_none() {
+            parser.result.port = Some(String::new());
+        }
+        Ok(parser.result)
+    }
+
+    fn change_state(&mut self, new_state: ParserState, skip: usize) {
+        use ParserState::*;
+
+        if !matches!(self.state, Init | Authority | Done) {
+            let component = self.make_component_string();
+            self.result.set(self.state, component);
+        }
+        if self.state != Init && new_state != Done {
+            if matches!(self.state, Protocol | Authority | Username | Password)
+                && matches!(new_state, Port | Pathname | Search | Hash)
+                && self.result.hostname.is_none()
+            {
+                self.result.hostname = Some(String::new());
+            }
+            if matches!(
+                self.state,
+                Protocol | Authority | Username | Password | Hostname | Port
+            ) && matches!(new_state, Search | Hash)
+                && self.result.pathname.is_none()
+            {
+                self.result.pathname = Some(match self.protocol_matches_special_scheme {
+                    true => "/".into(),
+                    false => String::new(),
+                });
+            }
+            if matches!(
+                self.state,
+                Protocol | Authority | Username | Password | Hostname | Port | Pathname
+            ) && new_state == Hash
+                && self.result.search.is_none()
+            {
+                self.result.search = Some(String::new());
+            }
+        }
+
+        self.state = new_state;
+        self.token_index += skip;
+        self.component_start = self.token_index;
+        self.token_increment = 0;
+    }
+
+    fn rewind(&mut self) {
+        self.token_index = self.component_start;
+        self.token_increment = 0;
+    }
+
+    fn rewind_and_set_state(&mut self, state: ParserState) {
+        self.rewind();
+        self.state = state;
+    }
+
+    fn safe_token(&self, index: usize) -> &Token {
+        self.tokens
+            .get(index)
+            .unwrap_or_else(|| &self.tokens[self.tokens.len() - 1])
+    }
+
+    fn is_token(&self, kind: TokenType) -> bool {
+        self.tokens[self.token_index].kind == kind
+    }
+
+    fn is_non_special_pattern_char(&self, index: usize, value: &str) -> bool {
+        let token = self.safe_token(index);
+        token.value == value
+            && matches!(
+                token.kind,
+                TokenType::Char | TokenType::EscapedChar | TokenType::InvalidChar
+            )
+    }
+
+    fn is_search_prefix(&self) -> bool {
+        if self.is_non_special_pattern_char(self.token_index, "?") {
+            return true;
+        }
+        if self.tokens[self.token_index].value != "?" {
+            return false;
+        }
+        let Some(previous) = self.token_index.checked_sub(1) else {
+            return true;
+        };
+        !matches!(
+            self.safe_token(previous).kind,
+            TokenType::Name | TokenType::Regexp | TokenType::Close | TokenType::Asterisk
+        )
+    }
+
+    fn is_hash_prefix(&self) -> bool {
+        self.is_non_special_pattern_char(self.token_index, "#")
+    }
+
+    fn next_is_authority_slashes(&self) -> bool {
+        self.is_non_special_pattern_char(self.token_index + 1, "/")
+            && self.is_non_special_pattern_char(self.token_index + 2, "/")
+    }
+
+    fn make_component_string(&self) -> String {
+        let end = self.safe_token(self.token_index).index;
+        let start = self.safe_token(self.component_start).index;
+        self.input[start..end].to_string()
+    }
+
+    fn compute_protocol_matches_special_scheme(&mut self) -> Result<()> {
+        let protocol = self.make_component_string();
+        let component = Component::compile(
+            self.ctx,
+            &protocol,
+            canonicalize_protocol,
+            &PatternOptions::DEFAULT,
+        )?;
+        self.protocol_matches_special_scheme = component.matches_special_scheme()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct Component<'js> {
+    pattern: String,
+    regexp: Object<'js>,
+    names: Vec<String>,
+    has_regexp_groups: bool,
+}
+
+impl<'js> Trace<'js> for Component<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.regexp.trace(tracer);
+    }
+}
+
+impl<'js> Component<'js> {
+    // https://urlpattern.spec.whatwg.org/#compile-a-component
+    fn compile(
+        ctx: &Ctx<'js>,
+        input: &str,
+        encode: EncodingCallback,
+        options: &PatternOptions,
+    ) -> Result<Self> {
+        let parts = PatternParser::parse(ctx, input, options, encode)?;
+        let (source, names) = generate_regexp(&parts, options);
+        let flags = if options.ignore_case { "ui" } else { "u" };
+        let regexp = BasePrimordials::get(ctx)?
+            .constructor_regexp
+            .construct((source, flags))?;
+
+        Ok(Self {
+            pattern: generate_pattern_string(&parts, options),
+            regexp,
+            names,
+            has_regexp_groups: parts.iter().any(|part| part.kind == PartType::Regexp),
+        })
+    }
+
+    fn exec(&self, input: &str) -> Result<Option<Array<'js>>> {
+        let exec: Function = self.regexp.get("exec")?;
+        exec.call((This(self.regexp.clone()), input))
+    }
+
+    fn matches_special_scheme(&self) -> Result<bool> {
+        for scheme in SPECIAL_SCHEMES {
+            if self.exec(scheme)?.is_some() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn result(&self, ctx: &Ctx<'js>, input: &str, matched: Array<'js>) -> Result<Object<'js>> {
+        let groups = Object::new(ctx.clone())?;
+        for (index, name) in self.names.iter().enumerate() {
+            let value: Value = matched.get(index + 1)?;
+            groups.set(name.as_str(), value)?;
+        }
+        let result = Object::new(ctx.clone())?;
+        result.set("input", input)?;
+        result.set("groups", groups)?;
+        Ok(result)
+    }
+}
+
+/// Represents a JavaScript
+/// [`URLPattern`](https://developer.mozilla.org/en-US/docs/Web/API/URLPattern) used to match
+/// URLs against patterns with named groups and wildcards.
+#[derive(Clone, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class]
+pub struct URLPattern<'js> {
+    protocol: Component<'js>,
+    username: Component<'js>,
+    password: Component<'js>,
+    hostname: Component<'js>,
+    port: Component<'js>,
+    pathname: Component<'js>,
+    search: Component<'js>,
+    hash: Component<'js>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> URLPattern<'js> {
+    #[qjs(constructor)]
+    pub fn new(
+        ctx: Ctx<'js>,
+        input: Opt<Value<'js>>,
+        base_or_options: Opt<Value<'js>>,
+        options: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let (base, options) = match base_or_options.0 {
+            Some(base) if base.is_string() => (Some(base), options.0),
+            options => (None, options),
+        };
+        let ignore_case = match options.as_ref().and_then(|o| o.as_object()) {
+            Some(options) => options
+                .get::<_, Option<bool>>("ignoreCase")?
+                .unwrap_or_default(),
+            None => false,
+        };
+
+        let init = match input.0 {
+            Some(input) if input.is_string() => {
+                let input: String = input.get()?;
+                let mut init = ConstructorStringParser::parse(&ctx, &input)?;
+                let base = base.map(|base| base.get::<String>()).transpose()?;
+                if base.is_none() && init.protocol.is_none() {
+                    return Err(Exception::throw_type(
+                        &ctx,
+                        "Relative constructor string requires a base URL",
+                    ));
+                }
+                init.base_url = base;
+                init
+            }
+            Some(input) if input.is_object() => {
+                if base.is_some() {
+                    return Err(Exception::throw_type(
+                        &ctx,
+                        "Base URL is not allowed with a URLPatternInit",
+                    ));
+                }
+                UrlPatternInit::from_object(input.as_object().unwrap())?
+            }
+            Some(input) if !input.is_undefined() => {
+                return Err(Exception::throw_type(&ctx, "Invalid URLPattern input"));
+            }
+            _ => UrlPatternInit::default(),
+        };
+
+        Self::from_init(&ctx, &init, ignore_case)
+    }
+
+    pub fn test(
+        &self,
+        ctx: Ctx<'js>,
+        input: Opt<Value<'js>>,
+        base: Opt<Coerced<String>>,
+    ) -> Result<bool> {
+        Ok(self.match_input(&ctx, input, base)?.is_some())
+    }
+
+    pub fn exec(
+        &self,
+        ctx: Ctx<'js>,
+        input: Opt<Value<'js>>,
+        base: Opt<Coerced<String>>,
+    ) -> Result<Value<'js>> {
+        Ok(match self.match_input(&ctx, input, base)? {
+            Some(result) => result.into_value(),
+            None => Value::new_null(ctx),
+        })
+    }
+
+    #[qjs(get)]
+    pub fn protocol(&self) -> String {
+        self.protocol.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn username(&self) -> String {
+        self.username.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn password(&self) -> String {
+        self.password.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn hostname(&self) -> String {
+        self.hostname.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn port(&self) -> String {
+        self.port.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn pathname(&self) -> String {
+        self.pathname.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn search(&self) -> String {
+        self.search.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn hash(&self) -> String {
+        self.hash.pattern.clone()
+    }
+
+    #[qjs(get)]
+    pub fn has_reg_exp_groups(&self) -> bool {
+        self.components().iter().any(|c| c.has_regexp_groups)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        stringify!(URLPattern)
+    }
+}
+
+impl<'js> URLPattern<'js> {
+    // https://urlpattern.spec.whatwg.org/#urlpattern-create
+    fn from_init(ctx: &Ctx<'js>, init: &UrlPatternInit, ignore_case: bool) -> Result<Self> {
+        let mut init = process_init(ctx, init, InitType::Pattern, None)?;
+        let wildcard = || Some("*".to_string());
+        for field in [
+            &mut init.protocol,
+            &mut init.username,
+            &mut init.password,
+            &mut init.hostname,
+            &mut init.port,
+            &mut init.pathname,
+            &mut init.search,
+            &mut init.hash,
+        ] {
+            if field.is_none() {
+                *field = wildcard();
+            }
+        }
+        let field = |value: &Option<String>| value.clone().unwrap_or_default();
+        let protocol = field(&init.protocol);
+        let mut port = field(&init.port);
+        if SPECIAL_SCHEMES.contains(&protocol.as_str())
+            && default_port(&protocol).map(|p| p.to_string()).as_deref() == Some(port.as_str())
+        {
+            port.clear();
+        }
+
+        let default_options = PatternOptions::DEFAULT;
+        let protocol = Component::compile(ctx, &protocol, canonicalize_protocol, &default_options)?;
+        let hostname = field(&init.hostname);
+        let hostname_encoding = match is_ipv6_hostname_pattern(&hostname) {
+            true => canonicalize_ipv6_hostname,
+            false => canonicalize_hostname,
+        };
+        let pathname = match protocol.matches_special_scheme()? {
+            true => Component::compile(
+                ctx,
+                &field(&init.pathname),
+                canonicalize_pathname,
+                &PatternOptions::PATHNAME.with_ignore_case(ignore_case),
+            )?,
+            false => Component::compile(
+                ctx,
+                &field(&init.pathname),
+                canonicalize_opaque_pathname,
+                &PatternOptions::DEFAULT.with_ignore_case(ignore_case),
+            )?,
+        };
+        let compile_options = PatternOptions::DEFAULT.with_ignore_case(ignore_case);
+
+        Ok(Self {
+            username: Component::compile(
+                ctx,
+                &field(&init.username),
+                canonicalize_username,
+                &default_options,
+            )?,
+            password: Component::compile(
+                ctx,
+                &field(&init.password),
+                canonicalize_password,
+                &default_options,
+            )?,
+            hostname: Component::compile(
+                ctx,
+                &hostname,
+                hostname_encoding,
+                &PatternOptions::HOSTNAME,
+            )?,
+            port: Component::compile(ctx, &port, canonicalize_port, &default_options)?,
+            pathname,
+            search: Component::compile(
+                ctx,
+                &field(&init.search),
+                canonicalize_search,
+                &compile_options,
+            )?,
+            hash: Component::compile(ctx, &field(&init.hash), canonicalize_hash, &compile_options)?,
+            protocol,
+        })
+    }
+
+    fn components(&self) -> [&Component<'js>; 8] {
+        [
+            &self.protocol,
+            &self.username,
+            &self.password,
+            &self.hostname,
+            &self.port,
+            &self.pathname,
+            &self.search,
+            &self.hash,
+        ]
+    }
+
+    // https://urlpattern.spec.whatwg.org/#url-pattern-match
+    fn match_input(
+        &self,
+        ctx: &Ctx<'js>,
+        input: Opt<Value<'js>>,
+        base: Opt<Coerced<String>>,
+    ) -> Result<Option<Object<'js>>> {
+        let input = match input.0 {
+            Some(input) => input,
+            None => Object::new(ctx.clone())?.into_value(),
+        };
+        let inputs = Array::new(ctx.clone())?;
+        inputs.set(0, input.clone())?;
+
+        let values: [String; 8] = if let Some(init) = input.as_object() {
+            if base.0.is_some() {
+                return Err(Exception::throw_type(
+                    ctx,
+                    "Base URL is not allowed with a URLPatternInit",
+                ));
+            }
+            let init = UrlPatternInit::from_object(init)?;
+            // inputs that fail to canonicalize simply don't match
+            let Ok(init) = process_init(ctx, &init, InitType::Url, Some("")) else {
+                let _ = ctx.catch();
+                return Ok(None);
+            };
+            let field = |value: Option<String>| value.unwrap_or_default();
+            [
+                field(init.protocol),
+                field(init.username),
+                field(init.password),
+                field(init.hostname),
+                field(init.port),
+                field(init.pathname),
+                field(init.search),
+                field(init.hash),
+            ]
+        } else {
+            let input: Coerced<String> = input.get()?;
+            let url = match base.0 {
+                Some(Coerced(base)) => {
+                    inputs.set(1, base.as_str())?;
+                    let Ok(base) = Url::parse(&base) else {
+                        return Ok(None);
+                    };
+                    base.join(&input)
+                }
+                None => Url::parse(&input),
+            };
+            let Ok(url) = url else {
+                return Ok(None);
+            };
+            [
+                url.scheme().into(),
+                url.username().into(),
+                url.password().unwrap_or_default().into(),
+                url.host_str().unwrap_or_default().into(),
+                url.port().map(|p| p.to_string()).unwrap_or_default(),
+                url.path().into(),
+                url.query().unwrap_or_default().into(),
+                url.fragment().unwrap_or_default().into(),
+            ]
+        };
+
+        let names = [
+            "protocol", "username", "password", "hostname", "port", "pathname", "search", "hash",
+        ];
+        let result = Object::new(ctx.clone())?;
+        result.set("inputs", inputs)?;
+        for ((component, value), name) in self.components().into_iter().zip(values).zip(names) {
+            let Some(matched) = component.exec(&value)? else {
+                return Ok(None);
+            };
+            result.set(name, component.result(ctx, &value, matched)?)?;
+        }
+        Ok(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::test_sync_with;
+    use rsquickjs::{CatchResultExt, Class};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_url_pattern() {
+        test_sync_with(|ctx| {
+            BasePrimordials::init(&ctx)?;
+            Class::<URLPattern>::define(&ctx.globals()).unwrap();
+            let result = ctx
+                .eval::<String, _>(
+                    r#"
+                const results = [];
+                const users = new URLPattern({ pathname: '/users/:id' });
+                results.push(users.test('https://example.com/users/42'));
+                results.push(users.exec('https://example.com/users/42').pathname.groups.id);
+                results.push(users.test('https://example.com/posts/42'));
+
+                const api = new URLPattern('https://*.example.com/api/:version(v\\d+)/*');
+                const match = api.exec('https://eu.example.com/api/v2/items/7?x=1');
+                results.push(match.hostname.groups[0], match.pathname.groups.version);
+                results.push(match.pathname.groups[0], api.hasRegExpGroups);
+
+                const relative = new URLPattern('/books/:title?', 'https://example.com');
+                results.push(relative.test('https://example.com/books'));
+                results.push(relative.pathname, relative.protocol, relative.port);
+
+                const files = new URLPattern({ pathname: '/static/*.:ext' });
+                results.push(files.exec({ pathname: '/static/app.min.js' }).pathname.groups.ext);
+                results.join(',')
+            "#,
+                )
+                .catch(&ctx)
+                .unwrap();
+            assert_eq!(
+                result,
+                "true,42,false,eu,v2,items/7,true,true,/books/:title?,https,,js"
+            );
+            Ok(())
+        })
+        .await
+    }
+}
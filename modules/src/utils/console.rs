@@ -1,12 +1,4 @@
-use std::{
-    collections::HashSet,
-    io::{stderr, stdout, IsTerminal, Write},
-    mem,
-    ops::Deref,
-    process::exit,
-    slice,
-    string::String,
-};
+use std::{collections::HashSet, mem, ops::Deref, process::exit, slice, string::String};
 
 use super::{
     class::get_class_name,
@@ -14,6 +6,7 @@ use super::{
     hash,
     primordials::{BasePrimordials, Primordial},
 };
+use crate::permissions::get_stdio;
 use crate::utils::json::stringify::json_stringify;
 use crate::utils::numbers::float_to_string;
 use rsquickjs::{
@@ -26,6 +19,7 @@ use rsquickjs::{
     Error::{self},
     Function, Object, Result, Symbol, Type, Value,
 };
+use xmas_vsys::StdStream;
 
 pub const NEWLINE: char = '\n';
 pub const CARRIAGE_RETURN: char = '\r';
@@ -143,6 +137,7 @@ pub struct FormatOptions<'js> {
     parse_int: Function<'js>,
     object_filter: Filter,
     custom_inspect_symbol: Symbol<'js>,
+    max_depth: usize,
 }
 
 impl<'js> FormatOptions<'js> {
@@ -170,9 +165,17 @@ impl<'js> FormatOptions<'js> {
             parse_float,
             parse_int,
             custom_inspect_symbol,
+            max_depth: MAX_EXPANSION_DEPTH,
         };
         Ok(options)
     }
+
+    /// Sets how many levels of nested objects are expanded before they are printed as
+    /// `[Object]` or `[Array]`.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
 }
 
 pub fn format_plain<'js>(ctx: Ctx<'js>, newline: bool, args: Rest<Value<'js>>) -> Result<String> {
@@ -180,7 +183,8 @@ pub fn format_plain<'js>(ctx: Ctx<'js>, newline: bool, args: Rest<Value<'js>>) -
 }
 
 pub fn format<'js>(ctx: &Ctx<'js>, newline: bool, args: Rest<Value<'js>>) -> Result<String> {
-    format_values(ctx, args, stdout().is_terminal(), newline)
+    let tty = (get_stdio(ctx).is_terminal)(StdStream::Stdout);
+    format_values(ctx, args, tty, newline)
 }
 
 pub fn format_values<'js>(
@@ -474,6 +478,18 @@ fn format_raw_inner<'js>(
                 result.push_str(&name);
                 result.push_str(": ");
                 result.push_str(&message);
+                // syntax errors of transpiled sources point at the code
+                if let Ok(Some(code_frame)) = obj.get::<_, Option<String>>("codeFrame") {
+                    for line in code_frame.split('\n') {
+                        result.push(if options.newline {
+                            NEWLINE
+                        } else {
+                            CARRIAGE_RETURN
+                        });
+                        push_indentation(result, depth + 1);
+                        result.push_str(line);
+                    }
+                }
                 if color_enabled {
                     Color::BLACK.push(result);
                 }
@@ -535,7 +551,7 @@ fn format_raw_inner<'js>(
                 }
             }
 
-            if depth < MAX_EXPANSION_DEPTH {
+            if depth < options.max_depth {
                 let mut is_typed_array = false;
                 if let Some(class_name) = class_name {
                     result.push_str(&class_name);
@@ -872,8 +888,9 @@ pub fn print_error_and_exit<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) -> ! {
     };
 }
 
-fn print_error<'js>(ctx: &Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
-    let is_tty = stderr().is_terminal();
+pub fn print_error<'js>(ctx: &Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
+    let stdio = get_stdio(ctx);
+    let is_tty = (stdio.is_terminal)(StdStream::Stderr);
     let mut result = String::new();
 
     let mut options = FormatOptions::new(ctx, is_tty, true)?;
@@ -882,7 +899,7 @@ fn print_error<'js>(ctx: &Ctx<'js>, args: Rest<Value<'js>>) -> Result<()> {
     result.push(NEWLINE);
 
     //we don't care if output is interrupted
-    let _ = stderr().write_all(result.as_bytes());
+    let _ = stdio.write(StdStream::Stderr, result.as_bytes());
 
     Ok(())
 }
@@ -1,5 +1,5 @@
 use super::primordials::{BasePrimordials, Primordial};
-use rsquickjs::{atom::PredefinedAtom, CatchResultExt, CaughtError, Object};
+use rsquickjs::{atom::PredefinedAtom, CatchResultExt, CaughtError, Exception, Object};
 use rsquickjs::{Ctx, Result};
 use std::future::Future;
 use std::ptr::NonNull;
@@ -36,6 +36,12 @@ pub trait CtxExtension<'js> {
     where
         F: Future<Output = Result<()>> + 'js;
 
+    /// Same as `spawn_exit_simple`, but the future doesn't keep the script running,
+    /// see [`crate::event_loop`].
+    fn spawn_unref<F>(&self, future: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + 'js;
+
     fn get_background_task_poller(&self) -> tokio::task::JoinHandle<()>;
 }
 
@@ -78,6 +84,23 @@ impl<'js> CtxExtension<'js> for Ctx<'js> {
         });
     }
 
+    fn spawn_unref<F>(&self, future: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + 'js,
+    {
+        let mut shutdown = crate::event_loop::track_unref(self)?;
+        let ctx = self.clone();
+        self.spawn_exit_simple(async move {
+            let result = tokio::select! {
+                result = future => result,
+                _ = shutdown.wait_for(|stop| *stop) => Ok(()),
+            };
+            crate::event_loop::untrack_unref(&ctx);
+            result
+        });
+        Ok(())
+    }
+
     /// Get a background task poller handle
     fn get_background_task_poller(&self) -> tokio::task::JoinHandle<()> {
         let ctx1 = self.clone().as_raw().as_ptr() as usize;
@@ -92,25 +115,49 @@ impl<'js> CtxExtension<'js> for Ctx<'js> {
     }
 }
 
+/// Awaits `future` until the deadline set with `Ctx::set_deadline`, then drops it and
+/// throws an `InternalError`. The interrupt only stops running code, this also ends
+/// code that is stuck waiting on a promise.
+pub async fn until_deadline<'js, F, R>(ctx: &Ctx<'js>, future: F) -> Result<R>
+where
+    F: Future<Output = Result<R>>,
+{
+    match ctx.deadline() {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), future)
+            .await
+            .unwrap_or_else(|_| Err(Exception::throw_internal(ctx, "timed out"))),
+        None => future.await,
+    }
+}
+
 fn handle_spawn_error<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>, stack: Option<String>) {
-    let error_handler = if let Some(handler) = ERROR_HANDLER.get() {
-        handler
-    } else {
-        tracing::error!("Future error: {:?}", err);
-        return;
-    };
-    if let CaughtError::Exception(err) = err {
-        if err.stack().is_none() {
-            if let Some(stack) = stack {
-                err.set(PredefinedAtom::Stack, stack).unwrap();
+    let err = match err {
+        CaughtError::Exception(err) => {
+            if err.stack().is_none() {
+                if let Some(stack) = stack {
+                    err.set(PredefinedAtom::Stack, stack).unwrap();
+                }
             }
+            CaughtError::Exception(err)
         }
-        error_handler(ctx, CaughtError::Exception(err));
-    } else {
-        error_handler(ctx, err);
+        err => err,
+    };
+    match ERROR_HANDLER.get() {
+        Some(error_handler) => error_handler(ctx, err),
+        None => report_uncaught_error(ctx, err),
     }
 }
 
+#[cfg(feature = "event")]
+pub(crate) fn report_uncaught_error<'js>(ctx: &Ctx<'js>, err: CaughtError<'js>) {
+    crate::process::handle_uncaught_exception(ctx, err);
+}
+
+#[cfg(not(feature = "event"))]
+pub(crate) fn report_uncaught_error<'js>(_ctx: &Ctx<'js>, err: CaughtError<'js>) {
+    tracing::error!("Future error: {:?}", err);
+}
+
 pub fn set_spawn_error_handler<F>(handler: F)
 where
     F: for<'js> Fn(&Ctx<'js>, CaughtError<'js>) + Sync + Send + 'static,
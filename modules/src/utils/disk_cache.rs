@@ -0,0 +1,108 @@
+//! Shared pieces of the caches kept under `~/.xmas`, whose entries are named by a hash of
+//! everything they were derived from.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ring::{
+    digest::{Context, SHA256},
+    hmac,
+};
+
+use super::encoding::bytes_to_hex_string;
+
+/// `~/.xmas/<name>`, the directory of a cache.
+pub fn dir(name: &str) -> PathBuf {
+    home::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".xmas")
+        .join(name)
+}
+
+/// A secret of this build, generated by `build.rs`, so an entry signed with it was
+/// written by this very binary.
+const BUILD_KEY: &str = env!("XMAS_BUILD_KEY");
+
+/// The name of the entry derived from `parts`. The build is part of every key, so
+/// another build never reads what this one wrote.
+pub fn key(parts: &[&[u8]]) -> String {
+    let mut hash = Context::new(&SHA256);
+    for part in [BUILD_KEY.as_bytes()].iter().chain(parts) {
+        hash.update(&part.len().to_le_bytes());
+        hash.update(part);
+    }
+    bytes_to_hex_string(hash.finish().as_ref())
+}
+
+/// Writes an entry through a temporary file renamed into place, so readers never see
+/// half of one.
+pub fn write_entry(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(uuid::Uuid::new_v4().to_string());
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Writes an entry like [`write_entry`], prefixed with an HMAC of its name and contents
+/// under the key of this build, for [`read_signed_entry`].
+pub fn write_signed_entry(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut entry = sign(path, bytes).as_ref().to_vec();
+    entry.extend_from_slice(bytes);
+    write_entry(path, &entry)
+}
+
+/// The contents of an entry written by [`write_signed_entry`] of this build, `None` when
+/// it is missing, truncated, edited, or was written by another build.
+pub fn read_signed_entry(path: &Path) -> Option<Vec<u8>> {
+    let mut bytes = fs::read(path).ok()?;
+    let tag_len = hmac::HMAC_SHA256.digest_algorithm().output_len();
+    if bytes.len() < tag_len {
+        return None;
+    }
+    let contents = bytes.split_off(tag_len);
+    (sign(path, &contents).as_ref() == bytes.as_slice()).then_some(contents)
+}
+
+fn sign(path: &Path, bytes: &[u8]) -> hmac::Tag {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, BUILD_KEY.as_bytes());
+    let mut context = hmac::Context::with_key(&key);
+    // an entry moved under another name doesn't verify
+    let name = path.file_name().unwrap_or_default().as_encoded_bytes();
+    context.update(&name.len().to_le_bytes());
+    context.update(name);
+    context.update(bytes);
+    context.sign()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_entry() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let path = dir.join(key(&[b"entry"]));
+        write_signed_entry(&path, b"bytecode").unwrap();
+        assert_eq!(read_signed_entry(&path).as_deref(), Some(&b"bytecode"[..]));
+
+        // an edited entry, or one under another name, is rejected
+        let mut edited = fs::read(&path).unwrap();
+        *edited.last_mut().unwrap() ^= 1;
+        fs::write(&path, &edited).unwrap();
+        assert_eq!(read_signed_entry(&path), None);
+        write_signed_entry(&path, b"bytecode").unwrap();
+        let moved = dir.join(key(&[b"other"]));
+        fs::rename(&path, &moved).unwrap();
+        assert_eq!(read_signed_entry(&moved), None);
+        fs::write(&path, b"short").unwrap();
+        assert_eq!(read_signed_entry(&path), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
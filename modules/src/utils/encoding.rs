@@ -6,6 +6,7 @@ use hex_simd::AsciiCase;
 pub enum Encoder {
     Hex,
     Base64,
+    Base64Url,
     Windows1252,
     Utf8,
     Utf16le,
@@ -15,6 +16,7 @@ pub enum Encoder {
 const ENCODING_MAP: phf::Map<&'static str, Encoder> = phf::phf_map! {
     "hex" => Encoder::Hex,
     "base64" => Encoder::Base64,
+    "base64url" => Encoder::Base64Url,
     "unicode-1-1-utf-8" => Encoder::Utf8,
     "unicode11utf8" => Encoder::Utf8,
     "unicode20utf8" => Encoder::Utf8,
@@ -34,6 +36,7 @@ const ENCODING_MAP: phf::Map<&'static str, Encoder> = phf::phf_map! {
     "utf-16be" => Encoder::Utf16be,
     "ansi_x3.4-1968" => Encoder::Windows1252,
     "ascii" => Encoder::Windows1252,
+    "binary" => Encoder::Windows1252,
     "cp1252" => Encoder::Windows1252,
     "cp819" => Encoder::Windows1252,
     "csisolatin1" => Encoder::Windows1252,
@@ -71,7 +74,9 @@ impl Encoder {
         match self {
             Self::Hex => Ok(bytes_to_hex_string(bytes)),
             Self::Base64 => Ok(bytes_to_b64_string(bytes)),
-            Self::Utf8 | Self::Windows1252 => bytes_to_utf8_string(bytes, lossy),
+            Self::Base64Url => Ok(bytes_to_b64_url_safe_string(bytes)),
+            Self::Windows1252 => Ok(bytes_to_latin1_string(bytes)),
+            Self::Utf8 => bytes_to_utf8_string(bytes, lossy),
             Self::Utf16le => bytes_to_utf16_string(bytes, Endian::Little, lossy),
             Self::Utf16be => bytes_to_utf16_string(bytes, Endian::Big, lossy),
         }
@@ -82,6 +87,7 @@ impl Encoder {
         match self {
             Self::Hex => Ok(bytes_to_hex(bytes)),
             Self::Base64 => Ok(bytes_to_b64(bytes)),
+            Self::Base64Url => Ok(base64_simd::URL_SAFE_NO_PAD.encode_type(bytes)),
             Self::Utf8 | Self::Windows1252 | Self::Utf16le | Self::Utf16be => Ok(bytes.to_vec()),
         }
     }
@@ -89,7 +95,7 @@ impl Encoder {
     pub fn decode<'a, T: Into<Cow<'a, [u8]>>>(&self, bytes: T) -> Result<Vec<u8>, String> {
         match self {
             Self::Hex => bytes_from_hex(bytes),
-            Self::Base64 => bytes_from_b64(bytes),
+            Self::Base64 | Self::Base64Url => bytes_from_b64(bytes),
             Self::Utf8 | Self::Windows1252 | Self::Utf16le | Self::Utf16be => {
                 Ok(bytes.into().into())
             }
@@ -99,8 +105,10 @@ impl Encoder {
     pub fn decode_from_string(&self, string: String) -> Result<Vec<u8>, String> {
         match self {
             Self::Hex => bytes_from_hex(string.into_bytes()),
-            Self::Base64 => bytes_from_b64(string.into_bytes()),
-            Self::Utf8 | Self::Windows1252 => Ok(string.into_bytes()),
+            Self::Base64 | Self::Base64Url => bytes_from_b64(string.into_bytes()),
+            Self::Utf8 => Ok(string.into_bytes()),
+            // code points above U+00FF keep their low byte, like in node
+            Self::Windows1252 => Ok(string.chars().map(|char| char as u8).collect()),
             Self::Utf16le => Ok(string
                 .encode_utf16()
                 .flat_map(|utf16| utf16.to_le_bytes())
@@ -116,6 +124,7 @@ impl Encoder {
         match self {
             Self::Hex => "hex",
             Self::Base64 => "base64",
+            Self::Base64Url => "base64url",
             Self::Windows1252 => "windows-1252",
             Self::Utf8 => "utf-8",
             Self::Utf16le => "utf-16le",
@@ -124,6 +133,112 @@ impl Encoder {
     }
 }
 
+/// Incremental decoder used by `TextDecoder` in streaming mode and `TextDecoderStream`.
+/// Incomplete trailing sequences are held back until the next chunk completes them.
+#[derive(Clone)]
+pub struct StreamDecoder {
+    encoder: Encoder,
+    lossy: bool,
+    ignore_bom: bool,
+    bom_seen: bool,
+    pending: Vec<u8>,
+}
+
+impl StreamDecoder {
+    pub fn new(encoder: Encoder, lossy: bool, ignore_bom: bool) -> Self {
+        Self {
+            encoder,
+            lossy,
+            ignore_bom,
+            bom_seen: false,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    /// Decodes `bytes` after any pending input. When `stream` is false the decoder is flushed
+    /// and reset, so the next call starts a new stream.
+    pub fn decode(&mut self, bytes: &[u8], stream: bool) -> Result<String, String> {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+        if stream {
+            let complete = input.len() - self.incomplete_suffix_len(&input);
+            self.pending = input.split_off(complete);
+        }
+
+        let result = self.encoder.encode_to_string(&input, self.lossy);
+        let mut output = match result {
+            Ok(output) => output,
+            Err(err) => {
+                self.reset();
+                return Err(err);
+            }
+        };
+        if !self.bom_seen && !output.is_empty() {
+            self.bom_seen = true;
+            if !self.ignore_bom && output.starts_with('\u{FEFF}') {
+                output.remove(0);
+            }
+        }
+        if !stream {
+            self.reset();
+        }
+        Ok(output)
+    }
+
+    fn reset(&mut self) {
+        self.bom_seen = false;
+        self.pending.clear();
+    }
+
+    fn incomplete_suffix_len(&self, bytes: &[u8]) -> usize {
+        match self.encoder {
+            Encoder::Utf8 => incomplete_utf8_suffix_len(bytes),
+            Encoder::Utf16le => incomplete_utf16_suffix_len(bytes, Endian::Little),
+            Encoder::Utf16be => incomplete_utf16_suffix_len(bytes, Endian::Big),
+            Encoder::Hex | Encoder::Base64 | Encoder::Base64Url | Encoder::Windows1252 => 0,
+        }
+    }
+}
+
+fn incomplete_utf8_suffix_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        // skip continuation bytes until the lead byte of the last sequence
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let needed = match byte {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
+        return if needed > back { back } else { 0 };
+    }
+    0
+}
+
+fn incomplete_utf16_suffix_len(bytes: &[u8], endian: Endian) -> usize {
+    let odd = bytes.len() % 2;
+    let even = bytes.len() - odd;
+    if even >= 2 {
+        let pair = [bytes[even - 2], bytes[even - 1]];
+        let unit = match endian {
+            Endian::Little => u16::from_le_bytes(pair),
+            Endian::Big => u16::from_be_bytes(pair),
+        };
+        // a trailing high surrogate needs the next chunk's low surrogate
+        if (0xD800..0xDC00).contains(&unit) {
+            return odd + 2;
+        }
+    }
+    odd
+}
+
 pub fn bytes_to_hex(bytes: &[u8]) -> Vec<u8> {
     hex_simd::encode_type(bytes, AsciiCase::Lower)
 }
@@ -184,6 +299,10 @@ pub fn bytes_to_utf8_string(bytes: &[u8], lossy: bool) -> Result<String, String>
     }
 }
 
+pub fn bytes_to_latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
 #[derive(Clone, Copy)]
 pub enum Endian {
     Little,
@@ -211,3 +330,61 @@ pub fn bytes_to_utf16_string(bytes: &[u8], endian: Endian, lossy: bool) -> Resul
         String::from_utf16(&data16).map_err(|e| e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_decoder_split_sequences() {
+        let mut decoder = StreamDecoder::new(Encoder::Utf8, false, false);
+        // BOM followed by "é€" with every sequence split across chunks
+        let bytes = [0xEF, 0xBB, 0xBF, 0xC3, 0xA9, 0xE2, 0x82, 0xAC];
+        let mut output = String::new();
+        for chunk in bytes.chunks(2) {
+            output.push_str(&decoder.decode(chunk, true).unwrap());
+        }
+        output.push_str(&decoder.decode(&[], false).unwrap());
+        assert_eq!(output, "é€");
+
+        let mut decoder = StreamDecoder::new(Encoder::Utf16le, false, false);
+        let bytes: Vec<u8> = "a😀"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        let mut output = String::new();
+        for chunk in bytes.chunks(3) {
+            output.push_str(&decoder.decode(chunk, true).unwrap());
+        }
+        assert_eq!(output, "a😀");
+
+        let mut decoder = StreamDecoder::new(Encoder::Utf8, false, false);
+        assert_eq!(decoder.decode(&[0x61, 0xE2, 0x82], true).unwrap(), "a");
+        assert!(decoder.decode(&[], false).is_err());
+    }
+
+    #[test]
+    fn test_base64url_and_latin1() {
+        let encoder = Encoder::from_str("base64url").unwrap();
+        assert_eq!(
+            encoder.encode_to_string(&[0xfb, 0xff], false).unwrap(),
+            "-_8"
+        );
+        assert_eq!(
+            encoder.decode_from_string("-_8".into()).unwrap(),
+            [0xfb, 0xff]
+        );
+
+        let encoder = Encoder::from_str("binary").unwrap();
+        assert_eq!(
+            encoder
+                .encode_to_string(&[0x61, 0xe9, 0xff], false)
+                .unwrap(),
+            "aéÿ"
+        );
+        assert_eq!(
+            encoder.decode_from_string("aéÿ".into()).unwrap(),
+            [0x61, 0xe9, 0xff]
+        );
+    }
+}
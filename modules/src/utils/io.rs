@@ -24,4 +24,13 @@ macro_rules! define_supported_extensions {
     };
 }
 
-define_supported_extensions!(BYTECODE_FILE_EXT, ".js", ".mjs", ".cjs");
+define_supported_extensions!(
+    BYTECODE_FILE_EXT,
+    ".js",
+    ".mjs",
+    ".cjs",
+    ".ts",
+    ".mts",
+    ".tsx",
+    ".jsx"
+);
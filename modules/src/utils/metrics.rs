@@ -0,0 +1,68 @@
+//! Periodic export of the runtime metrics through `tracing`.
+use std::time::Duration;
+
+use rsquickjs::AsyncRuntime;
+use tokio::{
+    task::JoinHandle,
+    time::{interval, MissedTickBehavior},
+};
+use tracing::info;
+
+/// The `tracing` target the metrics are reported under.
+pub const METRICS_TARGET: &str = "xmas::metrics";
+
+/// Reports the metrics and heap size of `runtime` every `period` until it is dropped.
+///
+/// The event loop lag is how long the report waited for the runtime after its tick, that
+/// is the time JS kept the runtime busy without yielding.
+pub fn spawn_metrics_reporter(runtime: &AsyncRuntime, period: Duration) -> JoinHandle<()> {
+    let runtime = runtime.weak();
+    tokio::spawn(async move {
+        let mut ticks = interval(period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // the first tick completes right away
+        ticks.tick().await;
+        loop {
+            let tick = ticks.tick().await;
+            let Some(runtime) = runtime.try_ref() else {
+                break;
+            };
+            let metrics = runtime.metrics().await;
+            let lag = tick.elapsed();
+            let heap = runtime.memory_stats().await.malloc_size;
+            info!(
+                target: METRICS_TARGET,
+                lag_ms = lag.as_secs_f64() * 1000.0,
+                job_pending = metrics.job_pending,
+                jobs_executed = metrics.jobs_executed,
+                background_tasks = metrics.background_tasks,
+                gc_runs = metrics.gc_runs,
+                evals = metrics.evals,
+                heap_bytes = heap,
+                "runtime metrics"
+            );
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::utils::test::given_runtime;
+
+    #[tokio::test]
+    async fn test_reporter_stops_with_runtime() {
+        let (rt, ctx) = given_runtime().await;
+        let reporter = super::spawn_metrics_reporter(&rt, Duration::from_millis(5));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!reporter.is_finished());
+
+        drop(ctx);
+        drop(rt);
+        tokio::time::timeout(Duration::from_secs(1), reporter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
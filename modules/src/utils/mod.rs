@@ -4,6 +4,7 @@ pub mod class;
 pub mod compression;
 pub mod console;
 pub mod ctx;
+pub mod disk_cache;
 pub mod encoding;
 pub mod error;
 pub mod fs;
@@ -11,6 +12,7 @@ pub mod hash;
 pub mod io;
 pub mod json;
 pub mod mc_oneshot;
+pub mod metrics;
 pub mod module;
 pub mod numbers;
 pub mod object;
@@ -18,6 +20,7 @@ pub mod options;
 pub mod primordials;
 pub mod provider;
 pub mod result;
+pub mod snapshot;
 pub mod test;
 pub mod time;
 
@@ -0,0 +1,77 @@
+//! Startup snapshot of the scripts modules run when a context is initialized.
+//!
+//! QuickJS can't write out a context, most of what `init` sets up being native functions,
+//! but the scripts run along with them only need compiling once. The first context of a
+//! process compiles each to bytecode, kept for the contexts that follow and in
+//! `~/.xmas/snapshot` for the processes that follow, so they start without parsing any.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rsquickjs::{Ctx, FromJs, Result, WriteOptions};
+use tracing::info;
+
+use super::disk_cache::{self, read_signed_entry, write_signed_entry};
+use crate::module::package::bytecode;
+
+static COMPILED: Mutex<HashMap<&'static str, Arc<[u8]>>> = Mutex::new(HashMap::new());
+
+/// Runs the script `name` of a module, from its bytecode once compiled.
+pub fn eval<'js, V: FromJs<'js>>(ctx: &Ctx<'js>, name: &'static str, source: &str) -> Result<V> {
+    let compiled = COMPILED.lock().unwrap().get(name).cloned();
+    let bytes = match compiled {
+        Some(bytes) => bytes,
+        None => {
+            let bytes: Arc<[u8]> = compile(ctx, name, source)?.into();
+            COMPILED.lock().unwrap().insert(name, bytes.clone());
+            bytes
+        }
+    };
+    // SAFETY: the bytes were compiled by `compile`, in this process or in another one
+    // running this very binary, whose signature on the cached entry was verified
+    unsafe { ctx.eval_compiled(&bytes) }
+}
+
+/// The bytecode of `source`, from `~/.xmas/snapshot` when bytecode is cached.
+fn compile(ctx: &Ctx<'_>, name: &str, source: &str) -> Result<Vec<u8>> {
+    let cached = bytecode::is_enabled();
+    let path =
+        disk_cache::dir("snapshot").join(disk_cache::key(&[name.as_bytes(), source.as_bytes()]));
+    if cached {
+        if let Some(bytes) = read_signed_entry(&path) {
+            return Ok(bytes);
+        }
+    }
+
+    let bytes = ctx.compile(source, Default::default(), WriteOptions::default())?;
+    if cached {
+        if let Err(err) = write_signed_entry(&path, &bytes) {
+            info!("Failed to cache bytecode of {}: {}", name, err);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rsquickjs::Object;
+
+    use super::*;
+    use crate::utils::test::test_sync_with;
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        const SOURCE: &str = "({ answer: 40 + 2 })";
+        for _ in 0..2 {
+            test_sync_with(|ctx| {
+                let object: Object = eval(&ctx, "test_snapshot", SOURCE)?;
+                assert_eq!(object.get::<_, i32>("answer")?, 42);
+                assert!(COMPILED.lock().unwrap().contains_key("test_snapshot"));
+                Ok(())
+            })
+            .await;
+        }
+    }
+}
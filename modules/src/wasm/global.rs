@@ -0,0 +1,75 @@
+use rsquickjs::{atom::PredefinedAtom, function::Opt, Ctx, Exception, Object, Result, Value};
+use wasmi::{Mutability, Val};
+
+use super::{from_wasm, parse_val_type, to_wasm, wasm_state};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+/// A [`WebAssembly.Global`](https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/JavaScript_interface/Global).
+#[derive(Clone, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "Global")]
+pub struct WasmGlobal {
+    #[qjs(skip_trace)]
+    pub global: wasmi::Global,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl WasmGlobal {
+    #[qjs(constructor)]
+    pub fn new<'js>(
+        ctx: Ctx<'js>,
+        descriptor: Object<'js>,
+        value: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let state = wasm_state(&ctx)?;
+
+        let ty: String = descriptor
+            .get_optional("value")?
+            .or_throw_type(&ctx, "Global descriptor requires 'value'")?;
+        let ty = parse_val_type(&ctx, &ty)?;
+        let mutability = match descriptor.get_optional::<_, bool>("mutable")? {
+            Some(true) => Mutability::Var,
+            _ => Mutability::Const,
+        };
+
+        let value = match value.0.filter(|value| !value.is_undefined()) {
+            Some(value) => to_wasm(&ctx, &state, value, ty)?,
+            None => Val::default(ty),
+        };
+        let global = state.with_store(&ctx, |mut store| {
+            wasmi::Global::new(&mut store, value, mutability)
+        })?;
+        Ok(Self { global })
+    }
+
+    #[qjs(get)]
+    pub fn value<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let state = wasm_state(&ctx)?;
+        let value = state.with_store(&ctx, |store| self.global.get(&store))?;
+        from_wasm(&ctx, &state, &value)
+    }
+
+    #[qjs(set, rename = "value")]
+    pub fn set_value<'js>(&self, ctx: Ctx<'js>, value: Value<'js>) -> Result<()> {
+        let state = wasm_state(&ctx)?;
+        let ty = state.with_store(&ctx, |store| self.global.ty(&store))?;
+        if ty.mutability() == Mutability::Const {
+            return Err(Exception::throw_type(
+                &ctx,
+                "Can't set the value of an immutable global",
+            ));
+        }
+        let value = to_wasm(&ctx, &state, value, ty.content())?;
+        state
+            .with_store(&ctx, |mut store| self.global.set(&mut store, value))?
+            .or_throw_type(&ctx, "")
+    }
+
+    pub fn value_of<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.value(ctx)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "WebAssembly.Global"
+    }
+}
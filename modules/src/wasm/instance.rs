@@ -0,0 +1,149 @@
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, function::Opt, Class, Ctx, Exception, Function, Object,
+    Result, Value,
+};
+use wasmi::{Extern, ExternType, Global, Linker, Mutability};
+
+use super::{
+    global::WasmGlobal, memory::WasmMemory, module::WasmModule, table::WasmTable, throw_error,
+    to_wasm, wasm_state, wrap_func, HostState, WasmErrorKind,
+};
+use crate::utils::{
+    primordials::{BasePrimordials, Primordial},
+    result::ResultExt,
+};
+
+/// An instantiated [`WebAssembly.Instance`](https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/JavaScript_interface/Instance).
+#[derive(Clone, Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "Instance")]
+pub struct WasmInstance<'js> {
+    exports: Object<'js>,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl<'js> WasmInstance<'js> {
+    #[qjs(constructor)]
+    pub fn new(
+        ctx: Ctx<'js>,
+        module: Class<'js, WasmModule>,
+        imports: Opt<Object<'js>>,
+    ) -> Result<Self> {
+        instantiate(&ctx, &module.borrow().module, imports.0)
+    }
+
+    #[qjs(get)]
+    pub fn exports(&self) -> Object<'js> {
+        self.exports.clone()
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "WebAssembly.Instance"
+    }
+}
+
+/// Links `module` against the JavaScript `imports` object and runs its start function.
+pub fn instantiate<'js>(
+    ctx: &Ctx<'js>,
+    module: &wasmi::Module,
+    imports: Option<Object<'js>>,
+) -> Result<WasmInstance<'js>> {
+    let state = wasm_state(ctx)?;
+    let mut linker = Linker::<HostState>::new(state.engine());
+
+    for import in module.imports() {
+        let Some(imports) = &imports else {
+            return Err(Exception::throw_type(
+                ctx,
+                "Imports argument must be present and must be an object",
+            ));
+        };
+        let namespace: Value = imports.get(import.module())?;
+        let namespace = namespace.as_object().or_throw_type(
+            ctx,
+            &["Import module '", import.module(), "' must be an object"].concat(),
+        )?;
+        let value: Value = namespace.get(import.name())?;
+        let link_error = |message: &str| {
+            throw_error(
+                ctx,
+                WasmErrorKind::Link,
+                [
+                    "Import '",
+                    import.module(),
+                    ".",
+                    import.name(),
+                    "': ",
+                    message,
+                ]
+                .concat(),
+            )
+        };
+
+        let item = match import.ty() {
+            ExternType::Func(ty) => {
+                let function = value
+                    .as_function()
+                    .cloned()
+                    .ok_or_else(|| link_error("function import requires a callable"))?;
+                Extern::Func(state.host_func(ctx, function, ty.clone())?)
+            }
+            ExternType::Memory(_) => Class::<WasmMemory>::from_value(&value)
+                .map(|memory| Extern::Memory(memory.borrow().memory))
+                .map_err(|_| link_error("memory import must be a WebAssembly.Memory object"))?,
+            ExternType::Table(_) => Class::<WasmTable>::from_value(&value)
+                .map(|table| Extern::Table(table.borrow().table))
+                .map_err(|_| link_error("table import must be a WebAssembly.Table object"))?,
+            ExternType::Global(ty) => match Class::<WasmGlobal>::from_value(&value) {
+                Ok(global) => Extern::Global(global.borrow().global),
+                Err(_) if ty.mutability() == Mutability::Var => {
+                    return Err(link_error(
+                        "imported mutable global must be a WebAssembly.Global object",
+                    ))
+                }
+                Err(_) => {
+                    let value = to_wasm(ctx, &state, value, ty.content())?;
+                    Extern::Global(state.with_store(ctx, |mut store| {
+                        Global::new(&mut store, value, Mutability::Const)
+                    })?)
+                }
+            },
+        };
+
+        linker
+            .define(import.module(), import.name(), item)
+            .map_err(|err| link_error(&err.to_string()))?;
+    }
+
+    let pre = state
+        .with_store(ctx, |mut store| linker.instantiate(&mut store, module))?
+        .map_err(|err| throw_error(ctx, WasmErrorKind::Link, err))?;
+    let instance = state.with_store(ctx, |mut store| pre.start(&mut store))?;
+    state.sync_memory_buffers(ctx)?;
+    let instance = instance.map_err(|err| state.call_error(ctx, err))?;
+
+    let exports = Object::new(ctx.clone())?;
+    for export in module.exports() {
+        let name = export.name();
+        let item = state
+            .with_store(ctx, |store| instance.get_export(&store, name))?
+            .or_throw_msg(ctx, &["Missing export '", name, "'"].concat())?;
+        let value = match item {
+            Extern::Func(func) => wrap_func(ctx, &state, func)?.into_value(),
+            Extern::Memory(memory) => {
+                Class::instance(ctx.clone(), WasmMemory::from_memory(&state, memory))?.into_value()
+            }
+            Extern::Table(table) => Class::instance(ctx.clone(), WasmTable { table })?.into_value(),
+            Extern::Global(global) => {
+                Class::instance(ctx.clone(), WasmGlobal { global })?.into_value()
+            }
+        };
+        exports.set(name, value)?;
+    }
+
+    let primordials = BasePrimordials::get(ctx)?;
+    let freeze: Function = primordials.constructor_object.get("freeze")?;
+    freeze.call::<_, ()>((exports.clone(),))?;
+
+    Ok(WasmInstance { exports })
+}
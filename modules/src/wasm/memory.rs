@@ -0,0 +1,90 @@
+use rsquickjs::{atom::PredefinedAtom, ArrayBuffer, Ctx, Exception, Object, Result};
+use wasmi::MemoryType;
+
+use super::{get_descriptor_u32, wasm_state, WasmState};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+/// A [`WebAssembly.Memory`](https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/JavaScript_interface/Memory).
+///
+/// `buffer` aliases the wasm memory without copying. It is detached whenever the memory
+/// grows or moves, so stale views can never reach freed data.
+#[derive(Clone, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "Memory")]
+pub struct WasmMemory {
+    #[qjs(skip_trace)]
+    pub memory: wasmi::Memory,
+    view: usize,
+}
+
+impl WasmMemory {
+    pub fn from_memory(state: &WasmState, memory: wasmi::Memory) -> Self {
+        Self {
+            memory,
+            view: state.add_memory(memory),
+        }
+    }
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl WasmMemory {
+    #[qjs(constructor)]
+    pub fn new(ctx: Ctx<'_>, descriptor: Object<'_>) -> Result<Self> {
+        let state = wasm_state(&ctx)?;
+
+        let initial = match get_descriptor_u32(&ctx, &descriptor, "initial")? {
+            Some(initial) => initial,
+            None => get_descriptor_u32(&ctx, &descriptor, "minimum")?
+                .or_throw_type(&ctx, "Memory descriptor requires 'initial'")?,
+        };
+        let maximum = get_descriptor_u32(&ctx, &descriptor, "maximum")?;
+        if maximum.is_some_and(|maximum| maximum < initial) {
+            return Err(Exception::throw_range(
+                &ctx,
+                "'maximum' must not be less than 'initial'",
+            ));
+        }
+        if descriptor.get_optional::<_, bool>("shared")? == Some(true) {
+            return Err(Exception::throw_type(
+                &ctx,
+                "Shared memories are not supported",
+            ));
+        }
+
+        let ty = MemoryType::new(initial, maximum).or_throw_range(&ctx, "")?;
+        let memory = state
+            .with_store(&ctx, |mut store| wasmi::Memory::new(&mut store, ty))?
+            .or_throw_range(&ctx, "")?;
+        Ok(Self::from_memory(&state, memory))
+    }
+
+    #[qjs(get)]
+    pub fn buffer<'js>(&self, ctx: Ctx<'js>) -> Result<ArrayBuffer<'js>> {
+        wasm_state(&ctx)?.memory_buffer(&ctx, self.view)
+    }
+
+    pub fn grow(&self, ctx: Ctx<'_>, delta: f64) -> Result<u32> {
+        let state = wasm_state(&ctx)?;
+        if !delta.is_finite() || delta < 0.0 || delta > u32::MAX as f64 {
+            return Err(Exception::throw_type(
+                &ctx,
+                "'delta' must be a 32-bit unsigned integer",
+            ));
+        }
+
+        let previous = state
+            .with_store(&ctx, |mut store| self.memory.grow(&mut store, delta as u32))?
+            .or_throw_range(
+                &ctx,
+                "WebAssembly.Memory.grow(): Maximum memory size exceeded",
+            )?;
+        // Growing always detaches the old buffer, even by zero pages
+        state.detach_memory_buffer(&ctx, self.view)?;
+        state.sync_memory_buffers(&ctx)?;
+        Ok(previous)
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "WebAssembly.Memory"
+    }
+}
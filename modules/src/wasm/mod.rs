@@ -0,0 +1,708 @@
+//! The [`WebAssembly`](https://webassembly.github.io/spec/js-api/) namespace, backed by the
+//! [wasmi](https://github.com/wasmi-labs/wasmi) interpreter.
+//!
+//! Every context owns one engine and one store shared by all of its modules. While wasm
+//! code calls into a JavaScript import the store is borrowed by the running call, so the
+//! host function's [`Caller`] is handed to JavaScript instead, letting imports read
+//! memories and call exports re-entrantly.
+use std::{cell::RefCell, fmt::Display, ptr::NonNull, rc::Rc};
+
+use rsquickjs::{
+    atom::PredefinedAtom,
+    function::{Constructor, Func},
+    prelude::{Async, IntoJs, Opt, Rest, This},
+    qjs, Array, ArrayBuffer, CatchResultExt, Class, Coerced, Ctx, Exception, Function, JsLifetime,
+    Object, Persistent, Promise, Result, Symbol, Value,
+};
+use wasmi::{
+    core::{ValType, F32, F64},
+    AsContext, AsContextMut, Caller, Engine, ExternRef, FuncRef, FuncType, Store, StoreContextMut,
+    Val,
+};
+
+use crate::utils::{
+    bytes::ObjectBytes, error::ErrorExtensions, object::ObjectExt, result::ResultExt,
+};
+
+use self::{
+    global::WasmGlobal,
+    instance::{instantiate, WasmInstance},
+    memory::WasmMemory,
+    module::WasmModule,
+    table::WasmTable,
+};
+
+pub mod global;
+pub mod instance;
+pub mod memory;
+pub mod module;
+pub mod table;
+
+const ERRORS_SOURCE: &str = r#"
+(() => {
+    const define = (name) => {
+        const WasmError = class extends Error {};
+        Object.defineProperty(WasmError, "name", { value: name });
+        Object.defineProperty(WasmError.prototype, "name", {
+            value: name,
+            writable: true,
+            configurable: true,
+        });
+        return WasmError;
+    };
+    return {
+        CompileError: define("CompileError"),
+        LinkError: define("LinkError"),
+        RuntimeError: define("RuntimeError"),
+    };
+})()
+"#;
+
+#[derive(Debug, Clone, Copy)]
+pub enum WasmErrorKind {
+    Compile,
+    Link,
+    Runtime,
+}
+
+impl WasmErrorKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Compile => "CompileError",
+            Self::Link => "LinkError",
+            Self::Runtime => "RuntimeError",
+        }
+    }
+}
+
+/// Store data, giving host functions a way back to their context.
+pub struct HostState {
+    ctx: NonNull<qjs::JSContext>,
+}
+
+unsafe impl Send for HostState {}
+
+struct MemoryView {
+    memory: wasmi::Memory,
+    /// The `ArrayBuffer` aliasing the memory, with the data pointer and length it was created for.
+    buffer: Option<(Persistent<ArrayBuffer<'static>>, usize, usize)>,
+}
+
+pub struct WasmState {
+    engine: Engine,
+    store: RefCell<Store<HostState>>,
+    /// Callers of the host functions currently running, innermost last.
+    callers: RefCell<Vec<NonNull<Caller<'static, HostState>>>>,
+    imports: RefCell<Vec<Persistent<Function<'static>>>>,
+    funcs: RefCell<Vec<wasmi::Func>>,
+    // Values passed as `externref` stay alive for the lifetime of the store
+    externs: RefCell<Vec<Persistent<Value<'static>>>>,
+    memories: RefCell<Vec<MemoryView>>,
+    /// A JavaScript exception thrown by an import, rethrown once the wasm call unwinds.
+    exception: RefCell<Option<Persistent<Value<'static>>>>,
+    errors: Persistent<Object<'static>>,
+    func_key: Persistent<Symbol<'static>>,
+}
+
+#[derive(JsLifetime)]
+struct WasmContext(Rc<WasmState>);
+
+pub fn wasm_state(ctx: &Ctx<'_>) -> Result<Rc<WasmState>> {
+    let state = ctx
+        .userdata::<WasmContext>()
+        .or_throw_msg(ctx, "WebAssembly is not initialized")?;
+    Ok(state.0.clone())
+}
+
+pub fn throw_error(ctx: &Ctx<'_>, kind: WasmErrorKind, message: impl Display) -> rsquickjs::Error {
+    let error = wasm_state(ctx).and_then(|state| {
+        let errors = state.errors.clone().restore(ctx)?;
+        let constructor: Constructor = errors.get(kind.name())?;
+        constructor.construct::<_, Value>((message.to_string(),))
+    });
+    match error {
+        Ok(error) => ctx.throw(error),
+        Err(err) => err,
+    }
+}
+
+impl WasmState {
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Runs `f` against the store, or against the innermost running host call's caller.
+    pub fn with_store<R>(
+        &self,
+        ctx: &Ctx<'_>,
+        f: impl FnOnce(StoreContextMut<'_, HostState>) -> R,
+    ) -> Result<R> {
+        let caller = self.callers.borrow().last().copied();
+        if let Some(mut caller) = caller {
+            // SAFETY: a caller is only on the stack while its host function is running
+            let caller = unsafe { caller.as_mut() };
+            return Ok(f(caller.as_context_mut()));
+        }
+        let mut store = self
+            .store
+            .try_borrow_mut()
+            .or_throw_msg(ctx, "WebAssembly store is busy")?;
+        Ok(f(store.as_context_mut()))
+    }
+
+    /// Turns an error returned by wasm execution into the exception to throw.
+    pub fn call_error(&self, ctx: &Ctx<'_>, err: wasmi::Error) -> rsquickjs::Error {
+        let exception = self.exception.borrow_mut().take();
+        match exception.map(|exception| exception.restore(ctx)) {
+            Some(Ok(exception)) => ctx.throw(exception),
+            Some(Err(err)) => err,
+            None => throw_error(ctx, WasmErrorKind::Runtime, err),
+        }
+    }
+
+    pub fn add_memory(&self, memory: wasmi::Memory) -> usize {
+        let mut memories = self.memories.borrow_mut();
+        memories.push(MemoryView {
+            memory,
+            buffer: None,
+        });
+        memories.len() - 1
+    }
+
+    /// Returns the `ArrayBuffer` over a memory's current data, creating it when needed.
+    pub fn memory_buffer<'js>(&self, ctx: &Ctx<'js>, view: usize) -> Result<ArrayBuffer<'js>> {
+        self.sync_memory_buffers(ctx)?;
+        if let Some((buffer, _, _)) = &self.memories.borrow()[view].buffer {
+            return buffer.clone().restore(ctx);
+        }
+
+        let memory = self.memories.borrow()[view].memory;
+        let (ptr, len) = self.with_store(ctx, |mut store| {
+            let data = memory.data_mut(&mut store);
+            (data.as_mut_ptr(), data.len())
+        })?;
+
+        // SAFETY: the buffer does not own the data and is detached as soon as the memory
+        // moves or grows, see `sync_memory_buffers`
+        let buffer = unsafe {
+            let buffer = qjs::JS_NewArrayBuffer(
+                ctx.as_raw().as_ptr(),
+                ptr,
+                len as _,
+                None,
+                std::ptr::null_mut(),
+                false,
+            );
+            Value::from_raw(ctx.clone(), buffer)
+        };
+        if buffer.is_exception() {
+            return Err(rsquickjs::Error::Exception);
+        }
+        let buffer = ArrayBuffer::from_value(buffer).or_throw(ctx)?;
+
+        self.memories.borrow_mut()[view].buffer =
+            Some((Persistent::save(ctx, buffer.clone()), ptr as usize, len));
+        Ok(buffer)
+    }
+
+    pub fn detach_memory_buffer(&self, ctx: &Ctx<'_>, view: usize) -> Result<()> {
+        let buffer = self.memories.borrow_mut()[view].buffer.take();
+        if let Some((buffer, _, _)) = buffer {
+            buffer.restore(ctx)?.detach();
+        }
+        Ok(())
+    }
+
+    /// Detaches every memory buffer whose memory was moved or resized by wasm code.
+    pub fn sync_memory_buffers(&self, ctx: &Ctx<'_>) -> Result<()> {
+        let stale = self.with_store(ctx, |store| {
+            let mut stale = Vec::new();
+            for view in self.memories.borrow_mut().iter_mut() {
+                let data = view.memory.data(&store);
+                let moved = matches!(
+                    &view.buffer,
+                    Some((_, ptr, len)) if *ptr != data.as_ptr() as usize || *len != data.len()
+                );
+                if moved {
+                    stale.extend(view.buffer.take().map(|(buffer, _, _)| buffer));
+                }
+            }
+            stale
+        })?;
+        for buffer in stale {
+            buffer.restore(ctx)?.detach();
+        }
+        Ok(())
+    }
+
+    /// Creates a wasm function calling the JavaScript `function`.
+    pub fn host_func<'js>(
+        &self,
+        ctx: &Ctx<'js>,
+        function: Function<'js>,
+        ty: FuncType,
+    ) -> Result<wasmi::Func> {
+        let index = {
+            let mut imports = self.imports.borrow_mut();
+            imports.push(Persistent::save(ctx, function));
+            imports.len() - 1
+        };
+        self.with_store(ctx, |mut store| {
+            wasmi::Func::new(
+                &mut store,
+                ty,
+                move |mut caller: Caller<'_, HostState>, params: &[Val], results: &mut [Val]| {
+                    // SAFETY: the store only runs wasm while its context is entered
+                    let ctx = unsafe { Ctx::from_raw(caller.data().ctx) };
+                    call_import(&ctx, &mut caller, index, params, results)
+                },
+            )
+        })
+    }
+}
+
+fn call_import(
+    ctx: &Ctx<'_>,
+    caller: &mut Caller<'_, HostState>,
+    index: usize,
+    params: &[Val],
+    results: &mut [Val],
+) -> std::result::Result<(), wasmi::Error> {
+    let state = wasm_state(ctx).map_err(|err| wasmi::Error::new(err.to_string()))?;
+
+    state
+        .callers
+        .borrow_mut()
+        .push(NonNull::from(caller).cast());
+    let result = invoke_import(ctx, &state, index, params, results).catch(ctx);
+    state.callers.borrow_mut().pop();
+
+    result.map_err(|err| {
+        let message = err.to_string();
+        if let Ok(exception) = err.into_value(ctx) {
+            *state.exception.borrow_mut() = Some(Persistent::save(ctx, exception));
+        }
+        wasmi::Error::new(message)
+    })
+}
+
+fn invoke_import<'js>(
+    ctx: &Ctx<'js>,
+    state: &WasmState,
+    index: usize,
+    params: &[Val],
+    results: &mut [Val],
+) -> Result<()> {
+    // The memory may have grown since wasm code was entered
+    state.sync_memory_buffers(ctx)?;
+
+    let function = state.imports.borrow()[index].clone().restore(ctx)?;
+    let args = params
+        .iter()
+        .map(|param| from_wasm(ctx, state, param))
+        .collect::<Result<Vec<_>>>()?;
+    let value: Value = function.call((Rest(args),))?;
+
+    match results {
+        [] => {}
+        [result] => *result = to_wasm(ctx, state, value, result.ty())?,
+        results => {
+            let values: Vec<Value> = value
+                .get()
+                .or_throw_type(ctx, "Multi-value results must be an array")?;
+            if values.len() != results.len() {
+                return Err(Exception::throw_type(
+                    ctx,
+                    "Multi-value result length does not match the function signature",
+                ));
+            }
+            for (result, value) in results.iter_mut().zip(values) {
+                *result = to_wasm(ctx, state, value, result.ty())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Wraps an exported wasm function in a JavaScript function.
+pub fn wrap_func<'js>(
+    ctx: &Ctx<'js>,
+    state: &WasmState,
+    func: wasmi::Func,
+) -> Result<Function<'js>> {
+    let ty = state.with_store(ctx, |store| func.ty(&store))?;
+    let index = {
+        let mut funcs = state.funcs.borrow_mut();
+        funcs.push(func);
+        funcs.len() - 1
+    };
+
+    let function = Function::new(
+        ctx.clone(),
+        move |ctx: Ctx<'js>, args: Rest<Value<'js>>| -> Result<Value<'js>> {
+            call_func(&ctx, func, &ty, args.0)
+        },
+    )?;
+    function.set(state.func_key.clone().restore(ctx)?, index)?;
+    Ok(function)
+}
+
+fn call_func<'js>(
+    ctx: &Ctx<'js>,
+    func: wasmi::Func,
+    ty: &FuncType,
+    args: Vec<Value<'js>>,
+) -> Result<Value<'js>> {
+    let state = wasm_state(ctx)?;
+
+    let params = ty
+        .params()
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            let arg = args
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| Value::new_undefined(ctx.clone()));
+            to_wasm(ctx, &state, arg, *ty)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut results: Vec<Val> = ty.results().iter().map(|ty| Val::default(*ty)).collect();
+
+    let outcome = state.with_store(ctx, |mut store| {
+        func.call(&mut store, &params, &mut results)
+    })?;
+    state.sync_memory_buffers(ctx)?;
+    outcome.map_err(|err| state.call_error(ctx, err))?;
+
+    match results.as_slice() {
+        [] => Ok(Value::new_undefined(ctx.clone())),
+        [result] => from_wasm(ctx, &state, result),
+        results => {
+            let array = Array::new(ctx.clone())?;
+            for (i, result) in results.iter().enumerate() {
+                array.set(i, from_wasm(ctx, &state, result)?)?;
+            }
+            Ok(array.into_value())
+        }
+    }
+}
+
+/// Converts a JavaScript value to a wasm value of type `ty`, per `ToWebAssemblyValue`.
+pub fn to_wasm<'js>(
+    ctx: &Ctx<'js>,
+    state: &WasmState,
+    value: Value<'js>,
+    ty: ValType,
+) -> Result<Val> {
+    Ok(match ty {
+        ValType::I32 => Val::I32(value.get::<Coerced<i32>>()?.0),
+        ValType::I64 => match value.as_big_int() {
+            Some(big_int) => Val::I64(big_int.clone().to_i64()?),
+            None => {
+                return Err(Exception::throw_type(
+                    ctx,
+                    "Cannot convert a non BigInt value to an i64",
+                ))
+            }
+        },
+        ValType::F32 => Val::F32(F32::from(value.get::<Coerced<f64>>()?.0 as f32)),
+        ValType::F64 => Val::F64(F64::from(value.get::<Coerced<f64>>()?.0)),
+        ValType::FuncRef => {
+            if value.is_null() {
+                return Ok(Val::FuncRef(FuncRef::null()));
+            }
+            let index: Option<usize> = match value.as_function() {
+                Some(function) => function.get(state.func_key.clone().restore(ctx)?)?,
+                None => None,
+            };
+            let func = index
+                .and_then(|index| state.funcs.borrow().get(index).copied())
+                .or_throw_type(ctx, "Value is not an exported WebAssembly function")?;
+            Val::FuncRef(FuncRef::new(func))
+        }
+        ValType::ExternRef => {
+            if value.is_null() {
+                return Ok(Val::ExternRef(ExternRef::null()));
+            }
+            let index = {
+                let mut externs = state.externs.borrow_mut();
+                externs.push(Persistent::save(ctx, value));
+                externs.len() - 1
+            };
+            Val::ExternRef(state.with_store(ctx, |mut store| ExternRef::new(&mut store, index))?)
+        }
+    })
+}
+
+/// Converts a wasm value to a JavaScript value, per `ToJSValue`.
+pub fn from_wasm<'js>(ctx: &Ctx<'js>, state: &WasmState, value: &Val) -> Result<Value<'js>> {
+    Ok(match value {
+        Val::I32(value) => Value::new_int(ctx.clone(), *value),
+        Val::I64(value) => Value::new_big_int(ctx.clone(), *value),
+        Val::F32(value) => Value::new_float(ctx.clone(), f32::from(*value) as f64),
+        Val::F64(value) => Value::new_float(ctx.clone(), f64::from(*value)),
+        Val::FuncRef(func_ref) => match func_ref.func() {
+            Some(func) => wrap_func(ctx, state, *func)?.into_value(),
+            None => Value::new_null(ctx.clone()),
+        },
+        Val::ExternRef(extern_ref) => {
+            let index = state.with_store(ctx, |store| {
+                extern_ref
+                    .data(store.as_context())
+                    .and_then(|data| data.downcast_ref::<usize>().copied())
+            })?;
+            match index {
+                Some(index) => state.externs.borrow()[index].clone().restore(ctx)?,
+                None => Value::new_null(ctx.clone()),
+            }
+        }
+    })
+}
+
+/// Parses a value type name as used by `WebAssembly.Table` and `WebAssembly.Global` descriptors.
+pub fn parse_val_type(ctx: &Ctx<'_>, name: &str) -> Result<ValType> {
+    Ok(match name {
+        "i32" => ValType::I32,
+        "i64" => ValType::I64,
+        "f32" => ValType::F32,
+        "f64" => ValType::F64,
+        "anyfunc" | "funcref" => ValType::FuncRef,
+        "externref" => ValType::ExternRef,
+        _ => {
+            return Err(Exception::throw_type(
+                ctx,
+                &["Invalid value type '", name, "'"].concat(),
+            ))
+        }
+    })
+}
+
+/// Reads an optional `[EnforceRange] unsigned long` member of a descriptor.
+pub fn get_descriptor_u32(
+    ctx: &Ctx<'_>,
+    descriptor: &Object<'_>,
+    key: &str,
+) -> Result<Option<u32>> {
+    let Some(value) = descriptor.get_optional::<_, Coerced<f64>>(key)? else {
+        return Ok(None);
+    };
+    let value = value.0.trunc();
+    if !value.is_finite() || value < 0.0 || value > u32::MAX as f64 {
+        return Err(Exception::throw_type(
+            ctx,
+            &["'", key, "' must be a 32-bit unsigned integer"].concat(),
+        ));
+    }
+    Ok(Some(value as u32))
+}
+
+fn validate<'js>(ctx: Ctx<'js>, bytes: ObjectBytes<'js>) -> Result<bool> {
+    let state = wasm_state(&ctx)?;
+    Ok(wasmi::Module::validate(state.engine(), bytes.as_bytes(&ctx)?).is_ok())
+}
+
+async fn compile_module<'js>(ctx: &Ctx<'js>, bytes: ObjectBytes<'js>) -> Result<WasmModule> {
+    let state = wasm_state(ctx)?;
+    let engine = state.engine().clone();
+    let bytes = bytes.into_bytes(ctx)?;
+
+    let module = tokio::task::spawn_blocking(move || wasmi::Module::new(&engine, &bytes))
+        .await
+        .or_throw(ctx)?
+        .map_err(|err| throw_error(ctx, WasmErrorKind::Compile, err))?;
+    Ok(WasmModule { module })
+}
+
+async fn compile<'js>(ctx: Ctx<'js>, bytes: ObjectBytes<'js>) -> Result<Class<'js, WasmModule>> {
+    let module = compile_module(&ctx, bytes).await?;
+    Class::instance(ctx, module)
+}
+
+async fn instantiate_source<'js>(
+    ctx: Ctx<'js>,
+    source: Value<'js>,
+    imports: Opt<Object<'js>>,
+) -> Result<Value<'js>> {
+    if let Ok(module) = Class::<WasmModule>::from_value(&source) {
+        let instance = instantiate(&ctx, &module.borrow().module, imports.0)?;
+        return Ok(Class::instance(ctx, instance)?.into_value());
+    }
+
+    let bytes = ObjectBytes::from(&ctx, &source)?;
+    let module = Class::instance(ctx.clone(), compile_module(&ctx, bytes).await?)?;
+    let instance = instantiate(&ctx, &module.borrow().module, imports.0)?;
+
+    let result = Object::new(ctx.clone())?;
+    result.set("module", module)?;
+    result.set("instance", Class::instance(ctx.clone(), instance)?)?;
+    Ok(result.into_value())
+}
+
+/// Resolves the `Response` (or promise of one) given to the streaming APIs to its bytes.
+async fn response_bytes<'js>(ctx: &Ctx<'js>, source: Value<'js>) -> Result<ObjectBytes<'js>> {
+    let response: Object = match source.as_promise() {
+        Some(promise) => promise.clone().into_future().await?,
+        None => source
+            .into_object()
+            .or_throw_type(ctx, "Argument must be a Response or a promise of one")?,
+    };
+    let array_buffer: Function = response.get("arrayBuffer")?;
+    let buffer: Promise = array_buffer.call((This(response),))?;
+    let buffer: Value = buffer.into_future().await?;
+    ObjectBytes::from(ctx, &buffer)
+}
+
+async fn compile_streaming<'js>(
+    ctx: Ctx<'js>,
+    source: Value<'js>,
+) -> Result<Class<'js, WasmModule>> {
+    let bytes = response_bytes(&ctx, source).await?;
+    compile(ctx, bytes).await
+}
+
+async fn instantiate_streaming<'js>(
+    ctx: Ctx<'js>,
+    source: Value<'js>,
+    imports: Opt<Object<'js>>,
+) -> Result<Value<'js>> {
+    let bytes = response_bytes(&ctx, source).await?;
+    instantiate_source(ctx.clone(), bytes.into_js(&ctx)?, imports).await
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let globals = ctx.globals();
+
+    let errors: Object = crate::utils::snapshot::eval(ctx, "wasm", ERRORS_SOURCE)?;
+    let engine = Engine::default();
+    let store = Store::new(&engine, HostState { ctx: ctx.as_raw() });
+    let state = WasmState {
+        engine,
+        store: RefCell::new(store),
+        callers: RefCell::new(Vec::new()),
+        imports: RefCell::new(Vec::new()),
+        funcs: RefCell::new(Vec::new()),
+        externs: RefCell::new(Vec::new()),
+        memories: RefCell::new(Vec::new()),
+        exception: RefCell::new(None),
+        errors: Persistent::save(ctx, errors.clone()),
+        func_key: Persistent::save(ctx, Symbol::new(ctx.clone(), "wasm.func")?),
+    };
+    ctx.store_userdata(WasmContext(Rc::new(state)))?;
+
+    let web_assembly = Object::new(ctx.clone())?;
+    Class::<WasmModule>::define(&web_assembly)?;
+    Class::<WasmInstance>::define(&web_assembly)?;
+    Class::<WasmMemory>::define(&web_assembly)?;
+    Class::<WasmTable>::define(&web_assembly)?;
+    Class::<WasmGlobal>::define(&web_assembly)?;
+    for kind in [
+        WasmErrorKind::Compile,
+        WasmErrorKind::Link,
+        WasmErrorKind::Runtime,
+    ] {
+        web_assembly.set(kind.name(), errors.get::<_, Value>(kind.name())?)?;
+    }
+    web_assembly.set("validate", Func::from(validate))?;
+    web_assembly.set("compile", Func::from(Async(compile)))?;
+    web_assembly.set("instantiate", Func::from(Async(instantiate_source)))?;
+    web_assembly.set("compileStreaming", Func::from(Async(compile_streaming)))?;
+    web_assembly.set(
+        "instantiateStreaming",
+        Func::from(Async(instantiate_streaming)),
+    )?;
+    web_assembly.set(PredefinedAtom::SymbolToStringTag, "WebAssembly")?;
+
+    globals.set("WebAssembly", web_assembly)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test::{call_test, test_async_with, ModuleEvaluator};
+
+    use super::*;
+
+    // (module
+    //   (import "env" "log" (func $log (param i32)))
+    //   (memory (export "memory") 1)
+    //   (func (export "add") (param i32 i32) (result i32)
+    //     (call $log (i32.add (local.get 0) (local.get 1)))
+    //     (i32.add (local.get 0) (local.get 1))))
+    const ADD_WASM: &str = "0,97,115,109,1,0,0,0,1,11,2,96,2,127,127,1,127,96,1,127,0,2,11,1,3,101,110,118,3,108,111,103,0,1,3,2,1,0,5,3,1,0,1,7,16,2,3,97,100,100,0,1,6,109,101,109,111,114,121,2,0,10,16,1,14,0,32,0,32,1,106,16,0,32,0,32,1,106,11";
+
+    #[tokio::test]
+    async fn test_web_assembly() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                crate::utils::primordials::BasePrimordials::init(&ctx).unwrap();
+                init(&ctx).unwrap();
+
+                let source = r#"
+                    export async function test() {
+                        const bytes = new Uint8Array([ADD_WASM]);
+                        const results = [];
+                        const logged = [];
+
+                        results.push(WebAssembly.validate(bytes), WebAssembly.validate(new Uint8Array([0, 1, 2])));
+
+                        const { module, instance } = await WebAssembly.instantiate(bytes, {
+                            env: { log: (value) => logged.push(value) },
+                        });
+                        results.push(instance.exports.add(2, 3), logged.join());
+                        results.push(WebAssembly.Module.imports(module).map((i) => `${i.module}.${i.name}:${i.kind}`).join());
+                        results.push(WebAssembly.Module.exports(module).map((e) => `${e.name}:${e.kind}`).join());
+
+                        const memory = instance.exports.memory;
+                        const buffer = memory.buffer;
+                        new Uint8Array(buffer)[0] = 42;
+                        results.push(buffer.byteLength, memory.grow(1), buffer.byteLength);
+                        results.push(memory.buffer.byteLength, new Uint8Array(memory.buffer)[0]);
+
+                        const failing = new WebAssembly.Instance(module, {
+                            env: { log: () => { throw new Error("from import"); } },
+                        });
+                        try {
+                            failing.exports.add(1, 1);
+                        } catch (error) {
+                            results.push(error.message);
+                        }
+
+                        try {
+                            new WebAssembly.Module(new Uint8Array([0, 1, 2]));
+                        } catch (error) {
+                            results.push(error instanceof WebAssembly.CompileError, error.name);
+                        }
+                        try {
+                            new WebAssembly.Instance(module, { env: { log: 1 } });
+                        } catch (error) {
+                            results.push(error instanceof WebAssembly.LinkError);
+                        }
+
+                        const global = new WebAssembly.Global({ value: "i64", mutable: true }, 7n);
+                        global.value = 9n;
+                        results.push(String(global.value));
+
+                        const table = new WebAssembly.Table({ element: "anyfunc", initial: 2 });
+                        table.set(1, instance.exports.add);
+                        results.push(table.length, table.get(0), table.get(1)(4, 5));
+
+                        return results.join("|");
+                    }
+                "#
+                .replace("ADD_WASM", ADD_WASM);
+
+                let module = ModuleEvaluator::eval_js(ctx.clone(), "test", &source)
+                    .await
+                    .unwrap();
+                let result = call_test::<String, _>(&ctx, &module, ()).await;
+                assert_eq!(
+                    result,
+                    "true|false|5|5|env.log:function|add:function,memory:memory|65536|1|0|131072|42|from import|true|CompileError|true|9|2||9"
+                );
+            })
+        })
+        .await;
+    }
+}
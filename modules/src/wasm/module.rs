@@ -0,0 +1,71 @@
+use rsquickjs::{atom::PredefinedAtom, Class, Ctx, Object, Result};
+use wasmi::ExternType;
+
+use super::{throw_error, wasm_state, WasmErrorKind};
+use crate::utils::bytes::ObjectBytes;
+
+/// A compiled [`WebAssembly.Module`](https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/JavaScript_interface/Module).
+#[derive(Clone, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "Module")]
+pub struct WasmModule {
+    #[qjs(skip_trace)]
+    pub module: wasmi::Module,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl WasmModule {
+    #[qjs(constructor)]
+    pub fn new<'js>(ctx: Ctx<'js>, bytes: ObjectBytes<'js>) -> Result<Self> {
+        let state = wasm_state(&ctx)?;
+        let module = wasmi::Module::new(state.engine(), bytes.as_bytes(&ctx)?)
+            .map_err(|err| throw_error(&ctx, WasmErrorKind::Compile, err))?;
+        Ok(Self { module })
+    }
+
+    #[qjs(static)]
+    pub fn exports<'js>(ctx: Ctx<'js>, module: Class<'js, Self>) -> Result<Vec<Object<'js>>> {
+        module
+            .borrow()
+            .module
+            .exports()
+            .map(|export| descriptor(&ctx, None, export.name(), export.ty()))
+            .collect()
+    }
+
+    #[qjs(static)]
+    pub fn imports<'js>(ctx: Ctx<'js>, module: Class<'js, Self>) -> Result<Vec<Object<'js>>> {
+        module
+            .borrow()
+            .module
+            .imports()
+            .map(|import| descriptor(&ctx, Some(import.module()), import.name(), import.ty()))
+            .collect()
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "WebAssembly.Module"
+    }
+}
+
+fn descriptor<'js>(
+    ctx: &Ctx<'js>,
+    module: Option<&str>,
+    name: &str,
+    ty: &ExternType,
+) -> Result<Object<'js>> {
+    let kind = match ty {
+        ExternType::Func(_) => "function",
+        ExternType::Table(_) => "table",
+        ExternType::Memory(_) => "memory",
+        ExternType::Global(_) => "global",
+    };
+
+    let descriptor = Object::new(ctx.clone())?;
+    if let Some(module) = module {
+        descriptor.set("module", module)?;
+    }
+    descriptor.set("name", name)?;
+    descriptor.set("kind", kind)?;
+    Ok(descriptor)
+}
@@ -0,0 +1,108 @@
+use rsquickjs::{atom::PredefinedAtom, function::Opt, Ctx, Exception, Object, Result, Value};
+use wasmi::{TableType, Val};
+
+use super::{from_wasm, get_descriptor_u32, parse_val_type, to_wasm, wasm_state};
+use crate::utils::{object::ObjectExt, result::ResultExt};
+
+/// A [`WebAssembly.Table`](https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/JavaScript_interface/Table)
+/// of function or extern references.
+#[derive(Clone, rsquickjs::class::Trace, rsquickjs::JsLifetime)]
+#[rsquickjs::class(rename = "Table")]
+pub struct WasmTable {
+    #[qjs(skip_trace)]
+    pub table: wasmi::Table,
+}
+
+#[rsquickjs::methods(rename_all = "camelCase")]
+impl WasmTable {
+    #[qjs(constructor)]
+    pub fn new<'js>(
+        ctx: Ctx<'js>,
+        descriptor: Object<'js>,
+        value: Opt<Value<'js>>,
+    ) -> Result<Self> {
+        let state = wasm_state(&ctx)?;
+
+        let element: String = descriptor
+            .get_optional("element")?
+            .or_throw_type(&ctx, "Table descriptor requires 'element'")?;
+        let element = parse_val_type(&ctx, &element)?;
+        if !matches!(
+            element,
+            wasmi::core::ValType::FuncRef | wasmi::core::ValType::ExternRef
+        ) {
+            return Err(Exception::throw_type(
+                &ctx,
+                "Table 'element' must be 'anyfunc' or 'externref'",
+            ));
+        }
+        let initial = match get_descriptor_u32(&ctx, &descriptor, "initial")? {
+            Some(initial) => initial,
+            None => get_descriptor_u32(&ctx, &descriptor, "minimum")?
+                .or_throw_type(&ctx, "Table descriptor requires 'initial'")?,
+        };
+        let maximum = get_descriptor_u32(&ctx, &descriptor, "maximum")?;
+        if maximum.is_some_and(|maximum| maximum < initial) {
+            return Err(Exception::throw_range(
+                &ctx,
+                "'maximum' must not be less than 'initial'",
+            ));
+        }
+
+        let init = match value.0.filter(|value| !value.is_undefined()) {
+            Some(value) => to_wasm(&ctx, &state, value, element)?,
+            None => Val::default(element),
+        };
+        let table = state
+            .with_store(&ctx, |mut store| {
+                wasmi::Table::new(&mut store, TableType::new(element, initial, maximum), init)
+            })?
+            .or_throw_range(&ctx, "")?;
+        Ok(Self { table })
+    }
+
+    #[qjs(get)]
+    pub fn length(&self, ctx: Ctx<'_>) -> Result<u32> {
+        wasm_state(&ctx)?.with_store(&ctx, |store| self.table.size(&store))
+    }
+
+    pub fn get<'js>(&self, ctx: Ctx<'js>, index: u32) -> Result<Value<'js>> {
+        let state = wasm_state(&ctx)?;
+        let value = state
+            .with_store(&ctx, |store| self.table.get(&store, index))?
+            .or_throw_range(&ctx, "Table index is out of bounds")?;
+        from_wasm(&ctx, &state, &value)
+    }
+
+    pub fn set<'js>(&self, ctx: Ctx<'js>, index: u32, value: Opt<Value<'js>>) -> Result<()> {
+        let state = wasm_state(&ctx)?;
+        let element = state.with_store(&ctx, |store| self.table.ty(&store).element())?;
+        let value = match value.0 {
+            Some(value) => to_wasm(&ctx, &state, value, element)?,
+            None => Val::default(element),
+        };
+        state
+            .with_store(&ctx, |mut store| self.table.set(&mut store, index, value))?
+            .or_throw_range(&ctx, "Table index is out of bounds")
+    }
+
+    pub fn grow<'js>(&self, ctx: Ctx<'js>, delta: u32, value: Opt<Value<'js>>) -> Result<u32> {
+        let state = wasm_state(&ctx)?;
+        let element = state.with_store(&ctx, |store| self.table.ty(&store).element())?;
+        let init = match value.0 {
+            Some(value) => to_wasm(&ctx, &state, value, element)?,
+            None => Val::default(element),
+        };
+        state
+            .with_store(&ctx, |mut store| self.table.grow(&mut store, delta, init))?
+            .or_throw_range(
+                &ctx,
+                "WebAssembly.Table.grow(): Maximum table size exceeded",
+            )
+    }
+
+    #[qjs(get, rename = PredefinedAtom::SymbolToStringTag)]
+    pub fn to_string_tag(&self) -> &'static str {
+        "WebAssembly.Table"
+    }
+}
@@ -15,6 +15,9 @@ pub struct Args {
     /// Prevent any modifications to the lockfile
     #[clap(long, global = true)]
     pub immutable: bool,
+    /// Fail if the lockfile would change, for CI
+    #[clap(long, global = true)]
+    pub frozen_lockfile: bool,
     /// Run in a custom working directory
     #[clap(long, global = true, alias = "cwd")]
     pub working_dir: Option<PathBuf>,
@@ -42,11 +45,19 @@ pub enum Subcommand {
         #[clap(long, alias = "exact")]
         pin: bool,
     },
-    /// Run a script defined in package.json
+    /// Run a script defined in package.json, or else the file `name`,
+    /// `scripts/<name>.ts` or a bin from node_modules/.bin
     Run {
-        name: CompactString,
+        /// Lists the scripts when left out
+        name: Option<CompactString>,
         #[clap(long)]
         watch: Vec<PathBuf>,
+        /// List the scripts of package.json instead of running one
+        #[clap(long)]
+        list: bool,
+        /// Print the list as a JSON object of names and commands
+        #[clap(long, requires = "list")]
+        json: bool,
     },
     /// Clean packages installed in `node_modules` and remove cache
     Clean,
@@ -3,10 +3,19 @@
 use color_eyre::eyre::Result;
 use std::fs::remove_dir_all;
 use std::io::ErrorKind;
+use std::path::PathBuf;
 
-/// Execute the clean command.
-pub fn cmd_clean() -> Result<()> {
-    for dir in ["node_modules", ".xmas"] {
+use crate::store::configured_store_dir;
+
+/// Execute the clean command, removing the packages of the project and the store they
+/// were linked from.
+pub async fn cmd_clean() -> Result<()> {
+    let dirs = [
+        PathBuf::from("node_modules"),
+        PathBuf::from(".xmas"),
+        configured_store_dir().await?,
+    ];
+    for dir in dirs {
         match remove_dir_all(dir) {
             Ok(()) => {}
             Err(e) if e.kind() == ErrorKind::NotFound => {}
@@ -9,12 +9,15 @@ use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::env::{current_dir, current_exe, set_current_dir, set_var, temp_dir};
 use std::ffi::{OsStr, OsString};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use tokio::fs::create_dir;
 use which::which;
+use xmas_vsys::{ProcCommand, ProcVTable};
 
 use crate::commands::add::add_packages;
-use crate::commands::{install, join_paths};
+use crate::commands::{install, join_paths, new_path};
+use crate::package::PackageMetadata;
 use crate::progress::log_verbose;
 use crate::util::save_package;
 
@@ -102,22 +105,201 @@ pub async fn install_bin_temp(args: &crate::Args, package_name: &str) -> Result<
     Ok(())
 }
 
+static PROC_VTABLE: OnceLock<Arc<ProcVTable>> = OnceLock::new();
+
+/// Routes package scripts through `proc` instead of the built-in shell.
+///
+/// Scripts then run with the platform shell, spawned by the vtable.
+pub fn set_proc_vtable(proc: Arc<ProcVTable>) -> Result<()> {
+    PROC_VTABLE
+        .set(proc)
+        .map_err(|_| eyre!("A process vtable is already set"))
+}
+
+/// The environment of the `event` script of `package`, read from `package_json`, with
+/// the variables npm sets for lifecycle scripts.
+pub fn lifecycle_env(
+    package: &PackageMetadata,
+    package_json: &Path,
+    event: &str,
+) -> Result<HashMap<OsString, OsString>> {
+    let mut env = HashMap::new();
+    let mut set = |key: &str, value: OsString| {
+        env.insert(OsString::from(key), value);
+    };
+    let init_cwd = current_dir()?;
+    set("PATH", new_path()?);
+    set(
+        "npm_package_json",
+        init_cwd.join(package_json).into_os_string(),
+    );
+    set("INIT_CWD", init_cwd.into_os_string());
+    set("npm_execpath", current_exe()?.into_os_string());
+    set("npm_lifecycle_event", event.into());
+    if let Some(Value::String(script)) = package.scripts.get(event) {
+        set("npm_lifecycle_script", script.into());
+    }
+    set("npm_package_name", package.name.as_str().into());
+    if let Some(version) = &package.version {
+        set("npm_package_version", version.to_string().into());
+    }
+    for (key, value) in &package.config {
+        flatten_config(&format!("npm_package_config_{key}"), value, &mut set);
+    }
+
+    Ok(env)
+}
+
+/// Sets `value` as `prefix`, or its fields as `prefix_<field>` when it's an object.
+fn flatten_config(prefix: &str, value: &Value, set: &mut impl FnMut(&str, OsString)) {
+    match value {
+        Value::Null => {}
+        Value::String(s) => set(prefix, s.into()),
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                flatten_config(&format!("{prefix}_{key}"), value, set);
+            }
+        }
+        value => set(prefix, value.to_string().into()),
+    }
+}
+
+/// Execute a package script.
 pub async fn shell(
     text: &str,
     cwd: PathBuf,
     new_env: HashMap<OsString, OsString>,
     kill_signal: KillSignal,
 ) -> Result<i32> {
-    // parse
-    let list =
-        deno_task_shell::parser::parse(&text).map_err(|e| eyre!("Shell parse error: {}", e))?;
+    if let Some(proc) = PROC_VTABLE.get() {
+        return shell_with_vtable(proc.clone(), text, cwd, new_env).await;
+    }
 
-    // execute
     let mut env_vars = std::env::vars_os().collect::<HashMap<_, _>>();
     for (k, v) in new_env {
         let _ = env_vars.insert(k, v);
     }
 
+    execute_shell(text, cwd, env_vars, kill_signal).await
+}
+
+/// The variables sandboxed scripts keep from the environment of xmas.
+const SANDBOX_INHERITED_ENV: [&str; 8] = [
+    "PATH", "HOME", "TMPDIR", "TEMP", "TMP", "LANG", "TERM", "SHELL",
+];
+
+/// Whether [`sandboxed_shell`] can run scripts on this system.
+pub fn sandbox_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        sandboxed_command("exit 0", Path::new("."), HashMap::new())
+            .and_then(|mut command| command.status().ok())
+            .is_some_and(|status| status.success())
+    })
+}
+
+/// Execute a package script with `sh`, without network access and only with
+/// [`SANDBOX_INHERITED_ENV`], so tokens and credentials don't leak either. On Linux it runs
+/// in network and user namespaces of its own, on macOS under `sandbox-exec`; elsewhere,
+/// or when those are unavailable (see [`sandbox_available`]), it fails without running.
+pub async fn sandboxed_shell(
+    text: &str,
+    cwd: PathBuf,
+    new_env: HashMap<OsString, OsString>,
+) -> Result<i32> {
+    if !sandbox_available() {
+        return Err(eyre!("Scripts can't run sandboxed on this system"));
+    }
+    let command = sandboxed_command(text, &cwd, new_env).wrap_err("Scripts can't run sandboxed")?;
+    let status = tokio::process::Command::from(command).status().await?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// The command running `text` sandboxed, `None` where there is no sandbox.
+fn sandboxed_command(
+    text: &str,
+    cwd: &Path,
+    new_env: HashMap<OsString, OsString>,
+) -> Option<std::process::Command> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("/usr/bin/sandbox-exec");
+        command
+            .args(["-p", "(version 1) (allow default) (deny network*)"])
+            .arg("sh");
+        command
+    } else if cfg!(target_os = "linux") {
+        std::process::Command::new("sh")
+    } else {
+        return None;
+    };
+    command
+        .arg("-c")
+        .arg(text)
+        .current_dir(cwd)
+        .env_clear()
+        .envs(
+            SANDBOX_INHERITED_ENV
+                .iter()
+                .filter_map(|key| Some((*key, std::env::var_os(key)?))),
+        )
+        .envs(new_env);
+    #[cfg(target_os = "linux")]
+    isolate_network(&mut command);
+    Some(command)
+}
+
+/// Moves the child into a network namespace of its own, with only a loopback interface that
+/// is down. Unprivileged users need a user namespace for that, where the child keeps its ids
+/// so the files it creates still belong to them.
+#[cfg(target_os = "linux")]
+fn isolate_network(command: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+
+    // Formatted before forking, as the child may not allocate
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let uid_map = format!("{uid} {uid} 1");
+    let gid_map = format!("{gid} {gid} 1");
+    unsafe {
+        command.pre_exec(move || {
+            if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            write_proc(c"/proc/self/setgroups", b"deny")?;
+            write_proc(c"/proc/self/uid_map", uid_map.as_bytes())?;
+            write_proc(c"/proc/self/gid_map", gid_map.as_bytes())
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_proc(path: &std::ffi::CStr, data: &[u8]) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, data.as_ptr().cast(), data.len());
+        let result = if written < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        libc::close(fd);
+        result
+    }
+}
+
+async fn execute_shell(
+    text: &str,
+    cwd: PathBuf,
+    env_vars: HashMap<OsString, OsString>,
+    kill_signal: KillSignal,
+) -> Result<i32> {
+    // parse
+    let list =
+        deno_task_shell::parser::parse(&text).map_err(|e| eyre!("Shell parse error: {}", e))?;
+
+    // execute
     let exit_code = deno_task_shell::execute(
         list,
         env_vars,
@@ -129,3 +311,16 @@ pub async fn shell(
 
     Ok(exit_code)
 }
+
+async fn shell_with_vtable(
+    proc: Arc<ProcVTable>,
+    text: &str,
+    cwd: PathBuf,
+    new_env: HashMap<OsString, OsString>,
+) -> Result<i32> {
+    let mut command = ProcCommand::shell(text).cwd(cwd);
+    command.env.extend(new_env);
+
+    let status = tokio::task::spawn_blocking(move || proc.run(&command)).await??;
+    Ok(status.code().unwrap_or(1))
+}
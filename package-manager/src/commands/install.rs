@@ -7,7 +7,6 @@ use compact_str::{CompactString, ToCompactString};
 use deno_task_shell::KillSignal;
 use itertools::Itertools;
 use serde_json::Value;
-use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
 use std::io::ErrorKind;
@@ -16,15 +15,14 @@ use std::time::Instant;
 use tokio::fs::{create_dir_all, read_to_string};
 use tokio::process::Command;
 
-use crate::commands::exec::shell;
-use crate::config::read_config;
+use crate::commands::exec::{lifecycle_env, sandbox_available, sandboxed_shell, shell};
+use crate::config::{read_config, Config, InstallScriptsMode};
 use crate::npm::DependencyTree;
 use crate::package::PackageMetadata;
 use crate::plan::{execute_plan, setup_bins, tree_size, Plan};
 use crate::progress::{finish_progress, log_progress, log_verbose, set_total, PROGRESS_BAR};
-use crate::resolve::Lockfile;
 use crate::scoped_path::scoped_join;
-use crate::util::{load_graph_from_lockfile, read_package, write_json};
+use crate::util::{load_graph_from_lockfile, read_package, save_lockfile, write_json};
 use crate::Args;
 
 /// Execute the install command.
@@ -40,7 +38,7 @@ pub async fn prepare_plan(args: &Args, package: &PackageMetadata) -> Result<Plan
 
     if !args.immutable {
         graph.append(package.iter_all(), true).await?;
-        write_json("xmas.lock", Lockfile::new(graph.clone())).await?;
+        save_lockfile(&graph, args.frozen_lockfile).await?;
     }
 
     log_progress("Retrieved dependency graph");
@@ -79,7 +77,20 @@ pub async fn verify_installation(package: &PackageMetadata, plan: &Plan) -> Resu
     Ok(installed.satisfies(package))
 }
 
-async fn exec_install_scripts_in(stack: &[CompactString]) -> Result<()> {
+/// A dependency that has install scripts, and what was done with them.
+struct InstallScripts {
+    package: String,
+    scripts: Vec<&'static str>,
+    mode: InstallScriptsMode,
+}
+
+const INSTALL_SCRIPTS: [&str; 3] = ["preinstall", "install", "postinstall"];
+
+async fn exec_install_scripts_in(
+    stack: &[CompactString],
+    config: &Config,
+    summary: &mut Vec<InstallScripts>,
+) -> Result<()> {
     let path = stack.join("/node_modules/");
 
     let dir = scoped_join("node_modules", path)?;
@@ -94,23 +105,50 @@ async fn exec_install_scripts_in(stack: &[CompactString]) -> Result<()> {
 
     let package_json: PackageMetadata = serde_json::from_str(&package_json)?;
 
-    for script_name in ["preinstall", "install", "postinstall"] {
-        if let Some(Value::String(script)) = package_json.scripts.get(script_name) {
-            PROGRESS_BAR.suspend(|| {
-                println!("Executing {script_name} script for {}", stack.join(" > "));
-            });
-
-            let mut new_env = HashMap::new();
-            new_env.insert(OsString::from("PATH"), new_path()?);
-            let child = shell(script, dir.clone(), new_env, KillSignal::default()).await?;
-
-            if child > 0 {
-                return Err(eyre!(
-                    "{} script failed with exit code {}",
-                    script_name,
-                    child
-                ));
-            }
+    let scripts = INSTALL_SCRIPTS
+        .into_iter()
+        .filter_map(|name| match package_json.scripts.get(name) {
+            Some(Value::String(script)) => Some((name, script)),
+            _ => None,
+        })
+        .collect_vec();
+    if scripts.is_empty() {
+        return Ok(());
+    }
+    // the name in package.json is chosen by the package's author, so the allowlist is
+    // checked against the name the dependency was installed under
+    let installed_as = stack.last().map(CompactString::as_str).unwrap_or_default();
+    let mut mode = config.install_script_mode(installed_as);
+    if mode == InstallScriptsMode::Sandbox && !sandbox_available() {
+        mode = InstallScriptsMode::Deny;
+    }
+    summary.push(InstallScripts {
+        package: stack.join(" > "),
+        scripts: scripts.iter().map(|(name, _)| *name).collect(),
+        mode,
+    });
+    if mode == InstallScriptsMode::Deny {
+        return Ok(());
+    }
+
+    for (script_name, script) in scripts {
+        PROGRESS_BAR.suspend(|| {
+            println!("Executing {script_name} script for {}", stack.join(" > "));
+        });
+
+        let new_env = lifecycle_env(&package_json, &dir.join("package.json"), script_name)?;
+        let child = if mode == InstallScriptsMode::Sandbox {
+            sandboxed_shell(script, dir.clone(), new_env).await?
+        } else {
+            shell(script, dir.clone(), new_env, KillSignal::default()).await?
+        };
+
+        if child > 0 {
+            return Err(eyre!(
+                "{} script failed with exit code {}",
+                script_name,
+                child
+            ));
         }
     }
 
@@ -120,12 +158,14 @@ async fn exec_install_scripts_in(stack: &[CompactString]) -> Result<()> {
 async fn exec_install_scripts(
     tree: &DependencyTree,
     initial_stack: &[CompactString],
+    config: &Config,
+    summary: &mut Vec<InstallScripts>,
 ) -> Result<()> {
     let mut work_stack: Vec<(&DependencyTree, Vec<CompactString>)> =
         vec![(tree, initial_stack.to_vec())];
 
     while let Some((current_tree, mut stack)) = work_stack.pop() {
-        exec_install_scripts_in(&stack).await?;
+        exec_install_scripts_in(&stack, config, summary).await?;
 
         stack.push(current_tree.root.name.clone());
         for child_tree in current_tree.children.values() {
@@ -165,11 +205,11 @@ pub async fn install(args: &Args) -> Result<()> {
             }
         });
 
-        if !config.disallow_install_scripts {
-            for (name, tree) in plan.trees.iter() {
-                exec_install_scripts(tree, &mut vec![name.clone()]).await?;
-            }
+        let mut summary = Vec::new();
+        for (name, tree) in plan.trees.iter() {
+            exec_install_scripts(tree, &[name.clone()], &config, &mut summary).await?;
         }
+        print_install_scripts_summary(&summary, &config);
 
         setup_bins(&plan).await?;
 
@@ -181,6 +221,40 @@ pub async fn install(args: &Args) -> Result<()> {
     Ok(())
 }
 
+/// Lists the dependencies that wanted to run install scripts, so new ones stand out.
+fn print_install_scripts_summary(summary: &[InstallScripts], config: &Config) {
+    if summary.is_empty() {
+        return;
+    }
+    PROGRESS_BAR.suspend(|| {
+        println!("{} packages have install scripts:", summary.len().yellow());
+        for entry in summary {
+            let outcome = match entry.mode {
+                InstallScriptsMode::Allow => "ran".green().to_string(),
+                InstallScriptsMode::Sandbox => "ran sandboxed".yellow().to_string(),
+                InstallScriptsMode::Deny => "skipped".red().to_string(),
+            };
+            println!(
+                "  {} ({}): {}",
+                entry.package.bright_blue(),
+                entry.scripts.join(", "),
+                outcome
+            );
+        }
+        if summary
+            .iter()
+            .any(|entry| entry.mode == InstallScriptsMode::Deny)
+        {
+            if config.install_scripts.mode == Some(InstallScriptsMode::Sandbox)
+                && !sandbox_available()
+            {
+                println!("Scripts can't run sandboxed on this system, so they were skipped");
+            }
+            println!("Add skipped packages to `install_scripts.allow` to run their scripts");
+        }
+    });
+}
+
 /// Create a new PATH with node_modules/.bin prepended.
 pub fn new_path() -> Result<OsString> {
     let path = env::var_os("PATH").unwrap_or_default();
@@ -202,9 +276,45 @@ pub fn join_paths() -> Result<()> {
 
 /// Initialize storage directories.
 pub async fn init_storage() -> Result<()> {
-    create_dir_all(".xmas/store").await?;
+    crate::store::init().await?;
     create_dir_all("node_modules/.xmas").await?;
     create_dir_all("node_modules/.bin").await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::InstallScriptsPolicy;
+
+    #[tokio::test]
+    async fn test_allowlist_uses_installed_name() {
+        let root = env::temp_dir().join(format!("xmas-install-scripts-{}", std::process::id()));
+        let package = root.join("node_modules").join("evil");
+        create_dir_all(&package).await.unwrap();
+        tokio::fs::write(
+            package.join("package.json"),
+            r#"{ "name": "esbuild", "version": "1.0.0", "scripts": { "postinstall": "exit 1" } }"#,
+        )
+        .await
+        .unwrap();
+        env::set_current_dir(&root).unwrap();
+
+        let config = Config {
+            install_scripts: InstallScriptsPolicy {
+                mode: Some(InstallScriptsMode::Deny),
+                allow: vec!["esbuild".into()],
+            },
+            ..Config::default()
+        };
+        let mut summary = Vec::new();
+        exec_install_scripts_in(&["evil".into()], &config, &mut summary)
+            .await
+            .unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].mode, InstallScriptsMode::Deny);
+
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
@@ -17,7 +17,7 @@ pub use create::cmd_create;
 pub use exec::cmd_exec;
 pub use install::{cmd_install, init_storage, install, join_paths, new_path};
 pub use remove::cmd_remove;
-pub use run::cmd_run;
+pub use run::{cmd_list_scripts, cmd_run};
 pub use update::cmd_update;
 pub use upgrade::cmd_upgrade;
 pub use why::cmd_why;
@@ -31,8 +31,16 @@ pub async fn execute_command(args: &Args) -> Result<()> {
         Subcommand::Install => cmd_install(&args).await,
         Subcommand::Update => cmd_update(&args).await,
         Subcommand::Add { names, dev, pin } => cmd_add(&names, *dev, *pin).await,
-        Subcommand::Run { name, watch } => cmd_run(&args, &name, &watch).await,
-        Subcommand::Clean => cmd_clean(),
+        Subcommand::Run {
+            name,
+            watch,
+            list,
+            json,
+        } => match name {
+            Some(name) if !list => cmd_run(&args, &name, &watch).await,
+            _ => cmd_list_scripts(*json).await,
+        },
+        Subcommand::Clean => cmd_clean().await,
         Subcommand::Upgrade { pin } => cmd_upgrade(*pin).await,
 
         // TODO: fix with deno task shell
@@ -1,18 +1,21 @@
 //! Run command implementation.
 
-use color_eyre::eyre::{ContextCompat, Result};
+use color_eyre::eyre::{eyre, ContextCompat, Result};
 use color_eyre::owo_colors::OwoColorize;
+use color_eyre::Section;
 use compact_str::CompactString;
 use deno_task_shell::KillSignal;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use tokio::process::Command;
 
-use crate::commands::exec::shell;
-use crate::commands::{install, join_paths, new_path};
+use crate::commands::exec::{lifecycle_env, shell};
+use crate::commands::{install, join_paths};
+use crate::package::PackageMetadata;
 use crate::progress::PROGRESS_BAR;
-use crate::util::read_package;
+use crate::util::{edit_distance, read_package};
 use crate::watch::async_watch;
 
 /// Execute the run command.
@@ -37,18 +40,10 @@ pub async fn cmd_run(arg: &crate::Args, name: &CompactString, watch: &[PathBuf])
         let install = async {
             let package = read_package().await?;
 
-            let script = package
-                .scripts
-                .get(name)
-                .wrap_err(format!("Script `{name}` is not defined"))?
-                .as_str()
-                .wrap_err(format!("Script `{name}` is not a string"))?;
-
             install(arg).await?;
-            let cwd = std::env::current_dir()?;
-            let mut new_env = HashMap::new();
-            new_env.insert(OsString::from("PATH"), new_path()?);
-            let exit_code = shell(script, cwd, new_env, KillSignal::default()).await?;
+            let target = find_target(&package, name)?;
+            let new_env = lifecycle_env(&package, Path::new("package.json"), name)?;
+            let exit_code = target.run(new_env).await?;
 
             if exit_code != 0 {
                 exit(exit_code);
@@ -67,3 +62,105 @@ pub async fn cmd_run(arg: &crate::Args, name: &CompactString, watch: &[PathBuf])
         }
     }
 }
+
+/// Extensions of the files `xmas run <name>` finds in `scripts/`.
+const SCRIPT_EXTENSIONS: [&str; 6] = ["ts", "mts", "tsx", "js", "mjs", "jsx"];
+
+/// What `xmas run <name>` runs, in the order it is looked up.
+enum Target {
+    /// A script from package.json
+    Script(String),
+    /// A file run with xmas, `name` itself or `scripts/<name>.<ext>`
+    File(PathBuf),
+    /// An executable from `node_modules/.bin`, like `npm exec`
+    Bin(PathBuf),
+}
+
+impl Target {
+    /// Run the target with `new_env`, returning its exit code.
+    async fn run(self, new_env: HashMap<OsString, OsString>) -> Result<i32> {
+        let mut command = match self {
+            Target::Script(script) => {
+                let cwd = std::env::current_dir()?;
+                return shell(&script, cwd, new_env, KillSignal::default()).await;
+            }
+            Target::File(file) => {
+                let mut command = Command::new(std::env::current_exe()?);
+                command.arg(file);
+                command
+            }
+            Target::Bin(bin) => Command::new(bin),
+        };
+        let status = command.envs(new_env).status().await?;
+        Ok(status.code().unwrap_or(1))
+    }
+}
+
+/// Find what `name` refers to, after dependencies were installed.
+fn find_target(package: &PackageMetadata, name: &str) -> Result<Target> {
+    if let Some(script) = package.scripts.get(name) {
+        let script = script
+            .as_str()
+            .wrap_err(format!("Script `{name}` is not a string"))?;
+        return Ok(Target::Script(script.to_string()));
+    }
+
+    let file = Path::new(name);
+    if file.is_file() {
+        return Ok(Target::File(file.to_path_buf()));
+    }
+    for ext in SCRIPT_EXTENSIONS {
+        let file = Path::new("scripts").join(format!("{name}.{ext}"));
+        if file.is_file() {
+            return Ok(Target::File(file));
+        }
+    }
+
+    let cwd = std::env::current_dir()?;
+    if let Ok(bin) = which::which_in(name, Some("node_modules/.bin"), cwd) {
+        return Ok(Target::Bin(bin));
+    }
+
+    let error = eyre!("Script `{name}` is not defined, and there is no such file or package bin");
+    Err(match closest_script(package, name) {
+        Some(closest) => error.with_suggestion(|| format!("Did you mean `{closest}`?")),
+        None => error.with_note(|| "`xmas run --list` lists the scripts of package.json"),
+    })
+}
+
+/// The script named most like `name`, if any is close enough to be a typo of it.
+fn closest_script<'a>(package: &'a PackageMetadata, name: &str) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    package
+        .scripts
+        .keys()
+        .map(|script| (edit_distance(name, script), script.as_str()))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, script)| script)
+}
+
+/// Execute `run` without a name or with `--list`: print the scripts of package.json
+/// with their commands, as a JSON object with `json`.
+pub async fn cmd_list_scripts(json: bool) -> Result<()> {
+    let package = read_package().await?;
+    let scripts: BTreeMap<_, _> = package
+        .scripts
+        .iter()
+        .filter_map(|(name, script)| Some((name.as_str(), script.as_str()?)))
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&scripts)?);
+    } else if scripts.is_empty() {
+        println!("No scripts in package.json");
+    } else {
+        println!("Scripts in package.json:");
+        for (name, script) in scripts {
+            println!("  {}", name.bright_blue());
+            println!("    {}", script.dimmed());
+        }
+    }
+
+    Ok(())
+}
@@ -8,8 +8,8 @@ use std::time::Instant;
 
 use crate::commands::init_storage;
 use crate::progress::PROGRESS_BAR;
-use crate::resolve::{Graph, Lockfile};
-use crate::util::{read_package, write_json};
+use crate::resolve::Graph;
+use crate::util::{read_package, save_lockfile};
 use crate::Args;
 
 /// Execute the update command.
@@ -26,7 +26,7 @@ pub async fn cmd_update(args: &Args) -> Result<()> {
 
     let mut graph = Graph::default();
     graph.append(package.iter_all(), false).await?;
-    write_json("xmas.lock", Lockfile::new(graph.clone())).await?;
+    save_lockfile(&graph, args.frozen_lockfile).await?;
 
     PROGRESS_BAR.suspend(|| {
         println!(
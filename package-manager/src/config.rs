@@ -1,16 +1,204 @@
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, WrapErr};
 use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
 use tokio::fs::read_to_string;
+use xmas_vsys::Permissions;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default)]
     pub registry: Vec<Registry>,
+    /// Shorthand of `install_scripts.mode = "deny"`
     #[serde(default)]
     pub disallow_install_scripts: bool,
+    /// Which dependencies run their install scripts, and how
+    #[serde(default)]
+    pub install_scripts: InstallScriptsPolicy,
+    /// Sandbox policy for scripts run in this project
+    #[serde(default)]
+    pub permissions: Option<Permissions>,
+    /// JSX factory of the sources transformed at runtime
+    #[serde(default)]
+    pub jsx: JsxConfig,
+    /// Specifiers replaced before resolving, `"@" = "./src"` maps `@/a` to `./src/a`
+    #[serde(default)]
+    pub alias: BTreeMap<String, String>,
+    /// Defaults of `xmas bun`
+    #[serde(default)]
+    pub bundle: BundleDefaults,
+    /// How sources are transpiled at runtime
+    #[serde(default)]
+    pub transform: TransformConfig,
+    /// `.env` files loaded before running, `.env` and `.env.local` when unset
+    #[serde(default)]
+    pub env_file: Option<Vec<PathBuf>>,
+    /// Format of the console output of scripts
+    #[serde(default)]
+    pub log_format: Option<LogFormat>,
+    /// Files written or packages linked at once during installs, the number of CPUs
+    /// when unset
+    #[serde(default)]
+    pub install_concurrency: Option<usize>,
+    /// Where installed packages are stored, the cache directory of the platform when
+    /// unset; a relative path like `.xmas/store` is in the project
+    #[serde(default)]
+    pub store_dir: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Formatted like Node.js, colored on terminals
+    Text,
+    /// One JSON object per console call, for log aggregators
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct InstallScriptsPolicy {
+    /// How the scripts of packages not in `allow` run, `allow` when unset
+    pub mode: Option<InstallScriptsMode>,
+    /// Packages whose scripts always run with the full environment
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallScriptsMode {
+    /// Run with the environment of xmas
+    Allow,
+    /// Run with a minimal environment, so tokens don't leak, and without network access;
+    /// skipped where scripts can't be sandboxed
+    Sandbox,
+    /// Don't run
+    Deny,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct JsxConfig {
+    pub runtime: Option<JsxRuntime>,
+    /// Package of the automatic runtime, e.g. `preact`
+    pub import_source: Option<String>,
+    /// Element factory of the classic runtime, e.g. `h`
+    pub pragma: Option<String>,
+    /// Fragment factory of the classic runtime
+    pub pragma_frag: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum JsxRuntime {
+    /// Calls the pragma, like `"jsx": "react"` in tsconfig.json
+    Classic,
+    /// Imports `<import_source>/jsx-runtime`, like `"jsx": "react-jsx"`
+    Automatic,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TransformConfig {
+    /// ECMAScript version or engines to lower syntax for, e.g. `es2019` or `chrome80`
+    pub target: Option<String>,
+    pub decorators: Option<Decorators>,
+    pub emit_decorator_metadata: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Decorators {
+    /// Like `"experimentalDecorators": true` in tsconfig.json
+    Legacy,
+    /// The TC39 proposal
+    Tc39,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BundleDefaults {
+    pub output_dir: Option<PathBuf>,
+    pub minify: Option<bool>,
+    pub source_map: Option<bool>,
+    /// esm, cjs or iife
+    pub format: Option<String>,
+    #[serde(default)]
+    pub external: Vec<String>,
+}
+
+impl Config {
+    /// `self` overridden by `other`, whose registries are tried first.
+    pub fn merge(self, other: Config) -> Config {
+        let mut alias = self.alias;
+        alias.extend(other.alias);
+        let mut external = self.bundle.external;
+        external.extend(other.bundle.external);
+        Config {
+            registry: other.registry.into_iter().chain(self.registry).collect(),
+            disallow_install_scripts: self.disallow_install_scripts
+                || other.disallow_install_scripts,
+            install_scripts: InstallScriptsPolicy {
+                mode: other.install_scripts.mode.or(self.install_scripts.mode),
+                allow: self
+                    .install_scripts
+                    .allow
+                    .into_iter()
+                    .chain(other.install_scripts.allow)
+                    .collect(),
+            },
+            permissions: other.permissions.or(self.permissions),
+            jsx: JsxConfig {
+                runtime: other.jsx.runtime.or(self.jsx.runtime),
+                import_source: other.jsx.import_source.or(self.jsx.import_source),
+                pragma: other.jsx.pragma.or(self.jsx.pragma),
+                pragma_frag: other.jsx.pragma_frag.or(self.jsx.pragma_frag),
+            },
+            alias,
+            bundle: BundleDefaults {
+                output_dir: other.bundle.output_dir.or(self.bundle.output_dir),
+                minify: other.bundle.minify.or(self.bundle.minify),
+                source_map: other.bundle.source_map.or(self.bundle.source_map),
+                format: other.bundle.format.or(self.bundle.format),
+                external,
+            },
+            transform: TransformConfig {
+                target: other.transform.target.or(self.transform.target),
+                decorators: other.transform.decorators.or(self.transform.decorators),
+                emit_decorator_metadata: other
+                    .transform
+                    .emit_decorator_metadata
+                    .or(self.transform.emit_decorator_metadata),
+            },
+            env_file: other.env_file.or(self.env_file),
+            log_format: other.log_format.or(self.log_format),
+            install_concurrency: other.install_concurrency.or(self.install_concurrency),
+            store_dir: other.store_dir.or(self.store_dir),
+        }
+    }
+
+    /// How the install scripts of the package `name` run.
+    pub fn install_script_mode(&self, name: &str) -> InstallScriptsMode {
+        if self
+            .install_scripts
+            .allow
+            .iter()
+            .any(|allowed| allowed == name)
+        {
+            return InstallScriptsMode::Allow;
+        }
+        match self.install_scripts.mode {
+            Some(mode) => mode,
+            None if self.disallow_install_scripts => InstallScriptsMode::Deny,
+            None => InstallScriptsMode::Allow,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
@@ -70,11 +258,129 @@ impl AuthSource {
     }
 }
 
+/// `~/.xmas/config.toml`, the config of every project of the user.
+pub fn global_config_path() -> Option<PathBuf> {
+    home::home_dir().map(|home| home.join(".xmas").join("config.toml"))
+}
+
+/// The user-global config overridden by the JSX and decorator options of tsconfig.json,
+/// then by the config of the project, from xmas.toml or else the `"xmas"` field of package.json.
 pub async fn read_config() -> Result<Config> {
-    let config = read_to_string("xmas.toml").await;
-    if let Ok(config) = config {
-        Ok(toml::from_str(&config)?)
-    } else {
-        Ok(Config::default())
+    let global = match global_config_path() {
+        Some(path) => read_toml(&path).await?,
+        None => None,
+    };
+    let project = match read_toml(Path::new("xmas.toml")).await? {
+        Some(config) => Some(config),
+        None => read_package_json_config().await?,
+    };
+    let tsconfig = read_tsconfig().await;
+    Ok(global
+        .unwrap_or_default()
+        .merge(tsconfig)
+        .merge(project.unwrap_or_default()))
+}
+
+async fn read_toml(path: &Path) -> Result<Option<Config>> {
+    let Ok(config) = read_to_string(path).await else {
+        return Ok(None);
+    };
+    let config = toml::from_str(&config).wrap_err_with(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(config))
+}
+
+async fn read_package_json_config() -> Result<Option<Config>> {
+    let Ok(package) = read_to_string("package.json").await else {
+        return Ok(None);
+    };
+    // a broken package.json is reported by whoever needs the rest of it
+    let Ok(serde_json::Value::Object(mut package)) = serde_json::from_str(&package) else {
+        return Ok(None);
+    };
+    let Some(config) = package.remove("xmas") else {
+        return Ok(None);
+    };
+    let config = serde_json::from_value(config).wrap_err("Invalid \"xmas\" in package.json")?;
+    Ok(Some(config))
+}
+
+/// The JSX and decorator options of the `compilerOptions` of tsconfig.json, `extends`
+/// isn't followed.
+async fn read_tsconfig() -> Config {
+    let Ok(tsconfig) = read_to_string("tsconfig.json").await else {
+        return Config::default();
+    };
+    // a broken tsconfig.json is reported by the type checker
+    let Ok(tsconfig) = serde_json::from_str::<serde_json::Value>(&strip_jsonc(&tsconfig)) else {
+        return Config::default();
+    };
+    let options = &tsconfig["compilerOptions"];
+    let string = |key: &str| options[key].as_str().map(String::from);
+    Config {
+        jsx: JsxConfig {
+            runtime: match options["jsx"].as_str() {
+                Some("react") => Some(JsxRuntime::Classic),
+                Some("react-jsx" | "react-jsxdev") => Some(JsxRuntime::Automatic),
+                _ => None,
+            },
+            import_source: string("jsxImportSource"),
+            pragma: string("jsxFactory"),
+            pragma_frag: string("jsxFragmentFactory"),
+        },
+        transform: TransformConfig {
+            target: None,
+            decorators: options["experimentalDecorators"].as_bool().map(|legacy| {
+                if legacy {
+                    Decorators::Legacy
+                } else {
+                    Decorators::Tc39
+                }
+            }),
+            emit_decorator_metadata: options["emitDecoratorMetadata"].as_bool(),
+        },
+        ..Config::default()
+    }
+}
+
+/// `source` without the comments and trailing commas tsconfig.json may have.
+fn strip_jsonc(source: &str) -> String {
+    let mut json = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => json.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => while chars.next_if(|c| *c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (']' | '}', _) => {
+                let trimmed = json.trim_end();
+                if trimmed.ends_with(',') {
+                    json.truncate(trimmed.len() - 1);
+                }
+                json.push(c);
+            }
+            _ => {
+                in_string = c == '"';
+                json.push(c);
+            }
+        }
     }
+    json
 }
@@ -0,0 +1,87 @@
+//! Loading of `.env` files into the environment of the process.
+
+use color_eyre::eyre::{Result, WrapErr};
+use std::{collections::HashSet, env, ffi::OsString, io::ErrorKind, path::PathBuf};
+
+/// The files loaded when the config doesn't list any.
+pub const DEFAULT_ENV_FILES: [&str; 2] = [".env", ".env.local"];
+
+/// Sets the variables of the `optional` files, skipped when missing, then of the `required`
+/// ones. Later files override earlier ones, but none overrides a variable the process was
+/// started with.
+pub fn load_env_files(optional: &[PathBuf], required: &[PathBuf]) -> Result<()> {
+    let inherited: HashSet<OsString> = env::vars_os().map(|(key, _)| key).collect();
+    let files = optional
+        .iter()
+        .map(|path| (path, false))
+        .chain(required.iter().map(|path| (path, true)));
+    for (path, required) in files {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => continue,
+            Err(e) => return Err(e).wrap_err_with(|| format!("Failed to read {}", path.display())),
+        };
+        for (key, value) in parse(&source) {
+            if !inherited.contains(OsString::from(&key).as_os_str()) {
+                env::set_var(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The variables of a `.env` file: `KEY=value` lines, optionally prefixed by `export`,
+/// with `#` comments. Single-quoted values are taken as they are, double-quoted ones
+/// unescape `\n`, and both may span lines.
+pub fn parse(source: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    let mut rest = source;
+    while !rest.is_empty() {
+        let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+        rest = next;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            continue;
+        }
+        let value = value.trim_start();
+        let quote = match value.chars().next() {
+            Some(quote @ ('"' | '\'' | '`')) => quote,
+            _ => {
+                // unquoted values end at a comment
+                let value = match value.find(" #") {
+                    Some(comment) => &value[..comment],
+                    None => value,
+                };
+                vars.push((key.to_string(), value.trim_end().to_string()));
+                continue;
+            }
+        };
+        // a quoted value may go on over the following lines until its closing quote
+        let mut quoted = value[1..].to_string();
+        while !quoted.contains(quote) && !rest.is_empty() {
+            let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+            rest = next;
+            quoted.push('\n');
+            quoted.push_str(line.trim_end_matches('\r'));
+        }
+        let value = match quoted.find(quote) {
+            Some(end) => &quoted[..end],
+            None => &quoted[..],
+        };
+        let value = if quote == '"' {
+            value.replace("\\n", "\n")
+        } else {
+            value.to_string()
+        };
+        vars.push((key.to_string(), value));
+    }
+    vars
+}
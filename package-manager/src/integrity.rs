@@ -0,0 +1,136 @@
+//! Verifying downloaded tarballs against the hashes the registry publishes in `dist`.
+//!
+//! `integrity` is a subresource integrity string, `sha512-<base64>` for most packages,
+//! and may list several hashes of which the strongest one known is checked. Packages
+//! published before npm recorded it only have `shasum`, the hex sha1 of the tarball.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::{
+    eyre::{eyre, Result},
+    Section,
+};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::package::Dist;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sha1" => Some(Self::Sha1),
+            "sha256" => Some(Self::Sha256),
+            "sha384" => Some(Self::Sha384),
+            "sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// The hash a tarball is expected to have.
+#[derive(Clone, Debug)]
+pub struct Integrity {
+    pub algorithm: Algorithm,
+    pub digest: Vec<u8>,
+}
+
+impl Integrity {
+    /// The strongest hash of `dist`, if it has one this can check.
+    pub fn from_dist(dist: &Dist) -> Option<Self> {
+        let integrity = dist.integrity.iter().flat_map(|x| x.split_whitespace());
+        let from_integrity = integrity
+            .filter_map(|entry| {
+                let (algorithm, digest) = entry.split_once('-')?;
+                // options may follow the digest, `sha512-<base64>?<options>`
+                let digest = digest.split('?').next()?;
+                Some(Self {
+                    algorithm: Algorithm::parse(algorithm)?,
+                    digest: STANDARD.decode(digest).ok()?,
+                })
+            })
+            .max_by_key(|x| x.algorithm);
+
+        from_integrity.or_else(|| {
+            Some(Self {
+                algorithm: Algorithm::Sha1,
+                digest: decode_hex(dist.shasum.as_deref()?)?,
+            })
+        })
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self.algorithm {
+            Algorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha384 => Hasher::Sha384(Sha384::new()),
+            Algorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    /// Checks the digest `hasher` computed over the whole tarball of `id`.
+    pub fn verify(&self, id: &str, hasher: Hasher) -> Result<()> {
+        let digest = hasher.finalize();
+        if digest == self.digest {
+            return Ok(());
+        }
+        Err(eyre!("The tarball of {id} doesn't match its integrity"))
+            .with_note(|| format!("Expected {}", self.to_sri(&self.digest)))
+            .with_note(|| format!("Received {}", self.to_sri(&digest)))
+            .with_suggestion(|| "The registry or a proxy served a different tarball")
+    }
+
+    fn to_sri(&self, digest: &[u8]) -> String {
+        let algorithm = match self.algorithm {
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha384 => "sha384",
+            Algorithm::Sha512 => "sha512",
+        };
+        format!("{algorithm}-{}", STANDARD.encode(digest))
+    }
+}
+
+/// Hashes a tarball as it is downloaded.
+pub enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(x) => x.update(data),
+            Self::Sha256(x) => x.update(data),
+            Self::Sha384(x) => x.update(data),
+            Self::Sha512(x) => x.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha1(x) => x.finalize().to_vec(),
+            Self::Sha256(x) => x.finalize().to_vec(),
+            Self::Sha384(x) => x.finalize().to_vec(),
+            Self::Sha512(x) => x.finalize().to_vec(),
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
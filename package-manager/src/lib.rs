@@ -7,12 +7,15 @@ pub mod cache;
 pub mod cli;
 pub mod commands;
 pub mod config;
+pub mod env_file;
+pub mod integrity;
 pub mod npm;
 pub mod package;
 pub mod plan;
 pub mod progress;
 pub mod resolve;
 pub mod scoped_path;
+pub mod store;
 pub mod util;
 pub mod watch;
 
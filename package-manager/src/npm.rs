@@ -1,6 +1,4 @@
 use async_compression::tokio::bufread::GzipDecoder;
-use async_recursion::async_recursion;
-use cached::proc_macro::cached;
 use color_eyre::{
     eyre::{eyre, ContextCompat, Result},
     Report,
@@ -123,10 +121,21 @@ pub async fn fetch_package(name: &str) -> Result<Arc<RegistryResponse>> {
         .map_err(Report::msg)
 }
 
+/// Resolves `d` to a version and its metadata. Concurrent requests for the same
+/// specifier share one resolution.
 #[tracing::instrument]
-#[cached(result)]
-#[async_recursion]
 pub async fn fetch_versioned_package(d: PackageSpecifier) -> Result<(Version, Arc<PackageInfo>)> {
+    static CACHE: LazyLock<Cache<PackageSpecifier, ArcResult<(Version, Arc<PackageInfo>)>>> =
+        LazyLock::new(|| {
+            Cache::new(|key: PackageSpecifier| async move {
+                resolve_versioned_package(key).await.map_err(Arc::new)
+            })
+        });
+
+    CACHE.get(d).await.map_err(Report::msg)
+}
+
+async fn resolve_versioned_package(d: PackageSpecifier) -> Result<(Version, Arc<PackageInfo>)> {
     log_progress(&format!("Fetched {}", d.name.bright_blue()));
 
     match &d.version {
@@ -232,7 +241,7 @@ pub async fn fetch_versioned_package(d: PackageSpecifier) -> Result<(Version, Ar
 pub struct DependencyTree {
     #[serde(flatten)]
     pub root: Dependency,
-    pub children: FxHashMap<CompactString, DependencyTree>,
+    pub children: BTreeMap<CompactString, DependencyTree>,
 }
 
 impl DependencyTree {
@@ -32,6 +32,20 @@ pub struct PackageMetadata {
     pub os: PlatformMap,
     pub cpu: PlatformMap,
     pub scripts: FxHashMap<CompactString, Value>,
+    #[serde(
+        deserialize_with = "string_map",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub engines: BTreeMap<CompactString, CompactString>,
+    #[serde(
+        deserialize_with = "string_map",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub peer_dependencies: BTreeMap<CompactString, CompactString>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub peer_dependencies_meta: BTreeMap<CompactString, PeerDependencyMeta>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub config: BTreeMap<CompactString, Value>,
 }
 
 impl PackageMetadata {
@@ -49,6 +63,9 @@ impl PackageMetadata {
                 .iter()
                 .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_compact_string())))
                 .collect(),
+            engines: self.engines,
+            peer_dependencies: self.peer_dependencies,
+            peer_dependencies_meta: self.peer_dependencies_meta,
         }
     }
 }
@@ -71,6 +88,32 @@ pub struct PackageInfo {
     pub bin: Option<Bin>,
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub scripts: BTreeMap<CompactString, CompactString>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub engines: BTreeMap<CompactString, CompactString>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub peer_dependencies: BTreeMap<CompactString, CompactString>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub peer_dependencies_meta: BTreeMap<CompactString, PeerDependencyMeta>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default, Hash)]
+#[serde(default)]
+pub struct PeerDependencyMeta {
+    pub optional: bool,
+}
+
+/// A map of strings, dropping what isn't one. Old packages list `engines` as an array.
+fn string_map<'de, D>(deserializer: D) -> Result<BTreeMap<CompactString, CompactString>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Object(map) => map
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.to_compact_string(), v.as_str()?.to_compact_string())))
+            .collect(),
+        _ => BTreeMap::new(),
+    })
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Deserialize)]
@@ -116,6 +159,10 @@ pub enum Bin {
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug, Default, PartialOrd, Ord)]
 pub struct Dist {
     pub tarball: CompactString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<CompactString>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shasum: Option<CompactString>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
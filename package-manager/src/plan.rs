@@ -8,37 +8,47 @@ use futures::{StreamExt, TryStreamExt};
 use owo_colors::OwoColorize;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::fs::{create_dir_all, exists, metadata, read_dir, remove_dir_all, set_permissions, File};
+use std::fs::{create_dir_all, exists, remove_dir_all, set_permissions, File};
 use std::{
+    collections::BTreeMap,
     fs::Permissions,
     io::{self, ErrorKind},
-    path::{Path, PathBuf},
+    path::PathBuf,
     sync::{Arc, LazyLock},
 };
 use tap::Pipe;
 use tokio::{sync::Semaphore, task::JoinHandle};
-use tokio_tar::Archive;
 use tokio_util::io::StreamReader;
 
 use crate::{
     cache::Cache,
     config::{client_auth, read_config},
+    integrity::Integrity,
     npm::{Dependency, DependencyTree},
     package::PackageMetadata,
-    progress::{log_progress, log_verbose, log_warning},
+    progress::{log_progress, log_verbose},
     scoped_path::scoped_join,
-    util::{retry, VersionSpecifier, CLIENT, CLIENT_LIMIT},
+    store,
+    util::{retry, spawn_blocking_limited, VersionSpecifier, CLIENT, CLIENT_LIMIT},
 };
 
+/// The version of the format of `plan.json`, an installation planned by another one is
+/// installed again.
+pub const PLAN_VERSION: u32 = 1;
+
+/// What is installed in node_modules, sorted so `plan.json` only changes along with it.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct Plan {
-    #[serde(flatten)]
-    pub trees: FxHashMap<CompactString, DependencyTree>,
+    pub version: u32,
+    pub trees: BTreeMap<CompactString, DependencyTree>,
 }
 
 impl Plan {
-    pub fn new(trees: FxHashMap<CompactString, DependencyTree>) -> Self {
-        Self { trees }
+    pub fn new(trees: BTreeMap<CompactString, DependencyTree>) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            trees,
+        }
     }
 
     pub fn satisfies(&self, package: &PackageMetadata) -> bool {
@@ -58,7 +68,7 @@ impl Plan {
     }
 }
 
-pub fn tree_size(trees: &FxHashMap<CompactString, DependencyTree>) -> usize {
+pub fn tree_size(trees: &BTreeMap<CompactString, DependencyTree>) -> usize {
     trees.len()
         + trees
             .values()
@@ -68,11 +78,8 @@ pub fn tree_size(trees: &FxHashMap<CompactString, DependencyTree>) -> usize {
 
 #[tracing::instrument]
 async fn download_package(dep: &Dependency) -> Result<()> {
-    let target_path = scoped_join(".xmas/store", dep.id())?;
-
-    create_dir_all(&target_path)?;
-
-    if metadata(target_path.join("_complete")).is_ok() {
+    store::init().await?;
+    if store::has_package(&dep.id())? {
         log_verbose(&format!("Skipped downloading {}", dep.id()));
         return Ok(());
     }
@@ -98,6 +105,12 @@ async fn download_package(dep: &Dependency) -> Result<()> {
         .bytes_stream()
         .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
 
+    let integrity = Integrity::from_dist(&dep.dist);
+    if integrity.is_none() {
+        log_verbose(&format!("No integrity to verify {} with", dep.id()));
+    }
+    let mut hasher = integrity.as_ref().map(Integrity::hasher);
+
     let res = {
         let (tx, rx) = async_channel::unbounded();
         tokio::spawn(async move {
@@ -108,21 +121,27 @@ async fn download_package(dep: &Dependency) -> Result<()> {
             }
             drop(permit);
         });
-        rx.into_stream()
+        rx.into_stream().inspect_ok(|buf| {
+            if let Some(hasher) = &mut hasher {
+                hasher.update(buf);
+            }
+        })
     };
 
     let reader = StreamReader::new(res);
-    let reader = GzipDecoder::new(reader);
-    let reader = Box::pin(reader);
-
-    let mut archive = Archive::new(reader);
+    let mut reader = Box::pin(GzipDecoder::new(reader));
 
-    archive
-        .unpack(&target_path)
+    let index = store::unpack(reader.as_mut(), &dep.id())
         .await
         .map_err(|e| eyre!("{e:?}"))?;
+    // the archive may end before the tarball does, the rest is hashed too
+    tokio::io::copy(&mut reader.as_mut().get_pin_mut(), &mut tokio::io::sink()).await?;
+    drop(reader);
 
-    File::create(target_path.join("_complete"))?;
+    if let (Some(integrity), Some(hasher)) = (integrity, hasher) {
+        integrity.verify(&dep.id(), hasher)?;
+    }
+    store::add_package(&dep.id(), index).await?;
 
     log_progress(&format!("Downloaded {}", dep.id().bright_blue()));
 
@@ -139,32 +158,6 @@ pub async fn download_package_shared(dep: Dependency) -> Result<()> {
     CACHE.get(dep).await.map_err(Report::msg)
 }
 
-fn hardlink_dir(src: PathBuf, dst: PathBuf) -> Result<()> {
-    std::fs::create_dir_all(&dst)?;
-    let dir = std::fs::read_dir(src)?;
-    for entry in dir {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            hardlink_dir(entry.path(), dst.join(entry.file_name()))?;
-        } else {
-            std::fs::hard_link(entry.path(), dst.join(entry.file_name()))?;
-        }
-    }
-    Ok(())
-}
-
-fn get_package_src(src: &Path) -> Result<PathBuf> {
-    let mut dir = read_dir(src)?;
-    while let Some(entry) = dir.next().transpose()? {
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            return Ok(entry.path());
-        }
-    }
-    Err(Report::msg("No package src found"))
-}
-
 #[tracing::instrument]
 pub async fn install_package(prefix: &[CompactString], dep: &Dependency) -> Result<()> {
     download_package_shared(dep.clone()).await?;
@@ -191,13 +184,14 @@ pub async fn install_package(prefix: &[CompactString], dep: &Dependency) -> Resu
         return Ok(());
     }
 
-    let _ = remove_dir_all(&target_path);
-
-    let src_path = scoped_join(".xmas/store", dep.id())?;
-
-    hardlink_dir(get_package_src(&src_path)?, target_path)?;
-
-    File::create(&install_marker)?;
+    let id = dep.id();
+    spawn_blocking_limited(move || {
+        let _ = remove_dir_all(&target_path);
+        store::link_package(&id, &target_path)?;
+        File::create(&install_marker)?;
+        Ok(())
+    })
+    .await?;
 
     log_progress(&format!("Installed {}", dep.id().bright_blue()));
 
@@ -322,12 +316,6 @@ pub async fn setup_bins(plan: &Plan) -> Result<()> {
                         }
                     }
                 }
-
-                if read_config().await?.disallow_install_scripts {
-                    log_warning(
-                        &format!("Package {} may require install scripts; consider disabling `disallow_install_scripts`", dep.id())
-                    );
-                }
             }
         }
     }
@@ -3,19 +3,23 @@ use crate::npm::{Dependency, DependencyTree};
 use crate::package::{PackageInfo, PackageSpecifier, VersionedPackageInfo};
 use crate::plan::download_package_shared;
 use crate::progress::log_verbose;
+use crate::util::CLIENT_LIMIT;
 use color_eyre::eyre::ContextCompat;
 use color_eyre::{Report, Section};
 use compact_str::{CompactString, ToCompactString};
-use dashmap::{DashMap, DashSet};
 use itertools::Itertools;
 use node_semver::Version;
 use owo_colors::OwoColorize;
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::{Deserialize, Serialize};
+use serde::{de, ser::SerializeStruct, Deserialize, Serialize};
 use std::collections::{BTreeMap, VecDeque};
 use std::mem::take;
 use std::sync::Arc;
-use tokio::task::JoinHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// How many packages [`Graph::append`] resolves at once.
+const RESOLVE_LIMIT: usize = CLIENT_LIMIT;
 
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct Graph {
@@ -24,95 +28,61 @@ pub struct Graph {
 }
 
 impl Graph {
+    /// Resolves `remaining` and everything they depend on, fetching registry metadata for
+    /// up to [`RESOLVE_LIMIT`] packages at once. Requests already in the graph are only
+    /// walked for their dependencies, and each one is fetched once however many
+    /// packages depend on it.
     pub async fn append(
         &mut self,
         remaining: impl Iterator<Item = PackageSpecifier>,
         download: bool,
     ) -> color_eyre::Result<()> {
-        fn queue_resolve(
-            send: async_channel::Sender<JoinHandle<color_eyre::Result<()>>>,
-            req: PackageSpecifier,
-            relations: Arc<DashMap<PackageSpecifier, VersionedPackageInfo>>,
-            seen: Arc<DashSet<PackageSpecifier>>,
-            download: bool,
-        ) -> color_eyre::Result<()> {
-            if !seen.insert(req.clone()) {
-                return Ok(());
-            }
-
-            if let Some(subpackage) = relations.get(&req) {
-                for child_req in subpackage.package.iter() {
-                    queue_resolve(
-                        send.clone(),
-                        child_req,
-                        relations.clone(),
-                        seen.clone(),
-                        download,
-                    )?;
+        let semaphore = Arc::new(Semaphore::new(RESOLVE_LIMIT));
+        let mut relations = take(&mut self.relations);
+        let mut seen = FxHashSet::default();
+        let mut queue: VecDeque<_> = remaining.collect();
+        let mut tasks = JoinSet::new();
+
+        loop {
+            while let Some(req) = queue.pop_front() {
+                if !seen.insert(req.clone()) {
+                    continue;
                 }
-
-                return Ok(());
-            }
-
-            send.clone().send(tokio::spawn(async move {
-                let (version, subpackage) = npm::fetch_versioned_package(req.clone()).await?;
-
-                if download && subpackage.supported() {
-                    tokio::spawn(download_package_shared(Dependency {
-                        name: req.name.to_compact_string(),
-                        version: version.clone(),
-                        dist: subpackage.dist.clone(),
-                        bins: subpackage.bins().into_iter().collect(),
-                        scripts: subpackage.scripts.clone(),
-                    }));
+                if let Some(known) = relations.get(&req) {
+                    queue.extend(known.package.iter());
+                    continue;
                 }
 
-                relations.insert(
-                    req.clone(),
-                    VersionedPackageInfo {
-                        package: subpackage.clone(),
-                        version,
-                    },
-                );
-
-                for child_req in subpackage.iter() {
-                    queue_resolve(
-                        send.clone(),
-                        child_req,
-                        relations.clone(),
-                        seen.clone(),
-                        download,
-                    )?;
-                }
-
-                Ok(()) as color_eyre::Result<_>
-            }));
-
-            Ok(())
-        }
-
-        let relations: Arc<DashMap<_, _>> =
-            Arc::new(take(&mut self.relations).into_iter().collect());
-
-        let (send, recv) = async_channel::unbounded();
-
-        let seen = Arc::new(DashSet::new());
-
-        for req in remaining {
-            queue_resolve(send.clone(), req, relations.clone(), seen.clone(), download)?;
-        }
+                let semaphore = semaphore.clone();
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await?;
+                    let (version, package) = npm::fetch_versioned_package(req.clone()).await?;
+                    Ok((req, VersionedPackageInfo { package, version })) as color_eyre::Result<_>
+                });
+            }
 
-        drop(send);
+            // dependencies are queued as soon as their parent resolved, whichever is first
+            let Some(resolved) = tasks.join_next().await else {
+                break;
+            };
+            let (req, resolved) = resolved??;
+
+            if download && resolved.package.supported() {
+                tokio::spawn(download_package_shared(Dependency {
+                    name: req.name.to_compact_string(),
+                    version: resolved.version.clone(),
+                    dist: resolved.package.dist.clone(),
+                    bins: resolved.package.bins().into_iter().collect(),
+                    scripts: resolved.package.scripts.clone(),
+                }));
+            }
 
-        while let Ok(f) = recv.recv().await {
-            f.await??;
+            queue.extend(resolved.package.iter());
+            relations.insert(req, resolved);
         }
 
-        self.relations = relations
-            .iter()
-            .filter(|x| seen.contains(x.key()))
-            .map(|x| (x.key().clone(), x.value().clone()))
-            .collect();
+        relations.retain(|req, _| seen.contains(req));
+        self.relations = relations;
 
         Ok(())
     }
@@ -262,10 +232,67 @@ impl Graph {
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+/// The version of the lockfile format [`Lockfile`] writes.
+pub const LOCKFILE_VERSION: u32 = 2;
+
+/// The resolved graph, with what it takes to install it again: the tarball each
+/// package was resolved to, its integrity, and the engines, platforms and peers it
+/// declares.
+///
+/// Version 1 lockfiles, a flat map of `[version, package]` pairs, are still read.
+#[derive(Default)]
 pub struct Lockfile {
+    pub relations: BTreeMap<PackageSpecifier, LockedPackage>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub version: Version,
     #[serde(flatten)]
-    pub relations: BTreeMap<PackageSpecifier, (Version, PackageInfo)>,
+    pub package: PackageInfo,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LockfileFormat {
+    V2 {
+        version: u32,
+        packages: BTreeMap<PackageSpecifier, LockedPackage>,
+    },
+    V1(BTreeMap<PackageSpecifier, (Version, PackageInfo)>),
+}
+
+impl Serialize for Lockfile {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut lockfile = serializer.serialize_struct("Lockfile", 2)?;
+        lockfile.serialize_field("version", &LOCKFILE_VERSION)?;
+        lockfile.serialize_field("packages", &self.relations)?;
+        lockfile.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Lockfile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let relations = match LockfileFormat::deserialize(deserializer)? {
+            LockfileFormat::V2 { version, .. } if version > LOCKFILE_VERSION => {
+                return Err(de::Error::custom(format!(
+                    "Lockfile version {version} is newer than this version of xmas supports"
+                )));
+            }
+            LockfileFormat::V2 { packages, .. } => packages,
+            LockfileFormat::V1(relations) => relations
+                .into_iter()
+                .map(|(req, (version, package))| (req, LockedPackage { version, package }))
+                .collect(),
+        };
+        Ok(Self { relations })
+    }
 }
 
 impl Lockfile {
@@ -274,7 +301,15 @@ impl Lockfile {
             relations: graph
                 .relations
                 .into_iter()
-                .map(|(req, pkg)| (req, (pkg.version, (*pkg.package).clone())))
+                .map(|(req, pkg)| {
+                    (
+                        req,
+                        LockedPackage {
+                            version: pkg.version,
+                            package: (*pkg.package).clone(),
+                        },
+                    )
+                })
                 .collect(),
         }
     }
@@ -288,8 +323,8 @@ impl Lockfile {
                     (
                         req,
                         VersionedPackageInfo {
-                            package: Arc::new(pkg.1),
-                            version: pkg.0,
+                            package: Arc::new(pkg.package),
+                            version: pkg.version,
                         },
                     )
                 })
@@ -2,7 +2,9 @@
 // This single file has been extracted to ensure compatibility with multiple platforms.
 
 use std::io::{Error, ErrorKind, Result};
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
+
+use xmas_vsys::path::PathStyle;
 
 // Follow the same configuration as
 // [secure_join](https://github.com/cyphar/filepath-securejoin/blob/master/join.go#L51)
@@ -13,32 +15,31 @@ fn do_scoped_resolve<R: AsRef<Path>, U: AsRef<Path>>(
     unsafe_path: U,
 ) -> Result<(PathBuf, PathBuf)> {
     let root = root.as_ref().canonicalize()?;
+    // split like the `path` module of the runtime does, so `\` separates segments
+    // and drive or UNC roots are recognized on Windows
+    let style = PathStyle::NATIVE;
 
     let mut nlinks = 0u32;
-    let mut curr_path = unsafe_path.as_ref().to_path_buf();
+    let mut curr_path = unsafe_path.as_ref().to_string_lossy().into_owned();
     'restart: loop {
-        let mut subpath = PathBuf::new();
-        let mut iter = curr_path.components();
+        if !style.device(&curr_path).is_empty() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("Invalid path prefix in: {}", unsafe_path.as_ref().display()),
+            ));
+        }
 
-        'next_comp: while let Some(comp) = iter.next() {
-            match comp {
-                // Linux paths don't have prefixes.
-                Component::Prefix(_) => {
-                    return Err(Error::new(
-                        ErrorKind::Other,
-                        format!("Invalid path prefix in: {}", unsafe_path.as_ref().display()),
-                    ));
-                }
-                // `RootDir` should always be the first component, and Path::components() ensures
-                // that.
-                Component::RootDir | Component::CurDir => {
-                    continue 'next_comp;
-                }
-                Component::ParentDir => {
+        let mut subpath = PathBuf::new();
+        // the root is skipped, the path is evaluated relative to `root` anyway
+        let segments: Vec<&str> = style.segments(&curr_path).collect();
+        for (i, segment) in segments.iter().enumerate() {
+            match *segment {
+                "." => {}
+                ".." => {
                     subpath.pop();
                 }
-                Component::Normal(n) => {
-                    let path = root.join(&subpath).join(n);
+                name => {
+                    let path = root.join(&subpath).join(name);
                     if let Ok(v) = path.read_link() {
                         nlinks += 1;
                         if nlinks > MAX_SYMLINK_DEPTH {
@@ -50,14 +51,16 @@ fn do_scoped_resolve<R: AsRef<Path>, U: AsRef<Path>>(
                                 ),
                             ));
                         }
-                        curr_path = if v.is_absolute() {
-                            v.join(iter.as_path())
+                        let rest: PathBuf = segments[i + 1..].iter().collect();
+                        let next = if v.is_absolute() {
+                            v.join(rest)
                         } else {
-                            subpath.join(v).join(iter.as_path())
+                            subpath.join(v).join(rest)
                         };
+                        curr_path = next.to_string_lossy().into_owned();
                         continue 'restart;
                     } else {
-                        subpath.push(n);
+                        subpath.push(name);
                     }
                 }
             }
@@ -0,0 +1,252 @@
+//! The content-addressable store, shared by the projects of the user.
+//!
+//! It lives in the cache directory of the platform unless `store_dir` is configured,
+//! `.xmas/store` keeps a project to itself.
+//!
+//! Tarballs are unpacked in a single pass: every file is hashed while it is written to
+//! `cas/<first two hex digits>/<rest of the sha256>`, and each package gets an index of
+//! its paths and their hashes. Installing a package hard links the indexed files, so
+//! nothing is read back and a file shared by several packages is stored once.
+//!
+//! Writing and linking run on the blocking pool through [`spawn_blocking_limited`], the
+//! tarball itself is decoded on the async runtime as it arrives.
+
+use color_eyre::eyre::{eyre, Result};
+use futures::TryStreamExt;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{create_dir_all, exists, hard_link, rename};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task::JoinSet;
+use tokio_tar::Archive;
+
+use crate::config::read_config;
+use crate::progress::log_warning;
+use crate::scoped_path::scoped_join;
+use crate::util::spawn_blocking_limited;
+
+/// The store of projects that opted out of the shared one, and of the user when the
+/// platform has no cache directory.
+const PROJECT_STORE_DIR: &str = ".xmas/store";
+
+static STORE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The store in the cache directory of the platform, `$XDG_CACHE_HOME/xmas/store`,
+/// `~/Library/Caches/xmas/store` or `%LOCALAPPDATA%\xmas\store`.
+pub fn default_store_dir() -> Option<PathBuf> {
+    let cache = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home::home_dir().map(|home| home.join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home::home_dir().map(|home| home.join(".cache")))
+    };
+    cache.map(|dir| dir.join("xmas").join("store"))
+}
+
+/// The store the config asks for, a relative `store_dir` is in the project.
+pub async fn configured_store_dir() -> Result<PathBuf> {
+    Ok(match read_config().await?.store_dir {
+        Some(dir) => dir,
+        None => default_store_dir().unwrap_or_else(|| PathBuf::from(PROJECT_STORE_DIR)),
+    })
+}
+
+/// Creates the configured store, which the process uses from then on.
+pub async fn init() -> Result<()> {
+    if STORE_DIR.get().is_none() {
+        let dir = configured_store_dir().await?;
+        create_dir_all(&dir)?;
+        let _ = STORE_DIR.set(dir);
+    }
+    Ok(())
+}
+
+fn store_dir() -> &'static Path {
+    STORE_DIR
+        .get()
+        .map_or(Path::new(PROJECT_STORE_DIR), |dir| dir.as_path())
+}
+
+/// The files of an unpacked package, by their path inside the package.
+#[derive(Serialize, Deserialize, Default)]
+pub struct PackageIndex {
+    pub files: BTreeMap<String, IndexedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub hash: String,
+    #[serde(default)]
+    pub executable: bool,
+}
+
+fn index_path(id: &str) -> Result<PathBuf> {
+    let dir = store_dir().join("index");
+    create_dir_all(&dir)?;
+    let path = scoped_join(dir, format!("{id}.json"))?;
+    // scoped ids are nested where `/` isn't the separator they are escaped from
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Where a file with `hash` is stored. Hard links share their mode, so executables
+/// are kept apart from the same content without the bit.
+fn cas_path(hash: &str, executable: bool) -> PathBuf {
+    let (dir, rest) = hash.split_at(2);
+    let name = if executable {
+        format!("{rest}-exec")
+    } else {
+        rest.to_string()
+    };
+    store_dir().join("cas").join(dir).join(name)
+}
+
+/// A path in the `tmp` directory of the store nothing else writes to.
+fn temp_path() -> Result<PathBuf> {
+    let dir = store_dir().join("tmp");
+    create_dir_all(&dir)?;
+    let name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    Ok(dir.join(name))
+}
+
+/// Whether the package `id` is unpacked, its index is written last.
+pub fn has_package(id: &str) -> Result<bool> {
+    Ok(exists(index_path(id)?)?)
+}
+
+/// Unpacks the tarball read from `reader` into the store as the package `id`. It is
+/// only found once [`add_package`] records the returned index.
+pub async fn unpack(reader: impl AsyncRead + Unpin + Send, id: &str) -> Result<PackageIndex> {
+    let mut archive = Archive::new(reader);
+    let mut entries = archive.entries()?;
+    let mut files = JoinSet::new();
+
+    while let Some(mut entry) = entries.try_next().await? {
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path()?.into_owned();
+        let Some(path) = package_path(&entry_path) else {
+            log_warning(&format!(
+                "Skipped {} in {id}, it is outside of the package",
+                entry_path.display()
+            ));
+            continue;
+        };
+        let executable = entry.header().mode().is_ok_and(|mode| mode & 0o111 != 0);
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).await?;
+        // the next entries are decoded while this one is written
+        files.spawn(spawn_blocking_limited(move || {
+            let hash = store_file(&contents, executable)?;
+            Ok((path, IndexedFile { hash, executable }))
+        }));
+    }
+
+    let mut index = PackageIndex::default();
+    while let Some(file) = files.join_next().await {
+        let (path, file) = file??;
+        index.files.insert(path, file);
+    }
+    Ok(index)
+}
+
+/// Records the unpacked package `id`, once its tarball is verified.
+pub async fn add_package(id: &str, index: PackageIndex) -> Result<()> {
+    let index = serde_json::to_vec(&index)?;
+    let index_path = index_path(id)?;
+    spawn_blocking_limited(move || {
+        let temp = temp_path()?;
+        std::fs::write(&temp, index)?;
+        rename(temp, index_path)?;
+        Ok(())
+    })
+    .await
+}
+
+/// The path of a tarball entry inside its package, without the directory the tarball
+/// wraps the package in.
+fn package_path(path: &Path) -> Option<String> {
+    let mut components = path.components();
+    components.next()?;
+    let segments = components
+        .map(|component| match component {
+            Component::Normal(segment) => segment.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+/// Writes `contents` to the store, returning their hash.
+fn store_file(contents: &[u8], executable: bool) -> Result<String> {
+    let temp = temp_path()?;
+    std::fs::write(&temp, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if executable { 0o755 } else { 0o644 };
+        std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(mode))?;
+    }
+
+    let hash = format!("{:x}", Sha256::digest(contents));
+    let target = cas_path(&hash, executable);
+    if exists(&target)? {
+        std::fs::remove_file(&temp)?;
+    } else {
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)?;
+        }
+        // another download may have stored the same file meanwhile
+        if let Err(e) = rename(&temp, &target) {
+            std::fs::remove_file(&temp)?;
+            if !exists(&target)? {
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+/// Links the files of the package `id` into `target`.
+pub fn link_package(id: &str, target: &Path) -> Result<()> {
+    let index = std::fs::read(index_path(id)?)?;
+    let index: PackageIndex =
+        serde_json::from_slice(&index).map_err(|e| eyre!("Invalid index of {id}: {e}"))?;
+
+    create_dir_all(target)?;
+    for (path, file) in &index.files {
+        let dst = target.join(path);
+        if let Some(parent) = dst.parent() {
+            create_dir_all(parent)?;
+        }
+        let src = cas_path(&file.hash, file.executable);
+        // links can't cross devices, the file is copied then
+        if hard_link(&src, &dst).is_err() {
+            std::fs::copy(&src, &dst)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
-use color_eyre::eyre::{Context, Result};
-use color_eyre::Report;
+use color_eyre::eyre::{eyre, Context, Result};
+use color_eyre::{Report, Section};
 use compact_str::{CompactString, ToCompactString};
 use node_semver::{Range, Version};
 use reqwest::{Client, ClientBuilder, Url};
@@ -16,14 +16,39 @@ use std::{
 };
 use tokio::fs::{read_to_string, File};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::{OnceCell, Semaphore};
 use tracing::instrument;
 
+use crate::config::read_config;
 use crate::package::PackageMetadata;
 use crate::progress::log_warning;
 use crate::resolve::{Graph, Lockfile};
 
 pub const CLIENT_LIMIT: usize = 100;
 
+/// Runs the blocking filesystem work `f` on the blocking pool, at most
+/// `install_concurrency` jobs at once so large installs neither starve the async
+/// runtime nor start a thread per file.
+pub async fn spawn_blocking_limited<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    static S: OnceCell<Semaphore> = OnceCell::const_new();
+    let semaphore = S
+        .get_or_try_init(|| async {
+            let limit = match read_config().await?.install_concurrency {
+                Some(limit) => limit.max(1),
+                None => std::thread::available_parallelism().map_or(4, |n| n.get()),
+            };
+            Ok(Semaphore::new(limit)) as Result<_>
+        })
+        .await?;
+    let _permit = semaphore.acquire().await?;
+
+    tokio::task::spawn_blocking(f).await?
+}
+
 pub static CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
 pub static CLIENT_Z: LazyLock<Client> = LazyLock::new(|| {
     ClientBuilder::new()
@@ -170,10 +195,13 @@ pub async fn read_json<T: DeserializeOwned>(path: impl AsRef<Path> + std::fmt::D
 }
 
 pub async fn write_json<T: Serialize>(path: impl AsRef<Path>, data: T) -> Result<()> {
+    write_string(path, &serde_json::to_string_pretty(&data)?).await
+}
+
+async fn write_string(path: impl AsRef<Path>, contents: &str) -> Result<()> {
     let mut file = File::create(path).await?;
 
-    file.write_all(serde_json::to_string_pretty(&data)?.as_bytes())
-        .await?;
+    file.write_all(contents.as_bytes()).await?;
 
     file.flush().await?;
 
@@ -181,8 +209,49 @@ pub async fn write_json<T: Serialize>(path: impl AsRef<Path>, data: T) -> Result
 }
 
 pub async fn load_graph_from_lockfile() -> Graph {
-    let lockfile: Lockfile = read_json("xmas.lock").await.unwrap_or_default();
+    let lockfile = match read_json::<Lockfile>("xmas.lock").await {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            if Path::new("xmas.lock").exists() {
+                log_warning(&format!("Ignored xmas.lock, it can't be read: {e}"));
+            }
+            Lockfile::default()
+        }
+    };
     lockfile.into_graph()
 }
 
+/// Writes the lockfile of `graph`. With `frozen`, nothing is written and it fails if
+/// xmas.lock doesn't already say the same.
+pub async fn save_lockfile(graph: &Graph, frozen: bool) -> Result<()> {
+    let lockfile = serde_json::to_string_pretty(&Lockfile::new(graph.clone()))?;
+    if !frozen {
+        return write_string("xmas.lock", &lockfile).await;
+    }
+    match read_to_string("xmas.lock").await {
+        Ok(current) if current == lockfile => Ok(()),
+        Ok(_) => Err(eyre!("xmas.lock is out of date"))
+            .with_suggestion(|| "Run xmas install without --frozen-lockfile and commit xmas.lock"),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(eyre!("xmas.lock doesn't exist"))
+            .with_suggestion(|| "Run xmas install without --frozen-lockfile and commit xmas.lock"),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub type ArcResult<T, E = Report> = Result<T, Arc<E>>;
+
+/// The Levenshtein distance of `a` and `b`, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -1,7 +1,7 @@
 use clap::Parser;
 use colored::*;
 use rsquickjs::prelude::Rest;
-use rsquickjs::{AsyncContext, AsyncRuntime, CatchResultExt, Value};
+use rsquickjs::{context::EvalOptions, AsyncContext, AsyncRuntime, CatchResultExt, Promise, Value};
 use rustyline::completion::FilenameCompleter;
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
@@ -9,8 +9,8 @@ use rustyline::hint::HistoryHinter;
 use rustyline::validate::MatchingBracketValidator;
 use rustyline::{Completer, Helper, Hinter, Validator};
 use rustyline::{CompletionType, Config, EditMode, Editor};
-use std::io::stdout;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::{SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
@@ -19,8 +19,9 @@ use xmas_js_modules::console::write_log;
 use xmas_js_modules::module::package::loader::PackageLoader;
 use xmas_js_modules::module::package::resolver::PackageResolver;
 use xmas_js_modules::permissions::Permissions;
-use xmas_js_modules::utils::ctx::CtxExtension;
+use xmas_js_modules::utils::ctx::{until_deadline, CtxExtension};
 use xmas_js_modules::utils::result::ResultExt;
+use xmas_vsys::StdStream;
 
 /// Transform static import statements to dynamic import for REPL compatibility
 /// - `import * as name from "module"` -> `const name = await import("module")`
@@ -226,10 +227,12 @@ fn print_version() {
     );
 }
 
-pub async fn repl() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::Subscriber::builder()
+/// Runs the interactive shell, aborting any input that runs longer than `timeout`.
+pub async fn repl(timeout: Option<Duration>) -> anyhow::Result<()> {
+    // the runtime may have set up its own already
+    let _ = tracing_subscriber::fmt::Subscriber::builder()
         .with_max_level(tracing::Level::WARN)
-        .init();
+        .try_init();
     let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
@@ -266,6 +269,19 @@ pub async fn repl() -> anyhow::Result<()> {
     runtime
         .set_loader((resolver, PackageResolver), (loader, PackageLoader))
         .await;
+    // An unhandled error must not end an interactive session
+    xmas_js_modules::process::set_unhandled_error_mode(
+        xmas_js_modules::process::UnhandledErrorMode::Warn,
+    );
+    runtime
+        .set_rejection_handler(xmas_js_modules::process::handle_rejection)
+        .await;
+    runtime
+        .set_source_map_handler(Some(xmas_js_modules::source_map::source_map_handler()))
+        .await;
+    runtime
+        .set_promise_hook(Some(xmas_js_modules::async_hooks::promise_hook_tracker()))
+        .await;
     rsquickjs::async_with!(context => |ctx| {
         let vsys = xmas_vsys::Vsys::builder()
             .permissions(Permissions::allow_all())
@@ -306,6 +322,7 @@ pub async fn repl() -> anyhow::Result<()> {
                                             verbose: true,
                                             working_dir: std::env::current_dir().ok(),
                                             immutable: false,
+                                            frozen_lockfile: false,
                                             cmd
                                         };
                                         let _ = xmas_package_manager::execute_command(&args).await;
@@ -348,17 +365,34 @@ pub async fn repl() -> anyhow::Result<()> {
                     // import { a, b } from "module" -> const { a, b } = await import("module")
                     // import name from "module" -> const { default: name } = await import("module")
                     let line = transform_import_to_dynamic(&line);
-                    let ast = xmas_js_modules::script::parse("tsx", &line, &allocator).or_throw(&ctx)?;
+                    let ast = xmas_js_modules::script::parse("tsx", &line, &allocator).map_err(|diagnostics| {
+                        xmas_js_modules::script::throw_diagnostics(&ctx, "<repl_input>.tsx", diagnostics)
+                    })?;
                     let transformed = xmas_js_modules::script::transform(
                         &format!("<repl_input>.tsx"),
                         None,
-                        false,
+                        None,
                         &allocator,
                         ast,
                     ).or_throw(&ctx)?;
-                    match ctx.eval_promise::<_>(transformed.as_bytes()) {
+                    ctx.set_deadline(timeout.map(|timeout| Instant::now() + timeout));
+                    let result = match ctx.eval_with_options::<Promise, _>(
+                        transformed.code,
+                        EvalOptions {
+                            promise: true,
+                            filename: Some("<repl_input>.js".into()),
+                            source_map: transformed.map,
+                            ..Default::default()
+                        },
+                    ) {
+                        Ok(res) => Ok(until_deadline(&ctx, res.into_future::<Value>()).await),
+                        Err(err) => Err(err),
+                    };
+                    // printing the result must not run into the deadline of the input
+                    ctx.set_deadline(None);
+                    match result {
                         Ok(res) => {
-                            res.into_future::<Value>().await
+                            res
                             .catch(&ctx)
                             .and_then(|v| {
                                 let v = if v.is_object() {
@@ -366,7 +400,7 @@ pub async fn repl() -> anyhow::Result<()> {
                                 } else {
                                     v
                                 };
-                                let _ = write_log(stdout(), &ctx, Rest(vec![v]));
+                                let _ = write_log(StdStream::Stdout, &ctx, Rest(vec![v]));
                                 Ok(())
                             })
                             .unwrap_or_else(|err| {
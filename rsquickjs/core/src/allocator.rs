@@ -5,6 +5,12 @@ use crate::qjs;
 mod rust;
 
 pub use rust::RustAllocator;
+
+#[cfg(feature = "mimalloc")]
+mod mimalloc;
+
+#[cfg(feature = "mimalloc")]
+pub use self::mimalloc::MiMallocAllocator;
 use std::boxed::Box;
 
 /// The allocator interface
@@ -0,0 +1,66 @@
+use std::ffi::c_void;
+
+use libmimalloc_sys as mi;
+
+use super::Allocator;
+
+/// The allocator which uses mimalloc
+///
+/// mimalloc keeps track of the size of its allocations itself, so unlike
+/// [`RustAllocator`](super::RustAllocator) no header is stored in front of them.
+pub struct MiMallocAllocator;
+
+unsafe impl Allocator for MiMallocAllocator {
+    fn calloc(&mut self, count: usize, size: usize) -> *mut u8 {
+        if count == 0 || size == 0 {
+            return std::ptr::null_mut();
+        }
+        unsafe { mi::mi_calloc(count, size).cast() }
+    }
+
+    fn alloc(&mut self, size: usize) -> *mut u8 {
+        unsafe { mi::mi_malloc(size).cast() }
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8) {
+        mi::mi_free(ptr.cast::<c_void>());
+    }
+
+    unsafe fn realloc(&mut self, ptr: *mut u8, new_size: usize) -> *mut u8 {
+        mi::mi_realloc(ptr.cast::<c_void>(), new_size).cast()
+    }
+
+    unsafe fn usable_size(ptr: *mut u8) -> usize {
+        mi::mi_usable_size(ptr.cast::<c_void>())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MiMallocAllocator;
+    use crate::{AsyncContext, AsyncRuntime};
+
+    #[tokio::test]
+    async fn runtime_with_mimalloc() {
+        let rt = AsyncRuntime::new_with_alloc(MiMallocAllocator).unwrap();
+        let context = AsyncContext::full(&rt).await.unwrap();
+
+        context
+            .with(|ctx| {
+                let joined: String = ctx
+                    .eval(
+                        r#"
+                    const parts = [];
+                    for (let i = 0; i < 10_000; i++) {
+                        parts.push({ text: "part" + i });
+                    }
+                    parts.slice(-2).map((part) => part.text).join(",")
+                "#,
+                    )
+                    .unwrap();
+                assert_eq!(joined, "part9998,part9999");
+            })
+            .await;
+        assert!(rt.memory_stats().await.malloc_size > 0);
+    }
+}
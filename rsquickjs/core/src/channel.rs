@@ -0,0 +1,132 @@
+//! Message passing between runtimes.
+//!
+//! [`channel`] delivers anything which is `Send`, such as values serialized with the
+//! structured clone algorithm, to a receiver awaiting them on another thread or runtime.
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    sync::Arc,
+    task::{Poll, Waker},
+};
+
+use parking_lot::Mutex;
+
+use crate::StdResult;
+
+struct State<T> {
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    senders: usize,
+    closed: bool,
+}
+
+/// Creates an unbounded channel, whose receiver can be awaited from any runtime.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let state = Arc::new(Mutex::new(State {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+        closed: false,
+    }));
+    (Sender(state.clone()), Receiver(state))
+}
+
+/// The sending half of a [`channel`].
+pub struct Sender<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Sender<T> {
+    /// Queues `value`, handing it back if the receiver was dropped.
+    pub fn send(&self, value: T) -> StdResult<(), T> {
+        let mut state = self.0.lock();
+        if state.closed {
+            return Err(value);
+        }
+        state.queue.push_back(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The receiving half of a [`channel`].
+pub struct Receiver<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Receiver<T> {
+    /// Waits for the next value, `None` once every sender was dropped and the queue
+    /// is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| {
+            let mut state = self.0.lock();
+            match state.queue.pop_front() {
+                Some(value) => Poll::Ready(Some(value)),
+                None if state.senders == 0 => Poll::Ready(None),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Takes the next value if one is queued.
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.0.lock().queue.pop_front()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.closed = true;
+        state.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn channel_between_threads() {
+        let (sender, mut receiver) = channel();
+
+        let task = tokio::spawn(async move {
+            sender.send(1).unwrap();
+            sender.clone().send(2).unwrap();
+        });
+
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.recv().await, Some(2));
+        task.await.unwrap();
+        assert_eq!(receiver.recv().await, None);
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[tokio::test]
+    async fn closed_receiver() {
+        let (sender, receiver) = channel();
+        drop(receiver);
+        assert_eq!(sender.send(1), Err(1));
+    }
+}
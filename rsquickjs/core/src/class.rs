@@ -49,6 +49,17 @@ pub trait JsClass<'js>: Trace<'js> + JsLifetime<'js> + Sized {
         let _ = this;
         Ok(Value::new_undefined(params.ctx().clone()))
     }
+
+    /// Returns the element at `index`, read as `object[index]` from JavaScript, or `None` if
+    /// the class has no element there.
+    fn get_index(
+        this: &JsCell<'js, Self>,
+        ctx: &Ctx<'js>,
+        index: u32,
+    ) -> Result<Option<Value<'js>>> {
+        let _ = (this, ctx, index);
+        Ok(None)
+    }
 }
 
 /// A object which is instance of a Rust class.
@@ -1,5 +1,5 @@
 use super::{JsClass, Tracer};
-use crate::{class::JsCell, function::Params, qjs, runtime::opaque::Opaque, Value};
+use crate::{class::JsCell, function::Params, qjs, runtime::opaque::Opaque, Ctx, Value};
 use std::boxed::Box;
 use std::{any::TypeId, panic::AssertUnwindSafe, ptr::NonNull};
 
@@ -61,6 +61,55 @@ pub(crate) unsafe extern "C" fn call(
     (ptr.as_ref().v_table.call)(ptr, ctx, function, this, argc, argv, flags)
 }
 
+/// Atoms with this bit set are integers, stored in the remaining bits.
+const JS_ATOM_TAG_INT: qjs::JSAtom = 1 << 31;
+
+/// Exotic behaviour of non callable classes, resolving integer keys through the class.
+pub(crate) static CLASS_EXOTIC: qjs::JSClassExoticMethods = qjs::JSClassExoticMethods {
+    get_own_property: Some(class_get_own_property),
+    get_own_property_names: None,
+    delete_property: None,
+    define_own_property: None,
+    has_property: None,
+    get_property: None,
+    set_property: None,
+};
+
+/// FFI hook looking up own properties which the object itself doesn't have.
+unsafe extern "C" fn class_get_own_property(
+    ctx: *mut qjs::JSContext,
+    desc: *mut qjs::JSPropertyDescriptor,
+    obj: qjs::JSValue,
+    prop: qjs::JSAtom,
+) -> qjs::c_int {
+    if prop & JS_ATOM_TAG_INT == 0 {
+        return 0;
+    }
+    let rt = qjs::JS_GetRuntime(ctx);
+    let class_id = Opaque::from_runtime_ptr(rt).get_class_id();
+    // The hook can run before the class cell is attached to a new object.
+    let Some(ptr) = NonNull::new(qjs::JS_GetOpaque(obj, class_id)) else {
+        return 0;
+    };
+    let ptr = ptr.cast::<ClassCell<()>>();
+    let value = (ptr.as_ref().v_table.get_index)(ptr, ctx, prop & !JS_ATOM_TAG_INT);
+    match qjs::JS_VALUE_GET_NORM_TAG(value) {
+        qjs::JS_TAG_EXCEPTION => -1,
+        qjs::JS_TAG_UNINITIALIZED => 0,
+        _ => {
+            if let Some(desc) = desc.as_mut() {
+                desc.flags = (qjs::JS_PROP_ENUMERABLE | qjs::JS_PROP_CONFIGURABLE) as qjs::c_int;
+                desc.value = value;
+                desc.getter = qjs::JS_UNDEFINED;
+                desc.setter = qjs::JS_UNDEFINED;
+            } else {
+                qjs::JS_FreeValue(ctx, value);
+            }
+            1
+        }
+    }
+}
+
 pub(crate) type FinalizerFunc = unsafe fn(this: NonNull<ClassCell<()>>);
 pub(crate) type TraceFunc =
     for<'a> unsafe fn(this: NonNull<ClassCell<()>>, tracer: Tracer<'a, 'static>);
@@ -74,6 +123,13 @@ pub(crate) type CallFunc = for<'a> unsafe fn(
     flags: qjs::c_int,
 ) -> qjs::JSValue;
 
+/// Returns the element at the index, `JS_UNINITIALIZED` if there is none.
+pub(crate) type GetIndexFunc = unsafe fn(
+    this_ptr: NonNull<ClassCell<()>>,
+    ctx: *mut qjs::JSContext,
+    index: u32,
+) -> qjs::JSValue;
+
 pub(crate) type TypeIdFn = fn() -> TypeId;
 
 pub(crate) struct VTable {
@@ -81,6 +137,7 @@ pub(crate) struct VTable {
     finalizer: FinalizerFunc,
     trace: TraceFunc,
     call: CallFunc,
+    get_index: GetIndexFunc,
 }
 
 impl VTable {
@@ -119,6 +176,23 @@ impl VTable {
         }))
     }
 
+    unsafe fn get_index_impl<'js, C: JsClass<'js>>(
+        this_ptr: NonNull<ClassCell<()>>,
+        ctx: *mut qjs::JSContext,
+        index: u32,
+    ) -> qjs::JSValue {
+        let this_ptr = this_ptr.cast::<ClassCell<JsCell<C>>>();
+        let ctx = Ctx::from_ptr(ctx);
+
+        ctx.handle_panic(AssertUnwindSafe(|| {
+            match C::get_index(&this_ptr.as_ref().data, &ctx, index) {
+                Ok(Some(value)) => value.into_js_value(),
+                Ok(None) => qjs::JS_UNINITIALIZED,
+                Err(e) => e.throw(&ctx),
+            }
+        }))
+    }
+
     pub fn get<'js, C: JsClass<'js>>() -> &'static VTable {
         trait HasVTable {
             const VTABLE: VTable;
@@ -130,6 +204,7 @@ impl VTable {
                 finalizer: VTable::finalizer_impl::<'js, C>,
                 trace: VTable::trace_impl::<C>,
                 call: VTable::call_impl::<C>,
+                get_index: VTable::get_index_impl::<C>,
             };
         }
         &<C as HasVTable>::VTABLE
@@ -1,6 +1,7 @@
 //! Helper classes and functions for use inside the macros.
 
-use crate::{value::Constructor, Ctx, Object, Result};
+use super::{JsCell, JsClass};
+use crate::{value::Constructor, Ctx, Object, Result, Value};
 use std::marker::PhantomData;
 
 /// Trait used for borrow specialization for implementing methods without access to the class.
@@ -17,6 +18,18 @@ pub trait ConstructorCreator<'js, T>: Sized {
     }
 }
 
+/// Trait used for borrow specialization for reading indexed elements without access to the class.
+pub trait IndexGetter<'js, T: JsClass<'js>>: Sized {
+    fn get_index(
+        &self,
+        _this: &JsCell<'js, T>,
+        _ctx: &Ctx<'js>,
+        _index: u32,
+    ) -> Result<Option<Value<'js>>> {
+        Ok(None)
+    }
+}
+
 /// A helper type for borrow specialization
 #[derive(Default)]
 pub struct MethodImpl<T>(PhantomData<T>);
@@ -37,6 +50,16 @@ impl<T> ConstructorCreate<T> {
     }
 }
 
+/// A helper type for borrow specialization
+#[derive(Default)]
+pub struct IndexGet<T>(PhantomData<T>);
+
+impl<T> IndexGet<T> {
+    pub fn new() -> Self {
+        IndexGet(PhantomData)
+    }
+}
+
 /// Specialization isn't stabilized yet so in the macro we can't normally have a default
 /// implementation for class prototypes if it doesn't have an associated impl item.
 ///
@@ -61,6 +84,8 @@ impl<T> MethodImplementor<T> for &MethodImpl<T> {}
 
 impl<'js, T> ConstructorCreator<'js, T> for &ConstructorCreate<T> {}
 
+impl<'js, T: JsClass<'js>> IndexGetter<'js, T> for &IndexGet<T> {}
+
 /// A helper struct to implement [`FromJs`](crate::FromJs) for types which implement [`Clone`].
 pub struct CloneWrapper<'a, T>(pub &'a T);
 /// A helper trait to implement [`FromJs`](crate::FromJs) for types which implement [`Clone`].
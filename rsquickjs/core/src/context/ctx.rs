@@ -6,6 +6,8 @@ use std::{
     mem::{self, MaybeUninit},
     ptr::NonNull,
     result::Result as StdResult,
+    slice,
+    time::Instant,
 };
 use std::{boxed::Box, ffi::CString, vec::Vec};
 
@@ -14,9 +16,14 @@ use std::{fs, path::Path, string::String as StdString};
 use crate::AsyncContext;
 use crate::{
     markers::Invariant,
+    module::Declared,
     qjs,
-    runtime::{opaque::Opaque, UserDataError, UserDataGuard},
-    Atom, Error, FromJs, Function, IntoJs, JsLifetime, Object, Promise, Result, String, Value,
+    runtime::{
+        opaque::Opaque, raw::update_interrupt_handler, RuntimeMemoryStats, RuntimeMetrics,
+        UserDataError, UserDataGuard,
+    },
+    Atom, Error, FromJs, Function, IntoJs, JsLifetime, Module, Object, Promise, Result, String,
+    Value, WriteOptions,
 };
 
 /// Eval options.
@@ -32,6 +39,9 @@ pub struct EvalOptions {
     pub promise: bool,
     /// Filename. Ignored when calling eval_file_*.
     pub filename: Option<StdString>,
+    /// Source map of the code, handed to the runtime's source map handler under the
+    /// filename before the code runs.
+    pub source_map: Option<StdString>,
 }
 
 impl EvalOptions {
@@ -67,6 +77,7 @@ impl Default for EvalOptions {
             promise: false,
 
             filename: None,
+            source_map: None,
         }
     }
 }
@@ -133,6 +144,7 @@ impl<'js> Ctx<'js> {
         let src = source.into();
         let len = src.len();
         let src = CString::new(src)?;
+        self.get_opaque().metrics().evaluated();
         let val = qjs::JS_Eval(
             self.ctx.as_ptr(),
             src.as_ptr(),
@@ -175,6 +187,9 @@ impl<'js> Ctx<'js> {
                 c"eval_script"
             }
         };
+        if let Some(source_map) = &options.source_map {
+            self.register_source_map(file_name, source_map)?;
+        }
 
         V::from_js(self, unsafe {
             let val = self.eval_raw(source, file_name, options.to_flag())?;
@@ -200,6 +215,9 @@ impl<'js> Ctx<'js> {
                 .to_string_lossy()
                 .into_owned(),
         )?;
+        if let Some(source_map) = &options.source_map {
+            self.register_source_map(&file_name, source_map)?;
+        }
 
         V::from_js(self, unsafe {
             let val = self.eval_raw(buffer, file_name.as_c_str(), options.to_flag())?;
@@ -207,6 +225,14 @@ impl<'js> Ctx<'js> {
         })
     }
 
+    fn register_source_map(&self, file_name: &CStr, source_map: &str) -> Result<()> {
+        let file_name = file_name.to_string_lossy();
+        unsafe {
+            self.get_opaque()
+                .run_source_map_handler(self.clone(), &file_name, source_map)
+        }
+    }
+
     /// Returns the global object of this context.
     pub fn globals(&self) -> Object<'js> {
         unsafe {
@@ -247,6 +273,108 @@ impl<'js> Ctx<'js> {
         Error::Exception
     }
 
+    /// Serialize a value to QuickJS bytecode, modules are written with [`Module::write`].
+    ///
+    /// The bytecode can only be read back by the same QuickJS version.
+    pub fn write_object(&self, value: &Value<'js>, options: WriteOptions) -> Result<Vec<u8>> {
+        let mut len = MaybeUninit::uninit();
+        let buf = unsafe {
+            qjs::JS_WriteObject(
+                self.ctx.as_ptr(),
+                len.as_mut_ptr(),
+                value.as_js_value(),
+                options.to_flag(),
+            )
+        };
+        if buf.is_null() {
+            return Err(self.raise_exception());
+        }
+        let len = unsafe { len.assume_init() };
+        let obj = Vec::from(unsafe { slice::from_raw_parts(buf, len as _) });
+        unsafe { qjs::js_free(self.ctx.as_ptr(), buf as _) };
+        Ok(obj)
+    }
+
+    unsafe fn read_raw(&self, bytes: &[u8]) -> Result<qjs::JSValue> {
+        let val = qjs::JS_ReadObject(
+            self.ctx.as_ptr(),
+            bytes.as_ptr(),
+            bytes.len() as _,
+            (qjs::JS_READ_OBJ_BYTECODE | qjs::JS_READ_OBJ_REFERENCE) as i32,
+        );
+        self.handle_exception(val)
+    }
+
+    /// Deserialize a value from QuickJS bytecode written by [`Ctx::write_object`].
+    ///
+    /// Unlike [`Module::load`] the bytes are copied, so they don't have to outlive the value.
+    /// Modules are owned by the context and can't be a [`Value`], read them with
+    /// [`Ctx::read_module`] instead.
+    ///
+    /// # Safety
+    /// User must ensure that bytes handed to this function contain valid bytecode.
+    pub unsafe fn read_object(&self, bytes: &[u8]) -> Result<Value<'js>> {
+        let val = self.read_raw(bytes)?;
+        if qjs::JS_VALUE_GET_TAG(val) == qjs::JS_TAG_MODULE {
+            return Err(Error::new_from_js("module", "value"));
+        }
+        Ok(Value::from_js_value(self.clone(), val))
+    }
+
+    /// Compile a script to bytecode without running it, to run later with
+    /// [`Ctx::eval_compiled`] in this context or another one of the same QuickJS version.
+    pub fn compile<S: Into<Vec<u8>>>(
+        &self,
+        source: S,
+        options: EvalOptions,
+        write: WriteOptions,
+    ) -> Result<Vec<u8>> {
+        let file_name = match &options.filename {
+            Some(filename) => CString::new(filename.clone())?,
+            None => c"eval_script".to_owned(),
+        };
+        let flag = options.to_flag() | qjs::JS_EVAL_FLAG_COMPILE_ONLY as i32;
+        // SAFETY: the function bytecode compiled is owned by the value, freed once written
+        let function = unsafe {
+            let function = self.eval_raw(source, &file_name, flag)?;
+            Value::from_js_value(self.clone(), function)
+        };
+        self.write_object(&function, write)
+    }
+
+    /// Run a script compiled with [`Ctx::compile`], like [`Ctx::eval_with_options`] would
+    /// have run its source.
+    ///
+    /// # Safety
+    /// User must ensure that bytes handed to this function contain valid bytecode.
+    pub unsafe fn eval_compiled<V: FromJs<'js>>(&self, bytes: &[u8]) -> Result<V> {
+        let function = self.read_raw(bytes)?;
+        if qjs::JS_VALUE_GET_TAG(function) != qjs::JS_TAG_FUNCTION_BYTECODE {
+            qjs::JS_FreeValue(self.ctx.as_ptr(), function);
+            return Err(Error::new_from_js("value", "compiled script"));
+        }
+        self.get_opaque().metrics().evaluated();
+        // JS_EvalFunction takes ownership of the function
+        let val = qjs::JS_EvalFunction(self.ctx.as_ptr(), function);
+        let val = self.handle_exception(val)?;
+        V::from_js(self, Value::from_js_value(self.clone(), val))
+    }
+
+    /// Declare a module from bytecode written by [`Module::write`], without parsing its
+    /// source again.
+    ///
+    /// # Safety
+    /// User must ensure that bytes handed to this function contain valid bytecode.
+    pub unsafe fn read_module(&self, bytes: &[u8]) -> Result<Module<'js, Declared>> {
+        let val = self.read_raw(bytes)?;
+        if qjs::JS_VALUE_GET_TAG(val) != qjs::JS_TAG_MODULE {
+            qjs::JS_FreeValue(self.ctx.as_ptr(), val);
+            return Err(Error::new_from_js("value", "module"));
+        }
+        let ptr = NonNull::new(qjs::JS_VALUE_GET_PTR(val).cast()).ok_or(Error::Unknown)?;
+        Ok(Module::from_ptr(self.clone(), ptr))
+    }
+
     /// Parse json into a JavaScript value.
     pub fn json_parse<S>(&self, json: S) -> Result<Value<'js>>
     where
@@ -376,6 +504,9 @@ impl<'js> Ctx<'js> {
         let mut ptr = MaybeUninit::<*mut qjs::JSContext>::uninit();
         let rt = unsafe { qjs::JS_GetRuntime(self.ctx.as_ptr()) };
         let res = unsafe { qjs::JS_ExecutePendingJob(rt, ptr.as_mut_ptr()) };
+        if res == 1 {
+            unsafe { self.get_opaque().metrics().job_executed() };
+        }
         res != 0
     }
 
@@ -383,6 +514,22 @@ impl<'js> Ctx<'js> {
         Opaque::from_runtime_ptr(qjs::JS_GetRuntime(self.ctx.as_ptr()))
     }
 
+    /// Creates a new realm, a context on the same runtime with its own global object and
+    /// intrinsics.
+    ///
+    /// The realm shares the job queue, module loader and userdata of the runtime, so its
+    /// promises settle and its spawned futures run along with this context. Values can be
+    /// passed between the two, objects keep the prototypes of the realm that created them.
+    pub fn new_realm(&self) -> Result<Ctx<'js>> {
+        let rt = unsafe { qjs::JS_GetRuntime(self.ctx.as_ptr()) };
+        let ctx = NonNull::new(unsafe { qjs::JS_NewContext(rt) }).ok_or(Error::Allocation)?;
+        // takes over the reference `JS_NewContext` returned
+        Ok(Ctx {
+            ctx,
+            _marker: self._marker,
+        })
+    }
+
     /// Spawn future on QuickJS's task queue (same thread as JS).
     /// Use this when the future needs to access JS values.
 
@@ -507,7 +654,49 @@ impl<'js> Ctx<'js> {
     /// This runs the cyclic reference collector cycle, types which are not part of a reference cycle
     /// will be freed the momement their reference count becomes zero.
     pub fn run_gc(&self) {
-        unsafe { qjs::JS_RunGC(qjs::JS_GetRuntime(self.ctx.as_ptr())) }
+        unsafe {
+            qjs::JS_RunGC(qjs::JS_GetRuntime(self.ctx.as_ptr()));
+            self.get_opaque().metrics().gc_run();
+        }
+    }
+
+    /// Memory usage stats of the runtime this context belongs to.
+    pub fn memory_stats(&self) -> RuntimeMemoryStats {
+        let mut usage = MaybeUninit::uninit();
+        unsafe {
+            qjs::JS_ComputeMemoryUsage(qjs::JS_GetRuntime(self.ctx.as_ptr()), usage.as_mut_ptr());
+            usage.assume_init().into()
+        }
+    }
+
+    /// Activity counters of the runtime this context belongs to.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        unsafe {
+            let rt = qjs::JS_GetRuntime(self.ctx.as_ptr());
+            let opaque = self.get_opaque();
+            opaque
+                .metrics()
+                .snapshot(qjs::JS_IsJobPending(rt) as i32 != 0, opaque.spawner_len())
+        }
+    }
+
+    /// Interrupts any code the runtime executes after `deadline`, or lifts the limit with
+    /// `None`.
+    ///
+    /// The interrupt raises an uncatchable `InternalError: interrupted` and returns control
+    /// to the caller, so an infinite loop ends at the deadline. The deadline stays in place
+    /// for every later call until it is changed, code waiting on a promise is not
+    /// interrupted as nothing runs.
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        unsafe {
+            self.get_opaque().set_deadline(deadline);
+            update_interrupt_handler(qjs::JS_GetRuntime(self.ctx.as_ptr()));
+        }
+    }
+
+    /// The deadline set with [`Ctx::set_deadline`], if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        unsafe { self.get_opaque().deadline() }
     }
 
     /// Store a type in the runtime which can be retrieved later with `Ctx::userdata`.
@@ -576,6 +765,189 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn bytecode() {
+        use crate::{AsyncContext, AsyncRuntime, Module, Object, WriteOptions};
+
+        let runtime = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        let (module_bytes, object_bytes) = ctx
+            .with(|ctx| {
+                let module_bytes =
+                    Module::declare(ctx.clone(), "bytecode", "export const a = 1 + 1;")
+                        .unwrap()
+                        .write(WriteOptions::default())
+                        .unwrap();
+                let object: Object = ctx.eval("({ a: [1, 'b'] })").unwrap();
+                let object_bytes = ctx
+                    .write_object(&object.into_value(), WriteOptions::default())
+                    .unwrap();
+                (module_bytes, object_bytes)
+            })
+            .await;
+
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        ctx.with(|ctx| {
+            let (module, promise) = unsafe { ctx.read_module(&module_bytes) }
+                .unwrap()
+                .eval()
+                .unwrap();
+            promise.finish::<()>().unwrap();
+            assert_eq!(module.get::<_, i32>("a").unwrap(), 2);
+
+            let object = unsafe { ctx.read_object(&object_bytes) }.unwrap();
+            let json = ctx.json_stringify(object).unwrap().unwrap();
+            assert_eq!(json.to_string().unwrap(), r#"{"a":[1,"b"]}"#);
+            assert!(unsafe { ctx.read_object(&module_bytes) }.is_err());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn compiled_script() {
+        use crate::{AsyncContext, AsyncRuntime, WriteOptions};
+
+        let runtime = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        let bytes = ctx
+            .with(|ctx| {
+                let bytes = ctx
+                    .compile(
+                        "globalThis.ran = (globalThis.ran ?? 0) + 1; 40 + 2",
+                        Default::default(),
+                        WriteOptions::default(),
+                    )
+                    .unwrap();
+                // compiling doesn't run the script
+                assert!(ctx
+                    .globals()
+                    .get::<_, Option<i32>>("ran")
+                    .unwrap()
+                    .is_none());
+                bytes
+            })
+            .await;
+
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        ctx.with(|ctx| {
+            let result: i32 = unsafe { ctx.eval_compiled(&bytes) }.unwrap();
+            assert_eq!(result, 42);
+            assert_eq!(ctx.globals().get::<_, i32>("ran").unwrap(), 1);
+
+            let object = ctx
+                .write_object(&ctx.eval("({})").unwrap(), WriteOptions::default())
+                .unwrap();
+            assert!(unsafe { ctx.eval_compiled::<()>(&object) }.is_err());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn deadline() {
+        use crate::{AsyncContext, AsyncRuntime, Error};
+        use std::time::{Duration, Instant};
+
+        let runtime = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        ctx.with(|ctx| {
+            ctx.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+            // not even a catch block survives the interrupt
+            let err = ctx
+                .eval::<(), _>("try { while (true) {} } catch {}")
+                .unwrap_err();
+            assert!(matches!(err, Error::Exception));
+            let exception = ctx.catch().into_exception().unwrap();
+            assert_eq!(exception.message().as_deref(), Some("interrupted"));
+
+            ctx.set_deadline(None);
+            assert_eq!(ctx.deadline(), None);
+            assert_eq!(ctx.eval::<i32, _>("1 + 1").unwrap(), 2);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn eval_source_map() {
+        use crate::{context::EvalOptions, AsyncContext, AsyncRuntime};
+        use std::sync::{Arc, Mutex};
+
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let runtime = AsyncRuntime::new().unwrap();
+        let handler_registered = registered.clone();
+        runtime
+            .set_source_map_handler(Some(Box::new(move |_ctx, filename, map| {
+                handler_registered
+                    .lock()
+                    .unwrap()
+                    .push((filename.to_string(), map.to_string()));
+                Ok(())
+            })))
+            .await;
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        ctx.with(|ctx| {
+            let value: i32 = ctx
+                .eval_with_options(
+                    "1 + 1",
+                    EvalOptions {
+                        filename: Some("input.js".into()),
+                        source_map: Some("{}".into()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(value, 2);
+            ctx.eval::<(), _>("undefined").unwrap();
+        })
+        .await;
+        assert_eq!(
+            *registered.lock().unwrap(),
+            [("input.js".to_string(), "{}".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_stats() {
+        use crate::{AsyncContext, AsyncRuntime, Object};
+
+        let runtime = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        let before = runtime.memory_stats().await;
+        assert_eq!(before.malloc_limit, None);
+        ctx.with(|ctx| {
+            let objects: Object = ctx
+                .eval("globalThis.objects = Array.from({ length: 1000 }, () => ({}))")
+                .unwrap();
+            let stats = ctx.memory_stats();
+            assert!(stats.object_count >= before.object_count + 1000);
+            assert!(stats.memory_used_size > before.memory_used_size);
+            drop(objects);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn realm() {
+        use crate::{AsyncContext, AsyncRuntime, Function, Object, Promise};
+
+        let runtime = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&runtime).await.unwrap();
+        crate::async_with!(ctx => |ctx| {
+            ctx.globals().set("shared", 1).unwrap();
+            let realm = ctx.new_realm().unwrap();
+            assert!(!realm.globals().contains_key("shared").unwrap());
+
+            // objects keep the intrinsics of their realm
+            let array: Object = realm.eval("[]").unwrap();
+            let is_array: Function = ctx.eval("(value) => value instanceof Array").unwrap();
+            assert!(!is_array.call::<_, bool>((array,)).unwrap());
+
+            // and share the job queue
+            let promise: Promise = realm.eval("Promise.resolve(42)").unwrap();
+            assert_eq!(promise.into_future::<i32>().await.unwrap(), 42);
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn eval() {
         use crate::{AsyncContext, AsyncRuntime};
@@ -37,12 +37,15 @@ pub use value::{
 };
 
 pub mod allocator;
+pub mod channel;
 pub mod loader;
 
 pub use context::AsyncContext;
 #[cfg(feature = "multi-ctx")]
 pub use context::MultiWith;
-pub use value::{ArrayBuffer, Iterable, JsIterator, TypedArray};
+pub use value::{
+    ArrayBuffer, AsyncIterable, Iterable, IteratorObject, JsAsyncIterator, JsIterator, TypedArray,
+};
 
 //#[doc(hidden)]
 pub mod qjs {
@@ -1,5 +1,7 @@
 //! QuickJS runtime related types.
 
+mod memory;
+mod metrics;
 pub(crate) mod opaque;
 pub(crate) mod raw;
 mod userdata;
@@ -10,6 +12,8 @@ mod spawner;
 
 pub(crate) mod task_queue;
 
+pub use memory::RuntimeMemoryStats;
+pub use metrics::RuntimeMetrics;
 pub use spawner::DriveFuture;
 
 use std::boxed::Box;
@@ -18,7 +22,7 @@ pub use r#async::{AsyncRuntime, AsyncWeakRuntime};
 pub use userdata::{UserDataError, UserDataGuard};
 
 use crate::value::promise::PromiseHookType;
-use crate::{Ctx, Value};
+use crate::{Ctx, Result, Value};
 
 /// The type of the promise hook.
 pub type PromiseHook =
@@ -28,6 +32,10 @@ pub type PromiseHook =
 pub type RejectionTracker =
     Box<dyn for<'a> Fn(Ctx<'a>, Value<'a>, Value<'a>, bool) + Send + 'static>;
 
+/// The type of the source map handler, called with the filename and the source map
+/// of code evaluated with [`EvalOptions::source_map`](crate::context::EvalOptions::source_map).
+pub type SourceMapHandler = Box<dyn for<'a> Fn(Ctx<'a>, &str, &str) -> Result<()> + Send + 'static>;
+
 /// The type of the interrupt handler.
 pub type InterruptHandler = Box<dyn FnMut() -> bool + Send + 'static>;
 
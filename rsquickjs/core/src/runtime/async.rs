@@ -1,5 +1,5 @@
 use std::{ffi::CString, vec::Vec};
-use std::{ptr::NonNull, result::Result as StdResult, task::Poll};
+use std::{ptr::NonNull, result::Result as StdResult, task::Poll, time::Instant};
 
 use async_lock::Mutex;
 use std::sync::mpsc::{self, Receiver, Sender};
@@ -7,13 +7,14 @@ use std::sync::Arc;
 
 use super::{
     opaque::Opaque, raw::RawRuntime, spawner::DriveFuture, task_queue::TaskPoll, InterruptHandler,
-    MemoryUsage, PromiseHook, RejectionTracker,
+    MemoryUsage, PromiseHook, RejectionTracker, RuntimeMemoryStats, RuntimeMetrics,
+    SourceMapHandler,
 };
 use crate::allocator::Allocator;
 
 use crate::loader::{Loader, Resolver};
 use crate::qjs;
-use crate::{context::AsyncContext, result::AsyncJobException, Ctx, Result};
+use crate::{context::AsyncContext, result::AsyncJobException, Ctx, Result, Value};
 
 pub(crate) type RuntimeLock<T> = Mutex<T>;
 
@@ -125,6 +126,10 @@ impl AsyncRuntime {
     }
 
     /// Set a closure which is called when a promise is rejected.
+    ///
+    /// It receives the promise, the reason and `false` when a promise is rejected without
+    /// a handler, and is called again with `true` and an undefined reason if a handler is
+    /// attached later, which is what `unhandledRejection` and `rejectionHandled` are built on.
     pub async fn set_host_promise_rejection_tracker(&self, tracker: Option<RejectionTracker>) {
         unsafe {
             self.lock()
@@ -134,11 +139,31 @@ impl AsyncRuntime {
         }
     }
 
+    /// Set a closure which is called with the context, the promise, the reason and whether the
+    /// rejection is handled when a promise is rejected, see
+    /// [`set_host_promise_rejection_tracker`](Self::set_host_promise_rejection_tracker).
+    pub async fn set_rejection_handler<F>(&self, handler: F)
+    where
+        F: for<'js> Fn(Ctx<'js>, Value<'js>, Value<'js>, bool) + Send + 'static,
+    {
+        self.set_host_promise_rejection_tracker(Some(Box::new(handler)))
+            .await
+    }
+
     /// Set a closure which is called when a promise is created, resolved, or chained.
     pub async fn set_promise_hook(&self, tracker: Option<PromiseHook>) {
         unsafe { self.lock().await.runtime.set_promise_hook(tracker) }
     }
 
+    /// Set a closure which registers the source map passed with
+    /// [`EvalOptions::source_map`](crate::context::EvalOptions::source_map), so whatever
+    /// formats stack traces can map positions back to the original source.
+    ///
+    /// Without a handler those source maps are ignored.
+    pub async fn set_source_map_handler(&self, handler: Option<SourceMapHandler>) {
+        unsafe { self.lock().await.runtime.set_source_map_handler(handler) }
+    }
+
     /// Set a closure which is regularly called by the engine when it is executing code.
     ///
     /// If the provided closure returns `true` the interpreter will raise an uncatchable
@@ -147,6 +172,15 @@ impl AsyncRuntime {
         unsafe { self.lock().await.runtime.set_interrupt_handler(handler) }
     }
 
+    /// Set the instant after which executing code is interrupted, or `None` to
+    /// let it run without a limit.
+    ///
+    /// Inside a context use [`Ctx::set_deadline`](crate::Ctx::set_deadline)
+    /// instead, which doesn't need the runtime lock.
+    pub async fn set_deadline(&self, deadline: Option<Instant>) {
+        unsafe { self.lock().await.runtime.set_deadline(deadline) }
+    }
+
     /// Set the module loader.
 
     pub async fn set_loader<R: Resolver + 'static, L: Loader + 'static>(
@@ -202,6 +236,16 @@ impl AsyncRuntime {
         unsafe { self.lock().await.runtime.memory_usage() }
     }
 
+    /// Get memory usage stats as a [`RuntimeMemoryStats`].
+    pub async fn memory_stats(&self) -> RuntimeMemoryStats {
+        self.memory_usage().await.into()
+    }
+
+    /// Get a snapshot of the activity of the runtime as [`RuntimeMetrics`].
+    pub async fn metrics(&self) -> RuntimeMetrics {
+        self.lock().await.runtime.metrics()
+    }
+
     /// Test for pending jobs.
     ///
     /// Returns true when at least one job is pending.
@@ -384,6 +428,28 @@ mod test {
         assert_eq!(number.load(Ordering::SeqCst),1);
     });
 
+    async_test_case!(rejection_tracker => (rt,ctx){
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        rt.set_rejection_handler(move |_ctx, _promise, reason, is_handled| {
+            let reason = reason.as_string().and_then(|reason| reason.to_string().ok());
+            events_clone.lock().unwrap().push((reason, is_handled));
+        }).await;
+
+        async_with!(&ctx => |ctx|{
+            ctx.eval::<(), _>("globalThis.rejected = Promise.reject('boom')").unwrap();
+            ctx.eval::<(), _>("rejected.catch(() => {})").unwrap();
+        }).await;
+        rt.idle().await;
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![(Some("boom".to_string()), false), (None, true)]
+        );
+    });
+
     async_test_case!(recursive_spawn => (rt,ctx){
         use tokio::sync::oneshot;
 
@@ -454,6 +520,28 @@ mod test {
         assert_eq!(COUNT.load(Ordering::Relaxed), 2);
     });
 
+    async_test_case!(metrics => (rt,ctx){
+        let before = rt.metrics().await;
+        async_with!(&ctx => |ctx|{
+            ctx.eval::<(), _>("Promise.resolve().then(() => {})").unwrap();
+            ctx.spawn(async move {
+                tokio::task::yield_now().await;
+            });
+            let metrics = ctx.metrics();
+            assert!(metrics.job_pending);
+            assert_eq!(metrics.background_tasks, 1);
+        }).await;
+        rt.idle().await;
+        rt.run_gc().await;
+
+        let after = rt.metrics().await;
+        assert_eq!(after.evals, before.evals + 1);
+        assert_eq!(after.gc_runs, before.gc_runs + 1);
+        assert!(after.jobs_executed > before.jobs_executed);
+        assert!(!after.job_pending);
+        assert_eq!(after.background_tasks, 0);
+    });
+
     #[tokio::test]
     async fn ensure_types_are_send() {
         fn assert_send<T: Send>(_: &T) {}
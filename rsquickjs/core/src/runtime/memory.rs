@@ -0,0 +1,74 @@
+use super::MemoryUsage;
+
+/// How much memory a runtime holds, broken down by the kind of data.
+///
+/// Sizes are in bytes, counts are the number of allocations or items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeMemoryStats {
+    /// Bytes handed out by the allocator.
+    pub malloc_size: u64,
+    /// The limit set with `set_memory_limit`, if any.
+    pub malloc_limit: Option<u64>,
+    /// Bytes the runtime accounts for in its own structures.
+    pub memory_used_size: u64,
+    pub malloc_count: u64,
+    pub memory_used_count: u64,
+    pub atom_count: u64,
+    pub atom_size: u64,
+    pub string_count: u64,
+    pub string_size: u64,
+    pub object_count: u64,
+    pub object_size: u64,
+    pub property_count: u64,
+    pub property_size: u64,
+    pub shape_count: u64,
+    pub shape_size: u64,
+    pub js_function_count: u64,
+    pub js_function_size: u64,
+    /// Bytes of bytecode of the JS functions.
+    pub js_function_code_size: u64,
+    pub js_function_pc2line_count: u64,
+    pub js_function_pc2line_size: u64,
+    pub c_function_count: u64,
+    pub array_count: u64,
+    pub fast_array_count: u64,
+    pub fast_array_elements: u64,
+    /// `ArrayBuffer`s and typed arrays.
+    pub binary_object_count: u64,
+    pub binary_object_size: u64,
+}
+
+impl From<MemoryUsage> for RuntimeMemoryStats {
+    fn from(usage: MemoryUsage) -> Self {
+        // QuickJS reports everything as signed, the limit is negative or zero when unset
+        let unsigned = |value: i64| value.max(0) as u64;
+        RuntimeMemoryStats {
+            malloc_size: unsigned(usage.malloc_size),
+            malloc_limit: (usage.malloc_limit > 0).then(|| usage.malloc_limit as u64),
+            memory_used_size: unsigned(usage.memory_used_size),
+            malloc_count: unsigned(usage.malloc_count),
+            memory_used_count: unsigned(usage.memory_used_count),
+            atom_count: unsigned(usage.atom_count),
+            atom_size: unsigned(usage.atom_size),
+            string_count: unsigned(usage.str_count),
+            string_size: unsigned(usage.str_size),
+            object_count: unsigned(usage.obj_count),
+            object_size: unsigned(usage.obj_size),
+            property_count: unsigned(usage.prop_count),
+            property_size: unsigned(usage.prop_size),
+            shape_count: unsigned(usage.shape_count),
+            shape_size: unsigned(usage.shape_size),
+            js_function_count: unsigned(usage.js_func_count),
+            js_function_size: unsigned(usage.js_func_size),
+            js_function_code_size: unsigned(usage.js_func_code_size),
+            js_function_pc2line_count: unsigned(usage.js_func_pc2line_count),
+            js_function_pc2line_size: unsigned(usage.js_func_pc2line_size),
+            c_function_count: unsigned(usage.c_func_count),
+            array_count: unsigned(usage.array_count),
+            fast_array_count: unsigned(usage.fast_array_count),
+            fast_array_elements: unsigned(usage.fast_array_elements),
+            binary_object_count: unsigned(usage.binary_object_count),
+            binary_object_size: unsigned(usage.binary_object_size),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::cell::Cell;
+
+/// What a runtime has been doing, for monitoring long running embedders.
+///
+/// Counters are cumulative since the runtime was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeMetrics {
+    /// Whether promise jobs are queued, QuickJS doesn't expose the length of its queue.
+    pub job_pending: bool,
+    /// Promise jobs executed.
+    pub jobs_executed: u64,
+    /// Futures spawned on the runtime which haven't completed yet.
+    pub background_tasks: usize,
+    /// Garbage collections requested with `run_gc`. The ones QuickJS triggers on its own
+    /// aren't reported.
+    pub gc_runs: u64,
+    /// Scripts and modules evaluated.
+    pub evals: u64,
+}
+
+/// The counters behind [`RuntimeMetrics`], kept in the runtime opaque.
+#[derive(Default)]
+pub(crate) struct MetricsCounters {
+    jobs_executed: Cell<u64>,
+    gc_runs: Cell<u64>,
+    evals: Cell<u64>,
+}
+
+impl MetricsCounters {
+    pub fn job_executed(&self) {
+        self.jobs_executed.set(self.jobs_executed.get() + 1)
+    }
+
+    pub fn gc_run(&self) {
+        self.gc_runs.set(self.gc_runs.get() + 1)
+    }
+
+    pub fn evaluated(&self) {
+        self.evals.set(self.evals.get() + 1)
+    }
+
+    pub fn snapshot(&self, job_pending: bool, background_tasks: usize) -> RuntimeMetrics {
+        RuntimeMetrics {
+            job_pending,
+            jobs_executed: self.jobs_executed.get(),
+            background_tasks,
+            gc_runs: self.gc_runs.get(),
+            evals: self.evals.get(),
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use crate::{
     class::{self, ffi::VTable, JsClass},
-    qjs, Ctx, Error, JsLifetime, Object, Value,
+    qjs, Ctx, Error, JsLifetime, Object, Result, Value,
 };
 
 use super::{
+    metrics::MetricsCounters,
     userdata::{UserDataGuard, UserDataMap},
-    InterruptHandler, PromiseHook, PromiseHookType, RejectionTracker, UserDataError,
+    InterruptHandler, PromiseHook, PromiseHookType, RejectionTracker, SourceMapHandler,
+    UserDataError,
 };
 use std::boxed::Box;
 use std::{
@@ -13,6 +15,7 @@ use std::{
     cell::{Cell, UnsafeCell},
     marker::PhantomData,
     ptr,
+    time::Instant,
 };
 
 use std::collections::{hash_map::Entry, HashMap};
@@ -35,9 +38,18 @@ pub(crate) struct Opaque<'js> {
     /// The user provided rejection tracker, if any.
     rejection_tracker: UnsafeCell<Option<RejectionTracker>>,
 
+    /// The user provided source map handler, if any.
+    source_map_handler: UnsafeCell<Option<SourceMapHandler>>,
+
     /// The user provided interrupt handler, if any.
     interrupt_handler: UnsafeCell<Option<InterruptHandler>>,
 
+    /// The instant after which executing code is interrupted, if any.
+    deadline: Cell<Option<Instant>>,
+
+    /// Counters reported by `RuntimeMetrics`.
+    metrics: MetricsCounters,
+
     /// The class id for rust classes.
     class_id: qjs::JSClassID,
     /// The class id for rust classes which can be called.
@@ -61,8 +73,14 @@ impl<'js> Opaque<'js> {
 
             rejection_tracker: UnsafeCell::new(None),
 
+            source_map_handler: UnsafeCell::new(None),
+
             interrupt_handler: UnsafeCell::new(None),
 
+            deadline: Cell::new(None),
+
+            metrics: MetricsCounters::default(),
+
             class_id: qjs::JS_INVALID_CLASS_ID,
             callable_class_id: qjs::JS_INVALID_CLASS_ID,
 
@@ -91,7 +109,8 @@ impl<'js> Opaque<'js> {
             finalizer: Some(class::ffi::class_finalizer),
             gc_mark: Some(class::ffi::class_trace),
             call: None,
-            exotic: ptr::null_mut(),
+            // QuickJS only reads the methods.
+            exotic: ptr::addr_of!(class::ffi::CLASS_EXOTIC).cast_mut(),
         };
 
         if 0 != qjs::JS_NewClass(rt, self.class_id, &class_def) {
@@ -138,6 +157,13 @@ impl<'js> Opaque<'js> {
         unsafe { (*self.queue().get()).is_empty() }
     }
 
+    /// The number of spawned futures, zero for a runtime without a spawner.
+    pub fn spawner_len(&self) -> usize {
+        self.queue
+            .as_ref()
+            .map_or(0, |queue| unsafe { (*queue.get()).len() })
+    }
+
     pub fn poll(&self, cx: &mut Context) -> TaskPoll {
         unsafe { (*self.queue().get()).poll(cx) }
     }
@@ -196,12 +222,53 @@ impl<'js> Opaque<'js> {
         }
     }
 
+    pub fn set_source_map_handler(&self, handler: Option<SourceMapHandler>) {
+        unsafe { (*self.source_map_handler.get()) = handler }
+    }
+
+    /// Hands the source map to the handler, dropping it if there is none.
+    pub fn run_source_map_handler(&self, ctx: Ctx<'_>, filename: &str, map: &str) -> Result<()> {
+        unsafe {
+            match (*self.source_map_handler.get()).as_ref() {
+                Some(handler) => handler(ctx, filename, map),
+                None => Ok(()),
+            }
+        }
+    }
+
     pub fn set_interrupt_handler(&self, interupt: Option<InterruptHandler>) {
         unsafe { (*self.interrupt_handler.get()) = interupt }
     }
 
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        self.deadline.set(deadline)
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline.get()
+    }
+
+    pub fn metrics(&self) -> &MetricsCounters {
+        &self.metrics
+    }
+
+    pub fn has_interrupt_handler(&self) -> bool {
+        self.deadline.get().is_some() || unsafe { (*self.interrupt_handler.get()).is_some() }
+    }
+
     pub fn run_interrupt_handler(&self) -> bool {
-        unsafe { (*self.interrupt_handler.get()).as_mut().unwrap()() }
+        if self
+            .deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return true;
+        }
+        unsafe {
+            (*self.interrupt_handler.get())
+                .as_mut()
+                .is_some_and(|handler| handler())
+        }
     }
 
     #[allow(dead_code)] // not used in no_std
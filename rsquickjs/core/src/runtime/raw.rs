@@ -1,6 +1,6 @@
 #![allow(dead_code, unused_imports)]
 use std::{boxed::Box, ffi::CString};
-use std::{mem, panic::AssertUnwindSafe, ptr::NonNull, result::Result as StdResult};
+use std::{mem, panic::AssertUnwindSafe, ptr::NonNull, result::Result as StdResult, time::Instant};
 
 use rquickjs_sys::JSPromiseHookType;
 
@@ -12,7 +12,10 @@ use crate::{
     Ctx, Error, Result, Value,
 };
 
-use super::{opaque::Opaque, InterruptHandler, PromiseHook, PromiseHookType, RejectionTracker};
+use super::{
+    opaque::Opaque, InterruptHandler, PromiseHook, PromiseHookType, RejectionTracker,
+    RuntimeMetrics, SourceMapHandler,
+};
 
 const DUMP_BYTECODE_FINAL: u64 = 0x01;
 const DUMP_BYTECODE_PASS2: u64 = 0x02;
@@ -200,6 +203,14 @@ impl RawRuntime {
         (unsafe { qjs::JS_IsJobPending(self.rt.as_ptr()) } as i32) != 0
     }
 
+    /// Snapshot of the runtime metrics.
+    pub fn metrics(&self) -> RuntimeMetrics {
+        let opaque = self.get_opaque();
+        opaque
+            .metrics()
+            .snapshot(self.is_job_pending(), opaque.spawner_len())
+    }
+
     pub fn execute_pending_job(&mut self) -> StdResult<bool, *mut qjs::JSContext> {
         let mut ctx_ptr = mem::MaybeUninit::<*mut qjs::JSContext>::uninit();
         let result = unsafe { qjs::JS_ExecutePendingJob(self.rt.as_ptr(), ctx_ptr.as_mut_ptr()) };
@@ -209,6 +220,7 @@ impl RawRuntime {
         }
         if result == 1 {
             // single job executed
+            self.get_opaque().metrics().job_executed();
             return Ok(true);
         }
         Err(unsafe { ctx_ptr.assume_init() })
@@ -267,6 +279,7 @@ impl RawRuntime {
     /// cyclic references.
     pub unsafe fn run_gc(&mut self) {
         qjs::JS_RunGC(self.rt.as_ptr());
+        self.get_opaque().metrics().gc_run();
     }
 
     /// Get memory usage stats
@@ -370,42 +383,24 @@ impl RawRuntime {
         self.get_opaque().set_rejection_tracker(tracker);
     }
 
+    /// Set a closure which receives the source maps of evaluated code.
+    pub unsafe fn set_source_map_handler(&mut self, handler: Option<SourceMapHandler>) {
+        self.get_opaque().set_source_map_handler(handler);
+    }
+
     /// Set a closure which is regularly called by the engine when it is executing code.
     /// If the provided closure returns `true` the interpreter will raise and uncatchable
     /// exception and return control flow to the caller.
     pub unsafe fn set_interrupt_handler(&mut self, handler: Option<InterruptHandler>) {
-        unsafe extern "C" fn interrupt_handler_trampoline(
-            _rt: *mut qjs::JSRuntime,
-            opaque: *mut ::std::ffi::c_void,
-        ) -> ::std::ffi::c_int {
-            // This should be safe as the value is set below to a non-null pointer.
-            let opaque = NonNull::new_unchecked(opaque).cast::<Opaque>();
-
-            let should_interrupt = {
-                let catch_unwind = crate::util::catch_unwind(AssertUnwindSafe(move || {
-                    opaque.as_ref().run_interrupt_handler()
-                }));
-                match catch_unwind {
-                    Ok(should_interrupt) => should_interrupt,
-                    Err(panic) => {
-                        opaque.as_ref().set_panic(panic);
-                        // Returning true here will cause the interpreter to raise an un-catchable exception.
-                        // The Rust code that is running the interpreter will see that exception and continue
-                        // the panic handling. See crate::result::{handle_exception, handle_panic} for details.
-                        true
-                    }
-                }
-            };
-
-            should_interrupt as _
-        }
-
-        qjs::JS_SetInterruptHandler(
-            self.rt.as_ptr(),
-            handler.as_ref().map(|_| interrupt_handler_trampoline as _),
-            qjs::JS_GetRuntimeOpaque(self.rt.as_ptr()),
-        );
         self.get_opaque().set_interrupt_handler(handler);
+        update_interrupt_handler(self.rt.as_ptr());
+    }
+
+    /// Set the instant after which executing code is interrupted, see
+    /// [`Ctx::set_deadline`].
+    pub unsafe fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.get_opaque().set_deadline(deadline);
+        update_interrupt_handler(self.rt.as_ptr());
     }
 
     fn add_dump_flags(rt: *mut rquickjs_sys::JSRuntime) {
@@ -452,3 +447,42 @@ mod test {
         assert_eq!(*counter.lock().unwrap(), 1);
     }
 }
+
+/// Installs the interrupt trampoline while the runtime has a handler or a
+/// deadline, so code runs without the check otherwise.
+pub(crate) unsafe fn update_interrupt_handler(rt: *mut qjs::JSRuntime) {
+    unsafe extern "C" fn interrupt_handler_trampoline(
+        _rt: *mut qjs::JSRuntime,
+        opaque: *mut ::std::ffi::c_void,
+    ) -> ::std::ffi::c_int {
+        // This should be safe as the value is set below to a non-null pointer.
+        let opaque = NonNull::new_unchecked(opaque).cast::<Opaque>();
+
+        let should_interrupt = {
+            let catch_unwind = crate::util::catch_unwind(AssertUnwindSafe(move || {
+                opaque.as_ref().run_interrupt_handler()
+            }));
+            match catch_unwind {
+                Ok(should_interrupt) => should_interrupt,
+                Err(panic) => {
+                    opaque.as_ref().set_panic(panic);
+                    // Returning true here will cause the interpreter to raise an un-catchable exception.
+                    // The Rust code that is running the interpreter will see that exception and continue
+                    // the panic handling. See crate::result::{handle_exception, handle_panic} for details.
+                    true
+                }
+            }
+        };
+
+        should_interrupt as _
+    }
+
+    let opaque = qjs::JS_GetRuntimeOpaque(rt);
+    qjs::JS_SetInterruptHandler(
+        rt,
+        Opaque::from_runtime_ptr(rt)
+            .has_interrupt_handler()
+            .then_some(interrupt_handler_trampoline as _),
+        opaque,
+    );
+}
@@ -43,6 +43,10 @@ impl TaskQueue {
         self.inner.tasks.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.inner.tasks.len()
+    }
+
     /// # Safety
     /// Caller must ensure future lifetime is valid
     pub unsafe fn push<F: Future<Output = ()>>(&self, future: F) {
@@ -28,11 +28,13 @@ pub use string::{CString, String};
 pub use symbol::Symbol;
 
 pub mod array_buffer;
+pub mod async_iterable;
 pub mod iterable;
 pub mod typed_array;
 
 pub use array_buffer::ArrayBuffer;
-pub use iterable::{Iterable, JsIterator};
+pub use async_iterable::{AsyncIterable, JsAsyncIterator};
+pub use iterable::{Iterable, IteratorObject, JsIterator};
 pub use typed_array::TypedArray;
 
 /// Any JavaScript value
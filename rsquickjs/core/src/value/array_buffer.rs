@@ -1,5 +1,5 @@
 use crate::{qjs, Ctx, Error, FromJs, IntoJs, JsLifetime, Object, Result, Value};
-use std::vec::Vec;
+use std::{boxed::Box, vec::Vec};
 use std::{
     ffi::c_void,
     fmt,
@@ -77,6 +77,62 @@ impl<'js> ArrayBuffer<'js> {
         })))
     }
 
+    /// Create array buffer over the memory of `owner` without copying it.
+    ///
+    /// The owner is dropped when the buffer is garbage collected, so a memory map or a
+    /// shared byte buffer is released like any other value.
+    pub fn new_external<T, B>(ctx: Ctx<'js>, owner: B) -> Result<Self>
+    where
+        T: Copy,
+        B: AsMut<[T]> + Send + 'static,
+    {
+        // boxed first, so the memory of an inline array doesn't move afterwards
+        let mut owner = Box::new(owner);
+        let src = (*owner).as_mut();
+        let ptr = src.as_mut_ptr();
+        let size = mem::size_of_val(src);
+        unsafe { Self::from_raw_parts(ctx, ptr as _, size, move || drop(owner)) }
+    }
+
+    /// Create array buffer over `len` bytes at `ptr`, calling `free` once it is garbage
+    /// collected.
+    ///
+    /// # Safety
+    /// The memory must stay valid, and must not be written elsewhere, until `free` is called.
+    pub unsafe fn from_raw_parts<F>(
+        ctx: Ctx<'js>,
+        ptr: *mut u8,
+        len: usize,
+        free: F,
+    ) -> Result<Self>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        extern "C" fn free_raw<F: FnOnce()>(
+            _rt: *mut qjs::JSRuntime,
+            opaque: *mut c_void,
+            _ptr: *mut c_void,
+        ) {
+            let free = unsafe { Box::from_raw(opaque as *mut F) };
+            free();
+        }
+
+        let opaque = Box::into_raw(Box::new(free));
+        let val = qjs::JS_NewArrayBuffer(
+            ctx.as_ptr(),
+            ptr,
+            len as _,
+            Some(free_raw::<F>),
+            opaque as _,
+            false,
+        );
+        ctx.handle_exception(val).inspect_err(|_| {
+            // the buffer never took the memory over
+            Box::from_raw(opaque)();
+        })?;
+        Ok(Self(Object(Value::from_js_value(ctx, val))))
+    }
+
     /// Create array buffer from slice
     pub fn new_copy<T: Copy>(ctx: Ctx<'js>, src: impl AsRef<[T]>) -> Result<Self> {
         let src = src.as_ref();
@@ -270,6 +326,45 @@ mod test {
         .await;
     }
 
+    #[tokio::test]
+    async fn external_memory() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        struct Owner(std::vec::Vec<u16>, Arc<AtomicBool>);
+
+        impl AsMut<[u16]> for Owner {
+            fn as_mut(&mut self) -> &mut [u16] {
+                &mut self.0
+            }
+        }
+
+        impl Drop for Owner {
+            fn drop(&mut self) {
+                self.1.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let freed = Arc::new(AtomicBool::new(false));
+        let owner = Owner(vec![1, 2, 3], freed.clone());
+        test_with(move |ctx| {
+            let array = TypedArray::<u16>::new_external(ctx.clone(), owner).unwrap();
+            ctx.globals().set("a", array).unwrap();
+            let sum: u16 = ctx.eval("a[2] = 4; a[0] + a[1] + a[2]").unwrap();
+            assert_eq!(sum, 7);
+            let array: TypedArray<u16> = ctx.globals().get("a").unwrap();
+            assert_eq!(array.as_ref() as &[u16], &[1, 2, 4]);
+
+            ctx.globals().remove("a").unwrap();
+            drop(array);
+            ctx.run_gc();
+        })
+        .await;
+        assert!(freed.load(Ordering::SeqCst));
+    }
+
     #[tokio::test]
     async fn into_javascript_i8() {
         test_with(|ctx| {
@@ -0,0 +1,454 @@
+//! JavaScript async iterables from Rust streams, and Rust streams from JavaScript async
+//! iterators.
+
+use crate::{
+    atom::PredefinedAtom,
+    function::This,
+    promise::{MaybePromise, MaybePromiseFuture},
+    Ctx, Error, FromJs, Function, IntoJs, Object, Promise, Result, Value,
+};
+use futures_core::Stream;
+use std::{
+    cell::RefCell,
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+type SharedStream<S> = Rc<RefCell<Option<Pin<Box<S>>>>>;
+
+/// Converts a Rust stream into a JavaScript async iterable object.
+///
+/// The resulting object has a `[Symbol.asyncIterator]` method, so it can be consumed with
+/// `for await`. Every `next()` returns a promise settled with the next item of the stream,
+/// and an item which fails to convert, such as an `Err` of a stream of results, rejects it.
+/// Breaking out of the loop drops the stream.
+///
+/// Like [`Iterable`](crate::Iterable) the stream can only be consumed once.
+///
+/// A native function can return one, an [`Async`](crate::function::Async) one included,
+/// to let JavaScript iterate results it produces over time. Types which aren't a stream
+/// can be iterated with [`AsyncIterable::unfold`].
+///
+/// # Example
+/// ```rust,ignore
+/// # use rquickjs::{AsyncRuntime, AsyncContext, AsyncIterable, Result};
+/// # let rt = AsyncRuntime::new().unwrap();
+/// # let ctx = AsyncContext::full(&rt).await.unwrap();
+/// # ctx.with(|ctx| -> Result<()> {
+/// let stream = futures::stream::iter(vec![1, 2, 3]);
+/// ctx.globals().set("numbers", AsyncIterable::from(stream))?;
+/// ctx.eval::<(), _>("(async () => { for await (const n of numbers) console.log(n) })()")?;
+/// # Ok(())
+/// # }).await.unwrap();
+/// ```
+pub struct AsyncIterable<S>(pub S);
+
+impl<S> From<S> for AsyncIterable<S> {
+    fn from(stream: S) -> Self {
+        AsyncIterable(stream)
+    }
+}
+
+impl<St, F, Fut> AsyncIterable<Unfold<St, F, Fut>> {
+    /// Creates an async iterable from a state, such as a database cursor, and an async
+    /// function producing the next item and the state after it, or `None` at the end.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// # use rquickjs::{prelude::{Async, Func}, AsyncIterable};
+    /// let pages = Func::from(Async(|count: u32| async move {
+    ///     AsyncIterable::unfold(0, move |page| async move {
+    ///         (page < count).then(|| (fetch_page(page).await, page + 1))
+    ///     })
+    /// }));
+    /// // for await (const page of await pages(3)) { ... }
+    /// ```
+    pub fn unfold(init: St, f: F) -> Self {
+        AsyncIterable(Unfold {
+            state: Some(init),
+            f,
+            pending: None,
+        })
+    }
+}
+
+/// The stream created by [`AsyncIterable::unfold`].
+pub struct Unfold<St, F, Fut> {
+    state: Option<St>,
+    f: F,
+    pending: Option<Pin<Box<Fut>>>,
+}
+
+// the state and function are only moved, never pinned
+impl<St, F, Fut> Unpin for Unfold<St, F, Fut> {}
+
+impl<St, F, Fut, T> Stream for Unfold<St, F, Fut>
+where
+    F: FnMut(St) -> Fut,
+    Fut: Future<Output = Option<(T, St)>>,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if let Some(state) = this.state.take() {
+            this.pending = Some(Box::pin((this.f)(state)));
+        }
+        let Some(pending) = this.pending.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let next = match pending.as_mut().poll(cx) {
+            Poll::Ready(next) => next,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.pending = None;
+        Poll::Ready(next.map(|(item, state)| {
+            this.state = Some(state);
+            item
+        }))
+    }
+}
+
+impl<'js, S, T> IntoJs<'js> for AsyncIterable<S>
+where
+    S: Stream<Item = T> + 'js,
+    T: IntoJs<'js> + 'js,
+{
+    fn into_js(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        let stream: SharedStream<S> = Rc::new(RefCell::new(Some(Box::pin(self.0))));
+
+        let iterator_fn =
+            Function::new(ctx.clone(), move |ctx: Ctx<'js>| -> Result<Object<'js>> {
+                let state: SharedStream<S> = Rc::new(RefCell::new(stream.borrow_mut().take()));
+                let iterator = Object::new(ctx.clone())?;
+
+                let next_state = state.clone();
+                let next_fn = Function::new(ctx.clone(), move |ctx: Ctx<'js>| {
+                    next(&ctx, next_state.clone())
+                })?;
+                let return_fn = Function::new(ctx.clone(), move |ctx: Ctx<'js>| {
+                    state.borrow_mut().take();
+                    let result = iter_result(&ctx, None)?;
+                    Promise::wrap_future(&ctx, async move { result })
+                })?;
+
+                iterator.set(PredefinedAtom::Next, next_fn)?;
+                iterator.set(PredefinedAtom::Return, return_fn)?;
+                Ok(iterator)
+            })?;
+
+        let obj = Object::new(ctx.clone())?;
+        obj.set(PredefinedAtom::SymbolAsyncIterator, iterator_fn)?;
+        Ok(obj.into_value())
+    }
+}
+
+fn next<'js, S, T>(ctx: &Ctx<'js>, state: SharedStream<S>) -> Result<Promise<'js>>
+where
+    S: Stream<Item = T> + 'js,
+    T: IntoJs<'js> + 'js,
+{
+    let ctx_clone = ctx.clone();
+    Promise::wrap_future(ctx, async move {
+        let item = poll_fn(|cx| match state.borrow_mut().as_mut() {
+            Some(stream) => stream.as_mut().poll_next(cx),
+            None => Poll::Ready(None),
+        })
+        .await;
+        match item {
+            Some(item) => {
+                let value = item.into_js(&ctx_clone)?;
+                iter_result(&ctx_clone, Some(value))
+            }
+            None => {
+                state.borrow_mut().take();
+                iter_result(&ctx_clone, None)
+            }
+        }
+    })
+}
+
+/// Creates an iterator result, which is done when there is no value.
+fn iter_result<'js>(ctx: &Ctx<'js>, value: Option<Value<'js>>) -> Result<Object<'js>> {
+    let result = Object::new(ctx.clone())?;
+    result.set(PredefinedAtom::Done, value.is_none())?;
+    if let Some(value) = value {
+        result.set(PredefinedAtom::Value, value)?;
+    }
+    Ok(result)
+}
+
+/// A stream over values from a JavaScript async iterable.
+///
+/// This struct wraps a JavaScript async iterator object and implements the `Stream` trait,
+/// awaiting the promise returned by each `next()` call. Plain iterables are accepted too,
+/// like `for await` does. The stream ends after the first error.
+///
+/// The type parameter `T` specifies what type each value should be converted to.
+///
+/// # Example
+/// ```rust,ignore
+/// # use rquickjs::{AsyncRuntime, AsyncContext, JsAsyncIterator, Result, async_with};
+/// # use futures::StreamExt;
+/// # let rt = AsyncRuntime::new().unwrap();
+/// # let ctx = AsyncContext::full(&rt).await.unwrap();
+/// async_with!(ctx => |ctx| {
+///     let mut stream: JsAsyncIterator<i32> = ctx
+///         .eval("(async function*() { yield 1; yield 2; })()")
+///         .unwrap();
+///     while let Some(value) = stream.next().await {
+///         println!("{}", value.unwrap());
+///     }
+/// })
+/// .await;
+/// ```
+pub struct JsAsyncIterator<'js, T = Value<'js>> {
+    iterator: Object<'js>,
+    pending: Option<MaybePromiseFuture<'js, Object<'js>>>,
+    done: bool,
+    _marker: PhantomData<T>,
+}
+
+// Nothing is actually pinned so the iterator is unpin.
+impl<'js, T> Unpin for JsAsyncIterator<'js, T> {}
+
+impl<'js, T> JsAsyncIterator<'js, T> {
+    fn new(iterator: Object<'js>) -> Self {
+        JsAsyncIterator {
+            iterator,
+            pending: None,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the underlying JS iterator object.
+    pub fn into_inner(self) -> Object<'js> {
+        self.iterator
+    }
+}
+
+impl<'js, T: FromJs<'js>> Stream for JsAsyncIterator<'js, T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        if this.pending.is_none() {
+            let result = this
+                .iterator
+                .get::<_, Function>(PredefinedAtom::Next)
+                .and_then(|next| next.call::<_, MaybePromise>((This(this.iterator.clone()),)));
+            match result {
+                Ok(result) => this.pending = Some(result.into_future()),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+
+        let result = match Pin::new(this.pending.as_mut().unwrap()).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.pending = None;
+
+        let ctx = this.iterator.ctx();
+        let item = result.and_then(|result| {
+            if result.get::<_, Option<bool>>(PredefinedAtom::Done)? == Some(true) {
+                return Ok(None);
+            }
+            T::from_js(ctx, result.get(PredefinedAtom::Value)?).map(Some)
+        });
+        match item {
+            Ok(Some(value)) => Poll::Ready(Some(Ok(value))),
+            Ok(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Err(err) => {
+                this.done = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+impl<'js, T: FromJs<'js>> FromJs<'js> for JsAsyncIterator<'js, T> {
+    fn from_js(_ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        let obj = Object::from_value(value)?;
+
+        for symbol in [
+            PredefinedAtom::SymbolAsyncIterator,
+            PredefinedAtom::SymbolIterator,
+        ] {
+            if let Some(iter_fn) = obj.get::<_, Option<Function<'js>>>(symbol)? {
+                let iterator: Object<'js> = iter_fn.call((This(obj),))?;
+                return Ok(JsAsyncIterator::new(iterator));
+            }
+        }
+
+        // Fall back to treating it as an iterator (has `next` method)
+        if obj.contains_key(PredefinedAtom::Next)? {
+            return Ok(JsAsyncIterator::new(obj));
+        }
+
+        Err(Error::new_from_js(
+            "value",
+            "async iterable (object with Symbol.asyncIterator, Symbol.iterator or next)",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{async_with, AsyncContext, AsyncRuntime, CatchResultExt, Exception};
+    use futures_rs::{stream, StreamExt};
+
+    #[tokio::test]
+    async fn stream_for_await() {
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        async_with!(ctx => |ctx| {
+            let numbers = AsyncIterable::from(stream::iter(vec![1i32, 2, 3]));
+            ctx.globals().set("numbers", numbers).unwrap();
+            let sum: Promise = ctx
+                .eval(
+                    r#"
+                (async () => {
+                    let sum = 0;
+                    for await (const n of numbers) { sum += n; }
+                    return sum;
+                })()
+            "#,
+                )
+                .unwrap();
+            assert_eq!(sum.into_future::<i32>().await.catch(&ctx).unwrap(), 6);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn stream_error_rejects() {
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        async_with!(ctx => |ctx| {
+            let ctx_clone = ctx.clone();
+            let items = stream::iter(vec![1i32, 2]).map(move |n| {
+                if n == 2 {
+                    Err(Exception::throw_type(&ctx_clone, "broken"))
+                } else {
+                    Ok(n)
+                }
+            });
+            ctx.globals().set("items", AsyncIterable::from(items)).unwrap();
+            let seen: Promise = ctx
+                .eval(
+                    r#"
+                (async () => {
+                    const seen = [];
+                    try {
+                        for await (const n of items) { seen.push(n); }
+                    } catch (err) {
+                        seen.push(err.message);
+                    }
+                    return seen.join(",");
+                })()
+            "#,
+                )
+                .unwrap();
+            let seen: std::string::String = seen.into_future().await.catch(&ctx).unwrap();
+            assert_eq!(seen, "1,broken");
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn unfold_from_async_function() {
+        use crate::prelude::{Async, Func};
+
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        async_with!(ctx => |ctx| {
+            let open_cursor = Func::from(Async(|limit: i32| async move {
+                tokio::task::yield_now().await;
+                AsyncIterable::unfold(0, move |row| async move {
+                    tokio::task::yield_now().await;
+                    (row < limit).then(|| (row * 10, row + 1))
+                })
+            }));
+            ctx.globals().set("openCursor", open_cursor).unwrap();
+            let rows: Promise = ctx
+                .eval(
+                    r#"
+                (async () => {
+                    const rows = [];
+                    for await (const row of await openCursor(3)) { rows.push(row); }
+                    for await (const row of await openCursor(0)) { rows.push(row); }
+                    return rows.join(",");
+                })()
+            "#,
+                )
+                .unwrap();
+            let rows: std::string::String = rows.into_future().await.catch(&ctx).unwrap();
+            assert_eq!(rows, "0,10,20");
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn async_generator_as_stream() {
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        async_with!(ctx => |ctx| {
+            let stream: JsAsyncIterator<i32> = ctx
+                .eval(
+                    r#"
+                (async function*() {
+                    yield 10;
+                    await null;
+                    yield 20;
+                })()
+            "#,
+                )
+                .unwrap();
+            let values: Vec<i32> = stream.map(|value| value.unwrap()).collect().await;
+            assert_eq!(values, vec![10, 20]);
+
+            let stream: JsAsyncIterator<i32> = ctx.eval("[1, 2]").unwrap();
+            let values: Vec<i32> = stream.map(|value| value.unwrap()).collect().await;
+            assert_eq!(values, vec![1, 2]);
+
+            let mut stream: JsAsyncIterator<i32> = ctx
+                .eval("(async function*() { yield 1; throw new Error('boom'); })()")
+                .unwrap();
+            assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+            assert!(stream.next().await.unwrap().is_err());
+            ctx.catch();
+            assert!(stream.next().await.is_none());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn stream_roundtrip() {
+        let rt = AsyncRuntime::new().unwrap();
+        let ctx = AsyncContext::full(&rt).await.unwrap();
+        async_with!(ctx => |ctx| {
+            let numbers = AsyncIterable::from(stream::iter(vec![4i32, 5, 6]));
+            ctx.globals().set("numbers", numbers).unwrap();
+            let stream: JsAsyncIterator<i32> = ctx.eval("numbers").unwrap();
+            let values: Vec<i32> = stream.map(|value| value.unwrap()).collect().await;
+            assert_eq!(values, vec![4, 5, 6]);
+        })
+        .await;
+    }
+}
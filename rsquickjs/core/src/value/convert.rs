@@ -6,6 +6,8 @@ mod atom;
 mod coerce;
 mod from;
 mod into;
+#[cfg(feature = "serde_json")]
+mod json;
 
 /// The wrapper for values to force coercion
 ///
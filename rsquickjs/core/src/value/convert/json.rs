@@ -0,0 +1,150 @@
+//! Conversion between `serde_json` values and JavaScript values, without going through a
+//! JSON string.
+
+use crate::{Array, Ctx, Error, FromJs, IntoJs, Object, Result, Type, Value};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::string::String as StdString;
+
+/// Nesting deeper than this is taken for a cycle, which JSON can't represent.
+const MAX_DEPTH: usize = 512;
+
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+impl<'js> IntoJs<'js> for JsonValue {
+    fn into_js(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        (&self).into_js(ctx)
+    }
+}
+
+impl<'js> IntoJs<'js> for &JsonValue {
+    fn into_js(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        Ok(match self {
+            JsonValue::Null => Value::new_null(ctx.clone()),
+            JsonValue::Bool(value) => Value::new_bool(ctx.clone(), *value),
+            JsonValue::Number(number) => match number.as_i64() {
+                Some(value) if i32::try_from(value).is_ok() => {
+                    Value::new_int(ctx.clone(), value as i32)
+                }
+                _ => Value::new_number(ctx.clone(), number.as_f64().unwrap_or(f64::NAN)),
+            },
+            JsonValue::String(value) => value.as_str().into_js(ctx)?,
+            JsonValue::Array(values) => {
+                let array = Array::new(ctx.clone())?;
+                for (index, value) in values.iter().enumerate() {
+                    array.set(index, value)?;
+                }
+                array.into_value()
+            }
+            JsonValue::Object(map) => {
+                let object = Object::new(ctx.clone())?;
+                for (key, value) in map {
+                    object.set(key.as_str(), value)?;
+                }
+                object.into_value()
+            }
+        })
+    }
+}
+
+/// Converts like `JSON.stringify` would: functions, symbols and undefined are left out of
+/// objects and become null in arrays, non-finite numbers become null. `toJSON` is not called.
+impl<'js> FromJs<'js> for JsonValue {
+    fn from_js(ctx: &Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        from_js_value(ctx, &value, 0)?
+            .ok_or_else(|| Error::new_from_js(value.type_name(), "JSON value"))
+    }
+}
+
+fn from_js_value<'js>(
+    ctx: &Ctx<'js>,
+    value: &Value<'js>,
+    depth: usize,
+) -> Result<Option<JsonValue>> {
+    if depth > MAX_DEPTH {
+        return Err(Error::new_from_js_message(
+            value.type_name(),
+            "JSON value",
+            "too deeply nested or cyclic",
+        ));
+    }
+    Ok(Some(match value.type_of() {
+        Type::Null => JsonValue::Null,
+        Type::Bool => JsonValue::Bool(value.as_bool().unwrap_or_default()),
+        Type::Int => JsonValue::Number(value.as_int().unwrap_or_default().into()),
+        Type::Float => {
+            let value = value.as_float().unwrap_or_default();
+            // integers which don't fit an int are still integers to serde
+            if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+                JsonValue::Number((value as i64).into())
+            } else {
+                Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number)
+            }
+        }
+        Type::String => JsonValue::String(StdString::from_js(ctx, value.clone())?),
+        Type::Array => {
+            let array = value.as_array().unwrap();
+            let mut values = std::vec::Vec::with_capacity(array.len());
+            for item in array.iter::<Value>() {
+                values.push(from_js_value(ctx, &item?, depth + 1)?.unwrap_or(JsonValue::Null));
+            }
+            JsonValue::Array(values)
+        }
+        Type::Object | Type::Exception | Type::Promise | Type::Proxy => {
+            let object = value.as_object().unwrap();
+            let mut map = Map::new();
+            for prop in object.props::<StdString, Value>() {
+                let (key, value) = prop?;
+                if let Some(value) = from_js_value(ctx, &value, depth + 1)? {
+                    map.insert(key, value);
+                }
+            }
+            JsonValue::Object(map)
+        }
+        Type::BigInt => {
+            return Err(Error::new_from_js_message(
+                "BigInt",
+                "JSON value",
+                "BigInt can't be serialized",
+            ))
+        }
+        _ => return Ok(None),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use serde_json::{json, Value as JsonValue};
+
+    #[tokio::test]
+    async fn serde_json_roundtrip() {
+        test_with(|ctx| {
+            let value = json!({
+                "name": "xmas",
+                "version": 1,
+                "big": 4_294_967_296u64,
+                "ratio": 0.5,
+                "tags": ["a", null, true],
+                "nested": { "empty": {} },
+            });
+            ctx.globals().set("value", &value).unwrap();
+            let checked: bool = ctx
+                .eval("value.tags[2] && value.nested.empty && value.big === 2 ** 32")
+                .unwrap();
+            assert!(checked);
+
+            let back: JsonValue = ctx.globals().get("value").unwrap();
+            assert_eq!(back, value);
+
+            let skipped: JsonValue = ctx
+                .eval("({ f() {}, u: undefined, n: NaN, list: [undefined, () => {}] })")
+                .unwrap();
+            assert_eq!(skipped, json!({ "n": null, "list": [null, null] }));
+
+            assert!(ctx
+                .eval::<JsonValue, _>("const a = {}; a.a = a; a")
+                .is_err());
+        })
+        .await;
+    }
+}
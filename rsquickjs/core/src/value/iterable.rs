@@ -51,33 +51,8 @@ where
         let iterator_fn = Function::new(
             ctx.clone(),
             MutFn::new(move |ctx: Ctx<'js>| -> Result<Object<'js>> {
-                let iter_obj = Object::new(ctx.clone())?;
                 let iter_taken = iter.lock().take();
-
-                let state = Mut::new(iter_taken);
-                let next_fn = Function::new(
-                    ctx.clone(),
-                    MutFn::new(move |ctx: Ctx<'js>| -> Result<Object<'js>> {
-                        let result = Object::new(ctx.clone())?;
-                        let mut state_ref = state.lock();
-
-                        if let Some(ref mut it) = *state_ref {
-                            if let Some(value) = it.next() {
-                                result.set(PredefinedAtom::Value, value.into_js(&ctx)?)?;
-                                result.set(PredefinedAtom::Done, false)?;
-                            } else {
-                                result.set(PredefinedAtom::Done, true)?;
-                                *state_ref = None;
-                            }
-                        } else {
-                            result.set(PredefinedAtom::Done, true)?;
-                        }
-                        Ok(result)
-                    }),
-                )?;
-
-                iter_obj.set(PredefinedAtom::Next, next_fn)?;
-                Ok(iter_obj)
+                iterator_object(&ctx, iter_taken)
             }),
         )?;
 
@@ -87,6 +62,78 @@ where
     }
 }
 
+/// Converts a Rust iterator into a JavaScript iterator object.
+///
+/// Unlike [`Iterable`] the resulting object has a `next` method itself, so it can be returned
+/// from a `[Symbol.iterator]` method. It is also iterable, returning itself from its own
+/// `[Symbol.iterator]`.
+///
+/// # Example
+/// ```rust,ignore
+/// # use rquickjs::{AsyncRuntime, AsyncContext, Result, IteratorObject};
+/// # let rt = AsyncRuntime::new().unwrap();
+/// # let ctx = AsyncContext::full(&rt).await.unwrap();
+/// # ctx.with(|ctx| -> Result<()> {
+/// ctx.globals().set("myIterator", IteratorObject::from(vec![1, 2, 3]))?;
+/// let first: i32 = ctx.eval("myIterator.next().value")?;
+/// assert_eq!(first, 1);
+/// # Ok(())
+/// # }).await.unwrap();
+/// ```
+pub struct IteratorObject<I>(pub I);
+
+impl<I> From<I> for IteratorObject<I> {
+    fn from(iter: I) -> Self {
+        IteratorObject(iter)
+    }
+}
+
+impl<'js, I, T> IntoJs<'js> for IteratorObject<I>
+where
+    I: IntoIterator<Item = T> + 'js,
+    I::IntoIter: 'js,
+    T: IntoJs<'js> + 'js,
+{
+    fn into_js(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        iterator_object(ctx, Some(self.0.into_iter())).map(Object::into_value)
+    }
+}
+
+/// Create an object following the iterator protocol, yielding the items of `iter`.
+fn iterator_object<'js, I, T>(ctx: &Ctx<'js>, iter: Option<I>) -> Result<Object<'js>>
+where
+    I: Iterator<Item = T> + 'js,
+    T: IntoJs<'js> + 'js,
+{
+    let state = Mut::new(iter);
+    let next_fn = Function::new(
+        ctx.clone(),
+        MutFn::new(move |ctx: Ctx<'js>| -> Result<Object<'js>> {
+            let result = Object::new(ctx.clone())?;
+            let mut state_ref = state.lock();
+
+            if let Some(ref mut it) = *state_ref {
+                if let Some(value) = it.next() {
+                    result.set(PredefinedAtom::Value, value.into_js(&ctx)?)?;
+                    result.set(PredefinedAtom::Done, false)?;
+                } else {
+                    result.set(PredefinedAtom::Done, true)?;
+                    *state_ref = None;
+                }
+            } else {
+                result.set(PredefinedAtom::Done, true)?;
+            }
+            Ok(result)
+        }),
+    )?;
+    let self_fn = Function::new(ctx.clone(), |this: This<Object<'js>>| this.0)?;
+
+    let iter_obj = Object::new(ctx.clone())?;
+    iter_obj.set(PredefinedAtom::Next, next_fn)?;
+    iter_obj.set(PredefinedAtom::SymbolIterator, self_fn)?;
+    Ok(iter_obj)
+}
+
 /// An iterator over values from a JavaScript iterable.
 ///
 /// This struct wraps a JavaScript iterator object and implements Rust's `Iterator` trait,
@@ -254,6 +301,21 @@ mod test {
         .await;
     }
 
+    #[tokio::test]
+    async fn iterator_object() {
+        test_with(|ctx| {
+            let iter = IteratorObject::from(vec![1i32, 2, 3]);
+            ctx.globals().set("myIterator", iter).unwrap();
+            let result: Vec<i32> = ctx
+                .eval("[myIterator.next().value, ...myIterator]")
+                .unwrap();
+            let done: bool = ctx.eval("myIterator.next().done").unwrap();
+            assert!(done);
+            assert_eq!(result, vec![1, 2, 3]);
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn iterable_single_use() {
         test_with(|ctx| {
@@ -97,6 +97,17 @@ impl<'js, T> TypedArray<'js, T> {
         Self::from_arraybuffer(ab)
     }
 
+    /// Create typed array over the memory of `owner` without copying it, see
+    /// [`ArrayBuffer::new_external`].
+    pub fn new_external<B>(ctx: Ctx<'js>, owner: B) -> Result<Self>
+    where
+        T: Copy + TypedArrayItem,
+        B: AsMut<[T]> + Send + 'static,
+    {
+        let ab = ArrayBuffer::new_external(ctx, owner)?;
+        Self::from_arraybuffer(ab)
+    }
+
     /// Create typed array from slice
     pub fn new_copy(ctx: Ctx<'js>, src: impl AsRef<[T]>) -> Result<Self>
     where
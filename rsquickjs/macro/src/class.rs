@@ -20,10 +20,12 @@ pub(crate) struct ClassConfig {
     pub crate_: Option<String>,
     pub rename: Option<String>,
     pub rename_all: Option<Case>,
+    pub extends: Option<syn::Type>,
 }
 
 pub(crate) enum ClassOption {
     Frozen(FlagOption<kw::frozen>),
+    Extends(ValueOption<kw::extends, Box<syn::Type>>),
     Crate(ValueOption<Token![crate], LitStr>),
     Rename(ValueOption<kw::rename, LitStr>),
     RenameAll(ValueOption<kw::rename_all, Case>),
@@ -33,6 +35,8 @@ impl Parse for ClassOption {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         if input.peek(kw::frozen) {
             input.parse().map(Self::Frozen)
+        } else if input.peek(kw::extends) {
+            input.parse().map(Self::Extends)
         } else if input.peek(Token![crate]) {
             input.parse().map(Self::Crate)
         } else if input.peek(kw::rename) {
@@ -51,6 +55,9 @@ impl ClassConfig {
             ClassOption::Frozen(ref x) => {
                 self.frozen = x.is_true();
             }
+            ClassOption::Extends(ref x) => {
+                self.extends = Some((*x.value).clone());
+            }
             ClassOption::Crate(ref x) => {
                 self.crate_ = Some(x.value.value());
             }
@@ -258,6 +265,30 @@ impl Class {
         }
     }
 
+    /// Links the prototype, and the constructor when the parent defined one, to those of
+    /// the class this one extends.
+    pub fn expand_extends(&self, crate_name: &Ident) -> (TokenStream, TokenStream) {
+        let Some(parent) = self.config().extends.as_ref() else {
+            return (TokenStream::new(), TokenStream::new());
+        };
+        let proto = quote! {
+            if let Some(parent) = #crate_name::class::Class::<#parent>::prototype(ctx)? {
+                proto.set_prototype(Some(&parent))?;
+            }
+        };
+        let constructor = quote! {
+            if let (Some(constructor), Some(parent)) = (
+                constructor.as_ref(),
+                #crate_name::class::Class::<#parent>::prototype(ctx)?,
+            ) {
+                if let Some(parent) = parent.get::<_, Option<#crate_name::Object>>("constructor")? {
+                    constructor.set_prototype(Some(&parent))?;
+                }
+            }
+        };
+        (proto, constructor)
+    }
+
     pub fn expand_props(&self, crate_name: &Ident) -> TokenStream {
         let Class::Struct { ref fields, .. } = self else {
             return TokenStream::new();
@@ -344,6 +375,7 @@ impl Class {
 
         let mutability = self.mutability();
         let props = self.expand_props(&crate_name);
+        let (extends_proto, extends_constructor) = self.expand_extends(&crate_name);
         let reexpand = self.reexpand();
 
         let res = quote! {
@@ -362,6 +394,7 @@ impl Class {
                         use #crate_name::class::impl_::MethodImplementor;
 
                         let proto = #crate_name::Object::new(ctx.clone())?;
+                        #extends_proto
                         #props
                         let implementor = #crate_name::class::impl_::MethodImpl::<Self>::new();
                         (&implementor).implement(&proto)?;
@@ -372,7 +405,16 @@ impl Class {
                         use #crate_name::class::impl_::ConstructorCreator;
 
                         let implementor = #crate_name::class::impl_::ConstructorCreate::<Self>::new();
-                        (&implementor).create_constructor(ctx)
+                        let constructor = (&implementor).create_constructor(ctx)?;
+                        #extends_constructor
+                        Ok(constructor)
+                    }
+
+                    fn get_index(this: &#crate_name::class::JsCell<'js, Self>, ctx: &#crate_name::Ctx<'js>, index: u32) -> #crate_name::Result<Option<#crate_name::Value<'js>>>{
+                        use #crate_name::class::impl_::IndexGetter;
+
+                        let implementor = #crate_name::class::impl_::IndexGet::<Self>::new();
+                        (&implementor).get_index(this, ctx, index)
                     }
                 }
 
@@ -125,6 +125,7 @@ impl<'a> Fold for SelfReplacer<'a> {
 
 pub(crate) mod kw {
     syn::custom_keyword!(frozen);
+    syn::custom_keyword!(extends);
     syn::custom_keyword!(skip_trace);
     syn::custom_keyword!(rename);
     syn::custom_keyword!(rename_all);
@@ -136,6 +137,8 @@ pub(crate) mod kw {
     syn::custom_keyword!(skip);
     syn::custom_keyword!(configurable);
     syn::custom_keyword!(enumerable);
+    syn::custom_keyword!(iterator);
+    syn::custom_keyword!(index);
     syn::custom_keyword!(prefix);
     syn::custom_keyword!(declare);
     syn::custom_keyword!(evaluate);
@@ -135,6 +135,8 @@ pub(crate) struct JsFunction {
     pub name: Ident,
     pub rust_function: TokenStream,
     pub is_async: bool,
+    /// Returns its result as a JavaScript iterator object.
+    pub iterator: bool,
     pub params: JsParams,
 }
 
@@ -179,6 +181,7 @@ impl JsFunction {
             vis,
             name: ident.clone(),
             is_async,
+            iterator: false,
             rust_function,
             params,
         })
@@ -224,6 +227,12 @@ impl JsFunction {
 
                 #lib_crate::IntoJs::into_js(#lib_crate::promise::Promised(fut), &ctx)
             }
+        } else if self.iterator {
+            quote! {
+                #arg_extract
+                let res = #rust_function(#arg_apply);
+                #lib_crate::IntoJs::into_js(#lib_crate::IteratorObject(res),&ctx)
+            }
         } else {
             quote! {
                 #arg_extract
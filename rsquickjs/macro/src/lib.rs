@@ -46,6 +46,10 @@ mod trace;
 /// | `rename`     | String    | Changes the name of the implemented class on the JavaScript side.                                                                                                                       |
 /// | `rename_all` | Casing    | Converts the case of all the fields of this struct which have implement accessors. Can be one of `lowercase`, `UPPERCASE`, `camelCase`, `PascalCase`,`snake_case`, or `SCREAMING_SNAKE` |
 /// | `frozen`     | Flag      | Changes the class implementation to only allow borrowing immutably.  Trying to borrow mutably will result in an error.                                                                  |
+/// | `extends`    | Type      | Chains the prototype to the one of another Rust class, and the constructor to its constructor if that was defined first, so `instanceof` and inherited statics work.                  |
+///
+/// A class which `extends` another is still a distinct Rust type: methods of the parent which
+/// borrow `self` can't be called on it, only those taking `This<Object>` or the like.
 ///
 /// # Field options
 ///
@@ -188,12 +192,14 @@ pub fn function(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
 /// | `static`       | Flag                                                              | Makes the method a static method i.e. defined on the type constructor instead of the prototype. |
 /// | `constructor`  | Flag                                                              | Marks this method a the constructor for this type.                                              |
 /// | `skip`         | Flag                                                              | Skips defining this method on the JavaScript class.                                             |
+/// | `iterator`     | Flag                                                              | Defines this method as `[Symbol.iterator]`, turning the Rust iterator it returns into a JavaScript iterator. |
+/// | `index`        | Flag                                                              | Reads `object[index]` through this method, which takes the index as a `u32` and returns an `Option` of the element. |
 ///
 /// # Example
 /// ```
 /// use rsquickjs::{
-///     atom::PredefinedAtom, class::Trace, prelude::Func, CatchResultExt, Class, Context, Ctx,
-///     Object, Result, Runtime, JsLifetime
+///     atom::PredefinedAtom, class::Trace, CatchResultExt, Class, Context, Runtime, JsLifetime,
+///     Value,
 /// };
 ///
 /// #[derive(Trace, JsLifetime)]
@@ -254,21 +260,26 @@ pub fn function(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
 ///     #[qjs(skip)]
 ///     pub fn inner_function(&self) {}
 ///
-///     /// Functions can also be renamed to specific symbols. This allows you to make an Rust type
-///     /// act like an iteratable value for example.
-///     #[qjs(rename = PredefinedAtom::SymbolIterator)]
-///     pub fn iterate<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
-///         let res = Object::new(ctx)?;
-///
-///         res.set(
-///             PredefinedAtom::Next,
-///             Func::from(|ctx: Ctx<'js>| -> Result<Object<'js>> {
-///                 let res = Object::new(ctx)?;
-///                 res.set(PredefinedAtom::Done, true)?;
-///                 Ok(res)
-///             }),
-///         )?;
-///         Ok(res)
+///     /// Functions can also be renamed to specific symbols, static ones included.
+///     #[qjs(static, rename = PredefinedAtom::SymbolHasInstance)]
+///     pub fn has_instance(value: Value<'_>) -> bool {
+///         value.as_object().is_some_and(|obj| obj.instance_of::<Self>())
+///     }
+///
+///     /// Makes the type iterable, yielding the items of the returned Rust iterator.
+///     #[qjs(iterator)]
+///     pub fn values(&self) -> Vec<u32> {
+///         vec![self.value, self.another_value]
+///     }
+///
+///     /// Makes the values readable by index, `undefined` past the end.
+///     #[qjs(index)]
+///     pub fn get(&self, index: u32) -> Option<u32> {
+///         match index {
+///             0 => Some(self.value),
+///             1 => Some(self.another_value),
+///             _ => None,
+///         }
 ///     }
 /// }
 ///
@@ -285,6 +296,9 @@ pub fn function(attr: TokenStream1, item: TokenStream1) -> TokenStream1 {
 ///             if(nv.value !== 5){
 ///                 throw new Error('invalid value')
 ///             }
+///             if([...nv].length !== 2 || nv[1] !== 5 || nv[2] !== undefined){
+///                 throw new Error('invalid protocols')
+///             }
 ///         "#,
 ///         ).catch(&ctx).unwrap();
 ///     });
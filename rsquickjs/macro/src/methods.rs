@@ -134,6 +134,7 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
     let mut accessors = HashMap::new();
     let mut functions = Vec::new();
     let mut constructor: Option<Method> = None;
+    let mut index: Option<Method> = None;
     let mut static_span: Option<Span> = None;
     //let mut consts = Vec::new();
 
@@ -160,6 +161,13 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
                         error.extend(Error::new(first_span, "First constructor defined here"));
                         return Err(error);
                     }
+                } else if function.config.index {
+                    if let Some(first) = index.replace(function) {
+                        let first_span = first.attr_span;
+                        let mut error = Error::new(span, "A class can only have a single index");
+                        error.extend(Error::new(first_span, "First index defined here"));
+                        return Err(error);
+                    }
                 } else {
                     if static_span.is_none() && function.config.r#static {
                         static_span = Some(function.attr_span);
@@ -185,6 +193,7 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
     let function_impls = functions.iter().map(|func| func.expand_impl());
     let accessor_impls = accessors.values().map(|access| access.expand_impl());
     let constructor_impl = constructor.as_ref().map(|constr| constr.expand_impl());
+    let index_impl = index.as_ref().map(|index| index.expand_impl());
 
     let function_js_impls = functions
         .iter()
@@ -205,7 +214,13 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
         .iter()
         .filter(|&func| !func.config.r#static)
         .map(|func| {
-            func.expand_apply_to_object(&prefix, &self_ty, &proto_ident, config.rename_all)
+            func.expand_apply_to_object(
+                &prefix,
+                &self_ty,
+                &proto_ident,
+                config.rename_all,
+                &crate_name,
+            )
         });
     let accessor_apply_proto = accessors
         .values()
@@ -228,6 +243,7 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
                         &self_ty,
                         &constructor_ident,
                         config.rename_all,
+                        &crate_name,
                     )
                 });
 
@@ -244,6 +260,10 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
         TokenStream::new()
     };
 
+    let index_getter = index
+        .as_ref()
+        .map(|index| index.expand_index_getter(&self_ty, &add_js_lifetime(&generics), &crate_name));
+
     let class_name = get_class_name(&self_ty);
     let impl_mod_name = format_ident!("__impl_methods_{class_name}__");
 
@@ -253,6 +273,7 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
             #(#function_impls)*
             #(#accessor_impls)*
             #constructor_impl
+            #index_impl
         }
 
 
@@ -276,8 +297,109 @@ pub(crate) fn expand(options: OptionList<ImplOption>, item: ItemImpl) -> Result<
             }
 
             #constructor_create
+
+            #index_getter
         }
     };
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+
+    use super::expand;
+
+    fn expand_impl(item: syn::ItemImpl) -> syn::Result<String> {
+        expand(parse_quote!(crate = "rsquickjs"), item).map(|tokens| tokens.to_string())
+    }
+
+    #[test]
+    fn symbol_static_methods_are_defined() {
+        let tokens = expand_impl(parse_quote! {
+            impl Pair {
+                #[qjs(constructor)]
+                pub fn new() -> Self { Pair }
+
+                #[qjs(static, rename = PredefinedAtom::SymbolHasInstance)]
+                pub fn has_instance(value: Value<'_>) -> bool { true }
+            }
+        })
+        .unwrap();
+        assert!(tokens.contains(
+            "constr . prop (PredefinedAtom :: SymbolHasInstance , rsquickjs :: object :: Property"
+        ));
+    }
+
+    #[test]
+    fn protocols() {
+        let tokens = expand_impl(parse_quote! {
+            impl Pair {
+                #[qjs(iterator)]
+                pub fn values(&self) -> Vec<u32> { vec![] }
+
+                #[qjs(index)]
+                pub fn get(&self, index: u32) -> Option<u32> { None }
+            }
+        })
+        .unwrap();
+        assert!(tokens.contains(
+            "_proto . set (rsquickjs :: atom :: PredefinedAtom :: SymbolIterator , < Pair > :: js_values) ?"
+        ));
+        assert!(tokens.contains("rsquickjs :: IteratorObject (res)"));
+        assert!(tokens.contains(
+            "impl < 'js > rsquickjs :: class :: impl_ :: IndexGetter < 'js , Pair > for rsquickjs :: class :: impl_ :: IndexGet < Pair >"
+        ));
+        assert!(!tokens.contains("_proto . set (\"get\""));
+    }
+
+    #[test]
+    fn invalid_protocols() {
+        let errors = [
+            parse_quote! {
+                impl Pair {
+                    #[qjs(iterator, index)]
+                    pub fn values(&self) -> Vec<u32> { vec![] }
+                }
+            },
+            parse_quote! {
+                impl Pair {
+                    #[qjs(static, iterator)]
+                    pub fn values() -> Vec<u32> { vec![] }
+                }
+            },
+            parse_quote! {
+                impl Pair {
+                    #[qjs(index, rename = "at")]
+                    pub fn get(&self, index: u32) -> Option<u32> { None }
+                }
+            },
+            parse_quote! {
+                impl Pair {
+                    #[qjs(iterator)]
+                    pub async fn values(&self) -> Vec<u32> { vec![] }
+                }
+            },
+            parse_quote! {
+                impl Pair {
+                    #[qjs(index)]
+                    pub fn get(&self, index: u32) -> Option<u32> { None }
+                    #[qjs(index)]
+                    pub fn at(&self, index: u32) -> Option<u32> { None }
+                }
+            },
+        ]
+        .map(|item| expand_impl(item).unwrap_err().to_string());
+        assert_eq!(
+            errors,
+            [
+                "a function can't both be an iterator and an index at the same time.",
+                "iterator and index can't be set for static methods, constructors, getters or setters.",
+                "Can't rename an iterator or index, their key is given by the protocol",
+                "an iterator method can't be async.",
+                "A class can only have a single index",
+            ]
+        );
+    }
+}
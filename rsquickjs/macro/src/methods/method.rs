@@ -4,7 +4,8 @@ use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
     spanned::Spanned,
-    Attribute, Block, Error, Expr, ImplItemFn, LitStr, Result, Signature, Token, Type, Visibility,
+    Attribute, Block, Error, Expr, Generics, ImplItemFn, LitStr, Result, Signature, Token, Type,
+    Visibility,
 };
 
 use crate::{
@@ -22,6 +23,8 @@ pub(crate) struct MethodConfig {
     pub enumerable: bool,
     pub get: bool,
     pub set: bool,
+    pub iterator: bool,
+    pub index: bool,
     pub rename: Option<Expr>,
 }
 
@@ -49,6 +52,12 @@ impl MethodConfig {
             MethodOption::Set(x) => {
                 self.set = x.is_true();
             }
+            MethodOption::Iterator(x) => {
+                self.iterator = x.is_true();
+            }
+            MethodOption::Index(x) => {
+                self.index = x.is_true();
+            }
             MethodOption::Rename(x) => {
                 self.rename = Some(x.value.clone());
             }
@@ -64,6 +73,8 @@ pub(crate) enum MethodOption {
     Enumerable(FlagOption<kw::enumerable>),
     Get(FlagOption<kw::get>),
     Set(FlagOption<kw::set>),
+    Iterator(FlagOption<kw::iterator>),
+    Index(FlagOption<kw::index>),
     Rename(ValueOption<kw::rename, Expr>),
 }
 
@@ -83,6 +94,10 @@ impl Parse for MethodOption {
             input.parse().map(Self::Get)
         } else if input.peek(kw::set) {
             input.parse().map(Self::Set)
+        } else if input.peek(kw::iterator) {
+            input.parse().map(Self::Iterator)
+        } else if input.peek(kw::index) {
+            input.parse().map(Self::Index)
         } else if input.peek(kw::rename) {
             input.parse().map(Self::Rename)
         } else {
@@ -133,6 +148,29 @@ impl MethodConfig {
                 "enumerable can only be set for getters and setters.",
             ));
         }
+
+        if self.iterator && self.index {
+            return Err(Error::new(
+                span,
+                "a function can't both be an iterator and an index at the same time.",
+            ));
+        }
+
+        if (self.iterator || self.index)
+            && (self.r#static || self.constructor || self.get || self.set)
+        {
+            return Err(Error::new(
+                span,
+                "iterator and index can't be set for static methods, constructors, getters or setters.",
+            ));
+        }
+
+        if (self.iterator || self.index) && self.rename.is_some() {
+            return Err(Error::new(
+                span,
+                "Can't rename an iterator or index, their key is given by the protocol",
+            ));
+        }
         Ok(())
     }
 }
@@ -189,7 +227,16 @@ impl Method {
 
         attrs.retain(|x| !x.path().is_ident("qjs"));
 
-        let function = JsFunction::new(vis.clone(), &sig, Some(self_ty))?;
+        let mut function = JsFunction::new(vis.clone(), &sig, Some(self_ty))?;
+        if config.iterator {
+            if let Some(asyncness) = sig.asyncness {
+                return Err(Error::new(
+                    asyncness.span(),
+                    "an iterator method can't be async.",
+                ));
+            }
+            function.iterator = true;
+        }
 
         Ok(Method {
             config,
@@ -271,14 +318,48 @@ impl Method {
         self_ty: &Type,
         object_name: &Ident,
         case: Option<Case>,
+        lib_crate: &Ident,
     ) -> TokenStream {
         if self.config.skip {
             return TokenStream::new();
         }
-        let func_name_str = self.name(case);
+        let func_name_str = if self.config.iterator {
+            quote!(#lib_crate::atom::PredefinedAtom::SymbolIterator)
+        } else {
+            let name = self.name(case);
+            quote!(#name)
+        };
         let js_func_name = self.function.expand_carry_type_name(prefix);
+        if self.config.r#static {
+            // Defined rather than set, as a constructor inherits non writable properties like
+            // `Symbol.hasInstance` from `Function.prototype` which can't be overridden by setting.
+            quote! {
+                #object_name.prop(#func_name_str, #lib_crate::object::Property::from(<#self_ty>::#js_func_name).writable().configurable())?;
+            }
+        } else {
+            quote! {
+                #object_name.set(#func_name_str,<#self_ty>::#js_func_name)?;
+            }
+        }
+    }
+
+    /// Expands the implementation reading class elements by index through this method.
+    pub(crate) fn expand_index_getter(
+        &self,
+        self_ty: &Type,
+        generics: &Generics,
+        lib_crate: &Ident,
+    ) -> TokenStream {
+        let name = &self.function.name;
         quote! {
-            #object_name.set(#func_name_str,<#self_ty>::#js_func_name)?;
+            impl #generics #lib_crate::class::impl_::IndexGetter<'js, #self_ty> for #lib_crate::class::impl_::IndexGet<#self_ty> {
+                fn get_index(&self, this: &#lib_crate::class::JsCell<'js, #self_ty>, ctx: &#lib_crate::Ctx<'js>, index: u32) -> #lib_crate::Result<Option<#lib_crate::Value<'js>>>{
+                    let this = this.try_borrow().map_err(#lib_crate::Error::ClassBorrow)?;
+                    <#self_ty>::#name(&this, index)
+                        .map(|element| #lib_crate::IntoJs::into_js(element, ctx))
+                        .transpose()
+                }
+            }
         }
     }
 }
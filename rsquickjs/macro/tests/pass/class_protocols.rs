@@ -0,0 +1,101 @@
+use rsquickjs::{
+    atom::PredefinedAtom, class::Trace, AsyncContext, AsyncRuntime, CatchResultExt, Class,
+    JsLifetime, Value,
+};
+
+#[derive(Trace, JsLifetime)]
+#[rsquickjs::class]
+pub struct Pair {
+    first: u32,
+    second: u32,
+}
+
+#[rsquickjs::methods]
+impl Pair {
+    #[qjs(constructor)]
+    pub fn new(first: u32, second: u32) -> Self {
+        Pair { first, second }
+    }
+
+    /// Arrays of two values count as pairs too.
+    #[qjs(static, rename = PredefinedAtom::SymbolHasInstance)]
+    pub fn has_instance(value: Value<'_>) -> bool {
+        if let Some(array) = value.as_array() {
+            return array.len() == 2;
+        }
+        value
+            .as_object()
+            .is_some_and(|object| object.instance_of::<Self>())
+    }
+
+    #[qjs(iterator)]
+    pub fn values(&self) -> Vec<u32> {
+        vec![self.first, self.second]
+    }
+
+    #[qjs(index)]
+    pub fn get(&self, index: u32) -> Option<u32> {
+        match index {
+            0 => Some(self.first),
+            1 => Some(self.second),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Trace, JsLifetime)]
+#[rsquickjs::class(extends = Pair)]
+pub struct NamedPair {
+    #[qjs(get)]
+    name: String,
+}
+
+#[rsquickjs::methods]
+impl NamedPair {
+    #[qjs(constructor)]
+    pub fn new(name: String) -> Self {
+        NamedPair { name }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let rt = AsyncRuntime::new().unwrap();
+    let ctx = AsyncContext::full(&rt).await.unwrap();
+
+    ctx.with(|ctx| {
+        Class::<Pair>::define(&ctx.globals()).unwrap();
+        Class::<NamedPair>::define(&ctx.globals()).unwrap();
+        ctx.eval::<(), _>(
+            r#"
+            const pair = new Pair(1, 2);
+            if (!(pair instanceof Pair) || !([3, 4] instanceof Pair) || [3] instanceof Pair) {
+                throw new Error("static symbol method");
+            }
+            if ([...pair].join() !== "1,2") {
+                throw new Error("iterator");
+            }
+            const iterator = pair[Symbol.iterator]();
+            if (iterator[Symbol.iterator]() !== iterator) {
+                throw new Error("iterator is not iterable");
+            }
+            if (pair[0] !== 1 || pair[1] !== 2 || pair[2] !== undefined) {
+                throw new Error("index");
+            }
+            if (!(1 in pair) || 2 in pair) {
+                throw new Error("index keys");
+            }
+            const named = new NamedPair("named");
+            if (Object.getPrototypeOf(NamedPair) !== Pair) {
+                throw new Error("extends");
+            }
+            if (named.name !== "named" || typeof named[Symbol.iterator] !== "function") {
+                throw new Error("inherited methods");
+            }
+        "#,
+        )
+        .catch(&ctx)
+        .unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,488 @@
+//! `xmas daemon`, which keeps a runtime warm for the scripts run in its project.
+//!
+//! The daemon listens on `.xmas/daemon.sock`. A script run in the project sends it its
+//! stdio, working directory, environment and arguments, and the daemon runs the script in
+//! a process forked from its own, where the runtime is already set up, then reports the
+//! exit code of that process back.
+//!
+//! The daemon stays on a single thread, forking from it is then safe: children are reaped
+//! from its loop when `SIGCHLD` wakes it up, and the async runtime a script runs on is
+//! started in the forked process. The socket is only accessible to the user running the
+//! daemon, who is also the only one it serves.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+
+use colored::*;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc::__errno_location as errno_location;
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+use libc::__error as errno_location;
+use serde::{Deserialize, Serialize};
+
+/// Where the daemon of the working directory listens.
+pub const SOCKET_PATH: &str = ".xmas/daemon.sock";
+
+/// A script for the daemon to run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    /// Working directory of the script
+    pub cwd: PathBuf,
+    /// The script followed by its own arguments
+    pub args: Vec<String>,
+    /// Environment of the script
+    pub env: Vec<(String, String)>,
+}
+
+/// How long the daemon waits for a request, which the client sends as soon as it
+/// connects, so a client that never sends one doesn't hold up the others.
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Answer of a daemon refusing a request it may not serve, the client then runs the
+/// script itself.
+const REFUSED: &str = "refused";
+
+/// Runs `args`, the script and its arguments, in the daemon of the working directory with
+/// the stdio of this process. Returns the exit code of the script, or `None` without a
+/// daemon listening, or with one of another user or project.
+pub fn forward(args: &[OsString]) -> anyhow::Result<Option<i32>> {
+    let Ok(mut stream) = UnixStream::connect(SOCKET_PATH) else {
+        return Ok(None);
+    };
+    // the stdio of this process only goes to a daemon of the same user
+    if peer_uid(&stream)? != current_uid() {
+        return Ok(None);
+    }
+    send_fds(&stream, &[0, 1, 2])?;
+    let request = Request {
+        cwd: std::env::current_dir()?,
+        args: args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        env: std::env::vars().collect(),
+    };
+    serde_json::to_writer(&mut stream, &request)?;
+    stream.write_all(b"\n")?;
+
+    let mut code = String::new();
+    BufReader::new(&stream).read_line(&mut code)?;
+    if code.trim() == REFUSED {
+        return Ok(None);
+    }
+    // a daemon gone before answering failed the script
+    Ok(Some(code.trim().parse().unwrap_or(1)))
+}
+
+/// Serves the scripts of the project until the daemon is stopped, running each with `run`
+/// in a process of its own.
+///
+/// Only processes of the user running the daemon are served, and only for scripts run
+/// from the root of the project, the directory the daemon was started in.
+pub fn serve(run: impl Fn(Request) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let socket = Path::new(SOCKET_PATH);
+    std::fs::create_dir_all(".xmas")?;
+    if UnixStream::connect(socket).is_ok() {
+        anyhow::bail!("A daemon is already running in this project");
+    }
+    // the socket of a daemon that didn't stop cleanly
+    let _ = std::fs::remove_file(socket);
+    let listener = {
+        // SAFETY: the process has a single thread, nothing else creates files meanwhile
+        let umask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(socket);
+        unsafe { libc::umask(umask) };
+        listener?
+    };
+    listener.set_nonblocking(true)?;
+    let root = std::env::current_dir()?.canonicalize()?;
+    let uid = current_uid();
+    let child_signal = ChildSignal::install()?;
+    println!(
+        "{} {}",
+        "Daemon listening on".green().bold(),
+        socket.display()
+    );
+
+    // the streams of the scripts running, answered with the exit code of their process
+    let mut children: HashMap<libc::pid_t, UnixStream> = HashMap::new();
+    loop {
+        let mut fds = [
+            libc::pollfd {
+                fd: listener.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: child_signal.read.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        // SAFETY: `fds` is valid for its length
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) } < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e.into());
+        }
+        if fds[1].revents != 0 {
+            child_signal.drain();
+            reap(&mut children);
+        }
+        if fds[0].revents == 0 {
+            continue;
+        }
+
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                continue;
+            }
+        };
+        // accepted streams take after the listener on some systems
+        if let Err(e) = stream.set_nonblocking(false) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            continue;
+        }
+        match peer_uid(&stream) {
+            Ok(peer) if peer == uid => {}
+            Ok(peer) => {
+                eprintln!(
+                    "{}: Refused a process of user {}",
+                    "Error".red().bold(),
+                    peer
+                );
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                continue;
+            }
+        }
+        let (fds, request) = match receive(&stream) {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("{}: Invalid request: {}", "Error".red().bold(), e);
+                continue;
+            }
+        };
+        // the flags and config of the daemon are the ones of its project only
+        if request.cwd.canonicalize().ok().as_ref() != Some(&root) {
+            let _ = writeln!(&stream, "{}", REFUSED);
+            continue;
+        }
+
+        // SAFETY: the daemon has a single thread, its children are reaped from this loop
+        match unsafe { libc::fork() } {
+            -1 => {
+                eprintln!(
+                    "{}: Failed to fork: {}",
+                    "Error".red().bold(),
+                    std::io::Error::last_os_error()
+                );
+            }
+            0 => {
+                // SAFETY: the descriptors received are open, and the copies replace stdio
+                unsafe {
+                    libc::signal(libc::SIGCHLD, libc::SIG_DFL);
+                    libc::close(listener.as_raw_fd());
+                    for (target, fd) in fds.iter().enumerate() {
+                        libc::dup2(fd.as_raw_fd(), target as RawFd);
+                    }
+                }
+                drop(child_signal);
+                drop(fds);
+                drop(stream);
+                // the streams of the other scripts are answered by the daemon
+                children.clear();
+                let code = match run(request) {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        eprintln!("{}: {}", "Error".red().bold(), e);
+                        1
+                    }
+                };
+                let _ = std::io::stdout().flush();
+                std::process::exit(code);
+            }
+            pid => {
+                drop(fds);
+                children.insert(pid, stream);
+                // a child may have ended before it was recorded
+                reap(&mut children);
+            }
+        }
+    }
+}
+
+/// Answers the streams of the children that ended with their exit code.
+fn reap(children: &mut HashMap<libc::pid_t, UnixStream>) {
+    loop {
+        let mut status = 0;
+        // SAFETY: only children of this process are waited for, without blocking
+        let pid = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+        if pid <= 0 {
+            return;
+        }
+        if let Some(stream) = children.remove(&pid) {
+            let _ = writeln!(&stream, "{}", exit_code(status));
+        }
+    }
+}
+
+/// The exit code of a child from its wait status, 128 plus the signal when a signal
+/// ended it.
+fn exit_code(status: libc::c_int) -> i32 {
+    if libc::WIFEXITED(status) {
+        libc::WEXITSTATUS(status)
+    } else if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else {
+        1
+    }
+}
+
+/// Write end of the pipe of [`ChildSignal`], for the signal handler.
+static CHILD_SIGNAL_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// A pipe written to on `SIGCHLD`, which wakes up the loop of the daemon to reap children.
+struct ChildSignal {
+    read: OwnedFd,
+    _write: OwnedFd,
+}
+
+impl ChildSignal {
+    fn install() -> std::io::Result<Self> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for both ends, owned once created
+        let (read, write) = unsafe {
+            if libc::pipe(fds.as_mut_ptr()) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+        };
+        for fd in [&read, &write] {
+            // SAFETY: `fd` is open
+            unsafe {
+                libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK);
+                libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+        }
+        CHILD_SIGNAL_FD.store(write.as_raw_fd(), Ordering::Relaxed);
+
+        // SAFETY: the handler only does what is safe in a signal handler
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_child_signal as extern "C" fn(libc::c_int) as usize;
+            action.sa_flags = libc::SA_RESTART | libc::SA_NOCLDSTOP;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(libc::SIGCHLD, &action, std::ptr::null_mut()) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(Self {
+            read,
+            _write: write,
+        })
+    }
+
+    /// Empties the pipe, the children are then all reaped at once.
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        loop {
+            // SAFETY: `buf` is valid for its length, and the pipe doesn't block
+            let read =
+                unsafe { libc::read(self.read.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if read <= 0 {
+                return;
+            }
+        }
+    }
+}
+
+extern "C" fn on_child_signal(_: libc::c_int) {
+    // SAFETY: `write` is async-signal-safe, and `errno` is restored for the code the
+    // signal interrupted
+    unsafe {
+        let errno = *errno_location();
+        let fd = CHILD_SIGNAL_FD.load(Ordering::Relaxed);
+        libc::write(fd, [0u8].as_ptr().cast(), 1);
+        *errno_location() = errno;
+    }
+}
+
+fn current_uid() -> libc::uid_t {
+    // SAFETY: always successful
+    unsafe { libc::geteuid() }
+}
+
+/// The user of the process at the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` is valid for `len`
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// The user of the process at the other end of `stream`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: both out pointers are valid
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Reads the stdio and the request of a script, within [`RECEIVE_TIMEOUT`].
+fn receive(stream: &UnixStream) -> anyhow::Result<(Vec<OwnedFd>, Request)> {
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    stream.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+    let fds = recv_fds(stream, 3)?;
+    if fds.len() != 3 {
+        anyhow::bail!("expected 3 file descriptors, got {}", fds.len());
+    }
+    // the client sends nothing after the line until it is answered
+    let mut line = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !line.ends_with(b"\n") {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            anyhow::bail!("timed out");
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        match (&*stream).read(&mut buf)? {
+            0 => anyhow::bail!("the client closed the connection"),
+            read => line.extend_from_slice(&buf[..read]),
+        }
+    }
+    Ok((fds, serde_json::from_slice(&line)?))
+}
+
+/// Sends `fds` along with a byte of data.
+fn send_fds(stream: &UnixStream, fds: &[RawFd]) -> std::io::Result<()> {
+    let data = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let fds_len = std::mem::size_of_val(fds) as u32;
+    // SAFETY: the control buffer is sized for `fds`, and the header written is its first
+    unsafe {
+        let mut control = vec![0u8; libc::CMSG_SPACE(fds_len) as usize];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = control.len() as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+        std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg).cast(), fds.len());
+        if libc::sendmsg(stream.as_raw_fd(), &msg, 0) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Receives up to `max` descriptors sent with [`send_fds`].
+fn recv_fds(stream: &UnixStream, max: usize) -> std::io::Result<Vec<OwnedFd>> {
+    let mut data = [0u8];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let mut fds = Vec::new();
+    // SAFETY: the control buffer outlives the message, and the descriptors in it were
+    // just received so nothing else owns them
+    unsafe {
+        let mut control = vec![0u8; libc::CMSG_SPACE((max * size_of::<RawFd>()) as u32) as usize];
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = control.len() as _;
+        match libc::recvmsg(stream.as_raw_fd(), &mut msg, 0) {
+            n if n < 0 => return Err(std::io::Error::last_os_error()),
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            _ => {}
+        }
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                let len =
+                    ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize) / size_of::<RawFd>();
+                for i in 0..len {
+                    fds.push(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Ok(fds)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn test_fd_passing() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (mut write, mut read) = UnixStream::pair().unwrap();
+        super::send_fds(&client, &[write.as_raw_fd()]).unwrap();
+        let fds = super::recv_fds(&server, 3).unwrap();
+        assert_eq!(fds.len(), 1);
+
+        let mut received = UnixStream::from(fds.into_iter().next().unwrap());
+        received.write_all(b"hi").unwrap();
+        write.write_all(b"!").unwrap();
+        let mut buf = [0u8; 3];
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi!");
+    }
+
+    #[test]
+    fn test_receive_times_out() {
+        let (client, server) = UnixStream::pair().unwrap();
+        super::send_fds(&client, &[0, 1, 2]).unwrap();
+        (&client).write_all(b"{\"cwd\":").unwrap();
+        let start = std::time::Instant::now();
+        assert!(super::receive(&server).is_err());
+        assert!(start.elapsed() < super::RECEIVE_TIMEOUT * 2);
+    }
+}
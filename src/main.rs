@@ -5,7 +5,22 @@ use colored::*;
 use compact_str::CompactString;
 use rsquickjs::{context::EvalOptions, Promise};
 use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+use tracing_subscriber::{
+    filter::Targets, layer::SubscriberExt, util::SubscriberInitExt, Layer as _,
+};
 use xmas::utils::ctx::CtxExtension;
+use xmas_js_modules::inspector::DEFAULT_ADDRESS as DEFAULT_INSPECT_ADDRESS;
+use xmas_js_modules::module::module_builder::GlobalAttachment;
+use xmas_js_modules::permissions::{BlackOrWhiteList, Permissions};
+use xmas_js_modules::source_map::SourceMap;
+
+#[cfg(unix)]
+mod daemon;
+#[cfg(feature = "otel")]
+mod otel;
 
 /// Xmas.JS - A Modern System Scripting Runtime for the JavaScript Era
 #[derive(Parser)]
@@ -20,20 +35,267 @@ struct Cli {
     #[arg(long, global = true, alias = "cwd")]
     working_dir: Option<PathBuf>,
 
+    /// What to do with uncaught errors nobody handles (warn, exit)
+    #[arg(long, global = true, default_value = "exit")]
+    unhandled_rejections: xmas_js_modules::process::UnhandledErrorMode,
+
+    /// Output format of `node:test` results (spec, tap)
+    #[arg(long, global = true, default_value = "spec")]
+    test_reporter: xmas_js_modules::test_runner::TestReporter,
+
+    /// Format of the console output of scripts (text, json), json printing one object per
+    /// call for log aggregators
+    #[arg(long, global = true)]
+    log_format: Option<xmas_js_modules::console::LogType>,
+
+    /// Download remote `https:` modules again instead of using the cache
+    #[arg(long, global = true)]
+    reload: bool,
+
+    /// Parse every module instead of reusing its cached bytecode
+    #[arg(long, global = true)]
+    no_bytecode_cache: bool,
+
+    /// Abort the script, or each REPL input, after this many seconds
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// Bundle the script with Rolldown before running it
+    #[arg(long, global = true, overrides_with = "no_bundle")]
+    bundle: bool,
+
+    /// Load the modules of the script one by one, transpiling TypeScript and JSX (default)
+    #[arg(long, global = true, overrides_with = "bundle")]
+    no_bundle: bool,
+
+    /// Lower syntax newer than this target when transpiling, e.g. es2015 or chrome80
+    #[arg(long, global = true)]
+    target: Option<String>,
+
+    /// Decorators to compile (legacy, tc39), legacy being TypeScript's experimentalDecorators
+    #[arg(long, global = true)]
+    decorators: Option<xmas_js_modules::script::Decorators>,
+
+    /// Emit the design-time types of decorated members, like emitDecoratorMetadata
+    #[arg(long, global = true)]
+    emit_decorator_metadata: bool,
+
+    /// Type-check the script with `xmas check` before running it
+    #[arg(long, global = true)]
+    check: bool,
+
+    /// Print the memory usage of the JS heap when the script exits
+    #[arg(long, global = true)]
+    heap_stats: bool,
+
+    /// Export the spans of downloads, installs, evaluations and fetch calls to the OTLP
+    /// collector of OTEL_EXPORTER_OTLP_ENDPOINT
+    #[arg(long, global = true, env = "XMAS_OTEL")]
+    otel: bool,
+
+    /// Log runtime metrics and event loop lag every this many seconds
+    #[arg(long, global = true, value_name = "SECONDS", value_parser = parse_timeout)]
+    metrics_interval: Option<Duration>,
+
+    /// Load environment variables from this file, after the `env_file` ones of the config
+    #[arg(long, global = true, value_name = "PATH")]
+    env_file: Vec<PathBuf>,
+
+    #[command(flatten)]
+    permissions: PermissionFlags,
+
+    /// Append every fs/net/module access of the script to this JSONL file
+    #[arg(long, global = true)]
+    audit_log: Option<PathBuf>,
+
+    /// Ask before granting fs/net/env access the policy denies (none is granted without a permissions policy in the config)
+    #[arg(long, global = true)]
+    prompt: bool,
+
+    /// Serve the Chrome DevTools inspector while the script runs
+    #[arg(
+        long,
+        global = true,
+        value_name = "HOST:PORT",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_INSPECT_ADDRESS
+    )]
+    inspect: Option<SocketAddr>,
+
+    /// Like --inspect, but wait for DevTools to attach before running the script
+    #[arg(
+        long,
+        global = true,
+        value_name = "HOST:PORT",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_INSPECT_ADDRESS
+    )]
+    inspect_brk: Option<SocketAddr>,
+
+    /// Run this code instead of a script file, `-` as the script reads it from stdin
+    #[arg(short = 'e', long, value_name = "CODE", conflicts_with = "print")]
+    eval: Option<String>,
+
+    /// Like --eval, printing the value of the expression
+    #[arg(short = 'p', long, value_name = "CODE")]
+    print: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Script file to run (if no subcommand is provided)
-    #[arg(trailing_var_arg = true)]
+    /// Script file to run (if no subcommand is provided), the arguments after it are the
+    /// script's own
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "SCRIPT [ARGS]"
+    )]
     script: Vec<OsString>,
 }
 
+/// Deno-style permission flags. Once one is passed, they replace the policy of xmas.toml
+/// and the script gets nothing they don't grant.
+#[derive(clap::Args)]
+struct PermissionFlags {
+    /// Allow reading files, anywhere or only the given files and directories
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_read: Option<Vec<String>>,
+
+    /// Allow writing files, anywhere or only the given files and directories
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_write: Option<Vec<String>>,
+
+    /// Allow network access, to any host or only the given ones (`*.example.com` included)
+    #[arg(
+        long,
+        global = true,
+        value_name = "HOSTS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_net: Option<Vec<String>>,
+
+    /// Allow reading environment variables, all or only the given ones
+    #[arg(
+        long,
+        global = true,
+        value_name = "VARS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true
+    )]
+    allow_env: Option<Vec<String>>,
+
+    /// Allow everything, including FFI
+    #[arg(short = 'A', long, global = true)]
+    allow_all: bool,
+}
+
+impl Cli {
+    /// Whether this runs a script file the way a daemon, started with flags of its own,
+    /// would run it. The daemon only gets the script, its arguments and the environment,
+    /// so any flag changing how the script runs keeps it out of the daemon.
+    #[cfg(unix)]
+    fn daemon_can_run(&self) -> bool {
+        self.command.is_none()
+            && self.script.first().is_some_and(|script| script != "-")
+            && self.eval.is_none()
+            && self.print.is_none()
+            && !self.verbose
+            && self.unhandled_rejections == Default::default()
+            && self.test_reporter == Default::default()
+            && self.log_format.is_none()
+            && !self.reload
+            && !self.no_bytecode_cache
+            && self.timeout.is_none()
+            && !self.bundle
+            && self.target.is_none()
+            && self.decorators.is_none()
+            && !self.emit_decorator_metadata
+            && !self.check
+            && !self.heap_stats
+            && !self.otel
+            && self.metrics_interval.is_none()
+            && self.env_file.is_empty()
+            && self.permissions.permissions().is_none()
+            && self.audit_log.is_none()
+            && !self.prompt
+            && self.inspect.is_none()
+            && self.inspect_brk.is_none()
+    }
+}
+
+impl PermissionFlags {
+    /// The permissions granted by the flags, `None` when none was passed.
+    fn permissions(&self) -> Option<Permissions> {
+        if self.allow_all {
+            return Some(Permissions::allow_all());
+        }
+        if self.allow_read.is_none()
+            && self.allow_write.is_none()
+            && self.allow_net.is_none()
+            && self.allow_env.is_none()
+        {
+            return None;
+        }
+        // Paths are relative to the working directory the flags were given in, not to the
+        // one the script may change to
+        let cwd = std::env::current_dir().unwrap_or_default();
+        // an empty list means the flag was passed without values
+        let list = |items: &Option<Vec<String>>, paths: bool| match items {
+            None => BlackOrWhiteList::deny_all(),
+            Some(items) if items.is_empty() => BlackOrWhiteList::allow_all(),
+            Some(items) => BlackOrWhiteList::whitelist(
+                items
+                    .iter()
+                    .map(|item| {
+                        // a trailing `*` grants everything below a path, which may be a
+                        // directory created later
+                        if paths {
+                            format!("{}*", cwd.join(item).display())
+                        } else {
+                            item.clone()
+                        }
+                    })
+                    .collect(),
+            ),
+        };
+        Some(Permissions {
+            fs_read: list(&self.allow_read, true),
+            fs_write: list(&self.allow_write, true),
+            net: list(&self.allow_net, false),
+            env: list(&self.allow_env, false),
+            stdio: true,
+            ..Permissions::deny_all()
+        })
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     // ==================== Package Manager ====================
     /// Install packages defined in package.json
     #[command(alias = "i")]
-    Install,
+    Install {
+        /// Fail if xmas.lock would change, for CI
+        #[arg(long)]
+        frozen_lockfile: bool,
+    },
 
     /// Add package to package.json
     #[command(alias = "a")]
@@ -58,17 +320,28 @@ enum Commands {
         dev: bool,
     },
 
-    /// Run a script defined in package.json
+    /// Run a script defined in package.json, or else the file `name`,
+    /// `scripts/<name>.ts` or a bin from node_modules/.bin
     Run {
-        /// Script name
-        name: CompactString,
+        /// Script, file or bin name, the scripts are listed when left out
+        name: Option<CompactString>,
         /// Watch files for changes
         #[arg(long)]
         watch: Vec<PathBuf>,
+        /// List the scripts of package.json instead of running one
+        #[arg(long)]
+        list: bool,
+        /// Print the list as a JSON object of names and commands
+        #[arg(long, requires = "list")]
+        json: bool,
     },
 
     /// Prepare and save a newly planned lockfile
-    Update,
+    Update {
+        /// Fail if xmas.lock would change, for CI
+        #[arg(long)]
+        frozen_lockfile: bool,
+    },
 
     /// Update packages to the latest available version
     Upgrade {
@@ -111,6 +384,12 @@ enum Commands {
         args: Vec<OsString>,
     },
 
+    /// Download remote `https:` modules into the local cache
+    Cache {
+        /// Module URLs to download
+        urls: Vec<String>,
+    },
+
     // ==================== Bundler ====================
     /// Bundle TypeScript/JavaScript files (powered by Rolldown)
     #[command(alias = "bundle")]
@@ -118,9 +397,9 @@ enum Commands {
         /// Entry point(s) for the bundle
         entry: Vec<PathBuf>,
 
-        /// Output directory
-        #[arg(short = 'o', long, default_value = "dist")]
-        output_dir: PathBuf,
+        /// Output directory (default: dist)
+        #[arg(short = 'o', long)]
+        output_dir: Option<PathBuf>,
 
         /// Output filename
         #[arg(short = 'n', long)]
@@ -130,26 +409,60 @@ enum Commands {
         #[arg(short = 'm', long)]
         minify: bool,
 
+        /// Don't rename local identifiers when minifying
+        #[arg(long = "no-mangle", action = clap::ArgAction::SetFalse)]
+        mangle: bool,
+
+        /// Don't fold constants or drop dead code when minifying
+        #[arg(long = "no-compress", action = clap::ArgAction::SetFalse)]
+        compress: bool,
+
+        /// Keep the names of functions and classes
+        #[arg(long)]
+        keep_names: bool,
+
         /// Enable source maps
         #[arg(short = 's', long)]
         source_map: bool,
 
-        /// Target format (esm, cjs, iife)
-        #[arg(short = 'f', long, default_value = "esm")]
-        format: xmas_bundler::BundleFormat,
+        /// Target format (esm, cjs, iife) (default: esm)
+        #[arg(short = 'f', long)]
+        format: Option<xmas_bundler::BundleFormat>,
 
         /// External modules (won't be bundled)
         #[arg(short = 'e', long)]
         external: Vec<String>,
     },
 
+    // ==================== Type Checker ====================
+    /// Report TypeScript type errors without emitting, using tsgo or tsc from node_modules or PATH
+    Check {
+        /// Entry points to check along with their imports (default: the project of tsconfig.json)
+        entry: Vec<PathBuf>,
+    },
+
+    // ==================== Test Runner ====================
+    /// Run `*.test.ts` and `*.spec.ts` files, each in a runtime of its own
+    Test {
+        /// Test files, or directories to search for them (default: the working directory)
+        paths: Vec<PathBuf>,
+
+        /// Report the lines of the sources the tests ran
+        #[arg(long)]
+        coverage: bool,
+    },
+
     // ==================== REPL ====================
     /// Start the interactive REPL
     Repl,
+
+    // ==================== Daemon ====================
+    /// Keep a runtime warm for the scripts run in this project, which then start in a
+    /// process forked from it with the flags the daemon was started with
+    Daemon,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Set working directory if specified
@@ -157,25 +470,208 @@ async fn main() -> anyhow::Result<()> {
         std::env::set_current_dir(cwd)?;
     }
 
-    match cli.command {
+    #[cfg(unix)]
+    {
+        if matches!(cli.command, Some(Commands::Daemon)) {
+            return run_daemon(cli);
+        }
+        // The daemon of the project runs the script, if there is one
+        if cli.daemon_can_run() {
+            if let Some(code) = daemon::forward(&cli.script)? {
+                std::process::exit(code);
+            }
+        }
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(cli))
+}
+
+/// Applies the flags and the config shared by every command, returning the config and the
+/// permissions scripts run with.
+async fn setup(
+    cli: &Cli,
+) -> anyhow::Result<(xmas_package_manager::config::Config, Option<Permissions>)> {
+    xmas_js_modules::process::set_unhandled_error_mode(cli.unhandled_rejections);
+    xmas_js_modules::test_runner::set_test_reporter(cli.test_reporter);
+    xmas_js_modules::module::package::remote::set_reload(cli.reload);
+    xmas_js_modules::module::package::bytecode::set_enabled(!cli.no_bytecode_cache);
+    init_tracing(cli.metrics_interval.is_some(), cli.otel)?;
+    let config = load_config().await?;
+    // Variables the process was started with win over the ones of the files
+    let env_files = env_files(&config);
+    xmas_package_manager::env_file::load_env_files(&env_files, &cli.env_file).map_err(|e| {
+        let causes: Vec<String> = e.chain().map(|cause| cause.to_string()).collect();
+        anyhow::anyhow!("{}", causes.join(": "))
+    })?;
+    // Flags override the [transform] section of the config
+    let transform = config.transform.clone();
+    xmas_js_modules::script::set_default_options(xmas_js_modules::script::ScriptTransformOptions {
+        target: cli.target.clone().or(transform.target),
+        decorators: cli.decorators.unwrap_or(match transform.decorators {
+            Some(xmas_package_manager::config::Decorators::Tc39) => {
+                xmas_js_modules::script::Decorators::Tc39
+            }
+            _ => xmas_js_modules::script::Decorators::Legacy,
+        }),
+        emit_decorator_metadata: cli.emit_decorator_metadata
+            || transform.emit_decorator_metadata.unwrap_or_default(),
+        jsx: jsx_options(config.jsx.clone()),
+    });
+    // Permissions given on the command line replace the ones of the config
+    let permissions = cli.permissions.permissions().or(config.permissions.clone());
+    Ok((config, permissions))
+}
+
+/// Serves the scripts of the project with `xmas daemon`. The runtime and its context,
+/// modules initialized, are set up on a tokio runtime of one thread, gone before the
+/// daemon forks, so each forked process only evaluates its script.
+#[cfg(unix)]
+fn run_daemon(cli: Cli) -> anyhow::Result<()> {
+    if cli.otel {
+        anyhow::bail!("--otel can't be used with the daemon");
+    }
+    let (warm, config, permissions) = {
+        let setup_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        setup_runtime.block_on(async {
+            let (config, permissions) = setup(&cli).await?;
+            let vsys = build_vsys(permissions.clone(), cli.audit_log.clone(), cli.prompt)?;
+            let warm = WarmRuntime::new(vsys, log_type(&cli, &config)).await?;
+            anyhow::Ok((warm, config, permissions))
+        })?
+    };
+    let warm = std::cell::RefCell::new(Some(warm));
+    let log_type = log_type(&cli, &config);
+
+    daemon::serve(|request| {
+        // the forked process takes over the environment of the script
+        std::env::set_current_dir(&request.cwd)?;
+        for (key, _) in std::env::vars_os() {
+            std::env::remove_var(key);
+        }
+        for (key, value) in &request.env {
+            std::env::set_var(key, value);
+        }
+        xmas_package_manager::env_file::load_env_files(&env_files(&config), &[])
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let Some((script_path, args)) = request.args.split_first() else {
+            anyhow::bail!("No script to run");
+        };
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?
+            .block_on(run_script(
+                script_path,
+                None,
+                &args,
+                false,
+                permissions.clone(),
+                cli.audit_log.clone(),
+                cli.prompt,
+                None,
+                cli.timeout,
+                cli.heap_stats,
+                cli.metrics_interval,
+                log_type.clone(),
+                warm.borrow_mut().take(),
+            ))
+    })
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let (config, permissions) = setup(&cli).await?;
+    let log_type = log_type(&cli, &config);
+
+    let result = match cli.command {
         // No command - enter REPL or run script
         None => {
-            if cli.script.is_empty() {
+            // Code given inline is bundled from a file of its own, next to what it imports
+            let (inline, args) = if let Some(code) = cli.eval {
+                (Some(code), &cli.script[..])
+            } else if let Some(code) = cli.print {
+                (
+                    Some(format!("console.log(await (\n{}\n));\n", code)),
+                    &cli.script[..],
+                )
+            } else if cli.script.first().is_some_and(|script| script == "-") {
+                let mut code = String::new();
+                std::io::Read::read_to_string(&mut std::io::stdin(), &mut code)?;
+                (Some(code), &cli.script[1..])
+            } else {
+                (None, cli.script.get(1..).unwrap_or_default())
+            };
+
+            if cli.script.is_empty() && inline.is_none() {
                 // No script provided, enter REPL
-                xmas::repl().await
+                xmas::repl(cli.timeout).await
             } else {
                 // Run script file
-                let script_path = cli.script[0].to_string_lossy().to_string();
-                run_script(&script_path, &cli.script[1..]).await
+                let script_path = match &inline {
+                    Some(_) => format!(".xmas-eval-{}.ts", std::process::id()),
+                    None => cli.script[0].to_string_lossy().to_string(),
+                };
+                if cli.check
+                    && inline.is_none()
+                    && !run_check(&[PathBuf::from(&script_path)]).await?
+                {
+                    std::process::exit(1);
+                }
+                let inspect = match (cli.inspect_brk, cli.inspect) {
+                    (Some(addr), _) => Some((addr, true)),
+                    (None, addr) => addr.map(|addr| (addr, false)),
+                };
+                run_script(
+                    &script_path,
+                    inline,
+                    args,
+                    cli.bundle && !cli.no_bundle,
+                    permissions,
+                    cli.audit_log,
+                    cli.prompt,
+                    inspect,
+                    cli.timeout,
+                    cli.heap_stats,
+                    cli.metrics_interval,
+                    log_type,
+                    None,
+                )
+                .await
+            }
+        }
+
+        // Type checker command
+        Some(Commands::Check { entry }) => {
+            if !run_check(&entry).await? {
+                std::process::exit(1);
             }
+            Ok(())
+        }
+
+        // Test runner command
+        Some(Commands::Test { paths, coverage }) => {
+            run_tests(paths, permissions, coverage, cli.timeout).await
         }
 
         // REPL command
-        Some(Commands::Repl) => xmas::repl().await,
+        Some(Commands::Repl) => xmas::repl(cli.timeout).await,
+
+        // Served by `run_daemon` before the async runtime starts
+        Some(Commands::Daemon) => anyhow::bail!("The daemon needs a Unix system"),
 
         // Package manager commands
-        Some(Commands::Install) => {
-            run_pm(xmas_package_manager::Subcommand::Install, cli.verbose).await
+        Some(Commands::Install { frozen_lockfile }) => {
+            run_pm_frozen(
+                xmas_package_manager::Subcommand::Install,
+                cli.verbose,
+                frozen_lockfile,
+            )
+            .await
         }
         Some(Commands::Add { names, dev, pin }) => {
             run_pm(
@@ -191,15 +687,30 @@ async fn main() -> anyhow::Result<()> {
             )
             .await
         }
-        Some(Commands::Run { name, watch }) => {
+        Some(Commands::Run {
+            name,
+            watch,
+            list,
+            json,
+        }) => {
             run_pm(
-                xmas_package_manager::Subcommand::Run { name, watch },
+                xmas_package_manager::Subcommand::Run {
+                    name,
+                    watch,
+                    list,
+                    json,
+                },
                 cli.verbose,
             )
             .await
         }
-        Some(Commands::Update) => {
-            run_pm(xmas_package_manager::Subcommand::Update, cli.verbose).await
+        Some(Commands::Update { frozen_lockfile }) => {
+            run_pm_frozen(
+                xmas_package_manager::Subcommand::Update,
+                cli.verbose,
+                frozen_lockfile,
+            )
+            .await
         }
         Some(Commands::Upgrade { pin }) => {
             run_pm(
@@ -238,37 +749,107 @@ async fn main() -> anyhow::Result<()> {
             .await
         }
 
+        Some(Commands::Cache { urls }) => {
+            for url in urls {
+                xmas_js_modules::module::package::remote::cache(&url, cli.reload)?;
+                println!("{} {}", "Cached".green().bold(), url);
+            }
+            Ok(())
+        }
+
         // Bundler command
         Some(Commands::Bun {
             entry,
             output_dir,
             output_filename,
             minify,
+            mangle,
+            compress,
+            keep_names,
             source_map,
             format,
             external,
         }) => {
+            // Flags override the [bundle] defaults of the config
+            let defaults = config.bundle;
+            let format = match (format, defaults.format) {
+                (Some(format), _) => format,
+                (None, Some(format)) => {
+                    <xmas_bundler::BundleFormat as clap::ValueEnum>::from_str(&format, true)
+                        .map_err(|e| anyhow::anyhow!("Invalid bundle format in config: {}", e))?
+                }
+                (None, None) => xmas_bundler::BundleFormat::Esm,
+            };
             let config = xmas_bundler::BundleConfig {
                 entry,
-                output_dir,
+                output_dir: output_dir
+                    .or(defaults.output_dir)
+                    .unwrap_or_else(|| PathBuf::from("dist")),
                 output_filename,
-                minify,
-                source_map,
+                minify: minify || defaults.minify.unwrap_or_default(),
+                mangle,
+                compress,
+                keep_names,
+                source_map: source_map || defaults.source_map.unwrap_or_default(),
                 format,
                 tree_shake: true,
-                external,
+                external: defaults.external.into_iter().chain(external).collect(),
+                alias: xmas_js_modules::module::package::resolver::aliases(),
             };
             xmas_bundler::bundle(config)
                 .await
                 .map_err(|e| anyhow::anyhow!("{}", e))
         }
+    };
+    #[cfg(feature = "otel")]
+    otel::shutdown();
+    result
+}
+
+/// Logs warnings, and the runtime metrics with `metrics`, to the terminal. With `otel`, the
+/// spans are exported as well.
+fn init_tracing(metrics: bool, otel: bool) -> anyhow::Result<()> {
+    use xmas_js_modules::utils::metrics::METRICS_TARGET;
+
+    // metrics are logged at info level under their own target
+    let mut filter = Targets::new().with_default(tracing::Level::WARN);
+    if metrics {
+        filter = filter.with_target(METRICS_TARGET, tracing::Level::INFO);
+    }
+    let registry = tracing_subscriber::registry().with(
+        tracing_subscriber::fmt::layer()
+            .without_time()
+            .with_filter(filter),
+    );
+    #[cfg(feature = "otel")]
+    let registry = registry.with(if otel {
+        let spans = Targets::new().with_default(tracing::Level::INFO);
+        Some(otel::layer()?.with_filter(spans))
+    } else {
+        None
+    });
+    #[cfg(not(feature = "otel"))]
+    if otel {
+        anyhow::bail!("--otel needs xmas built with the `otel` feature");
     }
+    registry.init();
+    Ok(())
 }
 
 async fn run_pm(cmd: xmas_package_manager::Subcommand, verbose: bool) -> anyhow::Result<()> {
+    run_pm_frozen(cmd, verbose, false).await
+}
+
+/// Runs `cmd`, failing instead of changing xmas.lock with `frozen_lockfile`.
+async fn run_pm_frozen(
+    cmd: xmas_package_manager::Subcommand,
+    verbose: bool,
+    frozen_lockfile: bool,
+) -> anyhow::Result<()> {
     let args = xmas_package_manager::Args {
         verbose,
         immutable: false,
+        frozen_lockfile,
         working_dir: None,
         cmd,
     };
@@ -277,95 +858,802 @@ async fn run_pm(cmd: xmas_package_manager::Subcommand, verbose: bool) -> anyhow:
         .map_err(|e| anyhow::anyhow!("{}", e))
 }
 
-async fn run_script(script_path: &str, _args: &[OsString]) -> anyhow::Result<()> {
-    use rsquickjs::{AsyncContext, AsyncRuntime};
-    use std::sync::Arc;
+/// A runtime loading modules the way xmas does, with its handlers of rejections, promises
+/// and source maps set, and the native modules to attach to its contexts.
+async fn new_runtime() -> anyhow::Result<(rsquickjs::AsyncRuntime, GlobalAttachment)> {
     use xmas_js_modules::module::module_builder::ModuleBuilder;
     use xmas_js_modules::module::package::loader::PackageLoader;
     use xmas_js_modules::module::package::resolver::PackageResolver;
-    use xmas_js_modules::permissions::Permissions;
 
-    // Initialize tracing
-    tracing_subscriber::fmt::Subscriber::builder()
-        .with_max_level(tracing::Level::WARN)
-        .without_time()
-        .init();
+    let runtime = rsquickjs::AsyncRuntime::new()?;
+    let (resolver, loader, ga) = ModuleBuilder::default().build();
+    runtime
+        .set_loader((resolver, PackageResolver), (loader, PackageLoader))
+        .await;
+    runtime
+        .set_rejection_handler(xmas_js_modules::process::handle_rejection)
+        .await;
+    runtime
+        .set_source_map_handler(Some(xmas_js_modules::source_map::source_map_handler()))
+        .await;
+    runtime
+        .set_promise_hook(Some(xmas_js_modules::async_hooks::promise_hook_tracker()))
+        .await;
+    Ok((runtime, ga))
+}
 
-    // Get the script name without extension for output
-    let script_name = std::path::Path::new(script_path)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("bundle");
+/// The permissions, audit log and prompt scripts run with.
+fn build_vsys(
+    configured: Option<Permissions>,
+    audit_log: Option<PathBuf>,
+    prompt: bool,
+) -> anyhow::Result<std::sync::Arc<xmas_vsys::Vsys>> {
+    let mut vsys = xmas_vsys::Vsys::builder();
+    if prompt {
+        vsys = vsys
+            .permissions(configured.unwrap_or(Permissions {
+                stdio: true,
+                ..Permissions::deny_all()
+            }))
+            .prompt(xmas_vsys::permissions::tty_prompt);
+    } else {
+        vsys = vsys.permissions(configured.unwrap_or_else(Permissions::allow_all));
+    }
+    if let Some(audit_log) = audit_log {
+        vsys = vsys.audit(xmas_vsys::AuditSink::jsonl(audit_log)?);
+    }
+    Ok(std::sync::Arc::new(vsys.build()))
+}
 
-    // Bundle the script first
-    println!("{} {}...", "Bundling".cyan().bold(), script_path);
-    let bundle_config = xmas_bundler::BundleConfig {
-        entry: vec![PathBuf::from(script_path)],
-        output_dir: PathBuf::from("."),
-        output_filename: Some(format!("{}.js", script_name)),
-        minify: false,
-        source_map: false,
-        format: xmas_bundler::BundleFormat::Esm,
-        tree_shake: true,
-        external: vec![],
-    };
-    xmas_bundler::bundle(bundle_config)
-        .await
-        .map_err(|e| anyhow::anyhow!("Bundle error: {}", e))?;
+/// A runtime and a context with the modules initialized, ready to evaluate a script.
+/// The daemon sets one up before it forks, so the processes it forks only evaluate.
+struct WarmRuntime {
+    runtime: rsquickjs::AsyncRuntime,
+    context: rsquickjs::AsyncContext,
+}
 
-    // Now run the bundled output
-    let bundled_path = format!("{}.js", script_name);
-    println!("{} {}...", "Running".green().bold(), bundled_path);
+impl WarmRuntime {
+    async fn new(
+        vsys: std::sync::Arc<xmas_vsys::Vsys>,
+        log_type: xmas_js_modules::console::LogType,
+    ) -> anyhow::Result<Self> {
+        let (runtime, ga) = new_runtime().await?;
+        let context = rsquickjs::AsyncContext::full(&runtime).await?;
+        context
+            .with(|ctx| {
+                xmas_js_modules::init(&ctx, vsys, log_type)?;
+                ga.attach(&ctx)
+            })
+            .await?;
+        Ok(Self { runtime, context })
+    }
+}
 
-    let runtime = AsyncRuntime::new()?;
-    let context = AsyncContext::full(&runtime).await?;
+/// Runs `script_path`, or the `inline` code named after it, bundled or module by module,
+/// on the `warm` runtime of the daemon if there is one.
+async fn run_script(
+    script_path: &str,
+    inline: Option<String>,
+    args: &[OsString],
+    bundle: bool,
+    configured: Option<Permissions>,
+    audit_log: Option<PathBuf>,
+    prompt: bool,
+    inspect: Option<(SocketAddr, bool)>,
+    timeout: Option<Duration>,
+    heap_stats: bool,
+    metrics_interval: Option<Duration>,
+    log_type: xmas_js_modules::console::LogType,
+    warm: Option<WarmRuntime>,
+) -> anyhow::Result<()> {
+    use xmas_js_modules::inspector::Inspector;
+    use xmas_js_modules::utils::ctx::until_deadline;
+    use xmas_js_modules::utils::metrics::spawn_metrics_reporter;
 
-    let (resolver, loader, ga) = ModuleBuilder::default().build();
-    runtime
-        .set_loader((resolver, PackageResolver), (loader, PackageLoader))
-        .await;
+    // One-liners only print what the code does
+    let quiet = inline.is_some();
 
-    // Read the bundled output
-    let script_content = std::fs::read_to_string(&bundled_path)?;
+    // Bundle the script first, or load its modules one by one as it imports them
+    let bundled = if bundle {
+        Some(bundle_script(script_path, inline.as_deref(), quiet).await?)
+    } else {
+        None
+    };
+    let entry_path = match &bundled {
+        Some(bundled) => bundled.path.clone(),
+        None => std::env::current_dir()?
+            .join(script_path)
+            .to_string_lossy()
+            .into_owned(),
+    };
+    if !quiet {
+        println!("{} {}...", "Running".green().bold(), entry_path);
+    }
 
-    rsquickjs::async_with!(context => |ctx| {
-        let vsys = xmas_vsys::Vsys::builder()
-            .permissions(Permissions::allow_all())
-            .build();
-        xmas_js_modules::init(&ctx, Arc::new(vsys), xmas_js_modules::console::LogType::Stdio)?;
-        ga.attach(&ctx)?;
+    // `process.argv` is the runtime, the script and the arguments following it
+    let script = match &inline {
+        Some(_) => "[eval]".to_string(),
+        None => std::fs::canonicalize(script_path).map_or_else(
+            |_| script_path.to_string(),
+            |path| path.to_string_lossy().into_owned(),
+        ),
+    };
+    let exe = std::env::current_exe().map_or_else(
+        |_| "xmas".to_string(),
+        |exe| exe.to_string_lossy().into_owned(),
+    );
+    let mut argv = vec![exe, script];
+    argv.extend(args.iter().map(|arg| arg.to_string_lossy().into_owned()));
+    xmas_js_modules::process::set_argv(argv);
+
+    let WarmRuntime { runtime, context } = match warm {
+        Some(warm) => {
+            // the context was set up by the daemon, before this process had its arguments
+            warm.context
+                .with(|ctx| xmas_js_modules::process::refresh(&ctx))
+                .await?;
+            warm
+        }
+        None => WarmRuntime::new(build_vsys(configured, audit_log, prompt)?, log_type).await?,
+    };
+    let reporter = metrics_interval.map(|period| spawn_metrics_reporter(&runtime, period));
+
+    // DevTools attaches to the entry, bundled or not, which is what actually runs
+    let mut inspector = match inspect {
+        Some((addr, _)) => {
+            let inspector = Inspector::listen(addr, &entry_path)?;
+            eprintln!(
+                "{} {}",
+                "Debugger listening on".cyan().bold(),
+                inspector.url()
+            );
+            Some(inspector)
+        }
+        None => None,
+    };
+    let wait_for_debugger = inspect.is_some_and(|(_, wait)| wait);
+
+    let result = rsquickjs::async_with!(context => |ctx| {
         let poller = ctx.get_background_task_poller();
+        if let Some(inspector) = inspector.as_mut().filter(|_| wait_for_debugger) {
+            eprintln!("Waiting for the debugger to attach...");
+            inspector.wait_for_debugger(&ctx).await;
+        }
+        // the clock starts once DevTools attached, and keeps running for timers and callbacks
+        ctx.set_deadline(timeout.map(|timeout| Instant::now() + timeout));
 
-        // Execute the bundled script directly (already transformed JS)
-        match ctx.eval_with_options(
-            script_content,
-            EvalOptions {
-                promise: true,
-                filename: Some(bundled_path.into()),
-                ..Default::default()
-            },
-        ) {
+        let span = tracing::info_span!("eval", script = %entry_path);
+        let promise = span.in_scope(|| match bundled {
+            // The bundle is already transformed JS, evaluated as the entry module
+            Some(bundled) => evaluate_entry(
+                &ctx,
+                &bundled.path,
+                bundled.content,
+                bundled.source_map.as_deref(),
+            ),
+            None => import_entry(&ctx, &entry_path, inline.as_deref()),
+        });
+        match promise {
             Ok(promise) => {
                 let promise : Promise<'_> = promise;
-                match promise.into_future::<()>().await {
+                let result = match inspector.as_mut() {
+                    Some(inspector) => {
+                        until_deadline(&ctx, inspector.run(&ctx, promise.into_future::<()>()))
+                            .instrument(span)
+                            .await
+                    }
+                    None => until_deadline(&ctx, promise.into_future::<()>()).instrument(span).await,
+                };
+                match result {
                     Ok(value) => {
-                        println!("{}: {:?}", "Result".green().bold(), value);
+                        if !quiet {
+                            println!("{}: {:?}", "Result".green().bold(), value);
+                        }
                     },
                     Err(e) => {
                         eprintln!("{}: {}", "Error".red().bold(), e);
-                                        let err = ctx.catch();
-                eprintln!("{}: {:?}", "Exception".red().bold(), err.into_exception().map(|e| e.to_string()));
+                        print_exception(&ctx);
                     }
                 }
             }
             Err(e) => {
                 eprintln!("{}: {}", "Error".red().bold(), e);
-                let err = ctx.catch();
-                eprintln!("{}: {:?}", "Exception".red().bold(), err.into_exception().map(|e| e.to_string()));
+                print_exception(&ctx);
             }
         }
         poller.abort();
+        Ok::<_, anyhow::Error>(())
+    })
+    .await;
+
+    // Let referenced work such as `node:test` tests and timers run to completion, a
+    // pending timer isn't interrupted so the deadline is enforced here as well
+    let drained = xmas_js_modules::event_loop::run_until_drained(&runtime, &context);
+    match context.with(|ctx| ctx.deadline()).await {
+        Some(deadline) => {
+            if tokio::time::timeout_at(deadline.into(), drained)
+                .await
+                .is_err()
+            {
+                eprintln!(
+                    "{}: Script timed out after {:?}",
+                    "Error".red().bold(),
+                    timeout.unwrap_or_default()
+                );
+                std::process::exit(1);
+            }
+        }
+        None => drained.await,
+    }
+    let exit_code = context
+        .with(|ctx| xmas_js_modules::process::emit_exit(&ctx))
+        .await;
+    if let Some(reporter) = reporter {
+        reporter.abort();
+    }
+    if heap_stats {
+        print_heap_stats(&runtime.memory_stats().await);
+    }
+    if xmas_js_modules::test_runner::has_failures() {
+        std::process::exit(1);
+    }
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+    result
+}
+
+/// Type checkers tried in order, the native port of TypeScript first.
+const TYPE_CHECKERS: [&str; 2] = ["tsgo", "tsc"];
+
+/// Type-checks `entries` and the modules they import, or the project of tsconfig.json when
+/// there are none, printing the diagnostics with code frames. Returns whether it passed.
+///
+/// Types are only stripped when bundling, so this is the one place wrong types are reported.
+async fn run_check(entries: &[PathBuf]) -> anyhow::Result<bool> {
+    let mut args: Vec<OsString> = vec!["--noEmit".into(), "--pretty".into()];
+    if entries.is_empty() {
+        args.extend(["--project".into(), ".".into()]);
+    } else {
+        // the compiler ignores tsconfig.json when given files, so match the bundler instead
+        for arg in [
+            "--skipLibCheck",
+            "--allowJs",
+            "--resolveJsonModule",
+            "--allowImportingTsExtensions",
+            "--target",
+            "esnext",
+            "--module",
+            "preserve",
+            "--moduleResolution",
+            "bundler",
+            "--jsx",
+            "preserve",
+        ] {
+            args.push(arg.into());
+        }
+        args.extend(entries.iter().map(|entry| entry.as_os_str().to_owned()));
+    }
+
+    // node_modules/.bin comes first, as it does for package scripts
+    let path = xmas_package_manager::commands::new_path()
+        .map_err(|e| anyhow::anyhow!("Invalid PATH: {}", e))?;
+    for checker in TYPE_CHECKERS {
+        let status = tokio::process::Command::new(checker)
+            .args(&args)
+            .env("PATH", &path)
+            .status()
+            .await;
+        match status {
+            Ok(status) => {
+                if status.success() {
+                    println!("{} no type errors", "Checked".green().bold());
+                }
+                return Ok(status.success());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(anyhow::anyhow!("Failed to run {}: {}", checker, e)),
+        }
+    }
+    anyhow::bail!(
+        "No type checker found, install one with `xmas add -D @typescript/native-preview` or `xmas add -D typescript`"
+    )
+}
+
+/// A test file and the bundle it was built into.
+struct TestBundle {
+    file: PathBuf,
+    dir: PathBuf,
+    script: PathBuf,
+}
+
+/// What running a test file left behind.
+#[derive(Default)]
+struct TestFileReport {
+    output: String,
+    error: Option<String>,
+    /// The source map of the bundle, where its counted statements are and how often each
+    /// ran.
+    coverage: Option<(SourceMap, Vec<(u32, u32)>, Vec<u32>)>,
+}
+
+async fn run_tests(
+    paths: Vec<PathBuf>,
+    configured: Option<Permissions>,
+    coverage: bool,
+    timeout: Option<Duration>,
+) -> anyhow::Result<()> {
+    use std::sync::{Arc, Mutex};
+    use xmas_js_modules::test_runner::{has_failures, Coverage};
+
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let mut files = Vec::new();
+    for path in &paths {
+        find_test_files(path, &mut files)?;
+    }
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        anyhow::bail!("No test files found in {:?}", paths);
+    }
+
+    // Every file is bundled on its own, with `node:test` left to the runtime
+    let out_dir = std::env::temp_dir().join(format!("xmas-test-{}", std::process::id()));
+    let mut bundles = Vec::with_capacity(files.len());
+    for (index, file) in files.into_iter().enumerate() {
+        let dir = out_dir.join(index.to_string());
+        let name = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("bundle")
+            .to_string();
+        let bundle_config = xmas_bundler::BundleConfig {
+            entry: vec![file.clone()],
+            output_dir: dir.clone(),
+            output_filename: Some(format!("{}.js", name)),
+            minify: false,
+            mangle: true,
+            compress: true,
+            keep_names: false,
+            source_map: true,
+            format: xmas_bundler::BundleFormat::Esm,
+            tree_shake: true,
+            external: vec![],
+            alias: xmas_js_modules::module::package::resolver::aliases(),
+        };
+        xmas_bundler::bundle(bundle_config)
+            .await
+            .map_err(|e| anyhow::anyhow!("Bundle error in {}: {}", file.display(), e))?;
+        let script = dir.join(format!("{}.js", name));
+        bundles.push(TestBundle { file, dir, script });
+    }
+
+    let vsys = Arc::new(
+        xmas_vsys::Vsys::builder()
+            .permissions(configured.unwrap_or_else(Permissions::allow_all))
+            .build(),
+    );
+
+    // Files run side by side, each worker thread driving runtimes on a loop of its own
+    let queue = Mutex::new(bundles.iter());
+    let failed_files = Mutex::new(Vec::new());
+    let merged = Mutex::new(Coverage::default());
+    let worker = || -> anyhow::Result<()> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        while let Some(bundle) = queue.lock().unwrap().next() {
+            let report = rt
+                .block_on(run_test_file(bundle, vsys.clone(), coverage, timeout))
+                .unwrap_or_else(|e| TestFileReport {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                });
+
+            let mut text = format!("{}\n", bundle.file.display().to_string().bold());
+            text.push_str(&report.output);
+            if let Some(error) = &report.error {
+                text.push_str(&format!("{}: {}\n", "Error".red().bold(), error));
+                failed_files.lock().unwrap().push(bundle.file.clone());
+            }
+            print!("{}", text);
+
+            if let Some((map, positions, hits)) = report.coverage {
+                merged
+                    .lock()
+                    .unwrap()
+                    .add(&bundle.dir, &map, &positions, &hits);
+            }
+        }
         Ok(())
+    };
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |workers| workers.get())
+        .min(bundles.len());
+    tokio::task::block_in_place(|| {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers).map(|_| scope.spawn(&worker)).collect();
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().expect("test worker panicked"))
+        })
+    })?;
+    let _ = std::fs::remove_dir_all(&out_dir);
+
+    if coverage {
+        let mut coverage = merged.into_inner().unwrap();
+        coverage.retain(|file| {
+            !file.contains("node_modules") && !is_test_file(std::path::Path::new(file))
+        });
+        print!("{}", coverage.report());
+    }
+    let failed_files = failed_files.into_inner().unwrap();
+    if !failed_files.is_empty() {
+        eprintln!(
+            "{} {} file(s) failed to run",
+            "Error".red().bold(),
+            failed_files.len()
+        );
+    }
+    if has_failures() || !failed_files.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Runs a bundled test file in a fresh runtime, with the `node:test` functions as globals.
+async fn run_test_file(
+    bundle: &TestBundle,
+    vsys: std::sync::Arc<xmas_vsys::Vsys>,
+    coverage: bool,
+    timeout: Option<Duration>,
+) -> anyhow::Result<TestFileReport> {
+    use rsquickjs::{AsyncContext, TypedArray};
+    use xmas_js_modules::script::{instrument_coverage, COVERAGE_GLOBAL};
+    use xmas_js_modules::test_runner;
+    use xmas_js_modules::utils::ctx::until_deadline;
+
+    let (runtime, ga) = new_runtime().await?;
+    let context = AsyncContext::full(&runtime).await?;
+
+    let mut script_content = std::fs::read_to_string(&bundle.script)?;
+    let source_map = std::fs::read_to_string(bundle.script.with_extension("js.map")).ok();
+    let instrumented = if coverage {
+        let (code, positions) = instrument_coverage(&script_content)
+            .ok_or_else(|| anyhow::anyhow!("Failed to instrument {}", bundle.file.display()))?;
+        let map = SourceMap::parse(source_map.as_deref().unwrap_or_default()).map_err(|e| {
+            anyhow::anyhow!("Invalid source map of {}: {}", bundle.file.display(), e)
+        })?;
+        script_content = code;
+        Some((map, positions))
+    } else {
+        None
+    };
+    let filename = bundle.script.to_string_lossy().into_owned();
+    let counters = instrumented.as_ref().map(|(_, positions)| positions.len());
+
+    let error = rsquickjs::async_with!(context => |ctx| {
+        xmas_js_modules::init(&ctx, vsys, xmas_js_modules::console::LogType::Stdio)?;
+        ga.attach(&ctx)?;
+        test_runner::buffer_output(&ctx)?;
+        test_runner::install_globals(&ctx).await?;
+        if let Some(counters) = counters {
+            let counters = TypedArray::<u32>::new(ctx.clone(), vec![0; counters])?;
+            ctx.globals().set(COVERAGE_GLOBAL, counters)?;
+        }
+        ctx.set_deadline(timeout.map(|timeout| Instant::now() + timeout));
+
+        let result = match ctx.eval_with_options::<Promise, _>(
+            script_content,
+            EvalOptions {
+                promise: true,
+                filename: Some(filename.into()),
+                source_map,
+                ..Default::default()
+            },
+        ) {
+            Ok(promise) => until_deadline(&ctx, promise.into_future::<()>()).await,
+            Err(e) => Err(e),
+        };
+        Ok::<_, anyhow::Error>(result.err().map(|e| exception_message(&ctx, e)))
+    })
+    .await?;
+
+    // The tests themselves run once the module body has registered them
+    let drained = xmas_js_modules::event_loop::run_until_drained(&runtime, &context);
+    let error = match context.with(|ctx| ctx.deadline()).await {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), drained)
+            .await
+            .err()
+            .map(|_| format!("Timed out after {:?}", timeout.unwrap_or_default()))
+            .or(error),
+        None => {
+            drained.await;
+            error
+        }
+    };
+
+    context
+        .with(|ctx| {
+            let output = test_runner::take_output(&ctx)?;
+            let coverage = match instrumented {
+                Some((map, positions)) => {
+                    let counters: TypedArray<u32> = ctx.globals().get(COVERAGE_GLOBAL)?;
+                    let hits = AsRef::<[u32]>::as_ref(&counters).to_vec();
+                    Some((map, positions, hits))
+                }
+                None => None,
+            };
+            Ok(TestFileReport {
+                output,
+                error,
+                coverage,
+            })
+        })
+        .await
+}
+
+/// Collects the test files under `path`, or `path` itself when it names a file.
+fn find_test_files(path: &std::path::Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            find_test_files(&path, files)?;
+        } else if is_test_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the name of `path` ends in `.test` or `.spec` and a script extension.
+fn is_test_file(path: &std::path::Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    [".test.", ".spec."].iter().any(|infix| {
+        ["ts", "tsx", "mts", "js", "jsx", "mjs"]
+            .iter()
+            .any(|extension| name.ends_with(&[infix, *extension].concat()))
+    })
+}
+
+/// Reads the user-global and project config, and applies its aliases to the runtime.
+/// Relative alias targets are resolved against the working directory.
+async fn load_config() -> anyhow::Result<xmas_package_manager::config::Config> {
+    let config = xmas_package_manager::config::read_config()
+        .await
+        .map_err(|e| {
+            let causes: Vec<String> = e.chain().map(|cause| cause.to_string()).collect();
+            anyhow::anyhow!("{}", causes.join(": "))
+        })?;
+
+    let cwd = std::env::current_dir()?;
+    let aliases = config
+        .alias
+        .iter()
+        .map(|(alias, target)| {
+            let target = if target.starts_with("./") || target.starts_with("../") {
+                cwd.join(target).to_string_lossy().into_owned()
+            } else {
+                target.clone()
+            };
+            (alias.clone(), target)
+        })
+        .collect();
+    xmas_js_modules::module::package::resolver::set_aliases(aliases);
+    Ok(config)
+}
+
+/// The format of console output, the flag overriding the config.
+fn log_type(
+    cli: &Cli,
+    config: &xmas_package_manager::config::Config,
+) -> xmas_js_modules::console::LogType {
+    use xmas_js_modules::console::LogType;
+    cli.log_format.clone().unwrap_or(match config.log_format {
+        Some(xmas_package_manager::config::LogFormat::Json) => LogType::Json,
+        _ => LogType::Stdio,
+    })
+}
+
+/// The `.env` files of the config, `.env` and `.env.local` when it lists none.
+fn env_files(config: &xmas_package_manager::config::Config) -> Vec<PathBuf> {
+    config.env_file.clone().unwrap_or_else(|| {
+        xmas_package_manager::env_file::DEFAULT_ENV_FILES
+            .iter()
+            .map(PathBuf::from)
+            .collect()
+    })
+}
+
+/// The JSX options of the config, where an import source alone asks for the automatic
+/// runtime as in tsconfig.json.
+fn jsx_options(
+    jsx: xmas_package_manager::config::JsxConfig,
+) -> xmas_js_modules::script::JsxOptions {
+    xmas_js_modules::script::JsxOptions {
+        automatic: match jsx.runtime {
+            Some(runtime) => runtime == xmas_package_manager::config::JsxRuntime::Automatic,
+            None => jsx.import_source.is_some(),
+        },
+        import_source: jsx.import_source,
+        pragma: jsx.pragma,
+        pragma_frag: jsx.pragma_frag,
+    }
+}
+
+/// A script bundled into a single file, as read back from the bundler output.
+struct BundledScript {
+    path: String,
+    content: String,
+    source_map: Option<String>,
+}
+
+/// Bundles `script_path` into the working directory with Rolldown. With `inline` code, it
+/// is written to `script_path` for bundling and nothing is left behind.
+async fn bundle_script(
+    script_path: &str,
+    inline: Option<&str>,
+    quiet: bool,
+) -> anyhow::Result<BundledScript> {
+    // Get the script name without extension for output
+    let script_name = std::path::Path::new(script_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("bundle");
+
+    if !quiet {
+        println!("{} {}...", "Bundling".cyan().bold(), script_path);
+    }
+    if let Some(code) = inline {
+        std::fs::write(script_path, code)?;
+    }
+    let bundle_config = xmas_bundler::BundleConfig {
+        entry: vec![PathBuf::from(script_path)],
+        output_dir: PathBuf::from("."),
+        output_filename: Some(format!("{}.js", script_name)),
+        minify: false,
+        mangle: true,
+        compress: true,
+        keep_names: false,
+        source_map: true,
+        format: xmas_bundler::BundleFormat::Esm,
+        tree_shake: true,
+        external: vec![],
+        alias: xmas_js_modules::module::package::resolver::aliases(),
+    };
+    let bundled = xmas_bundler::bundle(bundle_config).await;
+    if inline.is_some() {
+        let _ = std::fs::remove_file(script_path);
+    }
+    bundled.map_err(|e| anyhow::anyhow!("Bundle error: {}", e))?;
+
+    // Read the bundled output and the source map written next to it
+    let path = format!("{}.js", script_name);
+    let content = std::fs::read_to_string(&path)?;
+    let source_map = std::fs::read_to_string(format!("{}.map", path)).ok();
+    if inline.is_some() {
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(format!("{}.map", path));
+    }
+    Ok(BundledScript {
+        // the module name, which `import.meta` and relative imports resolve from
+        path: std::env::current_dir()?
+            .join(&path)
+            .to_string_lossy()
+            .into_owned(),
+        content,
+        source_map,
     })
-    .await
+}
+
+/// Imports the entry at `path` through the package loader, which loads and transpiles the
+/// modules it imports one by one. Inline code is declared under `path` instead, so its
+/// imports resolve from the working directory.
+fn import_entry<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    path: &str,
+    inline: Option<&str>,
+) -> rsquickjs::Result<Promise<'js>> {
+    use xmas_js_modules::module::package::transpile::transpile;
+
+    let Some(code) = inline else {
+        return rsquickjs::Module::import(ctx, path);
+    };
+    let output = transpile(ctx, path, code.as_bytes())?;
+    evaluate_entry(ctx, path, output.code, output.map.as_deref())
+}
+
+/// Evaluates `code` as the ES module at `path`, so `import.meta`, circular imports and
+/// top-level await behave as they do for a module loaded from disk.
+fn evaluate_entry<'js>(
+    ctx: &rsquickjs::Ctx<'js>,
+    path: &str,
+    code: impl Into<Vec<u8>>,
+    source_map: Option<&str>,
+) -> rsquickjs::Result<Promise<'js>> {
+    if let Some(source_map) = source_map {
+        xmas_js_modules::source_map::register_source_map(ctx, path, source_map)?;
+    }
+    let (_, promise) = rsquickjs::Module::declare(ctx.clone(), path, code)?.eval()?;
+    Ok(promise)
+}
+
+fn print_heap_stats(stats: &rsquickjs::runtime::RuntimeMemoryStats) {
+    eprintln!("{}", "Heap statistics".cyan().bold());
+    let limit = stats
+        .malloc_limit
+        .map_or_else(|| "none".to_string(), |limit| limit.to_string());
+    eprintln!(
+        "  {:<20} {:>10} bytes (limit {})",
+        "allocated", stats.malloc_size, limit
+    );
+    for (name, count, size) in [
+        ("in use", stats.memory_used_count, stats.memory_used_size),
+        ("atoms", stats.atom_count, stats.atom_size),
+        ("strings", stats.string_count, stats.string_size),
+        ("objects", stats.object_count, stats.object_size),
+        ("properties", stats.property_count, stats.property_size),
+        ("shapes", stats.shape_count, stats.shape_size),
+        ("functions", stats.js_function_count, stats.js_function_size),
+        (
+            "binary objects",
+            stats.binary_object_count,
+            stats.binary_object_size,
+        ),
+    ] {
+        eprintln!("  {:<20} {:>10} bytes in {} items", name, size, count);
+    }
+    eprintln!(
+        "  {:<20} {:>10} bytes",
+        "bytecode", stats.js_function_code_size
+    );
+    eprintln!(
+        "  {:<20} {:>10} arrays, {} fast with {} elements",
+        "arrays", stats.array_count, stats.fast_array_count, stats.fast_array_elements
+    );
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| format!("`{}` is not a positive number of seconds", value))
+}
+
+/// The pending exception with its stack mapped back to the original sources, or `error`
+/// when nothing was thrown.
+fn exception_message(ctx: &rsquickjs::Ctx<'_>, error: rsquickjs::Error) -> String {
+    ctx.catch()
+        .into_exception()
+        .map(|e| xmas_js_modules::source_map::remap_stack(ctx, &e.to_string()))
+        .unwrap_or_else(|| error.to_string())
+}
+
+/// Prints the pending exception, mapping its stack frames back to the original sources.
+fn print_exception(ctx: &rsquickjs::Ctx<'_>) {
+    let exception = ctx
+        .catch()
+        .into_exception()
+        .map(|e| xmas_js_modules::source_map::remap_stack(ctx, &e.to_string()));
+    eprintln!("{}: {:?}", "Exception".red().bold(), exception);
 }
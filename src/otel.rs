@@ -0,0 +1,49 @@
+//! Export of the `tracing` spans of xmas, from package downloads and installs to module
+//! evaluation and `fetch` calls, to an OpenTelemetry collector over OTLP.
+//!
+//! The exporter is configured with the standard `OTEL_EXPORTER_OTLP_*` variables, sending
+//! to `http://localhost:4318` by default, and `OTEL_SERVICE_NAME`, `xmas` by default.
+
+use std::sync::OnceLock;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// A layer exporting the spans it records in batches.
+pub fn layer<S>() -> anyhow::Result<impl Layer<S>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+    let mut resource = Resource::builder();
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name("xmas");
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build();
+    let tracer = provider.tracer("xmas");
+    PROVIDER
+        .set(provider)
+        .map_err(|_| anyhow::anyhow!("The OpenTelemetry exporter is already set up"))?;
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Exports the spans still queued. Spans of a process ending with `process.exit()` or a
+/// failing script aren't all exported.
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Failed to export the OpenTelemetry spans: {}", e);
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! Archive-backed filesystem vtable
+//!
+//! [`ArchiveFs`] indexes a `.tar`, `.tar.gz` or `.zip` archive in memory and
+//! [`FsVTable::archive`] serves it, read-only, at a mount point on top of the
+//! real filesystem. A bundle and its `node_modules` can then ship as a single
+//! file that is never extracted to disk.
+//!
+//! Paths below the mount point are looked up in the archive first and fall
+//! back to the real filesystem, so files next to the archive stay reachable.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{VsysError, VsysResult};
+use crate::fs::{
+    DirEntry, FileStat, FileType, FsHandle, FsHandleOps, FsVTable, OpenOptions, SeekFrom,
+};
+
+/// Symlinks followed while resolving a path before giving up, like `ELOOP`
+const MAX_SYMLINK_HOPS: u32 = 40;
+
+#[derive(Debug)]
+enum Node {
+    File(Arc<[u8]>),
+    Dir,
+    Symlink(PathBuf),
+}
+
+#[derive(Debug)]
+struct Entry {
+    node: Node,
+    mode: u32,
+    modified: Option<SystemTime>,
+}
+
+impl Entry {
+    fn dir() -> Self {
+        Self {
+            node: Node::Dir,
+            mode: 0o755,
+            modified: None,
+        }
+    }
+
+    fn file_type(&self) -> FileType {
+        match self.node {
+            Node::File(_) => FileType::File,
+            Node::Dir => FileType::Directory,
+            Node::Symlink(_) => FileType::Symlink,
+        }
+    }
+
+    fn contents(&self, path: &Path) -> VsysResult<&Arc<[u8]>> {
+        match &self.node {
+            Node::File(data) => Ok(data),
+            _ => Err(VsysError::InvalidArgument(format!(
+                "{} is not a file",
+                path.display()
+            ))),
+        }
+    }
+
+    fn stat(&self) -> FileStat {
+        let (size, type_bits) = match &self.node {
+            Node::File(data) => (data.len() as u64, 0o100000),
+            Node::Dir => (0, 0o040000),
+            Node::Symlink(target) => (target.as_os_str().len() as u64, 0o120000),
+        };
+        FileStat {
+            file_type: self.file_type(),
+            size,
+            readonly: true,
+            modified: self.modified,
+            accessed: None,
+            created: None,
+            mode: type_bits | (self.mode & 0o7777),
+            uid: 0,
+            gid: 0,
+        }
+    }
+}
+
+/// An archive indexed in memory, to be served by [`FsVTable::archive`]
+#[derive(Debug)]
+pub struct ArchiveFs {
+    mount: PathBuf,
+    entries: HashMap<PathBuf, Entry>,
+    children: HashMap<PathBuf, BTreeSet<String>>,
+}
+
+impl ArchiveFs {
+    /// Read the archive at `path`, to be mounted at `mount`
+    pub fn open(path: &Path, mount: impl Into<PathBuf>) -> VsysResult<Self> {
+        Self::from_bytes(&std::fs::read(path)?, mount)
+    }
+
+    /// Index an archive, detecting zip and gzip from their magic bytes and
+    /// reading anything else as a plain tar
+    pub fn from_bytes(bytes: &[u8], mount: impl Into<PathBuf>) -> VsysResult<Self> {
+        let mut archive = Self {
+            mount: normalize(&mount.into())?,
+            entries: HashMap::from([(PathBuf::new(), Entry::dir())]),
+            children: HashMap::new(),
+        };
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            archive.read_zip(bytes)?;
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            archive.read_tar(flate2::read::GzDecoder::new(bytes))?;
+        } else {
+            archive.read_tar(bytes)?;
+        }
+        Ok(archive)
+    }
+
+    /// Where the archive root appears in the filesystem
+    pub fn mount(&self) -> &Path {
+        &self.mount
+    }
+
+    fn read_tar(&mut self, reader: impl Read) -> VsysResult<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let Some(path) = archive_path(&entry.path()?) else {
+                continue;
+            };
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let mode = header.mode().unwrap_or(0o644);
+            let modified = header
+                .mtime()
+                .ok()
+                .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+            let node = if entry_type.is_dir() {
+                Node::Dir
+            } else if entry_type.is_symlink() {
+                let target = entry.link_name()?.unwrap_or_default().into_owned();
+                Node::Symlink(target)
+            } else if entry_type.is_file() {
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data)?;
+                Node::File(data.into())
+            } else {
+                // hard links, devices and extension headers
+                continue;
+            };
+            self.insert(
+                path,
+                Entry {
+                    node,
+                    mode,
+                    modified,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn read_zip(&mut self, bytes: &[u8]) -> VsysResult<()> {
+        let invalid = |err: zip::result::ZipError| VsysError::InvalidArgument(err.to_string());
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(invalid)?;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).map_err(invalid)?;
+            let Some(path) = file.enclosed_name().and_then(|path| archive_path(&path)) else {
+                continue;
+            };
+            let mode = file.unix_mode().unwrap_or(0o644);
+
+            let node = if file.is_dir() {
+                Node::Dir
+            } else {
+                let mut data = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut data)?;
+                if mode & 0o170000 == 0o120000 {
+                    Node::Symlink(PathBuf::from(String::from_utf8_lossy(&data).into_owned()))
+                } else {
+                    Node::File(data.into())
+                }
+            };
+            self.insert(
+                path,
+                Entry {
+                    node,
+                    mode,
+                    modified: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Adds `entry`, creating the directories above it that the archive
+    /// doesn't list itself
+    fn insert(&mut self, path: PathBuf, entry: Entry) {
+        let mut child = path.clone();
+        while let Some(parent) = child.parent() {
+            let name = child.file_name().unwrap_or_default();
+            self.children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .insert(name.to_string_lossy().into_owned());
+            self.entries
+                .entry(parent.to_path_buf())
+                .or_insert_with(Entry::dir);
+            child = parent.to_path_buf();
+        }
+        self.entries.insert(path, entry);
+    }
+
+    /// The key of the entry `path` refers to, if it is in the archive
+    fn find(&self, path: &Path, follow: bool) -> Option<PathBuf> {
+        let relative = normalize(path).ok()?;
+        let relative = relative.strip_prefix(&self.mount).ok()?;
+        self.resolve(relative, follow, 0)
+    }
+
+    fn resolve(&self, relative: &Path, follow: bool, hops: u32) -> Option<PathBuf> {
+        let components: Vec<_> = relative.components().collect();
+        let mut resolved = PathBuf::new();
+        for (index, component) in components.iter().enumerate() {
+            match component {
+                Component::Normal(name) => resolved.push(name),
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return None;
+                    }
+                    continue;
+                }
+                _ => continue,
+            }
+            let is_last = index + 1 == components.len();
+            if let Some(Entry {
+                node: Node::Symlink(target),
+                ..
+            }) = self.entries.get(&resolved)
+            {
+                if is_last && !follow {
+                    break;
+                }
+                if hops >= MAX_SYMLINK_HOPS || target.is_absolute() {
+                    return None;
+                }
+                let mut next = resolved.parent().unwrap_or(Path::new("")).join(target);
+                next.extend(&components[index + 1..]);
+                return self.resolve(&next, follow, hops + 1);
+            }
+        }
+        self.entries.contains_key(&resolved).then_some(resolved)
+    }
+}
+
+impl FsVTable {
+    /// Create a vtable serving `archive` at its mount point, read-only, on
+    /// top of the real filesystem
+    pub fn archive(archive: ArchiveFs) -> Self {
+        let mounted = Arc::new(Mounted {
+            archive,
+            real: FsVTable::default(),
+        });
+        Self {
+            read: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, true, |_, entry| Ok(entry.contents(path)?.to_vec()))
+                    .unwrap_or_else(|| (mounted.real.read)(path))
+            }),
+            read_to_string: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, true, |_, entry| {
+                        String::from_utf8(entry.contents(path)?.to_vec())
+                            .map_err(|err| VsysError::InvalidArgument(err.to_string()))
+                    })
+                    .unwrap_or_else(|| (mounted.real.read_to_string)(path))
+            }),
+            stat: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, true, |_, entry| Ok(entry.stat()))
+                    .unwrap_or_else(|| (mounted.real.stat)(path))
+            }),
+            lstat: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, false, |_, entry| Ok(entry.stat()))
+                    .unwrap_or_else(|| (mounted.real.lstat)(path))
+            }),
+            read_dir: with_state!(mounted, |path: &Path| mounted.read_dir(path)),
+            read_link: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, false, |_, entry| match &entry.node {
+                        Node::Symlink(target) => Ok(target.clone()),
+                        _ => Err(VsysError::InvalidArgument(format!(
+                            "{} is not a symlink",
+                            path.display()
+                        ))),
+                    })
+                    .unwrap_or_else(|| (mounted.real.read_link)(path))
+            }),
+            exists: with_state!(mounted, |path: &Path| {
+                mounted.archived(path, true, |_, _| Ok(())).is_some() || (mounted.real.exists)(path)
+            }),
+            is_file: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, true, |_, entry| {
+                        Ok(entry.file_type() == FileType::File)
+                    })
+                    .map_or_else(
+                        || (mounted.real.is_file)(path),
+                        |is_file| is_file.unwrap_or(false),
+                    )
+            }),
+            is_dir: with_state!(mounted, |path: &Path| {
+                mounted
+                    .archived(path, true, |_, entry| {
+                        Ok(entry.file_type() == FileType::Directory)
+                    })
+                    .map_or_else(
+                        || (mounted.real.is_dir)(path),
+                        |is_dir| is_dir.unwrap_or(false),
+                    )
+            }),
+            write: with_state!(mounted, |path: &Path, data: &[u8]| {
+                mounted.read_only(path)?;
+                (mounted.real.write)(path, data)
+            }),
+            append: with_state!(mounted, |path: &Path, data: &[u8]| {
+                mounted.read_only(path)?;
+                (mounted.real.append)(path, data)
+            }),
+            create_dir: with_state!(mounted, |path: &Path| {
+                mounted.read_only(path)?;
+                (mounted.real.create_dir)(path)
+            }),
+            create_dir_all: with_state!(mounted, |path: &Path| {
+                let is_dir = mounted.archived(path, true, |_, entry| {
+                    Ok(entry.file_type() == FileType::Directory)
+                });
+                match is_dir {
+                    Some(Ok(true)) => Ok(()),
+                    Some(_) => mounted.read_only(path),
+                    None => (mounted.real.create_dir_all)(path),
+                }
+            }),
+            remove_file: with_state!(mounted, |path: &Path| {
+                mounted.read_only(path)?;
+                (mounted.real.remove_file)(path)
+            }),
+            remove_dir: with_state!(mounted, |path: &Path| {
+                mounted.read_only(path)?;
+                (mounted.real.remove_dir)(path)
+            }),
+            remove_dir_all: with_state!(mounted, |path: &Path| {
+                mounted.read_only(path)?;
+                (mounted.real.remove_dir_all)(path)
+            }),
+            rename: with_state!(mounted, |from: &Path, to: &Path| {
+                mounted.read_only(from)?;
+                mounted.read_only(to)?;
+                (mounted.real.rename)(from, to)
+            }),
+            copy: with_state!(mounted, |from: &Path, to: &Path| {
+                mounted.read_only(to)?;
+                let data =
+                    mounted.archived(from, true, |_, entry| Ok(entry.contents(from)?.clone()));
+                match data {
+                    Some(data) => {
+                        let data = data?;
+                        (mounted.real.write)(to, &data)?;
+                        Ok(data.len() as u64)
+                    }
+                    None => (mounted.real.copy)(from, to),
+                }
+            }),
+            symlink: with_state!(mounted, |original: &Path, link: &Path| {
+                mounted.read_only(link)?;
+                (mounted.real.symlink)(original, link)
+            }),
+            hard_link: with_state!(mounted, |original: &Path, link: &Path| {
+                mounted.read_only(original)?;
+                mounted.read_only(link)?;
+                (mounted.real.hard_link)(original, link)
+            }),
+            truncate: with_state!(mounted, |path: &Path, size: u64| {
+                mounted.read_only(path)?;
+                (mounted.real.truncate)(path, size)
+            }),
+            set_times: with_state!(
+                mounted,
+                |path: &Path, accessed: SystemTime, modified: SystemTime| {
+                    mounted.read_only(path)?;
+                    (mounted.real.set_times)(path, accessed, modified)
+                }
+            ),
+            access: with_state!(mounted, |path: &Path, mode: u32| {
+                const W_OK: u32 = 2;
+                mounted
+                    .archived(path, true, |_, _| {
+                        if mode & W_OK != 0 {
+                            return Err(read_only_error(path));
+                        }
+                        Ok(())
+                    })
+                    .unwrap_or_else(|| (mounted.real.access)(path, mode))
+            }),
+            mkdtemp: with_state!(mounted, |prefix: &str| (mounted.real.mkdtemp)(prefix)),
+            set_permissions: with_state!(mounted, |path: &Path, readonly: bool| {
+                mounted.read_only(path)?;
+                (mounted.real.set_permissions)(path, readonly)
+            }),
+            set_mode: with_state!(mounted, |path: &Path, mode: u32| {
+                mounted.read_only(path)?;
+                (mounted.real.set_mode)(path, mode)
+            }),
+            chown: with_state!(mounted, |path: &Path, uid: u32, gid: u32| {
+                mounted.read_only(path)?;
+                (mounted.real.chown)(path, uid, gid)
+            }),
+            canonicalize: with_state!(mounted, |path: &Path| {
+                let key = mounted.archive.find(path, true);
+                key.map_or_else(
+                    || (mounted.real.canonicalize)(path),
+                    |key| Ok(mounted.archive.mount.join(key)),
+                )
+            }),
+            open: with_state!(mounted, |path: &Path, options: &OpenOptions| {
+                mounted
+                    .archived(path, true, |_, entry| {
+                        if options.write || options.append || options.truncate || options.create_new
+                        {
+                            return Err(read_only_error(path));
+                        }
+                        Ok(FsHandle::new(ArchiveHandle {
+                            data: entry.contents(path)?.clone(),
+                            position: 0,
+                            stat: entry.stat(),
+                        }))
+                    })
+                    .unwrap_or_else(|| (mounted.real.open)(path, options))
+            }),
+        }
+    }
+}
+
+/// An archive served by a vtable, on top of the real filesystem
+struct Mounted {
+    archive: ArchiveFs,
+    real: FsVTable,
+}
+
+impl Mounted {
+    /// Runs `op` on the entry `path` refers to, or returns `None` when it is
+    /// not in the archive and the real filesystem should handle it
+    fn archived<T>(
+        &self,
+        path: &Path,
+        follow: bool,
+        op: impl FnOnce(&ArchiveFs, &Entry) -> VsysResult<T>,
+    ) -> Option<VsysResult<T>> {
+        let key = self.archive.find(path, follow)?;
+        Some(op(&self.archive, &self.archive.entries[&key]))
+    }
+
+    fn read_only(&self, path: &Path) -> VsysResult<()> {
+        match self.archived(path, false, |_, _| Ok(())) {
+            Some(_) => Err(read_only_error(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Lists a directory of the archive, together with the real directory at
+    /// the same path if there is one
+    fn read_dir(&self, path: &Path) -> VsysResult<Vec<DirEntry>> {
+        let archive = &self.archive;
+        let Some(key) = archive.find(path, true) else {
+            return (self.real.read_dir)(path);
+        };
+        if archive.entries[&key].file_type() != FileType::Directory {
+            return Err(VsysError::InvalidArgument(format!(
+                "{} is not a directory",
+                path.display()
+            )));
+        }
+
+        let names = archive.children.get(&key).cloned().unwrap_or_default();
+        let mut entries: Vec<DirEntry> = names
+            .iter()
+            .map(|name| DirEntry {
+                name: name.clone(),
+                file_type: archive.entries[&key.join(name)].file_type(),
+            })
+            .collect();
+        if let Ok(real) = (self.real.read_dir)(path) {
+            entries.extend(
+                real.into_iter()
+                    .filter(|entry| !names.contains(&entry.name)),
+            );
+        }
+        Ok(entries)
+    }
+}
+
+fn read_only_error(path: &Path) -> VsysError {
+    VsysError::PermissionDenied(format!("{} is in a read-only archive", path.display()))
+}
+
+/// The path of an archive member relative to the archive root, or `None`
+/// for members that would land outside of it
+fn archive_path(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// Makes `path` absolute and removes `.` and `..` without touching the disk
+fn normalize(path: &Path) -> VsysResult<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    Ok(normalized)
+}
+
+/// Read-only handle over an archived file
+struct ArchiveHandle {
+    data: Arc<[u8]>,
+    position: u64,
+    stat: FileStat,
+}
+
+impl FsHandleOps for ArchiveHandle {
+    fn read(&mut self, buf: &mut [u8]) -> VsysResult<usize> {
+        let start = (self.position as usize).min(self.data.len());
+        let len = buf.len().min(self.data.len() - start);
+        buf[..len].copy_from_slice(&self.data[start..start + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> VsysResult<usize> {
+        Err(VsysError::PermissionDenied(
+            "archived files are read-only".into(),
+        ))
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> VsysResult<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (self.data.len() as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position
+            .ok_or_else(|| VsysError::InvalidArgument("seek to a negative position".into()))?;
+        Ok(self.position)
+    }
+
+    fn sync_all(&self) -> VsysResult<()> {
+        Ok(())
+    }
+
+    fn sync_data(&self) -> VsysResult<()> {
+        Ok(())
+    }
+
+    fn stat(&self) -> VsysResult<FileStat> {
+        Ok(self.stat.clone())
+    }
+
+    fn set_len(&self, _size: u64) -> VsysResult<()> {
+        Err(VsysError::PermissionDenied(
+            "archived files are read-only".into(),
+        ))
+    }
+
+    fn set_permissions(&self, _readonly: bool) -> VsysResult<()> {
+        Err(VsysError::PermissionDenied(
+            "archived files are read-only".into(),
+        ))
+    }
+
+    fn set_mode(&self, _mode: u32) -> VsysResult<()> {
+        Err(VsysError::PermissionDenied(
+            "archived files are read-only".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn tar_bytes() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut append = |path: &str, data: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data).unwrap();
+        };
+        append("main.js", b"import 'left-pad';");
+        append("node_modules/left-pad/index.js", b"export default 1;");
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_link(&mut header, "node_modules/pad", "left-pad")
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn zip_bytes() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("lib/util.js", options).unwrap();
+        writer.write_all(b"export const x = 1;").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_archive_fs() {
+        let mount = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let vtable = FsVTable::archive(ArchiveFs::from_bytes(&tar_bytes(), &mount).unwrap());
+
+        let index = mount.join("node_modules/left-pad/index.js");
+        assert_eq!((vtable.read)(&index).unwrap(), b"export default 1;");
+        assert!((vtable.is_dir)(&mount.join("node_modules")));
+        assert!((vtable.stat)(&index).unwrap().readonly);
+        assert!(!(vtable.exists)(&mount.join("missing.js")));
+
+        // symlinks resolve inside the archive
+        let linked = mount.join("node_modules/pad/index.js");
+        assert_eq!(
+            (vtable.read_to_string)(&linked).unwrap(),
+            "export default 1;"
+        );
+        assert!((vtable.lstat)(&mount.join("node_modules/pad"))
+            .unwrap()
+            .is_symlink());
+        assert_eq!((vtable.canonicalize)(&linked).unwrap(), index);
+
+        let mut names: Vec<String> = (vtable.read_dir)(&mount.join("node_modules"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["left-pad", "pad"]);
+
+        let mut handle = (vtable.open)(&index, &OpenOptions::new().read(true)).unwrap();
+        let mut buf = [0; 6];
+        assert_eq!(handle.read(&mut buf).unwrap(), 6);
+        assert_eq!(&buf, b"export");
+
+        // the archive is read-only
+        assert!(matches!(
+            (vtable.write)(&index, b"x"),
+            Err(VsysError::PermissionDenied(_))
+        ));
+        assert!((vtable.remove_file)(&index).is_err());
+        assert!((vtable.open)(&index, &OpenOptions::new().write(true)).is_err());
+        assert!((vtable.access)(&index, 2).is_err());
+        assert!((vtable.create_dir_all)(&mount.join("node_modules")).is_ok());
+
+        // zip archives work the same way
+        let vtable = FsVTable::archive(ArchiveFs::from_bytes(&zip_bytes(), &mount).unwrap());
+        let util = mount.join("lib/util.js");
+        assert_eq!((vtable.read)(&util).unwrap(), b"export const x = 1;");
+        assert!(!(vtable.exists)(&index));
+    }
+
+    #[test]
+    fn test_archive_fs_side_by_side() {
+        let mount = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let tar = FsVTable::archive(ArchiveFs::from_bytes(&tar_bytes(), &mount).unwrap());
+        let zip = FsVTable::archive(ArchiveFs::from_bytes(&zip_bytes(), &mount).unwrap());
+
+        // mounting the zip archive leaves the tar one in place
+        assert!((tar.exists)(&mount.join("main.js")));
+        assert!(!(tar.exists)(&mount.join("lib/util.js")));
+        assert!((zip.exists)(&mount.join("lib/util.js")));
+        assert!(!(zip.exists)(&mount.join("main.js")));
+    }
+}
@@ -0,0 +1,102 @@
+//! Audit log for vsys operations
+//!
+//! An [`AuditSink`] set on [`Vsys`](crate::Vsys) receives one [`AuditEvent`]
+//! per checked fs/net/module operation, whether it was allowed or not, so a
+//! security review can see exactly what a script touched.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Tracing target used by [`AuditSink::Tracing`]
+pub const AUDIT_TARGET: &str = "xmas_vsys::audit";
+
+/// A single audited operation
+#[derive(Debug, Clone, Copy)]
+pub struct AuditEvent<'a> {
+    /// Operation name, e.g. `fs.readFile`, `net.connect` or `module.load`
+    pub op: &'a str,
+    /// Path, host or specifier the operation was applied to
+    pub resource: &'a str,
+    /// Whether the operation was allowed
+    pub allowed: bool,
+    /// Module that requested the operation, when known
+    pub caller: Option<&'a str>,
+}
+
+/// Where audit events are recorded
+pub enum AuditSink {
+    /// Emit events as `tracing` events with the [`AUDIT_TARGET`] target
+    Tracing,
+    /// Append events to a file, one JSON object per line
+    Jsonl(Mutex<File>),
+}
+
+impl AuditSink {
+    /// Create a sink appending to the JSONL file at `path`
+    pub fn jsonl(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::Jsonl(Mutex::new(file)))
+    }
+
+    /// Record `event`, ignoring write errors so auditing never fails an operation
+    pub fn record(&self, event: &AuditEvent<'_>) {
+        match self {
+            AuditSink::Tracing => tracing::info!(
+                target: AUDIT_TARGET,
+                op = event.op,
+                resource = event.resource,
+                allowed = event.allowed,
+                caller = event.caller,
+            ),
+            AuditSink::Jsonl(file) => {
+                let line = serde_json::json!({
+                    "op": event.op,
+                    "resource": event.resource,
+                    "allowed": event.allowed,
+                    "caller": event.caller,
+                });
+                let mut file = file.lock().unwrap_or_else(|err| err.into_inner());
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_jsonl_sink() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let sink = AuditSink::jsonl(&path).unwrap();
+
+        sink.record(&AuditEvent {
+            op: "fs.readFile",
+            resource: "/etc/passwd",
+            allowed: false,
+            caller: Some("file:///main.js"),
+        });
+        sink.record(&AuditEvent {
+            op: "module.load",
+            resource: "node:fs",
+            allowed: true,
+            caller: None,
+        });
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["op"], "fs.readFile");
+        assert_eq!(lines[0]["allowed"], false);
+        assert_eq!(lines[0]["caller"], "file:///main.js");
+        assert_eq!(lines[1]["caller"], serde_json::Value::Null);
+    }
+}
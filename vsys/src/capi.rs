@@ -0,0 +1,778 @@
+//! C ABI for vsys
+//!
+//! Lets embedders that don't write Rust create a [`Vsys`] and back its
+//! filesystem and network with their own callbacks. Every callback receives
+//! the `user_data` pointer of its vtable and reports failures with a
+//! [`CVsysError`], using the `ERR_*` codes or a custom one.
+//!
+//! Buffers and error messages returned by callbacks are handed back to the
+//! vtable's `free` callback, when it has one, once they have been copied.
+//! Callbacks may be called from any thread.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{CVsysError, VsysError, VsysResult};
+use crate::fs::{DirEntry, FileStat, FileType, FsVTable};
+use crate::net::{DnsQuery, DnsRecord, NetVTable, RecordData, RecordType};
+use crate::permissions::Permissions;
+use crate::Vsys;
+
+/// A buffer allocated by a callback
+#[repr(C)]
+pub struct CVsysBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// File type codes of [`CFileStat`] and `read_dir` entries
+pub const C_FILE_TYPE_FILE: u8 = 0;
+pub const C_FILE_TYPE_DIRECTORY: u8 = 1;
+pub const C_FILE_TYPE_SYMLINK: u8 = 2;
+pub const C_FILE_TYPE_OTHER: u8 = 3;
+
+/// File statistics filled in by `stat`
+#[repr(C)]
+#[derive(Default)]
+pub struct CFileStat {
+    pub file_type: u8,
+    pub size: u64,
+    pub readonly: bool,
+    /// Milliseconds since the Unix epoch, negative when unknown
+    pub modified_ms: i64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+type CPathFn = unsafe extern "C" fn(user_data: *mut c_void, path: *const c_char) -> CVsysError;
+
+/// Filesystem callbacks
+///
+/// Missing callbacks, and the operations this vtable has no callback for,
+/// are denied.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CFsVTable {
+    pub user_data: *mut c_void,
+    /// Read a whole file into `out`
+    pub read: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            path: *const c_char,
+            out: *mut CVsysBuffer,
+        ) -> CVsysError,
+    >,
+    /// Replace the contents of a file, creating it if needed
+    pub write: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            path: *const c_char,
+            data: *const u8,
+            len: usize,
+        ) -> CVsysError,
+    >,
+    /// Stat a file, following symlinks
+    pub stat: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            path: *const c_char,
+            out: *mut CFileStat,
+        ) -> CVsysError,
+    >,
+    /// List a directory into `out`, each entry being a file type code
+    /// followed by the NUL-terminated name
+    pub read_dir: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            path: *const c_char,
+            out: *mut CVsysBuffer,
+        ) -> CVsysError,
+    >,
+    pub create_dir: Option<CPathFn>,
+    pub remove_file: Option<CPathFn>,
+    pub remove_dir: Option<CPathFn>,
+    pub rename: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            from: *const c_char,
+            to: *const c_char,
+        ) -> CVsysError,
+    >,
+    /// Release a buffer or error message returned by another callback
+    pub free: Option<unsafe extern "C" fn(user_data: *mut c_void, ptr: *mut c_void)>,
+}
+
+// Embedders promise thread-safe callbacks, see the module docs
+unsafe impl Send for CFsVTable {}
+unsafe impl Sync for CFsVTable {}
+
+/// An IP address, `family` being 4 or 6; IPv4 uses the first 4 octets
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CIpAddr {
+    pub family: u8,
+    pub octets: [u8; 16],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CSocketAddr {
+    pub ip: CIpAddr,
+    pub port: u16,
+}
+
+/// Network callbacks
+///
+/// Sockets are returned as file descriptors, or `SOCKET`s on Windows, whose
+/// ownership moves to vsys. Missing callbacks are denied.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CNetVTable {
+    pub user_data: *mut c_void,
+    /// Resolve `host` into at most `capacity` addresses, setting `len`
+    pub resolve: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            host: *const c_char,
+            out: *mut CIpAddr,
+            capacity: usize,
+            len: *mut usize,
+        ) -> CVsysError,
+    >,
+    /// Open a TCP connection
+    pub connect: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            addr: *const CSocketAddr,
+            socket: *mut i64,
+        ) -> CVsysError,
+    >,
+    /// Bind a TCP listener
+    pub listen: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            addr: *const CSocketAddr,
+            socket: *mut i64,
+        ) -> CVsysError,
+    >,
+    /// Release an error message returned by another callback
+    pub free: Option<unsafe extern "C" fn(user_data: *mut c_void, ptr: *mut c_void)>,
+}
+
+unsafe impl Send for CNetVTable {}
+unsafe impl Sync for CNetVTable {}
+
+/// Most addresses read from one `resolve` call
+const MAX_RESOLVED: usize = 64;
+
+impl FsVTable {
+    /// Create a vtable calling `vtable`
+    ///
+    /// # Safety
+    ///
+    /// The callbacks must be safe to call with `user_data` from any thread
+    /// for as long as the returned vtable is used.
+    pub unsafe fn from_c(vtable: CFsVTable) -> Self {
+        let mut fs = Self::deny_all();
+        fs.read = Arc::new(move |path: &Path| vtable.read(path));
+        fs.read_to_string = Arc::new(move |path: &Path| {
+            String::from_utf8(vtable.read(path)?)
+                .map_err(|err| VsysError::InvalidArgument(err.to_string()))
+        });
+        fs.stat = Arc::new(move |path: &Path| vtable.stat(path));
+        fs.lstat = Arc::new(move |path: &Path| vtable.stat(path));
+        fs.exists = Arc::new(move |path: &Path| vtable.stat(path).is_ok());
+        fs.is_file =
+            Arc::new(move |path: &Path| vtable.stat(path).is_ok_and(|stat| stat.is_file()));
+        fs.is_dir = Arc::new(move |path: &Path| vtable.stat(path).is_ok_and(|stat| stat.is_dir()));
+        fs.read_dir = Arc::new(move |path: &Path| vtable.read_dir(path));
+        fs.write = Arc::new(move |path: &Path, data: &[u8]| {
+            let write = vtable.write.ok_or_else(|| denied("fs write"))?;
+            let path = c_path(path)?;
+            unsafe {
+                vtable.result(write(
+                    vtable.user_data,
+                    path.as_ptr(),
+                    data.as_ptr(),
+                    data.len(),
+                ))
+            }
+        });
+        fs.create_dir =
+            Arc::new(move |path: &Path| vtable.path_op(path, vtable.create_dir, "mkdir"));
+        fs.create_dir_all = Arc::new(move |path: &Path| {
+            for dir in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+                if !dir.as_os_str().is_empty() && vtable.stat(dir).is_err() {
+                    vtable.path_op(dir, vtable.create_dir, "mkdir")?;
+                }
+            }
+            Ok(())
+        });
+        fs.remove_file =
+            Arc::new(move |path: &Path| vtable.path_op(path, vtable.remove_file, "remove"));
+        fs.remove_dir =
+            Arc::new(move |path: &Path| vtable.path_op(path, vtable.remove_dir, "rmdir"));
+        fs.rename = Arc::new(move |from: &Path, to: &Path| {
+            let rename = vtable.rename.ok_or_else(|| denied("fs rename"))?;
+            let (from, to) = (c_path(from)?, c_path(to)?);
+            unsafe { vtable.result(rename(vtable.user_data, from.as_ptr(), to.as_ptr())) }
+        });
+        fs.canonicalize = Arc::new(|path: &Path| Ok(path.to_path_buf()));
+        fs
+    }
+}
+
+impl NetVTable {
+    /// Create a vtable calling `vtable`
+    ///
+    /// # Safety
+    ///
+    /// The callbacks must be safe to call with `user_data` from any thread
+    /// for as long as the returned vtable is used.
+    pub unsafe fn from_c(vtable: CNetVTable) -> Self {
+        Self {
+            resolve: Arc::new(move |host: &str| vtable.resolve(host)),
+            // Only address records can be answered, from `resolve`
+            query: Arc::new(move |query: &DnsQuery<'_>| {
+                if !query.servers.is_empty() {
+                    return Err(denied("net query with custom servers"));
+                }
+                let addrs = vtable.resolve(query.name)?.into_iter();
+                let data: Vec<_> = match query.record_type {
+                    RecordType::A => addrs
+                        .filter_map(|addr| match addr {
+                            IpAddr::V4(ip) => Some(RecordData::A(ip)),
+                            IpAddr::V6(_) => None,
+                        })
+                        .collect(),
+                    RecordType::Aaaa => addrs
+                        .filter_map(|addr| match addr {
+                            IpAddr::V6(ip) => Some(RecordData::Aaaa(ip)),
+                            IpAddr::V4(_) => None,
+                        })
+                        .collect(),
+                    record_type => {
+                        return Err(denied(&format!("net query {}", record_type.name())))
+                    }
+                };
+                Ok(data
+                    .into_iter()
+                    .map(|data| DnsRecord { ttl: 0, data })
+                    .collect())
+            }),
+            connect: Arc::new(move |addr: &SocketAddr| {
+                let connect = vtable.connect.ok_or_else(|| denied("net connect"))?;
+                let socket = unsafe { vtable.socket(connect, addr)? };
+                Ok(unsafe { from_raw_socket::<TcpStream>(socket) })
+            }),
+            listen: Arc::new(move |addr: &SocketAddr| {
+                let listen = vtable.listen.ok_or_else(|| denied("net listen"))?;
+                let socket = unsafe { vtable.socket(listen, addr)? };
+                Ok(unsafe { from_raw_socket::<TcpListener>(socket) })
+            }),
+        }
+    }
+}
+
+impl CFsVTable {
+    unsafe fn result(&self, error: CVsysError) -> VsysResult<()> {
+        into_result(error, self.free, self.user_data)
+    }
+
+    /// Copies a buffer returned by a callback, then frees it
+    unsafe fn take(&self, buffer: CVsysBuffer) -> Vec<u8> {
+        if buffer.data.is_null() {
+            return Vec::new();
+        }
+        let data = std::slice::from_raw_parts(buffer.data, buffer.len).to_vec();
+        if let Some(free) = self.free {
+            free(self.user_data, buffer.data.cast());
+        }
+        data
+    }
+
+    fn path_op(&self, path: &Path, op: Option<CPathFn>, name: &str) -> VsysResult<()> {
+        let op = op.ok_or_else(|| denied(&format!("fs {}", name)))?;
+        let path = c_path(path)?;
+        unsafe { self.result(op(self.user_data, path.as_ptr())) }
+    }
+
+    fn read(&self, path: &Path) -> VsysResult<Vec<u8>> {
+        let read = self.read.ok_or_else(|| denied("fs read"))?;
+        let path = c_path(path)?;
+        let mut buffer = CVsysBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        };
+        unsafe {
+            self.result(read(self.user_data, path.as_ptr(), &mut buffer))?;
+            Ok(self.take(buffer))
+        }
+    }
+
+    fn stat(&self, path: &Path) -> VsysResult<FileStat> {
+        let stat = self.stat.ok_or_else(|| denied("fs stat"))?;
+        let path = c_path(path)?;
+        let mut out = CFileStat::default();
+        unsafe { self.result(stat(self.user_data, path.as_ptr(), &mut out))? };
+        Ok(FileStat {
+            file_type: file_type(out.file_type),
+            size: out.size,
+            readonly: out.readonly,
+            modified: u64::try_from(out.modified_ms)
+                .ok()
+                .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms)),
+            accessed: None,
+            created: None,
+            mode: out.mode,
+            uid: out.uid,
+            gid: out.gid,
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> VsysResult<Vec<DirEntry>> {
+        let read_dir = self.read_dir.ok_or_else(|| denied("fs readdir"))?;
+        let path = c_path(path)?;
+        let mut buffer = CVsysBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        };
+        let listing = unsafe {
+            self.result(read_dir(self.user_data, path.as_ptr(), &mut buffer))?;
+            self.take(buffer)
+        };
+
+        // The file type code comes first, so it is never taken for the NUL ending a name
+        let mut entries = Vec::new();
+        let mut rest = listing.as_slice();
+        while let [kind, entry @ ..] = rest {
+            let Some(end) = entry.iter().position(|&byte| byte == 0) else {
+                return Err(VsysError::InvalidArgument(
+                    "malformed directory listing".into(),
+                ));
+            };
+            entries.push(DirEntry {
+                name: String::from_utf8_lossy(&entry[..end]).into_owned(),
+                file_type: file_type(*kind),
+            });
+            rest = &entry[end + 1..];
+        }
+        Ok(entries)
+    }
+}
+
+impl CNetVTable {
+    unsafe fn result(&self, error: CVsysError) -> VsysResult<()> {
+        into_result(error, self.free, self.user_data)
+    }
+
+    unsafe fn socket(
+        &self,
+        open: unsafe extern "C" fn(*mut c_void, *const CSocketAddr, *mut i64) -> CVsysError,
+        addr: &SocketAddr,
+    ) -> VsysResult<i64> {
+        let addr = CSocketAddr {
+            ip: CIpAddr::from_ip(addr.ip()),
+            port: addr.port(),
+        };
+        let mut socket = -1;
+        self.result(open(self.user_data, &addr, &mut socket))?;
+        if socket < 0 {
+            return Err(VsysError::InvalidArgument(
+                "callback returned no socket".into(),
+            ));
+        }
+        Ok(socket)
+    }
+
+    fn resolve(&self, host: &str) -> VsysResult<Vec<IpAddr>> {
+        let resolve = self.resolve.ok_or_else(|| denied("net resolve"))?;
+        let host = CString::new(host).map_err(|err| VsysError::InvalidArgument(err.to_string()))?;
+        let mut addrs = [CIpAddr::default(); MAX_RESOLVED];
+        let mut len = 0;
+        unsafe {
+            self.result(resolve(
+                self.user_data,
+                host.as_ptr(),
+                addrs.as_mut_ptr(),
+                addrs.len(),
+                &mut len,
+            ))?;
+        }
+        addrs[..len.min(MAX_RESOLVED)]
+            .iter()
+            .map(|addr| addr.to_ip())
+            .collect()
+    }
+}
+
+impl CIpAddr {
+    fn from_ip(ip: IpAddr) -> Self {
+        let mut octets = [0; 16];
+        let family = match ip {
+            IpAddr::V4(ip) => {
+                octets[..4].copy_from_slice(&ip.octets());
+                4
+            }
+            IpAddr::V6(ip) => {
+                octets = ip.octets();
+                6
+            }
+        };
+        Self { family, octets }
+    }
+
+    fn to_ip(self) -> VsysResult<IpAddr> {
+        match self.family {
+            4 => Ok(IpAddr::V4(Ipv4Addr::new(
+                self.octets[0],
+                self.octets[1],
+                self.octets[2],
+                self.octets[3],
+            ))),
+            6 => Ok(IpAddr::V6(Ipv6Addr::from(self.octets))),
+            family => Err(VsysError::InvalidArgument(format!(
+                "unknown address family {}",
+                family
+            ))),
+        }
+    }
+}
+
+#[cfg(unix)]
+unsafe fn from_raw_socket<T: std::os::fd::FromRawFd>(socket: i64) -> T {
+    T::from_raw_fd(socket as std::os::fd::RawFd)
+}
+
+#[cfg(windows)]
+unsafe fn from_raw_socket<T: std::os::windows::io::FromRawSocket>(socket: i64) -> T {
+    T::from_raw_socket(socket as std::os::windows::io::RawSocket)
+}
+
+/// Converts an error returned by a callback, freeing its message
+unsafe fn into_result(
+    error: CVsysError,
+    free: Option<unsafe extern "C" fn(*mut c_void, *mut c_void)>,
+    user_data: *mut c_void,
+) -> VsysResult<()> {
+    if error.code == CVsysError::OK {
+        return Ok(());
+    }
+    let message = if error.message.is_null() {
+        String::new()
+    } else {
+        let message = CStr::from_ptr(error.message.cast::<c_char>())
+            .to_string_lossy()
+            .into_owned();
+        if let Some(free) = free {
+            free(user_data, error.message.cast());
+        }
+        message
+    };
+    Err(match error.code {
+        CVsysError::ERR_IO => VsysError::Io(io::Error::other(message)),
+        CVsysError::ERR_PERMISSION_DENIED => VsysError::PermissionDenied(message),
+        CVsysError::ERR_NOT_FOUND => VsysError::NotFound(message),
+        CVsysError::ERR_NOT_SUPPORTED => VsysError::NotSupported(message),
+        CVsysError::ERR_INVALID_ARGUMENT => VsysError::InvalidArgument(message),
+        CVsysError::ERR_QUOTA_EXCEEDED => VsysError::QuotaExceeded(message),
+        code => VsysError::Custom { code, message },
+    })
+}
+
+fn denied(op: &str) -> VsysError {
+    VsysError::PermissionDenied(format!("{} denied", op))
+}
+
+fn c_path(path: &Path) -> VsysResult<CString> {
+    CString::new(path.as_os_str().as_encoded_bytes())
+        .map_err(|err| VsysError::InvalidArgument(err.to_string()))
+}
+
+fn file_type(code: u8) -> FileType {
+    match code {
+        C_FILE_TYPE_FILE => FileType::File,
+        C_FILE_TYPE_DIRECTORY => FileType::Directory,
+        C_FILE_TYPE_SYMLINK => FileType::Symlink,
+        _ => FileType::Other,
+    }
+}
+
+fn ok_or_error(result: VsysResult<()>) -> CVsysError {
+    match result {
+        Ok(()) => CVsysError::ok(),
+        Err(err) => CVsysError::from_error(&err),
+    }
+}
+
+fn null_argument(name: &str) -> CVsysError {
+    CVsysError::from_error(&VsysError::InvalidArgument(format!("{} is null", name)))
+}
+
+/// Create a vsys using the real system, with every permission granted
+///
+/// The result must be released with [`xmas_vsys_free`].
+#[no_mangle]
+pub extern "C" fn xmas_vsys_new() -> *mut Vsys {
+    Box::into_raw(Box::new(Vsys::new()))
+}
+
+/// Create a vsys denying every operation
+///
+/// The result must be released with [`xmas_vsys_free`].
+#[no_mangle]
+pub extern "C" fn xmas_vsys_sandboxed() -> *mut Vsys {
+    Box::into_raw(Box::new(Vsys::sandboxed()))
+}
+
+/// Release a vsys
+///
+/// # Safety
+///
+/// `vsys` must be null or come from [`xmas_vsys_new`] or
+/// [`xmas_vsys_sandboxed`], and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn xmas_vsys_free(vsys: *mut Vsys) {
+    if !vsys.is_null() {
+        drop(Box::from_raw(vsys));
+    }
+}
+
+/// Serve the filesystem of `vsys` with `vtable`, which is copied
+///
+/// # Safety
+///
+/// `vsys` must be a live vsys and `vtable` point to a valid vtable whose
+/// callbacks are safe to call from any thread while `vsys` is used.
+#[no_mangle]
+pub unsafe extern "C" fn xmas_vsys_set_fs(vsys: *mut Vsys, vtable: *const CFsVTable) -> CVsysError {
+    let (Some(vsys), Some(vtable)) = (vsys.as_mut(), vtable.as_ref()) else {
+        return null_argument("vsys or vtable");
+    };
+    vsys.fs = Arc::new(FsVTable::from_c(*vtable));
+    CVsysError::ok()
+}
+
+/// Serve the network of `vsys` with `vtable`, which is copied
+///
+/// # Safety
+///
+/// `vsys` must be a live vsys and `vtable` point to a valid vtable whose
+/// callbacks are safe to call from any thread while `vsys` is used.
+#[no_mangle]
+pub unsafe extern "C" fn xmas_vsys_set_net(
+    vsys: *mut Vsys,
+    vtable: *const CNetVTable,
+) -> CVsysError {
+    let (Some(vsys), Some(vtable)) = (vsys.as_mut(), vtable.as_ref()) else {
+        return null_argument("vsys or vtable");
+    };
+    vsys.net = Arc::new(NetVTable::from_c(*vtable));
+    CVsysError::ok()
+}
+
+/// Replace the permissions of `vsys` with a policy in the JSON format of
+/// the `permissions` table of `xmas.toml`
+///
+/// # Safety
+///
+/// `vsys` must be a live vsys and `json` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn xmas_vsys_set_permissions(
+    vsys: *mut Vsys,
+    json: *const c_char,
+) -> CVsysError {
+    let Some(vsys) = vsys.as_mut() else {
+        return null_argument("vsys");
+    };
+    if json.is_null() {
+        return null_argument("json");
+    }
+    let result = CStr::from_ptr(json)
+        .to_str()
+        .map_err(|err| VsysError::InvalidArgument(err.to_string()))
+        .and_then(|json| {
+            Permissions::from_json(json).map_err(|err| VsysError::InvalidArgument(err.to_string()))
+        })
+        .map(|permissions| vsys.permissions = permissions);
+    ok_or_error(result)
+}
+
+/// Release the message of an error returned by vsys
+///
+/// # Safety
+///
+/// `error` must be null or point to an error returned by vsys whose message
+/// has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn xmas_vsys_error_free(error: *mut CVsysError) {
+    if let Some(error) = error.as_mut() {
+        if !error.message.is_null() {
+            drop(CString::from_raw(error.message.cast()));
+            error.message = std::ptr::null_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn test_read(
+        _user_data: *mut c_void,
+        path: *const c_char,
+        out: *mut CVsysBuffer,
+    ) -> CVsysError {
+        if CStr::from_ptr(path).to_bytes() != b"/hello.txt" {
+            return CVsysError {
+                code: CVsysError::ERR_NOT_FOUND,
+                message: c"no such file".as_ptr().cast_mut().cast(),
+            };
+        }
+        *out = CVsysBuffer {
+            data: b"hello".as_ptr().cast_mut(),
+            len: 5,
+        };
+        CVsysError::ok()
+    }
+
+    unsafe extern "C" fn test_stat(
+        _user_data: *mut c_void,
+        path: *const c_char,
+        out: *mut CFileStat,
+    ) -> CVsysError {
+        match CStr::from_ptr(path).to_bytes() {
+            b"/" => (*out).file_type = C_FILE_TYPE_DIRECTORY,
+            b"/hello.txt" => (*out).size = 5,
+            _ => {
+                return CVsysError {
+                    code: CVsysError::ERR_NOT_FOUND,
+                    message: std::ptr::null_mut(),
+                }
+            }
+        }
+        (*out).modified_ms = -1;
+        CVsysError::ok()
+    }
+
+    unsafe extern "C" fn test_read_dir(
+        _user_data: *mut c_void,
+        _path: *const c_char,
+        out: *mut CVsysBuffer,
+    ) -> CVsysError {
+        static LISTING: &[u8] = b"\x00hello.txt\x00\x01sub\x00";
+        *out = CVsysBuffer {
+            data: LISTING.as_ptr().cast_mut(),
+            len: LISTING.len(),
+        };
+        CVsysError::ok()
+    }
+
+    unsafe extern "C" fn test_read_user_data(
+        user_data: *mut c_void,
+        _path: *const c_char,
+        out: *mut CVsysBuffer,
+    ) -> CVsysError {
+        let data = CStr::from_ptr(user_data.cast::<c_char>()).to_bytes();
+        *out = CVsysBuffer {
+            data: data.as_ptr().cast_mut(),
+            len: data.len(),
+        };
+        CVsysError::ok()
+    }
+
+    #[test]
+    fn test_c_fs_vtables_side_by_side() {
+        let vtable = |user_data: &'static CStr| CFsVTable {
+            user_data: user_data.as_ptr().cast_mut().cast(),
+            read: Some(test_read_user_data),
+            write: None,
+            stat: None,
+            read_dir: None,
+            create_dir: None,
+            remove_file: None,
+            remove_dir: None,
+            rename: None,
+            free: None,
+        };
+        let (first, second) = unsafe {
+            (
+                FsVTable::from_c(vtable(c"first")),
+                FsVTable::from_c(vtable(c"second")),
+            )
+        };
+        assert_eq!((first.read)(Path::new("/")).unwrap(), b"first");
+        assert_eq!((second.read)(Path::new("/")).unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_c_fs_vtable() {
+        let vsys = xmas_vsys_sandboxed();
+        let vtable = CFsVTable {
+            user_data: std::ptr::null_mut(),
+            read: Some(test_read),
+            write: None,
+            stat: Some(test_stat),
+            read_dir: Some(test_read_dir),
+            create_dir: None,
+            remove_file: None,
+            remove_dir: None,
+            rename: None,
+            free: None,
+        };
+
+        unsafe {
+            let mut error = xmas_vsys_set_fs(vsys, &vtable);
+            assert_eq!(error.code, CVsysError::OK);
+            xmas_vsys_error_free(&mut error);
+
+            let fs = (*vsys).fs();
+            assert_eq!(
+                (fs.read_to_string)(Path::new("/hello.txt")).unwrap(),
+                "hello"
+            );
+            assert!(matches!(
+                (fs.read)(Path::new("/missing")),
+                Err(VsysError::NotFound(message)) if message == "no such file"
+            ));
+            assert!((fs.is_dir)(Path::new("/")));
+            assert!((fs.is_file)(Path::new("/hello.txt")));
+            assert!(!(fs.exists)(Path::new("/missing")));
+            assert!((fs.stat)(Path::new("/hello.txt"))
+                .unwrap()
+                .modified
+                .is_none());
+
+            let entries = (fs.read_dir)(Path::new("/")).unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[1].name, "sub");
+            assert_eq!(entries[1].file_type, FileType::Directory);
+
+            // callbacks left out are denied
+            assert!(matches!(
+                (fs.write)(Path::new("/hello.txt"), b"x"),
+                Err(VsysError::PermissionDenied(_))
+            ));
+
+            let mut error = xmas_vsys_set_permissions(
+                vsys,
+                c"{\"fs_read\": {\"whitelist\": [\"/*\"]}}".as_ptr(),
+            );
+            assert_eq!(error.code, CVsysError::OK);
+            assert!((*vsys).permissions().check_fs_read(Path::new("/")));
+
+            error = xmas_vsys_set_permissions(vsys, c"{\"unknown\": true}".as_ptr());
+            assert_eq!(error.code, CVsysError::ERR_INVALID_ARGUMENT);
+            assert!(!error.message.is_null());
+            xmas_vsys_error_free(&mut error);
+            assert!(error.message.is_null());
+
+            xmas_vsys_free(vsys);
+        }
+    }
+}
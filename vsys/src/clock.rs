@@ -0,0 +1,82 @@
+//! Clock virtual table for vsys
+//!
+//! This module provides a pluggable time source. By default it uses the
+//! system clock and tokio timers, but embedders can replace it to freeze or
+//! fast-forward time for tests and deterministic replay.
+//!
+//! Monotonic time is a `Duration` since an arbitrary, fixed origin, so a
+//! virtual clock can start counting from zero.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A boxed future returned by [`ClockVTable::sleep`]
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Clock operations vtable
+///
+/// All functions are safe Rust function pointers. For C ABI compatibility,
+/// wrap these in extern "C" functions when needed.
+pub struct ClockVTable {
+    /// Current wall clock time
+    pub now: fn() -> SystemTime,
+    /// Current monotonic time, measured from a fixed origin
+    pub monotonic_now: fn() -> Duration,
+    /// Wait for the given duration of monotonic time to pass
+    pub sleep: fn(duration: Duration) -> SleepFuture,
+}
+
+impl Default for ClockVTable {
+    fn default() -> Self {
+        Self {
+            now: SystemTime::now,
+            monotonic_now: default_monotonic_now,
+            sleep: default_sleep,
+        }
+    }
+}
+
+impl ClockVTable {
+    /// Milliseconds since the Unix epoch, as used by `Date.now()`
+    pub fn now_millis(&self) -> f64 {
+        (self.now)()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as f64
+    }
+}
+
+// Default implementations using std::time and tokio
+
+static ORIGIN: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+fn default_monotonic_now() -> Duration {
+    ORIGIN.elapsed()
+}
+
+fn default_sleep(duration: Duration) -> SleepFuture {
+    Box::pin(tokio::time::sleep(duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_monotonic_now() {
+        let vtable = ClockVTable::default();
+        let start = (vtable.monotonic_now)();
+        assert!((vtable.monotonic_now)() >= start);
+    }
+
+    #[test]
+    fn test_now_millis() {
+        let vtable = ClockVTable {
+            now: || SystemTime::UNIX_EPOCH + Duration::from_millis(1234),
+            ..Default::default()
+        };
+        assert_eq!(vtable.now_millis(), 1234.0);
+    }
+}
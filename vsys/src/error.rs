@@ -20,6 +20,8 @@ pub enum VsysError {
     NotSupported(String),
     /// Invalid argument
     InvalidArgument(String),
+    /// A filesystem quota would be exceeded
+    QuotaExceeded(String),
     /// Module resolution error
     ModuleResolution { specifier: String, message: String },
     /// Module loading error
@@ -36,6 +38,7 @@ impl fmt::Display for VsysError {
             VsysError::NotFound(msg) => write!(f, "Not found: {}", msg),
             VsysError::NotSupported(msg) => write!(f, "Not supported: {}", msg),
             VsysError::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            VsysError::QuotaExceeded(msg) => write!(f, "Quota exceeded: {}", msg),
             VsysError::ModuleResolution { specifier, message } => {
                 write!(f, "Cannot resolve module '{}': {}", specifier, message)
             }
@@ -84,6 +87,7 @@ impl CVsysError {
     pub const ERR_INVALID_ARGUMENT: i32 = -5;
     pub const ERR_MODULE_RESOLUTION: i32 = -6;
     pub const ERR_MODULE_LOAD: i32 = -7;
+    pub const ERR_QUOTA_EXCEEDED: i32 = -8;
 
     pub fn ok() -> Self {
         Self {
@@ -99,6 +103,7 @@ impl CVsysError {
             VsysError::NotFound(_) => (Self::ERR_NOT_FOUND, e.to_string()),
             VsysError::NotSupported(_) => (Self::ERR_NOT_SUPPORTED, e.to_string()),
             VsysError::InvalidArgument(_) => (Self::ERR_INVALID_ARGUMENT, e.to_string()),
+            VsysError::QuotaExceeded(_) => (Self::ERR_QUOTA_EXCEEDED, e.to_string()),
             VsysError::ModuleResolution { .. } => (Self::ERR_MODULE_RESOLUTION, e.to_string()),
             VsysError::ModuleLoad { .. } => (Self::ERR_MODULE_LOAD, e.to_string()),
             VsysError::Custom { code, .. } => (*code, e.to_string()),
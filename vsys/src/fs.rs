@@ -6,6 +6,7 @@
 
 use std::fs::Metadata;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use crate::error::{VsysError, VsysResult};
@@ -298,94 +299,100 @@ impl FsHandleOps for StdFsHandle {
 
 /// Filesystem operations vtable
 ///
-/// All functions are safe Rust function pointers. For C ABI compatibility,
-/// wrap these in extern "C" functions when needed.
+/// All functions are closures, so a vtable can carry state of its own, like
+/// the root of a scoped one. For C ABI compatibility, see [`crate::capi`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
 pub struct FsVTable {
     // Read operations
-    pub read: fn(path: &Path) -> VsysResult<Vec<u8>>,
-    pub read_to_string: fn(path: &Path) -> VsysResult<String>,
-    pub stat: fn(path: &Path) -> VsysResult<FileStat>,
-    pub lstat: fn(path: &Path) -> VsysResult<FileStat>,
-    pub read_dir: fn(path: &Path) -> VsysResult<Vec<DirEntry>>,
-    pub read_link: fn(path: &Path) -> VsysResult<std::path::PathBuf>,
-    pub exists: fn(path: &Path) -> bool,
-    pub is_file: fn(path: &Path) -> bool,
-    pub is_dir: fn(path: &Path) -> bool,
+    pub read: Arc<dyn Fn(&Path) -> VsysResult<Vec<u8>> + Send + Sync>,
+    pub read_to_string: Arc<dyn Fn(&Path) -> VsysResult<String> + Send + Sync>,
+    pub stat: Arc<dyn Fn(&Path) -> VsysResult<FileStat> + Send + Sync>,
+    pub lstat: Arc<dyn Fn(&Path) -> VsysResult<FileStat> + Send + Sync>,
+    pub read_dir: Arc<dyn Fn(&Path) -> VsysResult<Vec<DirEntry>> + Send + Sync>,
+    pub read_link: Arc<dyn Fn(&Path) -> VsysResult<std::path::PathBuf> + Send + Sync>,
+    pub exists: Arc<dyn Fn(&Path) -> bool + Send + Sync>,
+    pub is_file: Arc<dyn Fn(&Path) -> bool + Send + Sync>,
+    pub is_dir: Arc<dyn Fn(&Path) -> bool + Send + Sync>,
 
     // Write operations
-    pub write: fn(path: &Path, data: &[u8]) -> VsysResult<()>,
-    pub append: fn(path: &Path, data: &[u8]) -> VsysResult<()>,
-    pub create_dir: fn(path: &Path) -> VsysResult<()>,
-    pub create_dir_all: fn(path: &Path) -> VsysResult<()>,
-    pub remove_file: fn(path: &Path) -> VsysResult<()>,
-    pub remove_dir: fn(path: &Path) -> VsysResult<()>,
-    pub remove_dir_all: fn(path: &Path) -> VsysResult<()>,
-    pub rename: fn(from: &Path, to: &Path) -> VsysResult<()>,
-    pub copy: fn(from: &Path, to: &Path) -> VsysResult<u64>,
-    pub symlink: fn(original: &Path, link: &Path) -> VsysResult<()>,
-    pub truncate: fn(path: &Path, size: u64) -> VsysResult<()>,
+    pub write: Arc<dyn Fn(&Path, &[u8]) -> VsysResult<()> + Send + Sync>,
+    pub append: Arc<dyn Fn(&Path, &[u8]) -> VsysResult<()> + Send + Sync>,
+    pub create_dir: Arc<dyn Fn(&Path) -> VsysResult<()> + Send + Sync>,
+    pub create_dir_all: Arc<dyn Fn(&Path) -> VsysResult<()> + Send + Sync>,
+    pub remove_file: Arc<dyn Fn(&Path) -> VsysResult<()> + Send + Sync>,
+    pub remove_dir: Arc<dyn Fn(&Path) -> VsysResult<()> + Send + Sync>,
+    pub remove_dir_all: Arc<dyn Fn(&Path) -> VsysResult<()> + Send + Sync>,
+    pub rename: Arc<dyn Fn(&Path, &Path) -> VsysResult<()> + Send + Sync>,
+    pub copy: Arc<dyn Fn(&Path, &Path) -> VsysResult<u64> + Send + Sync>,
+    pub symlink: Arc<dyn Fn(&Path, &Path) -> VsysResult<()> + Send + Sync>,
+    pub hard_link: Arc<dyn Fn(&Path, &Path) -> VsysResult<()> + Send + Sync>,
+    pub truncate: Arc<dyn Fn(&Path, u64) -> VsysResult<()> + Send + Sync>,
+    pub set_times: Arc<dyn Fn(&Path, SystemTime, SystemTime) -> VsysResult<()> + Send + Sync>,
 
     // Access check (F_OK=0, R_OK=4, W_OK=2, X_OK=1)
-    pub access: fn(path: &Path, mode: u32) -> VsysResult<()>,
+    pub access: Arc<dyn Fn(&Path, u32) -> VsysResult<()> + Send + Sync>,
 
     // Temp directory
-    pub mkdtemp: fn(prefix: &str) -> VsysResult<std::path::PathBuf>,
+    pub mkdtemp: Arc<dyn Fn(&str) -> VsysResult<std::path::PathBuf> + Send + Sync>,
 
     // Permissions
-    pub set_permissions: fn(path: &Path, readonly: bool) -> VsysResult<()>,
-    pub set_mode: fn(path: &Path, mode: u32) -> VsysResult<()>,
-    pub chown: fn(path: &Path, uid: u32, gid: u32) -> VsysResult<()>,
+    pub set_permissions: Arc<dyn Fn(&Path, bool) -> VsysResult<()> + Send + Sync>,
+    pub set_mode: Arc<dyn Fn(&Path, u32) -> VsysResult<()> + Send + Sync>,
+    pub chown: Arc<dyn Fn(&Path, u32, u32) -> VsysResult<()> + Send + Sync>,
 
     // Canonicalize
-    pub canonicalize: fn(path: &Path) -> VsysResult<std::path::PathBuf>,
+    pub canonicalize: Arc<dyn Fn(&Path) -> VsysResult<std::path::PathBuf> + Send + Sync>,
 
     // File handle operations
-    pub open: fn(path: &Path, options: &OpenOptions) -> VsysResult<FsHandle>,
+    pub open: Arc<dyn Fn(&Path, &OpenOptions) -> VsysResult<FsHandle> + Send + Sync>,
 }
 
 impl Default for FsVTable {
     fn default() -> Self {
         Self {
             // Read operations
-            read: default_read,
-            read_to_string: default_read_to_string,
-            stat: default_stat,
-            lstat: default_lstat,
-            read_dir: default_read_dir,
-            read_link: default_read_link,
-            exists: default_exists,
-            is_file: default_is_file,
-            is_dir: default_is_dir,
+            read: Arc::new(default_read),
+            read_to_string: Arc::new(default_read_to_string),
+            stat: Arc::new(default_stat),
+            lstat: Arc::new(default_lstat),
+            read_dir: Arc::new(default_read_dir),
+            read_link: Arc::new(default_read_link),
+            exists: Arc::new(default_exists),
+            is_file: Arc::new(default_is_file),
+            is_dir: Arc::new(default_is_dir),
 
             // Write operations
-            write: default_write,
-            append: default_append,
-            create_dir: default_create_dir,
-            create_dir_all: default_create_dir_all,
-            remove_file: default_remove_file,
-            remove_dir: default_remove_dir,
-            remove_dir_all: default_remove_dir_all,
-            rename: default_rename,
-            copy: default_copy,
-            symlink: default_symlink,
-            truncate: default_truncate,
+            write: Arc::new(default_write),
+            append: Arc::new(default_append),
+            create_dir: Arc::new(default_create_dir),
+            create_dir_all: Arc::new(default_create_dir_all),
+            remove_file: Arc::new(default_remove_file),
+            remove_dir: Arc::new(default_remove_dir),
+            remove_dir_all: Arc::new(default_remove_dir_all),
+            rename: Arc::new(default_rename),
+            copy: Arc::new(default_copy),
+            symlink: Arc::new(default_symlink),
+            hard_link: Arc::new(default_hard_link),
+            truncate: Arc::new(default_truncate),
+            set_times: Arc::new(default_set_times),
 
             // Access check
-            access: default_access,
+            access: Arc::new(default_access),
 
             // Temp directory
-            mkdtemp: default_mkdtemp,
+            mkdtemp: Arc::new(default_mkdtemp),
 
             // Permissions
-            set_permissions: default_set_permissions,
-            set_mode: default_set_mode,
-            chown: default_chown,
+            set_permissions: Arc::new(default_set_permissions),
+            set_mode: Arc::new(default_set_mode),
+            chown: Arc::new(default_chown),
 
             // Canonicalize
-            canonicalize: default_canonicalize,
+            canonicalize: Arc::new(default_canonicalize),
 
             // File handle
-            open: default_open,
+            open: Arc::new(default_open),
         }
     }
 }
@@ -394,58 +401,113 @@ impl FsVTable {
     /// Create a vtable that denies all operations
     pub fn deny_all() -> Self {
         Self {
-            read: |_| Err(VsysError::PermissionDenied("fs read denied".into())),
-            read_to_string: |_| Err(VsysError::PermissionDenied("fs read denied".into())),
-            stat: |_| Err(VsysError::PermissionDenied("fs stat denied".into())),
-            lstat: |_| Err(VsysError::PermissionDenied("fs lstat denied".into())),
-            read_dir: |_| Err(VsysError::PermissionDenied("fs readdir denied".into())),
-            read_link: |_| Err(VsysError::PermissionDenied("fs readlink denied".into())),
-            exists: |_| false,
-            is_file: |_| false,
-            is_dir: |_| false,
-            write: |_, _| Err(VsysError::PermissionDenied("fs write denied".into())),
-            append: |_, _| Err(VsysError::PermissionDenied("fs append denied".into())),
-            create_dir: |_| Err(VsysError::PermissionDenied("fs mkdir denied".into())),
-            create_dir_all: |_| Err(VsysError::PermissionDenied("fs mkdir denied".into())),
-            remove_file: |_| Err(VsysError::PermissionDenied("fs remove denied".into())),
-            remove_dir: |_| Err(VsysError::PermissionDenied("fs rmdir denied".into())),
-            remove_dir_all: |_| Err(VsysError::PermissionDenied("fs rmdir denied".into())),
-            rename: |_, _| Err(VsysError::PermissionDenied("fs rename denied".into())),
-            copy: |_, _| Err(VsysError::PermissionDenied("fs copy denied".into())),
-            symlink: |_, _| Err(VsysError::PermissionDenied("fs symlink denied".into())),
-            truncate: |_, _| Err(VsysError::PermissionDenied("fs truncate denied".into())),
-            access: |_, _| Err(VsysError::PermissionDenied("fs access denied".into())),
-            mkdtemp: |_| Err(VsysError::PermissionDenied("fs mkdtemp denied".into())),
-            set_permissions: |_, _| Err(VsysError::PermissionDenied("fs chmod denied".into())),
-            set_mode: |_, _| Err(VsysError::PermissionDenied("fs chmod denied".into())),
-            chown: |_, _, _| Err(VsysError::PermissionDenied("fs chown denied".into())),
-            canonicalize: |_| Err(VsysError::PermissionDenied("fs canonicalize denied".into())),
-            open: |_, _| Err(VsysError::PermissionDenied("fs open denied".into())),
+            read: Arc::new(|_: &Path| Err(VsysError::PermissionDenied("fs read denied".into()))),
+            read_to_string: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs read denied".into()))
+            }),
+            stat: Arc::new(|_: &Path| Err(VsysError::PermissionDenied("fs stat denied".into()))),
+            lstat: Arc::new(|_: &Path| Err(VsysError::PermissionDenied("fs lstat denied".into()))),
+            read_dir: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs readdir denied".into()))
+            }),
+            read_link: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs readlink denied".into()))
+            }),
+            exists: Arc::new(|_: &Path| false),
+            is_file: Arc::new(|_: &Path| false),
+            is_dir: Arc::new(|_: &Path| false),
+            write: Arc::new(|_: &Path, _: &[u8]| {
+                Err(VsysError::PermissionDenied("fs write denied".into()))
+            }),
+            append: Arc::new(|_: &Path, _: &[u8]| {
+                Err(VsysError::PermissionDenied("fs append denied".into()))
+            }),
+            create_dir: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs mkdir denied".into()))
+            }),
+            create_dir_all: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs mkdir denied".into()))
+            }),
+            remove_file: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs remove denied".into()))
+            }),
+            remove_dir: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs rmdir denied".into()))
+            }),
+            remove_dir_all: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs rmdir denied".into()))
+            }),
+            rename: Arc::new(|_: &Path, _: &Path| {
+                Err(VsysError::PermissionDenied("fs rename denied".into()))
+            }),
+            copy: Arc::new(|_: &Path, _: &Path| {
+                Err(VsysError::PermissionDenied("fs copy denied".into()))
+            }),
+            symlink: Arc::new(|_: &Path, _: &Path| {
+                Err(VsysError::PermissionDenied("fs symlink denied".into()))
+            }),
+            hard_link: Arc::new(|_: &Path, _: &Path| {
+                Err(VsysError::PermissionDenied("fs link denied".into()))
+            }),
+            truncate: Arc::new(|_: &Path, _: u64| {
+                Err(VsysError::PermissionDenied("fs truncate denied".into()))
+            }),
+            set_times: Arc::new(|_: &Path, _: SystemTime, _: SystemTime| {
+                Err(VsysError::PermissionDenied("fs utimes denied".into()))
+            }),
+            access: Arc::new(|_: &Path, _: u32| {
+                Err(VsysError::PermissionDenied("fs access denied".into()))
+            }),
+            mkdtemp: Arc::new(|_: &str| {
+                Err(VsysError::PermissionDenied("fs mkdtemp denied".into()))
+            }),
+            set_permissions: Arc::new(|_: &Path, _: bool| {
+                Err(VsysError::PermissionDenied("fs chmod denied".into()))
+            }),
+            set_mode: Arc::new(|_: &Path, _: u32| {
+                Err(VsysError::PermissionDenied("fs chmod denied".into()))
+            }),
+            chown: Arc::new(|_: &Path, _: u32, _: u32| {
+                Err(VsysError::PermissionDenied("fs chown denied".into()))
+            }),
+            canonicalize: Arc::new(|_: &Path| {
+                Err(VsysError::PermissionDenied("fs canonicalize denied".into()))
+            }),
+            open: Arc::new(|_: &Path, _: &OpenOptions| {
+                Err(VsysError::PermissionDenied("fs open denied".into()))
+            }),
         }
     }
 
     /// Create a read-only vtable
     pub fn read_only() -> Self {
-        let mut vtable = Self::default();
-        vtable.write = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.append = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.create_dir = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.create_dir_all = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.remove_file = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.remove_dir = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.remove_dir_all = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.rename = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.copy = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.symlink = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.truncate = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.mkdtemp = |_| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.set_permissions = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.set_mode = |_, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable.chown = |_, _, _| Err(VsysError::PermissionDenied("fs is read-only".into()));
-        vtable
+        Self {
+            write: Arc::new(|_: &Path, _: &[u8]| Err(read_only())),
+            append: Arc::new(|_: &Path, _: &[u8]| Err(read_only())),
+            create_dir: Arc::new(|_: &Path| Err(read_only())),
+            create_dir_all: Arc::new(|_: &Path| Err(read_only())),
+            remove_file: Arc::new(|_: &Path| Err(read_only())),
+            remove_dir: Arc::new(|_: &Path| Err(read_only())),
+            remove_dir_all: Arc::new(|_: &Path| Err(read_only())),
+            rename: Arc::new(|_: &Path, _: &Path| Err(read_only())),
+            copy: Arc::new(|_: &Path, _: &Path| Err(read_only())),
+            symlink: Arc::new(|_: &Path, _: &Path| Err(read_only())),
+            hard_link: Arc::new(|_: &Path, _: &Path| Err(read_only())),
+            truncate: Arc::new(|_: &Path, _: u64| Err(read_only())),
+            set_times: Arc::new(|_: &Path, _: SystemTime, _: SystemTime| Err(read_only())),
+            mkdtemp: Arc::new(|_: &str| Err(read_only())),
+            set_permissions: Arc::new(|_: &Path, _: bool| Err(read_only())),
+            set_mode: Arc::new(|_: &Path, _: u32| Err(read_only())),
+            chown: Arc::new(|_: &Path, _: u32, _: u32| Err(read_only())),
+            ..Self::default()
+        }
     }
 }
 
+fn read_only() -> VsysError {
+    VsysError::PermissionDenied("fs is read-only".into())
+}
+
 // Default implementations using std::fs
 
 fn default_read(path: &Path) -> VsysResult<Vec<u8>> {
@@ -552,6 +614,30 @@ fn default_symlink(original: &Path, link: &Path) -> VsysResult<()> {
     }
 }
 
+fn default_hard_link(original: &Path, link: &Path) -> VsysResult<()> {
+    std::fs::hard_link(original, link).map_err(Into::into)
+}
+
+fn default_set_times(path: &Path, accessed: SystemTime, modified: SystemTime) -> VsysResult<()> {
+    #[cfg(not(windows))]
+    let file = std::fs::File::open(path)?;
+    // Windows needs the right to write attributes, and directories a flag to open them
+    #[cfg(windows)]
+    let file = {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        std::fs::OpenOptions::new()
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)?
+    };
+    let times = std::fs::FileTimes::new()
+        .set_accessed(accessed)
+        .set_modified(modified);
+    file.set_times(times).map_err(Into::into)
+}
+
 fn default_set_permissions(path: &Path, readonly: bool) -> VsysResult<()> {
     let mut perms = std::fs::metadata(path)?.permissions();
     perms.set_readonly(readonly);
@@ -710,7 +796,7 @@ fn default_open(path: &Path, options: &OpenOptions) -> VsysResult<FsHandle> {
     }
 
     let file = std_options.open(path)?;
-    Ok(FsHandle::new(StdFsHandle::new(file)))
+    Ok(FsHandle::new(StdFsHandle { file }))
 }
 
 #[cfg(test)]
@@ -807,6 +893,24 @@ mod tests {
         assert_eq!(data, b"hello");
     }
 
+    #[test]
+    fn test_hard_link_and_times() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("original.txt");
+        let link_path = dir.path().join("link.txt");
+
+        let vtable = FsVTable::default();
+
+        (vtable.write)(&file_path, b"hello").unwrap();
+        (vtable.hard_link)(&file_path, &link_path).unwrap();
+        (vtable.append)(&link_path, b" world").unwrap();
+        assert_eq!((vtable.read)(&file_path).unwrap(), b"hello world");
+
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        (vtable.set_times)(&file_path, time, time).unwrap();
+        assert_eq!((vtable.stat)(&file_path).unwrap().modified, Some(time));
+    }
+
     #[test]
     fn test_access() {
         let dir = tempdir().unwrap();
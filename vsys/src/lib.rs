@@ -6,10 +6,10 @@
 //!
 //! ## Design Goals
 //!
-//! - **C ABI compatible**: All function pointers use `extern "C"` for FFI compatibility
+//! - **C ABI compatible**: Embedders can supply vtables as C callbacks, see [`capi`]
 //! - **Runtime swappable**: Change implementation at runtime
-//! - **Zero-cost when static**: Compiler can inline when implementation is known
-//! - **No trait objects**: Avoids dynamic dispatch overhead
+//! - **Per-instance state**: Vtables whose implementations need state, like the root of
+//!   a scoped filesystem, hold closures owning it, so two of them never share it
 //!
 //! ## Usage
 //!
@@ -20,21 +20,58 @@
 //! // Sandboxed: Custom implementations
 //! let vsys = Vsys::builder()
 //!     .fs(custom_fs_vtable())
+//!     .net(proxied_net_vtable())
+//!     .clock(frozen_clock_vtable())
+//!     .proc(ProcVTable::deny_all())
+//!     .random(RandomVTable::seeded(42))
+//!     .stdio(StdioVTable::captured(capture.clone()))
 //!     .permissions(restricted_permissions())
 //!     .build();
 //! ```
 
+/// A vtable function owning a clone of the `Arc` `$state`, for vtables carrying state of
+/// their own
+macro_rules! with_state {
+    ($state:ident, |$($arg:tt: $ty:ty),*| $body:expr) => {{
+        let $state = ::std::sync::Arc::clone(&$state);
+        ::std::sync::Arc::new(move |$($arg: $ty),*| $body)
+    }};
+}
+
+pub mod archive;
+pub mod audit;
+pub mod capi;
+pub mod clock;
 pub mod error;
 pub mod fs;
 pub mod module_loader;
+pub mod net;
+pub mod path;
 pub mod permissions;
+pub mod proc;
+pub mod quota;
+pub mod random;
+pub mod remote;
+mod scoped;
+pub mod stdio;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+pub use archive::ArchiveFs;
+pub use audit::{AuditEvent, AuditSink};
+pub use clock::ClockVTable;
 pub use error::{VsysError, VsysResult};
 pub use fs::FsVTable;
 pub use module_loader::ModuleLoaderVTable;
-pub use permissions::{BlackOrWhiteList, Permissions};
+pub use net::{DnsQuery, DnsRecord, NetVTable, RecordData, RecordType};
+pub use permissions::{BlackOrWhiteList, Capability, ModuleScope, PermissionPrompt, Permissions};
+pub use proc::{ProcCommand, ProcVTable};
+pub use quota::{FsQuota, FsUsage};
+pub use random::RandomVTable;
+pub use remote::RemoteCache;
+pub use stdio::{StdStream, StdioCapture, StdioVTable};
 
 /// The main vsys context that holds all virtual system tables.
 ///
@@ -46,8 +83,24 @@ pub struct Vsys {
     pub fs: Arc<FsVTable>,
     /// Module loader/resolver vtable
     pub module_loader: Arc<ModuleLoaderVTable>,
+    /// Network operations vtable
+    pub net: Arc<NetVTable>,
+    /// Clock/time vtable
+    pub clock: Arc<ClockVTable>,
+    /// Subprocess vtable
+    pub proc: Arc<ProcVTable>,
+    /// Randomness vtable
+    pub random: Arc<RandomVTable>,
+    /// Standard streams vtable
+    pub stdio: Arc<StdioVTable>,
     /// Permissions configuration
     pub permissions: Permissions,
+    /// Audit sink, recording checked operations when set
+    pub audit: Option<Arc<AuditSink>>,
+    /// Asked about capabilities `permissions` denies
+    pub prompt: Option<PermissionPrompt>,
+    /// Answers of `prompt` for this session
+    answers: Arc<Mutex<HashMap<Capability, bool>>>,
 }
 
 impl Default for Vsys {
@@ -55,7 +108,15 @@ impl Default for Vsys {
         Self {
             fs: Arc::new(FsVTable::default()),
             module_loader: Arc::new(ModuleLoaderVTable::default()),
+            net: Arc::new(NetVTable::default()),
+            clock: Arc::new(ClockVTable::default()),
+            proc: Arc::new(ProcVTable::default()),
+            random: Arc::new(RandomVTable::default()),
+            stdio: Arc::new(StdioVTable::default()),
             permissions: Permissions::allow_all(),
+            audit: None,
+            prompt: None,
+            answers: Default::default(),
         }
     }
 }
@@ -76,7 +137,15 @@ impl Vsys {
         Self {
             fs: Arc::new(FsVTable::deny_all()),
             module_loader: Arc::new(ModuleLoaderVTable::default()),
+            net: Arc::new(NetVTable::deny_all()),
+            clock: Arc::new(ClockVTable::default()),
+            proc: Arc::new(ProcVTable::deny_all()),
+            random: Arc::new(RandomVTable::default()),
+            stdio: Arc::new(StdioVTable::default()),
             permissions: Permissions::default(), // deny all by default
+            audit: None,
+            prompt: None,
+            answers: Default::default(),
         }
     }
 
@@ -92,11 +161,99 @@ impl Vsys {
         &self.module_loader
     }
 
+    /// Get a reference to the network vtable
+    #[inline]
+    pub fn net(&self) -> &NetVTable {
+        &self.net
+    }
+
+    /// Get a reference to the clock vtable
+    #[inline]
+    pub fn clock(&self) -> &ClockVTable {
+        &self.clock
+    }
+
+    /// Get a reference to the subprocess vtable
+    #[inline]
+    pub fn proc(&self) -> &ProcVTable {
+        &self.proc
+    }
+
+    /// Get a reference to the randomness vtable
+    #[inline]
+    pub fn random(&self) -> &RandomVTable {
+        &self.random
+    }
+
+    /// Get a reference to the standard streams vtable
+    #[inline]
+    pub fn stdio(&self) -> &StdioVTable {
+        &self.stdio
+    }
+
     /// Get a reference to the permissions configuration
     #[inline]
     pub fn permissions(&self) -> &Permissions {
         &self.permissions
     }
+
+    /// Check `capability` is allowed, prompting when it is denied
+    pub fn check(&self, capability: &Capability) -> bool {
+        self.permissions.allows(capability) || self.ask(capability.clone())
+    }
+
+    /// Check the scope of `module`, if it has one, allows `capability`
+    ///
+    /// Scopes only narrow the global permissions and are never prompted for.
+    pub fn check_module(&self, module: &str, capability: &Capability) -> bool {
+        self.permissions
+            .for_module(module)
+            .is_none_or(|scope| scope.allows(capability))
+    }
+
+    /// Check read access to `path`, prompting when it is denied
+    pub fn check_fs_read(&self, path: &Path) -> bool {
+        self.check(&Capability::FsRead(path.to_path_buf()))
+    }
+
+    /// Check write access to `path`, prompting when it is denied
+    pub fn check_fs_write(&self, path: &Path) -> bool {
+        self.check(&Capability::FsWrite(path.to_path_buf()))
+    }
+
+    /// Check read and write access to `path`, prompting when either is denied
+    pub fn check_fs(&self, path: &Path) -> bool {
+        self.check_fs_read(path) && self.check_fs_write(path)
+    }
+
+    /// Check network access to `host`, prompting when it is denied
+    pub fn check_net(&self, host: &str) -> bool {
+        self.check(&Capability::Net(host.to_string()))
+    }
+
+    /// Check access to the environment variable `name`, prompting when it is denied
+    pub fn check_env(&self, name: &str) -> bool {
+        self.check(&Capability::Env(name.to_string()))
+    }
+
+    fn ask(&self, capability: Capability) -> bool {
+        let Some(prompt) = &self.prompt else {
+            return false;
+        };
+        // held while prompting, so concurrent checks ask only once
+        let mut answers = self.answers.lock().unwrap_or_else(|err| err.into_inner());
+        *answers
+            .entry(capability)
+            .or_insert_with_key(|capability| prompt(capability))
+    }
+
+    /// Record `event` in the audit sink, if there is one
+    #[inline]
+    pub fn audit(&self, event: AuditEvent<'_>) {
+        if let Some(audit) = &self.audit {
+            audit.record(&event);
+        }
+    }
 }
 
 /// Builder for constructing a customized Vsys instance
@@ -104,7 +261,14 @@ impl Vsys {
 pub struct VsysBuilder {
     fs: Option<FsVTable>,
     module_loader: Option<ModuleLoaderVTable>,
+    net: Option<NetVTable>,
+    clock: Option<ClockVTable>,
+    proc: Option<ProcVTable>,
+    random: Option<RandomVTable>,
+    stdio: Option<StdioVTable>,
     permissions: Option<Permissions>,
+    audit: Option<AuditSink>,
+    prompt: Option<PermissionPrompt>,
 }
 
 impl VsysBuilder {
@@ -118,16 +282,59 @@ impl VsysBuilder {
         self
     }
 
+    pub fn net(mut self, net: NetVTable) -> Self {
+        self.net = Some(net);
+        self
+    }
+
+    pub fn clock(mut self, clock: ClockVTable) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn proc(mut self, proc: ProcVTable) -> Self {
+        self.proc = Some(proc);
+        self
+    }
+
+    pub fn random(mut self, random: RandomVTable) -> Self {
+        self.random = Some(random);
+        self
+    }
+
+    pub fn stdio(mut self, stdio: StdioVTable) -> Self {
+        self.stdio = Some(stdio);
+        self
+    }
+
     pub fn permissions(mut self, permissions: Permissions) -> Self {
         self.permissions = Some(permissions);
         self
     }
 
+    pub fn audit(mut self, audit: AuditSink) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn prompt(mut self, prompt: impl Fn(&Capability) -> bool + Send + Sync + 'static) -> Self {
+        self.prompt = Some(Arc::new(prompt));
+        self
+    }
+
     pub fn build(self) -> Vsys {
         Vsys {
             fs: Arc::new(self.fs.unwrap_or_default()),
             module_loader: Arc::new(self.module_loader.unwrap_or_default()),
+            net: Arc::new(self.net.unwrap_or_default()),
+            clock: Arc::new(self.clock.unwrap_or_default()),
+            proc: Arc::new(self.proc.unwrap_or_default()),
+            random: Arc::new(self.random.unwrap_or_default()),
+            stdio: Arc::new(self.stdio.unwrap_or_default()),
             permissions: self.permissions.unwrap_or_else(Permissions::allow_all),
+            audit: self.audit.map(Arc::new),
+            prompt: self.prompt,
+            answers: Default::default(),
         }
     }
 }
@@ -153,4 +360,25 @@ mod tests {
         let vsys = Vsys::builder().permissions(Permissions::default()).build();
         assert!(!vsys.permissions.stdio);
     }
+
+    #[test]
+    fn test_prompt() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static PROMPTS: AtomicUsize = AtomicUsize::new(0);
+        let vsys = Vsys::builder()
+            .permissions(Permissions::default())
+            .prompt(|capability| {
+                PROMPTS.fetch_add(1, Ordering::SeqCst);
+                matches!(capability, Capability::Net(host) if host == "example.com")
+            })
+            .build();
+
+        assert!(vsys.check_net("example.com"));
+        assert!(vsys.check_net("example.com"));
+        assert!(!vsys.check_env("HOME"));
+        assert!(!vsys.check_env("HOME"));
+        assert_eq!(PROMPTS.load(Ordering::SeqCst), 2);
+        assert!(!Vsys::sandboxed().check_net("example.com"));
+    }
 }
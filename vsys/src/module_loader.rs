@@ -13,6 +13,7 @@
 //! - Support custom module sources (bundled, remote, in-memory)
 
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::error::{VsysError, VsysResult};
 use crate::fs::FsVTable;
@@ -60,11 +61,10 @@ pub struct ModuleSource {
 /// All functions receive a reference to `FsVTable` to perform filesystem operations,
 /// ensuring the module loader respects the virtual filesystem abstraction.
 ///
-/// # C ABI Compatibility
-///
-/// All function pointers use simple types and can be safely called from C.
-/// The `FsVTable` pointer allows the loader to perform filesystem operations
-/// through the virtual layer.
+/// All functions are closures, so a loader can carry state of its own, like
+/// the cache of a remote one.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
 pub struct ModuleLoaderVTable {
     /// Resolve a module specifier to an absolute path
     ///
@@ -76,12 +76,8 @@ pub struct ModuleLoaderVTable {
     ///
     /// # Returns
     /// Resolved module information or error
-    pub resolve: fn(
-        fs: &FsVTable,
-        specifier: &str,
-        referrer: &str,
-        is_esm: bool,
-    ) -> VsysResult<ResolvedModule>,
+    pub resolve:
+        Arc<dyn Fn(&FsVTable, &str, &str, bool) -> VsysResult<ResolvedModule> + Send + Sync>,
 
     /// Load a module's source code
     ///
@@ -91,20 +87,20 @@ pub struct ModuleLoaderVTable {
     ///
     /// # Returns
     /// Module source or error
-    pub load: fn(fs: &FsVTable, path: &str) -> VsysResult<ModuleSource>,
+    pub load: Arc<dyn Fn(&FsVTable, &str) -> VsysResult<ModuleSource> + Send + Sync>,
 
     /// Check if a module exists at the given path
     ///
     /// # Arguments
     /// * `fs` - The filesystem vtable to use for file operations
     /// * `path` - The path to check
-    pub exists: fn(fs: &FsVTable, path: &str) -> bool,
+    pub exists: Arc<dyn Fn(&FsVTable, &str) -> bool + Send + Sync>,
 
     /// Check if a specifier is a built-in module
-    pub is_builtin: fn(specifier: &str) -> bool,
+    pub is_builtin: Arc<dyn Fn(&str) -> bool + Send + Sync>,
 
     /// List all built-in module names
-    pub list_builtins: fn() -> Vec<String>,
+    pub list_builtins: Arc<dyn Fn() -> Vec<String> + Send + Sync>,
 
     /// Find the closest package.json from a directory
     ///
@@ -114,7 +110,7 @@ pub struct ModuleLoaderVTable {
     ///
     /// # Returns
     /// Path to package.json if found
-    pub find_package_json: fn(fs: &FsVTable, start_dir: &str) -> Option<String>,
+    pub find_package_json: Arc<dyn Fn(&FsVTable, &str) -> Option<String> + Send + Sync>,
 
     /// Read and parse package.json
     ///
@@ -124,19 +120,20 @@ pub struct ModuleLoaderVTable {
     ///
     /// # Returns
     /// Parsed package.json as JSON value
-    pub read_package_json: fn(fs: &FsVTable, path: &str) -> VsysResult<serde_json::Value>,
+    pub read_package_json:
+        Arc<dyn Fn(&FsVTable, &str) -> VsysResult<serde_json::Value> + Send + Sync>,
 }
 
 impl Default for ModuleLoaderVTable {
     fn default() -> Self {
         Self {
-            resolve: default_resolve,
-            load: default_load,
-            exists: default_exists,
-            is_builtin: default_is_builtin,
-            list_builtins: default_list_builtins,
-            find_package_json: default_find_package_json,
-            read_package_json: default_read_package_json,
+            resolve: Arc::new(default_resolve),
+            load: Arc::new(default_load),
+            exists: Arc::new(default_exists),
+            is_builtin: Arc::new(default_is_builtin),
+            list_builtins: Arc::new(default_list_builtins),
+            find_package_json: Arc::new(default_find_package_json),
+            read_package_json: Arc::new(default_read_package_json),
         }
     }
 }
@@ -145,18 +142,18 @@ impl ModuleLoaderVTable {
     /// Create a loader that only allows built-in modules
     pub fn builtins_only() -> Self {
         Self {
-            resolve: builtins_only_resolve,
-            load: builtins_only_load,
-            exists: |_, _| false,
-            is_builtin: default_is_builtin,
-            list_builtins: default_list_builtins,
-            find_package_json: |_, _| None,
-            read_package_json: |_, _| {
+            resolve: Arc::new(builtins_only_resolve),
+            load: Arc::new(builtins_only_load),
+            exists: Arc::new(|_: &FsVTable, _: &str| false),
+            is_builtin: Arc::new(default_is_builtin),
+            list_builtins: Arc::new(default_list_builtins),
+            find_package_json: Arc::new(|_: &FsVTable, _: &str| None),
+            read_package_json: Arc::new(|_: &FsVTable, _: &str| {
                 Err(VsysError::ModuleResolution {
                     specifier: String::new(),
                     message: "Filesystem access not allowed".to_string(),
                 })
-            },
+            }),
         }
     }
 }
@@ -226,7 +223,7 @@ fn default_list_builtins() -> Vec<String> {
     BUILTIN_MODULES.iter().map(|s| s.to_string()).collect()
 }
 
-fn default_resolve(
+pub(crate) fn default_resolve(
     fs: &FsVTable,
     specifier: &str,
     referrer: &str,
@@ -413,7 +410,7 @@ fn try_resolve_node_modules(
     None
 }
 
-fn detect_format(path: &Path) -> ModuleFormat {
+pub(crate) fn detect_format(path: &Path) -> ModuleFormat {
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     match ext {
         "mjs" | "mts" => ModuleFormat::ESM,
@@ -428,7 +425,7 @@ fn detect_format(path: &Path) -> ModuleFormat {
     }
 }
 
-fn default_load(fs: &FsVTable, path: &str) -> VsysResult<ModuleSource> {
+pub(crate) fn default_load(fs: &FsVTable, path: &str) -> VsysResult<ModuleSource> {
     // Built-in modules are handled separately
     if default_is_builtin(path) {
         return Err(VsysError::ModuleLoad {
@@ -448,7 +445,7 @@ fn default_load(fs: &FsVTable, path: &str) -> VsysResult<ModuleSource> {
     })
 }
 
-fn default_exists(fs: &FsVTable, path: &str) -> bool {
+pub(crate) fn default_exists(fs: &FsVTable, path: &str) -> bool {
     path_exists(fs, Path::new(path))
 }
 
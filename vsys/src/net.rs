@@ -0,0 +1,362 @@
+//! Network virtual table for vsys
+//!
+//! This module provides a pluggable network abstraction layer. By default it
+//! uses the real network stack (std::net), but embedders can replace it to
+//! mock, proxy or record every connection the runtime makes.
+//!
+//! The hooks are blocking and hand back std sockets; async callers run them
+//! on a blocking thread and convert the sockets afterwards.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{VsysError, VsysResult};
+
+/// Network operations vtable
+///
+/// All functions are closures, so a vtable can carry state of its own, like
+/// the callbacks of a C one. For C ABI compatibility, see [`crate::capi`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct NetVTable {
+    /// Resolve a host name to its addresses
+    pub resolve: Arc<dyn Fn(&str) -> VsysResult<Vec<IpAddr>> + Send + Sync>,
+    /// Query DNS records of one type
+    ///
+    /// A name that does not exist is `NotFound`; a name without records of
+    /// the requested type yields an empty list.
+    pub query: Arc<dyn Fn(&DnsQuery<'_>) -> VsysResult<Vec<DnsRecord>> + Send + Sync>,
+    /// Open a TCP connection
+    pub connect: Arc<dyn Fn(&SocketAddr) -> VsysResult<TcpStream> + Send + Sync>,
+    /// Bind a TCP listener
+    pub listen: Arc<dyn Fn(&SocketAddr) -> VsysResult<TcpListener> + Send + Sync>,
+}
+
+impl Default for NetVTable {
+    fn default() -> Self {
+        Self {
+            resolve: Arc::new(default_resolve),
+            query: Arc::new(default_query),
+            connect: Arc::new(default_connect),
+            listen: Arc::new(default_listen),
+        }
+    }
+}
+
+impl NetVTable {
+    /// Create a vtable that denies all operations
+    pub fn deny_all() -> Self {
+        Self {
+            resolve: Arc::new(|_: &str| {
+                Err(VsysError::PermissionDenied("net resolve denied".into()))
+            }),
+            query: Arc::new(|_: &DnsQuery<'_>| {
+                Err(VsysError::PermissionDenied("net query denied".into()))
+            }),
+            connect: Arc::new(|_: &SocketAddr| {
+                Err(VsysError::PermissionDenied("net connect denied".into()))
+            }),
+            listen: Arc::new(|_: &SocketAddr| {
+                Err(VsysError::PermissionDenied("net listen denied".into()))
+            }),
+        }
+    }
+
+    /// Connect to the first reachable of `addrs`, returning the last error otherwise
+    pub fn connect_any(&self, addrs: &[SocketAddr]) -> VsysResult<TcpStream> {
+        let mut last_error = None;
+        for addr in addrs {
+            match (self.connect)(addr) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| VsysError::NotFound("no addresses to connect".into())))
+    }
+}
+
+/// DNS record types understood by [`NetVTable::query`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Ns,
+    Ptr,
+    Soa,
+    Srv,
+    Txt,
+}
+
+impl RecordType {
+    /// Parse an uppercase record type name such as `"AAAA"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "A" => Self::A,
+            "AAAA" => Self::Aaaa,
+            "CNAME" => Self::Cname,
+            "MX" => Self::Mx,
+            "NS" => Self::Ns,
+            "PTR" => Self::Ptr,
+            "SOA" => Self::Soa,
+            "SRV" => Self::Srv,
+            "TXT" => Self::Txt,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Mx => "MX",
+            Self::Ns => "NS",
+            Self::Ptr => "PTR",
+            Self::Soa => "SOA",
+            Self::Srv => "SRV",
+            Self::Txt => "TXT",
+        }
+    }
+}
+
+/// A DNS query
+#[derive(Debug, Clone)]
+pub struct DnsQuery<'a> {
+    pub name: &'a str,
+    pub record_type: RecordType,
+    /// Name servers to ask, or the system configuration when empty
+    pub servers: &'a [SocketAddr],
+    /// Timeout of each attempt
+    pub timeout: Option<Duration>,
+    /// Attempts per name server
+    pub tries: Option<usize>,
+}
+
+/// A DNS answer record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsRecord {
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// Data of a DNS answer record, names have no trailing dot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Mx {
+        priority: u16,
+        exchange: String,
+    },
+    Ns(String),
+    Ptr(String),
+    Soa {
+        nsname: String,
+        hostmaster: String,
+        serial: u32,
+        refresh: i32,
+        retry: i32,
+        expire: i32,
+        minttl: u32,
+    },
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        name: String,
+    },
+    Txt(Vec<String>),
+}
+
+impl RecordData {
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            Self::A(_) => RecordType::A,
+            Self::Aaaa(_) => RecordType::Aaaa,
+            Self::Cname(_) => RecordType::Cname,
+            Self::Mx { .. } => RecordType::Mx,
+            Self::Ns(_) => RecordType::Ns,
+            Self::Ptr(_) => RecordType::Ptr,
+            Self::Soa { .. } => RecordType::Soa,
+            Self::Srv { .. } => RecordType::Srv,
+            Self::Txt(_) => RecordType::Txt,
+        }
+    }
+}
+
+// Default implementations using std::net and hickory-resolver
+
+fn default_resolve(host: &str) -> VsysResult<Vec<IpAddr>> {
+    let addrs = (host, 0).to_socket_addrs()?;
+    Ok(addrs.map(|addr| addr.ip()).collect())
+}
+
+fn default_query(query: &DnsQuery<'_>) -> VsysResult<Vec<DnsRecord>> {
+    use hickory_resolver::config::{NameServerConfig, ResolverConfig};
+    use hickory_resolver::name_server::TokioConnectionProvider;
+    use hickory_resolver::proto::rr::{RData, RecordType as HickoryType};
+    use hickory_resolver::proto::xfer::Protocol;
+    use hickory_resolver::{Name, Resolver};
+
+    let mut builder = if query.servers.is_empty() {
+        Resolver::builder_tokio().map_err(resolve_error)?
+    } else {
+        let mut config = ResolverConfig::new();
+        for addr in query.servers {
+            config.add_name_server(NameServerConfig::new(*addr, Protocol::Udp));
+            config.add_name_server(NameServerConfig::new(*addr, Protocol::Tcp));
+        }
+        Resolver::builder_with_config(config, TokioConnectionProvider::default())
+    };
+    let options = builder.options_mut();
+    if let Some(timeout) = query.timeout {
+        options.timeout = timeout;
+    }
+    if let Some(tries) = query.tries {
+        options.attempts = tries;
+    }
+    options.cache_size = 0;
+    let resolver = builder.build();
+
+    let record_type = match query.record_type {
+        RecordType::A => HickoryType::A,
+        RecordType::Aaaa => HickoryType::AAAA,
+        RecordType::Cname => HickoryType::CNAME,
+        RecordType::Mx => HickoryType::MX,
+        RecordType::Ns => HickoryType::NS,
+        RecordType::Ptr => HickoryType::PTR,
+        RecordType::Soa => HickoryType::SOA,
+        RecordType::Srv => HickoryType::SRV,
+        RecordType::Txt => HickoryType::TXT,
+    };
+    // Queries are not expanded with the search list, like c-ares in Node
+    let name = Name::from_utf8(format!("{}.", query.name.trim_end_matches('.')))
+        .map_err(|err| VsysError::InvalidArgument(err.to_string()))?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let lookup = match runtime.block_on(resolver.lookup(name, record_type)) {
+        Ok(lookup) => lookup,
+        Err(err) if err.is_no_records_found() && !err.is_nx_domain() => return Ok(Vec::new()),
+        Err(err) => return Err(resolve_error(err)),
+    };
+
+    let name = |name: &Name| name.to_utf8().trim_end_matches('.').to_string();
+    let records = lookup.record_iter().filter_map(|record| {
+        let data = match record.data() {
+            RData::A(a) => RecordData::A(a.0),
+            RData::AAAA(aaaa) => RecordData::Aaaa(aaaa.0),
+            RData::CNAME(cname) => RecordData::Cname(name(&cname.0)),
+            RData::MX(mx) => RecordData::Mx {
+                priority: mx.preference(),
+                exchange: name(mx.exchange()),
+            },
+            RData::NS(ns) => RecordData::Ns(name(&ns.0)),
+            RData::PTR(ptr) => RecordData::Ptr(name(&ptr.0)),
+            RData::SOA(soa) => RecordData::Soa {
+                nsname: name(soa.mname()),
+                hostmaster: name(soa.rname()),
+                serial: soa.serial(),
+                refresh: soa.refresh(),
+                retry: soa.retry(),
+                expire: soa.expire(),
+                minttl: soa.minimum(),
+            },
+            RData::SRV(srv) => RecordData::Srv {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                name: name(srv.target()),
+            },
+            RData::TXT(txt) => RecordData::Txt(
+                txt.iter()
+                    .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+                    .collect(),
+            ),
+            _ => return None,
+        };
+        // Answers may carry the CNAME chain that led to the records asked for
+        (data.record_type() == query.record_type).then_some(DnsRecord {
+            ttl: record.ttl(),
+            data,
+        })
+    });
+    Ok(records.collect())
+}
+
+fn resolve_error(err: hickory_resolver::ResolveError) -> VsysError {
+    use hickory_resolver::proto::ProtoErrorKind;
+
+    if err.is_nx_domain() {
+        return VsysError::NotFound(err.to_string());
+    }
+    match err.proto().map(|proto| proto.kind()) {
+        Some(ProtoErrorKind::Timeout) => io::Error::new(io::ErrorKind::TimedOut, err).into(),
+        Some(ProtoErrorKind::Io(io)) => io::Error::new(io.kind(), err).into(),
+        _ => io::Error::other(err).into(),
+    }
+}
+
+fn default_connect(addr: &SocketAddr) -> VsysResult<TcpStream> {
+    TcpStream::connect(addr).map_err(Into::into)
+}
+
+fn default_listen(addr: &SocketAddr) -> VsysResult<TcpListener> {
+    TcpListener::bind(addr).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_resolve_ip() {
+        let vtable = NetVTable::default();
+        let addrs = (vtable.resolve)("127.0.0.1").unwrap();
+        assert_eq!(addrs, vec![IpAddr::from([127, 0, 0, 1])]);
+    }
+
+    #[test]
+    fn test_default_listen_connect() {
+        let vtable = NetVTable::default();
+        let listener = (vtable.listen)(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let unreachable = "127.0.0.1:1".parse().unwrap();
+        assert!(vtable.connect_any(&[unreachable, addr]).is_ok());
+    }
+
+    #[test]
+    fn test_deny_all() {
+        let vtable = NetVTable::deny_all();
+        assert!(matches!(
+            (vtable.resolve)("localhost"),
+            Err(VsysError::PermissionDenied(_))
+        ));
+        assert!(vtable.connect_any(&[]).is_err());
+        let query = DnsQuery {
+            name: "localhost",
+            record_type: RecordType::A,
+            servers: &[],
+            timeout: None,
+            tries: None,
+        };
+        assert!(matches!(
+            (vtable.query)(&query),
+            Err(VsysError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_record_type_names() {
+        for name in ["A", "AAAA", "CNAME", "MX", "NS", "PTR", "SOA", "SRV", "TXT"] {
+            assert_eq!(RecordType::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(RecordType::from_name("ANY"), None);
+    }
+}
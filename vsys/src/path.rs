@@ -0,0 +1,676 @@
+//! Path strings with the semantics of Node's `path` module
+//!
+//! Paths are handled in the [`PathStyle`] asked for whatever the host is, which
+//! is what `path.posix` and `path.win32` need. Win32 paths take both `\` and `/`
+//! as separators and may start with a drive (`C:`, `C:\`) or a UNC root
+//! (`\\server\share\`). Functions resolving relative paths take the working
+//! directory to resolve against instead of reading it themselves.
+
+/// The flavor of paths to handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    Posix,
+    Win32,
+}
+
+/// The parts of a path, as `path.parse()` returns them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedPath {
+    pub root: String,
+    pub dir: String,
+    pub base: String,
+    pub ext: String,
+    pub name: String,
+}
+
+/// Where the root of a path ends: the device (drive or UNC share) comes first,
+/// then the separator making the path absolute
+struct Root {
+    device: usize,
+    end: usize,
+    absolute: bool,
+}
+
+impl PathStyle {
+    /// The style of the host
+    pub const NATIVE: Self = if cfg!(windows) {
+        Self::Win32
+    } else {
+        Self::Posix
+    };
+
+    pub fn sep(self) -> char {
+        match self {
+            Self::Posix => '/',
+            Self::Win32 => '\\',
+        }
+    }
+
+    fn sep_str(self) -> &'static str {
+        match self {
+            Self::Posix => "/",
+            Self::Win32 => "\\",
+        }
+    }
+
+    /// The separator of the entries of `PATH`
+    pub fn delimiter(self) -> char {
+        match self {
+            Self::Posix => ':',
+            Self::Win32 => ';',
+        }
+    }
+
+    pub fn is_sep(self, c: char) -> bool {
+        c == '/' || (self == Self::Win32 && c == '\\')
+    }
+
+    fn is_sep_at(self, path: &str, i: usize) -> bool {
+        path.as_bytes()
+            .get(i)
+            .is_some_and(|&b| self.is_sep(b as char))
+    }
+
+    fn parse_root(self, path: &str) -> Root {
+        let len = path.len();
+        if self == Self::Posix {
+            let absolute = self.is_sep_at(path, 0);
+            return Root {
+                device: 0,
+                end: absolute as usize,
+                absolute,
+            };
+        }
+        if self.is_sep_at(path, 0) {
+            // `\\server\share`, where neither part may be empty
+            if self.is_sep_at(path, 1) {
+                let server_end = (2..len).find(|&i| self.is_sep_at(path, i));
+                if let Some(server_end) = server_end.filter(|&i| i > 2) {
+                    let share_start = (server_end..len).find(|&i| !self.is_sep_at(path, i));
+                    if let Some(share_start) = share_start {
+                        let share_end = (share_start..len)
+                            .find(|&i| self.is_sep_at(path, i))
+                            .unwrap_or(len);
+                        return Root {
+                            device: share_end,
+                            end: (share_end + 1).min(len),
+                            absolute: true,
+                        };
+                    }
+                }
+            }
+            return Root {
+                device: 0,
+                end: 1,
+                absolute: true,
+            };
+        }
+        let bytes = path.as_bytes();
+        if len >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            let absolute = self.is_sep_at(path, 2);
+            return Root {
+                device: 2,
+                end: 2 + absolute as usize,
+                absolute,
+            };
+        }
+        Root {
+            device: 0,
+            end: 0,
+            absolute: false,
+        }
+    }
+
+    /// The root of `path` as written: `/`, or a drive or UNC root with win32
+    pub fn root(self, path: &str) -> &str {
+        &path[..self.parse_root(path).end]
+    }
+
+    /// The drive (`C:`) or UNC share (`\\server\share`) of `path`, empty without
+    pub fn device(self, path: &str) -> String {
+        path[..self.parse_root(path).device].replace('/', "\\")
+    }
+
+    /// The segments of `path` after its root, empty ones skipped
+    pub fn segments(self, path: &str) -> impl Iterator<Item = &str> {
+        path[self.parse_root(path).end..]
+            .split(move |c| self.is_sep(c))
+            .filter(|segment| !segment.is_empty())
+    }
+
+    pub fn is_absolute(self, path: &str) -> bool {
+        self.parse_root(path).absolute
+    }
+
+    /// Resolves the `.` and `..` segments of a path without root, keeping the
+    /// `..` going above it when `allow_above_root`
+    fn normalize_segments(self, path: &str, allow_above_root: bool) -> String {
+        let mut segments: Vec<&str> = Vec::new();
+        for segment in path.split(|c| self.is_sep(c)) {
+            match segment {
+                "" | "." => {}
+                ".." if segments.last().is_some_and(|last| *last != "..") => {
+                    segments.pop();
+                }
+                ".." if !allow_above_root => {}
+                segment => segments.push(segment),
+            }
+        }
+        segments.join(self.sep_str())
+    }
+
+    pub fn normalize(self, path: &str) -> String {
+        if path.is_empty() {
+            return ".".into();
+        }
+        let root = self.parse_root(path);
+        let mut tail = self.normalize_segments(&path[root.end..], !root.absolute);
+        if tail.is_empty() && !root.absolute {
+            tail.push('.');
+        }
+        if !tail.is_empty() && path.ends_with(|c| self.is_sep(c)) {
+            tail.push(self.sep());
+        }
+        let device = self.device(path);
+        if root.absolute {
+            format!("{device}{}{tail}", self.sep())
+        } else {
+            format!("{device}{tail}")
+        }
+    }
+
+    pub fn join<S: AsRef<str>>(self, parts: &[S]) -> String {
+        let parts: Vec<&str> = parts
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|part| !part.is_empty())
+            .collect();
+        let Some(first) = parts.first() else {
+            return ".".into();
+        };
+        let mut joined = parts.join(self.sep_str());
+        if self == Self::Win32 {
+            // only a first part starting like a UNC root makes one, not separators
+            // coming from several parts
+            let leading = |path: &str| path.chars().take_while(|&c| self.is_sep(c)).count();
+            let unc = first.len() > 2 && leading(first) == 2;
+            let count = leading(&joined);
+            if !unc && count >= 2 {
+                joined = format!("\\{}", &joined[count..]);
+            }
+        }
+        self.normalize(&joined)
+    }
+
+    /// Resolves `parts` from right to left into an absolute path, starting from `cwd`
+    pub fn resolve<S: AsRef<str>>(self, cwd: &str, parts: &[S]) -> String {
+        match self {
+            Self::Posix => self.resolve_posix(cwd, parts),
+            Self::Win32 => self.resolve_win32(cwd, parts),
+        }
+    }
+
+    fn resolve_posix<S: AsRef<str>>(self, cwd: &str, parts: &[S]) -> String {
+        let mut resolved = String::new();
+        let mut absolute = false;
+        for path in parts.iter().map(AsRef::as_ref).rev().chain([cwd]) {
+            if path.is_empty() {
+                continue;
+            }
+            resolved = format!("{path}/{resolved}");
+            absolute = path.starts_with('/');
+            if absolute {
+                break;
+            }
+        }
+        let resolved = self.normalize_segments(&resolved, !absolute);
+        if absolute {
+            format!("/{resolved}")
+        } else if resolved.is_empty() {
+            ".".into()
+        } else {
+            resolved
+        }
+    }
+
+    fn resolve_win32<S: AsRef<str>>(self, cwd: &str, parts: &[S]) -> String {
+        let mut device = String::new();
+        let mut tail = String::new();
+        let mut absolute = false;
+        let paths = parts.iter().map(|part| Some(part.as_ref())).rev();
+        for path in paths.chain([None]) {
+            let path = match path {
+                Some(path) => path.to_string(),
+                // the root of the drive resolved so far when the cwd is on another one
+                None if !device.is_empty()
+                    && !cwd
+                        .get(..2)
+                        .is_some_and(|drive| drive.eq_ignore_ascii_case(&device))
+                    && cwd.as_bytes().get(2) == Some(&b'\\') =>
+                {
+                    format!("{device}\\")
+                }
+                None => cwd.to_string(),
+            };
+            if path.is_empty() {
+                continue;
+            }
+            let root = self.parse_root(&path);
+            let path_device = self.device(&path);
+            if !path_device.is_empty() {
+                if device.is_empty() {
+                    device = path_device;
+                } else if !path_device.eq_ignore_ascii_case(&device) {
+                    // a path on another drive
+                    continue;
+                }
+            }
+            if absolute {
+                if !device.is_empty() {
+                    break;
+                }
+            } else {
+                tail = format!("{}\\{tail}", &path[root.end..]);
+                absolute = root.absolute;
+                if absolute && !device.is_empty() {
+                    break;
+                }
+            }
+        }
+        let tail = self.normalize_segments(&tail, !absolute);
+        if absolute {
+            format!("{device}\\{tail}")
+        } else if device.is_empty() && tail.is_empty() {
+            ".".into()
+        } else {
+            format!("{device}{tail}")
+        }
+    }
+
+    /// The path from `from` to `to`, both resolved from `cwd`
+    pub fn relative(self, cwd: &str, from: &str, to: &str) -> String {
+        if from == to {
+            return String::new();
+        }
+        let from = self.resolve(cwd, &[from]);
+        let to = self.resolve(cwd, &[to]);
+        match self {
+            Self::Posix => relative_posix(&from, &to),
+            Self::Win32 => relative_win32(&from, &to),
+        }
+    }
+
+    pub fn dirname(self, path: &str) -> String {
+        if path.is_empty() {
+            return ".".into();
+        }
+        let root = self.parse_root(path);
+        let mut end = None;
+        let mut matched_sep = true;
+        for i in (root.end.max(1)..path.len()).rev() {
+            if self.is_sep_at(path, i) {
+                if !matched_sep {
+                    end = Some(i);
+                    break;
+                }
+            } else {
+                matched_sep = false;
+            }
+        }
+        match end {
+            None if root.end == 0 => ".".into(),
+            None => path[..root.end].to_string(),
+            // `//a` keeps its double separator
+            Some(1) if self == Self::Posix && root.absolute => "//".into(),
+            Some(end) => path[..end].to_string(),
+        }
+    }
+
+    /// The last segment of `path`, without `suffix` when it ends with it
+    pub fn basename(self, path: &str, suffix: Option<&str>) -> String {
+        let start = match self.parse_root(path) {
+            Root { device: 2, .. } if self == Self::Win32 => 2,
+            _ => 0,
+        };
+        let mut end = path.len();
+        while end > start && self.is_sep_at(path, end - 1) {
+            end -= 1;
+        }
+        let base_start = (start..end)
+            .rev()
+            .find(|&i| self.is_sep_at(path, i))
+            .map_or(start, |i| i + 1);
+        let base = &path[base_start..end];
+        match suffix {
+            Some(suffix) if base != suffix => base.strip_suffix(suffix).unwrap_or(base),
+            _ => base,
+        }
+        .to_string()
+    }
+
+    pub fn extname(self, path: &str) -> String {
+        split_ext(&self.basename(path, None)).1.to_string()
+    }
+
+    pub fn parse(self, path: &str) -> ParsedPath {
+        let root = self.parse_root(path);
+        let mut parsed = ParsedPath {
+            root: path[..root.end].to_string(),
+            ..Default::default()
+        };
+        let mut end = path.len();
+        while end > root.end && self.is_sep_at(path, end - 1) {
+            end -= 1;
+        }
+        let base_start = match (root.end..end).rev().find(|&i| self.is_sep_at(path, i)) {
+            Some(sep) => {
+                parsed.dir = path[..sep].to_string();
+                sep + 1
+            }
+            None => {
+                parsed.dir = parsed.root.clone();
+                root.end
+            }
+        };
+        let base = &path[base_start..end];
+        let (name, ext) = split_ext(base);
+        parsed.name = name.to_string();
+        parsed.ext = ext.to_string();
+        parsed.base = base.to_string();
+        parsed
+    }
+
+    /// The path of `parsed`, `dir` or `root` followed by `base` or `name` and `ext`
+    pub fn format(self, parsed: &ParsedPath) -> String {
+        let dir = if parsed.dir.is_empty() {
+            &parsed.root
+        } else {
+            &parsed.dir
+        };
+        let base = if parsed.base.is_empty() {
+            let dot = if parsed.ext.is_empty() || parsed.ext.starts_with('.') {
+                ""
+            } else {
+                "."
+            };
+            format!("{}{dot}{}", parsed.name, parsed.ext)
+        } else {
+            parsed.base.clone()
+        };
+        if dir.is_empty() {
+            base
+        } else if *dir == parsed.root {
+            format!("{dir}{base}")
+        } else {
+            format!("{dir}{}{base}", self.sep())
+        }
+    }
+
+    /// The `\\?\` form of an absolute win32 path, which lifts the length limit of
+    /// Windows APIs. Other paths are returned as they are.
+    pub fn to_namespaced_path(self, cwd: &str, path: &str) -> String {
+        if self == Self::Posix || path.is_empty() {
+            return path.to_string();
+        }
+        let resolved = self.resolve(cwd, &[path]);
+        let bytes = resolved.as_bytes();
+        if bytes.len() <= 2 {
+            return path.to_string();
+        }
+        if bytes[0] == b'\\' {
+            if bytes[1] == b'\\' && bytes[2] != b'?' && bytes[2] != b'.' {
+                return format!("\\\\?\\UNC\\{}", &resolved[2..]);
+            }
+        } else if bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+            return format!("\\\\?\\{resolved}");
+        }
+        path.to_string()
+    }
+}
+
+/// Splits the extension off a file name: from its last dot, unless that is the
+/// first character
+fn split_ext(base: &str) -> (&str, &str) {
+    match base.rfind('.') {
+        _ if base == ".." => (base, ""),
+        None | Some(0) => (base, ""),
+        Some(dot) => base.split_at(dot),
+    }
+}
+
+fn relative_posix(from: &str, to: &str) -> String {
+    if from == to {
+        return String::new();
+    }
+    // both are absolute, compare what follows the root
+    let (from_rest, to_rest) = (&from.as_bytes()[1..], &to.as_bytes()[1..]);
+    let length = from_rest.len().min(to_rest.len());
+    let mut last_common_sep = None;
+    let mut i = 0;
+    while i < length && from_rest[i] == to_rest[i] {
+        if from_rest[i] == b'/' {
+            last_common_sep = Some(i);
+        }
+        i += 1;
+    }
+    if i == length {
+        if to_rest.len() > length {
+            // `from` is an ancestor of `to`
+            if to_rest[i] == b'/' {
+                return to[1 + i + 1..].to_string();
+            }
+            if i == 0 {
+                return to[1..].to_string();
+            }
+        } else if from_rest.len() > length {
+            // `to` is an ancestor of `from`
+            if from_rest[i] == b'/' || i == 0 {
+                last_common_sep = Some(i);
+            }
+        }
+    }
+    // a `..` for each segment of `from` after the common part
+    let rest = &from_rest[last_common_sep.map_or(0, |sep| sep + 1)..];
+    let out = vec![".."; rest.iter().filter(|&&b| b == b'/').count() + 1].join("/");
+    // what is left of `to` starts with a separator, the one after the common part
+    format!("{out}{}", &to[last_common_sep.map_or(0, |sep| sep + 1)..])
+}
+
+fn relative_win32(from_orig: &str, to_orig: &str) -> String {
+    // paths only differing in case are the same on Windows
+    let from = from_orig.to_ascii_lowercase();
+    let to = to_orig.to_ascii_lowercase();
+    if from == to {
+        return String::new();
+    }
+    let trim = |path: &str| {
+        let bytes = path.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() && bytes[start] == b'\\' {
+            start += 1;
+        }
+        let mut end = bytes.len();
+        while end > start + 1 && bytes[end - 1] == b'\\' {
+            end -= 1;
+        }
+        (start, end)
+    };
+    let (from_start, from_end) = trim(&from);
+    let (to_start, to_end) = trim(&to);
+    let (from, to) = (from.as_bytes(), to.as_bytes());
+    let from_len = from_end - from_start;
+    let to_len = to_end - to_start;
+    let length = from_len.min(to_len);
+    let mut last_common_sep = None;
+    let mut i = 0;
+    while i < length && from[from_start + i] == to[to_start + i] {
+        if from[from_start + i] == b'\\' {
+            last_common_sep = Some(i);
+        }
+        i += 1;
+    }
+    if i != length {
+        // paths on different drives have no relative path between them
+        if last_common_sep.is_none() {
+            return to_orig.to_string();
+        }
+    } else if to_len > length {
+        if to[to_start + i] == b'\\' {
+            return to_orig[to_start + i + 1..to_end].to_string();
+        }
+        // `C:\` is the root of `C:\foo`
+        if i == 2 {
+            return to_orig[to_start + i..to_end].to_string();
+        }
+    } else if from_len > length {
+        if from[from_start + i] == b'\\' {
+            last_common_sep = Some(i);
+        } else if i == 2 {
+            last_common_sep = Some(3);
+        }
+    }
+    let last_common_sep = last_common_sep.unwrap_or(0);
+    let rest = from.get(from_start + last_common_sep + 1..from_end);
+    let out = match rest {
+        Some(rest) => vec![".."; rest.iter().filter(|&&b| b == b'\\').count() + 1].join("\\"),
+        None => String::new(),
+    };
+    let mut to_start = to_start + last_common_sep;
+    if out.is_empty() && to.get(to_start) == Some(&b'\\') {
+        to_start += 1;
+    }
+    format!("{out}{}", &to_orig[to_start.min(to_end)..to_end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathStyle::{Posix, Win32};
+    use super::*;
+
+    #[test]
+    fn test_posix() {
+        assert_eq!(
+            Posix.normalize("/foo/bar//baz/asdf/quux/.."),
+            "/foo/bar/baz/asdf"
+        );
+        assert_eq!(Posix.normalize("./a/"), "a/");
+        assert_eq!(Posix.normalize("../../a"), "../../a");
+        assert_eq!(
+            Posix.join(&["/foo", "bar", "baz/asdf", "quux", ".."]),
+            "/foo/bar/baz/asdf"
+        );
+        assert_eq!(Posix.join(&["", ""]), ".");
+        assert_eq!(
+            Posix.resolve("/cwd", &["/foo/bar", "./baz"]),
+            "/foo/bar/baz"
+        );
+        assert_eq!(Posix.resolve("/cwd", &["a", "../b"]), "/cwd/b");
+        assert_eq!(
+            Posix.relative("/", "/data/orandea/test/aaa", "/data/orandea/impl/bbb"),
+            "../../impl/bbb"
+        );
+        assert_eq!(Posix.relative("/", "/a/b", "/a/b/c/d"), "c/d");
+        assert_eq!(Posix.relative("/", "/a/b/c", "/a"), "../..");
+        assert_eq!(Posix.relative("/", "/a", "/b"), "../b");
+        assert_eq!(Posix.dirname("/a/b/"), "/a");
+        assert_eq!(Posix.dirname("a"), ".");
+        assert_eq!(Posix.dirname("/"), "/");
+        assert_eq!(Posix.basename("/a/b.html", Some(".html")), "b");
+        assert_eq!(Posix.basename("/a/b/", None), "b");
+        assert_eq!(Posix.extname("index.coffee.md"), ".md");
+        assert_eq!(Posix.extname(".index"), "");
+        assert_eq!(Posix.extname("index."), ".");
+        assert_eq!(Posix.to_namespaced_path("/", "a\\b"), "a\\b");
+    }
+
+    #[test]
+    fn test_win32() {
+        assert!(Win32.is_absolute("C:/foo"));
+        assert!(Win32.is_absolute("\\\\server\\share"));
+        assert!(!Win32.is_absolute("C:foo"));
+        assert_eq!(
+            Win32.normalize("C:\\temp\\\\foo\\bar\\..\\"),
+            "C:\\temp\\foo\\"
+        );
+        assert_eq!(
+            Win32.normalize("C:////temp\\\\/\\/\\/foo/bar"),
+            "C:\\temp\\foo\\bar"
+        );
+        assert_eq!(
+            Win32.normalize("//server/share/a/../b"),
+            "\\\\server\\share\\b"
+        );
+        assert_eq!(Win32.normalize("C:"), "C:.");
+        assert_eq!(Win32.join(&["//server", "share"]), "\\\\server\\share\\");
+        assert_eq!(Win32.join(&["/", "/a"]), "\\a");
+        assert_eq!(
+            Win32.resolve("C:\\cwd", &["foo", "C:\\bar", "baz"]),
+            "C:\\bar\\baz"
+        );
+        assert_eq!(Win32.resolve("C:\\cwd", &["a"]), "C:\\cwd\\a");
+        assert_eq!(Win32.resolve("C:\\cwd", &["D:a"]), "D:\\a");
+        assert_eq!(
+            Win32.resolve("C:\\cwd", &["\\\\server\\share", "x"]),
+            "\\\\server\\share\\x"
+        );
+        assert_eq!(
+            Win32.relative("C:\\", "C:\\orandea\\test\\aaa", "C:\\orandea\\impl\\bbb"),
+            "..\\..\\impl\\bbb"
+        );
+        assert_eq!(Win32.relative("C:\\", "C:\\Foo", "c:\\foo"), "");
+        assert_eq!(Win32.relative("C:\\", "C:\\", "C:\\foo"), "foo");
+        assert_eq!(Win32.relative("C:\\", "C:\\foo\\bar", "C:\\foo"), "..");
+        assert_eq!(Win32.relative("C:\\", "C:\\a", "D:\\b"), "D:\\b");
+        assert_eq!(Win32.dirname("C:\\a\\b"), "C:\\a");
+        assert_eq!(Win32.dirname("C:\\a"), "C:\\");
+        assert_eq!(Win32.dirname("\\\\server\\share\\a"), "\\\\server\\share\\");
+        assert_eq!(Win32.basename("C:foo.html", Some(".html")), "foo");
+        assert_eq!(Win32.basename("C:\\", None), "");
+        assert_eq!(Win32.device("//server/share/a"), "\\\\server\\share");
+        assert_eq!(
+            Win32.segments("C:\\a/b\\\\c").collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+        assert_eq!(Win32.to_namespaced_path("C:\\", "C:\\a"), "\\\\?\\C:\\a");
+        assert_eq!(
+            Win32.to_namespaced_path("C:\\", "\\\\server\\share\\a"),
+            "\\\\?\\UNC\\server\\share\\a"
+        );
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let parsed = Posix.parse("/home/user/dir/file.txt");
+        assert_eq!(
+            parsed,
+            ParsedPath {
+                root: "/".into(),
+                dir: "/home/user/dir".into(),
+                base: "file.txt".into(),
+                ext: ".txt".into(),
+                name: "file".into(),
+            }
+        );
+        assert_eq!(Posix.format(&parsed), "/home/user/dir/file.txt");
+        assert_eq!(Posix.parse("/").dir, "/");
+        assert_eq!(Posix.parse("file").dir, "");
+
+        let parsed = Win32.parse("C:\\path\\dir\\file.txt");
+        assert_eq!(parsed.root, "C:\\");
+        assert_eq!(parsed.dir, "C:\\path\\dir");
+        assert_eq!(parsed.name, "file");
+        assert_eq!(Win32.format(&parsed), "C:\\path\\dir\\file.txt");
+        assert_eq!(
+            Win32.parse("\\\\server\\share\\a").root,
+            "\\\\server\\share\\"
+        );
+
+        let parsed = ParsedPath {
+            root: "/".into(),
+            name: "file".into(),
+            ext: "txt".into(),
+            ..Default::default()
+        };
+        assert_eq!(Posix.format(&parsed), "/file.txt");
+    }
+}
@@ -3,10 +3,18 @@
 //! This module provides fine-grained permission control for filesystem,
 //! network, and environment access.
 
-use std::path::{Path, PathBuf};
+use std::fmt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
 
 /// Black or white list for permission checking
-#[derive(Debug, PartialEq, Clone, Hash, Eq)]
+///
+/// Serialized as `{ "blacklist": [...] }` or `{ "whitelist": [...] }`.
+#[derive(Debug, PartialEq, Clone, Hash, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BlackOrWhiteList {
     /// Allow all except items in the list
     BlackList(Vec<String>),
@@ -44,9 +52,8 @@ impl BlackOrWhiteList {
 
     /// Check if a path is allowed
     pub fn check_path(&self, path: &Path) -> bool {
-        let canonical_path = match path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => return false,
+        let Some(canonical_path) = resolve_path(path) else {
+            return false;
         };
 
         let (is_whitelist, items) = match self {
@@ -61,12 +68,12 @@ impl BlackOrWhiteList {
         for item in items {
             if item.ends_with('*') {
                 let pattern = &item[..item.len() - 1];
-                if let Ok(p) = Path::new(pattern).canonicalize() {
+                if let Some(p) = resolve_path(Path::new(pattern)) {
                     pattern_paths.push(p);
                 } else {
                     pattern_paths.push(PathBuf::from(pattern));
                 }
-            } else if let Ok(p) = Path::new(item).canonicalize() {
+            } else if let Some(p) = resolve_path(Path::new(item)) {
                 normal_paths.push(p);
             } else {
                 normal_paths.push(PathBuf::from(item));
@@ -115,29 +122,83 @@ impl BlackOrWhiteList {
     }
 }
 
+/// Resolves `path` like `canonicalize`, also when it doesn't exist yet: its nearest existing
+/// ancestor is canonicalized and the rest appended, so a file about to be created in an
+/// allowed directory is allowed too
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    let path = std::env::current_dir().ok()?.join(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    let mut resolved = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
+        }
+        missing.push(existing.components().next_back()?);
+        existing = existing.parent()?;
+    };
+    // Missing directories can't be symlinks, so `..` below them is resolved lexically
+    for component in missing.into_iter().rev() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => resolved.push(name),
+            _ => {}
+        }
+    }
+    Some(resolved)
+}
+
 /// Struct representing permissions for filesystem, network, and environment access.
 ///
 /// **WARNING**: by default, no permissions are granted (all whitelists are empty).
-#[derive(Debug, Clone, Default)]
+/// Fields missing from a serialized config keep that default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Permissions {
-    /// Filesystem access permissions
-    pub fs: BlackOrWhiteList,
+    /// Filesystem read permissions (reading files, listing directories, stat)
+    pub fs_read: BlackOrWhiteList,
+    /// Filesystem write permissions (creating, modifying and removing entries)
+    pub fs_write: BlackOrWhiteList,
     /// Network access permissions
     pub net: BlackOrWhiteList,
     /// Environment variable access permissions
     pub env: BlackOrWhiteList,
     /// Standard I/O (console) access
     pub stdio: bool,
+    /// Native library loading and raw memory access (FFI)
+    pub ffi: bool,
+    /// Running shell commands and subprocesses
+    pub run: bool,
+    /// Narrower permissions for specific modules, the first matching one applies
+    pub modules: Vec<ModuleScope>,
+}
+
+/// Permissions of the modules whose path contains the components of `pattern`
+///
+/// They narrow the global permissions: a capability is only granted to a
+/// matching module when both allow it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleScope {
+    /// Whole components of the module path, e.g. `node_modules/left-pad`
+    pub pattern: String,
+    /// Permissions of the matching modules
+    pub permissions: Permissions,
 }
 
 impl Permissions {
     /// Create permissions that allow everything
     pub fn allow_all() -> Self {
         Self {
-            fs: BlackOrWhiteList::allow_all(),
+            fs_read: BlackOrWhiteList::allow_all(),
+            fs_write: BlackOrWhiteList::allow_all(),
             net: BlackOrWhiteList::allow_all(),
             env: BlackOrWhiteList::allow_all(),
             stdio: true,
+            ffi: true,
+            run: true,
+            modules: Vec::new(),
         }
     }
 
@@ -146,9 +207,51 @@ impl Permissions {
         Self::default()
     }
 
-    /// Check if filesystem access to path is allowed
+    /// Parse permissions from JSON, e.g. `{"fs_read": {"whitelist": ["./*"]}, "stdio": true}`
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Serialize permissions to pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("permissions are always serializable")
+    }
+
+    /// Check if reading path is allowed
+    pub fn check_fs_read(&self, path: &Path) -> bool {
+        self.fs_read.check_path(path)
+    }
+
+    /// Check if writing path is allowed
+    pub fn check_fs_write(&self, path: &Path) -> bool {
+        self.fs_write.check_path(path)
+    }
+
+    /// Check if `capability` is allowed
+    pub fn allows(&self, capability: &Capability) -> bool {
+        match capability {
+            Capability::FsRead(path) => self.check_fs_read(path),
+            Capability::FsWrite(path) => self.check_fs_write(path),
+            Capability::Net(host) => self.check_net(host),
+            Capability::Env(name) => self.check_env(name),
+        }
+    }
+
+    /// The scope applying to `module`, if any
+    pub fn for_module(&self, module: &str) -> Option<&Permissions> {
+        let module: Vec<&str> = path_components(module).collect();
+        self.modules
+            .iter()
+            .find(|scope| {
+                let pattern: Vec<&str> = path_components(&scope.pattern).collect();
+                !pattern.is_empty() && module.windows(pattern.len()).any(|x| x == pattern)
+            })
+            .map(|scope| &scope.permissions)
+    }
+
+    /// Check if both reading and writing path are allowed
     pub fn check_fs(&self, path: &Path) -> bool {
-        self.fs.check_path(path)
+        self.check_fs_read(path) && self.check_fs_write(path)
     }
 
     /// Check if network access to host is allowed
@@ -173,6 +276,58 @@ impl Permissions {
     }
 }
 
+/// The components of a module path or URL, split on both separators
+fn path_components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '\\']).filter(|x| !x.is_empty())
+}
+
+/// A capability a script needs, as passed to a [`PermissionPrompt`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Read access to a path
+    FsRead(PathBuf),
+    /// Write access to a path
+    FsWrite(PathBuf),
+    /// Network access to a host
+    Net(String),
+    /// Access to an environment variable
+    Env(String),
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::FsRead(path) => write!(f, "read access to \"{}\"", path.display()),
+            Capability::FsWrite(path) => write!(f, "write access to \"{}\"", path.display()),
+            Capability::Net(host) => write!(f, "network access to \"{}\"", host),
+            Capability::Env(name) => write!(f, "environment variable \"{}\"", name),
+        }
+    }
+}
+
+/// Asks whether a capability denied by [`Permissions`] should be granted
+///
+/// It is called once per capability; the answer is cached for the session.
+pub type PermissionPrompt = Arc<dyn Fn(&Capability) -> bool + Send + Sync>;
+
+/// A [`PermissionPrompt`] asking on the terminal, denying when stdin is not one
+pub fn tty_prompt(capability: &Capability) -> bool {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return false;
+    }
+
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(stderr, "Allow {}? [y/N] ", capability);
+    let _ = stderr.flush();
+
+    let mut answer = String::new();
+    if stdin.lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +336,8 @@ mod tests {
     fn test_allow_all_permissions() {
         let perm = Permissions::allow_all();
         assert!(perm.stdio);
+        assert!(perm.ffi);
+        assert!(perm.run);
         assert!(perm.check_net("example.com"));
         assert!(perm.check_env("PATH"));
     }
@@ -189,6 +346,8 @@ mod tests {
     fn test_deny_all_permissions() {
         let perm = Permissions::deny_all();
         assert!(!perm.stdio);
+        assert!(!perm.ffi);
+        assert!(!perm.run);
         assert!(!perm.check_net("example.com"));
         assert!(!perm.check_env("PATH"));
     }
@@ -223,4 +382,93 @@ mod tests {
         assert!(perm.check_net("example.com"));
         assert!(!perm.check_net("other.com"));
     }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let perm = Permissions::from_json(
+            r#"{"fs_read": {"whitelist": ["./*"]}, "net": {"blacklist": []}, "stdio": true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            perm.fs_read,
+            BlackOrWhiteList::whitelist(vec!["./*".to_string()])
+        );
+        assert_eq!(perm.fs_write, BlackOrWhiteList::deny_all());
+        assert_eq!(perm.net, BlackOrWhiteList::allow_all());
+        assert_eq!(perm.env, BlackOrWhiteList::deny_all());
+        assert!(perm.stdio);
+        assert!(!perm.ffi);
+        assert_eq!(Permissions::from_json(&perm.to_json()).unwrap(), perm);
+
+        assert!(Permissions::from_json(r#"{"fs_read": "all"}"#).is_err());
+        assert!(Permissions::from_json(r#"{"disk": {"whitelist": []}}"#).is_err());
+    }
+
+    #[test]
+    fn test_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let allowed = dir.path().join("allowed");
+        std::fs::create_dir(&allowed).unwrap();
+        let list = BlackOrWhiteList::whitelist(vec![format!("{}*", allowed.display())]);
+
+        assert!(list.check_path(&allowed.join("new.txt")));
+        assert!(list.check_path(&allowed.join("new/dir/file.txt")));
+        assert!(!list.check_path(&dir.path().join("new.txt")));
+        assert!(!list.check_path(&allowed.join("new/../../escape.txt")));
+
+        // a directory that doesn't exist yet covers what is created in it
+        let future =
+            BlackOrWhiteList::whitelist(vec![format!("{}*", allowed.join("out").display())]);
+        assert!(future.check_path(&allowed.join("out/bundle.js")));
+        assert!(!future.check_path(&allowed.join("outside.js")));
+
+        // an exact file is allowed before it exists
+        let file = allowed.join("log.txt");
+        let exact = BlackOrWhiteList::whitelist(vec![file.display().to_string()]);
+        assert!(exact.check_path(&file));
+        assert!(!exact.check_path(&allowed.join("other.txt")));
+    }
+
+    #[test]
+    fn test_read_only_fs() {
+        let dir = std::env::temp_dir();
+        let perm = Permissions {
+            fs_read: BlackOrWhiteList::allow_all(),
+            ..Default::default()
+        };
+        assert!(perm.check_fs_read(&dir));
+        assert!(!perm.check_fs_write(&dir));
+        assert!(!perm.check_fs(&dir));
+    }
+
+    #[test]
+    fn test_module_scopes() {
+        let perm = Permissions {
+            modules: vec![ModuleScope {
+                pattern: "node_modules/left-pad/".to_string(),
+                permissions: Permissions::deny_all(),
+            }],
+            ..Permissions::allow_all()
+        };
+        let scope = perm
+            .for_module("/app/node_modules/left-pad/index.js")
+            .unwrap();
+        assert!(!scope.allows(&Capability::Net("example.com".to_string())));
+        assert!(perm.for_module("/app/main.js").is_none());
+        assert!(perm
+            .for_module("C:\\app\\node_modules\\left-pad\\index.js")
+            .is_some());
+        assert!(perm
+            .for_module("/app/node_modules/left-pad-evil/index.js")
+            .is_none());
+        assert!(perm
+            .for_module("/app/vendor_node_modules/left-pad/index.js")
+            .is_none());
+        assert!(perm
+            .for_module("file:///app/node_modules/left-pad/lib/index.js")
+            .is_some());
+
+        let json = r#"{"modules": [{"pattern": "node_modules/x/", "permissions": {}}]}"#;
+        assert_eq!(Permissions::from_json(json).unwrap().modules.len(), 1);
+    }
 }
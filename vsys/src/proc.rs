@@ -0,0 +1,140 @@
+//! Process virtual table for vsys
+//!
+//! This module provides a pluggable subprocess abstraction layer. By default
+//! it spawns real processes (std::process), but embedders can replace it to
+//! intercept, deny or virtualize every command the runtime runs.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus};
+
+use crate::error::{VsysError, VsysResult};
+
+/// A command to spawn
+#[derive(Debug, Clone, Default)]
+pub struct ProcCommand {
+    /// Program to run, looked up in `PATH` when it is not a path
+    pub program: OsString,
+    /// Arguments passed to the program
+    pub args: Vec<OsString>,
+    /// Working directory, the current one when `None`
+    pub cwd: Option<PathBuf>,
+    /// Environment variables added to the inherited environment
+    pub env: Vec<(OsString, OsString)>,
+}
+
+impl ProcCommand {
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    /// A command running `script` with the platform shell
+    pub fn shell(script: &str) -> Self {
+        #[cfg(windows)]
+        let (program, flag) = ("cmd", "/C");
+        #[cfg(not(windows))]
+        let (program, flag) = ("sh", "-c");
+
+        Self::new(program).arg(flag).arg(script)
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// Process operations vtable
+///
+/// All functions are safe Rust function pointers. For C ABI compatibility,
+/// wrap these in extern "C" functions when needed.
+pub struct ProcVTable {
+    /// Spawn a child process
+    pub spawn: fn(command: &ProcCommand) -> VsysResult<Child>,
+    /// Kill a child process
+    pub kill: fn(child: &mut Child) -> VsysResult<()>,
+    /// Wait for a child process to exit
+    pub wait: fn(child: &mut Child) -> VsysResult<ExitStatus>,
+}
+
+impl Default for ProcVTable {
+    fn default() -> Self {
+        Self {
+            spawn: default_spawn,
+            kill: |child| child.kill().map_err(Into::into),
+            wait: |child| child.wait().map_err(Into::into),
+        }
+    }
+}
+
+impl ProcVTable {
+    /// Create a vtable that denies all operations
+    pub fn deny_all() -> Self {
+        Self {
+            spawn: |command| {
+                Err(VsysError::PermissionDenied(format!(
+                    "spawn denied: {}",
+                    command.program.to_string_lossy()
+                )))
+            },
+            kill: |_| Err(VsysError::PermissionDenied("kill denied".into())),
+            wait: |_| Err(VsysError::PermissionDenied("wait denied".into())),
+        }
+    }
+
+    /// Spawn `command` and wait for it to exit
+    pub fn run(&self, command: &ProcCommand) -> VsysResult<ExitStatus> {
+        let mut child = (self.spawn)(command)?;
+        (self.wait)(&mut child)
+    }
+}
+
+// Default implementations using std::process
+
+fn default_spawn(command: &ProcCommand) -> VsysResult<Child> {
+    let mut cmd = Command::new(&command.program);
+    cmd.args(&command.args)
+        .envs(command.env.iter().map(|(key, value)| (key, value)));
+    if let Some(cwd) = &command.cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd.spawn().map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_default_run() {
+        let vtable = ProcVTable::default();
+        assert!(vtable.run(&ProcCommand::shell("exit 0")).unwrap().success());
+        let status = vtable
+            .run(&ProcCommand::shell("exit $CODE").env("CODE", "3"))
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_deny_all() {
+        let vtable = ProcVTable::deny_all();
+        assert!(matches!(
+            vtable.run(&ProcCommand::new("true")),
+            Err(VsysError::PermissionDenied(_))
+        ));
+    }
+}
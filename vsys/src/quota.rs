@@ -0,0 +1,285 @@
+//! Filesystem quotas
+//!
+//! Limits on how much an untrusted script may write, enforced by a vtable
+//! wrapping another one, so they hold even when fs write permission is granted.
+//! Each limited vtable counts its own usage.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::error::{VsysError, VsysResult};
+use crate::fs::{FileStat, FsHandle, FsHandleOps, FsVTable, OpenOptions, SeekFrom};
+
+/// Filesystem limits, `None` meaning unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsQuota {
+    /// Total bytes that may be written by all operations together
+    pub max_bytes_written: Option<u64>,
+    /// Number of file handles that may be open at the same time
+    pub max_open_handles: Option<u64>,
+    /// Largest size a written file may grow to
+    pub max_file_size: Option<u64>,
+}
+
+impl Default for FsQuota {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+impl FsQuota {
+    pub const UNLIMITED: Self = Self {
+        max_bytes_written: None,
+        max_open_handles: None,
+        max_file_size: None,
+    };
+}
+
+/// A quota and how much of it the vtables enforcing it have used
+#[derive(Debug, Default)]
+pub struct FsUsage {
+    quota: FsQuota,
+    bytes_written: AtomicU64,
+    open_handles: AtomicU64,
+}
+
+impl FsUsage {
+    /// Start using `quota` with nothing written and no handle open
+    pub fn new(quota: FsQuota) -> Self {
+        Self {
+            quota,
+            ..Self::default()
+        }
+    }
+
+    /// The quota enforced
+    pub fn quota(&self) -> FsQuota {
+        self.quota
+    }
+
+    /// Bytes written so far
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::SeqCst)
+    }
+
+    /// File handles currently open
+    pub fn open_handles(&self) -> u64 {
+        self.open_handles.load(Ordering::SeqCst)
+    }
+
+    /// Accounts for writing `len` bytes that leave a file `new_size` bytes long.
+    fn reserve_write(&self, new_size: u64, len: u64) -> VsysResult<()> {
+        if let Some(max) = self.quota.max_file_size {
+            if new_size > max {
+                return Err(VsysError::QuotaExceeded(format!(
+                    "file size of {} bytes exceeds the maximum of {} bytes",
+                    new_size, max
+                )));
+            }
+        }
+
+        let Some(max) = self.quota.max_bytes_written else {
+            self.bytes_written.fetch_add(len, Ordering::SeqCst);
+            return Ok(());
+        };
+        self.bytes_written
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |written| {
+                written.checked_add(len).filter(|&written| written <= max)
+            })
+            .map(|_| ())
+            .map_err(|written| {
+                VsysError::QuotaExceeded(format!(
+                    "writing {} bytes exceeds the budget of {} bytes ({} already written)",
+                    len, max, written
+                ))
+            })
+    }
+
+    /// Counts a handle as open until the returned guard is dropped
+    fn acquire_handle(self: &Arc<Self>) -> VsysResult<HandleGuard> {
+        let max = self.quota.max_open_handles.unwrap_or(u64::MAX);
+        self.open_handles
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < max).then_some(open + 1)
+            })
+            .map(|_| HandleGuard(Arc::clone(self)))
+            .map_err(|open| {
+                VsysError::QuotaExceeded(format!(
+                    "{} file handles are open, the maximum is {}",
+                    open, max
+                ))
+            })
+    }
+}
+
+impl FsVTable {
+    /// Create a default vtable enforcing `quota`
+    pub fn with_quota(quota: FsQuota) -> Self {
+        Self::default().limited(Arc::new(FsUsage::new(quota)))
+    }
+
+    /// Wrap this vtable to enforce the quota of `usage`, counting against it
+    ///
+    /// Vtables sharing `usage` share its budget.
+    pub fn limited(self, usage: Arc<FsUsage>) -> Self {
+        let state = Arc::new((self.clone(), usage));
+        Self {
+            write: with_state!(state, |path: &Path, data: &[u8]| {
+                let (inner, usage) = &*state;
+                let len = data.len() as u64;
+                usage.reserve_write(len, len)?;
+                (inner.write)(path, data)
+            }),
+            append: with_state!(state, |path: &Path, data: &[u8]| {
+                let (inner, usage) = &*state;
+                let size = (inner.stat)(path).map(|stat| stat.size).unwrap_or(0);
+                let len = data.len() as u64;
+                usage.reserve_write(size + len, len)?;
+                (inner.append)(path, data)
+            }),
+            copy: with_state!(state, |from: &Path, to: &Path| {
+                let (inner, usage) = &*state;
+                let len = (inner.stat)(from)?.size;
+                usage.reserve_write(len, len)?;
+                (inner.copy)(from, to)
+            }),
+            truncate: with_state!(state, |path: &Path, size: u64| {
+                let (inner, usage) = &*state;
+                usage.reserve_write(size, 0)?;
+                (inner.truncate)(path, size)
+            }),
+            open: with_state!(state, |path: &Path, options: &OpenOptions| {
+                let (inner, usage) = &*state;
+                let guard = usage.acquire_handle()?;
+                let handle = (inner.open)(path, options)?;
+                Ok(FsHandle::new(LimitedHandle {
+                    inner: handle,
+                    append: options.append,
+                    guard,
+                }))
+            }),
+            ..self
+        }
+    }
+}
+
+/// An open file handle, counted against `max_open_handles` until dropped
+struct HandleGuard(Arc<FsUsage>);
+
+impl Drop for HandleGuard {
+    fn drop(&mut self) {
+        self.0.open_handles.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A file handle of a limited vtable, accounting for what is written through it
+struct LimitedHandle {
+    inner: FsHandle,
+    append: bool,
+    guard: HandleGuard,
+}
+
+impl FsHandleOps for LimitedHandle {
+    fn read(&mut self, buf: &mut [u8]) -> VsysResult<usize> {
+        self.inner.read(buf)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> VsysResult<usize> {
+        let size = self.inner.stat()?.size;
+        let len = buf.len() as u64;
+        let new_size = if self.append {
+            size + len
+        } else {
+            size.max(self.inner.seek(SeekFrom::Current(0))? + len)
+        };
+        self.guard.0.reserve_write(new_size, len)?;
+        self.inner.write(buf)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> VsysResult<u64> {
+        self.inner.seek(pos)
+    }
+
+    fn sync_all(&self) -> VsysResult<()> {
+        self.inner.sync_all()
+    }
+
+    fn sync_data(&self) -> VsysResult<()> {
+        self.inner.sync_data()
+    }
+
+    fn stat(&self) -> VsysResult<FileStat> {
+        self.inner.stat()
+    }
+
+    fn set_len(&self, size: u64) -> VsysResult<()> {
+        self.guard.0.reserve_write(size, 0)?;
+        self.inner.set_len(size)
+    }
+
+    fn set_permissions(&self, readonly: bool) -> VsysResult<()> {
+        self.inner.set_permissions(readonly)
+    }
+
+    fn set_mode(&self, mode: u32) -> VsysResult<()> {
+        self.inner.set_mode(mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fs_quota() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let usage = Arc::new(FsUsage::new(FsQuota {
+            max_bytes_written: Some(16),
+            max_open_handles: Some(1),
+            max_file_size: Some(8),
+        }));
+        let vtable = FsVTable::default().limited(Arc::clone(&usage));
+
+        (vtable.write)(&file, b"12345678").unwrap();
+        assert!(matches!(
+            (vtable.write)(&file, b"123456789"),
+            Err(VsysError::QuotaExceeded(_))
+        ));
+        assert!((vtable.append)(&file, b"9").is_err());
+        assert!((vtable.truncate)(&file, 9).is_err());
+
+        let options = OpenOptions::new().write(true);
+        let mut handle = (vtable.open)(&file, &options).unwrap();
+        assert!((vtable.open)(&file, &options).is_err());
+        assert_eq!(handle.write(b"abcd").unwrap(), 4);
+        assert_eq!(usage.bytes_written(), 12);
+        drop(handle);
+        assert_eq!(usage.open_handles(), 0);
+
+        // the budget has 4 bytes left
+        (vtable.write)(&file, b"wxyz").unwrap();
+        assert!((vtable.write)(&file, b"!").is_err());
+    }
+
+    #[test]
+    fn test_fs_quota_side_by_side() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let limited = FsVTable::with_quota(FsQuota {
+            max_bytes_written: Some(4),
+            ..FsQuota::UNLIMITED
+        });
+        let unlimited = FsVTable::default();
+
+        (limited.write)(&file, b"1234").unwrap();
+        assert!((limited.write)(&file, b"5").is_err());
+        (unlimited.write)(&file, b"not limited by the other vtable").unwrap();
+        let _handles = (0..4)
+            .map(|_| (unlimited.open)(&file, &OpenOptions::new().read(true)))
+            .collect::<VsysResult<Vec<_>>>()
+            .unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+//! Randomness virtual table for vsys
+//!
+//! This module provides a pluggable source of random bytes. By default it
+//! reads the operating system's secure generator, but embedders can seed it
+//! to make `Math.random()`, `crypto.randomBytes()` and generated UUIDs
+//! reproducible in tests and simulations.
+//!
+//! Key generation in `crypto.subtle` keeps using the system generator, so a
+//! seeded vtable never weakens keys.
+
+use std::sync::{Arc, Mutex};
+
+/// Randomness vtable
+///
+/// All functions are closures, so a seeded vtable can carry the state of its
+/// generator.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct RandomVTable {
+    /// Fill `buf` with random bytes
+    pub fill: Arc<dyn Fn(&mut [u8]) + Send + Sync>,
+}
+
+impl Default for RandomVTable {
+    fn default() -> Self {
+        Self {
+            fill: Arc::new(default_fill),
+        }
+    }
+}
+
+impl RandomVTable {
+    /// Create a vtable producing the same sequence for the same `seed`
+    ///
+    /// The generator (SplitMix64) is fast, not secure.
+    pub fn seeded(seed: u64) -> Self {
+        let state = Arc::new(Mutex::new(seed));
+        Self {
+            fill: with_state!(state, |buf: &mut [u8]| seeded_fill(&state, buf)),
+        }
+    }
+
+    /// A random `u64`
+    pub fn next_u64(&self) -> u64 {
+        let mut bytes = [0; 8];
+        (self.fill)(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// A random float in `[0, 1)`, as returned by `Math.random()`
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A random `u128`
+    pub fn next_u128(&self) -> u128 {
+        let mut bytes = [0; 16];
+        (self.fill)(&mut bytes);
+        u128::from_le_bytes(bytes)
+    }
+}
+
+// Default implementation using the OS generator
+
+fn default_fill(buf: &mut [u8]) {
+    getrandom::fill(buf).expect("the system random number generator failed");
+}
+
+fn seeded_fill(state: &Mutex<u64>, buf: &mut [u8]) {
+    let mut state = state.lock().unwrap_or_else(|err| err.into_inner());
+    for chunk in buf.chunks_mut(8) {
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_fill() {
+        let vtable = RandomVTable::default();
+        let value = vtable.next_f64();
+        assert!((0.0..1.0).contains(&value));
+        assert_ne!(vtable.next_u128(), vtable.next_u128());
+    }
+
+    #[test]
+    fn test_seeded() {
+        let vtable = RandomVTable::seeded(42);
+        let first: Vec<u64> = (0..4).map(|_| vtable.next_u64()).collect();
+        let mut bytes = [0; 13];
+        (vtable.fill)(&mut bytes);
+
+        let vtable = RandomVTable::seeded(42);
+        let again: Vec<u64> = (0..4).map(|_| vtable.next_u64()).collect();
+        assert_eq!(first, again);
+        assert_ne!(first[0], first[1]);
+        assert!((0.0..1.0).contains(&vtable.next_f64()));
+    }
+
+    #[test]
+    fn test_seeded_side_by_side() {
+        let vtable = RandomVTable::seeded(42);
+        let first = vtable.next_u64();
+        let other = RandomVTable::seeded(42);
+        assert_eq!(other.next_u64(), first);
+        // seeding another vtable doesn't restart this one
+        assert_ne!(vtable.next_u64(), first);
+    }
+}
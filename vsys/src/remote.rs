@@ -0,0 +1,325 @@
+//! Deno-style `https:` module loading for vsys
+//!
+//! [`RemoteCache`] keeps downloaded modules on disk and pins each URL to the
+//! hash of its first download in a lockfile, so a changed remote file fails
+//! loudly instead of silently running different code.
+//! [`ModuleLoaderVTable::remote`] builds a module loader on top of it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::error::{VsysError, VsysResult};
+use crate::fs::FsVTable;
+use crate::module_loader::{
+    default_exists, default_load, default_resolve, detect_format, ModuleFormat, ModuleLoaderVTable,
+    ModuleSource, ResolvedModule,
+};
+
+/// Held while updating a lockfile, so concurrent pins don't drop each other
+static LOCKFILE: Mutex<()> = Mutex::new(());
+
+pub fn is_remote(specifier: &str) -> bool {
+    specifier.starts_with("https://")
+}
+
+/// Resolves a remote specifier, or a relative one imported by a remote module
+pub fn resolve(base: &str, name: &str) -> Option<String> {
+    if is_remote(name) {
+        return Some(name.into());
+    }
+    let is_relative = name.starts_with("./") || name.starts_with("../") || name.starts_with('/');
+    if !is_remote(base) || !is_relative {
+        return None;
+    }
+    Url::parse(base).ok()?.join(name).ok().map(String::from)
+}
+
+/// Downloaded modules, pinned to their integrity in a lockfile
+#[derive(Debug, Clone)]
+pub struct RemoteCache {
+    dir: PathBuf,
+    lockfile: PathBuf,
+}
+
+impl RemoteCache {
+    /// A cache in `dir`, pinning integrities in `dir/lock.json`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let lockfile = dir.join("lock.json");
+        Self { dir, lockfile }
+    }
+
+    /// Pin integrities in `lockfile` instead, for example one kept next to
+    /// a project
+    pub fn with_lockfile(mut self, lockfile: impl Into<PathBuf>) -> Self {
+        self.lockfile = lockfile.into();
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn lockfile(&self) -> &Path {
+        &self.lockfile
+    }
+
+    /// Where the copy of `url` is cached
+    pub fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(key(url))
+    }
+
+    /// The integrity `url` is pinned to, if it was downloaded before
+    pub fn pinned(&self, url: &str) -> io::Result<Option<String>> {
+        if let Some(integrity) = self.read_lockfile()?.remove(url) {
+            return Ok(Some(integrity));
+        }
+        // caches written before the lockfile pinned each URL in a file of its own
+        let legacy = self.dir.join([&key(url), ".integrity"].concat());
+        match fs::read_to_string(legacy) {
+            Ok(integrity) => Ok(Some(integrity.trim().to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads `url` from the cache, downloading it when it is missing or
+    /// `reload` is set. The content has to match the integrity pinned for
+    /// `url`, which `reload` replaces.
+    pub fn fetch(
+        &self,
+        url: &str,
+        reload: bool,
+        download: impl FnOnce(&str) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<u8>> {
+        let path = self.path(url);
+        let (pinned, cached) = if reload {
+            (None, None)
+        } else {
+            (self.pinned(url)?, fs::read(&path).ok())
+        };
+        let is_cached = cached.is_some();
+        let bytes = match cached {
+            Some(bytes) => bytes,
+            None => download(url)?,
+        };
+
+        let integrity = integrity(&bytes);
+        match pinned {
+            Some(pinned) if pinned != integrity => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Integrity check failed for {}: expected {}, got {}",
+                        url, pinned, integrity
+                    ),
+                ));
+            }
+            Some(_) if is_cached => return Ok(bytes),
+            _ => {}
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, &bytes)?;
+        self.pin(url, integrity)?;
+        Ok(bytes)
+    }
+
+    fn read_lockfile(&self) -> io::Result<BTreeMap<String, String>> {
+        match fs::read(&self.lockfile) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn pin(&self, url: &str, integrity: String) -> io::Result<()> {
+        let _guard = LOCKFILE.lock().unwrap_or_else(|err| err.into_inner());
+        let mut pins = self.read_lockfile()?;
+        pins.insert(url.to_string(), integrity);
+        let content = serde_json::to_string_pretty(&pins).map_err(io::Error::other)?;
+        if let Some(parent) = self.lockfile.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.lockfile, content)
+    }
+}
+
+/// Name of the cached copy of `url`
+fn key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+/// Subresource integrity of `bytes`
+fn integrity(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    [
+        "sha256-",
+        &base64::engine::general_purpose::STANDARD.encode(digest),
+    ]
+    .concat()
+}
+
+impl ModuleLoaderVTable {
+    /// Create a loader that also imports `https:` modules, downloading them
+    /// once into `cache_dir`
+    pub fn remote(cache_dir: impl Into<PathBuf>) -> Self {
+        Self::remote_cache(RemoteCache::new(cache_dir))
+    }
+
+    /// Create a loader that also imports `https:` modules through `cache`
+    ///
+    /// Downloads don't go through the net vtable, and permissions are left
+    /// to the caller.
+    pub fn remote_cache(cache: RemoteCache) -> Self {
+        let cache = Arc::new(cache);
+        Self {
+            resolve: Arc::new(remote_resolve),
+            load: with_state!(cache, |fs: &FsVTable, path: &str| {
+                remote_load(&cache, fs, path)
+            }),
+            exists: with_state!(cache, |fs: &FsVTable, path: &str| {
+                if is_remote(path) {
+                    return cache.path(path).is_file();
+                }
+                default_exists(fs, path)
+            }),
+            ..Self::default()
+        }
+    }
+}
+
+fn remote_resolve(
+    fs: &FsVTable,
+    specifier: &str,
+    referrer: &str,
+    is_esm: bool,
+) -> VsysResult<ResolvedModule> {
+    let Some(url) = resolve(referrer, specifier) else {
+        return default_resolve(fs, specifier, referrer, is_esm);
+    };
+    Ok(ResolvedModule {
+        format: url_format(&url),
+        path: url,
+        is_builtin: false,
+        needs_cjs_wrapper: false,
+    })
+}
+
+fn remote_load(cache: &RemoteCache, fs: &FsVTable, path: &str) -> VsysResult<ModuleSource> {
+    if !is_remote(path) {
+        return default_load(fs, path);
+    }
+    let source = cache
+        .fetch(path, false, download)
+        .map_err(|err| VsysError::ModuleLoad {
+            path: path.to_string(),
+            message: err.to_string(),
+        })?;
+    Ok(ModuleSource {
+        source,
+        format: url_format(path),
+        path: path.to_string(),
+    })
+}
+
+/// Remote modules are ESM unless their path says otherwise
+fn url_format(url: &str) -> ModuleFormat {
+    let path = Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    match detect_format(Path::new(&path)) {
+        ModuleFormat::Binary => ModuleFormat::ESM,
+        format => format,
+    }
+}
+
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .set("accept", "application/javascript, text/javascript, */*")
+        .call()
+        .map_err(io::Error::other)?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://example.com/lib/mod.js";
+        assert_eq!(
+            resolve(base, "./dep.js").as_deref(),
+            Some("https://example.com/lib/dep.js")
+        );
+        assert_eq!(resolve("/home/main.js", "./dep.js"), None);
+
+        let loader = ModuleLoaderVTable::remote(std::env::temp_dir());
+        let fs = FsVTable::default();
+        let resolved = (loader.resolve)(&fs, "../data.json", base, true).unwrap();
+        assert_eq!(resolved.path, "https://example.com/data.json");
+        assert_eq!(resolved.format, ModuleFormat::Json);
+        let resolved = (loader.resolve)(&fs, "node:fs", base, true).unwrap();
+        assert!(resolved.is_builtin);
+    }
+
+    #[test]
+    fn test_remote_loaders_side_by_side() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let url = "https://example.com/mod.js";
+        let cached = RemoteCache::new(dir.join("cached"));
+        fs::create_dir_all(cached.dir()).unwrap();
+        fs::write(cached.path(url), "export default 1;").unwrap();
+
+        let first = ModuleLoaderVTable::remote_cache(cached);
+        let second = ModuleLoaderVTable::remote(dir.join("empty"));
+        let fs = FsVTable::default();
+        assert!((first.exists)(&fs, url));
+        assert!(!(second.exists)(&fs, url));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lockfile_pinning() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let cache = RemoteCache::new(dir.join("cache")).with_lockfile(dir.join("xmas.lock"));
+        let url = "https://example.com/mod.js";
+        let downloads = Cell::new(0);
+        let fetch = |cache: &RemoteCache, reload, content: &'static [u8]| {
+            cache.fetch(url, reload, |_| {
+                downloads.set(downloads.get() + 1);
+                Ok(content.to_vec())
+            })
+        };
+
+        assert_eq!(fetch(&cache, false, b"1").unwrap(), b"1");
+        assert_eq!(fetch(&cache, false, b"ignored").unwrap(), b"1");
+        assert_eq!(downloads.get(), 1);
+        assert!(cache.pinned(url).unwrap().unwrap().starts_with("sha256-"));
+
+        // a fresh cache still has to match the lockfile
+        let fresh = RemoteCache::new(dir.join("other")).with_lockfile(cache.lockfile());
+        let err = fetch(&fresh, false, b"2").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // reloading accepts and pins the new content
+        assert_eq!(fetch(&fresh, true, b"2").unwrap(), b"2");
+        assert_eq!(fetch(&fresh, false, b"ignored").unwrap(), b"2");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+//! Root-scoped filesystem vtable
+//!
+//! [`FsVTable::scoped`] jails every operation to a single directory. Paths are
+//! resolved like the OS would — symlinks and `..` included — and rejected when
+//! they end up outside the root, so a link pointing out of the project cannot
+//! be used to escape it.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::error::{VsysError, VsysResult};
+use crate::fs::{FsVTable, OpenOptions};
+
+/// The root of a scoped vtable and the real filesystem it serves
+struct Scope {
+    root: PathBuf,
+    real: FsVTable,
+}
+
+impl FsVTable {
+    /// Create a vtable that only allows access below `root`
+    ///
+    /// The root is canonicalized once, here, and kept by the vtable.
+    pub fn scoped(root: PathBuf) -> Self {
+        let root = root.canonicalize().unwrap_or(root);
+        let scope = Arc::new(Scope {
+            root,
+            real: FsVTable::default(),
+        });
+
+        Self {
+            read: with_state!(scope, |path: &Path| (scope.real.read)(
+                &scope.resolve(path, true)?
+            )),
+            read_to_string: with_state!(scope, |path: &Path| {
+                (scope.real.read_to_string)(&scope.resolve(path, true)?)
+            }),
+            stat: with_state!(scope, |path: &Path| (scope.real.stat)(
+                &scope.resolve(path, true)?
+            )),
+            lstat: with_state!(scope, |path: &Path| {
+                (scope.real.lstat)(&scope.resolve(path, false)?)
+            }),
+            read_dir: with_state!(scope, |path: &Path| {
+                (scope.real.read_dir)(&scope.resolve(path, true)?)
+            }),
+            read_link: with_state!(scope, |path: &Path| {
+                (scope.real.read_link)(&scope.resolve(path, false)?)
+            }),
+            exists: with_state!(scope, |path: &Path| {
+                scope
+                    .resolve(path, true)
+                    .is_ok_and(|path| (scope.real.exists)(&path))
+            }),
+            is_file: with_state!(scope, |path: &Path| {
+                scope
+                    .resolve(path, true)
+                    .is_ok_and(|path| (scope.real.is_file)(&path))
+            }),
+            is_dir: with_state!(scope, |path: &Path| {
+                scope
+                    .resolve(path, true)
+                    .is_ok_and(|path| (scope.real.is_dir)(&path))
+            }),
+            write: with_state!(scope, |path: &Path, data: &[u8]| {
+                (scope.real.write)(&scope.resolve(path, true)?, data)
+            }),
+            append: with_state!(scope, |path: &Path, data: &[u8]| {
+                (scope.real.append)(&scope.resolve(path, true)?, data)
+            }),
+            create_dir: with_state!(scope, |path: &Path| {
+                (scope.real.create_dir)(&scope.resolve(path, false)?)
+            }),
+            create_dir_all: with_state!(scope, |path: &Path| {
+                (scope.real.create_dir_all)(&scope.resolve(path, false)?)
+            }),
+            remove_file: with_state!(scope, |path: &Path| {
+                (scope.real.remove_file)(&scope.resolve(path, false)?)
+            }),
+            remove_dir: with_state!(scope, |path: &Path| {
+                (scope.real.remove_dir)(&scope.resolve(path, false)?)
+            }),
+            remove_dir_all: with_state!(scope, |path: &Path| {
+                (scope.real.remove_dir_all)(&scope.resolve(path, false)?)
+            }),
+            rename: with_state!(scope, |from: &Path, to: &Path| {
+                (scope.real.rename)(&scope.resolve(from, false)?, &scope.resolve(to, false)?)
+            }),
+            copy: with_state!(scope, |from: &Path, to: &Path| {
+                (scope.real.copy)(&scope.resolve(from, true)?, &scope.resolve(to, true)?)
+            }),
+            symlink: with_state!(scope, |original: &Path, link: &Path| {
+                scope.symlink(original, link)
+            }),
+            hard_link: with_state!(scope, |original: &Path, link: &Path| {
+                (scope.real.hard_link)(
+                    &scope.resolve(original, true)?,
+                    &scope.resolve(link, false)?,
+                )
+            }),
+            truncate: with_state!(scope, |path: &Path, size: u64| {
+                (scope.real.truncate)(&scope.resolve(path, true)?, size)
+            }),
+            set_times: with_state!(
+                scope,
+                |path: &Path, accessed: SystemTime, modified: SystemTime| {
+                    (scope.real.set_times)(&scope.resolve(path, true)?, accessed, modified)
+                }
+            ),
+            access: with_state!(scope, |path: &Path, mode: u32| {
+                (scope.real.access)(&scope.resolve(path, true)?, mode)
+            }),
+            mkdtemp: with_state!(scope, |prefix: &str| scope.mkdtemp(prefix)),
+            set_permissions: with_state!(scope, |path: &Path, readonly: bool| {
+                (scope.real.set_permissions)(&scope.resolve(path, true)?, readonly)
+            }),
+            set_mode: with_state!(scope, |path: &Path, mode: u32| {
+                (scope.real.set_mode)(&scope.resolve(path, true)?, mode)
+            }),
+            chown: with_state!(scope, |path: &Path, uid: u32, gid: u32| {
+                (scope.real.chown)(&scope.resolve(path, true)?, uid, gid)
+            }),
+            canonicalize: with_state!(scope, |path: &Path| scope.resolve(path, true)),
+            open: with_state!(scope, |path: &Path, options: &OpenOptions| {
+                (scope.real.open)(&scope.resolve(path, true)?, options)
+            }),
+        }
+    }
+}
+
+impl Scope {
+    /// Resolves `path` to the real path it refers to, failing when that is
+    /// outside the root. The last component is only followed when `follow` is
+    /// set, so operations on a link itself (lstat, unlink, ...) stay possible.
+    fn resolve(&self, path: &Path, follow: bool) -> VsysResult<PathBuf> {
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(path)
+        };
+
+        let resolved = match (follow, path.file_name()) {
+            (true, _) | (false, None) => canonicalize_existing(&path)?,
+            (false, Some(name)) => {
+                let parent = path.parent().unwrap_or(Path::new("/"));
+                canonicalize_existing(parent)?.join(name)
+            }
+        };
+
+        if resolved.starts_with(&self.root) {
+            Ok(resolved)
+        } else {
+            Err(VsysError::PermissionDenied(format!(
+                "{} is outside of {}",
+                path.display(),
+                self.root.display()
+            )))
+        }
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> VsysResult<()> {
+        let link_path = self.resolve(link, false)?;
+        // relative targets are relative to the directory of the link
+        let target = link_path.parent().unwrap_or(Path::new("/")).join(original);
+        self.resolve(&target, true)?;
+        (self.real.symlink)(original, &link_path)
+    }
+
+    /// Temporary directories are created inside the root instead of the system
+    /// temp directory, which is normally outside of it.
+    fn mkdtemp(&self, prefix: &str) -> VsysResult<PathBuf> {
+        let name = format!("{}{}", prefix, uuid::Uuid::new_v4().simple());
+        let path = self.resolve(&self.root.join(name), false)?;
+        (self.real.create_dir_all)(&path)?;
+        Ok(path)
+    }
+}
+
+/// Canonicalizes the longest existing ancestor of `path` and appends the
+/// components that do not exist yet, which may not step back up with `..`.
+fn canonicalize_existing(path: &Path) -> VsysResult<PathBuf> {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        match existing.canonicalize() {
+            Ok(mut resolved) => {
+                for component in missing.iter().rev() {
+                    match component {
+                        Component::Normal(name) => resolved.push(name),
+                        Component::CurDir => {}
+                        _ => {
+                            return Err(VsysError::PermissionDenied(format!(
+                                "{} escapes through a missing directory",
+                                path.display()
+                            )))
+                        }
+                    }
+                }
+                return Ok(resolved);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                // a dangling link would be followed once its target is created
+                if existing.symlink_metadata().is_ok() {
+                    return Err(VsysError::PermissionDenied(format!(
+                        "{} is a dangling symlink",
+                        existing.display()
+                    )));
+                }
+                let mut components = existing.components();
+                match components.next_back() {
+                    Some(component) => missing.push(component),
+                    None => return Err(err.into()),
+                }
+                existing = components.as_path();
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scoped_fs() {
+        let outside = tempdir().unwrap();
+        let root = tempdir().unwrap();
+        let secret = outside.path().join("secret.txt");
+        std::fs::write(&secret, "secret").unwrap();
+
+        let vtable = FsVTable::scoped(root.path().to_path_buf());
+        let inside = root.path().join("dir/file.txt");
+        (vtable.create_dir_all)(&root.path().join("dir")).unwrap();
+        (vtable.write)(&inside, b"hello").unwrap();
+        assert_eq!((vtable.read)(&inside).unwrap(), b"hello");
+        assert!((vtable.exists)(&inside));
+
+        // direct and `..` escapes
+        assert!(matches!(
+            (vtable.read)(&secret),
+            Err(VsysError::PermissionDenied(_))
+        ));
+        let dotdot = root
+            .path()
+            .join("dir/../..")
+            .join(outside.path().file_name().unwrap());
+        assert!((vtable.read_dir)(&dotdot).is_err());
+        assert!((vtable.write)(&root.path().join("missing/../../x"), b"x").is_err());
+        assert!(!(vtable.exists)(&secret));
+
+        // symlinks pointing out of the root can be created and removed, not followed
+        #[cfg(unix)]
+        {
+            let link = root.path().join("link");
+            std::os::unix::fs::symlink(outside.path(), &link).unwrap();
+            assert!((vtable.read)(&link.join("secret.txt")).is_err());
+            assert!((vtable.write)(&link.join("new.txt"), b"x").is_err());
+            assert!((vtable.lstat)(&link).unwrap().is_symlink());
+            (vtable.remove_file)(&link).unwrap();
+            assert!((vtable.symlink)(&secret, &link).is_err());
+            (vtable.symlink)(Path::new("dir/file.txt"), &link).unwrap();
+            assert_eq!((vtable.read)(&link).unwrap(), b"hello");
+        }
+
+        let temp = (vtable.mkdtemp)("tmp-").unwrap();
+        assert!(temp.starts_with(root.path().canonicalize().unwrap()));
+    }
+
+    #[test]
+    fn test_scoped_fs_side_by_side() {
+        let (left, right) = (tempdir().unwrap(), tempdir().unwrap());
+        let left_file = left.path().join("file.txt");
+        let right_file = right.path().join("file.txt");
+
+        let left_vtable = FsVTable::scoped(left.path().to_path_buf());
+        let right_vtable = FsVTable::scoped(right.path().to_path_buf());
+        (left_vtable.write)(&left_file, b"left").unwrap();
+        (right_vtable.write)(&right_file, b"right").unwrap();
+
+        // each keeps its own root, whichever was created last
+        assert_eq!((left_vtable.read)(&left_file).unwrap(), b"left");
+        assert!((left_vtable.read)(&right_file).is_err());
+        assert_eq!((right_vtable.read)(&right_file).unwrap(), b"right");
+        assert!((right_vtable.read)(&left_file).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Standard streams virtual table for vsys
+//!
+//! This module provides a pluggable abstraction over stdin, stdout and
+//! stderr. By default it uses the host's streams, but embedders can replace
+//! it to capture or redirect what scripts print through `console`,
+//! `process.stdout` and the REPL.
+
+use std::io::{stderr, stdin, stdout, IsTerminal, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::error::VsysResult;
+
+/// One of the standard streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StdStream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+/// Standard streams vtable
+///
+/// All functions are closures, so a capturing vtable can carry the buffers
+/// it collects output in.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct StdioVTable {
+    /// Write all of `data` to stdout
+    pub write_stdout: Arc<dyn Fn(&[u8]) -> VsysResult<()> + Send + Sync>,
+    /// Write all of `data` to stderr
+    pub write_stderr: Arc<dyn Fn(&[u8]) -> VsysResult<()> + Send + Sync>,
+    /// Read from stdin, returning 0 at the end of input
+    pub read_stdin: Arc<dyn Fn(&mut [u8]) -> VsysResult<usize> + Send + Sync>,
+    /// Whether a stream is an interactive terminal, which enables colors
+    pub is_terminal: Arc<dyn Fn(StdStream) -> bool + Send + Sync>,
+}
+
+impl Default for StdioVTable {
+    fn default() -> Self {
+        Self {
+            write_stdout: Arc::new(default_write_stdout),
+            write_stderr: Arc::new(default_write_stderr),
+            read_stdin: Arc::new(default_read_stdin),
+            is_terminal: Arc::new(default_is_terminal),
+        }
+    }
+}
+
+/// Output collected by capturing vtables, see [`StdioVTable::captured`]
+#[derive(Debug, Default)]
+pub struct StdioCapture {
+    stdout: Mutex<Vec<u8>>,
+    stderr: Mutex<Vec<u8>>,
+}
+
+impl StdioCapture {
+    /// Take the stdout and stderr collected so far
+    pub fn take(&self) -> (Vec<u8>, Vec<u8>) {
+        (
+            std::mem::take(&mut *lock(&self.stdout)),
+            std::mem::take(&mut *lock(&self.stderr)),
+        )
+    }
+}
+
+impl StdioVTable {
+    /// Create a vtable discarding all output, with an empty stdin
+    pub fn null() -> Self {
+        Self {
+            write_stdout: Arc::new(|_: &[u8]| Ok(())),
+            write_stderr: Arc::new(|_: &[u8]| Ok(())),
+            read_stdin: Arc::new(|_: &mut [u8]| Ok(0)),
+            is_terminal: Arc::new(|_: StdStream| false),
+        }
+    }
+
+    /// Create a vtable collecting output in `capture`, with an empty stdin
+    pub fn captured(capture: Arc<StdioCapture>) -> Self {
+        Self {
+            write_stdout: with_state!(capture, |data: &[u8]| {
+                lock(&capture.stdout).extend_from_slice(data);
+                Ok(())
+            }),
+            write_stderr: with_state!(capture, |data: &[u8]| {
+                lock(&capture.stderr).extend_from_slice(data);
+                Ok(())
+            }),
+            ..Self::null()
+        }
+    }
+
+    /// Whether output written to `stream` should contain ANSI colors
+    ///
+    /// `FORCE_COLOR` and `NO_COLOR` take precedence over [`StdioVTable::is_terminal`],
+    /// so output piped to a file stays plain and CI logs can opt back in.
+    pub fn has_colors(&self, stream: StdStream) -> bool {
+        color_env_override().unwrap_or_else(|| (self.is_terminal)(stream))
+    }
+
+    /// Write all of `data` to `stream`, ignoring stdin
+    pub fn write(&self, stream: StdStream, data: &[u8]) -> VsysResult<()> {
+        match stream {
+            StdStream::Stdin => Ok(()),
+            StdStream::Stdout => (self.write_stdout)(data),
+            StdStream::Stderr => (self.write_stderr)(data),
+        }
+    }
+}
+
+/// The color choice forced by the environment, `None` when it depends on the terminal
+///
+/// Follows Node.js: a `FORCE_COLOR` other than `0`/`false` enables colors, while
+/// `FORCE_COLOR=0`, a non-empty `NO_COLOR` or `TERM=dumb` disable them.
+pub fn color_env_override() -> Option<bool> {
+    let var = |name| std::env::var(name).ok();
+    parse_color_env(
+        var("FORCE_COLOR").as_deref(),
+        var("NO_COLOR").as_deref(),
+        var("TERM").as_deref(),
+    )
+}
+
+fn parse_color_env(
+    force_color: Option<&str>,
+    no_color: Option<&str>,
+    term: Option<&str>,
+) -> Option<bool> {
+    if let Some(force_color) = force_color {
+        return Some(!matches!(force_color, "0" | "false"));
+    }
+    if no_color.is_some_and(|value| !value.is_empty()) || term == Some("dumb") {
+        return Some(false);
+    }
+    None
+}
+
+fn lock(buffer: &Mutex<Vec<u8>>) -> std::sync::MutexGuard<'_, Vec<u8>> {
+    buffer.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+// Default implementations using the host's streams
+
+fn default_write_stdout(data: &[u8]) -> VsysResult<()> {
+    let mut stdout = stdout().lock();
+    stdout.write_all(data)?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn default_write_stderr(data: &[u8]) -> VsysResult<()> {
+    stderr().write_all(data).map_err(Into::into)
+}
+
+fn default_read_stdin(buf: &mut [u8]) -> VsysResult<usize> {
+    stdin().read(buf).map_err(Into::into)
+}
+
+fn default_is_terminal(stream: StdStream) -> bool {
+    match stream {
+        StdStream::Stdin => stdin().is_terminal(),
+        StdStream::Stdout => stdout().is_terminal(),
+        StdStream::Stderr => stderr().is_terminal(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_stdio() {
+        let vtable = StdioVTable::null();
+        assert!((vtable.write_stdout)(b"dropped").is_ok());
+        assert_eq!((vtable.read_stdin)(&mut [0; 4]).unwrap(), 0);
+        assert!(!(vtable.is_terminal)(StdStream::Stdout));
+    }
+
+    #[test]
+    fn test_parse_color_env() {
+        assert_eq!(parse_color_env(None, None, Some("xterm")), None);
+        assert_eq!(parse_color_env(None, Some("1"), None), Some(false));
+        assert_eq!(parse_color_env(None, Some(""), None), None);
+        assert_eq!(parse_color_env(None, None, Some("dumb")), Some(false));
+        assert_eq!(parse_color_env(Some(""), Some("1"), None), Some(true));
+        assert_eq!(parse_color_env(Some("3"), None, Some("dumb")), Some(true));
+        assert_eq!(parse_color_env(Some("0"), None, None), Some(false));
+        assert_eq!(parse_color_env(Some("false"), None, None), Some(false));
+    }
+
+    #[test]
+    fn test_captured_stdio() {
+        let capture = Arc::new(StdioCapture::default());
+        let vtable = StdioVTable::captured(capture.clone());
+        vtable.write(StdStream::Stdout, b"out").unwrap();
+        vtable.write(StdStream::Stderr, b"err").unwrap();
+
+        let (stdout, stderr) = capture.take();
+        assert_eq!(stdout, b"out");
+        assert_eq!(stderr, b"err");
+        assert_eq!(capture.take(), (Vec::new(), Vec::new()));
+
+        // another capture collects only its own vtable's output
+        let other = Arc::new(StdioCapture::default());
+        StdioVTable::captured(other.clone())
+            .write(StdStream::Stdout, b"other")
+            .unwrap();
+        assert_eq!(capture.take(), (Vec::new(), Vec::new()));
+        assert_eq!(other.take().0, b"other");
+    }
+}